@@ -0,0 +1,18 @@
+//! Feeds arbitrary opcode streams straight to the CPU.
+//!
+//! The first byte picks the memory mode and seeds the RNG; the rest is
+//! read as big-endian opcodes.
+
+#![no_main]
+
+use chip8::fuzz::{fuzz_cpu, opcodes_from_bytes, run_opcodes};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&mode, rest)) = data.split_first() else {
+        return;
+    };
+    let mut cpu = fuzz_cpu(mode as u64);
+    cpu.memory_mut().set_wraparound(mode & 1 != 0);
+    run_opcodes(&mut cpu, &opcodes_from_bytes(rest));
+});
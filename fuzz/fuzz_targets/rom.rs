@@ -0,0 +1,11 @@
+//! Loads arbitrary data as a ROM and runs it through the normal fetch loop.
+
+#![no_main]
+
+use chip8::fuzz::{fuzz_cpu, run_rom};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let mut cpu = fuzz_cpu(0);
+    run_rom(&mut cpu, data, 10_000);
+});
@@ -0,0 +1,96 @@
+#ifndef CHIP8_H
+#define CHIP8_H
+
+/* Generated by cbindgen from src/capi.rs; do not edit by hand. */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+/// Result of a C API call.
+typedef enum Chip8Status {
+  /// The call succeeded.
+  CHIP8_STATUS_OK = 0,
+  /// A required pointer was null.
+  CHIP8_STATUS_NULL_POINTER = 1,
+  /// An argument was out of range.
+  CHIP8_STATUS_INVALID_ARGUMENT = 2,
+  /// The ROM was empty or too large.
+  CHIP8_STATUS_ROM_ERROR = 3,
+  /// The program faulted (unknown opcode, stack fault, bad memory access).
+  CHIP8_STATUS_FAULT = 4,
+} Chip8Status;
+
+/// Opaque emulator handle.
+typedef struct Chip8Emulator Chip8Emulator;
+
+/// A view of the display: one byte per pixel, 0 for off and 1 for on, row-major.
+///
+/// The pointer stays valid until the next call that takes the emulator
+/// mutably, or until it is destroyed.
+typedef struct Chip8Framebuffer {
+  /// First pixel of the top row.
+  const uint8_t *pixels;
+  /// Width in pixels.
+  uint32_t width;
+  /// Height in pixels.
+  uint32_t height;
+  /// Bytes between the starts of consecutive rows.
+  uint32_t stride;
+} Chip8Framebuffer;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/// Creates an emulator. Free it with `chip8_destroy`.
+Chip8Emulator *chip8_create(void);
+
+/// Destroys an emulator created by `chip8_create`.
+///
+/// # Safety
+/// `emulator` must be null or a handle from `chip8_create` that hasn't
+/// been destroyed yet.
+void chip8_destroy(Chip8Emulator *emulator);
+
+/// Loads a ROM from memory and resets the machine.
+///
+/// Registers, timers, memory and the display are cleared first. An invalid
+/// ROM is rejected before anything changes.
+///
+/// # Safety
+/// `emulator` must be a live handle and `data` must point to `length`
+/// readable bytes.
+Chip8Status chip8_load_rom(Chip8Emulator *emulator, const uint8_t *data, size_t length);
+
+/// Runs one 60 Hz frame: a frame's worth of instructions and one timer tick.
+///
+/// # Safety
+/// `emulator` must be a live handle.
+Chip8Status chip8_step_frame(Chip8Emulator *emulator);
+
+/// Gets the current display contents.
+///
+/// Returns a framebuffer with a null `pixels` pointer if `emulator` is null.
+///
+/// # Safety
+/// `emulator` must be null or a live handle.
+Chip8Framebuffer chip8_framebuffer(const Chip8Emulator *emulator);
+
+/// Presses or releases a keypad key (0x0-0xF).
+///
+/// # Safety
+/// `emulator` must be a live handle.
+Chip8Status chip8_set_key(Chip8Emulator *emulator, uint8_t key, bool pressed);
+
+/// Checks if the buzzer should be sounding.
+///
+/// # Safety
+/// `emulator` must be null or a live handle.
+bool chip8_sound_active(const Chip8Emulator *emulator);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif // __cplusplus
+
+#endif  /* CHIP8_H */
@@ -0,0 +1,498 @@
+//! Static control-flow analysis.
+//!
+//! Walks a ROM from its entry point, following jumps, calls and skips to
+//! find the reachable code. The result is split into basic blocks joined by
+//! typed edges and can be exported as a Graphviz DOT graph.
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use super::disasm::{disassemble, opcode_pattern, DisassembledLine};
+use super::ROM_BASE;
+
+/// Kind of control transfer between two blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EdgeKind {
+    /// Execution continues with the next instruction.
+    Fallthrough,
+
+    /// Unconditional jump (1NNN).
+    Jump,
+
+    /// Subroutine call (2NNN).
+    Call,
+
+    /// Conditional skip over the next instruction.
+    Skip,
+
+    /// Jump relative to V0 (BNNN); only the V0 = 0 target is known.
+    Indirect,
+}
+
+/// A directed edge between two blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    /// Start address of the source block.
+    pub from: u16,
+
+    /// Target address.
+    pub to: u16,
+
+    /// Kind of transfer.
+    pub kind: EdgeKind,
+}
+
+/// A straight-line run of instructions with a single entry point.
+#[derive(Debug, Clone)]
+pub struct BasicBlock {
+    /// Address of the first instruction.
+    pub start: u16,
+
+    /// Address just past the last instruction.
+    pub end: u16,
+
+    /// Decoded instructions in the block.
+    pub instructions: Vec<DisassembledLine>,
+}
+
+/// A store through I that overwrites reachable code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SelfModifyingWrite {
+    /// Address of the store instruction.
+    pub pc: u16,
+
+    /// Address of the first instruction it overwrites.
+    pub target: u16,
+}
+
+/// Control-flow graph of a ROM.
+#[derive(Debug, Clone, Default)]
+pub struct ControlFlowGraph {
+    /// Basic blocks keyed by start address.
+    pub blocks: BTreeMap<u16, BasicBlock>,
+
+    /// Edges between blocks.
+    pub edges: Vec<Edge>,
+
+    /// Subroutine entry points and the addresses that call them.
+    pub subroutines: BTreeMap<u16, Vec<u16>>,
+
+    /// Byte ranges never reached by execution (start, end exclusive; an
+    /// end of 0 is the end of the 64K address space).
+    pub unreachable: Vec<(u16, u16)>,
+
+    /// Stores that overwrite reachable code.
+    pub self_modifying: Vec<SelfModifyingWrite>,
+
+    /// Addresses of BNNN jumps whose real target depends on V0.
+    pub indirect_jumps: Vec<u16>,
+
+    /// Addresses where execution ran into an unknown opcode.
+    pub invalid: Vec<u16>,
+}
+
+/// Decoded control-flow behavior of one instruction.
+struct Flow {
+    /// Instruction length in bytes.
+    length: u16,
+
+    /// Possible successors.
+    successors: Vec<(u16, EdgeKind)>,
+
+    /// Whether the instruction ends its basic block.
+    terminates: bool,
+}
+
+/// Reads the opcode at an address, if it lies inside the ROM.
+fn fetch(rom: &[u8], address: u16) -> Option<u16> {
+    let offset = address.checked_sub(ROM_BASE)? as usize;
+    let bytes = rom.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+/// Gets the length of the instruction at an address (F000 NNNN is 4 bytes).
+fn instruction_length(rom: &[u8], address: u16) -> u16 {
+    if fetch(rom, address) == Some(0xF000) {
+        4
+    } else {
+        2
+    }
+}
+
+/// Works out where execution can go after the instruction at `address`.
+fn flow(rom: &[u8], address: u16, opcode: u16) -> Flow {
+    let length = if opcode == 0xF000 { 4 } else { 2 };
+    let next = address.wrapping_add(length);
+    let nnn = opcode & 0x0FFF;
+
+    let (successors, terminates) = match opcode_pattern(opcode).map(|(p, _)| p) {
+        Some("1NNN") => (vec![(nnn, EdgeKind::Jump)], true),
+        Some("2NNN") => (
+            vec![(nnn, EdgeKind::Call), (next, EdgeKind::Fallthrough)],
+            true,
+        ),
+        Some("BNNN") => (vec![(nnn, EdgeKind::Indirect)], true),
+        Some("00EE") | Some("00FD") | None => (Vec::new(), true),
+        Some("3XNN" | "4XNN" | "5XY0" | "9XY0" | "EX9E" | "EXA1") => {
+            let skipped = next.wrapping_add(instruction_length(rom, next));
+            (
+                vec![(next, EdgeKind::Fallthrough), (skipped, EdgeKind::Skip)],
+                true,
+            )
+        }
+        Some(_) => (vec![(next, EdgeKind::Fallthrough)], false),
+    };
+
+    Flow {
+        length,
+        successors,
+        terminates,
+    }
+}
+
+/// Gets the memory range written through I by a store instruction.
+fn store_length(opcode: u16) -> Option<u16> {
+    let x = (opcode >> 8) & 0xF;
+    let y = (opcode >> 4) & 0xF;
+
+    match opcode_pattern(opcode).map(|(p, _)| p) {
+        Some("FX55") => Some(x + 1),
+        Some("FX33") => Some(3),
+        Some("5XY2") => Some(x.abs_diff(y) + 1),
+        _ => None,
+    }
+}
+
+/// Builds the control-flow graph of a ROM loaded at 0x200.
+pub fn build_cfg(rom: &[u8]) -> ControlFlowGraph {
+    let mut cfg = ControlFlowGraph::default();
+
+    // Pass 1: find reachable instructions and block leaders
+    let mut reachable: BTreeMap<u16, Flow> = BTreeMap::new();
+    let mut leaders = BTreeSet::from([ROM_BASE]);
+    let mut worklist = vec![ROM_BASE];
+    let mut stores: Vec<(u16, u16, u16)> = Vec::new();
+
+    while let Some(mut address) = worklist.pop() {
+        // I is only tracked along straight-line code
+        let mut index: Option<u16> = None;
+
+        while !reachable.contains_key(&address) {
+            let Some(opcode) = fetch(rom, address) else {
+                break;
+            };
+            if opcode_pattern(opcode).is_none() {
+                cfg.invalid.push(address);
+            }
+
+            match opcode_pattern(opcode).map(|(p, _)| p) {
+                Some("ANNN") => index = Some(opcode & 0x0FFF),
+                Some("F000") => index = fetch(rom, address.wrapping_add(2)),
+                Some("FX1E" | "FX29" | "FX30" | "FX65") => index = None,
+                _ => {}
+            }
+            if let (Some(i), Some(length)) = (index, store_length(opcode)) {
+                stores.push((address, i, length));
+            }
+
+            let info = flow(rom, address, opcode);
+            for &(target, kind) in &info.successors {
+                match kind {
+                    EdgeKind::Call => cfg.subroutines.entry(target).or_default().push(address),
+                    EdgeKind::Indirect => cfg.indirect_jumps.push(address),
+                    _ => {}
+                }
+                if info.terminates {
+                    leaders.insert(target);
+                    worklist.push(target);
+                }
+            }
+
+            let next = address.wrapping_add(info.length);
+            let terminates = info.terminates;
+            reachable.insert(address, info);
+            if terminates {
+                break;
+            }
+            if reachable.contains_key(&next) {
+                // Joined code that was already walked
+                leaders.insert(next);
+            }
+            address = next;
+        }
+    }
+
+    // Pass 2: group reachable instructions into basic blocks
+    let mut current: Option<BasicBlock> = None;
+    for (&address, info) in &reachable {
+        let contiguous = current.as_ref().is_some_and(|block| block.end == address);
+        if !contiguous || leaders.contains(&address) {
+            if let Some(block) = current.take() {
+                if contiguous {
+                    cfg.edges.push(Edge {
+                        from: block.start,
+                        to: address,
+                        kind: EdgeKind::Fallthrough,
+                    });
+                }
+                cfg.blocks.insert(block.start, block);
+            }
+        }
+
+        let block = current.get_or_insert_with(|| BasicBlock {
+            start: address,
+            end: address,
+            instructions: Vec::new(),
+        });
+
+        let opcode = fetch(rom, address).unwrap_or(0);
+        let text = match (opcode, fetch(rom, address.wrapping_add(2))) {
+            (0xF000, Some(long)) => format!("LD I, 0x{:04X}", long),
+            _ => disassemble(opcode),
+        };
+        block.instructions.push(DisassembledLine {
+            address,
+            opcode,
+            text,
+        });
+        block.end = address.wrapping_add(info.length);
+
+        if info.terminates {
+            let block = current.take().expect("block was just inserted");
+            for &(to, kind) in &info.successors {
+                cfg.edges.push(Edge {
+                    from: block.start,
+                    to,
+                    kind,
+                });
+            }
+            cfg.blocks.insert(block.start, block);
+        }
+    }
+    if let Some(block) = current.take() {
+        cfg.blocks.insert(block.start, block);
+    }
+
+    // Bytes never covered by a reachable instruction
+    let mut covered = vec![false; rom.len()];
+    for (&address, info) in &reachable {
+        let offset = (address - ROM_BASE) as usize;
+        let end = (offset + info.length as usize).min(rom.len());
+        covered[offset..end].iter_mut().for_each(|c| *c = true);
+    }
+    let mut offset = 0;
+    while offset < rom.len() {
+        if covered[offset] {
+            offset += 1;
+            continue;
+        }
+        let start = offset;
+        while offset < rom.len() && !covered[offset] {
+            offset += 1;
+        }
+        // A ROM filling the 64K address space ends at 0x10000, which wraps to 0
+        cfg.unreachable.push((
+            ROM_BASE.wrapping_add(start as u16),
+            ROM_BASE.wrapping_add(offset as u16),
+        ));
+    }
+
+    // Stores whose destination overlaps reachable code
+    for (pc, start, length) in stores {
+        let end = start.saturating_add(length);
+        let hit = reachable
+            .iter()
+            .find(|(&address, info)| address < end && start < address.wrapping_add(info.length));
+        if let Some((&target, _)) = hit {
+            cfg.self_modifying.push(SelfModifyingWrite { pc, target });
+        }
+    }
+
+    for callers in cfg.subroutines.values_mut() {
+        callers.sort_unstable();
+        callers.dedup();
+    }
+    cfg.indirect_jumps.sort_unstable();
+    cfg.indirect_jumps.dedup();
+    cfg.invalid.sort_unstable();
+    cfg
+}
+
+impl ControlFlowGraph {
+    /// Gets the block containing an address.
+    pub fn block_containing(&self, address: u16) -> Option<&BasicBlock> {
+        self.blocks
+            .range(..=address)
+            .next_back()
+            .map(|(_, block)| block)
+            .filter(|block| address < block.end)
+    }
+
+    /// Checks if an address is the entry of a called subroutine.
+    pub fn is_subroutine(&self, address: u16) -> bool {
+        self.subroutines.contains_key(&address)
+    }
+
+    /// Exports the graph in Graphviz DOT format.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        dot.push_str("digraph cfg {\n");
+        dot.push_str("    node [shape=box, fontname=\"monospace\"];\n");
+
+        for block in self.blocks.values() {
+            let mut label = String::new();
+            for line in &block.instructions {
+                let _ = write!(label, "{}\\l", line);
+            }
+            let style = if self.is_subroutine(block.start) {
+                ", peripheries=2"
+            } else {
+                ""
+            };
+            let _ = writeln!(
+                dot,
+                "    \"0x{:04X}\" [label=\"{}\"{}];",
+                block.start, label, style
+            );
+        }
+
+        for edge in &self.edges {
+            let attributes = match edge.kind {
+                EdgeKind::Fallthrough => "",
+                EdgeKind::Jump => " [label=\"jump\"]",
+                EdgeKind::Call => " [label=\"call\", style=dashed]",
+                EdgeKind::Skip => " [label=\"skip\"]",
+                EdgeKind::Indirect => " [label=\"+V0\", style=dotted]",
+            };
+            let _ = writeln!(
+                dot,
+                "    \"0x{:04X}\" -> \"0x{:04X}\"{};",
+                edge.from, edge.to, attributes
+            );
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cfg_straight_line() {
+        // LD V0, 1; LD V1, 2; JP 0x204
+        let rom = [0x60, 0x01, 0x61, 0x02, 0x12, 0x04];
+        let cfg = build_cfg(&rom);
+
+        assert_eq!(cfg.blocks.len(), 2);
+        assert_eq!(cfg.block_containing(0x202).unwrap().start, 0x200);
+        assert!(cfg.edges.contains(&Edge {
+            from: 0x204,
+            to: 0x204,
+            kind: EdgeKind::Jump
+        }));
+        assert!(cfg.unreachable.is_empty());
+    }
+
+    #[test]
+    fn test_cfg_skip_and_subroutine() {
+        let rom = [
+            0x30, 0x00, // 200: SE V0, 0
+            0x22, 0x0A, // 202: CALL 0x20A
+            0x12, 0x08, // 204: JP 0x208
+            0xAB, 0xCD, // 206: data
+            0x12, 0x08, // 208: JP 0x208
+            0x00, 0xEE, // 20A: RET
+        ];
+        let cfg = build_cfg(&rom);
+
+        assert_eq!(cfg.subroutines.get(&0x20A), Some(&vec![0x202]));
+        assert!(cfg.edges.contains(&Edge {
+            from: 0x200,
+            to: 0x204,
+            kind: EdgeKind::Skip
+        }));
+        assert!(cfg.edges.contains(&Edge {
+            from: 0x202,
+            to: 0x204,
+            kind: EdgeKind::Fallthrough
+        }));
+        assert_eq!(cfg.unreachable, vec![(0x206, 0x208)]);
+        assert!(cfg.blocks.contains_key(&0x20A));
+    }
+
+    #[test]
+    fn test_cfg_splits_blocks_at_jump_targets() {
+        let rom = [
+            0x60, 0x00, // 200: LD V0, 0
+            0x70, 0x01, // 202: ADD V0, 1
+            0x12, 0x02, // 204: JP 0x202
+        ];
+        let cfg = build_cfg(&rom);
+
+        assert_eq!(
+            cfg.blocks.keys().copied().collect::<Vec<_>>(),
+            [0x200, 0x202]
+        );
+        assert!(cfg.edges.contains(&Edge {
+            from: 0x200,
+            to: 0x202,
+            kind: EdgeKind::Fallthrough
+        }));
+    }
+
+    #[test]
+    fn test_cfg_self_modifying_write() {
+        let rom = [
+            0xA2, 0x06, // 200: LD I, 0x206
+            0xF0, 0x55, // 202: LD [I], V0
+            0x12, 0x06, // 204: JP 0x206
+            0x12, 0x06, // 206: JP 0x206
+        ];
+        let cfg = build_cfg(&rom);
+
+        assert_eq!(
+            cfg.self_modifying,
+            vec![SelfModifyingWrite {
+                pc: 0x202,
+                target: 0x206
+            }]
+        );
+    }
+
+    #[test]
+    fn test_cfg_indirect_and_invalid() {
+        let rom = [0xB2, 0x04, 0x00, 0x00, 0xFF, 0xFF];
+        let cfg = build_cfg(&rom);
+
+        assert_eq!(cfg.indirect_jumps, vec![0x200]);
+        assert_eq!(cfg.invalid, vec![0x204]);
+    }
+
+    #[test]
+    fn test_cfg_rom_filling_address_space() {
+        // JP 0x200, then data up to 0xFFFF
+        let mut rom = vec![0; 0x10000 - ROM_BASE as usize];
+        rom[..2].copy_from_slice(&[0x12, 0x00]);
+        let cfg = build_cfg(&rom);
+        assert_eq!(cfg.unreachable, [(0x202, 0x0000)]);
+    }
+
+    #[test]
+    fn test_cfg_dot_export() {
+        let rom = [0x22, 0x04, 0x12, 0x02, 0x00, 0xEE];
+        let dot = build_cfg(&rom).to_dot();
+
+        assert!(dot.starts_with("digraph cfg {"));
+        assert!(dot.contains("\"0x0200\" -> \"0x0204\" [label=\"call\", style=dashed];"));
+        assert!(dot.contains("peripheries=2"));
+        assert!(dot.contains("0204: 00EE  RET\\l"));
+        assert!(dot.trim_end().ends_with('}'));
+    }
+}
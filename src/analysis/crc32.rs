@@ -0,0 +1,36 @@
+//! CRC-32 checksum for verifying patched ROMs.
+//!
+//! BPS patches carry the CRC-32 of the ROM they apply to, the ROM they
+//! produce and the patch itself. The checksum is small enough to compute
+//! bit by bit rather than pulling in a dependency.
+
+/// Reversed IEEE 802.3 polynomial.
+const POLYNOMIAL: u32 = 0xEDB8_8320;
+
+/// Computes the CRC-32 (IEEE) of the given data.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (POLYNOMIAL & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc32_known_vectors() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(
+            crc32(b"The quick brown fox jumps over the lazy dog"),
+            0x414F_A339
+        );
+    }
+}
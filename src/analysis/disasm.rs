@@ -0,0 +1,268 @@
+//! Chip-8 instruction disassembler.
+//!
+//! Decodes opcodes into Cowgod-style mnemonics. SUPER-CHIP and XO-CHIP
+//! extensions are recognized so that ROMs for those variants disassemble
+//! sensibly and can be detected.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+/// Instruction set an opcode belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum InstructionSet {
+    /// Original COSMAC VIP Chip-8.
+    Chip8,
+
+    /// SUPER-CHIP 1.1 extension.
+    SuperChip,
+
+    /// XO-CHIP extension.
+    XoChip,
+}
+
+/// A single disassembled instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisassembledLine {
+    /// Address of the instruction.
+    pub address: u16,
+
+    /// Raw opcode.
+    pub opcode: u16,
+
+    /// Human-readable mnemonic.
+    pub text: String,
+}
+
+impl core::fmt::Display for DisassembledLine {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{:04X}: {:04X}  {}",
+            self.address, self.opcode, self.text
+        )
+    }
+}
+
+/// Gets the opcode pattern (e.g. "6XNN") and instruction set for an opcode.
+///
+/// # Returns
+/// `None` if the opcode is not a recognized instruction (likely data).
+pub fn opcode_pattern(opcode: u16) -> Option<(&'static str, InstructionSet)> {
+    use InstructionSet::*;
+
+    let nibbles = (
+        (opcode >> 12) & 0xF,
+        (opcode >> 8) & 0xF,
+        (opcode >> 4) & 0xF,
+        opcode & 0xF,
+    );
+
+    let pattern = match nibbles {
+        (0x0, 0x0, 0xE, 0x0) => ("00E0", Chip8),
+        (0x0, 0x0, 0xE, 0xE) => ("00EE", Chip8),
+        (0x0, 0x0, 0xC, _) => ("00CN", SuperChip),
+        (0x0, 0x0, 0xD, _) => ("00DN", XoChip),
+        (0x0, 0x0, 0xF, 0xB) => ("00FB", SuperChip),
+        (0x0, 0x0, 0xF, 0xC) => ("00FC", SuperChip),
+        (0x0, 0x0, 0xF, 0xD) => ("00FD", SuperChip),
+        (0x0, 0x0, 0xF, 0xE) => ("00FE", SuperChip),
+        (0x0, 0x0, 0xF, 0xF) => ("00FF", SuperChip),
+        (0x0, _, _, _) if opcode != 0 => ("0NNN", Chip8),
+        (0x1, _, _, _) => ("1NNN", Chip8),
+        (0x2, _, _, _) => ("2NNN", Chip8),
+        (0x3, _, _, _) => ("3XNN", Chip8),
+        (0x4, _, _, _) => ("4XNN", Chip8),
+        (0x5, _, _, 0x0) => ("5XY0", Chip8),
+        (0x5, _, _, 0x2) => ("5XY2", XoChip),
+        (0x5, _, _, 0x3) => ("5XY3", XoChip),
+        (0x6, _, _, _) => ("6XNN", Chip8),
+        (0x7, _, _, _) => ("7XNN", Chip8),
+        (0x8, _, _, 0x0) => ("8XY0", Chip8),
+        (0x8, _, _, 0x1) => ("8XY1", Chip8),
+        (0x8, _, _, 0x2) => ("8XY2", Chip8),
+        (0x8, _, _, 0x3) => ("8XY3", Chip8),
+        (0x8, _, _, 0x4) => ("8XY4", Chip8),
+        (0x8, _, _, 0x5) => ("8XY5", Chip8),
+        (0x8, _, _, 0x6) => ("8XY6", Chip8),
+        (0x8, _, _, 0x7) => ("8XY7", Chip8),
+        (0x8, _, _, 0xE) => ("8XYE", Chip8),
+        (0x9, _, _, 0x0) => ("9XY0", Chip8),
+        (0xA, _, _, _) => ("ANNN", Chip8),
+        (0xB, _, _, _) => ("BNNN", Chip8),
+        (0xC, _, _, _) => ("CXNN", Chip8),
+        (0xD, _, _, 0x0) => ("DXY0", SuperChip),
+        (0xD, _, _, _) => ("DXYN", Chip8),
+        (0xE, _, 0x9, 0xE) => ("EX9E", Chip8),
+        (0xE, _, 0xA, 0x1) => ("EXA1", Chip8),
+        (0xF, 0x0, 0x0, 0x0) => ("F000", XoChip),
+        (0xF, _, 0x0, 0x1) => ("FN01", XoChip),
+        (0xF, 0x0, 0x0, 0x2) => ("F002", XoChip),
+        (0xF, _, 0x0, 0x7) => ("FX07", Chip8),
+        (0xF, _, 0x0, 0xA) => ("FX0A", Chip8),
+        (0xF, _, 0x1, 0x5) => ("FX15", Chip8),
+        (0xF, _, 0x1, 0x8) => ("FX18", Chip8),
+        (0xF, _, 0x1, 0xE) => ("FX1E", Chip8),
+        (0xF, _, 0x2, 0x9) => ("FX29", Chip8),
+        (0xF, _, 0x3, 0x0) => ("FX30", SuperChip),
+        (0xF, _, 0x3, 0x3) => ("FX33", Chip8),
+        (0xF, _, 0x3, 0xA) => ("FX3A", XoChip),
+        (0xF, _, 0x5, 0x5) => ("FX55", Chip8),
+        (0xF, _, 0x6, 0x5) => ("FX65", Chip8),
+        (0xF, _, 0x7, 0x5) => ("FX75", SuperChip),
+        (0xF, _, 0x8, 0x5) => ("FX85", SuperChip),
+        _ => return None,
+    };
+
+    Some(pattern)
+}
+
+/// Disassembles a single opcode into a mnemonic.
+///
+/// Unrecognized opcodes are shown as a data word (`DW 0xNNNN`).
+pub fn disassemble(opcode: u16) -> String {
+    let x = (opcode >> 8) & 0xF;
+    let y = (opcode >> 4) & 0xF;
+    let n = opcode & 0xF;
+    let nn = opcode & 0xFF;
+    let nnn = opcode & 0xFFF;
+
+    let Some((pattern, _)) = opcode_pattern(opcode) else {
+        return format!("DW 0x{:04X}", opcode);
+    };
+
+    match pattern {
+        "00E0" => "CLS".to_string(),
+        "00EE" => "RET".to_string(),
+        "00CN" => format!("SCD {}", n),
+        "00DN" => format!("SCU {}", n),
+        "00FB" => "SCR".to_string(),
+        "00FC" => "SCL".to_string(),
+        "00FD" => "EXIT".to_string(),
+        "00FE" => "LOW".to_string(),
+        "00FF" => "HIGH".to_string(),
+        "0NNN" => format!("SYS 0x{:03X}", nnn),
+        "1NNN" => format!("JP 0x{:03X}", nnn),
+        "2NNN" => format!("CALL 0x{:03X}", nnn),
+        "3XNN" => format!("SE V{:X}, 0x{:02X}", x, nn),
+        "4XNN" => format!("SNE V{:X}, 0x{:02X}", x, nn),
+        "5XY0" => format!("SE V{:X}, V{:X}", x, y),
+        "5XY2" => format!("SAVE V{:X}-V{:X}", x, y),
+        "5XY3" => format!("LOAD V{:X}-V{:X}", x, y),
+        "6XNN" => format!("LD V{:X}, 0x{:02X}", x, nn),
+        "7XNN" => format!("ADD V{:X}, 0x{:02X}", x, nn),
+        "8XY0" => format!("LD V{:X}, V{:X}", x, y),
+        "8XY1" => format!("OR V{:X}, V{:X}", x, y),
+        "8XY2" => format!("AND V{:X}, V{:X}", x, y),
+        "8XY3" => format!("XOR V{:X}, V{:X}", x, y),
+        "8XY4" => format!("ADD V{:X}, V{:X}", x, y),
+        "8XY5" => format!("SUB V{:X}, V{:X}", x, y),
+        "8XY6" => format!("SHR V{:X}, V{:X}", x, y),
+        "8XY7" => format!("SUBN V{:X}, V{:X}", x, y),
+        "8XYE" => format!("SHL V{:X}, V{:X}", x, y),
+        "9XY0" => format!("SNE V{:X}, V{:X}", x, y),
+        "ANNN" => format!("LD I, 0x{:03X}", nnn),
+        "BNNN" => format!("JP V0, 0x{:03X}", nnn),
+        "CXNN" => format!("RND V{:X}, 0x{:02X}", x, nn),
+        "DXY0" => format!("DRW V{:X}, V{:X}, 16x16", x, y),
+        "DXYN" => format!("DRW V{:X}, V{:X}, {}", x, y, n),
+        "EX9E" => format!("SKP V{:X}", x),
+        "EXA1" => format!("SKNP V{:X}", x),
+        "F000" => "LD I, long".to_string(),
+        "FN01" => format!("PLANE {}", x),
+        "F002" => "AUDIO".to_string(),
+        "FX07" => format!("LD V{:X}, DT", x),
+        "FX0A" => format!("LD V{:X}, K", x),
+        "FX15" => format!("LD DT, V{:X}", x),
+        "FX18" => format!("LD ST, V{:X}", x),
+        "FX1E" => format!("ADD I, V{:X}", x),
+        "FX29" => format!("LD F, V{:X}", x),
+        "FX30" => format!("LD HF, V{:X}", x),
+        "FX33" => format!("LD B, V{:X}", x),
+        "FX3A" => format!("PITCH V{:X}", x),
+        "FX55" => format!("LD [I], V{:X}", x),
+        "FX65" => format!("LD V{:X}, [I]", x),
+        "FX75" => format!("LD R, V{:X}", x),
+        "FX85" => format!("LD V{:X}, R", x),
+        _ => format!("DW 0x{:04X}", opcode),
+    }
+}
+
+/// Disassembles up to `count` instructions from a ROM image.
+///
+/// # Arguments
+/// * `rom` - ROM bytes
+/// * `base` - Address the ROM is loaded at
+/// * `offset` - Byte offset into the ROM to start from
+/// * `count` - Maximum number of instructions to decode
+pub fn disassemble_range(
+    rom: &[u8],
+    base: u16,
+    offset: usize,
+    count: usize,
+) -> Vec<DisassembledLine> {
+    rom.get(offset..)
+        .unwrap_or(&[])
+        .chunks_exact(2)
+        .take(count)
+        .enumerate()
+        .map(|(i, word)| {
+            let opcode = u16::from_be_bytes([word[0], word[1]]);
+            DisassembledLine {
+                address: base.wrapping_add((offset + i * 2) as u16),
+                opcode,
+                text: disassemble(opcode),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disassemble_chip8() {
+        assert_eq!(disassemble(0x00E0), "CLS");
+        assert_eq!(disassemble(0x00EE), "RET");
+        assert_eq!(disassemble(0x1234), "JP 0x234");
+        assert_eq!(disassemble(0x6A0C), "LD VA, 0x0C");
+        assert_eq!(disassemble(0x8124), "ADD V1, V2");
+        assert_eq!(disassemble(0xD125), "DRW V1, V2, 5");
+        assert_eq!(disassemble(0xF265), "LD V2, [I]");
+    }
+
+    #[test]
+    fn test_disassemble_extensions() {
+        assert_eq!(
+            opcode_pattern(0x00FF),
+            Some(("00FF", InstructionSet::SuperChip))
+        );
+        assert_eq!(disassemble(0xD120), "DRW V1, V2, 16x16");
+        assert_eq!(
+            opcode_pattern(0xF000),
+            Some(("F000", InstructionSet::XoChip))
+        );
+        assert_eq!(disassemble(0x5123), "LOAD V1-V2");
+    }
+
+    #[test]
+    fn test_disassemble_data() {
+        assert_eq!(opcode_pattern(0x0000), None);
+        assert_eq!(disassemble(0x5121), "DW 0x5121");
+        assert_eq!(disassemble(0xFFFF), "DW 0xFFFF");
+    }
+
+    #[test]
+    fn test_disassemble_range() {
+        let rom = [0x00, 0xE0, 0x60, 0x05, 0x12, 0x00, 0xFF];
+        let lines = disassemble_range(&rom, 0x200, 0, 10);
+
+        // The trailing odd byte is ignored
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1].address, 0x202);
+        assert_eq!(lines[1].to_string(), "0202: 6005  LD V0, 0x05");
+    }
+}
@@ -0,0 +1,155 @@
+//! Static ROM analysis.
+//!
+//! This module inspects ROM images without running them: hashing,
+//! disassembly, opcode statistics, control-flow graphs, sanity checks and
+//! hints about which Chip-8 variant a ROM was written for.
+
+pub mod cfg;
+pub mod crc32;
+pub mod disasm;
+pub mod sha1;
+pub mod validate;
+
+pub use cfg::{build_cfg, ControlFlowGraph};
+pub use crc32::crc32;
+pub use disasm::{
+    disassemble, disassemble_range, opcode_pattern, DisassembledLine, InstructionSet,
+};
+pub use sha1::{sha1, sha1_hex};
+pub use validate::{validate_rom, RomIssue, RomLoader, RomReport, Severity};
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Address ROMs are loaded at.
+pub const ROM_BASE: u16 = 0x200;
+
+/// Largest ROM that fits in standard Chip-8 memory.
+pub const MAX_CHIP8_ROM_SIZE: usize = 4096 - ROM_BASE as usize;
+
+/// Number of instructions shown in the entry-point preview.
+const PREVIEW_LENGTH: usize = 10;
+
+/// Results of a static analysis pass over a ROM.
+#[derive(Debug, Clone)]
+pub struct RomAnalysis {
+    /// ROM size in bytes.
+    pub size: usize,
+
+    /// SHA-1 digest as a lowercase hex string.
+    pub sha1: String,
+
+    /// Instruction sets the ROM appears to use, beyond base Chip-8.
+    pub variant_hints: Vec<InstructionSet>,
+
+    /// Count of each recognized opcode pattern (e.g. "DXYN").
+    pub histogram: BTreeMap<&'static str, usize>,
+
+    /// Number of words that did not decode to a known instruction.
+    pub unknown_words: usize,
+
+    /// Disassembly of the first instructions from the entry point.
+    pub entry_preview: Vec<DisassembledLine>,
+}
+
+impl RomAnalysis {
+    /// Gets the most frequent opcode patterns, most common first.
+    pub fn top_opcodes(&self, count: usize) -> Vec<(&'static str, usize)> {
+        let mut entries: Vec<_> = self.histogram.iter().map(|(&k, &v)| (k, v)).collect();
+        entries.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        entries.truncate(count);
+        entries
+    }
+
+    /// Checks if the ROM appears to use a given extension.
+    pub fn uses(&self, set: InstructionSet) -> bool {
+        self.variant_hints.contains(&set)
+    }
+}
+
+/// Analyzes a ROM image.
+///
+/// The ROM is swept linearly as aligned 16-bit words starting at the entry
+/// point. Embedded sprite data will be counted too, so the histogram and
+/// variant hints are heuristics rather than exact results.
+pub fn analyze_rom(rom: &[u8]) -> RomAnalysis {
+    let mut histogram = BTreeMap::new();
+    let mut unknown_words = 0;
+    let mut variant_hints = Vec::new();
+
+    for word in rom.chunks_exact(2) {
+        let opcode = u16::from_be_bytes([word[0], word[1]]);
+        match opcode_pattern(opcode) {
+            Some((pattern, set)) => {
+                *histogram.entry(pattern).or_insert(0) += 1;
+                if set != InstructionSet::Chip8 && !variant_hints.contains(&set) {
+                    variant_hints.push(set);
+                }
+            }
+            None => unknown_words += 1,
+        }
+    }
+
+    // Only XO-CHIP's 64K address space can hold ROMs this large
+    if rom.len() > MAX_CHIP8_ROM_SIZE && !variant_hints.contains(&InstructionSet::XoChip) {
+        variant_hints.push(InstructionSet::XoChip);
+    }
+    variant_hints.sort();
+
+    RomAnalysis {
+        size: rom.len(),
+        sha1: sha1_hex(rom),
+        variant_hints,
+        histogram,
+        unknown_words,
+        entry_preview: disassemble_range(rom, ROM_BASE, 0, PREVIEW_LENGTH),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyze_plain_chip8() {
+        // CLS; LD V0, 5; LD V1, 10; DRW V0, V1, 5; JP 0x208
+        let rom = [0x00, 0xE0, 0x60, 0x05, 0x61, 0x0A, 0xD0, 0x15, 0x12, 0x08];
+        let analysis = analyze_rom(&rom);
+
+        assert_eq!(analysis.size, 10);
+        assert!(analysis.variant_hints.is_empty());
+        assert_eq!(analysis.histogram.get("6XNN"), Some(&2));
+        assert_eq!(analysis.top_opcodes(1), vec![("6XNN", 2)]);
+        assert_eq!(analysis.unknown_words, 0);
+        assert_eq!(analysis.entry_preview.len(), 5);
+        assert_eq!(analysis.entry_preview[0].text, "CLS");
+    }
+
+    #[test]
+    fn test_analyze_variant_hints() {
+        // HIGH; LD R, V3; PLANE 1
+        let rom = [0x00, 0xFF, 0xF3, 0x75, 0xF1, 0x01];
+        let analysis = analyze_rom(&rom);
+
+        assert!(analysis.uses(InstructionSet::SuperChip));
+        assert!(analysis.uses(InstructionSet::XoChip));
+    }
+
+    #[test]
+    fn test_analyze_large_rom_is_xochip() {
+        let rom = vec![0x60; MAX_CHIP8_ROM_SIZE + 2];
+        let analysis = analyze_rom(&rom);
+
+        assert_eq!(analysis.variant_hints, vec![InstructionSet::XoChip]);
+    }
+
+    #[test]
+    fn test_analyze_counts_unknown_words() {
+        let rom = [0x00, 0x00, 0xFF, 0xFF];
+        let analysis = analyze_rom(&rom);
+
+        assert_eq!(analysis.unknown_words, 2);
+        assert!(analysis.histogram.is_empty());
+    }
+}
@@ -0,0 +1,392 @@
+//! ROM sanity checks.
+//!
+//! Catches ROMs that would load fine but cannot run: odd lengths, images
+//! that are all zeros, reachable opcodes the selected variant does not
+//! implement and jumps that leave the loaded program. Only code reachable
+//! from the entry point is checked, so embedded sprite data is not flagged.
+
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::fmt;
+
+use super::cfg::{build_cfg, EdgeKind};
+use super::disasm::{opcode_pattern, InstructionSet};
+use super::ROM_BASE;
+use crate::emulator::Cpu;
+use crate::error::{EmulatorError, Result};
+use crate::hardware::Hardware;
+
+/// How serious a ROM issue is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Suspicious, but the ROM may still run.
+    Warning,
+
+    /// The ROM will not run correctly.
+    Error,
+}
+
+/// A problem found by `validate_rom`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RomIssue {
+    /// The ROM has an odd number of bytes, so the last byte is not a
+    /// whole instruction.
+    OddLength { size: usize },
+
+    /// Every byte of the ROM is zero.
+    AllZero,
+
+    /// Reachable code does not decode to any known instruction.
+    InvalidOpcode { address: u16, opcode: u16 },
+
+    /// Reachable code uses an instruction from a later variant.
+    UnsupportedOpcode {
+        address: u16,
+        opcode: u16,
+        set: InstructionSet,
+    },
+
+    /// A jump or call targets an address outside the loaded ROM.
+    JumpOutOfRange { address: u16, target: u16 },
+}
+
+impl RomIssue {
+    /// Gets how serious the issue is.
+    pub fn severity(&self) -> Severity {
+        match self {
+            RomIssue::OddLength { .. } | RomIssue::JumpOutOfRange { .. } => Severity::Warning,
+            RomIssue::AllZero
+            | RomIssue::InvalidOpcode { .. }
+            | RomIssue::UnsupportedOpcode { .. } => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for RomIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RomIssue::OddLength { size } => {
+                write!(f, "odd length ({} bytes); the last byte is ignored", size)
+            }
+            RomIssue::AllZero => write!(f, "ROM contains only zeros"),
+            RomIssue::InvalidOpcode { address, opcode } => {
+                write!(f, "invalid opcode {:04X} at {:04X}", opcode, address)
+            }
+            RomIssue::UnsupportedOpcode {
+                address,
+                opcode,
+                set,
+            } => write!(
+                f,
+                "{:?} opcode {:04X} at {:04X} is not supported",
+                set, opcode, address
+            ),
+            RomIssue::JumpOutOfRange { address, target } => write!(
+                f,
+                "jump at {:04X} targets {:04X}, outside the loaded ROM",
+                address, target
+            ),
+        }
+    }
+}
+
+/// Results of the sanity checks on a ROM.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RomReport {
+    /// ROM size in bytes.
+    pub size: usize,
+
+    /// Issues found, in address order after the whole-ROM checks.
+    pub issues: Vec<RomIssue>,
+}
+
+impl RomReport {
+    /// Checks if no issues were found.
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Checks if any issue means the ROM will not run correctly.
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+
+    /// Gets the issues with `Severity::Error`.
+    pub fn errors(&self) -> impl Iterator<Item = &RomIssue> {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity() == Severity::Error)
+    }
+
+    /// Gets the issues with `Severity::Warning`.
+    pub fn warnings(&self) -> impl Iterator<Item = &RomIssue> {
+        self.issues
+            .iter()
+            .filter(|issue| issue.severity() == Severity::Warning)
+    }
+}
+
+/// Runs the sanity checks on a ROM loaded at 0x200.
+///
+/// # Arguments
+/// * `rom` - ROM data
+/// * `target` - Variant the ROM will run on; opcodes from later variants
+///   are reported as unsupported
+pub fn validate_rom(rom: &[u8], target: InstructionSet) -> RomReport {
+    let mut report = RomReport {
+        size: rom.len(),
+        issues: Vec::new(),
+    };
+
+    if !rom.len().is_multiple_of(2) {
+        report.issues.push(RomIssue::OddLength { size: rom.len() });
+    }
+    if !rom.is_empty() && rom.iter().all(|&byte| byte == 0) {
+        // Every instruction would be invalid; one issue says it all
+        report.issues.push(RomIssue::AllZero);
+        return report;
+    }
+
+    let cfg = build_cfg(rom);
+    let end = ROM_BASE as usize + rom.len();
+    let mut located = Vec::new();
+
+    for &address in &cfg.invalid {
+        let opcode = cfg
+            .block_containing(address)
+            .and_then(|block| block.instructions.iter().find(|i| i.address == address))
+            .map_or(0, |line| line.opcode);
+        located.push((address, RomIssue::InvalidOpcode { address, opcode }));
+    }
+
+    for block in cfg.blocks.values() {
+        for line in &block.instructions {
+            if let Some((_, set)) = opcode_pattern(line.opcode) {
+                if set > target {
+                    located.push((
+                        line.address,
+                        RomIssue::UnsupportedOpcode {
+                            address: line.address,
+                            opcode: line.opcode,
+                            set,
+                        },
+                    ));
+                }
+            }
+        }
+    }
+
+    for edge in &cfg.edges {
+        let transfers = matches!(
+            edge.kind,
+            EdgeKind::Jump | EdgeKind::Call | EdgeKind::Indirect
+        );
+        if !transfers || (ROM_BASE as usize..end).contains(&(edge.to as usize)) {
+            continue;
+        }
+        // The transfer is the last instruction of its block
+        let address = cfg.blocks[&edge.from]
+            .instructions
+            .last()
+            .map_or(edge.from, |line| line.address);
+        located.push((
+            address,
+            RomIssue::JumpOutOfRange {
+                address,
+                target: edge.to,
+            },
+        ));
+    }
+
+    located.sort_by_key(|&(address, _)| address);
+    report
+        .issues
+        .extend(located.into_iter().map(|(_, issue)| issue));
+    report
+}
+
+/// Loads ROMs into a CPU, optionally checking them first.
+///
+/// # Example
+/// ```no_run
+/// use chip8::analysis::{InstructionSet, RomLoader};
+/// use chip8::emulator::Cpu;
+/// use chip8::hardware::NullHardware;
+///
+/// let mut cpu = Cpu::with_hardware(NullHardware::new());
+/// let report = RomLoader::new()
+///     .with_target(InstructionSet::Chip8)
+///     .with_strict(true)
+///     .load(&mut cpu, &[0x00, 0xE0, 0x12, 0x02])?;
+/// assert!(report.is_clean());
+/// # Ok::<(), chip8::error::EmulatorError>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RomLoader {
+    /// Variant the ROM is checked against.
+    target: InstructionSet,
+
+    /// Whether the sanity checks run at all.
+    checks: bool,
+
+    /// Whether errors in the report reject the ROM.
+    strict: bool,
+}
+
+impl Default for RomLoader {
+    fn default() -> Self {
+        Self {
+            target: InstructionSet::Chip8,
+            checks: true,
+            strict: false,
+        }
+    }
+}
+
+impl RomLoader {
+    /// Creates a loader that checks against base Chip-8 and only reports.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the variant the ROM is checked against.
+    pub fn with_target(mut self, target: InstructionSet) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Turns the sanity checks on or off.
+    pub fn with_checks(mut self, checks: bool) -> Self {
+        self.checks = checks;
+        self
+    }
+
+    /// Sets whether a report with errors rejects the ROM.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Checks a ROM without loading it.
+    ///
+    /// # Returns
+    /// The report, or `RomRejected` in strict mode if it has errors.
+    pub fn check(&self, rom: &[u8]) -> Result<RomReport> {
+        if !self.checks {
+            return Ok(RomReport {
+                size: rom.len(),
+                issues: Vec::new(),
+            });
+        }
+
+        let report = validate_rom(rom, self.target);
+        if self.strict {
+            if let Some(issue) = report.errors().next() {
+                return Err(EmulatorError::RomRejected {
+                    reason: issue.to_string(),
+                });
+            }
+        }
+        Ok(report)
+    }
+
+    /// Checks a ROM, then loads it into a CPU.
+    ///
+    /// # Returns
+    /// The report, or an error if the ROM was rejected or does not fit.
+    /// A rejected ROM leaves the CPU untouched.
+    pub fn load<H: Hardware>(&self, cpu: &mut Cpu<H>, rom: &[u8]) -> Result<RomReport> {
+        let report = self.check(rom)?;
+        cpu.load_rom(rom)?;
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hardware::NullHardware;
+
+    #[test]
+    fn test_clean_rom() {
+        // CLS; LD I, 0x208; DRW V0, V0, 1; JP 0x206; sprite
+        let rom = [0x00, 0xE0, 0xA2, 0x08, 0xD0, 0x01, 0x12, 0x06, 0xFF, 0xFF];
+        let report = validate_rom(&rom, InstructionSet::Chip8);
+
+        assert!(report.is_clean(), "{:?}", report.issues);
+        assert_eq!(report.size, 10);
+    }
+
+    #[test]
+    fn test_whole_rom_checks() {
+        let report = validate_rom(&[0x00; 8], InstructionSet::Chip8);
+        assert_eq!(report.issues, vec![RomIssue::AllZero]);
+        assert!(report.has_errors());
+
+        // JP 0x200 plus a stray byte
+        let report = validate_rom(&[0x12, 0x00, 0x42], InstructionSet::Chip8);
+        assert_eq!(report.issues, vec![RomIssue::OddLength { size: 3 }]);
+        assert!(!report.has_errors());
+        assert_eq!(report.warnings().count(), 1);
+    }
+
+    #[test]
+    fn test_opcode_checks() {
+        // HIGH; CALL 0x300; JP 0x204; then an invalid word
+        let rom = [0x00, 0xFF, 0x23, 0x00, 0x12, 0x04, 0x00, 0x00];
+        let report = validate_rom(&rom, InstructionSet::Chip8);
+
+        assert_eq!(
+            report.issues,
+            vec![
+                RomIssue::UnsupportedOpcode {
+                    address: 0x200,
+                    opcode: 0x00FF,
+                    set: InstructionSet::SuperChip,
+                },
+                RomIssue::JumpOutOfRange {
+                    address: 0x202,
+                    target: 0x300,
+                },
+            ]
+        );
+
+        // The same ROM is fine on SUPER-CHIP apart from the call
+        let report = validate_rom(&rom, InstructionSet::SuperChip);
+        assert_eq!(report.issues.len(), 1);
+
+        // LD V0, 1; then an invalid word that is reached
+        let report = validate_rom(&[0x60, 0x01, 0x00, 0x00], InstructionSet::Chip8);
+        assert_eq!(
+            report.issues,
+            vec![RomIssue::InvalidOpcode {
+                address: 0x202,
+                opcode: 0x0000,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_rom_loader() {
+        let mut cpu = Cpu::with_hardware(NullHardware::new());
+        let garbage = [0x00; 4];
+
+        // Lenient loading reports but still loads
+        let report = RomLoader::new().load(&mut cpu, &garbage).unwrap();
+        assert!(report.has_errors());
+
+        // Strict loading rejects and leaves memory alone
+        cpu.load_rom(&[0x60, 0x05]).unwrap();
+        let result = RomLoader::new().with_strict(true).load(&mut cpu, &garbage);
+        assert!(matches!(result, Err(EmulatorError::RomRejected { .. })));
+        assert_eq!(cpu.get_memory().read_byte(0x200).unwrap(), 0x60);
+
+        // Checks can be turned off entirely
+        let report = RomLoader::new()
+            .with_checks(false)
+            .with_strict(true)
+            .load(&mut cpu, &garbage)
+            .unwrap();
+        assert!(report.is_clean());
+    }
+}
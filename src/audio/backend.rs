@@ -0,0 +1,190 @@
+//! Pluggable audio output backends.
+//!
+//! [`AudioSystem`](super::AudioSystem) renders samples into a ring buffer
+//! and leaves playing them to an [`AudioBackend`]. CPAL is always built in;
+//! rodio is available with the `rodio` Cargo feature.
+
+use super::device::DefaultDevice;
+use super::ring::RingConsumer;
+use super::stream::{AudioStream, StreamConfig};
+use super::AudioSystemResult;
+use crate::error::AudioError;
+
+/// Available audio output backends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum BackendKind {
+    /// Direct CPAL output stream.
+    #[default]
+    Cpal,
+
+    /// rodio output sink (requires the `rodio` feature).
+    Rodio,
+}
+
+impl BackendKind {
+    /// Gets the backend's display name.
+    pub fn name(self) -> &'static str {
+        match self {
+            BackendKind::Cpal => "cpal",
+            BackendKind::Rodio => "rodio",
+        }
+    }
+
+    /// Checks if the backend was compiled into this build.
+    pub fn is_available(self) -> bool {
+        match self {
+            BackendKind::Cpal => true,
+            BackendKind::Rodio => cfg!(feature = "rodio"),
+        }
+    }
+}
+
+/// An output stream that plays samples pulled from a ring buffer.
+pub trait AudioBackend {
+    /// Gets the kind of backend.
+    fn kind(&self) -> BackendKind;
+
+    /// Starts playback.
+    fn start(&mut self) -> AudioSystemResult<()>;
+
+    /// Pauses playback.
+    fn pause(&mut self) -> AudioSystemResult<()>;
+
+    /// Checks if the stream is playing.
+    fn is_playing(&self) -> bool;
+
+    /// Checks if the stream should be rebuilt, e.g. because the default
+    /// output device is no longer the one it plays on or the stream has
+    /// failed.
+    ///
+    /// This must not block: `device` was already queried, typically by a
+    /// [`DeviceWatcher`](super::device::DeviceWatcher).
+    fn needs_rebuild(&self, device: &DefaultDevice) -> bool;
+
+    /// Rebuilds the stream on the current default device, resuming
+    /// playback if it was playing.
+    fn rebuild(&mut self, source: RingConsumer, source_rate: u32) -> AudioSystemResult<()>;
+
+    /// Gets the stream configuration.
+    fn config(&self) -> &StreamConfig;
+
+    /// Gets the name of the output device.
+    fn device_name(&self) -> Result<String, AudioError>;
+}
+
+/// Opens a stream on the backend selected by `config.backend`.
+///
+/// # Arguments
+/// * `config` - Output stream configuration
+/// * `source` - Ring buffer the stream pulls buzzer samples from
+/// * `source_rate` - Sample rate the buzzer renders at
+pub fn open(
+    config: StreamConfig,
+    source: RingConsumer,
+    source_rate: u32,
+) -> AudioSystemResult<Box<dyn AudioBackend>> {
+    match config.backend {
+        BackendKind::Cpal => Ok(Box::new(AudioStream::new(config, source, source_rate)?)),
+        #[cfg(feature = "rodio")]
+        BackendKind::Rodio => Ok(Box::new(super::rodio_stream::RodioStream::new(
+            config,
+            source,
+            source_rate,
+        )?)),
+        #[cfg(not(feature = "rodio"))]
+        BackendKind::Rodio => Err(unavailable(BackendKind::Rodio)),
+    }
+}
+
+/// Opens a stream on a backend using the default device's native format.
+pub fn auto_detect(
+    kind: BackendKind,
+    source: RingConsumer,
+    source_rate: u32,
+) -> AudioSystemResult<Box<dyn AudioBackend>> {
+    match kind {
+        BackendKind::Cpal => Ok(Box::new(AudioStream::auto_detect(source, source_rate)?)),
+        #[cfg(feature = "rodio")]
+        BackendKind::Rodio => Ok(Box::new(super::rodio_stream::RodioStream::auto_detect(
+            source,
+            source_rate,
+        )?)),
+        #[cfg(not(feature = "rodio"))]
+        BackendKind::Rodio => Err(unavailable(BackendKind::Rodio)),
+    }
+}
+
+/// Error for a backend that was not compiled in.
+#[cfg(not(feature = "rodio"))]
+fn unavailable(kind: BackendKind) -> AudioError {
+    AudioError::StreamError(format!(
+        "the {} audio backend is not available in this build (enable the '{}' feature)",
+        kind.name(),
+        kind.name()
+    ))
+}
+
+impl AudioBackend for AudioStream {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Cpal
+    }
+
+    fn start(&mut self) -> AudioSystemResult<()> {
+        AudioStream::start(self)
+    }
+
+    fn pause(&mut self) -> AudioSystemResult<()> {
+        AudioStream::pause(self)
+    }
+
+    fn is_playing(&self) -> bool {
+        AudioStream::is_playing(self)
+    }
+
+    fn needs_rebuild(&self, device: &DefaultDevice) -> bool {
+        AudioStream::needs_rebuild(self, device)
+    }
+
+    fn rebuild(&mut self, source: RingConsumer, source_rate: u32) -> AudioSystemResult<()> {
+        AudioStream::rebuild(self, source, source_rate)
+    }
+
+    fn config(&self) -> &StreamConfig {
+        AudioStream::config(self)
+    }
+
+    fn device_name(&self) -> Result<String, AudioError> {
+        AudioStream::device_name(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_kind_serde() {
+        #[derive(serde::Serialize, serde::Deserialize)]
+        struct Wrapper {
+            backend: BackendKind,
+        }
+
+        let parsed: Wrapper = toml::from_str(r#"backend = "Rodio""#).unwrap();
+        assert_eq!(parsed.backend, BackendKind::Rodio);
+        assert_eq!(BackendKind::default(), BackendKind::Cpal);
+        assert!(BackendKind::Cpal.is_available());
+        assert_eq!(BackendKind::Rodio.is_available(), cfg!(feature = "rodio"));
+    }
+
+    #[cfg(not(feature = "rodio"))]
+    #[test]
+    fn test_missing_backend_is_an_error() {
+        use crate::audio::ring::{sample_ring, RingCounters};
+        use std::sync::Arc;
+
+        let (_producer, consumer) = sample_ring(16, Arc::new(RingCounters::new()));
+        let config = StreamConfig::new().with_backend(BackendKind::Rodio);
+        let error = open(config, consumer, 44100).err().unwrap();
+        assert!(error.to_string().contains("rodio"));
+    }
+}
@@ -0,0 +1,451 @@
+//! One audio output shared by several emulators.
+//!
+//! Every [`AudioSystem`](super::AudioSystem) opens its own output stream.
+//! A host running many emulators at once, such as a gallery of demos, can
+//! instead create one [`AudioBus`] and give each emulator a [`BusVoice`].
+//! Each voice has its own buzzer and mixer, so volume, mute and pause stay
+//! per emulator; the bus adds the voices together and plays the sum
+//! through a single stream.
+//!
+//! The bus and its voices belong to one thread, like the output streams
+//! they wrap.
+
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+use std::sync::Arc;
+
+use super::backend::{self, AudioBackend, BackendKind};
+use super::ring::{sample_ring, RingCounters, RingProducer};
+use super::{
+    AudioBuzzer, AudioClock, AudioStats, AudioSystemResult, BuzzerConfig, Mixer, SampleTap,
+    StreamConfig, RING_CAPACITY, RING_TARGET_FILL,
+};
+use crate::hardware::{Audio, AudioResult};
+
+/// One voice's sound before it is mixed.
+struct Channel {
+    /// Tone generator driven by the voice's sound timer.
+    buzzer: AudioBuzzer,
+
+    /// The voice's own volume, mute and pause fades.
+    mixer: Mixer,
+
+    /// Most recent samples of this voice alone.
+    tap: SampleTap,
+}
+
+impl Channel {
+    /// Renders the voice's next sample.
+    fn next_sample(&mut self) -> f32 {
+        let sample = self.mixer.process(self.buzzer.next_sample());
+        self.tap.push(sample);
+        sample
+    }
+}
+
+/// The shared output the voices are mixed into.
+struct Output {
+    /// Rate every voice renders at.
+    sample_rate: u32,
+
+    /// Voices on the bus; dropped voices are removed as the bus renders.
+    channels: Vec<Weak<RefCell<Channel>>>,
+
+    /// Backend used when the stream is opened.
+    backend: BackendKind,
+
+    /// The output stream, once opened.
+    stream: Option<Box<dyn AudioBackend>>,
+
+    /// Producer half of the ring feeding the stream.
+    producer: Option<RingProducer>,
+
+    /// Underrun statistics.
+    counters: Arc<RingCounters>,
+
+    /// Most recent mixed samples.
+    tap: SampleTap,
+}
+
+impl Output {
+    /// Renders mixed samples into the ring up to the target fill level.
+    fn pump(&mut self) {
+        self.channels.retain(|channel| channel.strong_count() > 0);
+        let Some(producer) = self.producer.as_mut() else {
+            return;
+        };
+
+        while producer.len() < RING_TARGET_FILL {
+            if !producer.push(mix(&self.channels, &mut self.tap)) {
+                break;
+            }
+        }
+    }
+
+    /// Renders until every voice's mixer fade is complete.
+    fn render_fade(&mut self) {
+        let Some(producer) = self.producer.as_mut() else {
+            return;
+        };
+
+        while !settled(&self.channels) {
+            if !producer.push(mix(&self.channels, &mut self.tap)) {
+                break;
+            }
+        }
+    }
+
+    /// Starts the stream if it isn't playing.
+    fn start(&mut self) -> AudioSystemResult<()> {
+        if let Some(ref mut stream) = self.stream {
+            if !stream.is_playing() {
+                stream.start()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Installs a stream together with the producer feeding it.
+    fn attach(&mut self, stream: Box<dyn AudioBackend>, producer: RingProducer) {
+        self.stream = Some(stream);
+        self.producer = Some(producer);
+        self.pump();
+    }
+}
+
+/// Adds together the next sample of every live voice.
+fn mix(channels: &[Weak<RefCell<Channel>>], tap: &mut SampleTap) -> f32 {
+    let sum: f32 = channels
+        .iter()
+        .filter_map(Weak::upgrade)
+        .map(|channel| channel.borrow_mut().next_sample())
+        .sum();
+    let sample = sum.clamp(-1.0, 1.0);
+    tap.push(sample);
+    sample
+}
+
+/// Checks if no voice is in the middle of a mixer fade.
+fn settled(channels: &[Weak<RefCell<Channel>>]) -> bool {
+    channels
+        .iter()
+        .filter_map(Weak::upgrade)
+        .all(|channel| channel.borrow().mixer.is_settled())
+}
+
+/// An output stream shared by several emulators' voices.
+pub struct AudioBus {
+    /// State shared with the voices.
+    output: Rc<RefCell<Output>>,
+}
+
+impl AudioBus {
+    /// Creates a bus whose voices render at `sample_rate`.
+    ///
+    /// No stream is opened until [`initialize_stream`](Self::initialize_stream)
+    /// or [`initialize_with_defaults`](Self::initialize_with_defaults).
+    pub fn new(sample_rate: u32) -> Self {
+        let output = Output {
+            sample_rate,
+            channels: Vec::new(),
+            backend: BackendKind::default(),
+            stream: None,
+            producer: None,
+            counters: Arc::new(RingCounters::new()),
+            tap: SampleTap::default(),
+        };
+        Self {
+            output: Rc::new(RefCell::new(output)),
+        }
+    }
+
+    /// Sets the backend the stream is opened on.
+    pub fn with_backend(self, backend: BackendKind) -> Self {
+        self.output.borrow_mut().backend = backend;
+        self
+    }
+
+    /// Opens the output stream.
+    pub fn initialize_stream(&mut self, config: StreamConfig) -> AudioSystemResult<()> {
+        let mut output = self.output.borrow_mut();
+        output.backend = config.backend;
+        let (producer, consumer) = sample_ring(RING_CAPACITY, Arc::clone(&output.counters));
+        let stream = backend::open(config, consumer, output.sample_rate)?;
+        output.attach(stream, producer);
+        Ok(())
+    }
+
+    /// Opens the output stream using the default device's native format.
+    pub fn initialize_with_defaults(&mut self) -> AudioSystemResult<()> {
+        let mut output = self.output.borrow_mut();
+        let (producer, consumer) = sample_ring(RING_CAPACITY, Arc::clone(&output.counters));
+        let stream = backend::auto_detect(output.backend, consumer, output.sample_rate)?;
+        output.attach(stream, producer);
+        Ok(())
+    }
+
+    /// Adds a voice for one emulator.
+    ///
+    /// The voice renders at the bus's sample rate whatever `config` says;
+    /// the rest of the buzzer and mixer settings are its own.
+    pub fn voice(&self, config: BuzzerConfig) -> AudioSystemResult<BusVoice> {
+        let mut output = self.output.borrow_mut();
+        let config = config.with_sample_rate(output.sample_rate);
+        let mixer = Mixer::new(config.mixer, output.sample_rate);
+        let channel = Rc::new(RefCell::new(Channel {
+            buzzer: AudioBuzzer::new(config)?,
+            mixer,
+            tap: SampleTap::default(),
+        }));
+        output.channels.push(Rc::downgrade(&channel));
+
+        Ok(BusVoice {
+            channel,
+            output: Rc::clone(&self.output),
+            playing: false,
+        })
+    }
+
+    /// Gets the number of voices still in use.
+    pub fn voice_count(&self) -> usize {
+        let output = self.output.borrow();
+        output
+            .channels
+            .iter()
+            .filter(|channel| channel.strong_count() > 0)
+            .count()
+    }
+
+    /// Renders mixed samples into the stream's ring buffer.
+    ///
+    /// Voices pump the bus whenever their emulator updates them; hosts
+    /// that pause every emulator can call this to let release tails finish.
+    pub fn pump(&mut self) {
+        self.output.borrow_mut().pump();
+    }
+
+    /// Renders mixed samples into the preview tap only, when no stream is open.
+    pub fn render_preview(&mut self, count: usize) {
+        let mut output = self.output.borrow_mut();
+        if output.producer.is_some() {
+            return;
+        }
+        output.channels.retain(|channel| channel.strong_count() > 0);
+        let output = &mut *output;
+        for _ in 0..count {
+            mix(&output.channels, &mut output.tap);
+        }
+    }
+
+    /// Gets the most recent mixed samples, oldest first.
+    pub fn recent_samples(&self) -> Vec<f32> {
+        self.output.borrow().tap.samples()
+    }
+
+    /// Gets the ring buffer and underrun statistics.
+    pub fn stats(&self) -> AudioStats {
+        let output = self.output.borrow();
+        let buffered = output.producer.as_ref().map_or(0, |p| p.len());
+        output.counters.snapshot(buffered)
+    }
+
+    /// Gets a clock that follows the samples consumed by the output device.
+    pub fn clock(&self) -> AudioClock {
+        let output = self.output.borrow();
+        AudioClock::new(Arc::clone(&output.counters), output.sample_rate)
+    }
+
+    /// Checks if the output stream is open.
+    pub fn is_initialized(&self) -> bool {
+        self.output.borrow().stream.is_some()
+    }
+
+    /// Gets the sample rate the voices render at.
+    pub fn sample_rate(&self) -> u32 {
+        self.output.borrow().sample_rate
+    }
+}
+
+impl Default for AudioBus {
+    fn default() -> Self {
+        Self::new(BuzzerConfig::default().sample_rate)
+    }
+}
+
+/// One emulator's audio on an [`AudioBus`].
+///
+/// Dropping the voice removes it from the bus.
+pub struct BusVoice {
+    /// This voice's buzzer and mixer.
+    channel: Rc<RefCell<Channel>>,
+
+    /// The bus the voice plays through.
+    output: Rc<RefCell<Output>>,
+
+    /// Current playing state.
+    playing: bool,
+}
+
+impl BusVoice {
+    /// Renders the bus after this voice has changed.
+    fn pump(&self) {
+        self.output.borrow_mut().pump();
+    }
+}
+
+impl Audio for BusVoice {
+    fn play_beep(&mut self) -> AudioResult<()> {
+        self.channel.borrow_mut().buzzer.start_tone()?;
+        self.pump();
+        self.output.borrow_mut().start()?;
+        self.playing = true;
+        Ok(())
+    }
+
+    fn stop_beep(&mut self) -> AudioResult<()> {
+        // Other voices may still be playing, so the stream keeps running
+        self.channel.borrow_mut().buzzer.stop_tone()?;
+        self.pump();
+        self.playing = false;
+        Ok(())
+    }
+
+    fn is_playing(&self) -> bool {
+        self.playing && self.channel.borrow().buzzer.is_playing()
+    }
+
+    fn set_volume(&mut self, volume: f32) -> AudioResult<()> {
+        self.channel.borrow_mut().buzzer.set_volume(volume)
+    }
+
+    fn get_volume(&self) -> f32 {
+        self.channel.borrow().buzzer.get_volume()
+    }
+
+    fn set_frequency(&mut self, frequency: f32) -> AudioResult<()> {
+        self.channel.borrow_mut().buzzer.set_frequency(frequency)
+    }
+
+    fn get_frequency(&self) -> f32 {
+        self.channel.borrow().buzzer.get_frequency()
+    }
+
+    fn set_sound_timer(&mut self, value: u8) -> AudioResult<()> {
+        self.channel.borrow_mut().buzzer.set_sound_timer(value);
+        Ok(())
+    }
+
+    fn underruns(&self) -> u64 {
+        let output = self.output.borrow();
+        output.counters.snapshot(0).underruns
+    }
+
+    fn set_master_volume(&mut self, volume: f32) -> AudioResult<()> {
+        self.channel.borrow_mut().mixer.set_master_volume(volume);
+        Ok(())
+    }
+
+    fn master_volume(&self) -> f32 {
+        self.channel.borrow().mixer.master_volume()
+    }
+
+    fn set_muted(&mut self, muted: bool) -> AudioResult<()> {
+        self.channel.borrow_mut().mixer.set_muted(muted);
+        self.output.borrow_mut().render_fade();
+        Ok(())
+    }
+
+    fn is_muted(&self) -> bool {
+        self.channel.borrow().mixer.is_muted()
+    }
+
+    fn set_paused(&mut self, paused: bool) -> AudioResult<()> {
+        self.channel.borrow_mut().mixer.set_paused(paused);
+        self.output.borrow_mut().render_fade();
+        Ok(())
+    }
+
+    fn recent_samples(&self) -> Vec<f32> {
+        self.channel.borrow().tap.samples()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::Envelope;
+    use crate::frontend::{EmulatorBuilder, SimpleEmulator};
+    use crate::hardware::Hardware;
+
+    fn config() -> BuzzerConfig {
+        BuzzerConfig::new()
+            .with_volume(0.5)
+            .with_envelope(Envelope::none())
+    }
+
+    #[test]
+    fn test_voices_mix_into_one_output() {
+        let mut bus = AudioBus::new(8000);
+        let mut first = bus.voice(config()).unwrap();
+        let mut second = bus.voice(config().with_sample_rate(44100)).unwrap();
+        assert_eq!(bus.voice_count(), 2);
+        assert_eq!(second.channel.borrow().buzzer.config().sample_rate, 8000);
+
+        first.play_beep().unwrap();
+        bus.render_preview(16);
+        assert!(bus.recent_samples().iter().all(|&s| s.abs() == 0.5));
+        assert!(second.recent_samples().iter().all(|&s| s == 0.0));
+
+        // The bus plays the sum, and muting one voice leaves the other
+        second.play_beep().unwrap();
+        bus.render_preview(16);
+        let mixed = bus.recent_samples();
+        let (first_samples, second_samples) = (first.recent_samples(), second.recent_samples());
+        for i in 1..=16 {
+            let sum =
+                first_samples[first_samples.len() - i] + second_samples[second_samples.len() - i];
+            assert_eq!(mixed[mixed.len() - i], sum.clamp(-1.0, 1.0));
+        }
+        first.set_muted(true).unwrap();
+        bus.render_preview(1000);
+        assert_eq!(bus.recent_samples().last().unwrap().abs(), 0.5);
+
+        drop(first);
+        assert_eq!(bus.voice_count(), 1);
+        bus.render_preview(1);
+        assert_eq!(bus.output.borrow().channels.len(), 1);
+    }
+
+    #[test]
+    fn test_emulators_share_a_bus() {
+        let mut bus = AudioBus::new(8000);
+        // LD V0, 30; LD ST, V0; JP 0x204
+        let beeping = [0x60, 0x1E, 0xF0, 0x18, 0x12, 0x04];
+        let quiet = [0x12, 0x00];
+        let mut emulators: Vec<_> = [&beeping[..], &quiet[..]]
+            .into_iter()
+            .map(|rom| {
+                EmulatorBuilder::new()
+                    .audio(bus.voice(config()).unwrap())
+                    .rom_bytes(rom)
+                    .build()
+                    .unwrap()
+            })
+            .collect();
+
+        for emulator in &mut emulators {
+            emulator.run_cycles(3).unwrap();
+        }
+        bus.render_preview(32);
+        assert!(bus.recent_samples().iter().all(|&s| s.abs() == 0.5));
+
+        let audio = |emulator: &mut SimpleEmulator| {
+            let audio = emulator.cpu_mut().hardware_mut().audio();
+            (audio.is_playing(), audio.recent_samples())
+        };
+        let (playing, samples) = audio(&mut emulators[0]);
+        assert!(playing && samples.iter().all(|&s| s != 0.0));
+        let (playing, samples) = audio(&mut emulators[1]);
+        assert!(!playing && samples.iter().all(|&s| s == 0.0));
+    }
+}
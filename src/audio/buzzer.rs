@@ -3,10 +3,12 @@
 //! This module provides tone generation and buzzer functionality
 //! for the Chip-8 sound system.
 
+use super::backend::BackendKind;
+use super::mixer::MixerConfig;
 use super::AudioSystemResult;
 use crate::error::AudioError;
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 /// Configuration for the audio buzzer.
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -22,6 +24,26 @@ pub struct BuzzerConfig {
 
     /// Waveform type for the buzzer.
     pub waveform: WaveformType,
+
+    /// Amplitude envelope applied when the tone starts and stops.
+    #[serde(default)]
+    pub envelope: Envelope,
+
+    /// Pattern the tone follows while the sound timer is active.
+    #[serde(default)]
+    pub pattern: BeepPattern,
+
+    /// Paces emulation by wall-clock time instead of the audio output clock.
+    #[serde(default)]
+    pub wall_clock_pacing: bool,
+
+    /// Master volume and mute settings.
+    #[serde(default)]
+    pub mixer: MixerConfig,
+
+    /// Backend that plays the output stream.
+    #[serde(default)]
+    pub backend: BackendKind,
 }
 
 impl Default for BuzzerConfig {
@@ -31,6 +53,11 @@ impl Default for BuzzerConfig {
             volume: 0.3,      // 30% volume by default
             sample_rate: 44100,
             waveform: WaveformType::Square,
+            envelope: Envelope::default(),
+            pattern: BeepPattern::Continuous,
+            wall_clock_pacing: false,
+            mixer: MixerConfig::default(),
+            backend: BackendKind::Cpal,
         }
     }
 }
@@ -65,6 +92,18 @@ impl BuzzerConfig {
         self
     }
 
+    /// Sets the amplitude envelope.
+    pub fn with_envelope(mut self, envelope: Envelope) -> Self {
+        self.envelope = envelope;
+        self
+    }
+
+    /// Sets the beep pattern.
+    pub fn with_pattern(mut self, pattern: BeepPattern) -> Self {
+        self.pattern = pattern;
+        self
+    }
+
     /// Creates a classic Chip-8 buzzer configuration.
     pub fn classic() -> Self {
         Self::new()
@@ -98,14 +137,92 @@ pub enum WaveformType {
     Triangle,
 }
 
+/// ADSR amplitude envelope for the buzzer.
+///
+/// Times are in milliseconds. A short attack and release remove the
+/// audible click when the sound timer starts and stops the tone.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Envelope {
+    /// Time to ramp from silence to full level.
+    pub attack_ms: f32,
+
+    /// Time to fall from full level to the sustain level.
+    pub decay_ms: f32,
+
+    /// Level held while the tone is on (0.0 to 1.0).
+    pub sustain_level: f32,
+
+    /// Time to fade to silence after the tone stops.
+    pub release_ms: f32,
+}
+
+impl Default for Envelope {
+    fn default() -> Self {
+        Self {
+            attack_ms: 2.0,
+            decay_ms: 0.0,
+            sustain_level: 1.0,
+            release_ms: 5.0,
+        }
+    }
+}
+
+impl Envelope {
+    /// Creates an envelope with the given stage timings.
+    pub fn new(attack_ms: f32, decay_ms: f32, sustain_level: f32, release_ms: f32) -> Self {
+        Self {
+            attack_ms: attack_ms.max(0.0),
+            decay_ms: decay_ms.max(0.0),
+            sustain_level: sustain_level.clamp(0.0, 1.0),
+            release_ms: release_ms.max(0.0),
+        }
+    }
+
+    /// Creates an envelope that switches instantly (the original hard on/off behavior).
+    pub fn none() -> Self {
+        Self::new(0.0, 0.0, 1.0, 0.0)
+    }
+
+    /// Checks that all stage values are in range.
+    pub fn is_valid(&self) -> bool {
+        self.attack_ms >= 0.0
+            && self.decay_ms >= 0.0
+            && self.release_ms >= 0.0
+            && (0.0..=1.0).contains(&self.sustain_level)
+    }
+}
+
+/// Patterns the buzzer can follow while the sound timer is active.
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Serialize, serde::Deserialize)]
+pub enum BeepPattern {
+    /// A steady tone for as long as the sound timer is non-zero.
+    #[default]
+    Continuous,
+
+    /// A pulse train alternating between tone and silence.
+    Pulse { on_ms: f32, off_ms: f32 },
+
+    /// A tone whose pitch rises with the remaining sound timer value.
+    ///
+    /// The played frequency is `frequency + timer * hz_per_tick`.
+    TimerPitch { hz_per_tick: f32 },
+}
+
+/// Current stage of the envelope generator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvelopeStage {
+    Idle,
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+}
+
 /// Audio buzzer for generating tones.
 pub struct AudioBuzzer {
     /// Configuration for the buzzer.
     config: BuzzerConfig,
 
-    /// Current phase for waveform generation.
-    phase: f32,
-
     /// Whether the buzzer is currently playing.
     playing: bool,
 
@@ -117,27 +234,189 @@ pub struct AudioBuzzer {
 }
 
 /// Internal state of the buzzer.
+///
+/// [`BuzzerState::next_sample`] applies the waveform, envelope and
+/// pattern; the audio system renders through it into the output ring.
 #[derive(Debug)]
 pub struct BuzzerState {
     pub frequency: f32,
     pub volume: f32,
     pub playing: bool,
     pub phase: f32,
+    pub waveform: WaveformType,
+    pub envelope: Envelope,
+    pub pattern: BeepPattern,
+
+    /// Last sound timer value reported by the emulator.
+    pub timer_value: u8,
+
+    /// Current envelope stage.
+    pub stage: EnvelopeStage,
+
+    /// Current envelope level (0.0 to 1.0).
+    pub level: f32,
+
+    /// Samples generated since the tone started (drives pulse patterns).
+    pub elapsed_samples: u64,
 }
 
-impl AudioBuzzer {
-    /// Creates a new audio buzzer.
-    pub fn new(config: BuzzerConfig) -> AudioSystemResult<Self> {
-        let state = Arc::new(Mutex::new(BuzzerState {
+impl BuzzerState {
+    /// Creates the shared state for a buzzer configuration.
+    pub fn new(config: &BuzzerConfig) -> Self {
+        Self {
             frequency: config.frequency,
             volume: config.volume,
             playing: false,
             phase: 0.0,
-        }));
+            waveform: config.waveform,
+            envelope: config.envelope,
+            pattern: config.pattern,
+            timer_value: 0,
+            stage: EnvelopeStage::Idle,
+            level: 0.0,
+            elapsed_samples: 0,
+        }
+    }
+
+    /// Opens the envelope gate (tone on).
+    pub fn gate_on(&mut self) {
+        self.playing = true;
+        self.stage = EnvelopeStage::Attack;
+        self.elapsed_samples = 0;
+    }
+
+    /// Closes the envelope gate (tone off); the release tail keeps playing.
+    pub fn gate_off(&mut self) {
+        self.playing = false;
+        if self.stage != EnvelopeStage::Idle {
+            self.stage = EnvelopeStage::Release;
+        }
+    }
+
+    /// Checks if the state will produce any non-silent output.
+    pub fn is_audible(&self) -> bool {
+        self.stage != EnvelopeStage::Idle
+    }
+
+    /// Generates the next output sample.
+    ///
+    /// # Arguments
+    /// * `sample_rate` - Output sample rate in Hz
+    pub fn next_sample(&mut self, sample_rate: f32) -> f32 {
+        let level = self.advance_envelope(sample_rate);
+        if self.stage == EnvelopeStage::Idle {
+            return 0.0;
+        }
+
+        let (frequency, gate) = self.pattern_frequency(sample_rate);
+        self.elapsed_samples += 1;
+
+        let sample = waveform_sample(self.waveform, self.phase);
+        self.phase += frequency / sample_rate;
+        if self.phase >= 1.0 {
+            self.phase -= self.phase.floor();
+        }
+
+        sample * level * gate * self.volume
+    }
+
+    /// Gets the frequency and gate (0.0 or 1.0) for the current pattern position.
+    fn pattern_frequency(&self, sample_rate: f32) -> (f32, f32) {
+        match self.pattern {
+            BeepPattern::Continuous => (self.frequency, 1.0),
+            BeepPattern::Pulse { on_ms, off_ms } => {
+                let period_ms = on_ms + off_ms;
+                if period_ms <= 0.0 {
+                    return (self.frequency, 1.0);
+                }
+                let elapsed_ms = self.elapsed_samples as f32 * 1000.0 / sample_rate;
+                let gate = if elapsed_ms % period_ms < on_ms {
+                    1.0
+                } else {
+                    0.0
+                };
+                (self.frequency, gate)
+            }
+            BeepPattern::TimerPitch { hz_per_tick } => (
+                (self.frequency + self.timer_value as f32 * hz_per_tick).max(0.0),
+                1.0,
+            ),
+        }
+    }
+
+    /// Advances the envelope by one sample and returns the new level.
+    fn advance_envelope(&mut self, sample_rate: f32) -> f32 {
+        let step = |ms: f32| {
+            if ms <= 0.0 {
+                1.0
+            } else {
+                1000.0 / (ms * sample_rate)
+            }
+        };
+
+        match self.stage {
+            EnvelopeStage::Idle => self.level = 0.0,
+            EnvelopeStage::Attack => {
+                self.level += step(self.envelope.attack_ms);
+                if self.level >= 1.0 {
+                    self.level = 1.0;
+                    self.stage = EnvelopeStage::Decay;
+                }
+            }
+            EnvelopeStage::Decay => {
+                let sustain = self.envelope.sustain_level;
+                self.level -= step(self.envelope.decay_ms) * (1.0 - sustain);
+                if self.level <= sustain {
+                    self.level = sustain;
+                    self.stage = EnvelopeStage::Sustain;
+                }
+            }
+            EnvelopeStage::Sustain => self.level = self.envelope.sustain_level,
+            EnvelopeStage::Release => {
+                self.level -= step(self.envelope.release_ms);
+                if self.level <= 0.0 {
+                    self.level = 0.0;
+                    self.stage = EnvelopeStage::Idle;
+                    self.phase = 0.0;
+                }
+            }
+        }
+
+        self.level
+    }
+}
+
+/// Computes a single waveform sample for a phase in the range 0.0 to 1.0.
+pub fn waveform_sample(waveform: WaveformType, phase: f32) -> f32 {
+    use std::f32::consts::PI;
+
+    match waveform {
+        WaveformType::Sine => (phase * 2.0 * PI).sin(),
+        WaveformType::Square => {
+            if phase < 0.5 {
+                1.0
+            } else {
+                -1.0
+            }
+        }
+        WaveformType::Sawtooth => 2.0 * phase - 1.0,
+        WaveformType::Triangle => {
+            if phase < 0.5 {
+                4.0 * phase - 1.0
+            } else {
+                3.0 - 4.0 * phase
+            }
+        }
+    }
+}
+
+impl AudioBuzzer {
+    /// Creates a new audio buzzer.
+    pub fn new(config: BuzzerConfig) -> AudioSystemResult<Self> {
+        let state = Arc::new(Mutex::new(BuzzerState::new(&config)));
 
         Ok(Self {
             config,
-            phase: 0.0,
             playing: false,
             start_time: None,
             state,
@@ -150,16 +429,10 @@ impl AudioBuzzer {
         let config_clone = config.clone();
         Self::new(config).unwrap_or_else(|_| {
             // Fallback implementation if audio fails
-            let state = Arc::new(Mutex::new(BuzzerState {
-                frequency: 440.0,
-                volume: 0.0,
-                playing: false,
-                phase: 0.0,
-            }));
+            let state = Arc::new(Mutex::new(BuzzerState::new(&config_clone)));
 
             Self {
                 config: config_clone,
-                phase: 0.0,
                 playing: false,
                 start_time: None,
                 state,
@@ -168,31 +441,61 @@ impl AudioBuzzer {
     }
 
     /// Starts playing a tone.
+    ///
+    /// Calling this while the tone is already playing has no effect, so the
+    /// envelope is not retriggered on every CPU cycle.
     pub fn start_tone(&mut self) -> AudioSystemResult<()> {
+        if self.playing {
+            return Ok(());
+        }
+
         self.playing = true;
         self.start_time = Some(Instant::now());
-        self.phase = 0.0;
 
         if let Ok(mut state) = self.state.lock() {
-            state.playing = true;
-            state.phase = 0.0;
+            state.gate_on();
         }
 
         Ok(())
     }
 
     /// Stops playing the tone.
+    ///
+    /// The envelope release tail continues to be generated after this call.
     pub fn stop_tone(&mut self) -> AudioSystemResult<()> {
         self.playing = false;
         self.start_time = None;
 
         if let Ok(mut state) = self.state.lock() {
-            state.playing = false;
+            state.gate_off();
         }
 
         Ok(())
     }
 
+    /// Reports the current sound timer value (drives timer-pitch patterns).
+    pub fn set_sound_timer(&mut self, value: u8) {
+        if let Ok(mut state) = self.state.lock() {
+            state.timer_value = value;
+        }
+    }
+
+    /// Sets the amplitude envelope.
+    pub fn set_envelope(&mut self, envelope: Envelope) {
+        self.config.envelope = envelope;
+        if let Ok(mut state) = self.state.lock() {
+            state.envelope = envelope;
+        }
+    }
+
+    /// Sets the beep pattern.
+    pub fn set_pattern(&mut self, pattern: BeepPattern) {
+        self.config.pattern = pattern;
+        if let Ok(mut state) = self.state.lock() {
+            state.pattern = pattern;
+        }
+    }
+
     /// Checks if the buzzer is currently playing.
     pub fn is_playing(&self) -> bool {
         self.playing
@@ -237,14 +540,11 @@ impl AudioBuzzer {
 
     /// Generates the next audio sample.
     pub fn next_sample(&mut self) -> f32 {
-        if !self.playing {
-            return 0.0;
+        let sample_rate = self.config.sample_rate as f32;
+        match self.state.lock() {
+            Ok(mut state) => state.next_sample(sample_rate),
+            Err(_) => 0.0,
         }
-
-        let sample = self.generate_waveform_sample();
-        self.advance_phase();
-
-        sample * self.config.volume
     }
 
     /// Generates multiple samples into a buffer.
@@ -254,6 +554,25 @@ impl AudioBuzzer {
         }
     }
 
+    /// Renders the buzzer's output for a length of time without an audio device.
+    ///
+    /// The samples are generated at `sample_rate` rather than the configured
+    /// rate, from the buzzer's current tone, envelope and pattern state.
+    pub fn render_offline(&mut self, duration: Duration, sample_rate: u32) -> Vec<f32> {
+        let count = (duration.as_secs_f64() * sample_rate as f64).round() as usize;
+        self.render_samples(count, sample_rate)
+    }
+
+    /// Renders a number of samples at `sample_rate` without an audio device.
+    pub fn render_samples(&mut self, count: usize, sample_rate: u32) -> Vec<f32> {
+        match self.state.lock() {
+            Ok(mut state) => (0..count)
+                .map(|_| state.next_sample(sample_rate as f32))
+                .collect(),
+            Err(_) => vec![0.0; count],
+        }
+    }
+
     /// Gets a reference to the shared state (for audio threads).
     pub fn get_state(&self) -> Arc<Mutex<BuzzerState>> {
         Arc::clone(&self.state)
@@ -263,45 +582,6 @@ impl AudioBuzzer {
     pub fn config(&self) -> &BuzzerConfig {
         &self.config
     }
-
-    /// Generates a waveform sample based on the current configuration.
-    fn generate_waveform_sample(&self) -> f32 {
-        use std::f32::consts::PI;
-
-        match self.config.waveform {
-            WaveformType::Sine => (self.phase * 2.0 * PI).sin(),
-            WaveformType::Square => {
-                if self.phase < 0.5 {
-                    1.0
-                } else {
-                    -1.0
-                }
-            }
-            WaveformType::Sawtooth => 2.0 * self.phase - 1.0,
-            WaveformType::Triangle => {
-                if self.phase < 0.5 {
-                    4.0 * self.phase - 1.0
-                } else {
-                    3.0 - 4.0 * self.phase
-                }
-            }
-        }
-    }
-
-    /// Advances the phase for the next sample.
-    fn advance_phase(&mut self) {
-        self.phase += self.config.frequency / self.config.sample_rate as f32;
-
-        // Wrap phase to prevent overflow
-        if self.phase >= 1.0 {
-            self.phase -= 1.0;
-        }
-
-        // Update shared state
-        if let Ok(mut state) = self.state.lock() {
-            state.phase = self.phase;
-        }
-    }
 }
 
 /// Creates a simple buzzer for testing without audio output.
@@ -517,4 +797,184 @@ mod tests {
         assert_eq!(config.frequency, 440.0);
         assert_eq!(config.sample_rate, 44100);
     }
+
+    #[test]
+    fn test_envelope_attack_ramp() {
+        let envelope = Envelope::new(1.0, 0.0, 1.0, 0.0);
+        let config = BuzzerConfig::new()
+            .with_volume(1.0)
+            .with_waveform(WaveformType::Square)
+            .with_envelope(envelope);
+        let mut buzzer = AudioBuzzer::new(config).unwrap();
+
+        buzzer.start_tone().unwrap();
+        let first = buzzer.next_sample();
+
+        // Attack lasts ~44 samples at 44.1kHz, so the first sample is quiet
+        assert!(first > 0.0 && first < 0.1);
+
+        let samples: Vec<f32> = (0..100).map(|_| buzzer.next_sample()).collect();
+        assert!(samples.iter().any(|&s| s.abs() == 1.0));
+    }
+
+    #[test]
+    fn test_envelope_release_tail() {
+        let envelope = Envelope::new(0.0, 0.0, 1.0, 1.0);
+        let config = BuzzerConfig::new().with_volume(1.0).with_envelope(envelope);
+        let mut buzzer = AudioBuzzer::new(config).unwrap();
+
+        buzzer.start_tone().unwrap();
+        buzzer.next_sample();
+        buzzer.stop_tone().unwrap();
+        assert!(!buzzer.is_playing());
+
+        // Release keeps producing a fading tone
+        let tail = buzzer.next_sample();
+        assert!(tail.abs() > 0.0 && tail.abs() < 1.0);
+
+        // Then goes silent once the release has finished
+        for _ in 0..100 {
+            buzzer.next_sample();
+        }
+        assert_eq!(buzzer.next_sample(), 0.0);
+        assert!(!buzzer.get_state().lock().unwrap().is_audible());
+    }
+
+    #[test]
+    fn test_envelope_none_is_instant() {
+        let config = BuzzerConfig::new()
+            .with_volume(1.0)
+            .with_envelope(Envelope::none());
+        let mut buzzer = AudioBuzzer::new(config).unwrap();
+
+        buzzer.start_tone().unwrap();
+        assert_eq!(buzzer.next_sample(), 1.0);
+
+        buzzer.stop_tone().unwrap();
+        assert_eq!(buzzer.next_sample(), 0.0);
+    }
+
+    #[test]
+    fn test_envelope_decay_to_sustain() {
+        let envelope = Envelope::new(0.0, 1.0, 0.5, 0.0);
+        let config = BuzzerConfig::new().with_volume(1.0).with_envelope(envelope);
+        let mut buzzer = AudioBuzzer::new(config).unwrap();
+
+        buzzer.start_tone().unwrap();
+        for _ in 0..200 {
+            buzzer.next_sample();
+        }
+
+        let state = buzzer.get_state();
+        let state = state.lock().unwrap();
+        assert_eq!(state.stage, EnvelopeStage::Sustain);
+        assert_eq!(state.level, 0.5);
+    }
+
+    #[test]
+    fn test_envelope_validation() {
+        assert!(Envelope::default().is_valid());
+        assert!(Envelope::none().is_valid());
+
+        let clamped = Envelope::new(-1.0, -1.0, 2.0, -1.0);
+        assert!(clamped.is_valid());
+        assert_eq!(clamped.sustain_level, 1.0);
+
+        let invalid = Envelope {
+            sustain_level: 1.5,
+            ..Envelope::default()
+        };
+        assert!(!invalid.is_valid());
+    }
+
+    #[test]
+    fn test_start_tone_does_not_retrigger() {
+        let mut buzzer = create_test_buzzer();
+
+        buzzer.start_tone().unwrap();
+        for _ in 0..10 {
+            buzzer.next_sample();
+        }
+        let phase_before = buzzer.get_state().lock().unwrap().phase;
+
+        // The CPU calls play_beep every cycle while the timer is active
+        buzzer.start_tone().unwrap();
+        let phase_after = buzzer.get_state().lock().unwrap().phase;
+        assert_eq!(phase_before, phase_after);
+    }
+
+    #[test]
+    fn test_pulse_pattern_gating() {
+        let config = BuzzerConfig::new()
+            .with_volume(1.0)
+            .with_sample_rate(1000)
+            .with_envelope(Envelope::none())
+            .with_pattern(BeepPattern::Pulse {
+                on_ms: 10.0,
+                off_ms: 10.0,
+            });
+        let mut buzzer = AudioBuzzer::new(config).unwrap();
+
+        buzzer.start_tone().unwrap();
+        let samples: Vec<f32> = (0..40).map(|_| buzzer.next_sample()).collect();
+
+        // 1 sample per ms: 10 on, 10 off, repeating
+        assert!(samples[0..10].iter().all(|&s| s != 0.0));
+        assert!(samples[10..20].iter().all(|&s| s == 0.0));
+        assert!(samples[20..30].iter().any(|&s| s != 0.0));
+    }
+
+    #[test]
+    fn test_timer_pitch_pattern() {
+        let config = BuzzerConfig::new()
+            .with_frequency(400.0)
+            .with_pattern(BeepPattern::TimerPitch { hz_per_tick: 10.0 });
+        let state = BuzzerState::new(&config);
+        assert_eq!(state.pattern_frequency(44100.0).0, 400.0);
+
+        let mut buzzer = AudioBuzzer::new(config).unwrap();
+        buzzer.set_sound_timer(20);
+        let state = buzzer.get_state();
+        assert_eq!(state.lock().unwrap().pattern_frequency(44100.0).0, 600.0);
+    }
+
+    #[test]
+    fn test_render_offline() {
+        let config = BuzzerConfig::new()
+            .with_volume(1.0)
+            .with_envelope(Envelope::none());
+        let mut buzzer = AudioBuzzer::new(config).unwrap();
+
+        // Nothing plays until the tone starts
+        let samples = buzzer.render_offline(Duration::from_millis(10), 8000);
+        assert_eq!(samples.len(), 80);
+        assert!(samples.iter().all(|&s| s == 0.0));
+
+        buzzer.start_tone().unwrap();
+        let samples = buzzer.render_offline(Duration::from_millis(10), 8000);
+        assert!(samples.iter().all(|&s| s.abs() == 1.0));
+    }
+
+    #[test]
+    fn test_envelope_config_serde_defaults() {
+        let toml_str = r#"
+            frequency = 440.0
+            volume = 0.3
+            sample_rate = 44100
+            waveform = "Square"
+        "#;
+        let config: BuzzerConfig = toml::from_str(toml_str).unwrap();
+        assert_eq!(config.envelope, Envelope::default());
+        assert_eq!(config.pattern, BeepPattern::Continuous);
+        assert!(!config.wall_clock_pacing);
+        assert_eq!(config.backend, BackendKind::Cpal);
+
+        let config = BuzzerConfig::new().with_pattern(BeepPattern::Pulse {
+            on_ms: 50.0,
+            off_ms: 25.0,
+        });
+        let serialized = toml::to_string(&config).unwrap();
+        let parsed: BuzzerConfig = toml::from_str(&serialized).unwrap();
+        assert_eq!(parsed.pattern, config.pattern);
+    }
 }
@@ -0,0 +1,126 @@
+//! Watching the default output device.
+//!
+//! Asking the host for its default output device can block for a long
+//! time on some systems, so a [`DeviceWatcher`] asks on a background
+//! thread and keeps the latest answer. The audio system compares its
+//! stream against that answer from the emulator thread without waiting,
+//! and only rebuilds the stream when something changed.
+
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::Duration;
+
+use cpal::traits::{DeviceTrait, HostTrait};
+
+use crate::logging::AUDIO;
+
+/// The host's default output device at some moment.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DefaultDevice {
+    /// Name of the device, or `None` if there is no output device.
+    pub name: Option<String>,
+
+    /// The device's default sample rate in Hz, if it reports one.
+    pub sample_rate: Option<u32>,
+}
+
+impl DefaultDevice {
+    /// Asks the host for its default output device, which can block.
+    pub fn query() -> Self {
+        let Some(device) = cpal::default_host().default_output_device() else {
+            return Self::default();
+        };
+        Self {
+            name: device.name().ok(),
+            sample_rate: device
+                .default_output_config()
+                .ok()
+                .map(|config| config.sample_rate().0),
+        }
+    }
+}
+
+/// Keeps the latest [`DefaultDevice`], queried on a background thread.
+///
+/// The thread stops once the watcher is dropped.
+#[derive(Debug)]
+pub struct DeviceWatcher {
+    latest: Arc<Mutex<Option<DefaultDevice>>>,
+}
+
+impl DeviceWatcher {
+    /// Starts watching the host's default output device.
+    pub fn new(interval: Duration) -> Self {
+        Self::with_query(interval, DefaultDevice::query)
+    }
+
+    /// Starts watching with a custom query, run every `interval`.
+    pub fn with_query<F>(interval: Duration, query: F) -> Self
+    where
+        F: Fn() -> DefaultDevice + Send + 'static,
+    {
+        let latest = Arc::new(Mutex::new(None));
+        let watched: Weak<Mutex<Option<DefaultDevice>>> = Arc::downgrade(&latest);
+        let spawned = thread::Builder::new()
+            .name("audio-device-watcher".to_string())
+            .spawn(move || loop {
+                let device = query();
+                let Some(latest) = watched.upgrade() else {
+                    return;
+                };
+                *latest.lock().unwrap_or_else(|e| e.into_inner()) = Some(device);
+                drop(latest);
+                thread::sleep(interval);
+            });
+        if let Err(e) = spawned {
+            tracing::warn!(target: AUDIO, "Can't watch the output device: {}", e);
+        }
+        Self { latest }
+    }
+
+    /// Gets the default device as last queried.
+    ///
+    /// # Returns
+    /// The device, or `None` if the first query hasn't finished.
+    pub fn latest(&self) -> Option<DefaultDevice> {
+        self.latest
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Instant;
+
+    #[test]
+    fn test_watcher_keeps_latest_device() {
+        let queries = Arc::new(AtomicU32::new(0));
+        let counter = Arc::clone(&queries);
+        let watcher = DeviceWatcher::with_query(Duration::from_millis(1), move || {
+            let n = counter.fetch_add(1, Ordering::Relaxed);
+            DefaultDevice {
+                name: Some(format!("device {}", n)),
+                sample_rate: Some(48_000),
+            }
+        });
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while queries.load(Ordering::Relaxed) < 3 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(1));
+        }
+        let device = watcher.latest().unwrap();
+        assert!(device.name.unwrap().starts_with("device "));
+        assert_eq!(device.sample_rate, Some(48_000));
+
+        // The thread stops querying once the watcher is gone
+        drop(watcher);
+        thread::sleep(Duration::from_millis(20));
+        let stopped = queries.load(Ordering::Relaxed);
+        thread::sleep(Duration::from_millis(20));
+        assert!(queries.load(Ordering::Relaxed) <= stopped + 1);
+    }
+}
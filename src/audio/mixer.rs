@@ -0,0 +1,163 @@
+//! Output mixer between the buzzer and the sample ring.
+//!
+//! The mixer applies the master volume (separate from the buzzer's own
+//! volume) and mutes or pauses output by fading the gain instead of
+//! cutting it, so toggling either never clicks or leaves a tone hanging.
+
+use serde::{Deserialize, Serialize};
+
+/// Largest master volume.
+pub const MAX_MASTER_VOLUME: f32 = 1.0;
+
+/// Mixer settings, saved in the `[audio.mixer]` config section.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MixerConfig {
+    /// Master volume applied on top of the buzzer volume (0.0 to 1.0).
+    pub master_volume: f32,
+
+    /// Whether output is muted.
+    pub muted: bool,
+
+    /// Length of the fade when muting, pausing or resuming, in milliseconds.
+    pub fade_ms: f32,
+}
+
+impl Default for MixerConfig {
+    fn default() -> Self {
+        Self {
+            master_volume: MAX_MASTER_VOLUME,
+            muted: false,
+            fade_ms: 10.0,
+        }
+    }
+}
+
+/// Applies master volume, mute and pause fades to output samples.
+#[derive(Debug, Clone)]
+pub struct Mixer {
+    /// Current settings.
+    config: MixerConfig,
+
+    /// Whether emulation is paused.
+    paused: bool,
+
+    /// Gain applied to the last sample.
+    gain: f32,
+
+    /// Largest gain change per sample while fading.
+    fade_step: f32,
+}
+
+impl Mixer {
+    /// Creates a mixer for output at `sample_rate`.
+    pub fn new(config: MixerConfig, sample_rate: u32) -> Self {
+        let fade_samples = (config.fade_ms.max(0.0) / 1000.0 * sample_rate as f32).max(1.0);
+        let mut mixer = Self {
+            config,
+            paused: false,
+            gain: 0.0,
+            fade_step: 1.0 / fade_samples,
+        };
+        mixer.config.master_volume = config.master_volume.clamp(0.0, MAX_MASTER_VOLUME);
+        mixer.gain = mixer.target_gain();
+        mixer
+    }
+
+    /// Gets the current settings.
+    pub fn config(&self) -> MixerConfig {
+        self.config
+    }
+
+    /// Sets the master volume, clamped to 0.0 to 1.0.
+    pub fn set_master_volume(&mut self, volume: f32) {
+        self.config.master_volume = volume.clamp(0.0, MAX_MASTER_VOLUME);
+    }
+
+    /// Gets the master volume.
+    pub fn master_volume(&self) -> f32 {
+        self.config.master_volume
+    }
+
+    /// Mutes or unmutes output, fading either way.
+    pub fn set_muted(&mut self, muted: bool) {
+        self.config.muted = muted;
+    }
+
+    /// Checks if output is muted.
+    pub fn is_muted(&self) -> bool {
+        self.config.muted
+    }
+
+    /// Fades output out while paused and back in on resume.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    /// Checks if output is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Gets the gain the mixer is fading towards.
+    pub fn target_gain(&self) -> f32 {
+        if self.config.muted || self.paused {
+            0.0
+        } else {
+            self.config.master_volume
+        }
+    }
+
+    /// Checks if the gain has reached its target, i.e. no fade is running.
+    pub fn is_settled(&self) -> bool {
+        self.gain == self.target_gain()
+    }
+
+    /// Mixes one sample, moving the gain one step towards its target.
+    pub fn process(&mut self, sample: f32) -> f32 {
+        let target = self.target_gain();
+        if self.gain < target {
+            self.gain = (self.gain + self.fade_step).min(target);
+        } else if self.gain > target {
+            self.gain = (self.gain - self.fade_step).max(target);
+        }
+        sample * self.gain
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mixer_fades() {
+        // 10 samples per fade
+        let config = MixerConfig {
+            master_volume: 0.5,
+            fade_ms: 10.0,
+            ..Default::default()
+        };
+        let mut mixer = Mixer::new(config, 1000);
+        assert_eq!(mixer.process(1.0), 0.5);
+
+        // Muting ramps down instead of cutting off
+        mixer.set_muted(true);
+        let faded: Vec<f32> = (0..6).map(|_| mixer.process(1.0)).collect();
+        assert!((faded[0] - 0.4).abs() < 1e-6);
+        assert!(faded.windows(2).all(|pair| pair[1] <= pair[0]));
+        assert_eq!(faded[5], 0.0);
+        assert!(mixer.is_settled());
+
+        // Pause keeps it silent after unmuting, resume fades back in
+        mixer.set_muted(false);
+        mixer.set_paused(true);
+        assert_eq!(mixer.process(1.0), 0.0);
+        mixer.set_paused(false);
+        assert!((mixer.process(1.0) - 0.1).abs() < 1e-6);
+        let last = (0..10).map(|_| mixer.process(1.0)).last().unwrap();
+        assert_eq!(last, 0.5);
+
+        mixer.set_master_volume(2.0);
+        assert_eq!(mixer.master_volume(), MAX_MASTER_VOLUME);
+    }
+}
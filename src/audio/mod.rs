@@ -1,93 +1,428 @@
 //! Audio system for the Chip-8 emulator.
 //!
 //! This module provides actual audio implementation using CPAL
-//! for real-time audio output with buzzer functionality.
+//! (or rodio, with the `rodio` feature) for real-time audio output with
+//! buzzer functionality.
 
+pub mod backend;
+pub mod bus;
 pub mod buzzer;
+pub mod device;
+pub mod mixer;
+pub mod offline;
+pub mod ring;
+#[cfg(feature = "rodio")]
+pub mod rodio_stream;
 pub mod stream;
+pub mod tap;
 
 // Re-export commonly used types
-pub use buzzer::{AudioBuzzer, BuzzerConfig};
-pub use stream::{AudioStream, StreamConfig};
-
+pub use backend::{AudioBackend, BackendKind};
+pub use bus::{AudioBus, BusVoice};
+pub use buzzer::{AudioBuzzer, BeepPattern, BuzzerConfig, Envelope};
+pub use device::{DefaultDevice, DeviceWatcher};
+pub use mixer::{Mixer, MixerConfig};
+pub use offline::{render_sound_timer, SoundTimerWrite};
+pub use ring::AudioStats;
+pub use stream::{AudioStream, Resampler, StreamConfig};
+pub use tap::{SampleTap, TAP_CAPACITY};
+
+use crate::emulator::TimeSource;
 use crate::error::AudioError;
 use crate::hardware::{Audio, AudioResult};
+use crate::logging::AUDIO;
+use ring::{sample_ring, RingConsumer, RingCounters, RingProducer};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Minimum time between checks for output device changes.
+const DEVICE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Capacity of the sample ring between the emulator and the audio callback.
+const RING_CAPACITY: usize = 2048;
+
+/// Number of samples kept buffered ahead of the audio callback (~23ms at 44.1kHz).
+const RING_TARGET_FILL: usize = 1024;
 
 /// Result type for audio operations.
 pub type AudioSystemResult<T> = Result<T, AudioError>;
 
+/// Clock driven by the audio output's sample position.
+///
+/// Pacing emulation from this clock instead of wall-clock time keeps it in
+/// step with the sound card, which avoids slow drift and the crackle of the
+/// ring buffer running dry or overflowing over long sessions.
+#[derive(Debug, Clone)]
+pub struct AudioClock {
+    /// Counters updated by the audio callback.
+    counters: Arc<RingCounters>,
+
+    /// Rate the counted samples are rendered at.
+    sample_rate: u32,
+}
+
+impl AudioClock {
+    /// Creates a clock reading a set of ring counters.
+    ///
+    /// # Arguments
+    ///
+    /// * `counters` - Counters shared with the audio callback
+    /// * `sample_rate` - Rate the counted samples are rendered at
+    pub fn new(counters: Arc<RingCounters>, sample_rate: u32) -> Self {
+        Self {
+            counters,
+            sample_rate: sample_rate.max(1),
+        }
+    }
+
+    /// Gets the number of samples consumed so far.
+    pub fn samples(&self) -> u64 {
+        self.counters.position()
+    }
+
+    /// Gets the sample the latest tone started at.
+    pub fn tone_start(&self) -> Option<u64> {
+        self.counters.tone_start()
+    }
+
+    /// Works out when a rendered sample reaches the speakers.
+    ///
+    /// # Returns
+    /// The time, or `None` until the output device has taken the sample.
+    pub fn played_at(&self, sample: u64) -> Option<Instant> {
+        self.counters.played_at(sample, self.sample_rate)
+    }
+
+    /// Gets the output latency the audio stream last reported.
+    pub fn output_latency(&self) -> Duration {
+        self.counters.output_latency()
+    }
+}
+
+impl TimeSource for AudioClock {
+    fn now(&self) -> Duration {
+        let nanos = self.samples() as u128 * 1_000_000_000 / self.sample_rate as u128;
+        Duration::from_nanos(nanos as u64)
+    }
+}
+
 /// Complete audio system that implements the Audio trait with real output.
+///
+/// Samples are rendered on the emulator thread and handed to the audio
+/// callback through a lock-free ring buffer.
 pub struct AudioSystem {
     /// The audio buzzer for generating tones.
     buzzer: AudioBuzzer,
 
     /// The audio stream for output.
-    stream: Option<AudioStream>,
+    stream: Option<Box<dyn AudioBackend>>,
+
+    /// Backend used when the stream is (re)built.
+    backend: BackendKind,
+
+    /// Producer half of the ring feeding the current stream.
+    producer: Option<RingProducer>,
+
+    /// Master volume, mute and pause fades applied to the buzzer output.
+    mixer: Mixer,
+
+    /// Underrun statistics (kept across stream rebuilds).
+    counters: Arc<RingCounters>,
+
+    /// Most recent samples rendered for the output.
+    tap: SampleTap,
 
     /// Current playing state.
     is_playing: bool,
+
+    /// Whether an output stream has been requested (and should be kept alive).
+    stream_requested: bool,
+
+    /// Time of the last output device check.
+    last_device_check: Option<Instant>,
+
+    /// Default output device, watched once a stream is requested.
+    device_watcher: Option<DeviceWatcher>,
 }
 
 impl AudioSystem {
     /// Creates a new audio system.
     pub fn new() -> AudioSystemResult<Self> {
         let buzzer = AudioBuzzer::new(BuzzerConfig::default())?;
-
-        Ok(Self {
-            buzzer,
-            stream: None,
-            is_playing: false,
-        })
+        Ok(Self::from_buzzer(buzzer))
     }
 
     /// Creates an audio system with custom configuration.
     pub fn with_config(
         buzzer_config: BuzzerConfig,
-        _stream_config: StreamConfig,
+        stream_config: StreamConfig,
     ) -> AudioSystemResult<Self> {
         let buzzer = AudioBuzzer::new(buzzer_config)?;
+        let mut system = Self::from_buzzer(buzzer);
+        system.backend = stream_config.backend;
+        Ok(system)
+    }
+
+    /// Creates an audio system around an existing buzzer.
+    fn from_buzzer(buzzer: AudioBuzzer) -> Self {
+        let config = buzzer.config();
+        let mixer = Mixer::new(config.mixer, config.sample_rate);
 
-        Ok(Self {
+        Self {
             buzzer,
             stream: None,
+            backend: BackendKind::default(),
+            producer: None,
+            mixer,
+            counters: Arc::new(RingCounters::new()),
+            tap: SampleTap::default(),
             is_playing: false,
-        })
+            stream_requested: false,
+            last_device_check: None,
+            device_watcher: None,
+        }
     }
 
     /// Initializes the audio stream for output.
     pub fn initialize_stream(&mut self, config: StreamConfig) -> AudioSystemResult<()> {
-        let stream = AudioStream::new(config, &mut self.buzzer)?;
-        self.stream = Some(stream);
+        self.request_stream();
+        self.backend = config.backend;
+        let (producer, consumer) = self.new_ring();
+        let stream = backend::open(config, consumer, self.source_rate())?;
+        self.attach(stream, producer);
         Ok(())
     }
 
-    /// Initializes with default stream configuration.
+    /// Initializes the stream using the default device's native format.
+    ///
+    /// The buzzer keeps generating at its configured sample rate and is
+    /// resampled to the device rate.
     pub fn initialize_with_defaults(&mut self) -> AudioSystemResult<()> {
-        self.initialize_stream(StreamConfig::default())
+        self.request_stream();
+        let (producer, consumer) = self.new_ring();
+        let stream = backend::auto_detect(self.backend, consumer, self.source_rate())?;
+        self.attach(stream, producer);
+        Ok(())
+    }
+
+    /// Marks the stream as wanted and starts watching the output device.
+    fn request_stream(&mut self) {
+        self.stream_requested = true;
+        if self.device_watcher.is_none() {
+            self.device_watcher = Some(DeviceWatcher::new(DEVICE_CHECK_INTERVAL));
+        }
+    }
+
+    /// Checks the output device and rebuilds the stream if it has changed.
+    ///
+    /// Uses the device watcher's latest answer, or asks the host (which can
+    /// block) if it hasn't got one yet.
+    ///
+    /// # Returns
+    /// `true` if the stream was (re)built, `false` if nothing needed doing.
+    pub fn check_device(&mut self) -> AudioSystemResult<bool> {
+        let device = match self.device_watcher.as_ref().and_then(DeviceWatcher::latest) {
+            Some(device) => device,
+            None if self.stream_requested => DefaultDevice::query(),
+            None => DefaultDevice::default(),
+        };
+        self.check_device_against(&device)
+    }
+
+    /// Rebuilds the stream if it doesn't match the default output device.
+    fn check_device_against(&mut self, device: &DefaultDevice) -> AudioSystemResult<bool> {
+        self.last_device_check = Some(Instant::now());
+
+        if !self.stream_requested {
+            return Ok(false);
+        }
+
+        let needs_rebuild = match &self.stream {
+            Some(stream) => stream.needs_rebuild(device),
+            // Reopening blocks, so wait until there is a device to open
+            None => device.name.is_some(),
+        };
+        if !needs_rebuild {
+            return Ok(false);
+        }
+
+        let (producer, consumer) = self.new_ring();
+        let source_rate = self.source_rate();
+
+        let stream = match self.stream.take() {
+            Some(mut stream) => stream.rebuild(consumer, source_rate).map(|_| stream),
+            None => {
+                backend::auto_detect(self.backend, consumer, source_rate).and_then(|mut stream| {
+                    if self.is_playing {
+                        stream.start()?;
+                    }
+                    Ok(stream)
+                })
+            }
+        };
+
+        match stream {
+            Ok(stream) => {
+                self.attach(stream, producer);
+                Ok(true)
+            }
+            Err(e) => {
+                // Device is gone; retry on a later check
+                self.producer = None;
+                Err(e)
+            }
+        }
+    }
+
+    /// Compares the stream with the device watcher's latest answer once
+    /// the check interval has elapsed, without waiting on the host.
+    fn poll_device(&mut self) {
+        let due = self
+            .last_device_check
+            .is_none_or(|last| last.elapsed() >= DEVICE_CHECK_INTERVAL);
+        let Some(device) = self
+            .device_watcher
+            .as_ref()
+            .filter(|_| due)
+            .and_then(DeviceWatcher::latest)
+        else {
+            return;
+        };
+
+        if let Err(e) = self.check_device_against(&device) {
+            tracing::warn!(target: AUDIO, "Audio device unavailable: {}", e);
+        }
+    }
+
+    /// Renders buzzer samples into the ring buffer up to the target fill level.
+    ///
+    /// This is called automatically whenever the emulator updates the
+    /// buzzer, beeping or not, and also picks up output device changes.
+    /// Frontends that pause emulation can call it directly to let release
+    /// tails finish.
+    pub fn pump(&mut self) {
+        self.poll_device();
+        let Some(producer) = self.producer.as_mut() else {
+            return;
+        };
+
+        while producer.len() < RING_TARGET_FILL {
+            let sample = self.mixer.process(self.buzzer.next_sample());
+            if !producer.push(sample) {
+                break;
+            }
+            self.tap.push(sample);
+        }
+    }
+
+    /// Renders samples into the preview tap only, when no stream is open.
+    ///
+    /// Lets tests and tools without an audio device look at the waveform
+    /// through [`recent_samples`](Self::recent_samples). Does nothing while
+    /// a stream is attached, since the samples would be missing from it.
+    pub fn render_preview(&mut self, count: usize) {
+        if self.producer.is_some() {
+            return;
+        }
+        for _ in 0..count {
+            let sample = self.mixer.process(self.buzzer.next_sample());
+            self.tap.push(sample);
+        }
+    }
+
+    /// Gets the most recent samples rendered for the output, oldest first.
+    ///
+    /// At most [`TAP_CAPACITY`] samples are kept, at the buzzer's sample
+    /// rate and after the mixer's volume, mute and pause fades.
+    pub fn recent_samples(&self) -> Vec<f32> {
+        self.tap.samples()
+    }
+
+    /// Renders a mixer fade in full, even past the target fill level.
+    fn render_fade(&mut self) {
+        let Some(producer) = self.producer.as_mut() else {
+            return;
+        };
+
+        while !self.mixer.is_settled() {
+            let sample = self.mixer.process(self.buzzer.next_sample());
+            if !producer.push(sample) {
+                break;
+            }
+            self.tap.push(sample);
+        }
+    }
+
+    /// Gets the mixer.
+    pub fn mixer(&self) -> &Mixer {
+        &self.mixer
+    }
+
+    /// Gets the ring buffer and underrun statistics.
+    pub fn stats(&self) -> AudioStats {
+        let buffered = self.producer.as_ref().map_or(0, |p| p.len());
+        self.counters.snapshot(buffered)
+    }
+
+    /// Resets the underrun statistics.
+    pub fn reset_stats(&self) {
+        self.counters.reset();
+    }
+
+    /// Gets a clock that follows the samples consumed by the output device.
+    ///
+    /// The clock stands still while no stream is running, and keeps working
+    /// across stream rebuilds because the counters are shared.
+    pub fn clock(&self) -> AudioClock {
+        AudioClock::new(Arc::clone(&self.counters), self.source_rate())
     }
 
     /// Checks if the audio stream is initialized.
     pub fn is_initialized(&self) -> bool {
         self.stream.is_some()
     }
+
+    /// Gets the backend used for the output stream.
+    pub fn backend(&self) -> BackendKind {
+        self.backend
+    }
+
+    /// Gets the sample rate the buzzer renders at.
+    fn source_rate(&self) -> u32 {
+        self.buzzer.config().sample_rate
+    }
+
+    /// Creates a new ring buffer sharing the system's statistics counters.
+    fn new_ring(&self) -> (RingProducer, RingConsumer) {
+        sample_ring(RING_CAPACITY, Arc::clone(&self.counters))
+    }
+
+    /// Installs a stream together with the producer feeding it.
+    fn attach(&mut self, stream: Box<dyn AudioBackend>, producer: RingProducer) {
+        self.stream = Some(stream);
+        self.producer = Some(producer);
+        self.pump();
+    }
 }
 
 impl Default for AudioSystem {
     fn default() -> Self {
-        Self::new().unwrap_or_else(|_| Self {
-            buzzer: AudioBuzzer::silent(),
-            stream: None,
-            is_playing: false,
-        })
+        Self::new().unwrap_or_else(|_| Self::from_buzzer(AudioBuzzer::silent()))
     }
 }
 
 impl Audio for AudioSystem {
     fn play_beep(&mut self) -> AudioResult<()> {
+        if !self.is_playing {
+            self.counters.mark_tone_start();
+        }
         self.buzzer.start_tone()?;
+        self.pump();
 
         if let Some(ref mut stream) = self.stream {
-            stream.start()?;
+            if !stream.is_playing() {
+                stream.start()?;
+            }
         }
 
         self.is_playing = true;
@@ -95,11 +430,10 @@ impl Audio for AudioSystem {
     }
 
     fn stop_beep(&mut self) -> AudioResult<()> {
+        // The stream keeps running so the envelope release can fade out;
+        // the buzzer outputs silence once the release has finished.
         self.buzzer.stop_tone()?;
-
-        if let Some(ref mut stream) = self.stream {
-            stream.pause()?;
-        }
+        self.pump();
 
         self.is_playing = false;
         Ok(())
@@ -124,6 +458,45 @@ impl Audio for AudioSystem {
     fn get_frequency(&self) -> f32 {
         self.buzzer.get_frequency()
     }
+
+    fn set_sound_timer(&mut self, value: u8) -> AudioResult<()> {
+        self.buzzer.set_sound_timer(value);
+        Ok(())
+    }
+
+    fn underruns(&self) -> u64 {
+        self.stats().underruns
+    }
+
+    fn set_master_volume(&mut self, volume: f32) -> AudioResult<()> {
+        self.mixer.set_master_volume(volume);
+        Ok(())
+    }
+
+    fn master_volume(&self) -> f32 {
+        self.mixer.master_volume()
+    }
+
+    fn set_muted(&mut self, muted: bool) -> AudioResult<()> {
+        self.mixer.set_muted(muted);
+        self.render_fade();
+        Ok(())
+    }
+
+    fn is_muted(&self) -> bool {
+        self.mixer.is_muted()
+    }
+
+    fn set_paused(&mut self, paused: bool) -> AudioResult<()> {
+        // The fade is rendered now, since nothing pumps while paused
+        self.mixer.set_paused(paused);
+        self.render_fade();
+        Ok(())
+    }
+
+    fn recent_samples(&self) -> Vec<f32> {
+        AudioSystem::recent_samples(self)
+    }
 }
 
 /// Type alias for headless audio operation - use the hardware null implementation.
@@ -186,6 +559,14 @@ mod tests {
         // Failure is acceptable in CI/headless environments
     }
 
+    #[test]
+    fn test_audio_system_keeps_stream_backend() {
+        let stream_config = StreamConfig::new().with_backend(BackendKind::Rodio);
+        let system = AudioSystem::with_config(BuzzerConfig::new(), stream_config).unwrap();
+        assert_eq!(system.backend(), BackendKind::Rodio);
+        assert_eq!(AudioSystem::default().backend(), BackendKind::Cpal);
+    }
+
     #[test]
     fn test_audio_system_stream_initialization() {
         let mut system = AudioSystem::default();
@@ -299,6 +680,108 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_check_device_without_stream() {
+        let mut system = AudioSystem::default();
+
+        // No stream was requested, so there is nothing to rebuild
+        assert!(!system.check_device().unwrap());
+        assert!(!system.is_initialized());
+
+        // A lost stream isn't reopened until a device shows up
+        system.stream_requested = true;
+        assert!(!system
+            .check_device_against(&DefaultDevice::default())
+            .unwrap());
+        assert!(!system.is_initialized());
+    }
+
+    #[test]
+    fn test_audio_stats_without_stream() {
+        let mut system = AudioSystem::default();
+
+        // Without a stream nothing is rendered or buffered
+        system.pump();
+        let stats = system.stats();
+        assert_eq!(stats, AudioStats::default());
+    }
+
+    #[test]
+    fn test_audio_clock_follows_consumed_samples() {
+        let system = AudioSystem::default();
+        let clock = system.clock();
+        assert_eq!(clock.now(), Duration::ZERO);
+
+        // Played and silent samples both advance the clock
+        let (mut producer, mut consumer) = system.new_ring();
+        producer.push(0.5);
+        let rate = system.source_rate() as u64;
+        for _ in 0..rate / 10 {
+            consumer.next_or_silence();
+        }
+        assert_eq!(clock.samples(), rate / 10);
+        assert_eq!(clock.now(), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_pause_fades_out() {
+        let mut system = AudioSystem::default();
+        let (producer, mut consumer) = system.new_ring();
+        system.producer = Some(producer);
+        system.play_beep().unwrap();
+        assert_eq!(consumer.len(), RING_TARGET_FILL);
+
+        // The whole fade is rendered at once, ending in silence
+        system.set_paused(true).unwrap();
+        assert!(consumer.len() > RING_TARGET_FILL);
+        let samples: Vec<f32> = std::iter::from_fn(|| consumer.pop()).collect();
+        assert_eq!(*samples.last().unwrap(), 0.0);
+        system.pump();
+        assert!(std::iter::from_fn(|| consumer.pop()).all(|sample| sample == 0.0));
+
+        system.set_muted(true).unwrap();
+        system.set_master_volume(0.5).unwrap();
+        assert!(system.is_muted());
+        assert_eq!(system.master_volume(), 0.5);
+    }
+
+    #[test]
+    fn test_recent_samples_follow_the_tone() {
+        let config = BuzzerConfig::new()
+            .with_waveform(crate::audio::buzzer::WaveformType::Square)
+            .with_envelope(Envelope::none())
+            .with_frequency(441.0);
+        let mut system = AudioSystem::with_config(config, StreamConfig::new()).unwrap();
+        system.render_preview(100);
+        assert!(system.recent_samples().iter().all(|&sample| sample == 0.0));
+
+        system.play_beep().unwrap();
+        system.render_preview(TAP_CAPACITY * 2);
+        let samples = system.recent_samples();
+        assert_eq!(samples.len(), TAP_CAPACITY);
+
+        // A square wave swings between two levels of opposite sign
+        let peak = samples.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+        assert!(peak > 0.0);
+        assert!(samples.iter().any(|&s| s > peak * 0.9));
+        assert!(samples.iter().any(|&s| s < -peak * 0.9));
+
+        // Samples pumped into a stream are tapped too
+        let (producer, _consumer) = system.new_ring();
+        system.producer = Some(producer);
+        system.tap.clear();
+        system.pump();
+        assert_eq!(
+            system.recent_samples().len(),
+            TAP_CAPACITY.min(RING_TARGET_FILL)
+        );
+        system.render_preview(10);
+        assert_eq!(
+            system.recent_samples().len(),
+            TAP_CAPACITY.min(RING_TARGET_FILL)
+        );
+    }
+
     #[test]
     fn test_audio_system_error_edge_cases() {
         // Test with various invalid configurations
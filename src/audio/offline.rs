@@ -0,0 +1,149 @@
+//! Rendering the buzzer without an audio device.
+//!
+//! [`render_sound_timer`] plays a sequence of sound timer writes through a
+//! buzzer the way the CPU does: the timer counts down at 60Hz and the tone
+//! is on while it is non-zero. The samples come back as a buffer, so tests
+//! can check when a beep starts and stops and how the envelope shapes it
+//! without a sound card.
+
+use std::time::Duration;
+
+use super::AudioBuzzer;
+use crate::emulator::TIMER_FREQUENCY;
+
+/// A value written to the sound timer, as by FX18.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SoundTimerWrite {
+    /// Time of the write from the start of rendering.
+    pub at: Duration,
+
+    /// Value written to the timer.
+    pub value: u8,
+}
+
+impl SoundTimerWrite {
+    /// Creates a write at a time from the start of rendering.
+    pub fn new(at: Duration, value: u8) -> Self {
+        Self { at, value }
+    }
+
+    /// Creates a write a number of 60Hz timer ticks from the start of rendering.
+    pub fn at_tick(tick: u32, value: u8) -> Self {
+        let nanos = tick as u64 * 1_000_000_000 / TIMER_FREQUENCY as u64;
+        Self::new(Duration::from_nanos(nanos), value)
+    }
+}
+
+/// Renders a buzzer driven by a sequence of sound timer writes.
+///
+/// The timer starts at zero and ticks down at 60Hz; each write sets it.
+/// Writes at the same moment as a tick land after it, and writes at the
+/// same moment as each other apply in the order given.
+///
+/// # Arguments
+/// * `buzzer` - Buzzer to render; its state carries on from the last call
+/// * `writes` - Timer writes, in any order
+/// * `duration` - Length of audio to render
+/// * `sample_rate` - Rate to render at in Hz
+pub fn render_sound_timer(
+    buzzer: &mut AudioBuzzer,
+    writes: &[SoundTimerWrite],
+    duration: Duration,
+    sample_rate: u32,
+) -> Vec<f32> {
+    let mut writes = writes.to_vec();
+    writes.sort_by_key(|write| write.at);
+    let mut writes = writes.into_iter().peekable();
+
+    let total = sample_index(duration, sample_rate);
+    let mut samples = Vec::with_capacity(total);
+    let mut timer = 0u8;
+    let mut tick = 1u64;
+    while samples.len() < total {
+        let now = samples.len();
+        while tick_index(tick, sample_rate) <= now {
+            timer = timer.saturating_sub(1);
+            tick += 1;
+        }
+        while let Some(write) = writes.next_if(|write| sample_index(write.at, sample_rate) <= now) {
+            timer = write.value;
+        }
+
+        // Same order as the CPU: report the timer, then gate the tone on it
+        buzzer.set_sound_timer(timer);
+        let _ = if timer > 0 {
+            buzzer.start_tone()
+        } else {
+            buzzer.stop_tone()
+        };
+
+        // Nothing changes until the next tick or write
+        let next_write = writes
+            .peek()
+            .map_or(total, |write| sample_index(write.at, sample_rate));
+        let next = tick_index(tick, sample_rate).min(next_write).min(total);
+        samples.extend(buzzer.render_samples(next - now, sample_rate));
+    }
+    samples
+}
+
+/// Gets the index of the sample nearest to a time.
+fn sample_index(at: Duration, sample_rate: u32) -> usize {
+    ((at.as_nanos() * sample_rate as u128 + 500_000_000) / 1_000_000_000) as usize
+}
+
+/// Gets the index of the sample nearest to a timer tick.
+fn tick_index(tick: u64, sample_rate: u32) -> usize {
+    let frequency = TIMER_FREQUENCY as u64;
+    ((tick * sample_rate as u64 + frequency / 2) / frequency) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::{BuzzerConfig, Envelope};
+
+    fn full_volume(envelope: Envelope) -> AudioBuzzer {
+        let config = BuzzerConfig::new().with_volume(1.0).with_envelope(envelope);
+        AudioBuzzer::new(config).unwrap()
+    }
+
+    #[test]
+    fn test_beep_follows_sound_timer() {
+        // 6 ticks at 60Hz is 100ms, or 4800 samples at 48kHz
+        let mut buzzer = full_volume(Envelope::none());
+        let writes = [SoundTimerWrite::at_tick(0, 6)];
+        let samples = render_sound_timer(&mut buzzer, &writes, Duration::from_millis(200), 48_000);
+        assert_eq!(samples.len(), 9600);
+        assert!(samples[..4800].iter().all(|&s| s != 0.0));
+        assert!(samples[4800..].iter().all(|&s| s == 0.0));
+
+        // Writing again before the timer runs out extends the beep, and a
+        // later write starts a new one
+        let mut buzzer = full_volume(Envelope::none());
+        let writes = [
+            SoundTimerWrite::at_tick(10, 2),
+            SoundTimerWrite::at_tick(0, 3),
+            SoundTimerWrite::at_tick(2, 3),
+        ];
+        let samples = render_sound_timer(&mut buzzer, &writes, Duration::from_millis(250), 48_000);
+        assert!(samples[..4000].iter().all(|&s| s != 0.0));
+        assert!(samples[4000..8000].iter().all(|&s| s == 0.0));
+        assert!(samples[8000..9600].iter().all(|&s| s != 0.0));
+        assert!(samples[9600..].iter().all(|&s| s == 0.0));
+    }
+
+    #[test]
+    fn test_envelope_release_tail() {
+        // 2ms attack and 5ms release at 1kHz are 2 and 5 samples
+        let mut buzzer = full_volume(Envelope::default());
+        let writes = [SoundTimerWrite::new(Duration::ZERO, 1)];
+        let samples = render_sound_timer(&mut buzzer, &writes, Duration::from_millis(40), 1000);
+        let levels: Vec<f32> = samples.iter().map(|s| s.abs()).collect();
+
+        assert!(levels[0] > 0.0 && levels[0] < 1.0);
+        assert!(levels[2..17].iter().all(|&level| level == 1.0));
+        assert!(levels[17..21].windows(2).all(|pair| pair[1] < pair[0]));
+        assert!(levels[22..].iter().all(|&level| level == 0.0));
+    }
+}
@@ -0,0 +1,467 @@
+//! Lock-free single-producer single-consumer sample ring buffer.
+//!
+//! The emulator thread renders buzzer samples and pushes them into the
+//! ring; the audio callback pops them without taking any locks, so a
+//! stalled emulator can never block the real-time audio thread.
+
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Marks a timestamp that hasn't been recorded yet.
+const UNSET: u64 = u64::MAX;
+
+/// Counters updated by the audio callback.
+#[derive(Debug)]
+pub struct RingCounters {
+    underruns: AtomicU64,
+    underrun_samples: AtomicU64,
+    samples_played: AtomicU64,
+    dropped_samples: AtomicU64,
+
+    /// Samples pushed into the ring so far.
+    samples_written: AtomicU64,
+
+    /// Sample the latest tone started at.
+    tone_start: AtomicU64,
+
+    /// Time the timestamps below count from.
+    epoch: Instant,
+
+    /// Start of the latest output buffer, in nanoseconds since `epoch`.
+    buffer_time: AtomicU64,
+
+    /// Samples played before the latest output buffer.
+    buffer_position: AtomicU64,
+
+    /// Time from the latest buffer's callback to its playback, in nanoseconds.
+    output_latency: AtomicU64,
+}
+
+impl Default for RingCounters {
+    fn default() -> Self {
+        Self {
+            underruns: AtomicU64::new(0),
+            underrun_samples: AtomicU64::new(0),
+            samples_played: AtomicU64::new(0),
+            dropped_samples: AtomicU64::new(0),
+            samples_written: AtomicU64::new(0),
+            tone_start: AtomicU64::new(UNSET),
+            epoch: Instant::now(),
+            buffer_time: AtomicU64::new(UNSET),
+            buffer_position: AtomicU64::new(0),
+            output_latency: AtomicU64::new(0),
+        }
+    }
+}
+
+impl RingCounters {
+    /// Creates a new set of zeroed counters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes a snapshot of the counters.
+    pub fn snapshot(&self, buffered: usize) -> AudioStats {
+        AudioStats {
+            underruns: self.underruns.load(Ordering::Relaxed),
+            underrun_samples: self.underrun_samples.load(Ordering::Relaxed),
+            samples_played: self.samples_played.load(Ordering::Relaxed),
+            dropped_samples: self.dropped_samples.load(Ordering::Relaxed),
+            buffered,
+        }
+    }
+
+    /// Gets the number of samples the audio callback has consumed, played or
+    /// replaced by silence. It only moves forward until the counters are reset.
+    pub fn position(&self) -> u64 {
+        self.samples_played.load(Ordering::Relaxed) + self.underrun_samples.load(Ordering::Relaxed)
+    }
+
+    /// Resets all counters to zero.
+    ///
+    /// The sample positions used for timing keep counting.
+    pub fn reset(&self) {
+        self.underruns.store(0, Ordering::Relaxed);
+        self.underrun_samples.store(0, Ordering::Relaxed);
+        self.samples_played.store(0, Ordering::Relaxed);
+        self.dropped_samples.store(0, Ordering::Relaxed);
+    }
+
+    /// Gets the number of samples pushed into the ring so far.
+    pub fn samples_written(&self) -> u64 {
+        self.samples_written.load(Ordering::Relaxed)
+    }
+
+    /// Notes that a tone starts with the next sample pushed.
+    pub fn mark_tone_start(&self) {
+        self.tone_start
+            .store(self.samples_written(), Ordering::Relaxed);
+    }
+
+    /// Gets the sample the latest tone started at, counted like
+    /// [`samples_written`](Self::samples_written).
+    pub fn tone_start(&self) -> Option<u64> {
+        let sample = self.tone_start.load(Ordering::Relaxed);
+        (sample != UNSET).then_some(sample)
+    }
+
+    /// Gets the time from the latest output buffer being filled to it
+    /// playing, as reported by the audio stream.
+    pub fn output_latency(&self) -> Duration {
+        Duration::from_nanos(self.output_latency.load(Ordering::Relaxed))
+    }
+
+    /// Works out when a pushed sample reaches the speakers.
+    ///
+    /// Counts on from the latest output buffer at `sample_rate`, and adds
+    /// the stream's output latency.
+    ///
+    /// # Returns
+    /// The time, or `None` until the sample has been handed to the device.
+    pub fn played_at(&self, sample: u64, sample_rate: u32) -> Option<Instant> {
+        let buffer_time = self.buffer_time.load(Ordering::Acquire);
+        if buffer_time == UNSET || self.samples_played.load(Ordering::Relaxed) <= sample {
+            return None;
+        }
+        let position = self.buffer_position.load(Ordering::Relaxed);
+        let buffer_start = self.epoch + Duration::from_nanos(buffer_time) + self.output_latency();
+        let rate = sample_rate.max(1) as u128;
+        let offset =
+            |samples: u64| Duration::from_nanos((samples as u128 * 1_000_000_000 / rate) as u64);
+        Some(if sample >= position {
+            buffer_start + offset(sample - position)
+        } else {
+            buffer_start
+                .checked_sub(offset(position - sample))
+                .unwrap_or(buffer_start)
+        })
+    }
+}
+
+/// Snapshot of audio ring buffer statistics.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AudioStats {
+    /// Number of output buffers that ran out of samples.
+    pub underruns: u64,
+
+    /// Total number of samples replaced by silence.
+    pub underrun_samples: u64,
+
+    /// Total number of samples delivered to the device.
+    pub samples_played: u64,
+
+    /// Samples the producer could not push because the ring was full.
+    pub dropped_samples: u64,
+
+    /// Samples currently waiting in the ring.
+    pub buffered: usize,
+}
+
+/// Storage shared by the producer and consumer halves.
+#[derive(Debug)]
+struct Ring {
+    /// Sample slots, stored as `f32` bit patterns.
+    slots: Box<[AtomicU32]>,
+
+    /// Index of the next slot to read (owned by the consumer).
+    head: AtomicUsize,
+
+    /// Index of the next slot to write (owned by the producer).
+    tail: AtomicUsize,
+
+    /// Statistics counters.
+    counters: Arc<RingCounters>,
+}
+
+impl Ring {
+    fn len(&self) -> usize {
+        let head = self.head.load(Ordering::Acquire);
+        let tail = self.tail.load(Ordering::Acquire);
+        tail.wrapping_sub(head)
+    }
+}
+
+/// Creates a ring buffer and returns its producer and consumer halves.
+///
+/// # Arguments
+/// * `capacity` - Maximum number of buffered samples
+/// * `counters` - Statistics counters (shared so they survive stream rebuilds)
+pub fn sample_ring(capacity: usize, counters: Arc<RingCounters>) -> (RingProducer, RingConsumer) {
+    let capacity = capacity.max(1);
+    let slots = (0..capacity).map(|_| AtomicU32::new(0)).collect();
+    let ring = Arc::new(Ring {
+        slots,
+        head: AtomicUsize::new(0),
+        tail: AtomicUsize::new(0),
+        counters,
+    });
+
+    (
+        RingProducer {
+            ring: Arc::clone(&ring),
+        },
+        RingConsumer {
+            ring,
+            starved: false,
+        },
+    )
+}
+
+/// Writing half of the sample ring (emulator thread).
+#[derive(Debug)]
+pub struct RingProducer {
+    ring: Arc<Ring>,
+}
+
+impl RingProducer {
+    /// Pushes a sample, returning `false` if the ring is full.
+    pub fn push(&mut self, sample: f32) -> bool {
+        let tail = self.ring.tail.load(Ordering::Relaxed);
+        let head = self.ring.head.load(Ordering::Acquire);
+
+        if tail.wrapping_sub(head) >= self.ring.slots.len() {
+            self.ring
+                .counters
+                .dropped_samples
+                .fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+
+        self.ring.slots[tail % self.ring.slots.len()].store(sample.to_bits(), Ordering::Relaxed);
+        self.ring
+            .tail
+            .store(tail.wrapping_add(1), Ordering::Release);
+        self.ring
+            .counters
+            .samples_written
+            .fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    /// Gets the number of buffered samples.
+    pub fn len(&self) -> usize {
+        self.ring.len()
+    }
+
+    /// Checks if the ring is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Gets the ring capacity.
+    pub fn capacity(&self) -> usize {
+        self.ring.slots.len()
+    }
+
+    /// Gets the statistics counters.
+    pub fn counters(&self) -> &Arc<RingCounters> {
+        &self.ring.counters
+    }
+}
+
+/// Reading half of the sample ring (audio callback).
+#[derive(Debug)]
+pub struct RingConsumer {
+    ring: Arc<Ring>,
+
+    /// Whether the current output buffer has run out of samples.
+    starved: bool,
+}
+
+impl RingConsumer {
+    /// Pops a sample, or returns `None` if the ring is empty.
+    pub fn pop(&mut self) -> Option<f32> {
+        let head = self.ring.head.load(Ordering::Relaxed);
+        let tail = self.ring.tail.load(Ordering::Acquire);
+
+        if head == tail {
+            return None;
+        }
+
+        let bits = self.ring.slots[head % self.ring.slots.len()].load(Ordering::Relaxed);
+        self.ring
+            .head
+            .store(head.wrapping_add(1), Ordering::Release);
+        Some(f32::from_bits(bits))
+    }
+
+    /// Pops a sample, substituting silence and recording an underrun if empty.
+    pub fn next_or_silence(&mut self) -> f32 {
+        match self.pop() {
+            Some(sample) => {
+                let counters = &self.ring.counters;
+                counters.samples_played.fetch_add(1, Ordering::Relaxed);
+                sample
+            }
+            None => {
+                self.starved = true;
+                let counters = &self.ring.counters;
+                counters.underrun_samples.fetch_add(1, Ordering::Relaxed);
+                0.0
+            }
+        }
+    }
+
+    /// Marks the start of an output buffer, for working out when its
+    /// samples play.
+    ///
+    /// # Arguments
+    /// * `output_latency` - Time until the buffer plays, if the stream knows it
+    pub fn begin_buffer(&mut self, output_latency: Option<Duration>) {
+        let counters = &self.ring.counters;
+        let now = counters.epoch.elapsed().as_nanos() as u64;
+        let latency = output_latency.map_or(0, |latency| latency.as_nanos() as u64);
+        counters.output_latency.store(latency, Ordering::Relaxed);
+        counters.buffer_position.store(
+            counters.samples_played.load(Ordering::Relaxed),
+            Ordering::Relaxed,
+        );
+        counters.buffer_time.store(now, Ordering::Release);
+    }
+
+    /// Marks the end of an output buffer, counting it as an underrun if it starved.
+    pub fn finish_buffer(&mut self) {
+        if self.starved {
+            self.ring.counters.underruns.fetch_add(1, Ordering::Relaxed);
+            self.starved = false;
+        }
+    }
+
+    /// Gets the number of buffered samples.
+    pub fn len(&self) -> usize {
+        self.ring.len()
+    }
+
+    /// Checks if the ring is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ring_push_pop_order() {
+        let (mut producer, mut consumer) = sample_ring(4, Arc::new(RingCounters::new()));
+
+        assert!(producer.push(0.1));
+        assert!(producer.push(0.2));
+        assert_eq!(producer.len(), 2);
+
+        assert_eq!(consumer.pop(), Some(0.1));
+        assert_eq!(consumer.pop(), Some(0.2));
+        assert_eq!(consumer.pop(), None);
+        assert!(consumer.is_empty());
+    }
+
+    #[test]
+    fn test_ring_full_drops_samples() {
+        let counters = Arc::new(RingCounters::new());
+        let (mut producer, mut consumer) = sample_ring(2, Arc::clone(&counters));
+
+        assert!(producer.push(1.0));
+        assert!(producer.push(2.0));
+        assert!(!producer.push(3.0));
+        assert_eq!(counters.snapshot(producer.len()).dropped_samples, 1);
+
+        // Space is reclaimed after the consumer reads
+        assert_eq!(consumer.pop(), Some(1.0));
+        assert!(producer.push(3.0));
+        assert_eq!(consumer.pop(), Some(2.0));
+        assert_eq!(consumer.pop(), Some(3.0));
+    }
+
+    #[test]
+    fn test_ring_underrun_statistics() {
+        let counters = Arc::new(RingCounters::new());
+        let (mut producer, mut consumer) = sample_ring(8, Arc::clone(&counters));
+
+        producer.push(0.5);
+        let buffer: Vec<f32> = (0..4).map(|_| consumer.next_or_silence()).collect();
+        consumer.finish_buffer();
+
+        assert_eq!(buffer, vec![0.5, 0.0, 0.0, 0.0]);
+        let stats = counters.snapshot(consumer.len());
+        assert_eq!(stats.underruns, 1);
+        assert_eq!(stats.underrun_samples, 3);
+        assert_eq!(stats.samples_played, 1);
+        assert_eq!(counters.position(), 4);
+
+        // A fully supplied buffer is not an underrun
+        producer.push(0.5);
+        consumer.next_or_silence();
+        consumer.finish_buffer();
+        assert_eq!(counters.snapshot(0).underruns, 1);
+
+        counters.reset();
+        assert_eq!(counters.snapshot(0), AudioStats::default());
+    }
+
+    #[test]
+    fn test_ring_playback_timing() {
+        let counters = Arc::new(RingCounters::new());
+        let (mut producer, mut consumer) = sample_ring(8, Arc::clone(&counters));
+        for _ in 0..2 {
+            producer.push(0.0);
+        }
+        counters.mark_tone_start();
+        assert_eq!(counters.tone_start(), Some(2));
+        for _ in 0..4 {
+            producer.push(0.5);
+        }
+        assert_eq!(counters.samples_written(), 6);
+        assert_eq!(counters.played_at(2, 1000), None);
+
+        // 1kHz, so each sample is a millisecond after the buffer plays
+        consumer.begin_buffer(Some(Duration::from_millis(20)));
+        let start =
+            counters.epoch + Duration::from_nanos(counters.buffer_time.load(Ordering::Relaxed));
+        for _ in 0..4 {
+            consumer.next_or_silence();
+        }
+        consumer.finish_buffer();
+        assert_eq!(counters.played_at(4, 1000), None);
+        assert_eq!(
+            counters.played_at(2, 1000),
+            Some(start + Duration::from_millis(22))
+        );
+        assert_eq!(counters.output_latency(), Duration::from_millis(20));
+
+        // Later buffers count back to earlier samples
+        consumer.begin_buffer(None);
+        let start =
+            counters.epoch + Duration::from_nanos(counters.buffer_time.load(Ordering::Relaxed));
+        assert_eq!(
+            counters.played_at(2, 1000),
+            Some(start - Duration::from_millis(2))
+        );
+    }
+
+    #[test]
+    fn test_ring_across_threads() {
+        let (mut producer, mut consumer) = sample_ring(64, Arc::new(RingCounters::new()));
+
+        let handle = std::thread::spawn(move || {
+            let mut received = Vec::new();
+            while received.len() < 1000 {
+                if let Some(sample) = consumer.pop() {
+                    received.push(sample);
+                }
+            }
+            received
+        });
+
+        let mut next = 0;
+        while next < 1000 {
+            if producer.push(next as f32) {
+                next += 1;
+            }
+        }
+
+        let received = handle.join().unwrap();
+        assert!(received.iter().enumerate().all(|(i, &s)| s == i as f32));
+    }
+}
@@ -0,0 +1,225 @@
+//! Audio output through rodio.
+//!
+//! An alternative to the direct CPAL stream for setups where the CPAL
+//! stream doesn't work. rodio resamples to the device rate itself, so the
+//! ring buffer is played as-is at the buzzer's rate.
+
+use cpal::traits::{DeviceTrait, HostTrait};
+use rodio::{OutputStream, OutputStreamHandle, Sink, Source};
+use std::time::Duration;
+
+use super::backend::{AudioBackend, BackendKind};
+use super::device::DefaultDevice;
+use super::ring::RingConsumer;
+use super::stream::StreamConfig;
+use super::AudioSystemResult;
+use crate::error::AudioError;
+use crate::logging::AUDIO;
+
+/// Samples after which the ring source counts as having finished a buffer.
+const SOURCE_BUFFER_SAMPLES: usize = 512;
+
+/// rodio source that pulls samples from the ring buffer forever.
+struct RingSource {
+    /// Ring buffer filled by the emulator.
+    ring: RingConsumer,
+
+    /// Rate the ring is filled at.
+    sample_rate: u32,
+
+    /// Samples left until the current buffer ends.
+    remaining: usize,
+}
+
+impl RingSource {
+    fn new(ring: RingConsumer, sample_rate: u32) -> Self {
+        Self {
+            ring,
+            sample_rate: sample_rate.max(1),
+            remaining: SOURCE_BUFFER_SAMPLES,
+        }
+    }
+}
+
+impl Iterator for RingSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        // rodio doesn't report its output latency
+        if self.remaining == SOURCE_BUFFER_SAMPLES {
+            self.ring.begin_buffer(None);
+        }
+        let sample = self.ring.next_or_silence();
+
+        // rodio hides the device's buffers, so underruns are counted per
+        // fixed-size chunk instead
+        self.remaining -= 1;
+        if self.remaining == 0 {
+            self.ring.finish_buffer();
+            self.remaining = SOURCE_BUFFER_SAMPLES;
+        }
+
+        Some(sample)
+    }
+}
+
+impl Source for RingSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Audio stream played through a rodio sink.
+pub struct RodioStream {
+    /// The rodio output stream (must outlive the sink).
+    _stream: OutputStream,
+
+    /// Handle to the output stream, kept for the sink.
+    _handle: OutputStreamHandle,
+
+    /// Sink playing the ring source.
+    sink: Sink,
+
+    /// Configuration for the stream.
+    config: StreamConfig,
+
+    /// Name of the device when the stream was created.
+    device_name: String,
+}
+
+impl RodioStream {
+    /// Creates a stream on the default output device.
+    ///
+    /// rodio picks the device format itself; only `config.backend` and
+    /// `config.sample_rate` (reported back by [`AudioBackend::config`])
+    /// are kept.
+    pub fn new(
+        config: StreamConfig,
+        source: RingConsumer,
+        source_rate: u32,
+    ) -> AudioSystemResult<Self> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or(AudioError::DeviceNotAvailable)?;
+        let device_name = device.name().unwrap_or_default();
+
+        let (stream, handle) = OutputStream::try_from_device(&device)
+            .map_err(|e| AudioError::StreamError(e.to_string()))?;
+        let sink = Sink::try_new(&handle).map_err(|e| AudioError::StreamError(e.to_string()))?;
+
+        // Paused until started, like the CPAL stream
+        sink.pause();
+        sink.append(RingSource::new(source, source_rate));
+
+        Ok(Self {
+            _stream: stream,
+            _handle: handle,
+            sink,
+            config,
+            device_name,
+        })
+    }
+
+    /// Creates a stream reporting the default device's sample rate.
+    pub fn auto_detect(source: RingConsumer, source_rate: u32) -> AudioSystemResult<Self> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or(AudioError::DeviceNotAvailable)?;
+        let supported_config = device
+            .default_output_config()
+            .map_err(|e| AudioError::StreamError(e.to_string()))?;
+
+        let config = StreamConfig::new()
+            .with_sample_rate(supported_config.sample_rate().0)
+            .with_channels(supported_config.channels())
+            .with_backend(BackendKind::Rodio);
+
+        Self::new(config, source, source_rate)
+    }
+}
+
+impl AudioBackend for RodioStream {
+    fn kind(&self) -> BackendKind {
+        BackendKind::Rodio
+    }
+
+    fn start(&mut self) -> AudioSystemResult<()> {
+        self.sink.play();
+        Ok(())
+    }
+
+    fn pause(&mut self) -> AudioSystemResult<()> {
+        self.sink.pause();
+        Ok(())
+    }
+
+    fn is_playing(&self) -> bool {
+        !self.sink.is_paused()
+    }
+
+    fn needs_rebuild(&self, device: &DefaultDevice) -> bool {
+        device.name.as_deref() != Some(self.device_name.as_str())
+    }
+
+    fn rebuild(&mut self, source: RingConsumer, source_rate: u32) -> AudioSystemResult<()> {
+        let was_playing = self.is_playing();
+        let mut rebuilt = Self::auto_detect(source, source_rate)?;
+
+        if was_playing {
+            rebuilt.start()?;
+        }
+
+        tracing::info!(target: AUDIO, "Audio stream rebuilt on '{}' (rodio)", rebuilt.device_name);
+        *self = rebuilt;
+        Ok(())
+    }
+
+    fn config(&self) -> &StreamConfig {
+        &self.config
+    }
+
+    fn device_name(&self) -> Result<String, AudioError> {
+        Ok(self.device_name.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::ring::{sample_ring, RingCounters};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_ring_source_counts_underruns_per_chunk() {
+        let counters = Arc::new(RingCounters::new());
+        let (mut producer, consumer) = sample_ring(1024, Arc::clone(&counters));
+        for _ in 0..10 {
+            producer.push(0.25);
+        }
+
+        let mut source = RingSource::new(consumer, 22050);
+        assert_eq!(source.sample_rate(), 22050);
+        assert_eq!(source.channels(), 1);
+
+        // The source never ends; missing samples are silence
+        let samples: Vec<f32> = source.by_ref().take(SOURCE_BUFFER_SAMPLES).collect();
+        assert_eq!(samples[0], 0.25);
+        assert_eq!(samples[SOURCE_BUFFER_SAMPLES - 1], 0.0);
+
+        let stats = counters.snapshot(0);
+        assert_eq!(stats.underruns, 1);
+        assert_eq!(stats.samples_played, 10);
+    }
+}
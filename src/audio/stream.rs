@@ -7,13 +7,15 @@ use cpal::{
     traits::{DeviceTrait, HostTrait, StreamTrait},
     BufferSize, Device, SampleFormat, SampleRate, Stream, StreamConfig as CpalStreamConfig,
 };
-use std::sync::{Arc, Mutex};
-
-use super::{
-    buzzer::{AudioBuzzer, BuzzerState},
-    AudioSystemResult,
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
 };
+use std::time::Duration;
+
+use super::{backend::BackendKind, device::DefaultDevice, ring::RingConsumer, AudioSystemResult};
 use crate::error::AudioError;
+use crate::logging::AUDIO;
 
 /// Configuration for audio streaming.
 #[derive(Debug, Clone)]
@@ -29,6 +31,9 @@ pub struct StreamConfig {
 
     /// Sample format (f32, i16, etc.).
     pub sample_format: StreamSampleFormat,
+
+    /// Backend that plays the stream.
+    pub backend: BackendKind,
 }
 
 /// Supported sample formats for streaming.
@@ -56,6 +61,7 @@ impl Default for StreamConfig {
             channels: 1, // Mono for simplicity
             buffer_size: 1024,
             sample_format: StreamSampleFormat::F32,
+            backend: BackendKind::Cpal,
         }
     }
 }
@@ -90,6 +96,12 @@ impl StreamConfig {
         self
     }
 
+    /// Sets the output backend.
+    pub fn with_backend(mut self, backend: BackendKind) -> Self {
+        self.backend = backend;
+        self
+    }
+
     /// Creates a low-latency configuration.
     pub fn low_latency() -> Self {
         Self::new().with_buffer_size(256).with_sample_rate(48000)
@@ -104,6 +116,55 @@ impl StreamConfig {
     }
 }
 
+/// Linear-interpolating sample rate converter.
+///
+/// The buzzer always generates at its configured rate; the resampler
+/// converts that to whatever rate the output device is running at.
+#[derive(Debug, Clone)]
+pub struct Resampler {
+    /// Source samples consumed per output sample.
+    step: f64,
+
+    /// Fractional position between `previous` and `current`.
+    position: f64,
+
+    /// Previous source sample.
+    previous: f32,
+
+    /// Current source sample.
+    current: f32,
+}
+
+impl Resampler {
+    /// Creates a resampler converting from `source_rate` to `target_rate`.
+    pub fn new(source_rate: u32, target_rate: u32) -> Self {
+        Self {
+            step: source_rate.max(1) as f64 / target_rate.max(1) as f64,
+            position: 1.0,
+            previous: 0.0,
+            current: 0.0,
+        }
+    }
+
+    /// Gets the number of source samples consumed per output sample.
+    pub fn ratio(&self) -> f64 {
+        self.step
+    }
+
+    /// Produces the next output sample, pulling source samples as needed.
+    pub fn next_sample(&mut self, mut source: impl FnMut() -> f32) -> f32 {
+        while self.position >= 1.0 {
+            self.previous = self.current;
+            self.current = source();
+            self.position -= 1.0;
+        }
+
+        let sample = self.previous + (self.current - self.previous) * self.position as f32;
+        self.position += self.step;
+        sample
+    }
+}
+
 /// Audio stream for real-time audio output.
 pub struct AudioStream {
     /// The CPAL audio stream.
@@ -115,30 +176,58 @@ pub struct AudioStream {
     /// The audio device being used.
     device: Device,
 
+    /// Name of the device when the stream was created.
+    device_name: String,
+
+    /// The device's default sample rate when the stream was created.
+    device_rate: Option<u32>,
+
+    /// Set by the error callback when the stream stops working.
+    failed: Arc<AtomicBool>,
+
     /// Whether the stream is currently playing.
     is_playing: bool,
 }
 
 impl AudioStream {
     /// Creates a new audio stream.
-    pub fn new(config: StreamConfig, buzzer: &mut AudioBuzzer) -> AudioSystemResult<Self> {
+    ///
+    /// # Arguments
+    /// * `config` - Output stream configuration
+    /// * `source` - Ring buffer the callback pulls buzzer samples from
+    /// * `source_rate` - Sample rate the buzzer renders at
+    pub fn new(
+        config: StreamConfig,
+        source: RingConsumer,
+        source_rate: u32,
+    ) -> AudioSystemResult<Self> {
         let host = cpal::default_host();
         let device = host
             .default_output_device()
             .ok_or(AudioError::DeviceNotAvailable)?;
 
-        let stream = Self::create_stream(&device, &config, buzzer)?;
+        let device_name = device.name().unwrap_or_default();
+        let device_rate = device
+            .default_output_config()
+            .ok()
+            .map(|c| c.sample_rate().0);
+        let failed = Arc::new(AtomicBool::new(false));
+        let stream =
+            Self::create_stream(&device, &config, source, source_rate, Arc::clone(&failed))?;
 
         Ok(Self {
             stream,
             config,
             device,
+            device_name,
+            device_rate,
+            failed,
             is_playing: false,
         })
     }
 
     /// Creates a stream with automatic device detection.
-    pub fn auto_detect(buzzer: &mut AudioBuzzer) -> AudioSystemResult<Self> {
+    pub fn auto_detect(source: RingConsumer, source_rate: u32) -> AudioSystemResult<Self> {
         let host = cpal::default_host();
         let device = host
             .default_output_device()
@@ -158,9 +247,10 @@ impl AudioStream {
                 SampleFormat::U16 => StreamSampleFormat::U16,
                 _ => StreamSampleFormat::F32, // Fallback
             },
+            backend: BackendKind::Cpal,
         };
 
-        Self::new(config, buzzer)
+        Self::new(config, source, source_rate)
     }
 
     /// Starts the audio stream.
@@ -186,6 +276,46 @@ impl AudioStream {
         self.is_playing
     }
 
+    /// Checks if the stream has reported an error.
+    pub fn has_failed(&self) -> bool {
+        self.failed.load(Ordering::Relaxed)
+    }
+
+    /// Checks if the stream should be rebuilt.
+    ///
+    /// This is the case when the stream has failed, the default output
+    /// device has changed or disappeared, or its sample rate has changed.
+    ///
+    /// # Arguments
+    /// * `device` - The current default output device
+    pub fn needs_rebuild(&self, device: &DefaultDevice) -> bool {
+        self.has_failed()
+            || device.name.as_deref() != Some(self.device_name.as_str())
+            || device.sample_rate != self.device_rate
+    }
+
+    /// Rebuilds the stream on the current default device.
+    ///
+    /// The new stream is configured for the device's native format and
+    /// resumes playing if the old stream was playing.
+    pub fn rebuild(&mut self, source: RingConsumer, source_rate: u32) -> AudioSystemResult<()> {
+        let was_playing = self.is_playing;
+        let mut rebuilt = Self::auto_detect(source, source_rate)?;
+
+        if was_playing {
+            rebuilt.start()?;
+        }
+
+        tracing::info!(
+            target: AUDIO,
+            "Audio stream rebuilt on '{}' at {}Hz",
+            rebuilt.device_name,
+            rebuilt.config.sample_rate
+        );
+        *self = rebuilt;
+        Ok(())
+    }
+
     /// Gets the stream configuration.
     pub fn config(&self) -> &StreamConfig {
         &self.config
@@ -202,7 +332,9 @@ impl AudioStream {
     fn create_stream(
         device: &Device,
         config: &StreamConfig,
-        buzzer: &AudioBuzzer,
+        mut source: RingConsumer,
+        source_rate: u32,
+        failed: Arc<AtomicBool>,
     ) -> AudioSystemResult<Stream> {
         let sample_rate = SampleRate(config.sample_rate);
         let channels = config.channels;
@@ -214,18 +346,22 @@ impl AudioStream {
             buffer_size,
         };
 
-        let buzzer_state = buzzer.get_state();
-        let sample_rate_f32 = config.sample_rate as f32;
+        let mut resampler = Resampler::new(source_rate, config.sample_rate);
+        let error_callback = move |err| {
+            tracing::error!(target: AUDIO, "Audio stream error: {}", err);
+            failed.store(true, Ordering::Relaxed);
+        };
 
         match config.sample_format {
             StreamSampleFormat::F32 => {
                 let stream = device
                     .build_output_stream(
                         &cpal_config,
-                        move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
-                            Self::fill_f32_buffer(data, &buzzer_state, sample_rate_f32, channels);
+                        move |data: &mut [f32], info: &cpal::OutputCallbackInfo| {
+                            source.begin_buffer(output_latency(info));
+                            Self::fill_f32_buffer(data, &mut source, &mut resampler, channels);
                         },
-                        move |err| log::error!("Audio stream error: {}", err),
+                        error_callback,
                         None,
                     )
                     .map_err(|e| AudioError::StreamError(e.to_string()))?;
@@ -236,10 +372,11 @@ impl AudioStream {
                 let stream = device
                     .build_output_stream(
                         &cpal_config,
-                        move |data: &mut [i16], _: &cpal::OutputCallbackInfo| {
-                            Self::fill_i16_buffer(data, &buzzer_state, sample_rate_f32, channels);
+                        move |data: &mut [i16], info: &cpal::OutputCallbackInfo| {
+                            source.begin_buffer(output_latency(info));
+                            Self::fill_i16_buffer(data, &mut source, &mut resampler, channels);
                         },
-                        move |err| log::error!("Audio stream error: {}", err),
+                        error_callback,
                         None,
                     )
                     .map_err(|e| AudioError::StreamError(e.to_string()))?;
@@ -250,10 +387,11 @@ impl AudioStream {
                 let stream = device
                     .build_output_stream(
                         &cpal_config,
-                        move |data: &mut [u16], _: &cpal::OutputCallbackInfo| {
-                            Self::fill_u16_buffer(data, &buzzer_state, sample_rate_f32, channels);
+                        move |data: &mut [u16], info: &cpal::OutputCallbackInfo| {
+                            source.begin_buffer(output_latency(info));
+                            Self::fill_u16_buffer(data, &mut source, &mut resampler, channels);
                         },
-                        move |err| log::error!("Audio stream error: {}", err),
+                        error_callback,
                         None,
                     )
                     .map_err(|e| AudioError::StreamError(e.to_string()))?;
@@ -266,101 +404,64 @@ impl AudioStream {
     /// Fills an f32 audio buffer.
     fn fill_f32_buffer(
         data: &mut [f32],
-        buzzer_state: &Arc<Mutex<BuzzerState>>,
-        sample_rate: f32,
+        source: &mut RingConsumer,
+        resampler: &mut Resampler,
         channels: u16,
     ) {
-        if let Ok(mut state) = buzzer_state.lock() {
-            for frame in data.chunks_mut(channels as usize) {
-                let sample = if state.playing {
-                    let waveform_sample = (state.phase * 2.0 * std::f32::consts::PI).sin();
-                    state.phase += state.frequency / sample_rate;
-
-                    if state.phase >= 1.0 {
-                        state.phase -= 1.0;
-                    }
-
-                    waveform_sample * state.volume
-                } else {
-                    0.0
-                };
-
-                // Fill all channels with the same sample (mono -> stereo duplication)
-                for channel_sample in frame {
-                    *channel_sample = sample;
-                }
+        for frame in data.chunks_mut(channels as usize) {
+            let sample = resampler.next_sample(|| source.next_or_silence());
+
+            // Fill all channels with the same sample (mono -> stereo duplication)
+            for channel_sample in frame {
+                *channel_sample = sample;
             }
-        } else {
-            // If we can't lock the state, output silence
-            data.fill(0.0);
         }
+        source.finish_buffer();
     }
 
     /// Fills an i16 audio buffer.
     fn fill_i16_buffer(
         data: &mut [i16],
-        buzzer_state: &Arc<Mutex<BuzzerState>>,
-        sample_rate: f32,
+        source: &mut RingConsumer,
+        resampler: &mut Resampler,
         channels: u16,
     ) {
-        if let Ok(mut state) = buzzer_state.lock() {
-            for frame in data.chunks_mut(channels as usize) {
-                let sample = if state.playing {
-                    let waveform_sample = (state.phase * 2.0 * std::f32::consts::PI).sin();
-                    state.phase += state.frequency / sample_rate;
-
-                    if state.phase >= 1.0 {
-                        state.phase -= 1.0;
-                    }
-
-                    let float_sample = waveform_sample * state.volume;
-                    (float_sample * i16::MAX as f32) as i16
-                } else {
-                    0
-                };
-
-                for channel_sample in frame {
-                    *channel_sample = sample;
-                }
+        for frame in data.chunks_mut(channels as usize) {
+            let float_sample = resampler.next_sample(|| source.next_or_silence());
+            let sample = (float_sample * i16::MAX as f32) as i16;
+
+            for channel_sample in frame {
+                *channel_sample = sample;
             }
-        } else {
-            data.fill(0);
         }
+        source.finish_buffer();
     }
 
     /// Fills a u16 audio buffer.
     fn fill_u16_buffer(
         data: &mut [u16],
-        buzzer_state: &Arc<Mutex<BuzzerState>>,
-        sample_rate: f32,
+        source: &mut RingConsumer,
+        resampler: &mut Resampler,
         channels: u16,
     ) {
-        if let Ok(mut state) = buzzer_state.lock() {
-            for frame in data.chunks_mut(channels as usize) {
-                let sample = if state.playing {
-                    let waveform_sample = (state.phase * 2.0 * std::f32::consts::PI).sin();
-                    state.phase += state.frequency / sample_rate;
-
-                    if state.phase >= 1.0 {
-                        state.phase -= 1.0;
-                    }
-
-                    let float_sample = waveform_sample * state.volume;
-                    ((float_sample + 1.0) * 0.5 * u16::MAX as f32) as u16
-                } else {
-                    u16::MAX / 2 // Middle value for unsigned
-                };
-
-                for channel_sample in frame {
-                    *channel_sample = sample;
-                }
+        for frame in data.chunks_mut(channels as usize) {
+            let float_sample = resampler.next_sample(|| source.next_or_silence());
+            let sample = ((float_sample + 1.0) * 0.5 * u16::MAX as f32) as u16;
+
+            for channel_sample in frame {
+                *channel_sample = sample;
             }
-        } else {
-            data.fill(u16::MAX / 2);
         }
+        source.finish_buffer();
     }
 }
 
+/// Gets the time from a callback to its buffer playing, if the host reports it.
+fn output_latency(info: &cpal::OutputCallbackInfo) -> Option<Duration> {
+    let timestamp = info.timestamp();
+    timestamp.playback.duration_since(&timestamp.callback)
+}
+
 /// Lists available audio devices.
 pub fn list_audio_devices() -> Result<Vec<String>, AudioError> {
     let host = cpal::default_host();
@@ -402,6 +503,7 @@ mod tests {
         assert_eq!(config.channels, 1);
         assert_eq!(config.buffer_size, 1024);
         assert_eq!(config.sample_format, StreamSampleFormat::F32);
+        assert_eq!(config.backend, BackendKind::Cpal);
     }
 
     #[test]
@@ -480,6 +582,73 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_resampler_passthrough() {
+        let mut resampler = Resampler::new(44100, 44100);
+        let mut source = [0.1f32, 0.2, 0.3, 0.4].into_iter();
+
+        let output: Vec<f32> = (0..4)
+            .map(|_| resampler.next_sample(|| source.next().unwrap_or(0.0)))
+            .collect();
+
+        // One sample of latency, otherwise identical
+        assert_eq!(output, vec![0.0, 0.1, 0.2, 0.3]);
+    }
+
+    #[test]
+    fn test_resampler_downsampling() {
+        let mut resampler = Resampler::new(88200, 44100);
+        assert_eq!(resampler.ratio(), 2.0);
+
+        let mut pulled = 0;
+        for _ in 0..100 {
+            resampler.next_sample(|| {
+                pulled += 1;
+                0.0
+            });
+        }
+
+        // Two source samples per output sample
+        assert!((199..=201).contains(&pulled));
+    }
+
+    #[test]
+    fn test_resampler_upsampling_interpolates() {
+        let mut resampler = Resampler::new(22050, 44100);
+        let mut source = [1.0f32, 1.0, 0.0, 0.0].into_iter();
+
+        let output: Vec<f32> = (0..6)
+            .map(|_| resampler.next_sample(|| source.next().unwrap_or(0.0)))
+            .collect();
+
+        // Midpoints are linearly interpolated between source samples
+        assert_eq!(output, vec![0.0, 0.5, 1.0, 1.0, 1.0, 0.5]);
+    }
+
+    #[test]
+    fn test_fill_buffer_from_ring() {
+        use crate::audio::ring::{sample_ring, RingCounters};
+
+        let counters = Arc::new(RingCounters::new());
+        let (mut producer, mut consumer) = sample_ring(256, Arc::clone(&counters));
+        for _ in 0..20 {
+            producer.push(0.5);
+        }
+
+        let mut resampler = Resampler::new(44100, 44100);
+        let mut data = [0.0f32; 96];
+        AudioStream::fill_f32_buffer(&mut data, &mut consumer, &mut resampler, 2);
+
+        // Stereo frames carry the same sample on both channels
+        assert!(data.chunks(2).all(|frame| frame[0] == frame[1]));
+        assert!(data.contains(&0.5));
+
+        // 48 frames but only 20 samples buffered: the rest is an underrun
+        let stats = counters.snapshot(consumer.len());
+        assert_eq!(stats.underruns, 1);
+        assert_eq!(stats.samples_played, 20);
+    }
+
     // Note: AudioStream creation tests require an actual audio device,
     // so they would fail in headless CI environments.
     // For integration testing, these would need to be run manually
@@ -0,0 +1,85 @@
+//! Preview tap on the generated audio.
+//!
+//! The tap keeps the most recent samples rendered for the output, after the
+//! mixer, so frontends can draw an oscilloscope view and tests can check
+//! the waveform without an audio device.
+
+use std::collections::VecDeque;
+
+/// Number of samples kept by default (~23ms at 44.1kHz).
+pub const TAP_CAPACITY: usize = 1024;
+
+/// Ring of the most recent samples, oldest first.
+#[derive(Debug, Clone)]
+pub struct SampleTap {
+    /// Recent samples, oldest at the front.
+    samples: VecDeque<f32>,
+
+    /// Most samples kept at once.
+    capacity: usize,
+}
+
+impl SampleTap {
+    /// Creates an empty tap keeping up to `capacity` samples.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Records a sample, forgetting the oldest one if the tap is full.
+    pub fn push(&mut self, sample: f32) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    /// Copies the recorded samples, oldest first.
+    pub fn samples(&self) -> Vec<f32> {
+        self.samples.iter().copied().collect()
+    }
+
+    /// Gets the number of samples recorded.
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Checks if no samples have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Forgets every sample.
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+}
+
+impl Default for SampleTap {
+    fn default() -> Self {
+        Self::new(TAP_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tap_keeps_most_recent() {
+        let mut tap = SampleTap::new(3);
+        assert!(tap.is_empty());
+        for sample in [0.1, 0.2, 0.3, 0.4] {
+            tap.push(sample);
+        }
+        assert_eq!(tap.samples(), vec![0.2, 0.3, 0.4]);
+
+        tap.clear();
+        assert_eq!(tap.len(), 0);
+    }
+}
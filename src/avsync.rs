@@ -0,0 +1,269 @@
+//! Audio-visual sync measurement.
+//!
+//! [`sync_rom`] builds a tiny ROM that, at a fixed interval, starts the
+//! buzzer and lights a block in the middle of the screen on the same frame,
+//! then clears the block when the beep ends. A frontend running it feeds an
+//! [`AvSyncProbe`] the time each frame is presented and the sample each
+//! beep starts at; the audio stream's timestamps turn the sample into the
+//! time it reaches the speakers, and the probe reports how far the sound
+//! lands from the picture. That offset is what audio buffer sizes and the
+//! present mode trade against each other.
+
+use std::fmt;
+use std::time::Instant;
+
+/// Default frames from one flash to the next.
+pub const DEFAULT_SYNC_INTERVAL: u8 = 60;
+
+/// Default frames each flash and beep lasts.
+pub const DEFAULT_BEEP_FRAMES: u8 = 6;
+
+/// Default number of beeps measured.
+pub const DEFAULT_SYNC_BEEPS: usize = 10;
+
+/// Code of the sync ROM, with the timings left as zero.
+///
+/// Each flash sets the sound timer, restarts the delay timer for the whole
+/// interval and draws a 32x15 block back to back. The block is drawn again
+/// to clear it once the delay timer shows the flash is over, and the next
+/// flash starts when it runs out, so the interval doesn't drift with the
+/// time spent drawing.
+const SYNC_ROM: [u8; 67] = [
+    0x00, 0xE0, // CLS
+    0xA2, 0x34, // LD I, 0x234
+    0x64, 0x00, // LD V4, length
+    0x65, 0x00, // LD V5, interval
+    0x66, 0x00, // LD V6, interval - length
+    0xF4, 0x18, // 0x20A: LD ST, V4
+    0xF5, 0x15, // LD DT, V5
+    0x22, 0x20, // CALL 0x220
+    0xF1, 0x07, // 0x210: LD V1, DT
+    0x51, 0x60, // SE V1, V6
+    0x12, 0x10, // JP 0x210
+    0x22, 0x20, // CALL 0x220
+    0xF1, 0x07, // 0x218: LD V1, DT
+    0x31, 0x00, // SE V1, 0
+    0x12, 0x18, // JP 0x218
+    0x12, 0x0A, // JP 0x20A
+    0x62, 0x10, // 0x220: LD V2, 16
+    0x63, 0x08, // LD V3, 8
+    0xD2, 0x3F, // DRW V2, V3, 15
+    0x72, 0x08, // ADD V2, 8
+    0xD2, 0x3F, // DRW V2, V3, 15
+    0x72, 0x08, // ADD V2, 8
+    0xD2, 0x3F, // DRW V2, V3, 15
+    0x72, 0x08, // ADD V2, 8
+    0xD2, 0x3F, // DRW V2, V3, 15
+    0x00, 0xEE, // RET
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, // 0x234: block sprite
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+];
+
+/// Builds a ROM that flashes the screen and beeps on the same frame.
+///
+/// Values are clamped so each flash lasts at least a frame and is followed
+/// by a gap of at least one.
+///
+/// # Arguments
+/// * `interval` - Frames from one flash to the next
+/// * `length` - Frames each flash and beep lasts
+pub fn sync_rom(interval: u8, length: u8) -> Vec<u8> {
+    let length = length.clamp(1, u8::MAX - 1);
+    let interval = interval.max(length + 1);
+    let mut rom = SYNC_ROM.to_vec();
+    rom[5] = length;
+    rom[7] = interval;
+    rom[9] = interval - length;
+    rom
+}
+
+/// Pairs presented flashes with their beeps and collects the offsets.
+#[derive(Debug, Clone, Default)]
+pub struct AvSyncProbe {
+    /// Whether the last presented frame showed the flash.
+    lit: bool,
+
+    /// When the latest flash was presented, until its beep is heard.
+    flash: Option<Instant>,
+
+    /// Sample the latest beep started at, until it is paired.
+    tone: Option<u64>,
+
+    /// Latest beep seen, so each is only counted once.
+    last_tone: Option<u64>,
+
+    /// Measured offsets in milliseconds, positive when the sound is late.
+    offsets: Vec<f64>,
+}
+
+impl AvSyncProbe {
+    /// Creates a probe with no measurements.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Notes a frame reaching the screen, and whether it shows the flash.
+    pub fn frame_presented(&mut self, flash: bool, at: Instant) {
+        if flash && !self.lit {
+            self.flash = Some(at);
+        }
+        self.lit = flash;
+    }
+
+    /// Notes the sample the latest beep started at.
+    pub fn tone_started(&mut self, sample: Option<u64>) {
+        if sample.is_some() && sample != self.last_tone {
+            self.last_tone = sample;
+            self.tone = sample;
+        }
+    }
+
+    /// Measures the pending flash against its beep once the beep is heard.
+    ///
+    /// # Arguments
+    /// * `played_at` - When a sample reaches the speakers, once known
+    ///
+    /// # Returns
+    /// The new offset in milliseconds, positive when the sound is late.
+    pub fn update(&mut self, played_at: impl Fn(u64) -> Option<Instant>) -> Option<f64> {
+        let flash = self.flash?;
+        let heard = played_at(self.tone?)?;
+        let offset = if heard >= flash {
+            (heard - flash).as_secs_f64()
+        } else {
+            -(flash - heard).as_secs_f64()
+        } * 1000.0;
+        self.flash = None;
+        self.tone = None;
+        self.offsets.push(offset);
+        Some(offset)
+    }
+
+    /// Gets the offsets measured so far, in milliseconds.
+    pub fn offsets(&self) -> &[f64] {
+        &self.offsets
+    }
+
+    /// Summarizes the offsets measured so far.
+    pub fn report(&self) -> Option<AvSyncReport> {
+        AvSyncReport::from_offsets(&self.offsets)
+    }
+}
+
+/// Summary of measured audio-visual offsets, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AvSyncReport {
+    /// Beeps measured.
+    pub beeps: usize,
+
+    /// Average offset; positive when the sound comes after the picture.
+    pub mean_ms: f64,
+
+    /// Smallest offset.
+    pub min_ms: f64,
+
+    /// Largest offset.
+    pub max_ms: f64,
+
+    /// Standard deviation of the offsets.
+    pub jitter_ms: f64,
+}
+
+impl AvSyncReport {
+    /// Summarizes a set of offsets.
+    ///
+    /// # Returns
+    /// The summary, or `None` if there are no offsets.
+    pub fn from_offsets(offsets: &[f64]) -> Option<Self> {
+        if offsets.is_empty() {
+            return None;
+        }
+        let count = offsets.len() as f64;
+        let mean = offsets.iter().sum::<f64>() / count;
+        let variance = offsets.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / count;
+        Some(Self {
+            beeps: offsets.len(),
+            mean_ms: mean,
+            min_ms: offsets.iter().copied().fold(f64::INFINITY, f64::min),
+            max_ms: offsets.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            jitter_ms: variance.sqrt(),
+        })
+    }
+}
+
+impl fmt::Display for AvSyncReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&format!(
+            "A/V offset {:+.1} ms over {} beeps (min {:+.1}, max {:+.1}, jitter {:.1})",
+            self.mean_ms, self.beeps, self.min_ms, self.max_ms, self.jitter_ms
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::Cpu;
+    use crate::hardware::NullHardware;
+    use std::time::Duration;
+
+    #[test]
+    fn test_sync_rom_flashes_with_beep() {
+        let mut cpu = Cpu::with_hardware(NullHardware::new());
+        cpu.set_realtime_timers(false);
+        cpu.load_rom(&sync_rom(10, 3)).unwrap();
+
+        // Record the frames that end lit and beeping, at 11 instructions each
+        let mut frames = Vec::new();
+        for _ in 0..40 {
+            for _ in 0..11 {
+                cpu.cycle().unwrap();
+            }
+            let lit = cpu.get_display_buffer().iter().any(|&pixel| pixel);
+            let beeping = cpu.get_timers().get_sound_timer() > 0;
+            frames.push((lit, beeping));
+            cpu.timers_mut().update_by_ticks(1);
+        }
+        let flashes: Vec<usize> = (1..frames.len())
+            .filter(|&i| frames[i].0 && !frames[i - 1].0)
+            .collect();
+        assert!(flashes.len() >= 3, "{:?}", frames);
+        assert!(flashes.windows(2).all(|pair| pair[1] - pair[0] == 10));
+        assert!(flashes.iter().all(|&i| frames[i] == (true, true)));
+        let lit = frames.iter().filter(|frame| frame.0).count();
+        assert!((lit as f64 / frames.len() as f64) < 0.5);
+    }
+
+    #[test]
+    fn test_probe_pairs_flash_and_beep() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut probe = AvSyncProbe::new();
+        let heard = |sample: u64| (sample == 100).then(|| at(140));
+
+        probe.tone_started(Some(100));
+        assert_eq!(probe.update(heard), None);
+        probe.frame_presented(true, at(100));
+        probe.frame_presented(true, at(116));
+        assert_eq!(probe.update(heard), Some(40.0));
+        assert_eq!(probe.update(heard), None);
+
+        // The same beep isn't counted twice, and early sound is negative
+        probe.frame_presented(false, at(200));
+        probe.tone_started(Some(100));
+        probe.frame_presented(true, at(1000));
+        assert_eq!(probe.update(heard), None);
+        probe.tone_started(Some(200));
+        assert_eq!(probe.update(|_| Some(at(990))), Some(-10.0));
+
+        let report = probe.report().unwrap();
+        assert_eq!(report.beeps, 2);
+        assert_eq!(report.mean_ms, 15.0);
+        assert_eq!(report.jitter_ms, 25.0);
+        assert_eq!(
+            report.to_string(),
+            "A/V offset +15.0 ms over 2 beeps (min -10.0, max +40.0, jitter 25.0)"
+        );
+        assert!(AvSyncProbe::new().report().is_none());
+    }
+}
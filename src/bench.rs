@@ -0,0 +1,204 @@
+//! Headless benchmarks.
+//!
+//! Runs a ROM on null hardware for a fixed number of instructions, then
+//! measures `PixelRenderer` frame throughput and buzzer sample generation,
+//! so results can be compared across commits. Without a ROM, a built-in
+//! synthetic workload is used that mixes drawing, ALU operations, random
+//! numbers and subroutine calls.
+
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::audio::{AudioBuzzer, BuzzerConfig};
+use crate::emulator::{Cpu, CpuEvent};
+use crate::error::{EmulatorError, Result};
+use crate::graphics::PixelRenderer;
+use crate::hardware::{ChipKey, NullHardware};
+
+/// Built-in workload used when no ROM is given.
+///
+/// Loops forever drawing random font glyphs across the screen, with a
+/// subroutine call and some arithmetic on every iteration.
+pub const SYNTHETIC_ROM: [u8; 28] = [
+    0x00, 0xE0, // CLS
+    0x60, 0x00, // LD V0, 0
+    0x61, 0x00, // LD V1, 0
+    0xC2, 0xFF, // 0x206: RND V2, 0xFF
+    0xF2, 0x29, // LD F, V2
+    0xD0, 0x15, // DRW V0, V1, 5
+    0x70, 0x08, // ADD V0, 8
+    0x81, 0x24, // ADD V1, V2
+    0x22, 0x14, // CALL 0x214
+    0x12, 0x06, // JP 0x206
+    0x83, 0x00, // 0x214: LD V3, V0
+    0x83, 0x14, // ADD V3, V1
+    0x83, 0x25, // SUB V3, V2
+    0x00, 0xEE, // RET
+];
+
+/// Name reported for the built-in workload.
+pub const SYNTHETIC_NAME: &str = "synthetic";
+
+/// How much work each benchmark does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchOptions {
+    /// Instructions to execute.
+    pub instructions: u64,
+
+    /// Frames to render.
+    pub frames: u32,
+
+    /// Audio samples to generate.
+    pub samples: usize,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        Self {
+            instructions: 5_000_000,
+            frames: 2_000,
+            samples: 4_410_000,
+        }
+    }
+}
+
+/// Benchmark results.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BenchReport {
+    /// ROM file name, or `SYNTHETIC_NAME`.
+    pub rom: String,
+
+    /// Instructions executed.
+    pub instructions: u64,
+
+    /// Instructions executed per second.
+    pub instructions_per_second: f64,
+
+    /// Frames rendered.
+    pub frames: u32,
+
+    /// Frames rendered per second.
+    pub frames_per_second: f64,
+
+    /// Audio samples generated.
+    pub samples: usize,
+
+    /// Audio samples generated per second.
+    pub samples_per_second: f64,
+}
+
+/// Runs all benchmarks on a ROM.
+///
+/// # Arguments
+/// * `rom` - ROM data to execute
+/// * `name` - Name to report for the ROM
+/// * `options` - How much work each benchmark does
+pub fn run_benchmarks(rom: &[u8], name: &str, options: &BenchOptions) -> Result<BenchReport> {
+    let (cpu, cpu_time) = bench_cpu(rom, options.instructions)?;
+    let render_time = bench_renderer(cpu.get_display_buffer(), cpu.display_size(), options.frames)?;
+    let audio_time = bench_audio(options.samples)?;
+
+    Ok(BenchReport {
+        rom: name.to_string(),
+        instructions: options.instructions,
+        instructions_per_second: rate(options.instructions as f64, cpu_time),
+        frames: options.frames,
+        frames_per_second: rate(options.frames as f64, render_time),
+        samples: options.samples,
+        samples_per_second: rate(options.samples as f64, audio_time),
+    })
+}
+
+/// Executes a ROM for a fixed number of instructions.
+///
+/// Key waits are answered with key 0 so the run never blocks, and the
+/// timers tick every 11 instructions (700 instructions per second at 60Hz).
+///
+/// # Returns
+/// The CPU after the run and the time the run took.
+pub fn bench_cpu(rom: &[u8], instructions: u64) -> Result<(Cpu<NullHardware>, Duration)> {
+    let mut cpu = Cpu::with_hardware(NullHardware::new());
+    cpu.seed_rng(0);
+    cpu.set_realtime_timers(false);
+    cpu.load_rom(rom)?;
+
+    let start = Instant::now();
+    for executed in 1..=instructions {
+        if cpu.cycle()? == CpuEvent::WaitingForKey {
+            cpu.resume_with_key(ChipKey::Key0)?;
+        }
+        if executed % 11 == 0 {
+            cpu.timers_mut().update_by_ticks(1);
+        }
+    }
+    Ok((cpu, start.elapsed()))
+}
+
+/// Renders the same display buffer, `size` pixels across and down, a number
+/// of times with the default config.
+pub fn bench_renderer(buffer: &[bool], size: (usize, usize), frames: u32) -> Result<Duration> {
+    let mut renderer = PixelRenderer::with_defaults().map_err(EmulatorError::Graphics)?;
+    renderer
+        .resize(size.0, size.1)
+        .map_err(EmulatorError::Graphics)?;
+
+    let start = Instant::now();
+    for _ in 0..frames {
+        renderer.render(buffer).map_err(EmulatorError::Graphics)?;
+    }
+    Ok(start.elapsed())
+}
+
+/// Generates buzzer samples in 1024-sample blocks with the tone held on.
+pub fn bench_audio(samples: usize) -> Result<Duration> {
+    let mut buzzer = AudioBuzzer::new(BuzzerConfig::default())?;
+    buzzer.start_tone()?;
+    let mut block = [0.0f32; 1024];
+
+    let start = Instant::now();
+    let mut remaining = samples;
+    while remaining > 0 {
+        let count = remaining.min(block.len());
+        buzzer.fill_buffer(&mut block[..count]);
+        remaining -= count;
+    }
+    Ok(start.elapsed())
+}
+
+/// Divides a count by a duration, treating an instant run as one nanosecond.
+fn rate(count: f64, elapsed: Duration) -> f64 {
+    count / elapsed.as_secs_f64().max(1e-9)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_synthetic_workload() {
+        let options = BenchOptions {
+            instructions: 10_000,
+            frames: 3,
+            samples: 3000,
+        };
+        let report = run_benchmarks(&SYNTHETIC_ROM, SYNTHETIC_NAME, &options).unwrap();
+
+        assert_eq!(report.rom, SYNTHETIC_NAME);
+        assert_eq!(report.instructions, 10_000);
+        assert!(report.instructions_per_second > 0.0);
+        assert!(report.frames_per_second > 0.0);
+        assert!(report.samples_per_second > 0.0);
+
+        let json: serde_json::Value = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["frames"], 3);
+    }
+
+    #[test]
+    fn test_key_waits_do_not_block() {
+        // LD V0, K; JP 0x200
+        let (cpu, _) = bench_cpu(&[0xF0, 0x0A, 0x12, 0x00], 100).unwrap();
+        assert!(!cpu.is_waiting_for_key());
+        assert!(cpu.get_display_buffer().iter().all(|&pixel| !pixel));
+    }
+}
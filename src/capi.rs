@@ -0,0 +1,336 @@
+//! C ABI for embedding the emulator core.
+//!
+//! Enabled with the `capi` feature. The emulator is exposed as an opaque
+//! handle driven one frame at a time, with timers ticked per frame rather
+//! than from the wall clock so hosts control pacing. The matching header is
+//! `include/chip8.h` (regenerate it with `cbindgen --config cbindgen.toml
+//! --output include/chip8.h`).
+//!
+//! Handles are not thread-safe; each must be used from one thread at a time.
+
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use crate::frontend::SimpleEmulator;
+use crate::hardware::display::SoftwareDisplay;
+use crate::hardware::input::SoftwareInput;
+use crate::hardware::{ChipKey, Input, DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use crate::logging::{APP, CPU, INPUT};
+
+/// Result of a C API call.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chip8Status {
+    /// The call succeeded.
+    Ok = 0,
+
+    /// A required pointer was null.
+    NullPointer = 1,
+
+    /// An argument was out of range.
+    InvalidArgument = 2,
+
+    /// The ROM was empty or too large.
+    RomError = 3,
+
+    /// The program faulted (unknown opcode, stack fault, bad memory access).
+    Fault = 4,
+}
+
+/// A view of the display: one byte per pixel, 0 for off and 1 for on, row-major.
+///
+/// The pointer stays valid until the next call that takes the emulator
+/// mutably, or until it is destroyed.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Chip8Framebuffer {
+    /// First pixel of the top row.
+    pub pixels: *const u8,
+
+    /// Width in pixels.
+    pub width: u32,
+
+    /// Height in pixels.
+    pub height: u32,
+
+    /// Bytes between the starts of consecutive rows.
+    pub stride: u32,
+}
+
+/// Opaque emulator handle.
+pub struct Chip8Emulator {
+    /// The emulator being driven.
+    emulator: SimpleEmulator,
+
+    /// Keypad state set by the host.
+    input: Arc<Mutex<SoftwareInput>>,
+}
+
+impl Chip8Emulator {
+    /// Creates an emulator with an in-memory display and host-driven keypad.
+    fn new() -> Self {
+        let input = Arc::new(Mutex::new(SoftwareInput::new()));
+        let mut emulator = SimpleEmulator::new();
+        let cpu = emulator.cpu_mut();
+        cpu.set_display(Box::new(SoftwareDisplay::new()));
+        cpu.set_input(input.clone());
+        cpu.set_realtime_timers(false);
+
+        Self { emulator, input }
+    }
+
+    /// Locks the keypad, recovering it if a panic poisoned the lock.
+    ///
+    /// The key state is a plain set of flags, so it's still usable, and
+    /// panicking here would unwind across the C boundary.
+    fn input(&self) -> MutexGuard<'_, SoftwareInput> {
+        self.input.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Creates an emulator. Free it with `chip8_destroy`.
+#[no_mangle]
+pub extern "C" fn chip8_create() -> *mut Chip8Emulator {
+    Box::into_raw(Box::new(Chip8Emulator::new()))
+}
+
+/// Destroys an emulator created by `chip8_create`.
+///
+/// # Safety
+/// `emulator` must be null or a handle from `chip8_create` that hasn't
+/// been destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_destroy(emulator: *mut Chip8Emulator) {
+    if !emulator.is_null() {
+        drop(Box::from_raw(emulator));
+    }
+}
+
+/// Loads a ROM from memory and resets the machine.
+///
+/// Registers, timers, memory and the display are cleared first. An invalid
+/// ROM is rejected before anything changes.
+///
+/// # Safety
+/// `emulator` must be a live handle and `data` must point to `length`
+/// readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_load_rom(
+    emulator: *mut Chip8Emulator,
+    data: *const u8,
+    length: usize,
+) -> Chip8Status {
+    let Some(emulator) = emulator.as_mut() else {
+        return Chip8Status::NullPointer;
+    };
+    if data.is_null() {
+        return Chip8Status::NullPointer;
+    }
+
+    let rom = std::slice::from_raw_parts(data, length);
+    match emulator.emulator.swap_rom(rom) {
+        Ok(()) => Chip8Status::Ok,
+        Err(e) => {
+            tracing::warn!(target: APP, "Failed to load ROM: {}", e);
+            Chip8Status::RomError
+        }
+    }
+}
+
+/// Runs one 60 Hz frame: a frame's worth of instructions and one timer tick.
+///
+/// # Safety
+/// `emulator` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_step_frame(emulator: *mut Chip8Emulator) -> Chip8Status {
+    let Some(emulator) = emulator.as_mut() else {
+        return Chip8Status::NullPointer;
+    };
+
+    let result = emulator.emulator.advance_frame();
+    if let Err(e) = emulator.input().update() {
+        tracing::warn!(target: INPUT, "Input update error: {}", e);
+    }
+    match result {
+        Ok(_) => Chip8Status::Ok,
+        Err(e) => {
+            tracing::error!(target: CPU, "Emulator error: {}", e);
+            Chip8Status::Fault
+        }
+    }
+}
+
+/// Gets the current display contents.
+///
+/// Returns a framebuffer with a null `pixels` pointer if `emulator` is null.
+///
+/// # Safety
+/// `emulator` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_framebuffer(emulator: *const Chip8Emulator) -> Chip8Framebuffer {
+    let (pixels, (width, height)) = match emulator.as_ref() {
+        // bool is one byte holding 0 or 1
+        Some(emulator) => (
+            emulator.emulator.get_display_buffer().as_ptr() as *const u8,
+            emulator.emulator.display_size(),
+        ),
+        None => (std::ptr::null(), (DISPLAY_WIDTH, DISPLAY_HEIGHT)),
+    };
+
+    Chip8Framebuffer {
+        pixels,
+        width: width as u32,
+        height: height as u32,
+        stride: width as u32,
+    }
+}
+
+/// Presses or releases a keypad key (0x0-0xF).
+///
+/// # Safety
+/// `emulator` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_set_key(
+    emulator: *mut Chip8Emulator,
+    key: u8,
+    pressed: bool,
+) -> Chip8Status {
+    let Some(emulator) = emulator.as_mut() else {
+        return Chip8Status::NullPointer;
+    };
+    let Some(key) = ChipKey::from_u8(key) else {
+        return Chip8Status::InvalidArgument;
+    };
+
+    let mut input = emulator.input();
+    if pressed {
+        input.press_key(key);
+    } else {
+        input.release_key(key);
+    }
+    Chip8Status::Ok
+}
+
+/// Checks if the buzzer should be sounding.
+///
+/// # Safety
+/// `emulator` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn chip8_sound_active(emulator: *const Chip8Emulator) -> bool {
+    emulator
+        .as_ref()
+        .is_some_and(|emulator| emulator.emulator.cpu().should_play_sound())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capi_lifecycle() {
+        // LD V0, 5; LD F, V0; DRW V1, V1, 5; LD V2, K; JP 0x208
+        let rom = [0x60, 0x05, 0xF0, 0x29, 0xD1, 0x15, 0xF2, 0x0A, 0x12, 0x08];
+
+        unsafe {
+            let emulator = chip8_create();
+            assert_eq!(
+                chip8_load_rom(emulator, rom.as_ptr(), rom.len()),
+                Chip8Status::Ok
+            );
+            assert_eq!(chip8_step_frame(emulator), Chip8Status::Ok);
+
+            let frame = chip8_framebuffer(emulator);
+            assert_eq!((frame.width, frame.height, frame.stride), (64, 32, 64));
+            let pixels = std::slice::from_raw_parts(frame.pixels, 64 * 32);
+            // Top row of the "5" glyph is 0xF0
+            assert_eq!(&pixels[..5], &[1, 1, 1, 1, 0]);
+
+            // The program is blocked on LD V2, K until a key is pressed and released
+            assert_eq!(chip8_set_key(emulator, 0xA, true), Chip8Status::Ok);
+            chip8_step_frame(emulator);
+            assert_eq!(chip8_set_key(emulator, 0xA, false), Chip8Status::Ok);
+            chip8_step_frame(emulator);
+            assert_eq!((*emulator).emulator.cpu().get_state().v[2], 0xA);
+
+            assert_eq!(
+                chip8_set_key(emulator, 0x10, true),
+                Chip8Status::InvalidArgument
+            );
+            assert!(!chip8_sound_active(emulator));
+            chip8_destroy(emulator);
+        }
+    }
+
+    #[test]
+    fn test_capi_reload_rom() {
+        // LD V0, 5; LD F, V0; DRW V1, V1, 5; JP 0x206
+        let first = [0x60, 0x05, 0xF0, 0x29, 0xD1, 0x15, 0x12, 0x06];
+        // JP 0x200
+        let second = [0x12, 0x00];
+
+        unsafe {
+            let emulator = chip8_create();
+            chip8_load_rom(emulator, first.as_ptr(), first.len());
+            chip8_step_frame(emulator);
+
+            assert_eq!(
+                chip8_load_rom(emulator, second.as_ptr(), second.len()),
+                Chip8Status::Ok
+            );
+            let cpu = (*emulator).emulator.cpu();
+            let state = cpu.get_state();
+            assert_eq!(state.pc, 0x200);
+            assert_eq!(state.v[0], 0);
+            assert_eq!(state.i, 0);
+            // The tail of the first ROM is gone
+            assert_eq!(cpu.get_memory().read_byte(0x202).unwrap(), 0);
+
+            let frame = chip8_framebuffer(emulator);
+            let pixels = std::slice::from_raw_parts(frame.pixels, 64 * 32);
+            assert!(pixels.iter().all(|&pixel| pixel == 0));
+            chip8_destroy(emulator);
+        }
+    }
+
+    #[test]
+    fn test_capi_poisoned_input() {
+        unsafe {
+            let emulator = chip8_create();
+            let input = (*emulator).input.clone();
+            let _ = std::thread::spawn(move || {
+                let _guard = input.lock().unwrap();
+                panic!("poison the keypad lock");
+            })
+            .join();
+            assert!((*emulator).input.is_poisoned());
+
+            let rom = [0x12, 0x00];
+            chip8_load_rom(emulator, rom.as_ptr(), rom.len());
+            assert_eq!(chip8_set_key(emulator, 0x3, true), Chip8Status::Ok);
+            assert_eq!(chip8_step_frame(emulator), Chip8Status::Ok);
+            assert!((*emulator).input().is_key_pressed(ChipKey::Key3));
+            chip8_destroy(emulator);
+        }
+    }
+
+    #[test]
+    fn test_capi_errors() {
+        unsafe {
+            assert_eq!(
+                chip8_step_frame(std::ptr::null_mut()),
+                Chip8Status::NullPointer
+            );
+            assert!(chip8_framebuffer(std::ptr::null()).pixels.is_null());
+            chip8_destroy(std::ptr::null_mut());
+
+            let emulator = chip8_create();
+            assert_eq!(
+                chip8_load_rom(emulator, [0u8; 0].as_ptr(), 0),
+                Chip8Status::RomError
+            );
+            let rom = [0xFF, 0xFF];
+            chip8_load_rom(emulator, rom.as_ptr(), rom.len());
+            assert_eq!(chip8_step_frame(emulator), Chip8Status::Fault);
+            chip8_destroy(emulator);
+        }
+    }
+}
@@ -0,0 +1,472 @@
+//! Memory patch cheats.
+//!
+//! Cheats for a ROM live beside it in a `.cheats.toml` file (`pong.ch8`
+//! reads `pong.cheats.toml`). Each one has a name and a Game Genie-style
+//! code of one or more patches, separated by `+` or spaces:
+//!
+//! - `2F4:03` writes 0x03 at address 0x2F4
+//! - `2F4:6003` writes the bytes 0x60 0x03 from 0x2F4 on
+//! - `2F4:03?05` writes 0x03 only if the byte there is 0x05, so a code
+//!   made for one version of a ROM leaves other versions alone
+//!
+//! A `patch` cheat changes the program once, right after it is loaded,
+//! and puts the original bytes back when turned off. A `freeze` cheat
+//! rewrites its bytes every frame, pinning a variable such as a life
+//! counter.
+//!
+//! ```toml
+//! [[cheat]]
+//! name = "Infinite lives"
+//! code = "3F0:03"
+//! kind = "freeze"
+//! enabled = true
+//! ```
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::emulator::Memory;
+use crate::error::{CheatError, CheatResult, Result};
+use crate::logging::APP;
+
+/// Suffix of the cheat file beside a ROM.
+pub const CHEATS_EXTENSION: &str = "cheats.toml";
+
+/// When a cheat writes its bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CheatKind {
+    /// Once, when the ROM is loaded or the cheat is turned on.
+    #[default]
+    Patch,
+
+    /// Every frame.
+    Freeze,
+}
+
+/// Bytes to write at an address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Patch {
+    /// First address written.
+    pub address: u16,
+
+    /// Bytes written from `address` on.
+    pub value: Vec<u8>,
+
+    /// Bytes that must already be there for the patch to apply.
+    pub compare: Option<Vec<u8>>,
+}
+
+impl Patch {
+    /// Parses one `ADDR:VALUE` or `ADDR:VALUE?COMPARE` patch.
+    pub fn parse(code: &str) -> CheatResult<Self> {
+        let invalid = |reason: &str| CheatError::InvalidCode {
+            code: code.to_string(),
+            reason: reason.to_string(),
+        };
+
+        let (address, rest) = code
+            .split_once(':')
+            .ok_or_else(|| invalid("expected ADDR:VALUE"))?;
+        let address = u16::from_str_radix(address, 16)
+            .map_err(|_| invalid("the address must be 1-4 hex digits"))?;
+        let (value, compare) = match rest.split_once('?') {
+            Some((value, compare)) => (value, Some(compare)),
+            None => (rest, None),
+        };
+        let value = hex_bytes(value).ok_or_else(|| invalid("the value must be hex bytes"))?;
+        let compare = compare
+            .map(|compare| {
+                hex_bytes(compare).ok_or_else(|| invalid("the compare must be hex bytes"))
+            })
+            .transpose()?;
+        if compare
+            .as_ref()
+            .is_some_and(|compare| compare.len() != value.len())
+        {
+            return Err(invalid("the compare must be as long as the value"));
+        }
+        if address as usize + value.len() > 0x10000 {
+            return Err(invalid("the value runs past the end of memory"));
+        }
+
+        Ok(Self {
+            address,
+            value,
+            compare,
+        })
+    }
+
+    /// Gets the addresses the patch writes, with the byte for each.
+    fn bytes(&self) -> impl Iterator<Item = (u16, u8)> + '_ {
+        (self.address..).zip(self.value.iter().copied())
+    }
+
+    /// Checks the compare bytes, if any, match memory.
+    fn matches(&self, memory: &Memory) -> bool {
+        let Some(compare) = &self.compare else {
+            return true;
+        };
+        (self.address..)
+            .zip(compare)
+            .all(|(address, &expected)| memory.read_byte(address).ok() == Some(expected))
+    }
+}
+
+impl fmt::Display for Patch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:03X}:", self.address)?;
+        for byte in &self.value {
+            write!(f, "{:02X}", byte)?;
+        }
+        if let Some(compare) = &self.compare {
+            write!(f, "?")?;
+            for byte in compare {
+                write!(f, "{:02X}", byte)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses an even number of hex digits into bytes.
+fn hex_bytes(text: &str) -> Option<Vec<u8>> {
+    if text.is_empty() || !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Parses a code of patches separated by `+` or whitespace.
+pub fn parse_code(code: &str) -> CheatResult<Vec<Patch>> {
+    let patches = code
+        .split(|c: char| c == '+' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .map(Patch::parse)
+        .collect::<CheatResult<Vec<_>>>()?;
+    if patches.is_empty() {
+        return Err(CheatError::InvalidCode {
+            code: code.to_string(),
+            reason: "no patches".to_string(),
+        });
+    }
+    Ok(patches)
+}
+
+/// A named cheat.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cheat {
+    /// Name shown in the debug panel and used by `--cheat`.
+    pub name: String,
+
+    /// Patches, as written in the cheat file.
+    pub code: String,
+
+    /// When the patches are written.
+    #[serde(default)]
+    pub kind: CheatKind,
+
+    /// Whether the cheat is on.
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Patches parsed from `code`.
+    #[serde(skip)]
+    patches: Vec<Patch>,
+
+    /// Bytes a patch cheat replaced, for turning it off again.
+    #[serde(skip)]
+    replaced: Option<Vec<(u16, u8)>>,
+}
+
+impl Cheat {
+    /// Creates a cheat that is off.
+    pub fn new(name: impl Into<String>, code: &str, kind: CheatKind) -> CheatResult<Self> {
+        Ok(Self {
+            name: name.into(),
+            code: code.to_string(),
+            kind,
+            enabled: false,
+            patches: parse_code(code)?,
+            replaced: None,
+        })
+    }
+
+    /// Turns the cheat on or off.
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Gets the parsed patches.
+    pub fn patches(&self) -> &[Patch] {
+        &self.patches
+    }
+
+    /// Writes a patch cheat's bytes, remembering the ones it replaces.
+    fn apply(&mut self, memory: &mut Memory) {
+        if let Some(patch) = self.patches.iter().find(|patch| !patch.matches(memory)) {
+            tracing::warn!(
+                target: APP,
+                "Cheat {:?} doesn't match this ROM at {:03X}, skipping it",
+                self.name,
+                patch.address
+            );
+            return;
+        }
+        let mut replaced = Vec::new();
+        for (address, value) in self.patches.iter().flat_map(Patch::bytes) {
+            if let Ok(old) = memory.read_byte(address) {
+                if memory.write_byte(address, value).is_ok() {
+                    replaced.push((address, old));
+                }
+            }
+        }
+        self.replaced = Some(replaced);
+    }
+
+    /// Puts back the bytes a patch cheat replaced.
+    fn revert(&mut self, memory: &mut Memory) {
+        for (address, old) in self.replaced.take().into_iter().flatten().rev() {
+            let _ = memory.write_byte(address, old);
+        }
+    }
+}
+
+/// The cheat file format.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CheatFile {
+    /// Cheats in file order.
+    #[serde(default, rename = "cheat")]
+    cheats: Vec<Cheat>,
+}
+
+/// The cheats for a ROM.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CheatList {
+    /// Cheats in file order.
+    cheats: Vec<Cheat>,
+}
+
+impl CheatList {
+    /// Creates an empty list.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a cheat.
+    pub fn with_cheat(mut self, cheat: Cheat) -> Self {
+        self.cheats.push(cheat);
+        self
+    }
+
+    /// Gets the path of the cheat file for a ROM.
+    pub fn path_for(rom: &Path) -> PathBuf {
+        rom.with_extension(CHEATS_EXTENSION)
+    }
+
+    /// Loads the cheats for a ROM.
+    ///
+    /// # Returns
+    /// The cheats, or an empty list if the ROM has no cheat file.
+    pub fn load_for(rom: &Path) -> Result<Self> {
+        let path = Self::path_for(rom);
+        if !path.is_file() {
+            return Ok(Self::new());
+        }
+        Self::load(path)
+    }
+
+    /// Loads a cheat file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parses the contents of a cheat file.
+    pub fn parse(text: &str) -> Result<Self> {
+        let file: CheatFile = toml::from_str(text)?;
+        let cheats = file
+            .cheats
+            .into_iter()
+            .map(|cheat| {
+                Ok(Cheat::new(cheat.name, &cheat.code, cheat.kind)?.with_enabled(cheat.enabled))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { cheats })
+    }
+
+    /// Writes the cheats in the cheat file format.
+    pub fn to_toml(&self) -> Result<String> {
+        let file = CheatFile {
+            cheats: self.cheats.clone(),
+        };
+        Ok(toml::to_string(&file)?)
+    }
+
+    /// Gets the cheats in file order.
+    pub fn cheats(&self) -> &[Cheat] {
+        &self.cheats
+    }
+
+    /// Gets the number of cheats.
+    pub fn len(&self) -> usize {
+        self.cheats.len()
+    }
+
+    /// Checks if there are no cheats.
+    pub fn is_empty(&self) -> bool {
+        self.cheats.is_empty()
+    }
+
+    /// Gets the number of cheats that are on.
+    pub fn active(&self) -> usize {
+        self.cheats.iter().filter(|cheat| cheat.enabled).count()
+    }
+
+    /// Turns a cheat on or off by name, before a ROM is loaded.
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> CheatResult<()> {
+        let cheat = self
+            .cheats
+            .iter_mut()
+            .find(|cheat| cheat.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| CheatError::UnknownCheat {
+                name: name.to_string(),
+            })?;
+        cheat.enabled = enabled;
+        Ok(())
+    }
+
+    /// Turns every cheat off, before a ROM is loaded.
+    pub fn disable_all(&mut self) {
+        for cheat in &mut self.cheats {
+            cheat.enabled = false;
+        }
+    }
+
+    /// Applies the patch cheats that are on to a freshly loaded program.
+    pub fn apply_patches(&mut self, memory: &mut Memory) {
+        for cheat in &mut self.cheats {
+            cheat.replaced = None;
+            if cheat.enabled && cheat.kind == CheatKind::Patch {
+                cheat.apply(memory);
+            }
+        }
+    }
+
+    /// Turns a cheat on or off while the ROM runs.
+    ///
+    /// Patch cheats write or restore their bytes at once; freeze cheats
+    /// start or stop with the next frame.
+    ///
+    /// # Returns
+    /// Whether the cheat is now on, or `None` if there is no such cheat.
+    pub fn toggle(&mut self, index: usize, memory: &mut Memory) -> Option<bool> {
+        let cheat = self.cheats.get_mut(index)?;
+        cheat.enabled = !cheat.enabled;
+        if cheat.kind == CheatKind::Patch {
+            if cheat.enabled {
+                cheat.apply(memory);
+            } else {
+                cheat.revert(memory);
+            }
+        }
+        Some(cheat.enabled)
+    }
+
+    /// Writes the bytes of the freeze cheats that are on, once a frame.
+    pub fn enforce(&self, memory: &mut Memory) {
+        let frozen = self
+            .cheats
+            .iter()
+            .filter(|cheat| cheat.enabled && cheat.kind == CheatKind::Freeze);
+        for patch in frozen.flat_map(|cheat| &cheat.patches) {
+            if !patch.matches(memory) {
+                continue;
+            }
+            for (address, value) in patch.bytes() {
+                let _ = memory.write_byte(address, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_code() {
+        let patches = parse_code("2F4:03 + 300:6003?6005").unwrap();
+        assert_eq!(patches.len(), 2);
+        assert_eq!(
+            patches[1],
+            Patch {
+                address: 0x300,
+                value: vec![0x60, 0x03],
+                compare: Some(vec![0x60, 0x05]),
+            }
+        );
+        assert_eq!(patches[0].to_string(), "2F4:03");
+        assert_eq!(patches[1].to_string(), "300:6003?6005");
+
+        for bad in ["", "2F4", "XYZ:03", "2F4:3", "2F4:03?0506", "FFFF:0102"] {
+            assert!(parse_code(bad).is_err(), "{:?} should be rejected", bad);
+        }
+    }
+
+    #[test]
+    fn test_patch_and_freeze() {
+        let mut cheats = CheatList::parse(
+            r#"
+            [[cheat]]
+            name = "Skip intro"
+            code = "200:1210?00E0"
+            enabled = true
+
+            [[cheat]]
+            name = "Wrong version"
+            code = "202:FF?99"
+            enabled = true
+
+            [[cheat]]
+            name = "Lives"
+            code = "300:09"
+            kind = "freeze"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(cheats.len(), 3);
+        assert_eq!(cheats.active(), 2);
+
+        let mut memory = Memory::new();
+        memory.load_rom(&[0x00, 0xE0, 0x12, 0x00]).unwrap();
+        cheats.apply_patches(&mut memory);
+        assert_eq!(memory.read_word(0x200).unwrap(), 0x1210);
+        // A compare that doesn't match leaves the ROM alone
+        assert_eq!(memory.read_byte(0x202).unwrap(), 0x12);
+
+        // Turning a patch off restores the original bytes
+        assert_eq!(cheats.toggle(0, &mut memory), Some(false));
+        assert_eq!(memory.read_word(0x200).unwrap(), 0x00E0);
+
+        cheats.set_enabled("lives", true).unwrap();
+        memory.write_byte(0x300, 0).unwrap();
+        cheats.enforce(&mut memory);
+        assert_eq!(memory.read_byte(0x300).unwrap(), 0x09);
+        assert!(cheats.set_enabled("missing", true).is_err());
+
+        cheats.disable_all();
+        memory.write_byte(0x300, 0).unwrap();
+        cheats.enforce(&mut memory);
+        assert_eq!(memory.read_byte(0x300).unwrap(), 0);
+
+        let reloaded = CheatList::parse(&cheats.to_toml().unwrap()).unwrap();
+        assert_eq!(reloaded.cheats()[2].kind, CheatKind::Freeze);
+        assert_eq!(reloaded.cheats()[0].patches(), cheats.cheats()[0].patches());
+    }
+}
@@ -0,0 +1,392 @@
+//! Compatibility checks against reference interpreters.
+//!
+//! A reference dump records the screens another interpreter, such as Octo,
+//! showed at given frames of a ROM. [`compare`] runs the ROM here in
+//! lockstep frames with a fixed seed, the way replays do, captures the
+//! screen at the same frames and scores how many pixels agree.
+//!
+//! Dumps are JSON:
+//!
+//! ```json
+//! {
+//!   "rom": "pong.ch8",
+//!   "cycles_per_frame": 20,
+//!   "seed": 0,
+//!   "inputs": [{ "frame": 30, "keys": 2 }, { "frame": 40, "keys": 0 }],
+//!   "frames": [
+//!     { "frame": 60, "rows": ["....####....", "..."] },
+//!     { "frame": 120, "image": "pong-120.png" }
+//!   ]
+//! }
+//! ```
+//!
+//! * `rom` - ROM the dump was taken from, relative to the dump file
+//! * `cycles_per_frame` - Clock cycles per frame, which under simple timing
+//!   is instructions per frame (Octo's tickrate); the configured speed and
+//!   timing model set it if left out
+//! * `seed` - RNG seed, 0 if left out
+//! * `inputs` - Key mask held from each frame on, bit N for key N
+//! * `frames` - Screens after that many frames have run, each either as
+//!   `rows` of `#` (on) and `.` (off, `0` and `1` work too) or as an
+//!   `image` file relative to the dump. Images may be scaled up by a whole
+//!   number; a pixel is on if it is brighter than mid-grey. Reading images
+//!   needs the `png` feature.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use serde::Deserialize;
+
+use crate::emulator::{Cpu, Pcg32};
+use crate::error::{ReferenceError, ReferenceResult, Result};
+use crate::frontend::EmulatorBehaviorConfig;
+use crate::hardware::input::{Input, SoftwareInput};
+use crate::netplay;
+use crate::replay::InputEvent;
+
+/// Screen sizes a reference frame can have.
+const SCREEN_SIZES: [(usize, usize); 2] = [(64, 32), (128, 64)];
+
+/// A reference screen in a dump.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ReferenceFrame {
+    /// Frames run before the screen was captured.
+    pub frame: u32,
+
+    /// Rows of `#` and `.`, if the screen is given inline.
+    #[serde(default)]
+    pub rows: Option<Vec<String>>,
+
+    /// Image file holding the screen, relative to the dump.
+    #[serde(default)]
+    pub image: Option<PathBuf>,
+}
+
+/// Screens another interpreter showed while running a ROM.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ReferenceDump {
+    /// ROM the screens come from, relative to the dump.
+    #[serde(default)]
+    pub rom: Option<PathBuf>,
+
+    /// Clock cycles per frame, or `None` to follow the configured speed and
+    /// timing model.
+    #[serde(default)]
+    pub cycles_per_frame: Option<u32>,
+
+    /// RNG seed.
+    #[serde(default)]
+    pub seed: u64,
+
+    /// Key changes in frame order.
+    #[serde(default)]
+    pub inputs: Vec<InputEvent>,
+
+    /// Reference screens.
+    pub frames: Vec<ReferenceFrame>,
+
+    /// Directory the dump was read from, for resolving its files.
+    #[serde(skip)]
+    pub base: PathBuf,
+}
+
+impl ReferenceDump {
+    /// Parses a dump whose files are relative to `base`.
+    pub fn from_json(text: &str, base: &Path) -> ReferenceResult<Self> {
+        let mut dump: Self =
+            serde_json::from_str(text).map_err(|e| ReferenceError::InvalidDump(e.to_string()))?;
+        if dump.frames.is_empty() {
+            return Err(ReferenceError::InvalidDump("no frames listed".to_string()));
+        }
+        dump.frames.sort_by_key(|frame| frame.frame);
+        dump.inputs.sort_by_key(|event| event.frame);
+        dump.base = base.to_path_buf();
+        Ok(dump)
+    }
+
+    /// Reads a dump file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        let base = path.parent().unwrap_or(Path::new(""));
+        Ok(Self::from_json(&text, base)?)
+    }
+
+    /// Gets the path of the ROM named in the dump.
+    pub fn rom_path(&self) -> Option<PathBuf> {
+        self.rom.as_ref().map(|rom| self.base.join(rom))
+    }
+
+    /// Gets the keys held on a frame as a mask where bit N is key N.
+    fn keys_at(&self, frame: u32) -> u16 {
+        let next = self.inputs.partition_point(|event| event.frame <= frame);
+        next.checked_sub(1).map_or(0, |i| self.inputs[i].keys)
+    }
+}
+
+impl ReferenceFrame {
+    /// Loads the screen, as its width, height and pixels in row-major order.
+    pub fn screen(&self, base: &Path) -> ReferenceResult<(usize, usize, Vec<bool>)> {
+        let invalid = |reason: String| ReferenceError::InvalidFrame {
+            frame: self.frame,
+            reason,
+        };
+        let (width, height, pixels) = match (&self.rows, &self.image) {
+            (Some(rows), None) => parse_rows(rows).map_err(invalid)?,
+            (None, Some(image)) => read_image(&base.join(image)).map_err(invalid)?,
+            _ => return Err(invalid("give either rows or an image".to_string())),
+        };
+        if !SCREEN_SIZES.contains(&(width, height)) {
+            return Err(invalid(format!(
+                "{}x{} is not a 64x32 or 128x64 screen",
+                width, height
+            )));
+        }
+        Ok((width, height, pixels))
+    }
+}
+
+/// Parses rows of `#`/`1` (on) and `.`/`0` (off).
+fn parse_rows(rows: &[String]) -> std::result::Result<(usize, usize, Vec<bool>), String> {
+    let width = rows.first().map_or(0, |row| row.chars().count());
+    let mut pixels = Vec::with_capacity(width * rows.len());
+    for (y, row) in rows.iter().enumerate() {
+        if row.chars().count() != width {
+            return Err(format!("row {} is not {} pixels wide", y, width));
+        }
+        for pixel in row.chars() {
+            match pixel {
+                '#' | '1' => pixels.push(true),
+                '.' | '0' => pixels.push(false),
+                _ => return Err(format!("row {} has '{}'", y, pixel)),
+            }
+        }
+    }
+    Ok((width, rows.len(), pixels))
+}
+
+/// Reads a screen image, scaling it down to the screen it shows.
+#[cfg(feature = "png")]
+fn read_image(path: &Path) -> std::result::Result<(usize, usize, Vec<bool>), String> {
+    let image = image::open(path)
+        .map_err(|e| format!("{}: {}", path.display(), e))?
+        .to_luma8();
+    let (image_width, image_height) = (image.width() as usize, image.height() as usize);
+    let &(width, height) = SCREEN_SIZES
+        .iter()
+        .filter(|(width, height)| image_width % width == 0 && image_height % height == 0)
+        .find(|(width, height)| image_width / width == image_height / height)
+        .ok_or_else(|| {
+            format!(
+                "{}x{} is not a whole-number scale of a Chip-8 screen",
+                image_width, image_height
+            )
+        })?;
+    let scale = image_width / width;
+    let pixels = (0..width * height)
+        .map(|i| {
+            // Sample the middle of each scaled pixel, away from any grid lines
+            let x = (i % width * scale + scale / 2) as u32;
+            let y = (i / width * scale + scale / 2) as u32;
+            image.get_pixel(x, y).0[0] >= 128
+        })
+        .collect();
+    Ok((width, height, pixels))
+}
+
+/// Reads a screen image; images need the `png` feature.
+#[cfg(not(feature = "png"))]
+fn read_image(path: &Path) -> std::result::Result<(usize, usize, Vec<bool>), String> {
+    Err(format!(
+        "{}: reading images needs the 'png' feature",
+        path.display()
+    ))
+}
+
+/// How one captured screen compares with its reference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameComparison {
+    /// Frames run before the capture.
+    pub frame: u32,
+
+    /// Pixels that agree with the reference.
+    pub matching: usize,
+
+    /// Pixels in the reference screen.
+    pub total: usize,
+}
+
+impl FrameComparison {
+    /// Checks if every pixel agrees.
+    pub fn is_exact(&self) -> bool {
+        self.matching == self.total
+    }
+}
+
+/// How a ROM's screens compare with a reference dump.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompatReport {
+    /// Name of the ROM.
+    pub rom: String,
+
+    /// Comparisons in frame order.
+    pub frames: Vec<FrameComparison>,
+}
+
+impl CompatReport {
+    /// Gets the share of pixels that agree, averaged over the frames, as a
+    /// percentage.
+    pub fn score(&self) -> f64 {
+        if self.frames.is_empty() {
+            return 0.0;
+        }
+        let sum: f64 = self
+            .frames
+            .iter()
+            .map(|frame| frame.matching as f64 / frame.total.max(1) as f64)
+            .sum();
+        sum / self.frames.len() as f64 * 100.0
+    }
+
+    /// Counts the frames where every pixel agrees.
+    pub fn exact_frames(&self) -> usize {
+        self.frames.iter().filter(|frame| frame.is_exact()).count()
+    }
+}
+
+impl fmt::Display for CompatReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&format!(
+            "{}: {:.1}% ({}/{} frames exact)",
+            self.rom,
+            self.score(),
+            self.exact_frames(),
+            self.frames.len()
+        ))
+    }
+}
+
+/// Runs a ROM and compares its screens with a reference dump.
+///
+/// # Arguments
+/// * `rom` - ROM to run
+/// * `name` - Name of the ROM in the report
+/// * `dump` - Reference screens, with the seed, keys and speed to run at
+/// * `behavior` - Emulator settings, including quirks
+pub fn compare(
+    rom: &[u8],
+    name: &str,
+    dump: &ReferenceDump,
+    behavior: &EmulatorBehaviorConfig,
+) -> Result<CompatReport> {
+    let input = Arc::new(Mutex::new(SoftwareInput::new()));
+    let mut cpu = Cpu::new_with_config(behavior);
+    cpu.set_random_source(Box::new(Pcg32::new(dump.seed)));
+    cpu.set_realtime_timers(false);
+    cpu.set_input(input.clone());
+    cpu.load_rom(rom)?;
+
+    let cycles_per_frame = dump.cycles_per_frame.unwrap_or(cpu.cycles_per_frame());
+    let mut frame = 0;
+    let mut frames = Vec::with_capacity(dump.frames.len());
+    for reference in &dump.frames {
+        let (width, height, expected) = reference.screen(&dump.base)?;
+        while frame < reference.frame {
+            netplay::apply_mask(&mut input.lock().unwrap(), dump.keys_at(frame));
+            netplay::run_frame_for(&mut cpu, cycles_per_frame)?;
+            input.lock().unwrap().update()?;
+            frame += 1;
+        }
+        let matching = if cpu.display_size() == (width, height) {
+            cpu.get_display_buffer()
+                .iter()
+                .zip(&expected)
+                .filter(|(actual, expected)| actual == expected)
+                .count()
+        } else {
+            0
+        };
+        frames.push(FrameComparison {
+            frame: reference.frame,
+            matching,
+            total: expected.len(),
+        });
+    }
+    Ok(CompatReport {
+        rom: name.to_string(),
+        frames,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Draws the digit in V0 at (V1, V1) once key 1 is held
+    const ROM: [u8; 14] = [
+        0x60, 0x07, // LD V0, 7
+        0x62, 0x01, // 0x202: LD V2, 1
+        0xE2, 0x9E, // SKP V2
+        0x12, 0x02, // JP 0x202
+        0xF0, 0x29, // LD F, V0
+        0xD1, 0x15, // DRW V1, V1, 5
+        0x12, 0x0C, // 0x20C: JP 0x20C
+    ];
+
+    fn seven() -> Vec<String> {
+        let glyph = ["####", "...#", "..#.", ".#..", ".#.."];
+        (0..32)
+            .map(|y| {
+                let start = glyph.get(y).copied().unwrap_or("....");
+                format!("{}{}", start, ".".repeat(60))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_compare_scores_frames() {
+        let blank = vec![".".repeat(64); 32];
+        let json = serde_json::json!({
+            "cycles_per_frame": 10,
+            "inputs": [{ "frame": 5, "keys": 2 }],
+            "frames": [
+                { "frame": 20, "rows": seven() },
+                { "frame": 3, "rows": blank },
+            ],
+        });
+        let dump = ReferenceDump::from_json(&json.to_string(), Path::new("")).unwrap();
+        assert_eq!(dump.frames[0].frame, 3);
+
+        let report = compare(&ROM, "seven", &dump, &EmulatorBehaviorConfig::default()).unwrap();
+        assert_eq!(report.exact_frames(), 2);
+        assert_eq!(report.score(), 100.0);
+        assert_eq!(report.to_string(), "seven: 100.0% (2/2 frames exact)");
+
+        // Without the key press the seven is never drawn
+        let mut dump = dump;
+        dump.inputs.clear();
+        let report = compare(&ROM, "seven", &dump, &EmulatorBehaviorConfig::default()).unwrap();
+        assert_eq!(report.exact_frames(), 1);
+        assert_eq!(report.frames[1].total - report.frames[1].matching, 8);
+        assert!(report.score() < 100.0 && report.score() > 99.0);
+    }
+
+    #[test]
+    fn test_invalid_reference_frames() {
+        let base = Path::new("");
+        let frame = |rows: Vec<&str>| ReferenceFrame {
+            frame: 1,
+            rows: Some(rows.into_iter().map(String::from).collect()),
+            image: None,
+        };
+        assert!(frame(vec!["#."; 32]).screen(base).is_err());
+        assert!(frame(vec!["#x"; 2]).screen(base).is_err());
+        let row = "10".repeat(32);
+        let (width, height, pixels) = frame(vec![row.as_str(); 32]).screen(base).unwrap();
+        assert_eq!((width, height), (64, 32));
+        assert!(pixels[0] && !pixels[1]);
+
+        assert!(ReferenceDump::from_json(r#"{"frames": []}"#, base).is_err());
+        assert!(ReferenceDump::from_json("{", base).is_err());
+    }
+}
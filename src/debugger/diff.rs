@@ -0,0 +1,449 @@
+//! Lockstep comparison of two runs of a ROM.
+//!
+//! A ROM that works under one set of quirks and breaks under another
+//! usually goes wrong at one particular instruction. [`RunDiff`] steps two
+//! CPUs side by side, one instruction each, and stops after the first
+//! instruction that leaves their registers, timers, stack, memory or
+//! display different. Timers tick on the instruction count rather than the
+//! wall clock, so the same settings always give the same result.
+
+use std::fmt;
+
+use crate::emulator::{Cpu, CpuEvent, CpuState, InstructionInfo};
+
+/// Number of differing memory bytes listed in a report.
+const MEMORY_LISTED: usize = 16;
+
+/// How a run's last step ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StepEvent {
+    /// An instruction was executed.
+    Executed,
+
+    /// The CPU is blocked on FX0A.
+    WaitingForKey,
+
+    /// The program ran its exit instruction.
+    Halted,
+
+    /// The instruction failed.
+    Fault(String),
+}
+
+impl StepEvent {
+    /// Checks if the run can't go on.
+    fn is_stop(&self) -> bool {
+        !matches!(self, StepEvent::Executed)
+    }
+}
+
+impl fmt::Display for StepEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StepEvent::Executed => write!(f, "executed"),
+            StepEvent::WaitingForKey => write!(f, "waiting for a key"),
+            StepEvent::Halted => write!(f, "halted"),
+            StepEvent::Fault(message) => write!(f, "fault: {}", message),
+        }
+    }
+}
+
+/// A memory byte that differs between the runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryDifference {
+    /// Address of the byte.
+    pub address: usize,
+
+    /// Value in each run.
+    pub values: [u8; 2],
+}
+
+/// How the displays differ.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisplayDifference {
+    /// Width and height of each run's display.
+    pub sizes: [(usize, usize); 2],
+
+    /// Number of pixels that differ, if the sizes match.
+    pub pixels: usize,
+
+    /// Coordinates of the first differing pixel, if the sizes match.
+    pub first: Option<(usize, usize)>,
+}
+
+/// The first point at which two runs differ.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    /// Number of steps taken, including the one that diverged.
+    pub step: u64,
+
+    /// Instruction each run executed last.
+    pub last: [Option<InstructionInfo>; 2],
+
+    /// How each run's last step ended.
+    pub events: [StepEvent; 2],
+
+    /// Register state of each run.
+    pub states: [CpuState; 2],
+
+    /// Memory bytes that differ, lowest address first.
+    pub memory: Vec<MemoryDifference>,
+
+    /// Memory size of each run.
+    pub memory_sizes: [usize; 2],
+
+    /// How the displays differ, if they do.
+    pub display: Option<DisplayDifference>,
+}
+
+impl Divergence {
+    /// Lists the registers that differ as `(name, a, b)` rows.
+    pub fn registers(&self) -> Vec<(String, String, String)> {
+        let [a, b] = &self.states;
+        let mut rows = Vec::new();
+        let mut row = |name: String, a: String, b: String| {
+            if a != b {
+                rows.push((name, a, b));
+            }
+        };
+        row(
+            "PC".into(),
+            format!("{:03X}", a.pc),
+            format!("{:03X}", b.pc),
+        );
+        row("I".into(), format!("{:03X}", a.i), format!("{:03X}", b.i));
+        for (index, (va, vb)) in a.v.iter().zip(&b.v).enumerate() {
+            row(
+                format!("V{:X}", index),
+                format!("{:02X}", va),
+                format!("{:02X}", vb),
+            );
+        }
+        row("SP".into(), a.sp.to_string(), b.sp.to_string());
+        row(
+            "DT".into(),
+            a.delay_timer.to_string(),
+            b.delay_timer.to_string(),
+        );
+        row(
+            "ST".into(),
+            a.sound_timer.to_string(),
+            b.sound_timer.to_string(),
+        );
+        let stack = |state: &CpuState| {
+            let frames: Vec<String> = state
+                .stack_contents
+                .iter()
+                .map(|address| format!("{:03X}", address))
+                .collect();
+            format!("[{}]", frames.join(" "))
+        };
+        row("Stack".into(), stack(a), stack(b));
+        rows
+    }
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Runs diverge at step {}", self.step)?;
+        for (side, (last, event)) in ["A", "B"].iter().zip(self.last.iter().zip(&self.events)) {
+            match last {
+                Some(info) => write!(
+                    f,
+                    "  {}: {:03X}  {:04X}  {:<16}",
+                    side,
+                    info.pc,
+                    info.opcode,
+                    info.disassembly()
+                )?,
+                None => write!(f, "  {}: {:<28}", side, "(nothing executed)")?,
+            }
+            writeln!(f, " {}", event)?;
+        }
+
+        let registers = self.registers();
+        if !registers.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "  {:<6} {:>8} {:>8}", "", "A", "B")?;
+            for (name, a, b) in registers {
+                writeln!(f, "  {:<6} {:>8} {:>8}", name, a, b)?;
+            }
+        }
+
+        if self.memory_sizes[0] != self.memory_sizes[1] {
+            writeln!(f)?;
+            writeln!(
+                f,
+                "  Memory size: {} bytes vs {} bytes",
+                self.memory_sizes[0], self.memory_sizes[1]
+            )?;
+        }
+        if !self.memory.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "  Memory: {} bytes differ", self.memory.len())?;
+            for difference in self.memory.iter().take(MEMORY_LISTED) {
+                writeln!(
+                    f,
+                    "    {:04X}: {:02X} vs {:02X}",
+                    difference.address, difference.values[0], difference.values[1]
+                )?;
+            }
+            if self.memory.len() > MEMORY_LISTED {
+                writeln!(f, "    ...")?;
+            }
+        }
+
+        if let Some(display) = &self.display {
+            writeln!(f)?;
+            let [(wa, ha), (wb, hb)] = display.sizes;
+            match display.first {
+                Some((x, y)) => writeln!(
+                    f,
+                    "  Display: {} pixels differ, first at ({}, {})",
+                    display.pixels, x, y
+                )?,
+                None => writeln!(f, "  Display: {}x{} vs {}x{}", wa, ha, wb, hb)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Result of comparing two runs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffOutcome {
+    /// The runs stayed identical.
+    Matched {
+        /// Number of steps compared.
+        steps: u64,
+
+        /// Why both runs stopped early, if they did.
+        stopped: Option<StepEvent>,
+    },
+
+    /// The runs differ.
+    Diverged(Box<Divergence>),
+}
+
+impl fmt::Display for DiffOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiffOutcome::Matched { steps, stopped } => {
+                write!(f, "No divergence in {} steps", steps)?;
+                match stopped {
+                    Some(event) => writeln!(f, " (both runs {})", event),
+                    None => writeln!(f),
+                }
+            }
+            DiffOutcome::Diverged(divergence) => divergence.fmt(f),
+        }
+    }
+}
+
+/// Two runs of a ROM stepped in lockstep.
+pub struct RunDiff {
+    /// The two CPUs, with the ROM loaded.
+    cpus: [Cpu; 2],
+
+    /// Instructions per timer tick for each run.
+    cycles_per_tick: [u64; 2],
+
+    /// Number of steps taken.
+    steps: u64,
+}
+
+impl RunDiff {
+    /// Creates a comparison of two CPUs, which should already have the ROM
+    /// loaded and seeded random sources.
+    ///
+    /// Timers tick once per instruction until [`with_timer_rates`] says
+    /// otherwise.
+    ///
+    /// [`with_timer_rates`]: RunDiff::with_timer_rates
+    pub fn new(mut a: Cpu, mut b: Cpu) -> Self {
+        a.set_realtime_timers(false);
+        b.set_realtime_timers(false);
+        Self {
+            cpus: [a, b],
+            cycles_per_tick: [1, 1],
+            steps: 0,
+        }
+    }
+
+    /// Sets how many instructions each run executes per 60 Hz timer tick.
+    pub fn with_timer_rates(mut self, a: u64, b: u64) -> Self {
+        self.cycles_per_tick = [a.max(1), b.max(1)];
+        self
+    }
+
+    /// Gets one of the CPUs, 0 for run A and 1 for run B.
+    pub fn cpu(&self, side: usize) -> &Cpu {
+        &self.cpus[side]
+    }
+
+    /// Steps both runs until they differ, both stop, or `max_steps` steps
+    /// have been compared.
+    pub fn run(&mut self, max_steps: u64) -> DiffOutcome {
+        while self.steps < max_steps {
+            self.steps += 1;
+            let events = [self.step(0), self.step(1)];
+            let last = [
+                self.cpus[0].last_instruction_info(),
+                self.cpus[1].last_instruction_info(),
+            ];
+            if let Some(divergence) = self.compare(events.clone(), last) {
+                return DiffOutcome::Diverged(Box::new(divergence));
+            }
+            if events[0].is_stop() {
+                let [event, _] = events;
+                return DiffOutcome::Matched {
+                    steps: self.steps,
+                    stopped: Some(event),
+                };
+            }
+        }
+        DiffOutcome::Matched {
+            steps: self.steps,
+            stopped: None,
+        }
+    }
+
+    /// Runs one instruction on one side and ticks its timers when due.
+    fn step(&mut self, side: usize) -> StepEvent {
+        let cpu = &mut self.cpus[side];
+        match cpu.cycle() {
+            Ok(CpuEvent::WaitingForKey) => return StepEvent::WaitingForKey,
+            Ok(_) => {}
+            Err(e) => return StepEvent::Fault(e.to_string()),
+        }
+        if self.steps.is_multiple_of(self.cycles_per_tick[side]) {
+            cpu.timers_mut().update_by_ticks(1);
+        }
+        if cpu.is_halted() {
+            StepEvent::Halted
+        } else {
+            StepEvent::Executed
+        }
+    }
+
+    /// Compares the two runs after a step.
+    fn compare(
+        &self,
+        events: [StepEvent; 2],
+        last: [Option<InstructionInfo>; 2],
+    ) -> Option<Divergence> {
+        let [a, b] = &self.cpus;
+        let states = [a.get_state(), b.get_state()];
+        let registers_differ = {
+            let [sa, sb] = &states;
+            sa.pc != sb.pc
+                || sa.i != sb.i
+                || sa.sp != sb.sp
+                || sa.v != sb.v
+                || sa.delay_timer != sb.delay_timer
+                || sa.sound_timer != sb.sound_timer
+                || sa.stack_contents != sb.stack_contents
+        };
+
+        let (memory_a, memory_b) = (a.get_memory().bytes(), b.get_memory().bytes());
+        let memory_sizes = [memory_a.len(), memory_b.len()];
+        let memory: Vec<MemoryDifference> = if memory_a == memory_b {
+            Vec::new()
+        } else {
+            memory_a
+                .iter()
+                .zip(memory_b)
+                .enumerate()
+                .filter(|(_, (x, y))| x != y)
+                .map(|(address, (&x, &y))| MemoryDifference {
+                    address,
+                    values: [x, y],
+                })
+                .collect()
+        };
+
+        let sizes = [a.display_size(), b.display_size()];
+        let (display_a, display_b) = (a.get_display_buffer(), b.get_display_buffer());
+        let display = if sizes[0] != sizes[1] {
+            Some(DisplayDifference {
+                sizes,
+                pixels: 0,
+                first: None,
+            })
+        } else if display_a != display_b {
+            let width = sizes[0].0.max(1);
+            let mut differing = display_a
+                .iter()
+                .zip(display_b)
+                .enumerate()
+                .filter(|(_, (x, y))| x != y)
+                .map(|(index, _)| index);
+            let first = differing.next().map(|index| (index % width, index / width));
+            Some(DisplayDifference {
+                sizes,
+                pixels: 1 + differing.count(),
+                first,
+            })
+        } else {
+            None
+        };
+
+        let diverged = registers_differ
+            || memory_sizes[0] != memory_sizes[1]
+            || !memory.is_empty()
+            || display.is_some()
+            || events[0] != events[1];
+        diverged.then_some(Divergence {
+            step: self.steps,
+            last,
+            events,
+            states,
+            memory,
+            memory_sizes,
+            display,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::random::Pcg32;
+
+    fn cpu(rom: &[u8], seed: u64) -> Cpu {
+        let mut cpu = Cpu::new();
+        cpu.load_rom(rom).unwrap();
+        cpu.set_random_source(Box::new(Pcg32::new(seed)));
+        cpu
+    }
+
+    #[test]
+    fn test_runs_diverge_on_random() {
+        // LD V1, 7; RND V0, FF; ...
+        let rom = [0x61, 0x07, 0xC0, 0xFF, 0x12, 0x04];
+        let mut diff = RunDiff::new(cpu(&rom, 1), cpu(&rom, 2));
+        let DiffOutcome::Diverged(divergence) = diff.run(100) else {
+            panic!("runs should diverge");
+        };
+        assert_eq!(divergence.step, 2);
+        assert_eq!(divergence.last[0].unwrap().pc, 0x202);
+        assert_eq!(divergence.registers()[0].0, "V0");
+        assert!(divergence.memory.is_empty());
+        assert!(divergence.display.is_none());
+        assert!(divergence.to_string().contains("RND"));
+    }
+
+    #[test]
+    fn test_identical_runs_match() {
+        // CLS; LD V0, K
+        let rom = [0x00, 0xE0, 0xF0, 0x0A];
+        let mut diff = RunDiff::new(cpu(&rom, 1), cpu(&rom, 1));
+        assert_eq!(
+            diff.run(100),
+            DiffOutcome::Matched {
+                steps: 2,
+                stopped: Some(StepEvent::WaitingForKey),
+            }
+        );
+    }
+}
@@ -0,0 +1,604 @@
+//! Expressions over the CPU state, for conditional breakpoints and watches.
+//!
+//! The syntax is a small subset of C. Operands are numbers (`0x1F`, `31`)
+//! and registers (`V0`-`VF`, `I`, `PC`, `SP`, `DT`, `ST`, case-insensitive).
+//! Operators, from loosest to tightest binding:
+//!
+//! | Operators                 |
+//! | ------------------------- |
+//! | `\|\|`                    |
+//! | `&&`                      |
+//! | `\|`                      |
+//! | `^`                       |
+//! | `&`                       |
+//! | `==` `!=`                 |
+//! | `<` `<=` `>` `>=`         |
+//! | `<<` `>>`                 |
+//! | `+` `-`                   |
+//! | `*` `/` `%`               |
+//! | unary `!` `-` `~`         |
+//!
+//! Binary operators group left to right and parentheses override the
+//! order. Comparisons and logical operators give 1 or 0, and any non-zero
+//! value counts as true, so `V3 == 0x1F && I > 0x300` works as a condition.
+//!
+//! Expressions parsed with [`Expr::parse_with`] can also name variables
+//! from a [`VariableTable`], such as `lives == 0`. Register names win over
+//! variables of the same name, and evaluating a variable needs memory.
+
+use std::fmt;
+use std::str::FromStr;
+
+use super::variables::{Variable, VariableTable};
+use crate::emulator::{CpuState, Memory};
+use crate::error::{ExpressionError, ExpressionResult};
+
+/// A register an expression can read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Register {
+    /// General purpose register V0-VF.
+    V(u8),
+
+    /// Index register.
+    I,
+
+    /// Program counter.
+    Pc,
+
+    /// Stack pointer.
+    Sp,
+
+    /// Delay timer.
+    Dt,
+
+    /// Sound timer.
+    St,
+}
+
+impl Register {
+    /// Parses a register name, ignoring case.
+    fn parse(name: &str) -> Option<Self> {
+        let upper = name.to_ascii_uppercase();
+        let register = match upper.as_str() {
+            "I" => Register::I,
+            "PC" => Register::Pc,
+            "SP" => Register::Sp,
+            "DT" => Register::Dt,
+            "ST" => Register::St,
+            _ => {
+                let digit = upper.strip_prefix('V')?;
+                if digit.len() != 1 {
+                    return None;
+                }
+                Register::V(u8::from_str_radix(digit, 16).ok()?)
+            }
+        };
+        Some(register)
+    }
+
+    /// Reads the register from a CPU state.
+    fn read(&self, state: &CpuState) -> i64 {
+        match *self {
+            Register::V(x) => state.v[x as usize] as i64,
+            Register::I => state.i as i64,
+            Register::Pc => state.pc as i64,
+            Register::Sp => state.sp as i64,
+            Register::Dt => state.delay_timer as i64,
+            Register::St => state.sound_timer as i64,
+        }
+    }
+}
+
+/// A prefix operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UnaryOp {
+    Not,
+    Negate,
+    Complement,
+}
+
+/// An infix operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinaryOp {
+    Or,
+    And,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    ShiftLeft,
+    ShiftRight,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+}
+
+impl BinaryOp {
+    /// Finds the operator spelled `symbol`.
+    fn from_symbol(symbol: &str) -> Option<Self> {
+        let op = match symbol {
+            "||" => BinaryOp::Or,
+            "&&" => BinaryOp::And,
+            "|" => BinaryOp::BitOr,
+            "^" => BinaryOp::BitXor,
+            "&" => BinaryOp::BitAnd,
+            "==" => BinaryOp::Equal,
+            "!=" => BinaryOp::NotEqual,
+            "<" => BinaryOp::Less,
+            "<=" => BinaryOp::LessEqual,
+            ">" => BinaryOp::Greater,
+            ">=" => BinaryOp::GreaterEqual,
+            "<<" => BinaryOp::ShiftLeft,
+            ">>" => BinaryOp::ShiftRight,
+            "+" => BinaryOp::Add,
+            "-" => BinaryOp::Subtract,
+            "*" => BinaryOp::Multiply,
+            "/" => BinaryOp::Divide,
+            "%" => BinaryOp::Remainder,
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    /// Gets how tightly the operator binds; higher binds tighter.
+    fn precedence(&self) -> u8 {
+        match self {
+            BinaryOp::Or => 1,
+            BinaryOp::And => 2,
+            BinaryOp::BitOr => 3,
+            BinaryOp::BitXor => 4,
+            BinaryOp::BitAnd => 5,
+            BinaryOp::Equal | BinaryOp::NotEqual => 6,
+            BinaryOp::Less | BinaryOp::LessEqual | BinaryOp::Greater | BinaryOp::GreaterEqual => 7,
+            BinaryOp::ShiftLeft | BinaryOp::ShiftRight => 8,
+            BinaryOp::Add | BinaryOp::Subtract => 9,
+            BinaryOp::Multiply | BinaryOp::Divide | BinaryOp::Remainder => 10,
+        }
+    }
+
+    /// Applies the operator to two evaluated operands.
+    fn apply(&self, left: i64, right: i64) -> ExpressionResult<i64> {
+        let value = match self {
+            // Short-circuiting operators are handled by the caller
+            BinaryOp::Or => (left != 0 || right != 0) as i64,
+            BinaryOp::And => (left != 0 && right != 0) as i64,
+            BinaryOp::BitOr => left | right,
+            BinaryOp::BitXor => left ^ right,
+            BinaryOp::BitAnd => left & right,
+            BinaryOp::Equal => (left == right) as i64,
+            BinaryOp::NotEqual => (left != right) as i64,
+            BinaryOp::Less => (left < right) as i64,
+            BinaryOp::LessEqual => (left <= right) as i64,
+            BinaryOp::Greater => (left > right) as i64,
+            BinaryOp::GreaterEqual => (left >= right) as i64,
+            BinaryOp::ShiftLeft => left.wrapping_shl(right as u32),
+            BinaryOp::ShiftRight => left.wrapping_shr(right as u32),
+            BinaryOp::Add => left.wrapping_add(right),
+            BinaryOp::Subtract => left.wrapping_sub(right),
+            BinaryOp::Multiply => left.wrapping_mul(right),
+            BinaryOp::Divide | BinaryOp::Remainder if right == 0 => {
+                return Err(ExpressionError::DivisionByZero)
+            }
+            BinaryOp::Divide => left.wrapping_div(right),
+            BinaryOp::Remainder => left.wrapping_rem(right),
+        };
+        Ok(value)
+    }
+}
+
+/// A node of a parsed expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Number(i64),
+    Register(Register),
+    Variable(Variable),
+    Unary(UnaryOp, Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+}
+
+impl Node {
+    /// Evaluates the node against a CPU state and, for variables, memory.
+    fn eval(&self, state: &CpuState, memory: Option<&Memory>) -> ExpressionResult<i64> {
+        match self {
+            Node::Number(value) => Ok(*value),
+            Node::Register(register) => Ok(register.read(state)),
+            Node::Variable(variable) => {
+                memory
+                    .and_then(|memory| variable.read(memory))
+                    .ok_or_else(|| ExpressionError::UnreadableVariable {
+                        name: variable.name.clone(),
+                    })
+            }
+            Node::Unary(op, operand) => {
+                let value = operand.eval(state, memory)?;
+                Ok(match op {
+                    UnaryOp::Not => (value == 0) as i64,
+                    UnaryOp::Negate => value.wrapping_neg(),
+                    UnaryOp::Complement => !value,
+                })
+            }
+            Node::Binary(op, left, right) => {
+                let left = left.eval(state, memory)?;
+                match op {
+                    BinaryOp::And if left == 0 => Ok(0),
+                    BinaryOp::Or if left != 0 => Ok(1),
+                    _ => op.apply(left, right.eval(state, memory)?),
+                }
+            }
+        }
+    }
+}
+
+/// A parsed expression, keeping the text it was parsed from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expr {
+    /// The expression as written.
+    source: String,
+
+    /// Parsed form.
+    root: Node,
+}
+
+impl Expr {
+    /// Parses an expression.
+    pub fn parse(source: &str) -> ExpressionResult<Self> {
+        Self::parse_with(source, &VariableTable::new())
+    }
+
+    /// Parses an expression that may name variables from a table.
+    pub fn parse_with(source: &str, variables: &VariableTable) -> ExpressionResult<Self> {
+        let tokens = tokenize(source, variables)?;
+        let mut parser = Parser { tokens, next: 0 };
+        let root = parser.expression(1)?;
+        if let Some(token) = parser.tokens.get(parser.next) {
+            return Err(token.unexpected());
+        }
+
+        Ok(Self {
+            source: source.trim().to_string(),
+            root,
+        })
+    }
+
+    /// Gets the expression as written.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Evaluates the expression against a CPU state.
+    ///
+    /// Variables can't be read this way; use [`Expr::eval_in`].
+    pub fn eval(&self, state: &CpuState) -> ExpressionResult<i64> {
+        self.root.eval(state, None)
+    }
+
+    /// Evaluates the expression against a CPU state and its memory.
+    pub fn eval_in(&self, state: &CpuState, memory: &Memory) -> ExpressionResult<i64> {
+        self.root.eval(state, Some(memory))
+    }
+
+    /// Evaluates the expression as a condition: true unless it is zero.
+    pub fn is_true(&self, state: &CpuState) -> ExpressionResult<bool> {
+        Ok(self.eval(state)? != 0)
+    }
+
+    /// Evaluates the expression as a condition against a CPU state and its
+    /// memory.
+    pub fn is_true_in(&self, state: &CpuState, memory: &Memory) -> ExpressionResult<bool> {
+        Ok(self.eval_in(state, memory)? != 0)
+    }
+}
+
+impl FromStr for Expr {
+    type Err = ExpressionError;
+
+    fn from_str(source: &str) -> ExpressionResult<Self> {
+        Self::parse(source)
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// What a token is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TokenKind {
+    Number(i64),
+    Register(Register),
+    Variable(Variable),
+    Operator(&'static str),
+    Open,
+    Close,
+}
+
+/// A token and where it starts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Token {
+    kind: TokenKind,
+    text: String,
+    column: usize,
+}
+
+impl Token {
+    /// Builds the error for a token that doesn't belong where it is.
+    fn unexpected(&self) -> ExpressionError {
+        ExpressionError::UnexpectedToken {
+            found: self.text.clone(),
+            column: self.column,
+        }
+    }
+}
+
+/// Operator spellings, longest first so `<=` isn't read as `<`.
+const OPERATORS: [&str; 21] = [
+    "||", "&&", "==", "!=", "<=", ">=", "<<", ">>", "|", "^", "&", "<", ">", "+", "-", "*", "/",
+    "%", "!", "~", "=",
+];
+
+/// Splits an expression into tokens, looking up names that aren't registers
+/// in `variables`.
+fn tokenize(source: &str, variables: &VariableTable) -> ExpressionResult<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = source;
+
+    while let Some(c) = rest.chars().next() {
+        let column = source.len() - rest.len() + 1;
+        if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+
+        let (kind, length) = if c.is_ascii_alphanumeric() || c == '_' {
+            let length = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            let word = &rest[..length];
+            let kind = if c.is_ascii_digit() {
+                TokenKind::Number(parse_number(word)?)
+            } else if let Some(register) = Register::parse(word) {
+                TokenKind::Register(register)
+            } else if let Some(variable) = variables.get(word) {
+                TokenKind::Variable(variable.clone())
+            } else {
+                return Err(ExpressionError::UnexpectedToken {
+                    found: word.to_string(),
+                    column,
+                });
+            };
+            (kind, length)
+        } else if c == '(' {
+            (TokenKind::Open, 1)
+        } else if c == ')' {
+            (TokenKind::Close, 1)
+        } else if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(*op)) {
+            (TokenKind::Operator(op), op.len())
+        } else {
+            return Err(ExpressionError::UnexpectedToken {
+                found: c.to_string(),
+                column,
+            });
+        };
+
+        tokens.push(Token {
+            kind,
+            text: rest[..length].to_string(),
+            column,
+        });
+        rest = &rest[length..];
+    }
+    Ok(tokens)
+}
+
+/// Parses a decimal or `0x` hex number.
+fn parse_number(text: &str) -> ExpressionResult<i64> {
+    let parsed = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => i64::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    parsed.map_err(|_| ExpressionError::InvalidNumber {
+        text: text.to_string(),
+    })
+}
+
+/// Precedence-climbing parser over a token list.
+struct Parser {
+    tokens: Vec<Token>,
+    next: usize,
+}
+
+impl Parser {
+    /// Parses operators binding at least as tightly as `min_precedence`.
+    fn expression(&mut self, min_precedence: u8) -> ExpressionResult<Node> {
+        let mut left = self.unary()?;
+
+        while let Some(op) = self.peek_binary() {
+            if op.precedence() < min_precedence {
+                break;
+            }
+            self.next += 1;
+            let right = self.expression(op.precedence() + 1)?;
+            left = Node::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    /// Parses a prefix operator or an operand.
+    fn unary(&mut self) -> ExpressionResult<Node> {
+        let token = self
+            .tokens
+            .get(self.next)
+            .cloned()
+            .ok_or(ExpressionError::UnexpectedEnd)?;
+        self.next += 1;
+
+        match token.kind {
+            TokenKind::Number(value) => Ok(Node::Number(value)),
+            TokenKind::Register(register) => Ok(Node::Register(register)),
+            TokenKind::Variable(variable) => Ok(Node::Variable(variable)),
+            TokenKind::Operator(symbol) => {
+                let op = match symbol {
+                    "!" => UnaryOp::Not,
+                    "-" => UnaryOp::Negate,
+                    "~" => UnaryOp::Complement,
+                    _ => return Err(token.unexpected()),
+                };
+                Ok(Node::Unary(op, Box::new(self.unary()?)))
+            }
+            TokenKind::Open => {
+                let inner = self.expression(1)?;
+                match self.tokens.get(self.next) {
+                    Some(Token {
+                        kind: TokenKind::Close,
+                        ..
+                    }) => {
+                        self.next += 1;
+                        Ok(inner)
+                    }
+                    Some(other) => Err(other.unexpected()),
+                    None => Err(ExpressionError::UnexpectedEnd),
+                }
+            }
+            TokenKind::Close => Err(token.unexpected()),
+        }
+    }
+
+    /// Gets the binary operator at the current token, if it is one.
+    fn peek_binary(&self) -> Option<BinaryOp> {
+        match self.tokens.get(self.next)?.kind {
+            TokenKind::Operator(symbol) => BinaryOp::from_symbol(symbol),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state() -> CpuState {
+        let mut v = [0; 16];
+        v[3] = 0x1F;
+        v[0xA] = 2;
+        CpuState {
+            pc: 0x208,
+            sp: 1,
+            i: 0x340,
+            v,
+            delay_timer: 30,
+            sound_timer: 0,
+            stack_contents: vec![0x202],
+            instruction_count: 4,
+        }
+    }
+
+    fn eval(source: &str) -> i64 {
+        Expr::parse(source).unwrap().eval(&state()).unwrap()
+    }
+
+    #[test]
+    fn test_register_references() {
+        assert_eq!(eval("V3"), 0x1F);
+        assert_eq!(eval("va"), 2);
+        assert_eq!(eval("I"), 0x340);
+        assert_eq!(eval("pc"), 0x208);
+        assert_eq!(eval("SP + DT + ST"), 31);
+        assert!(Expr::parse("V10").is_err());
+        assert!(Expr::parse("VG").is_err());
+    }
+
+    #[test]
+    fn test_operator_precedence() {
+        assert_eq!(eval("2 + 3 * 4"), 14);
+        assert_eq!(eval("(2 + 3) * 4"), 20);
+        assert_eq!(eval("10 - 4 - 3"), 3);
+        assert_eq!(eval("1 << 2 + 1"), 8);
+        assert_eq!(eval("8 | 2 ^ 3 & 6"), 8);
+        assert_eq!(eval("1 + 1 == 2"), 1);
+        assert_eq!(eval("V3 & 0xF == 0xF"), 1);
+        assert_eq!(eval("-VA * 3"), -6);
+        assert_eq!(eval("!0 + ~0"), 0);
+        assert_eq!(eval("0 || 1 && 0"), 0);
+    }
+
+    #[test]
+    fn test_conditions() {
+        let condition = Expr::parse("V3 == 0x1F && I > 0x300").unwrap();
+        assert!(condition.is_true(&state()).unwrap());
+        assert_eq!(condition.to_string(), "V3 == 0x1F && I > 0x300");
+
+        let mut other = state();
+        other.i = 0x200;
+        assert!(!condition.is_true(&other).unwrap());
+
+        // The right side isn't evaluated once the result is known
+        assert_eq!(eval("0 && 1 / 0"), 0);
+        assert_eq!(
+            Expr::parse("V0 % V0").unwrap().eval(&state()),
+            Err(ExpressionError::DivisionByZero)
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Expr::parse("V3 ==").unwrap_err(),
+            ExpressionError::UnexpectedEnd
+        );
+        assert_eq!(
+            Expr::parse("(V3").unwrap_err(),
+            ExpressionError::UnexpectedEnd
+        );
+        assert_eq!(
+            Expr::parse("V3 = 1").unwrap_err(),
+            ExpressionError::UnexpectedToken {
+                found: "=".to_string(),
+                column: 4
+            }
+        );
+        assert_eq!(
+            Expr::parse("V1 $ 2").unwrap_err(),
+            ExpressionError::UnexpectedToken {
+                found: "$".to_string(),
+                column: 4
+            }
+        );
+        assert_eq!(
+            Expr::parse("0x").unwrap_err(),
+            ExpressionError::InvalidNumber {
+                text: "0x".to_string()
+            }
+        );
+        assert!(Expr::parse("1 2").is_err());
+        assert!(Expr::parse("").is_err());
+    }
+
+    #[test]
+    fn test_variables() {
+        let variables = VariableTable::new()
+            .with_variable(Variable::parse("lives", "0x3A0").unwrap())
+            .with_variable(Variable::parse("score", "0x3A1 (u16)").unwrap());
+        let mut memory = Memory::new();
+        memory.write_word(0x3A0, 0x0301).unwrap();
+        memory.write_byte(0x3A2, 0x02).unwrap();
+
+        let condition = Expr::parse_with("lives == 3 && score > 0x100", &variables).unwrap();
+        assert!(condition.is_true_in(&state(), &memory).unwrap());
+        assert_eq!(
+            condition.eval(&state()),
+            Err(ExpressionError::UnreadableVariable {
+                name: "lives".to_string()
+            })
+        );
+        assert!(Expr::parse("lives").is_err());
+    }
+}
@@ -0,0 +1,882 @@
+//! GDB remote serial protocol server.
+//!
+//! Lets gdb, lldb or custom clients attach to the emulator over TCP to read
+//! and write registers and memory, set breakpoints, step and continue.
+//!
+//! Chip-8 has no standard GDB architecture, so registers use this layout
+//! (multi-byte values are little-endian):
+//!
+//! | Number | Register | Size |
+//! | ------ | -------- | ---- |
+//! | 0-15   | V0-VF    | 1    |
+//! | 16     | I        | 2    |
+//! | 17     | PC       | 2    |
+//! | 18     | SP       | 1    |
+//! | 19     | DT       | 1    |
+//! | 20     | ST       | 1    |
+//!
+//! GDB cannot unwind Chip-8 stacks itself, so `monitor bt` prints a
+//! backtrace from the CPU's shadow call stack instead. GDB's own breakpoint
+//! conditions and watchpoints can't see Chip-8 registers either, so
+//! `monitor break LOCATION if CONDITION` and `monitor watch EXPRESSION`
+//! take [`Expr`] expressions evaluated by the stub.
+//!
+//! Instructions stepped or continued through by the session are recorded,
+//! so `reverse-stepi` (the `bs` packet) can undo them one at a time.
+
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+
+use super::{
+    format_backtrace, format_value, format_watches, Debugger, Expr, StopReason, SymbolTable,
+};
+use crate::emulator::{Cpu, NUM_REGISTERS};
+use crate::hardware::Hardware;
+use crate::logging::APP;
+
+/// Register number of I.
+const REG_I: usize = 16;
+
+/// Register number of PC.
+const REG_PC: usize = 17;
+
+/// Register number of SP.
+const REG_SP: usize = 18;
+
+/// Register number of the delay timer.
+const REG_DT: usize = 19;
+
+/// Register number of the sound timer.
+const REG_ST: usize = 20;
+
+/// Total number of registers exposed to the debugger.
+const REGISTER_COUNT: usize = 21;
+
+/// Largest packet the stub accepts, advertised in qSupported.
+const PACKET_SIZE: usize = 4096;
+
+/// CPU cycles between checks for a client interrupt while running.
+const INTERRUPT_POLL_INTERVAL: u32 = 1000;
+
+/// Byte a client sends to interrupt a running target (Ctrl-C).
+const INTERRUPT: u8 = 0x03;
+
+/// What the server should do after handling a packet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Send a reply packet.
+    Reply(String),
+
+    /// Execute one instruction, then report the stop.
+    Step,
+
+    /// Undo the last instruction, then report the stop.
+    StepBack,
+
+    /// Run until something stops execution, then report the stop.
+    Continue,
+
+    /// End the session and leave the emulator as it is.
+    Detach,
+
+    /// End the session at the client's request.
+    Kill,
+}
+
+/// Protocol state for one debugging session.
+#[derive(Debug, Default)]
+pub struct GdbStub {
+    /// Whether the client turned off packet acknowledgements.
+    no_ack: bool,
+
+    /// Symbols for monitor commands and history for stepping back.
+    debugger: Debugger,
+}
+
+impl GdbStub {
+    /// Creates a stub for a new session.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a stub whose monitor commands use a symbol table.
+    pub fn with_symbols(symbols: SymbolTable) -> Self {
+        Self {
+            no_ack: false,
+            debugger: Debugger::with_symbols(symbols),
+        }
+    }
+
+    /// Checks if acknowledgements are disabled (QStartNoAckMode).
+    pub fn no_ack(&self) -> bool {
+        self.no_ack
+    }
+
+    /// Handles a packet payload (without framing) and decides what to do.
+    pub fn handle_packet<H: Hardware>(&mut self, cpu: &mut Cpu<H>, packet: &str) -> Action {
+        let reply = |s: &str| Action::Reply(s.to_string());
+        // The payload is lossily decoded network input, so the command
+        // may be a multi-byte character
+        let split = packet.chars().next().map_or(0, char::len_utf8);
+        let (command, args) = packet.split_at(split);
+
+        match command {
+            "?" => reply("S05"),
+            "g" => Action::Reply(read_registers(cpu)),
+            "G" => match write_registers(cpu, args) {
+                Some(()) => reply("OK"),
+                None => reply("E01"),
+            },
+            "p" => match usize::from_str_radix(args, 16)
+                .ok()
+                .and_then(|n| read_register(cpu, n))
+            {
+                Some(value) => Action::Reply(value),
+                None => reply("E01"),
+            },
+            "P" => match parse_register_write(args).and_then(|(n, v)| write_register(cpu, n, &v)) {
+                Some(()) => reply("OK"),
+                None => reply("E01"),
+            },
+            "m" => match parse_address_length(args).and_then(|(a, l)| read_memory(cpu, a, l)) {
+                Some(hex) => Action::Reply(hex),
+                None => reply("E01"),
+            },
+            "M" => match write_memory(cpu, args) {
+                Some(()) => reply("OK"),
+                None => reply("E01"),
+            },
+            "Z" | "z" => self.handle_breakpoint(cpu, command == "Z", args),
+            "s" => Action::Step,
+            "b" if args == "s" => Action::StepBack,
+            "c" => Action::Continue,
+            "D" => Action::Detach,
+            "k" => Action::Kill,
+            "H" => reply("OK"),
+            "q" if packet.starts_with("qRcmd,") => {
+                monitor_command(&mut self.debugger, cpu, &packet[6..])
+            }
+            "q" | "Q" | "v" => self.handle_query(packet),
+            _ => reply(""),
+        }
+    }
+
+    /// Handles Z/z breakpoint packets. Hardware breakpoints behave like software ones.
+    fn handle_breakpoint<H: Hardware>(
+        &mut self,
+        cpu: &mut Cpu<H>,
+        insert: bool,
+        args: &str,
+    ) -> Action {
+        let mut parts = args.split(',');
+        let kind = parts.next();
+        let address = parts.next().and_then(|a| u16::from_str_radix(a, 16).ok());
+
+        match (kind, address) {
+            (Some("0" | "1"), Some(address)) => {
+                if insert {
+                    cpu.add_breakpoint(address);
+                } else {
+                    cpu.remove_breakpoint(address);
+                }
+                self.debugger.set_condition(address, None);
+                Action::Reply("OK".to_string())
+            }
+            // Watchpoints are not supported
+            (Some(_), Some(_)) => Action::Reply(String::new()),
+            _ => Action::Reply("E01".to_string()),
+        }
+    }
+
+    /// Handles general queries and settings.
+    fn handle_query(&mut self, packet: &str) -> Action {
+        let reply = match packet {
+            p if p.starts_with("qSupported") => {
+                format!("PacketSize={:x};QStartNoAckMode+;ReverseStep+", PACKET_SIZE)
+            }
+            "QStartNoAckMode" => {
+                self.no_ack = true;
+                "OK".to_string()
+            }
+            "qAttached" => "1".to_string(),
+            "qC" => "QC1".to_string(),
+            "qfThreadInfo" => "m1".to_string(),
+            "qsThreadInfo" => "l".to_string(),
+            _ => String::new(),
+        };
+        Action::Reply(reply)
+    }
+}
+
+/// Encodes a stop reason as a stop-reply packet.
+///
+/// Protected writes are reported as write watchpoint hits on the address.
+/// Watch expressions have no GDB equivalent, so their changes are reported
+/// as plain traps.
+pub fn stop_reply(reason: StopReason) -> String {
+    match reason {
+        StopReason::Stepped
+        | StopReason::Breakpoint
+        | StopReason::Halted
+        | StopReason::WatchChanged { .. } => "S05".to_string(),
+        StopReason::Fault => "S04".to_string(),
+        StopReason::Interrupted => "S02".to_string(),
+        StopReason::ProtectedWrite { address } => format!("T05watch:{:x};", address),
+    }
+}
+
+/// Computes the packet checksum (sum of bytes modulo 256).
+pub fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |sum, b| sum.wrapping_add(*b))
+}
+
+/// Frames a payload as `$payload#checksum`, escaping reserved characters.
+pub fn frame(payload: &str) -> Vec<u8> {
+    let mut body = Vec::with_capacity(payload.len());
+    for &byte in payload.as_bytes() {
+        if matches!(byte, b'$' | b'#' | b'}' | b'*') {
+            body.extend_from_slice(&[b'}', byte ^ 0x20]);
+        } else {
+            body.push(byte);
+        }
+    }
+
+    let mut packet = Vec::with_capacity(body.len() + 4);
+    packet.push(b'$');
+    packet.extend_from_slice(&body);
+    packet.extend_from_slice(format!("#{:02x}", checksum(&body)).as_bytes());
+    packet
+}
+
+/// Something received from the client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Incoming {
+    /// A packet with a valid checksum.
+    Packet(String),
+
+    /// A packet whose checksum did not match.
+    Corrupt,
+
+    /// An interrupt request.
+    Interrupt,
+}
+
+/// Extracts the next complete message from the receive buffer.
+fn parse_incoming(buffer: &mut Vec<u8>) -> Option<Incoming> {
+    loop {
+        let first = *buffer.first()?;
+        match first {
+            INTERRUPT => {
+                buffer.remove(0);
+                return Some(Incoming::Interrupt);
+            }
+            b'$' => break,
+            // Acks and line noise
+            _ => {
+                buffer.remove(0);
+            }
+        }
+    }
+
+    let hash = buffer.iter().position(|&b| b == b'#')?;
+    if buffer.len() < hash + 3 {
+        return None;
+    }
+
+    let body: Vec<u8> = buffer[1..hash].to_vec();
+    let expected = std::str::from_utf8(&buffer[hash + 1..hash + 3])
+        .ok()
+        .and_then(|s| u8::from_str_radix(s, 16).ok());
+    buffer.drain(..hash + 3);
+
+    if expected != Some(checksum(&body)) {
+        return Some(Incoming::Corrupt);
+    }
+
+    // Undo escaping
+    let mut payload = Vec::with_capacity(body.len());
+    let mut bytes = body.into_iter();
+    while let Some(byte) = bytes.next() {
+        if byte == b'}' {
+            payload.push(bytes.next().unwrap_or(0) ^ 0x20);
+        } else {
+            payload.push(byte);
+        }
+    }
+
+    Some(Incoming::Packet(
+        String::from_utf8_lossy(&payload).into_owned(),
+    ))
+}
+
+/// Encodes bytes as lowercase hex.
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decodes a hex string into bytes.
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Runs a `monitor` command and returns its hex-encoded output.
+fn monitor_command<H: Hardware>(debugger: &mut Debugger, cpu: &mut Cpu<H>, hex: &str) -> Action {
+    let Some(command) = from_hex(hex).map(|bytes| String::from_utf8_lossy(&bytes).to_string())
+    else {
+        return Action::Reply("E01".to_string());
+    };
+    let words: Vec<&str> = command.split_whitespace().collect();
+    let output = match words.as_slice() {
+        ["bt" | "backtrace"] => format_backtrace(&debugger.backtrace(cpu)),
+        ["break", location] => match debugger.add_breakpoint(cpu, location) {
+            Some(address) => {
+                debugger.set_condition(address, None);
+                format!(
+                    "Breakpoint at 0x{:04X} ({})\n",
+                    address,
+                    debugger.symbols().describe(address)
+                )
+            }
+            None => format!("Unknown location: {}\n", location),
+        },
+        ["break", location, "if", condition @ ..] => match Expr::parse(&condition.join(" ")) {
+            Ok(condition) => match debugger.add_conditional_breakpoint(cpu, location, condition) {
+                Some(address) => format!(
+                    "Breakpoint at 0x{:04X} ({}) if {}\n",
+                    address,
+                    debugger.symbols().describe(address),
+                    debugger.condition(address).map_or("", |c| c.source())
+                ),
+                None => format!("Unknown location: {}\n", location),
+            },
+            Err(e) => format!("Bad condition: {}\n", e),
+        },
+        ["watch"] => format_watches(debugger),
+        ["watch", expression @ ..] => match Expr::parse(&expression.join(" ")) {
+            Ok(expr) => {
+                let index = debugger.add_watch(cpu, expr);
+                let watch = &debugger.watches()[index];
+                format!(
+                    "Watch {}: {} = {}\n",
+                    index,
+                    watch.expr(),
+                    format_value(watch.value())
+                )
+            }
+            Err(e) => format!("Bad expression: {}\n", e),
+        },
+        ["unwatch", index] => match index.parse().ok().and_then(|i| debugger.remove_watch(i)) {
+            Some(watch) => format!("Removed watch {}\n", watch.expr()),
+            None => format!("No watch {}\n", index),
+        },
+        _ => "Supported monitor commands: bt, break LOCATION [if CONDITION], watch [EXPRESSION], unwatch INDEX\n"
+            .to_string(),
+    };
+    Action::Reply(to_hex(output.as_bytes()))
+}
+
+/// Gets the size in bytes of a register.
+fn register_size(number: usize) -> usize {
+    match number {
+        REG_I | REG_PC => 2,
+        _ => 1,
+    }
+}
+
+/// Reads one register as little-endian hex.
+fn read_register<H: Hardware>(cpu: &Cpu<H>, number: usize) -> Option<String> {
+    let state = cpu.get_state();
+    let bytes = match number {
+        n if n < NUM_REGISTERS => vec![state.v[n]],
+        REG_I => state.i.to_le_bytes().to_vec(),
+        REG_PC => state.pc.to_le_bytes().to_vec(),
+        REG_SP => vec![state.sp],
+        REG_DT => vec![state.delay_timer],
+        REG_ST => vec![state.sound_timer],
+        _ => return None,
+    };
+    Some(to_hex(&bytes))
+}
+
+/// Writes one register from little-endian hex.
+fn write_register<H: Hardware>(cpu: &mut Cpu<H>, number: usize, hex: &str) -> Option<()> {
+    let bytes = from_hex(hex)?;
+    if number >= REGISTER_COUNT || bytes.len() != register_size(number) {
+        return None;
+    }
+    let word = || u16::from_le_bytes([bytes[0], bytes[1]]);
+
+    match number {
+        n if n < NUM_REGISTERS => cpu.registers_mut().set_v(n as u8, bytes[0]).ok()?,
+        REG_I => cpu.registers_mut().set_i(word()),
+        REG_PC => cpu.registers_mut().set_pc(word()),
+        REG_SP => cpu.registers_mut().set_sp(bytes[0]),
+        REG_DT => cpu.timers_mut().set_delay_timer(bytes[0]),
+        _ => cpu.timers_mut().set_sound_timer(bytes[0]),
+    }
+    Some(())
+}
+
+/// Reads all registers as one hex string.
+fn read_registers<H: Hardware>(cpu: &Cpu<H>) -> String {
+    (0..REGISTER_COUNT)
+        .filter_map(|n| read_register(cpu, n))
+        .collect()
+}
+
+/// Writes all registers from one hex string.
+fn write_registers<H: Hardware>(cpu: &mut Cpu<H>, hex: &str) -> Option<()> {
+    let mut offset = 0;
+    for number in 0..REGISTER_COUNT {
+        let width = register_size(number) * 2;
+        write_register(cpu, number, hex.get(offset..offset + width)?)?;
+        offset += width;
+    }
+    Some(())
+}
+
+/// Parses `number=value` from a P packet.
+fn parse_register_write(args: &str) -> Option<(usize, String)> {
+    let (number, value) = args.split_once('=')?;
+    Some((usize::from_str_radix(number, 16).ok()?, value.to_string()))
+}
+
+/// Parses `address,length` from m and M packets.
+fn parse_address_length(args: &str) -> Option<(u16, usize)> {
+    let (address, length) = args.split_once(',')?;
+    Some((
+        u16::from_str_radix(address, 16).ok()?,
+        usize::from_str_radix(length, 16).ok()?,
+    ))
+}
+
+/// Reads memory as hex.
+fn read_memory<H: Hardware>(cpu: &Cpu<H>, address: u16, length: usize) -> Option<String> {
+    let bytes = cpu.get_memory().get_slice(address, length).ok()?;
+    Some(to_hex(bytes))
+}
+
+/// Writes memory from an `address,length:data` argument.
+fn write_memory<H: Hardware>(cpu: &mut Cpu<H>, args: &str) -> Option<()> {
+    let (header, data) = args.split_once(':')?;
+    let (address, length) = parse_address_length(header)?;
+    let bytes = from_hex(data)?;
+    if bytes.len() != length {
+        return None;
+    }
+
+    for (offset, byte) in bytes.into_iter().enumerate() {
+        let target = address.checked_add(offset as u16)?;
+        cpu.memory_mut().write_byte(target, byte).ok()?;
+    }
+    Some(())
+}
+
+/// TCP server that serves one debugging session at a time.
+pub struct GdbServer {
+    /// Listening socket.
+    listener: TcpListener,
+
+    /// Symbols given to each session.
+    symbols: SymbolTable,
+}
+
+impl GdbServer {
+    /// Binds the server to an address such as `127.0.0.1:1234`.
+    pub fn bind(address: impl ToSocketAddrs) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(address)?,
+            symbols: SymbolTable::new(),
+        })
+    }
+
+    /// Sets the symbols monitor commands use to name addresses.
+    pub fn with_symbols(mut self, symbols: SymbolTable) -> Self {
+        self.symbols = symbols;
+        self
+    }
+
+    /// Gets the bound port (useful when binding to port 0).
+    pub fn port(&self) -> io::Result<u16> {
+        Ok(self.listener.local_addr()?.port())
+    }
+
+    /// Waits for a client and runs the session until it detaches or disconnects.
+    pub fn serve<H: Hardware>(&self, cpu: &mut Cpu<H>) -> io::Result<()> {
+        let (stream, peer) = self.listener.accept()?;
+        tracing::info!(target: APP, "GDB client connected from {}", peer);
+
+        let stub = GdbStub::with_symbols(self.symbols.clone());
+        let result = Session::new(stream, stub)?.run(cpu);
+        tracing::info!(target: APP, "GDB client disconnected");
+        result
+    }
+}
+
+/// A connected client.
+struct Session {
+    /// Connection to the client.
+    stream: TcpStream,
+
+    /// Bytes received but not yet parsed.
+    buffer: Vec<u8>,
+
+    /// Protocol state.
+    stub: GdbStub,
+}
+
+impl Session {
+    /// Creates a session for a connected stream.
+    fn new(stream: TcpStream, stub: GdbStub) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            stream,
+            buffer: Vec::new(),
+            stub,
+        })
+    }
+
+    /// Processes packets until the client detaches, kills or disconnects.
+    fn run<H: Hardware>(&mut self, cpu: &mut Cpu<H>) -> io::Result<()> {
+        while let Some(incoming) = self.receive()? {
+            let packet = match incoming {
+                Incoming::Packet(packet) => packet,
+                Incoming::Corrupt => {
+                    self.stream.write_all(b"-")?;
+                    continue;
+                }
+                // Interrupts only matter while running
+                Incoming::Interrupt => continue,
+            };
+            if !self.stub.no_ack() {
+                self.stream.write_all(b"+")?;
+            }
+
+            match self.stub.handle_packet(cpu, &packet) {
+                Action::Reply(reply) => self.send(&reply)?,
+                Action::Step => {
+                    let reason = self.stub.debugger.step(cpu);
+                    self.send(&stop_reply(reason))?;
+                }
+                Action::StepBack => {
+                    let reply = match self.stub.debugger.step_back(cpu) {
+                        Ok(true) => "S05",
+                        // Tell the client it reached the start of the recording
+                        Ok(false) => "T05replaylog:begin;",
+                        Err(e) => {
+                            tracing::warn!(target: APP, "Step back failed: {}", e);
+                            "E01"
+                        }
+                    };
+                    self.send(reply)?;
+                }
+                Action::Continue => {
+                    let reason = self.run_until_stop(cpu)?;
+                    self.send(&stop_reply(reason))?;
+                }
+                Action::Detach => {
+                    self.send("OK")?;
+                    return Ok(());
+                }
+                Action::Kill => return Ok(()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs the CPU, checking the socket for an interrupt between batches.
+    fn run_until_stop<H: Hardware>(&mut self, cpu: &mut Cpu<H>) -> io::Result<StopReason> {
+        self.stream.set_nonblocking(true)?;
+        let mut error = None;
+
+        let (stream, buffer) = (&mut self.stream, &mut self.buffer);
+        let reason = self
+            .stub
+            .debugger
+            .run_until_stop(cpu, INTERRUPT_POLL_INTERVAL, || {
+                match poll_interrupt(stream, buffer) {
+                    Ok(interrupted) => interrupted,
+                    Err(e) => {
+                        error = Some(e);
+                        true
+                    }
+                }
+            });
+
+        self.stream.set_nonblocking(false)?;
+        match error {
+            Some(e) => Err(e),
+            None => Ok(reason),
+        }
+    }
+
+    /// Blocks until a message arrives, or returns None if the client hung up.
+    fn receive(&mut self) -> io::Result<Option<Incoming>> {
+        loop {
+            if let Some(incoming) = parse_incoming(&mut self.buffer) {
+                return Ok(Some(incoming));
+            }
+
+            let mut chunk = [0u8; 1024];
+            let n = self.stream.read(&mut chunk)?;
+            if n == 0 {
+                return Ok(None);
+            }
+            self.buffer.extend_from_slice(&chunk[..n]);
+        }
+    }
+
+    /// Sends a framed reply.
+    fn send(&mut self, payload: &str) -> io::Result<()> {
+        self.stream.write_all(&frame(payload))?;
+        self.stream.flush()
+    }
+}
+
+/// Checks for an interrupt byte without blocking.
+fn poll_interrupt(stream: &mut TcpStream, buffer: &mut Vec<u8>) -> io::Result<bool> {
+    let mut chunk = [0u8; 256];
+    match stream.read(&mut chunk) {
+        Ok(0) => Err(ErrorKind::UnexpectedEof.into()),
+        Ok(n) => {
+            buffer.extend_from_slice(&chunk[..n]);
+            if let Some(position) = buffer.iter().position(|&b| b == INTERRUPT) {
+                buffer.remove(position);
+                return Ok(true);
+            }
+            Ok(false)
+        }
+        Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::BufRead;
+
+    fn test_cpu() -> Cpu {
+        let mut cpu = Cpu::new();
+        cpu.load_rom(&[0x60, 0x05, 0x61, 0x06, 0x12, 0x04]).unwrap();
+        cpu
+    }
+
+    fn reply(action: Action) -> String {
+        match action {
+            Action::Reply(reply) => reply,
+            other => panic!("Expected reply, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_framing_round_trip() {
+        assert_eq!(frame("OK"), b"$OK#9a");
+
+        let mut buffer = b"+$m200,2#".to_vec();
+        buffer.extend_from_slice(format!("{:02x}", checksum(b"m200,2")).as_bytes());
+        assert_eq!(
+            parse_incoming(&mut buffer),
+            Some(Incoming::Packet("m200,2".to_string()))
+        );
+        assert!(buffer.is_empty());
+
+        let mut buffer = b"$g#00\x03".to_vec();
+        assert_eq!(parse_incoming(&mut buffer), Some(Incoming::Corrupt));
+        assert_eq!(parse_incoming(&mut buffer), Some(Incoming::Interrupt));
+    }
+
+    #[test]
+    fn test_escaped_payload() {
+        let mut buffer = frame("a}b#");
+        assert_eq!(
+            parse_incoming(&mut buffer),
+            Some(Incoming::Packet("a}b#".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_non_ascii_packet() {
+        let mut cpu = test_cpu();
+        let mut stub = GdbStub::new();
+
+        let packet = String::from_utf8_lossy(b"\xff00");
+        assert_eq!(reply(stub.handle_packet(&mut cpu, &packet)), "");
+        assert_eq!(reply(stub.handle_packet(&mut cpu, "é")), "");
+        assert_eq!(reply(stub.handle_packet(&mut cpu, "")), "");
+    }
+
+    #[test]
+    fn test_register_access() {
+        let mut cpu = test_cpu();
+        let mut stub = GdbStub::new();
+
+        let registers = reply(stub.handle_packet(&mut cpu, "g"));
+        assert_eq!(registers.len(), 23 * 2);
+        assert_eq!(reply(stub.handle_packet(&mut cpu, "p11")), "0002"); // PC
+
+        assert_eq!(reply(stub.handle_packet(&mut cpu, "P3=2a")), "OK");
+        assert_eq!(cpu.get_state().v[3], 0x2A);
+        assert_eq!(reply(stub.handle_packet(&mut cpu, "P10=0003")), "OK");
+        assert_eq!(cpu.get_state().i, 0x300);
+
+        let registers = reply(stub.handle_packet(&mut cpu, "g"));
+        assert_eq!(
+            reply(stub.handle_packet(&mut cpu, &format!("G{}", registers))),
+            "OK"
+        );
+        assert_eq!(reply(stub.handle_packet(&mut cpu, "P3=2a2a")), "E01");
+    }
+
+    #[test]
+    fn test_memory_access() {
+        let mut cpu = test_cpu();
+        let mut stub = GdbStub::new();
+
+        assert_eq!(reply(stub.handle_packet(&mut cpu, "m200,4")), "60056106");
+        assert_eq!(reply(stub.handle_packet(&mut cpu, "M300,2:abcd")), "OK");
+        assert_eq!(cpu.get_memory().read_word(0x300).unwrap(), 0xABCD);
+        assert_eq!(reply(stub.handle_packet(&mut cpu, "mffff,4")), "E01");
+    }
+
+    #[test]
+    fn test_breakpoints_and_execution() {
+        let mut cpu = test_cpu();
+        let mut stub = GdbStub::new();
+
+        assert_eq!(reply(stub.handle_packet(&mut cpu, "Z0,202,2")), "OK");
+        assert!(cpu.breakpoints().contains(&0x202));
+        assert_eq!(stub.handle_packet(&mut cpu, "c"), Action::Continue);
+        assert_eq!(stub.handle_packet(&mut cpu, "s"), Action::Step);
+        assert_eq!(stub.handle_packet(&mut cpu, "bs"), Action::StepBack);
+        assert_eq!(reply(stub.handle_packet(&mut cpu, "bc")), "");
+        assert_eq!(reply(stub.handle_packet(&mut cpu, "z0,202,2")), "OK");
+        assert!(cpu.breakpoints().is_empty());
+        assert_eq!(reply(stub.handle_packet(&mut cpu, "Z2,300,1")), "");
+    }
+
+    #[test]
+    fn test_stop_replies() {
+        assert_eq!(stop_reply(StopReason::Breakpoint), "S05");
+        assert_eq!(stop_reply(StopReason::Fault), "S04");
+        assert_eq!(
+            stop_reply(StopReason::ProtectedWrite { address: 0x1A0 }),
+            "T05watch:1a0;"
+        );
+    }
+
+    #[test]
+    fn test_queries() {
+        let mut cpu = test_cpu();
+        let mut stub = GdbStub::new();
+
+        assert!(
+            reply(stub.handle_packet(&mut cpu, "qSupported:multiprocess+")).contains("PacketSize")
+        );
+        assert!(reply(stub.handle_packet(&mut cpu, "qSupported")).ends_with("ReverseStep+"));
+        assert_eq!(reply(stub.handle_packet(&mut cpu, "QStartNoAckMode")), "OK");
+        assert!(stub.no_ack());
+        assert_eq!(reply(stub.handle_packet(&mut cpu, "?")), "S05");
+        assert_eq!(reply(stub.handle_packet(&mut cpu, "vCont?")), "");
+    }
+
+    #[test]
+    fn test_monitor_backtrace() {
+        let mut cpu = test_cpu();
+        let mut stub = GdbStub::new();
+
+        let command = format!("qRcmd,{}", to_hex(b"bt"));
+        let output = from_hex(&reply(stub.handle_packet(&mut cpu, &command))).unwrap();
+        assert!(String::from_utf8(output)
+            .unwrap()
+            .starts_with("#0  0x0200 in main: "));
+    }
+
+    #[test]
+    fn test_monitor_break_at_symbol() {
+        let mut cpu = test_cpu();
+        let symbols = [("second".to_string(), 0x202)].into_iter().collect();
+        let mut stub = GdbStub::with_symbols(symbols);
+
+        let command = format!("qRcmd,{}", to_hex(b"break second"));
+        let output = from_hex(&reply(stub.handle_packet(&mut cpu, &command))).unwrap();
+        assert_eq!(output, b"Breakpoint at 0x0202 (second)\n");
+        assert!(cpu.breakpoints().contains(&0x202));
+
+        let command = format!("qRcmd,{}", to_hex(b"break nowhere"));
+        let output = from_hex(&reply(stub.handle_packet(&mut cpu, &command))).unwrap();
+        assert_eq!(output, b"Unknown location: nowhere\n");
+    }
+
+    #[test]
+    fn test_monitor_conditions_and_watches() {
+        let mut cpu = test_cpu();
+        let mut stub = GdbStub::new();
+        let mut monitor = |cpu: &mut Cpu, command: &str| {
+            let packet = format!("qRcmd,{}", to_hex(command.as_bytes()));
+            String::from_utf8(from_hex(&reply(stub.handle_packet(cpu, &packet))).unwrap()).unwrap()
+        };
+
+        assert_eq!(
+            monitor(&mut cpu, "break 0x204 if V1 == 6"),
+            "Breakpoint at 0x0204 (0x0204) if V1 == 6\n"
+        );
+        assert!(monitor(&mut cpu, "break 0x204 if V1 ==").starts_with("Bad condition"));
+        assert_eq!(monitor(&mut cpu, "watch v0 + 1"), "Watch 0: v0 + 1 = 0x1\n");
+        assert_eq!(monitor(&mut cpu, "watch"), "Watch 0: v0 + 1 = 0x1\n");
+        assert_eq!(monitor(&mut cpu, "unwatch 0"), "Removed watch v0 + 1\n");
+        assert_eq!(monitor(&mut cpu, "unwatch 0"), "No watch 0\n");
+        assert_eq!(monitor(&mut cpu, "watch"), "No watches\n");
+        assert!(cpu.breakpoints().contains(&0x204));
+    }
+
+    #[test]
+    fn test_server_session() {
+        let server = GdbServer::bind("127.0.0.1:0").unwrap();
+        let port = server.port().unwrap();
+
+        let client = std::thread::spawn(move || {
+            let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+            let mut reader = io::BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+
+            let mut exchange = |payload: &str| -> String {
+                writer.write_all(&frame(payload)).unwrap();
+                let mut response = Vec::new();
+                reader.read_until(b'#', &mut response).unwrap();
+                let mut checksum = [0u8; 2];
+                reader.read_exact(&mut checksum).unwrap();
+                let text = String::from_utf8(response).unwrap();
+                text.trim_start_matches('+')
+                    .trim_start_matches('$')
+                    .trim_end_matches('#')
+                    .to_string()
+            };
+
+            let breakpoint = exchange("Z0,202,2");
+            let stop = exchange("c");
+            let pc = exchange("p11");
+            let back = [exchange("bs"), exchange("p11"), exchange("bs")];
+            exchange("s");
+            let detach = exchange("D");
+            (breakpoint, stop, pc, back, detach)
+        });
+
+        let mut cpu = test_cpu();
+        server.serve(&mut cpu).unwrap();
+
+        let (breakpoint, stop, pc, back, detach) = client.join().unwrap();
+        assert_eq!(breakpoint, "OK");
+        assert_eq!(stop, "S05");
+        assert_eq!(pc, "0202");
+        assert_eq!(back, ["S05", "0002", "T05replaylog:begin;"]);
+        assert_eq!(detach, "OK");
+        assert_eq!(cpu.get_state().v[0], 5);
+    }
+}
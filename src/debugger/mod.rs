@@ -0,0 +1,783 @@
+//! Debugging support for the Chip-8 emulator.
+//!
+//! This module provides execution control on top of the CPU's breakpoints
+//! (single stepping and running until something interesting happens) and a
+//! GDB remote serial protocol server for attaching external debuggers.
+//! It can also write fault report bundles when a program crashes, and with
+//! the `debug-panel` feature draw a live register and disassembly panel.
+//! Symbol maps loaded into a `Debugger` name addresses in its output, and
+//! a `Timeline` exports a run as a Chrome trace. A `Debugger` also records
+//! a snapshot before each instruction it runs, so it can step backwards,
+//! and can attach expression conditions to breakpoints and watch
+//! expressions for changes. A `Monitor` drives one from typed commands.
+//! A `RunDiff` steps two runs of a ROM side by side and reports where they
+//! first differ. A `VariableTable` names typed memory locations, which the
+//! monitor and panel show live and expressions can refer to.
+
+pub mod diff;
+pub mod expr;
+pub mod gdbstub;
+pub mod monitor;
+#[cfg(feature = "debug-panel")]
+pub mod panel;
+pub mod profile;
+pub mod report;
+pub mod symbols;
+pub mod timeline;
+pub mod variables;
+
+pub use diff::{DiffOutcome, Divergence, RunDiff};
+pub use expr::Expr;
+pub use gdbstub::{GdbServer, GdbStub};
+pub use monitor::{Monitor, MonitorReply};
+pub use profile::{CallProfiler, InstructionProfiler, SubroutineStats};
+pub use report::{report_fault, write_fault_report};
+pub use symbols::SymbolTable;
+pub use timeline::Timeline;
+pub use variables::{Variable, VariableTable, VariableType};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use crate::emulator::{Cpu, CpuEvent, RewindBuffer, Snapshot};
+use crate::error::{ExpressionResult, Result};
+use crate::hardware::Hardware;
+use crate::logging::APP;
+
+/// Why execution stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// A single instruction was stepped.
+    Stepped,
+
+    /// A breakpoint was hit; PC is at the breakpoint address.
+    Breakpoint,
+
+    /// The program entered a jump-to-self loop.
+    Halted,
+
+    /// Execution failed (unknown opcode, stack fault, ...).
+    Fault,
+
+    /// The debugger asked execution to stop.
+    Interrupted,
+
+    /// The program wrote to protected memory at `address`.
+    ProtectedWrite { address: u16 },
+
+    /// The watch expression at `index` changed value. A value is `None`
+    /// when the expression couldn't be evaluated, e.g. division by zero.
+    WatchChanged {
+        index: usize,
+        old: Option<i64>,
+        new: Option<i64>,
+    },
+}
+
+/// An expression checked for changes after each instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Watch {
+    /// The watched expression.
+    expr: Expr,
+
+    /// Value after the last check.
+    value: Option<i64>,
+}
+
+impl Watch {
+    /// Gets the watched expression.
+    pub fn expr(&self) -> &Expr {
+        &self.expr
+    }
+
+    /// Gets the value after the last check, or `None` if it couldn't be
+    /// evaluated.
+    pub fn value(&self) -> Option<i64> {
+        self.value
+    }
+}
+
+/// One frame of a backtrace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BacktraceEntry {
+    /// Address executing in this frame (the call site for outer frames).
+    pub pc: u16,
+
+    /// Entry point of the subroutine, or None for the top level.
+    pub function: Option<u16>,
+
+    /// Name shown for the subroutine.
+    pub name: String,
+
+    /// Disassembly of the instruction at `pc`.
+    pub instruction: String,
+}
+
+impl fmt::Display for BacktraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "0x{:04X} in {}: {}",
+            self.pc, self.name, self.instruction
+        )
+    }
+}
+
+/// Gets the symbol name used for a subroutine entry point.
+pub fn symbol_name(function: Option<u16>) -> String {
+    match function {
+        Some(addr) => format!("sub_{:04X}", addr),
+        None => "main".to_string(),
+    }
+}
+
+/// Builds a backtrace from the CPU's shadow call stack, innermost frame first.
+pub fn backtrace<H: Hardware>(cpu: &Cpu<H>) -> Vec<BacktraceEntry> {
+    backtrace_with_symbols(cpu, &SymbolTable::new())
+}
+
+/// Builds a backtrace that names subroutines and operands from a symbol table.
+pub fn backtrace_with_symbols<H: Hardware>(
+    cpu: &Cpu<H>,
+    symbols: &SymbolTable,
+) -> Vec<BacktraceEntry> {
+    let calls = cpu.call_stack();
+    let disassemble_at = |pc: u16| match cpu.get_memory().read_word(pc) {
+        Ok(opcode) => symbols.disassemble(opcode),
+        Err(_) => "??".to_string(),
+    };
+    let entry = |pc: u16, function: Option<u16>| BacktraceEntry {
+        pc,
+        function,
+        name: symbols.function_name(function),
+        instruction: disassemble_at(pc),
+    };
+
+    let mut frames = Vec::with_capacity(calls.len() + 1);
+    let mut pc = cpu.get_state().pc;
+    for call in calls.iter().rev() {
+        frames.push(entry(pc, Some(call.target)));
+        pc = call.call_site;
+    }
+    frames.push(entry(pc, None));
+    frames
+}
+
+/// Formats a backtrace GDB-style, one numbered frame per line.
+pub fn format_backtrace(frames: &[BacktraceEntry]) -> String {
+    frames
+        .iter()
+        .enumerate()
+        .map(|(i, frame)| format!("#{:<2} {}\n", i, frame))
+        .collect()
+}
+
+/// Lists watched expressions with their current values.
+pub(crate) fn format_watches(debugger: &Debugger) -> String {
+    if debugger.watches().is_empty() {
+        return "No watches\n".to_string();
+    }
+    debugger
+        .watches()
+        .iter()
+        .enumerate()
+        .map(|(index, watch)| {
+            format!(
+                "Watch {}: {} = {}\n",
+                index,
+                watch.expr(),
+                format_value(watch.value())
+            )
+        })
+        .collect()
+}
+
+/// Formats a watch value in hex, or `?` if it couldn't be evaluated.
+pub(crate) fn format_value(value: Option<i64>) -> String {
+    match value {
+        Some(value) if value < 0 => format!("-0x{:X}", value.unsigned_abs()),
+        Some(value) => format!("0x{:X}", value),
+        None => "?".to_string(),
+    }
+}
+
+/// Debugger state kept across stops: the symbols used to name addresses,
+/// the program's variables, breakpoint conditions, watches and the history
+/// used to step backwards.
+#[derive(Debug, Clone, Default)]
+pub struct Debugger {
+    /// Names for addresses in the loaded program.
+    symbols: SymbolTable,
+
+    /// Named memory locations in the loaded program.
+    variables: VariableTable,
+
+    /// Conditions of conditional breakpoints, by address.
+    conditions: HashMap<u16, Expr>,
+
+    /// Expressions checked after each instruction.
+    watches: Vec<Watch>,
+
+    /// Snapshots taken before each instruction the debugger ran.
+    history: RewindBuffer,
+
+    /// Instruction count the history leads up to.
+    history_end: Option<u64>,
+}
+
+impl Debugger {
+    /// Creates a debugger without symbols.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a debugger with a symbol table.
+    pub fn with_symbols(symbols: SymbolTable) -> Self {
+        Self {
+            symbols,
+            ..Self::default()
+        }
+    }
+
+    /// Loads a symbol map, replacing the current symbols.
+    ///
+    /// # Arguments
+    /// * `path` - TOML symbol map, or JSON if it ends in `.json`
+    ///
+    /// # Returns
+    /// The number of symbols loaded.
+    pub fn load_symbols<P: AsRef<Path>>(&mut self, path: P) -> Result<usize> {
+        self.symbols = SymbolTable::load(path)?;
+        Ok(self.symbols.len())
+    }
+
+    /// Gets the symbol table.
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    /// Replaces the variables expressions can name.
+    pub fn set_variables(&mut self, variables: VariableTable) {
+        self.variables = variables;
+    }
+
+    /// Loads a variable file, replacing the current variables.
+    ///
+    /// # Returns
+    /// The number of variables loaded.
+    pub fn load_variables<P: AsRef<Path>>(&mut self, path: P) -> Result<usize> {
+        self.variables = VariableTable::load(path)?;
+        Ok(self.variables.len())
+    }
+
+    /// Gets the variables.
+    pub fn variables(&self) -> &VariableTable {
+        &self.variables
+    }
+
+    /// Parses an expression that may name the debugger's variables.
+    pub fn parse_expr(&self, source: &str) -> ExpressionResult<Expr> {
+        Expr::parse_with(source, &self.variables)
+    }
+
+    /// Disassembles an opcode, naming its address operand if possible.
+    pub fn disassemble(&self, opcode: u16) -> String {
+        self.symbols.disassemble(opcode)
+    }
+
+    /// Builds a backtrace with subroutines named from the symbols.
+    pub fn backtrace<H: Hardware>(&self, cpu: &Cpu<H>) -> Vec<BacktraceEntry> {
+        backtrace_with_symbols(cpu, &self.symbols)
+    }
+
+    /// Sets a breakpoint at a symbol, `symbol+offset` or hex address.
+    ///
+    /// # Returns
+    /// The breakpoint address, or None if the location is unknown.
+    pub fn add_breakpoint<H: Hardware>(&self, cpu: &mut Cpu<H>, location: &str) -> Option<u16> {
+        let address = self.symbols.resolve(location)?;
+        cpu.add_breakpoint(address);
+        Some(address)
+    }
+
+    /// Sets a breakpoint that only stops when `condition` is true.
+    ///
+    /// # Returns
+    /// The breakpoint address, or None if the location is unknown.
+    pub fn add_conditional_breakpoint<H: Hardware>(
+        &mut self,
+        cpu: &mut Cpu<H>,
+        location: &str,
+        condition: Expr,
+    ) -> Option<u16> {
+        let address = self.add_breakpoint(cpu, location)?;
+        self.conditions.insert(address, condition);
+        Some(address)
+    }
+
+    /// Sets or clears the condition of the breakpoint at `address`.
+    pub fn set_condition(&mut self, address: u16, condition: Option<Expr>) {
+        match condition {
+            Some(condition) => self.conditions.insert(address, condition),
+            None => self.conditions.remove(&address),
+        };
+    }
+
+    /// Gets the condition of the breakpoint at `address`, if it has one.
+    pub fn condition(&self, address: u16) -> Option<&Expr> {
+        self.conditions.get(&address)
+    }
+
+    /// Starts watching an expression for changes.
+    ///
+    /// # Returns
+    /// The watch's index.
+    pub fn add_watch<H: Hardware>(&mut self, cpu: &Cpu<H>, expr: Expr) -> usize {
+        let value = expr.eval_in(&cpu.get_state(), cpu.get_memory()).ok();
+        self.watches.push(Watch { expr, value });
+        self.watches.len() - 1
+    }
+
+    /// Stops watching the expression at `index`.
+    pub fn remove_watch(&mut self, index: usize) -> Option<Watch> {
+        (index < self.watches.len()).then(|| self.watches.remove(index))
+    }
+
+    /// Gets the watched expressions.
+    pub fn watches(&self) -> &[Watch] {
+        &self.watches
+    }
+
+    /// Executes one instruction like [`step`], recording it for step-back
+    /// and updating watches.
+    pub fn step<H: Hardware>(&mut self, cpu: &mut Cpu<H>) -> StopReason {
+        self.sync_history(cpu);
+        let reason = step_with(cpu, Some(self));
+        self.history_end = Some(cpu.instruction_count());
+        reason
+    }
+
+    /// Runs like [`run_until_stop`], recording each instruction for
+    /// step-back. Breakpoints whose condition is false are run through, and
+    /// a change in a watched expression stops execution.
+    pub fn run_until_stop<H: Hardware>(
+        &mut self,
+        cpu: &mut Cpu<H>,
+        poll_interval: u32,
+        interrupted: impl FnMut() -> bool,
+    ) -> StopReason {
+        self.sync_history(cpu);
+        let reason = run_until_stop_with(cpu, poll_interval, interrupted, Some(self));
+        self.history_end = Some(cpu.instruction_count());
+        reason
+    }
+
+    /// Undoes the last instruction the debugger ran, restoring memory,
+    /// registers, timers and the display as they were before it.
+    ///
+    /// # Returns
+    /// False if there is no history to go back to, e.g. because the CPU ran
+    /// outside the debugger since its last step.
+    pub fn step_back<H: Hardware>(&mut self, cpu: &mut Cpu<H>) -> Result<bool> {
+        self.sync_history(cpu);
+        let Some(snapshot) = self.history.pop() else {
+            return Ok(false);
+        };
+        cpu.restore(&snapshot)?;
+        self.history_end = Some(snapshot.instruction_count);
+        Ok(true)
+    }
+
+    /// Gets the number of instructions that can be stepped back.
+    pub fn history_len(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Forgets the step-back history.
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+        self.history_end = None;
+    }
+
+    /// Drops the history if the CPU has moved on without the debugger.
+    fn sync_history<H: Hardware>(&mut self, cpu: &Cpu<H>) {
+        if self.history_end != Some(cpu.instruction_count()) {
+            self.clear_history();
+        }
+    }
+
+    /// Takes a snapshot to record if the coming cycle changes anything.
+    fn before_cycle<H: Hardware>(&self, cpu: &Cpu<H>) -> Option<Snapshot> {
+        (self.history.capacity() > 0).then(|| cpu.snapshot())
+    }
+
+    /// Records a cycle that changed the state and checks the watches.
+    ///
+    /// # Returns
+    /// A stop if a watched expression changed.
+    fn after_cycle<H: Hardware>(
+        &mut self,
+        cpu: &Cpu<H>,
+        before: Option<Snapshot>,
+        event: CpuEvent,
+    ) -> Option<StopReason> {
+        if matches!(event, CpuEvent::Breakpoint | CpuEvent::WaitingForKey) {
+            return None;
+        }
+        if let Some(before) = before {
+            self.history.push(before);
+        }
+        if self.watches.is_empty() {
+            return None;
+        }
+
+        let state = cpu.get_state();
+        let mut stop = None;
+        for (index, watch) in self.watches.iter_mut().enumerate() {
+            let value = watch.expr.eval_in(&state, cpu.get_memory()).ok();
+            if value != watch.value {
+                stop.get_or_insert(StopReason::WatchChanged {
+                    index,
+                    old: watch.value,
+                    new: value,
+                });
+                watch.value = value;
+            }
+        }
+        stop
+    }
+
+    /// Checks the condition of the breakpoint at PC. Breakpoints without a
+    /// condition, or whose condition can't be evaluated, always stop.
+    fn should_stop_at_breakpoint<H: Hardware>(&self, cpu: &Cpu<H>) -> bool {
+        let state = cpu.get_state();
+        let Some(condition) = self.conditions.get(&state.pc) else {
+            return true;
+        };
+        condition
+            .is_true_in(&state, cpu.get_memory())
+            .unwrap_or_else(|e| {
+                tracing::warn!(target: APP, "Breakpoint condition '{}' failed: {}", condition, e);
+                true
+            })
+    }
+}
+
+/// Executes exactly one instruction, stepping over a breakpoint at PC.
+pub fn step<H: Hardware>(cpu: &mut Cpu<H>) -> StopReason {
+    step_with(cpu, None)
+}
+
+/// Steps one instruction, letting `debugger` record and watch it.
+fn step_with<H: Hardware>(cpu: &mut Cpu<H>, mut debugger: Option<&mut Debugger>) -> StopReason {
+    // A breakpoint at PC is reported once before its instruction runs
+    for _ in 0..2 {
+        let before = debugger.as_ref().and_then(|d| d.before_cycle(cpu));
+        let result = cpu.cycle();
+        let watched = match (&mut debugger, &result) {
+            (Some(debugger), Ok(event)) => debugger.after_cycle(cpu, before, *event),
+            _ => None,
+        };
+        if let Some(address) = cpu.take_protection_violation() {
+            return StopReason::ProtectedWrite { address };
+        }
+        match result {
+            Ok(CpuEvent::Breakpoint) => continue,
+            Ok(_) => return watched.unwrap_or(StopReason::Stepped),
+            Err(_) => return StopReason::Fault,
+        }
+    }
+    StopReason::Stepped
+}
+
+/// Runs until a breakpoint, halt, fault or protected write, or until `interrupted` returns true.
+///
+/// `interrupted` is polled every `poll_interval` cycles.
+pub fn run_until_stop<H: Hardware>(
+    cpu: &mut Cpu<H>,
+    poll_interval: u32,
+    interrupted: impl FnMut() -> bool,
+) -> StopReason {
+    run_until_stop_with(cpu, poll_interval, interrupted, None)
+}
+
+/// Runs until a stop, letting `debugger` record, watch and filter breakpoints.
+fn run_until_stop_with<H: Hardware>(
+    cpu: &mut Cpu<H>,
+    poll_interval: u32,
+    mut interrupted: impl FnMut() -> bool,
+    mut debugger: Option<&mut Debugger>,
+) -> StopReason {
+    let was_halted = cpu.is_halted();
+    let mut cycles = 0u32;
+
+    loop {
+        let before = debugger.as_ref().and_then(|d| d.before_cycle(cpu));
+        let result = cpu.cycle();
+        let watched = match (&mut debugger, &result) {
+            (Some(debugger), Ok(event)) => debugger.after_cycle(cpu, before, *event),
+            _ => None,
+        };
+        if let Some(address) = cpu.take_protection_violation() {
+            return StopReason::ProtectedWrite { address };
+        }
+        if let Some(reason) = watched {
+            return reason;
+        }
+        match result {
+            Ok(CpuEvent::Breakpoint) => {
+                // A false condition runs on; the next cycle steps over it
+                if debugger
+                    .as_ref()
+                    .is_none_or(|d| d.should_stop_at_breakpoint(cpu))
+                {
+                    return StopReason::Breakpoint;
+                }
+            }
+            Ok(CpuEvent::WaitingForKey) => {
+                // Nothing will change until input arrives, so don't spin hard
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+            Ok(_) => {}
+            Err(_) => return StopReason::Fault,
+        }
+
+        if cpu.is_halted() && !was_halted {
+            return StopReason::Halted;
+        }
+
+        cycles += 1;
+        if cycles >= poll_interval.max(1) {
+            cycles = 0;
+            if interrupted() {
+                return StopReason::Interrupted;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::{MemoryProtection, MockClock};
+    use std::time::Duration;
+
+    #[test]
+    fn test_step_over_breakpoint() {
+        let mut cpu = Cpu::new();
+        cpu.load_rom(&[0x60, 0x05, 0x61, 0x06]).unwrap(); // LD V0, 5; LD V1, 6
+        cpu.add_breakpoint(0x200);
+
+        assert_eq!(step(&mut cpu), StopReason::Stepped);
+        assert_eq!(cpu.get_state().pc, 0x202);
+        assert_eq!(cpu.get_state().v[0], 5);
+    }
+
+    #[test]
+    fn test_run_until_breakpoint() {
+        let mut cpu = Cpu::new();
+        cpu.load_rom(&[0x60, 0x05, 0x61, 0x06, 0x12, 0x04]).unwrap();
+        cpu.add_breakpoint(0x202);
+
+        assert_eq!(
+            run_until_stop(&mut cpu, 100, || false),
+            StopReason::Breakpoint
+        );
+        assert_eq!(cpu.get_state().pc, 0x202);
+
+        assert_eq!(run_until_stop(&mut cpu, 100, || false), StopReason::Halted);
+    }
+
+    #[test]
+    fn test_run_until_fault_and_interrupt() {
+        let mut cpu = Cpu::new();
+        cpu.load_rom(&[0xFF, 0xFF]).unwrap();
+        assert_eq!(run_until_stop(&mut cpu, 100, || false), StopReason::Fault);
+
+        let mut cpu = Cpu::new();
+        cpu.load_rom(&[0x70, 0x01, 0x12, 0x00]).unwrap(); // ADD V0, 1; JP 0x200
+        assert_eq!(
+            run_until_stop(&mut cpu, 10, || true),
+            StopReason::Interrupted
+        );
+    }
+
+    #[test]
+    fn test_backtrace() {
+        let mut cpu = Cpu::new();
+        // CALL 0x206; JP 0x202; -; CALL 0x208; LD V0, 1
+        cpu.load_rom(&[0x22, 0x06, 0x12, 0x02, 0x00, 0x00, 0x22, 0x08, 0x60, 0x01])
+            .unwrap();
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+
+        let frames = backtrace(&cpu);
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].pc, 0x208);
+        assert_eq!(frames[0].function, Some(0x208));
+        assert_eq!(frames[1].pc, 0x206);
+        assert_eq!(frames[1].function, Some(0x206));
+        assert_eq!(frames[2].pc, 0x200);
+        assert_eq!(frames[2].function, None);
+
+        let text = format_backtrace(&frames);
+        assert!(text.starts_with("#0  0x0208 in sub_0208: LD V0, 0x01\n"));
+        assert!(text.contains("#2  0x0200 in main: CALL 0x206"));
+    }
+
+    #[test]
+    fn test_debugger_symbols() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("game.sym.toml");
+        std::fs::write(&path, "[symbols]\nouter = 0x206\ninner = 0x208\n").unwrap();
+
+        let mut debugger = Debugger::new();
+        assert_eq!(debugger.load_symbols(&path).unwrap(), 2);
+
+        let mut cpu = Cpu::new();
+        // CALL 0x206; JP 0x202; -; CALL 0x208; LD V0, 1
+        cpu.load_rom(&[0x22, 0x06, 0x12, 0x02, 0x00, 0x00, 0x22, 0x08, 0x60, 0x01])
+            .unwrap();
+        assert_eq!(debugger.add_breakpoint(&mut cpu, "inner"), Some(0x208));
+        assert_eq!(debugger.add_breakpoint(&mut cpu, "missing"), None);
+        assert_eq!(
+            run_until_stop(&mut cpu, 100, || false),
+            StopReason::Breakpoint
+        );
+
+        let text = format_backtrace(&debugger.backtrace(&cpu));
+        assert!(text.starts_with("#0  0x0208 in inner: LD V0, 0x01\n"));
+        assert!(text.contains("#1  0x0206 in outer: CALL inner"));
+        assert!(text.contains("#2  0x0200 in main: CALL outer"));
+        assert_eq!(debugger.disassemble(0x1206), "JP outer");
+    }
+
+    #[test]
+    fn test_stop_on_protected_write() {
+        let mut cpu = Cpu::new();
+        cpu.set_memory_protection(MemoryProtection::Ignore);
+        // LD I, 0x050; LD [I], V0; JP 0x204
+        cpu.load_rom(&[0xA0, 0x50, 0xF0, 0x55, 0x12, 0x04]).unwrap();
+
+        assert_eq!(
+            run_until_stop(&mut cpu, 100, || false),
+            StopReason::ProtectedWrite { address: 0x050 }
+        );
+        assert_eq!(cpu.get_state().pc, 0x204);
+        assert_eq!(cpu.get_memory().read_byte(0x050).unwrap(), 0xF0);
+    }
+
+    #[test]
+    fn test_step_back_restores_timers_and_display() {
+        let clock = MockClock::new();
+        let mut cpu = Cpu::new();
+        cpu.set_time_source(clock.clone());
+        // LD F, V0; DRW V0, V0, 5; LD V1, 60; LD DT, V1; DRW V0, V0, 5
+        cpu.load_rom(&[0xF0, 0x29, 0xD0, 0x05, 0x61, 0x3C, 0xF1, 0x15, 0xD0, 0x05])
+            .unwrap();
+
+        let mut debugger = Debugger::new();
+        assert!(!debugger.step_back(&mut cpu).unwrap());
+        let mut states = Vec::new();
+        for _ in 0..5 {
+            clock.advance(Duration::from_millis(10));
+            states.push(cpu.snapshot());
+            assert_eq!(debugger.step(&mut cpu), StopReason::Stepped);
+        }
+        assert!(!cpu.get_display_buffer()[0]);
+        assert_eq!(cpu.get_state().v[0xF], 1);
+
+        // Undoing the second DRW brings the sprite back
+        assert!(debugger.step_back(&mut cpu).unwrap());
+        assert_eq!(cpu.snapshot(), states[4]);
+        assert!(cpu.get_display_buffer()[0]);
+        assert_eq!(cpu.get_state().v[0xF], 0);
+        assert_eq!(cpu.get_timers().get_delay_timer(), 60);
+
+        // Undoing LD DT puts the timer and its tick phase back
+        assert!(debugger.step_back(&mut cpu).unwrap());
+        assert_eq!(cpu.snapshot(), states[3]);
+        assert_eq!(cpu.get_timers().get_delay_timer(), 0);
+        assert_ne!(states[3].timer_phase_ns, 0);
+        assert_eq!(debugger.history_len(), 3);
+
+        // Running outside the debugger invalidates the history
+        cpu.cycle().unwrap();
+        assert!(!debugger.step_back(&mut cpu).unwrap());
+        assert_eq!(debugger.history_len(), 0);
+    }
+
+    #[test]
+    fn test_step_back_after_run() {
+        let mut cpu = Cpu::new();
+        cpu.load_rom(&[0x70, 0x01, 0x70, 0x01, 0x70, 0x01, 0x12, 0x06])
+            .unwrap();
+        cpu.add_breakpoint(0x204);
+
+        let mut debugger = Debugger::new();
+        assert_eq!(
+            debugger.run_until_stop(&mut cpu, 100, || false),
+            StopReason::Breakpoint
+        );
+        assert_eq!(debugger.history_len(), 2);
+        assert!(debugger.step_back(&mut cpu).unwrap());
+        assert_eq!(cpu.get_state().pc, 0x202);
+        assert_eq!(cpu.get_state().v[0], 1);
+    }
+
+    #[test]
+    fn test_conditional_breakpoint() {
+        let mut cpu = Cpu::new();
+        // ADD V3, 1; JP 0x200
+        cpu.load_rom(&[0x73, 0x01, 0x12, 0x00]).unwrap();
+
+        let mut debugger = Debugger::new();
+        let condition = Expr::parse("V3 == 0x1F && I == 0").unwrap();
+        assert_eq!(
+            debugger.add_conditional_breakpoint(&mut cpu, "0x202", condition),
+            Some(0x202)
+        );
+        assert_eq!(
+            debugger.run_until_stop(&mut cpu, 100, || false),
+            StopReason::Breakpoint
+        );
+        assert_eq!(cpu.get_state().pc, 0x202);
+        assert_eq!(cpu.get_state().v[3], 0x1F);
+
+        // Without the condition every pass stops
+        debugger.set_condition(0x202, None);
+        debugger.step(&mut cpu);
+        assert_eq!(
+            debugger.run_until_stop(&mut cpu, 100, || false),
+            StopReason::Breakpoint
+        );
+        assert_eq!(cpu.get_state().v[3], 0x20);
+    }
+
+    #[test]
+    fn test_watch_stops_on_change() {
+        let mut cpu = Cpu::new();
+        // ADD V0, 1; SHL V0; JP 0x200
+        cpu.load_rom(&[0x70, 0x01, 0x80, 0x0E, 0x12, 0x00]).unwrap();
+
+        let mut debugger = Debugger::new();
+        assert_eq!(debugger.add_watch(&cpu, Expr::parse("V0 > 3").unwrap()), 0);
+        assert_eq!(debugger.watches()[0].value(), Some(0));
+
+        assert_eq!(
+            debugger.run_until_stop(&mut cpu, 100, || false),
+            StopReason::WatchChanged {
+                index: 0,
+                old: Some(0),
+                new: Some(1)
+            }
+        );
+        assert_eq!(cpu.get_state().v[0], 6);
+        assert_eq!(cpu.get_state().pc, 0x204);
+
+        assert!(debugger.remove_watch(0).is_some());
+        assert!(debugger.remove_watch(0).is_none());
+        assert_eq!(debugger.step(&mut cpu), StopReason::Stepped);
+    }
+}
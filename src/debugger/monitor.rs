@@ -0,0 +1,732 @@
+//! Interactive command-line monitor.
+//!
+//! `chip8 monitor` reads commands from the terminal and runs them against
+//! a [`Debugger`]: stepping and continuing, breakpoints and watches,
+//! registers, variables, memory dumps, disassembly and the screen as text.
+//! Locations can be symbols or hex addresses, expressions can name
+//! variables, and an empty line repeats the last command like GDB. With
+//! the `line-editor` feature, a terminal also gets rustyline's history,
+//! cursor movement and Tab completion of commands, symbols and variables.
+
+use std::io::{self, BufRead, Write};
+#[cfg(feature = "line-editor")]
+use std::path::Path;
+
+use super::{format_backtrace, format_value, format_watches, Debugger, StopReason};
+use crate::emulator::Cpu;
+use crate::hardware::{ascii_art, Hardware};
+#[cfg(feature = "line-editor")]
+use crate::logging::APP;
+
+/// Prompt printed before each command.
+pub const PROMPT: &str = "(chip8) ";
+
+/// Cycles `continue` runs before giving up on reaching a stop.
+pub const CONTINUE_LIMIT: u32 = 1_000_000;
+
+/// Cycles between checks of the `continue` limit.
+const POLL_INTERVAL: u32 = 1000;
+
+/// Bytes `mem` dumps when no length is given.
+const DEFAULT_DUMP_LENGTH: usize = 64;
+
+/// Instructions `dis` lists when no count is given.
+const DEFAULT_DISASSEMBLY_LENGTH: usize = 8;
+
+/// Most instructions `dis` lists: enough to cover the 64K address space,
+/// which reads wrap around.
+const MAX_DISASSEMBLY_LENGTH: usize = 0x8000;
+
+/// File the line editor keeps the command history in, in the data directory.
+pub const HISTORY_FILE: &str = "monitor-history.txt";
+
+/// Command names offered when completing the first word of a line.
+const COMMANDS: &[&str] = &[
+    "back", "break", "breaks", "bt", "continue", "delete", "dis", "draw", "help", "mem", "print",
+    "quit", "regs", "step", "unwatch", "vars", "watch",
+];
+
+/// Help text listing every command.
+const HELP: &str = "\
+Commands:
+  step [N]            (s) Run N instructions (default 1)
+  back                (sb) Undo the last instruction
+  continue            (c) Run until a breakpoint, watch change, halt or fault
+  regs                (r) Show registers, timers and the stack
+  vars                (v) Show the program's variables
+  mem ADDR [LEN]      (x) Dump LEN bytes of memory (default 64)
+  dis [ADDR] [N]      (d) Disassemble N instructions (default: 8 at PC)
+  break LOC [if COND] (b) Set a breakpoint at a symbol or address
+  delete LOC              Remove a breakpoint
+  breaks                  List breakpoints
+  watch [EXPR]            Watch an expression, or list watches
+  unwatch INDEX           Stop watching an expression
+  print EXPR          (p) Evaluate an expression
+  bt                      Show the call stack
+  draw                    Show the screen as text
+  help                (h) Show this help
+  quit                (q) Leave the monitor
+An empty line repeats the last command.
+";
+
+/// What a command produced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MonitorReply {
+    /// Text to print.
+    Output(String),
+
+    /// The user asked to leave.
+    Quit,
+}
+
+/// Command interpreter over a debugger.
+#[derive(Debug, Clone, Default)]
+pub struct Monitor {
+    /// Debugger the commands drive.
+    debugger: Debugger,
+
+    /// Last command run, repeated by an empty line.
+    last_command: Option<String>,
+}
+
+impl Monitor {
+    /// Creates a monitor over a debugger, e.g. one with symbols loaded.
+    pub fn new(debugger: Debugger) -> Self {
+        Self {
+            debugger,
+            last_command: None,
+        }
+    }
+
+    /// Gets the debugger.
+    pub fn debugger(&self) -> &Debugger {
+        &self.debugger
+    }
+
+    /// Reads commands from `input` until it ends or the user quits,
+    /// printing a prompt and each command's output to `output`.
+    pub fn run<H: Hardware>(
+        &mut self,
+        cpu: &mut Cpu<H>,
+        mut input: impl BufRead,
+        mut output: impl Write,
+    ) -> io::Result<()> {
+        let mut line = String::new();
+        loop {
+            write!(output, "{}", PROMPT)?;
+            output.flush()?;
+            line.clear();
+            if input.read_line(&mut line)? == 0 {
+                writeln!(output)?;
+                return Ok(());
+            }
+            match self.execute(cpu, &line) {
+                MonitorReply::Output(text) => write!(output, "{}", text)?,
+                MonitorReply::Quit => return Ok(()),
+            }
+        }
+    }
+
+    /// Reads commands from the terminal with line editing until the user
+    /// quits.
+    ///
+    /// Tab completes commands, symbols and variables. Ctrl-C clears the
+    /// line and Ctrl-D leaves.
+    ///
+    /// # Arguments
+    /// * `cpu` - CPU the commands run against
+    /// * `history` - File to load the command history from and save it to
+    #[cfg(feature = "line-editor")]
+    pub fn run_interactive<H: Hardware>(
+        &mut self,
+        cpu: &mut Cpu<H>,
+        history: Option<&Path>,
+    ) -> io::Result<()> {
+        use rustyline::error::ReadlineError;
+
+        let mut editor = rustyline::Editor::new().map_err(readline_error)?;
+        editor.set_helper(Some(editor::MonitorHelper::new(self.completion_names())));
+        if let Some(path) = history {
+            // There is none the first time
+            let _ = editor.load_history(path);
+        }
+
+        loop {
+            match editor.readline(PROMPT) {
+                Ok(line) => {
+                    if !line.trim().is_empty() {
+                        let _ = editor.add_history_entry(line.as_str());
+                    }
+                    match self.execute(cpu, &line) {
+                        MonitorReply::Output(text) => print!("{}", text),
+                        MonitorReply::Quit => break,
+                    }
+                }
+                Err(ReadlineError::Interrupted) => continue,
+                Err(ReadlineError::Eof) => {
+                    println!();
+                    break;
+                }
+                Err(e) => return Err(readline_error(e)),
+            }
+        }
+
+        if let Some(path) = history {
+            let saved = match path.parent() {
+                Some(dir) => std::fs::create_dir_all(dir).map_err(ReadlineError::Io),
+                None => Ok(()),
+            }
+            .and_then(|()| editor.save_history(path));
+            if let Err(e) = saved {
+                tracing::warn!(target: APP, "Failed to save monitor history: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Completes the word before `pos` in a command line: a command if it
+    /// is the first word, otherwise a symbol or variable.
+    ///
+    /// # Returns
+    /// The byte offset the word starts at and the candidates, sorted.
+    pub fn complete(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        complete(line, pos, &self.completion_names())
+    }
+
+    /// Gets the symbol and variable names arguments can complete to.
+    fn completion_names(&self) -> Vec<String> {
+        let symbols = self.debugger.symbols().iter().map(|(_, name)| name);
+        let variables = self
+            .debugger
+            .variables()
+            .iter()
+            .map(|variable| variable.name.as_str());
+        symbols.chain(variables).map(str::to_string).collect()
+    }
+
+    /// Runs one command line.
+    pub fn execute<H: Hardware>(&mut self, cpu: &mut Cpu<H>, line: &str) -> MonitorReply {
+        let line = line.trim();
+        let line = if line.is_empty() {
+            match self.last_command.clone() {
+                Some(last) => last,
+                None => return MonitorReply::Output(String::new()),
+            }
+        } else {
+            self.last_command = Some(line.to_string());
+            line.to_string()
+        };
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let output = match words.as_slice() {
+            ["quit" | "q" | "exit"] => return MonitorReply::Quit,
+            ["help" | "h" | "?"] => HELP.to_string(),
+            ["step" | "s"] => self.step(cpu, 1),
+            ["step" | "s", count] => match parse_number(count) {
+                Some(count) => self.step(cpu, count),
+                None => format!("Bad count: {}\n", count),
+            },
+            ["back" | "sb"] => match self.debugger.step_back(cpu) {
+                Ok(true) => self.current_instruction(cpu),
+                Ok(false) => "No history to step back through\n".to_string(),
+                Err(e) => format!("Step back failed: {}\n", e),
+            },
+            ["continue" | "c"] => {
+                let mut polls = 0;
+                let reason = self.debugger.run_until_stop(cpu, POLL_INTERVAL, || {
+                    polls += 1;
+                    polls * POLL_INTERVAL >= CONTINUE_LIMIT
+                });
+                self.describe_stop(cpu, reason)
+            }
+            ["regs" | "r"] => format_registers(cpu),
+            ["vars" | "v"] => self.format_variables(cpu),
+            ["mem" | "x", address] => self.dump(cpu, address, DEFAULT_DUMP_LENGTH),
+            ["mem" | "x", address, length] => match parse_number(length) {
+                Some(length) => self.dump(cpu, address, length),
+                None => format!("Bad length: {}\n", length),
+            },
+            ["dis" | "d"] => self.disassemble(cpu, cpu.get_state().pc, DEFAULT_DISASSEMBLY_LENGTH),
+            ["dis" | "d", location] => match self.debugger.symbols().resolve(location) {
+                Some(address) => self.disassemble(cpu, address, DEFAULT_DISASSEMBLY_LENGTH),
+                None => format!("Unknown location: {}\n", location),
+            },
+            ["dis" | "d", location, count] => {
+                match (
+                    self.debugger.symbols().resolve(location),
+                    parse_number(count),
+                ) {
+                    (Some(address), Some(count)) => self.disassemble(cpu, address, count),
+                    (None, _) => format!("Unknown location: {}\n", location),
+                    (_, None) => format!("Bad count: {}\n", count),
+                }
+            }
+            ["break" | "b", location] => match self.debugger.add_breakpoint(cpu, location) {
+                Some(address) => {
+                    self.debugger.set_condition(address, None);
+                    format!("Breakpoint at {}\n", self.describe(address))
+                }
+                None => format!("Unknown location: {}\n", location),
+            },
+            ["break" | "b", location, "if", condition @ ..] => {
+                match self.debugger.parse_expr(&condition.join(" ")) {
+                    Ok(condition) => {
+                        match self
+                            .debugger
+                            .add_conditional_breakpoint(cpu, location, condition)
+                        {
+                            Some(address) => format!(
+                                "Breakpoint at {} if {}\n",
+                                self.describe(address),
+                                self.debugger.condition(address).map_or("", |c| c.source())
+                            ),
+                            None => format!("Unknown location: {}\n", location),
+                        }
+                    }
+                    Err(e) => format!("Bad condition: {}\n", e),
+                }
+            }
+            ["delete", location] => match self.debugger.symbols().resolve(location) {
+                Some(address) if cpu.remove_breakpoint(address) => {
+                    self.debugger.set_condition(address, None);
+                    format!("Removed breakpoint at {}\n", self.describe(address))
+                }
+                Some(address) => format!("No breakpoint at {}\n", self.describe(address)),
+                None => format!("Unknown location: {}\n", location),
+            },
+            ["breaks"] => self.list_breakpoints(cpu),
+            ["watch"] => format_watches(&self.debugger),
+            ["watch", expression @ ..] => match self.debugger.parse_expr(&expression.join(" ")) {
+                Ok(expr) => {
+                    let index = self.debugger.add_watch(cpu, expr);
+                    let watch = &self.debugger.watches()[index];
+                    format!(
+                        "Watch {}: {} = {}\n",
+                        index,
+                        watch.expr(),
+                        format_value(watch.value())
+                    )
+                }
+                Err(e) => format!("Bad expression: {}\n", e),
+            },
+            ["unwatch", index] => match index
+                .parse()
+                .ok()
+                .and_then(|i| self.debugger.remove_watch(i))
+            {
+                Some(watch) => format!("Removed watch {}\n", watch.expr()),
+                None => format!("No watch {}\n", index),
+            },
+            ["print" | "p", expression @ ..] if !expression.is_empty() => {
+                match self.debugger.parse_expr(&expression.join(" ")) {
+                    Ok(expr) => match expr.eval_in(&cpu.get_state(), cpu.get_memory()) {
+                        Ok(value) => {
+                            format!("{} = {} ({})\n", expr, format_value(Some(value)), value)
+                        }
+                        Err(e) => format!("Can't evaluate {}: {}\n", expr, e),
+                    },
+                    Err(e) => format!("Bad expression: {}\n", e),
+                }
+            }
+            ["bt" | "backtrace"] => format_backtrace(&self.debugger.backtrace(cpu)),
+            ["draw"] => ascii_art(cpu.get_display_buffer(), cpu.display_size().0),
+            _ => format!("Unknown command: {} (try 'help')\n", line),
+        };
+        MonitorReply::Output(output)
+    }
+
+    /// Steps up to `count` instructions, stopping early at anything but a plain step.
+    fn step<H: Hardware>(&mut self, cpu: &mut Cpu<H>, count: usize) -> String {
+        for _ in 0..count.max(1) {
+            let reason = self.debugger.step(cpu);
+            if reason != StopReason::Stepped {
+                return self.describe_stop(cpu, reason);
+            }
+        }
+        self.current_instruction(cpu)
+    }
+
+    /// Explains why execution stopped, followed by the instruction at PC.
+    fn describe_stop<H: Hardware>(&self, cpu: &Cpu<H>, reason: StopReason) -> String {
+        let why = match reason {
+            StopReason::Stepped => String::new(),
+            StopReason::Breakpoint => "Breakpoint hit\n".to_string(),
+            StopReason::Halted => "Program halted in a jump-to-self loop\n".to_string(),
+            StopReason::Fault => "Program faulted\n".to_string(),
+            StopReason::Interrupted => {
+                format!("Stopped after {} cycles without a stop\n", CONTINUE_LIMIT)
+            }
+            StopReason::ProtectedWrite { address } => {
+                format!("Write to protected memory at 0x{:04X}\n", address)
+            }
+            StopReason::WatchChanged { index, old, new } => format!(
+                "Watch {} changed: {} -> {}\n",
+                index,
+                format_value(old),
+                format_value(new)
+            ),
+        };
+        why + &self.current_instruction(cpu)
+    }
+
+    /// Formats the instruction at PC.
+    fn current_instruction<H: Hardware>(&self, cpu: &Cpu<H>) -> String {
+        self.disassemble(cpu, cpu.get_state().pc, 1)
+    }
+
+    /// Lists `count` instructions from `address`, marking PC with `=>`.
+    fn disassemble<H: Hardware>(&self, cpu: &Cpu<H>, address: u16, count: usize) -> String {
+        let pc = cpu.get_state().pc;
+        let mut out = String::new();
+        for n in 0..count.min(MAX_DISASSEMBLY_LENGTH) {
+            let at = address.wrapping_add(2 * n as u16);
+            let Ok(opcode) = cpu.get_memory().read_word(at) else {
+                break;
+            };
+            out.push_str(&format!(
+                "{} 0x{:04X}: {:04X}  {:<20} <{}>\n",
+                if at == pc { "=>" } else { "  " },
+                at,
+                opcode,
+                self.debugger.disassemble(opcode),
+                self.debugger.symbols().describe(at)
+            ));
+        }
+        out
+    }
+
+    /// Dumps memory 16 bytes per line with an ASCII column.
+    fn dump<H: Hardware>(&self, cpu: &Cpu<H>, location: &str, length: usize) -> String {
+        let Some(start) = self.debugger.symbols().resolve(location) else {
+            return format!("Unknown location: {}\n", location);
+        };
+        let memory = cpu.get_memory();
+        let length = length.min(memory.size().saturating_sub(start as usize));
+        let Ok(bytes) = memory.get_slice(start, length) else {
+            return format!("Can't read 0x{:04X}\n", start);
+        };
+        bytes
+            .chunks(16)
+            .enumerate()
+            .map(|(row, chunk)| {
+                let hex: Vec<String> = chunk.iter().map(|b| format!("{:02X}", b)).collect();
+                let text: String = chunk
+                    .iter()
+                    .map(|&b| if b.is_ascii_graphic() { b as char } else { '.' })
+                    .collect();
+                format!(
+                    "0x{:04X}: {:<47}  {}\n",
+                    start as usize + row * 16,
+                    hex.join(" "),
+                    text
+                )
+            })
+            .collect()
+    }
+
+    /// Lists breakpoints in address order with their conditions.
+    fn list_breakpoints<H: Hardware>(&self, cpu: &Cpu<H>) -> String {
+        let mut addresses: Vec<u16> = cpu.breakpoints().iter().copied().collect();
+        if addresses.is_empty() {
+            return "No breakpoints\n".to_string();
+        }
+        addresses.sort_unstable();
+        addresses
+            .into_iter()
+            .map(|address| match self.debugger.condition(address) {
+                Some(condition) => format!("{} if {}\n", self.describe(address), condition),
+                None => format!("{}\n", self.describe(address)),
+            })
+            .collect()
+    }
+
+    /// Lists the variables with their addresses, types and values.
+    fn format_variables<H: Hardware>(&self, cpu: &Cpu<H>) -> String {
+        let variables = self.debugger.variables();
+        if variables.is_empty() {
+            return "No variables\n".to_string();
+        }
+        let width = variables.iter().map(|v| v.name.len()).max().unwrap_or(0);
+        variables
+            .iter()
+            .map(|variable| {
+                format!(
+                    "{:<width$} 0x{:04X} {:<4} = {}\n",
+                    variable.name,
+                    variable.address,
+                    variable.kind,
+                    variable.format(cpu.get_memory()),
+                    width = width
+                )
+            })
+            .collect()
+    }
+
+    /// Formats an address with its symbol.
+    fn describe(&self, address: u16) -> String {
+        format!(
+            "0x{:04X} ({})",
+            address,
+            self.debugger.symbols().describe(address)
+        )
+    }
+}
+
+/// Formats the registers, timers and stack.
+fn format_registers<H: Hardware>(cpu: &Cpu<H>) -> String {
+    let state = cpu.get_state();
+    let mut out = String::new();
+    for row in state.v.chunks(8).enumerate() {
+        let (row, values) = row;
+        let line: Vec<String> = values
+            .iter()
+            .enumerate()
+            .map(|(n, value)| format!("V{:X}={:02X}", row * 8 + n, value))
+            .collect();
+        out.push_str(&line.join(" "));
+        out.push('\n');
+    }
+    out.push_str(&format!(
+        "PC={:04X} I={:04X} SP={:X} DT={:02X} ST={:02X}\n",
+        state.pc, state.i, state.sp, state.delay_timer, state.sound_timer
+    ));
+    let stack: Vec<String> = state
+        .stack_contents
+        .iter()
+        .map(|address| format!("{:04X}", address))
+        .collect();
+    out.push_str(&format!("Stack: [{}]\n", stack.join(" ")));
+    out
+}
+
+/// Parses a count or length in decimal or `0x` hex.
+fn parse_number(text: &str) -> Option<usize> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+/// Completes the word before `pos`: a command if it is the first word,
+/// otherwise one of `names`.
+fn complete(line: &str, pos: usize, names: &[String]) -> (usize, Vec<String>) {
+    let line = line.get(..pos).unwrap_or(line);
+    let word = line.rsplit(char::is_whitespace).next().unwrap_or_default();
+    let start = line.len() - word.len();
+    let mut candidates: Vec<String> = if line[..start].trim().is_empty() {
+        COMMANDS
+            .iter()
+            .filter(|command| command.starts_with(word))
+            .map(|command| command.to_string())
+            .collect()
+    } else {
+        names
+            .iter()
+            .filter(|name| name.starts_with(word))
+            .cloned()
+            .collect()
+    };
+    candidates.sort();
+    candidates.dedup();
+    (start, candidates)
+}
+
+/// Converts a line editor error to an I/O error.
+#[cfg(feature = "line-editor")]
+fn readline_error(error: rustyline::error::ReadlineError) -> io::Error {
+    match error {
+        rustyline::error::ReadlineError::Io(e) => e,
+        e => io::Error::other(e),
+    }
+}
+
+/// rustyline hooks for the monitor's prompt.
+#[cfg(feature = "line-editor")]
+mod editor {
+    use rustyline::completion::Completer;
+    use rustyline::highlight::Highlighter;
+    use rustyline::hint::Hinter;
+    use rustyline::validate::Validator;
+    use rustyline::{Context, Helper};
+
+    /// Completes commands, symbols and variables.
+    pub(super) struct MonitorHelper {
+        names: Vec<String>,
+    }
+
+    impl MonitorHelper {
+        pub(super) fn new(names: Vec<String>) -> Self {
+            Self { names }
+        }
+    }
+
+    impl Completer for MonitorHelper {
+        type Candidate = String;
+
+        fn complete(
+            &self,
+            line: &str,
+            pos: usize,
+            _ctx: &Context<'_>,
+        ) -> rustyline::Result<(usize, Vec<String>)> {
+            Ok(super::complete(line, pos, &self.names))
+        }
+    }
+
+    impl Hinter for MonitorHelper {
+        type Hint = String;
+    }
+
+    impl Highlighter for MonitorHelper {}
+
+    impl Validator for MonitorHelper {}
+
+    impl Helper for MonitorHelper {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::debugger::VariableTable;
+
+    /// Loads a ROM into a fresh CPU.
+    fn cpu_with(rom: &[u8]) -> Cpu {
+        let mut cpu = Cpu::new();
+        cpu.load_rom(rom).unwrap();
+        cpu
+    }
+
+    fn output(reply: MonitorReply) -> String {
+        match reply {
+            MonitorReply::Output(text) => text,
+            MonitorReply::Quit => panic!("unexpected quit"),
+        }
+    }
+
+    #[test]
+    fn test_step_regs_mem_and_repeat() {
+        // LD V0, 0x05; ADD V0, 0x01; ADD V0, 0x01; JP 0x206
+        let mut cpu = cpu_with(&[0x60, 0x05, 0x70, 0x01, 0x70, 0x01, 0x12, 0x06]);
+        let mut monitor = Monitor::default();
+
+        let stepped = output(monitor.execute(&mut cpu, "step"));
+        assert!(stepped.starts_with("=> 0x0202: 7001"), "{}", stepped);
+        assert!(output(monitor.execute(&mut cpu, "regs")).contains("V0=05"));
+
+        // An empty line repeats the last command
+        monitor.execute(&mut cpu, "s");
+        output(monitor.execute(&mut cpu, ""));
+        assert_eq!(cpu.get_state().v[0], 7);
+        let back = output(monitor.execute(&mut cpu, "back"));
+        assert!(back.starts_with("=> 0x0204: 7001"), "{}", back);
+        assert_eq!(cpu.get_state().v[0], 6);
+
+        let dump = output(monitor.execute(&mut cpu, "mem 0x200 8"));
+        assert!(
+            dump.starts_with("0x0200: 60 05 70 01 70 01 12 06"),
+            "{}",
+            dump
+        );
+        assert_eq!(monitor.execute(&mut cpu, "quit"), MonitorReply::Quit);
+    }
+
+    #[test]
+    fn test_breakpoints_and_continue() {
+        // CLS; LD V1, 0x02; JP 0x204
+        let mut cpu = cpu_with(&[0x00, 0xE0, 0x61, 0x02, 0x12, 0x04]);
+        let mut monitor = Monitor::default();
+
+        assert!(output(monitor.execute(&mut cpu, "break 0x204")).contains("0x0204"));
+        let stop = output(monitor.execute(&mut cpu, "c"));
+        assert!(stop.starts_with("Breakpoint hit\n=> 0x0204"), "{}", stop);
+        assert!(output(monitor.execute(&mut cpu, "breaks")).contains("0x0204"));
+        assert!(output(monitor.execute(&mut cpu, "print V1 + 1")).contains("= 0x3 (3)"));
+
+        assert!(output(monitor.execute(&mut cpu, "delete 0x204")).starts_with("Removed"));
+        assert_eq!(
+            output(monitor.execute(&mut cpu, "breaks")),
+            "No breakpoints\n"
+        );
+        assert!(output(monitor.execute(&mut cpu, "c")).starts_with("Program halted"));
+
+        let listing = output(monitor.execute(&mut cpu, "dis 0x200 2"));
+        assert_eq!(listing.lines().count(), 2);
+        assert!(listing.contains("CLS"));
+        assert_eq!(
+            output(monitor.execute(&mut cpu, "draw")).lines().count(),
+            32
+        );
+        assert!(output(monitor.execute(&mut cpu, "frobnicate")).starts_with("Unknown command"));
+    }
+
+    #[test]
+    fn test_variables() {
+        // LD V0, 3; LD I, 0x300; LD [I], V0; JP 0x206
+        let mut cpu = cpu_with(&[0x60, 0x03, 0xA3, 0x00, 0xF0, 0x55, 0x12, 0x06]);
+        let mut monitor = Monitor::default();
+        assert_eq!(output(monitor.execute(&mut cpu, "vars")), "No variables\n");
+        assert!(output(monitor.execute(&mut cpu, "print lives")).starts_with("Bad expression"));
+
+        let mut debugger = Debugger::new();
+        debugger.set_variables(
+            VariableTable::parse("[variables]\nlives = 0x300\nflag = \"0x300 (bool)\"").unwrap(),
+        );
+        let mut monitor = Monitor::new(debugger);
+        assert!(output(monitor.execute(&mut cpu, "break 0x206 if lives == 3")).contains("lives"));
+        assert!(output(monitor.execute(&mut cpu, "c")).starts_with("Breakpoint hit"));
+        let vars = output(monitor.execute(&mut cpu, "v"));
+        assert_eq!(vars, "flag  0x0300 bool = true\nlives 0x0300 u8   = 3\n");
+        assert!(output(monitor.execute(&mut cpu, "p lives * 2")).contains("(6)"));
+    }
+
+    #[test]
+    fn test_disassembly_wraps_64k() {
+        let config = crate::frontend::EmulatorBehaviorConfig {
+            memory_layout: crate::emulator::MemoryLayout::XoChip64K,
+            ..Default::default()
+        };
+        let mut cpu = Cpu::new_with_config(&config);
+        cpu.load_rom(&[0x60, 0x05]).unwrap();
+        let mut monitor = Monitor::default();
+
+        let listing = output(monitor.execute(&mut cpu, "dis 0x200 40000"));
+        assert_eq!(listing.lines().count(), MAX_DISASSEMBLY_LENGTH);
+        assert!(listing.lines().last().unwrap().contains("0x01FE"));
+    }
+
+    #[test]
+    fn test_completion() {
+        let symbols = [("draw_score".to_string(), 0x2A4)].into_iter().collect();
+        let mut debugger = Debugger::with_symbols(symbols);
+        debugger.set_variables(VariableTable::parse("[variables]\nlives = 0x300").unwrap());
+        let monitor = Monitor::new(debugger);
+
+        assert_eq!(
+            monitor.complete("br", 2),
+            (0, vec!["break".into(), "breaks".into()])
+        );
+        assert_eq!(monitor.complete("  wa", 4), (2, vec!["watch".into()]));
+        assert_eq!(
+            monitor.complete("break dr", 8),
+            (6, vec!["draw_score".into()])
+        );
+        assert_eq!(
+            monitor.complete("break draw_score if li", 22),
+            (20, vec!["lives".into()])
+        );
+        assert_eq!(monitor.complete("print x", 7), (6, vec![]));
+    }
+
+    #[test]
+    fn test_run_reads_until_quit() {
+        let mut cpu = cpu_with(&[0x60, 0x05]);
+        let mut monitor = Monitor::default();
+        let mut out = Vec::new();
+        monitor
+            .run(&mut cpu, "step\nquit\nstep\n".as_bytes(), &mut out)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.matches(PROMPT).count(), 2);
+        assert_eq!(cpu.get_state().v[0], 5);
+    }
+}
@@ -0,0 +1,675 @@
+//! Live debug panel for the GUI.
+//!
+//! The panel shows the CPU's registers, timers and stack, and a scrolling
+//! disassembly around PC, drawn with the bitmap font beside the game. It
+//! takes a snapshot of the CPU each frame and has clickable buttons for
+//! pausing, single stepping and stepping back. The view button swaps the
+//! disassembly for the ROM's cheats, which can be clicked to turn them on
+//! and off, then for the ROM's variables with their live values, and then
+//! for the latest log messages.
+
+use super::VariableTable;
+use crate::analysis::disassemble;
+use crate::cheats::CheatList;
+use crate::emulator::{Cpu, CpuState};
+use crate::graphics::font::{draw_text, fill_rect, text_width, CHAR_ADVANCE, LINE_HEIGHT};
+use crate::graphics::Color;
+use crate::hardware::Hardware;
+use crate::logging::LogRecord;
+
+/// Width of the panel in pixels.
+pub const PANEL_WIDTH: usize = 128;
+
+/// Height of the panel in pixels.
+pub const PANEL_HEIGHT: usize = 128;
+
+/// Instructions shown before and after PC in the disassembly.
+const DISASSEMBLY_CONTEXT: u16 = 5;
+
+/// Top of the first disassembly line.
+const DISASSEMBLY_TOP: usize = 8 * LINE_HEIGHT + 2;
+
+/// Rows in the disassembly, cheat list, variable list and log.
+const LIST_ROWS: usize = 2 * DISASSEMBLY_CONTEXT as usize + 1;
+
+/// Log records the log view shows.
+pub const LOG_LINES: usize = LIST_ROWS;
+
+/// Top of the button row.
+const BUTTON_TOP: usize = PANEL_HEIGHT - LINE_HEIGHT - 1;
+
+/// Gap between the panel edge and its text.
+const MARGIN: usize = 2;
+
+/// Characters that fit on one line of the panel.
+const LINE_CHARS: usize = (PANEL_WIDTH - MARGIN) / CHAR_ADVANCE;
+
+/// A clickable button on the panel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanelButton {
+    /// Pauses or resumes execution.
+    Pause,
+
+    /// Executes one instruction while paused.
+    Step,
+
+    /// Undoes the last stepped instruction.
+    Back,
+
+    /// Switches between the disassembly, the cheat list, the variables and
+    /// the log.
+    Cheats,
+
+    /// Turns the cheat at this index on or off.
+    Cheat(usize),
+}
+
+impl PanelButton {
+    /// The buttons on the bottom row.
+    const ROW: [PanelButton; 4] = [
+        PanelButton::Pause,
+        PanelButton::Step,
+        PanelButton::Back,
+        PanelButton::Cheats,
+    ];
+
+    /// Gets the button's label, given the view the view button leads to.
+    fn label(&self, paused: bool, next: PanelView) -> &'static str {
+        match self {
+            PanelButton::Pause if paused => "[RUN]",
+            PanelButton::Pause => "[PAUSE]",
+            PanelButton::Step => "[STEP]",
+            PanelButton::Back => "[BACK]",
+            PanelButton::Cheats => match next {
+                PanelView::Disassembly => "[ASM]",
+                PanelView::Cheats => "[CHEAT]",
+                PanelView::Variables => "[VARS]",
+                PanelView::Log => "[LOG]",
+            },
+            PanelButton::Cheat(_) => "",
+        }
+    }
+
+    /// Gets the left edge of the button, relative to the panel.
+    fn left(&self) -> usize {
+        match self {
+            PanelButton::Pause | PanelButton::Cheat(_) => MARGIN,
+            PanelButton::Step => MARGIN + 8 * CHAR_ADVANCE,
+            PanelButton::Back => MARGIN + 15 * CHAR_ADVANCE,
+            PanelButton::Cheats => MARGIN + 22 * CHAR_ADVANCE,
+        }
+    }
+}
+
+/// What the lower part of the panel lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PanelView {
+    /// Disassembly around PC.
+    #[default]
+    Disassembly,
+
+    /// The ROM's cheats.
+    Cheats,
+
+    /// The ROM's variables.
+    Variables,
+
+    /// The latest log messages.
+    Log,
+}
+
+/// A line of disassembly around PC.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PanelLine {
+    /// Address of the instruction.
+    pub address: u16,
+
+    /// The instruction word, or `None` if it lies outside memory.
+    pub opcode: Option<u16>,
+
+    /// Mnemonic for the instruction.
+    pub mnemonic: String,
+}
+
+/// A cheat listed on the panel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PanelCheat {
+    /// Name of the cheat.
+    pub name: String,
+
+    /// Whether it is on.
+    pub enabled: bool,
+}
+
+/// A variable listed on the panel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PanelVariable {
+    /// Name of the variable.
+    pub name: String,
+
+    /// Formatted value, or `?` if it couldn't be read.
+    pub value: String,
+}
+
+/// Snapshot of the CPU drawn as a side panel.
+#[derive(Debug, Clone)]
+pub struct DebugPanel {
+    /// Registers, timers and stack.
+    state: CpuState,
+
+    /// Disassembly around PC, in address order.
+    lines: Vec<PanelLine>,
+
+    /// Whether execution is paused.
+    paused: bool,
+
+    /// The ROM's cheats.
+    cheats: Vec<PanelCheat>,
+
+    /// The ROM's variables.
+    variables: Vec<PanelVariable>,
+
+    /// The latest log messages, oldest first.
+    log: Vec<String>,
+
+    /// What the lower part of the panel lists.
+    view: PanelView,
+}
+
+impl DebugPanel {
+    /// Creates a panel with an empty snapshot.
+    pub fn new() -> Self {
+        Self {
+            state: Cpu::new().get_state(),
+            lines: Vec::new(),
+            paused: false,
+            cheats: Vec::new(),
+            variables: Vec::new(),
+            log: Vec::new(),
+            view: PanelView::default(),
+        }
+    }
+
+    /// Takes a new snapshot of the CPU.
+    ///
+    /// # Arguments
+    /// * `cpu` - CPU to inspect
+    /// * `paused` - Whether execution is paused, which picks the pause button's label
+    pub fn update<H: Hardware>(&mut self, cpu: &Cpu<H>, paused: bool) {
+        self.state = cpu.get_state();
+        self.paused = paused;
+
+        // Keep the window on PC's alignment so it decodes the same words the CPU will
+        let pc = self.state.pc;
+        let first = pc.saturating_sub(2 * DISASSEMBLY_CONTEXT);
+        let first = first + (pc - first) % 2;
+        self.lines = (0..=2 * DISASSEMBLY_CONTEXT)
+            .map(|i| first + 2 * i)
+            .map(|address| {
+                let opcode = cpu.get_memory().read_word(address).ok();
+                PanelLine {
+                    address,
+                    opcode,
+                    mnemonic: opcode.map_or_else(|| "??".to_string(), disassemble),
+                }
+            })
+            .collect();
+    }
+
+    /// Takes a new snapshot of the cheats.
+    pub fn update_cheats(&mut self, cheats: &CheatList) {
+        self.cheats = cheats
+            .cheats()
+            .iter()
+            .map(|cheat| PanelCheat {
+                name: cheat.name.clone(),
+                enabled: cheat.enabled,
+            })
+            .collect();
+    }
+
+    /// Takes a new snapshot of the variables.
+    pub fn update_variables<H: Hardware>(&mut self, variables: &VariableTable, cpu: &Cpu<H>) {
+        self.variables = variables
+            .iter()
+            .map(|variable| PanelVariable {
+                name: variable.name.clone(),
+                value: variable.format(cpu.get_memory()),
+            })
+            .collect();
+    }
+
+    /// Takes a new snapshot of the log, keeping the last [`LOG_LINES`] records.
+    pub fn update_log(&mut self, records: &[LogRecord]) {
+        let skip = records.len().saturating_sub(LOG_LINES);
+        self.log = records[skip..]
+            .iter()
+            .map(|record| {
+                let level = record.level.as_str();
+                format!("{} {} {}", &level[..1], record.target, record.message)
+            })
+            .collect();
+    }
+
+    /// Switches between the disassembly and the cheat list.
+    ///
+    /// # Returns
+    /// Whether the cheat list is now shown.
+    pub fn toggle_cheats(&mut self) -> bool {
+        self.view = if self.view == PanelView::Cheats {
+            PanelView::Disassembly
+        } else {
+            PanelView::Cheats
+        };
+        self.showing_cheats()
+    }
+
+    /// Checks if the cheat list is shown instead of the disassembly.
+    pub fn showing_cheats(&self) -> bool {
+        self.view == PanelView::Cheats
+    }
+
+    /// Gets what the lower part of the panel lists.
+    pub fn view(&self) -> PanelView {
+        self.view
+    }
+
+    /// Moves on to the next view: the disassembly, the cheats, then the
+    /// variables if the ROM has any and the log if anything was logged.
+    ///
+    /// # Returns
+    /// The view now shown.
+    pub fn next_view(&mut self) -> PanelView {
+        self.view = self.view_after();
+        self.view
+    }
+
+    /// Gets the view [`next_view`](Self::next_view) would switch to.
+    fn view_after(&self) -> PanelView {
+        match self.view {
+            PanelView::Disassembly => PanelView::Cheats,
+            PanelView::Cheats if !self.variables.is_empty() => PanelView::Variables,
+            PanelView::Cheats | PanelView::Variables if !self.log.is_empty() => PanelView::Log,
+            PanelView::Cheats | PanelView::Variables | PanelView::Log => PanelView::Disassembly,
+        }
+    }
+
+    /// Gets the CPU state from the last snapshot.
+    pub fn state(&self) -> &CpuState {
+        &self.state
+    }
+
+    /// Gets the disassembly from the last snapshot, in address order.
+    pub fn lines(&self) -> &[PanelLine] {
+        &self.lines
+    }
+
+    /// Finds the button under a position relative to the panel's top left.
+    pub fn button_at(&self, x: usize, y: usize) -> Option<PanelButton> {
+        if self.showing_cheats() && y >= DISASSEMBLY_TOP && x < PANEL_WIDTH {
+            let index = (y - DISASSEMBLY_TOP) / LINE_HEIGHT;
+            if index < self.cheats.len().min(LIST_ROWS) {
+                return Some(PanelButton::Cheat(index));
+            }
+        }
+        if !(BUTTON_TOP..BUTTON_TOP + LINE_HEIGHT).contains(&y) {
+            return None;
+        }
+        PanelButton::ROW.into_iter().find(|button| {
+            let width = text_width(button.label(self.paused, self.view_after()));
+            (button.left()..button.left() + width).contains(&x)
+        })
+    }
+
+    /// Draws the panel into an RGBA frame.
+    ///
+    /// # Arguments
+    /// * `frame` - RGBA pixel data, four bytes per pixel
+    /// * `width` - Frame width in pixels
+    /// * `left` - Left edge of the panel in the frame
+    /// * `foreground` - Text color
+    /// * `background` - Panel color
+    pub fn render(
+        &self,
+        frame: &mut [u8],
+        width: usize,
+        left: usize,
+        foreground: Color,
+        background: Color,
+    ) {
+        fill_rect(frame, width, left, 0, PANEL_WIDTH, PANEL_HEIGHT, background);
+        fill_rect(frame, width, left, 0, 1, PANEL_HEIGHT, foreground);
+
+        let state = &self.state;
+        let mut text = vec![
+            format!("PC {:04X}  I {:04X}  SP {:X}", state.pc, state.i, state.sp),
+            format!("DT {:02X}  ST {:02X}", state.delay_timer, state.sound_timer),
+        ];
+        for (row, values) in state.v.chunks(4).enumerate() {
+            let registers: Vec<String> = values
+                .iter()
+                .enumerate()
+                .map(|(i, value)| format!("V{:X} {:02X}", row * 4 + i, value))
+                .collect();
+            text.push(registers.join(" "));
+        }
+        let stack: Vec<String> = state
+            .stack_contents
+            .iter()
+            .rev()
+            .map(|address| format!("{:03X}", address))
+            .collect();
+        text.push(if stack.is_empty() {
+            "STACK -".to_string()
+        } else {
+            format!("STACK {}", stack.join(" "))
+        });
+        for (row, line) in text.iter().enumerate() {
+            let x = left + MARGIN;
+            draw_text(
+                frame,
+                width,
+                x,
+                MARGIN + row * LINE_HEIGHT,
+                &clip(line),
+                foreground,
+            );
+        }
+
+        match self.view {
+            PanelView::Disassembly => {}
+            PanelView::Cheats => self.render_cheats(frame, width, left, foreground),
+            PanelView::Variables => self.render_variables(frame, width, left, foreground),
+            PanelView::Log => {
+                for (row, line) in self.log.iter().enumerate() {
+                    draw_text(
+                        frame,
+                        width,
+                        left + MARGIN,
+                        DISASSEMBLY_TOP + row * LINE_HEIGHT,
+                        &clip(line),
+                        foreground,
+                    );
+                }
+            }
+        }
+        let lines = if self.view == PanelView::Disassembly {
+            &self.lines[..]
+        } else {
+            &[][..]
+        };
+        for (row, line) in lines.iter().enumerate() {
+            let y = DISASSEMBLY_TOP + row * LINE_HEIGHT;
+            let opcode = line
+                .opcode
+                .map_or_else(|| "----".to_string(), |opcode| format!("{:04X}", opcode));
+            let text = clip(&format!(
+                "{:03X} {} {}",
+                line.address, opcode, line.mnemonic
+            ));
+            if line.address == state.pc {
+                fill_rect(
+                    frame,
+                    width,
+                    left + 1,
+                    y - 1,
+                    PANEL_WIDTH - 1,
+                    LINE_HEIGHT + 1,
+                    foreground,
+                );
+                draw_text(frame, width, left + MARGIN, y, &text, background);
+            } else {
+                draw_text(frame, width, left + MARGIN, y, &text, foreground);
+            }
+        }
+
+        for button in PanelButton::ROW {
+            let x = left + button.left();
+            draw_text(
+                frame,
+                width,
+                x,
+                BUTTON_TOP,
+                button.label(self.paused, self.view_after()),
+                foreground,
+            );
+        }
+    }
+
+    /// Draws the cheat list where the disassembly goes.
+    fn render_cheats(&self, frame: &mut [u8], width: usize, left: usize, foreground: Color) {
+        if self.cheats.is_empty() {
+            draw_text(
+                frame,
+                width,
+                left + MARGIN,
+                DISASSEMBLY_TOP,
+                "NO CHEATS",
+                foreground,
+            );
+        }
+        for (row, cheat) in self.cheats.iter().take(LIST_ROWS).enumerate() {
+            let mark = if cheat.enabled { "[X]" } else { "[ ]" };
+            draw_text(
+                frame,
+                width,
+                left + MARGIN,
+                DISASSEMBLY_TOP + row * LINE_HEIGHT,
+                &clip(&format!("{} {}", mark, cheat.name)),
+                foreground,
+            );
+        }
+    }
+}
+
+impl DebugPanel {
+    /// Draws the variables and their values where the disassembly goes.
+    fn render_variables(&self, frame: &mut [u8], width: usize, left: usize, foreground: Color) {
+        if self.variables.is_empty() {
+            draw_text(
+                frame,
+                width,
+                left + MARGIN,
+                DISASSEMBLY_TOP,
+                "NO VARIABLES",
+                foreground,
+            );
+        }
+        let name_width = self
+            .variables
+            .iter()
+            .map(|variable| variable.name.len())
+            .max()
+            .unwrap_or(0)
+            .min(LINE_CHARS / 2);
+        for (row, variable) in self.variables.iter().take(LIST_ROWS).enumerate() {
+            let name: String = variable.name.chars().take(name_width).collect();
+            draw_text(
+                frame,
+                width,
+                left + MARGIN,
+                DISASSEMBLY_TOP + row * LINE_HEIGHT,
+                &clip(&format!("{:<name_width$} {}", name, variable.value)),
+                foreground,
+            );
+        }
+    }
+}
+
+impl Default for DebugPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Cuts text down to one line of the panel.
+fn clip(text: &str) -> String {
+    text.chars().take(LINE_CHARS).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cheats::{Cheat, CheatKind};
+
+    #[test]
+    fn test_disassembly_around_pc() {
+        let mut cpu = Cpu::new();
+        cpu.load_rom(&[0x60, 0x05, 0x61, 0x06, 0x22, 0x08]).unwrap();
+        cpu.cycle().unwrap();
+
+        let mut panel = DebugPanel::new();
+        panel.update(&cpu, false);
+        assert_eq!(panel.state().pc, 0x202);
+        assert_eq!(panel.state().v[0], 5);
+
+        let lines = panel.lines();
+        assert_eq!(lines.len(), 2 * DISASSEMBLY_CONTEXT as usize + 1);
+        let current = &lines[DISASSEMBLY_CONTEXT as usize];
+        assert_eq!(current.address, 0x202);
+        assert_eq!(current.opcode, Some(0x6106));
+        assert_eq!(current.mnemonic, "LD V1, 0x06");
+        assert_eq!(
+            lines[DISASSEMBLY_CONTEXT as usize + 1].mnemonic,
+            "CALL 0x208"
+        );
+    }
+
+    #[test]
+    fn test_disassembly_at_memory_edges() {
+        // At the start of memory the window is cut short before PC
+        let mut cpu = Cpu::new();
+        cpu.load_rom(&[0x10, 0x02]).unwrap();
+        cpu.cycle().unwrap();
+        let mut panel = DebugPanel::new();
+        panel.update(&cpu, true);
+        assert_eq!(panel.lines()[0].address, 0x000);
+
+        // Near the end of memory the window runs past it
+        let mut cpu = Cpu::new();
+        cpu.load_rom(&[0x1F, 0xFE]).unwrap();
+        cpu.cycle().unwrap();
+        panel.update(&cpu, true);
+        let last = panel.lines().last().unwrap();
+        assert_eq!(last.opcode, None);
+        assert_eq!(last.mnemonic, "??");
+    }
+
+    #[test]
+    fn test_buttons() {
+        let mut panel = DebugPanel::new();
+        panel.update(&Cpu::new(), false);
+
+        assert_eq!(
+            panel.button_at(MARGIN, BUTTON_TOP),
+            Some(PanelButton::Pause)
+        );
+        let step = PanelButton::Step.left();
+        assert_eq!(
+            panel.button_at(step + 1, BUTTON_TOP + 2),
+            Some(PanelButton::Step)
+        );
+        assert_eq!(panel.button_at(step + 1, BUTTON_TOP - 1), None);
+        assert_eq!(
+            panel.button_at(PanelButton::Back.left(), BUTTON_TOP),
+            Some(PanelButton::Back)
+        );
+        assert_eq!(panel.button_at(PANEL_WIDTH - 1, BUTTON_TOP), None);
+    }
+
+    #[test]
+    fn test_cheat_list() {
+        let cheats = CheatList::new()
+            .with_cheat(Cheat::new("Lives", "300:09", CheatKind::Freeze).unwrap())
+            .with_cheat(Cheat::new("Skip", "200:1210", CheatKind::Patch).unwrap());
+        let mut panel = DebugPanel::new();
+        panel.update_cheats(&cheats);
+
+        // Cheat rows only take clicks while the list is shown
+        assert_eq!(panel.button_at(MARGIN, DISASSEMBLY_TOP), None);
+        assert_eq!(
+            panel.button_at(PanelButton::Cheats.left(), BUTTON_TOP),
+            Some(PanelButton::Cheats)
+        );
+        assert!(panel.toggle_cheats());
+        assert_eq!(
+            panel.button_at(MARGIN, DISASSEMBLY_TOP + LINE_HEIGHT),
+            Some(PanelButton::Cheat(1))
+        );
+        assert_eq!(
+            panel.button_at(MARGIN, DISASSEMBLY_TOP + 2 * LINE_HEIGHT),
+            None
+        );
+
+        let mut frame = vec![0; PANEL_WIDTH * PANEL_HEIGHT * 4];
+        panel.render(&mut frame, PANEL_WIDTH, 0, Color::WHITE, Color::BLACK);
+        assert!(!panel.toggle_cheats());
+    }
+
+    #[test]
+    fn test_variable_view() {
+        let mut cpu = Cpu::new();
+        cpu.memory_mut().write_byte(0x300, 7).unwrap();
+        let mut panel = DebugPanel::new();
+
+        // Without variables the view button skips the variable list
+        assert_eq!(panel.next_view(), PanelView::Cheats);
+        assert_eq!(panel.next_view(), PanelView::Disassembly);
+
+        let variables = VariableTable::parse("[variables]\nlives = 0x300").unwrap();
+        panel.update_variables(&variables, &cpu);
+        assert_eq!(panel.next_view(), PanelView::Cheats);
+        assert_eq!(panel.next_view(), PanelView::Variables);
+        assert_eq!(
+            panel.button_at(PanelButton::Cheats.left(), BUTTON_TOP),
+            Some(PanelButton::Cheats)
+        );
+        let mut frame = vec![0; PANEL_WIDTH * PANEL_HEIGHT * 4];
+        panel.render(&mut frame, PANEL_WIDTH, 0, Color::WHITE, Color::BLACK);
+        assert_eq!(panel.next_view(), PanelView::Disassembly);
+    }
+
+    #[test]
+    fn test_log_view() {
+        let mut panel = DebugPanel::new();
+        let records: Vec<LogRecord> = (0..LOG_LINES + 2)
+            .map(|i| LogRecord {
+                level: tracing::Level::WARN,
+                target: "audio".to_string(),
+                message: format!("message {}", i),
+            })
+            .collect();
+        panel.update_log(&records);
+        assert_eq!(panel.log.len(), LOG_LINES);
+        assert_eq!(panel.log[0], "W audio message 2");
+
+        // The log comes after the cheats once something was logged
+        assert_eq!(panel.next_view(), PanelView::Cheats);
+        assert_eq!(panel.next_view(), PanelView::Log);
+        let mut frame = vec![0; PANEL_WIDTH * PANEL_HEIGHT * 4];
+        panel.render(&mut frame, PANEL_WIDTH, 0, Color::WHITE, Color::BLACK);
+        assert_eq!(panel.next_view(), PanelView::Disassembly);
+    }
+
+    #[test]
+    fn test_render_highlights_pc() {
+        let mut cpu = Cpu::new();
+        cpu.load_rom(&[0x00, 0xE0]).unwrap();
+        let mut panel = DebugPanel::new();
+        panel.update(&cpu, true);
+
+        let width = 200;
+        let left = width - PANEL_WIDTH;
+        let mut frame = vec![0; width * PANEL_HEIGHT * 4];
+        panel.render(&mut frame, width, left, Color::WHITE, Color::BLACK);
+
+        let lit = |x: usize, y: usize| frame[(y * width + x) * 4] > 0;
+        // The PC row is drawn inverted: lit everywhere except the text
+        let pc_row = DISASSEMBLY_TOP + DISASSEMBLY_CONTEXT as usize * LINE_HEIGHT;
+        assert!(lit(left + PANEL_WIDTH - 1, pc_row));
+        assert!(!lit(left + PANEL_WIDTH - 1, pc_row - LINE_HEIGHT));
+        // Nothing is drawn left of the panel
+        assert!(!lit(left - 1, 0));
+        assert!(lit(left, 0));
+    }
+}
@@ -0,0 +1,406 @@
+//! Runtime profiling.
+//!
+//! The call profiler watches the CPU's shadow call stack after every cycle
+//! to measure call depth, how often each subroutine is called and how many
+//! clock cycles are spent inside it (including nested calls).
+//!
+//! The instruction profiler counts executions per address and per opcode
+//! class, and how long the program spends blocked on input or waiting for
+//! the display. Results can be exported as JSON or CSV, or printed as a
+//! heat map over the disassembly.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::analysis::{disassemble, opcode_pattern};
+use crate::emulator::timing::vip_display_wait;
+use crate::emulator::{Cpu, Memory, TimingModel};
+use crate::hardware::Hardware;
+
+/// Opcode class used for words that don't decode.
+const UNKNOWN_CLASS: &str = "????";
+
+/// Shading from coldest to hottest for heat maps.
+const HEAT_BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// ANSI colors from coldest to hottest for heat maps.
+const HEAT_COLORS: [&str; 5] = ["34", "36", "32", "33", "31"];
+
+/// Call statistics for one subroutine.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SubroutineStats {
+    /// Number of times the subroutine was called.
+    pub calls: u64,
+
+    /// Clock cycles spent in the subroutine and anything it called.
+    pub cycles: u64,
+}
+
+/// Collects subroutine statistics while a program runs.
+#[derive(Debug, Clone, Default)]
+pub struct CallProfiler {
+    /// Call depth seen after the previous cycle.
+    depth: usize,
+
+    /// Deepest call depth seen.
+    max_depth: usize,
+
+    /// Total clock cycles recorded.
+    total_cycles: u64,
+
+    /// Statistics by subroutine entry point.
+    subroutines: BTreeMap<u16, SubroutineStats>,
+}
+
+impl CallProfiler {
+    /// Creates an empty profiler.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the cycle that just ran.
+    ///
+    /// # Arguments
+    /// * `cpu` - The CPU after the cycle
+    /// * `cost` - Clock cycles the cycle took
+    pub fn record<H: Hardware>(&mut self, cpu: &Cpu<H>, cost: u32) {
+        let calls = cpu.call_stack();
+
+        if calls.len() > self.depth {
+            for call in &calls[self.depth..] {
+                self.subroutines.entry(call.target).or_default().calls += 1;
+            }
+        }
+        self.depth = calls.len();
+        self.max_depth = self.max_depth.max(self.depth);
+        self.total_cycles += cost as u64;
+
+        // Recursive subroutines only count once per cycle
+        for (i, call) in calls.iter().enumerate() {
+            if calls[..i].iter().all(|outer| outer.target != call.target) {
+                self.subroutines.entry(call.target).or_default().cycles += cost as u64;
+            }
+        }
+    }
+
+    /// Gets the deepest call depth seen.
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Gets the total clock cycles recorded.
+    pub fn total_cycles(&self) -> u64 {
+        self.total_cycles
+    }
+
+    /// Gets statistics for every subroutine that was called.
+    pub fn subroutines(&self) -> &BTreeMap<u16, SubroutineStats> {
+        &self.subroutines
+    }
+
+    /// Gets the subroutines with the most cycles, hottest first.
+    pub fn hot_subroutines(&self, count: usize) -> Vec<(u16, SubroutineStats)> {
+        let mut entries: Vec<_> = self.subroutines.iter().map(|(&a, &s)| (a, s)).collect();
+        entries.sort_by(|a, b| b.1.cycles.cmp(&a.1.cycles).then(a.0.cmp(&b.0)));
+        entries.truncate(count);
+        entries
+    }
+}
+
+/// Execution count for one address.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AddressProfile {
+    /// Instruction address.
+    pub address: u16,
+
+    /// Instruction word at the address when the profile was taken.
+    pub opcode: u16,
+
+    /// Disassembly of the instruction.
+    pub instruction: String,
+
+    /// Number of times it ran.
+    pub count: u64,
+}
+
+/// Exported profile data.
+#[derive(Debug, Clone, Serialize)]
+pub struct InstructionProfile {
+    /// Instructions executed.
+    pub instructions: u64,
+
+    /// Clock cycles spent blocked on FX0A.
+    pub key_wait_cycles: u64,
+
+    /// Clock cycles draws spent waiting for the display interrupt.
+    pub display_wait_cycles: u64,
+
+    /// Executions per opcode class (e.g. "DXYN").
+    pub opcode_classes: BTreeMap<&'static str, u64>,
+
+    /// Executions per address, in address order.
+    pub addresses: Vec<AddressProfile>,
+}
+
+/// Counts instruction executions and wait time while a program runs.
+#[derive(Debug, Clone, Default)]
+pub struct InstructionProfiler {
+    /// Executions by address.
+    address_counts: BTreeMap<u16, u64>,
+
+    /// Executions by opcode class.
+    class_counts: BTreeMap<&'static str, u64>,
+
+    /// Instruction count seen after the previous cycle.
+    last_instruction_count: u64,
+
+    /// Instructions recorded.
+    instructions: u64,
+
+    /// Clock cycles spent blocked on FX0A.
+    key_wait_cycles: u64,
+
+    /// Clock cycles draws spent waiting for the display interrupt.
+    display_wait_cycles: u64,
+}
+
+impl InstructionProfiler {
+    /// Creates an empty profiler.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the cycle that just ran.
+    ///
+    /// # Arguments
+    /// * `cpu` - The CPU after the cycle
+    /// * `pc` - The program counter before the cycle
+    pub fn record<H: Hardware>(&mut self, cpu: &Cpu<H>, pc: u16) {
+        let count = cpu.instruction_count();
+        let executed = count != self.last_instruction_count;
+        self.last_instruction_count = count;
+
+        if !executed {
+            // Nothing ran: either blocked on a key or stopped at a breakpoint
+            if cpu.is_waiting_for_key() {
+                self.key_wait_cycles += cpu.last_cost() as u64;
+            }
+            return;
+        }
+
+        let opcode = cpu.get_memory().read_word(pc).unwrap_or(0);
+        *self.address_counts.entry(pc).or_default() += 1;
+        let class = opcode_pattern(opcode).map_or(UNKNOWN_CLASS, |(pattern, _)| pattern);
+        *self.class_counts.entry(class).or_default() += 1;
+        self.instructions += 1;
+
+        if cpu.timing_model() == TimingModel::CosmacVip && opcode & 0xF000 == 0xD000 {
+            let start = cpu.elapsed_cycles() - cpu.last_cost() as u64;
+            self.display_wait_cycles += vip_display_wait(start) as u64;
+        }
+    }
+
+    /// Gets the number of instructions recorded.
+    pub fn instructions(&self) -> u64 {
+        self.instructions
+    }
+
+    /// Gets the clock cycles spent blocked on FX0A.
+    pub fn key_wait_cycles(&self) -> u64 {
+        self.key_wait_cycles
+    }
+
+    /// Gets the clock cycles draws spent waiting for the display interrupt.
+    pub fn display_wait_cycles(&self) -> u64 {
+        self.display_wait_cycles
+    }
+
+    /// Gets execution counts by address.
+    pub fn address_counts(&self) -> &BTreeMap<u16, u64> {
+        &self.address_counts
+    }
+
+    /// Gets the most executed opcode classes, most common first.
+    pub fn top_classes(&self, count: usize) -> Vec<(&'static str, u64)> {
+        let mut entries: Vec<_> = self.class_counts.iter().map(|(&k, &v)| (k, v)).collect();
+        entries.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        entries.truncate(count);
+        entries
+    }
+
+    /// Builds the exportable profile, disassembling from `memory`.
+    pub fn profile(&self, memory: &Memory) -> InstructionProfile {
+        let addresses = self
+            .address_counts
+            .iter()
+            .map(|(&address, &count)| {
+                let opcode = memory.read_word(address).unwrap_or(0);
+                AddressProfile {
+                    address,
+                    opcode,
+                    instruction: disassemble(opcode),
+                    count,
+                }
+            })
+            .collect();
+
+        InstructionProfile {
+            instructions: self.instructions,
+            key_wait_cycles: self.key_wait_cycles,
+            display_wait_cycles: self.display_wait_cycles,
+            opcode_classes: self.class_counts.clone(),
+            addresses,
+        }
+    }
+
+    /// Formats the profile as pretty-printed JSON.
+    pub fn to_json(&self, memory: &Memory) -> String {
+        serde_json::to_string_pretty(&self.profile(memory)).unwrap_or_default()
+    }
+
+    /// Formats per-address counts as CSV with a header row.
+    pub fn to_csv(&self, memory: &Memory) -> String {
+        let mut out = String::from("address,opcode,instruction,count\n");
+        for entry in self.profile(memory).addresses {
+            let _ = writeln!(
+                out,
+                "0x{:04X},0x{:04X},\"{}\",{}",
+                entry.address, entry.opcode, entry.instruction, entry.count
+            );
+        }
+        out
+    }
+
+    /// Formats executed instructions as a disassembly listing with heat bars.
+    ///
+    /// Gaps between executed addresses are marked with `...`. With `color`,
+    /// ANSI escape codes tint each line from blue (cold) to red (hot).
+    pub fn heat_map(&self, memory: &Memory, color: bool) -> String {
+        let max = self.address_counts.values().copied().max().unwrap_or(0);
+        let total = self.instructions.max(1) as f64;
+        let mut out = String::new();
+        let mut next = None;
+
+        for entry in self.profile(memory).addresses {
+            if next.is_some_and(|next| next != entry.address) {
+                out.push_str("         ...\n");
+            }
+            next = Some(entry.address.wrapping_add(2));
+
+            let heat = entry.count as f64 / max as f64;
+            let bar_length = ((heat * 16.0).ceil() as usize).max(1);
+            let block = HEAT_BLOCKS[((heat * 7.0).round() as usize).min(7)];
+            let bar: String = std::iter::repeat_n(block, bar_length).collect();
+            let line = format!(
+                "0x{:04X}: {:04X}  {:<20} {:<16} {:>10} ({:5.1}%)",
+                entry.address,
+                entry.opcode,
+                entry.instruction,
+                bar,
+                entry.count,
+                entry.count as f64 * 100.0 / total
+            );
+
+            if color {
+                let shade = HEAT_COLORS[((heat * 4.0).round() as usize).min(4)];
+                let _ = writeln!(out, "\x1b[{}m{}\x1b[0m", shade, line);
+            } else {
+                let _ = writeln!(out, "{}", line);
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_call_profiler() {
+        let mut cpu = Cpu::new();
+        // 0x200: CALL 0x208; CALL 0x208; JP 0x204
+        // 0x208: CALL 0x20C; RET
+        // 0x20C: RET
+        cpu.load_rom(&[
+            0x22, 0x08, 0x22, 0x08, 0x12, 0x04, 0x00, 0x00, 0x22, 0x0C, 0x00, 0xEE, 0x00, 0xEE,
+        ])
+        .unwrap();
+
+        let mut profiler = CallProfiler::new();
+        for _ in 0..10 {
+            cpu.cycle().unwrap();
+            profiler.record(&cpu, cpu.last_cost());
+        }
+
+        assert_eq!(profiler.max_depth(), 2);
+        assert_eq!(profiler.total_cycles(), 10);
+
+        let outer = profiler.subroutines()[&0x208];
+        let inner = profiler.subroutines()[&0x20C];
+        assert_eq!(outer.calls, 2);
+        assert_eq!(inner.calls, 2);
+        // The call into 0x208, its call to 0x20C and the inner RET, twice
+        assert_eq!(outer.cycles, 6);
+        assert_eq!(inner.cycles, 2);
+        assert_eq!(profiler.hot_subroutines(1)[0].0, 0x208);
+    }
+
+    /// Runs a ROM under the instruction profiler.
+    fn profile_rom(rom: &[u8], cycles: usize, cpu: &mut Cpu) -> InstructionProfiler {
+        cpu.load_rom(rom).unwrap();
+        let mut profiler = InstructionProfiler::new();
+        for _ in 0..cycles {
+            let pc = cpu.get_state().pc;
+            cpu.cycle().unwrap();
+            profiler.record(cpu, pc);
+        }
+        profiler
+    }
+
+    #[test]
+    fn test_instruction_profiler() {
+        let mut cpu = Cpu::new();
+        // LD V0, 0; ADD V0, 1; JP 0x202
+        let profiler = profile_rom(&[0x60, 0x00, 0x70, 0x01, 0x12, 0x02], 9, &mut cpu);
+
+        assert_eq!(profiler.instructions(), 9);
+        assert_eq!(profiler.address_counts()[&0x200], 1);
+        assert_eq!(profiler.address_counts()[&0x202], 4);
+        assert_eq!(profiler.address_counts()[&0x204], 4);
+        assert_eq!(profiler.top_classes(1), vec![("1NNN", 4)]);
+
+        let csv = profiler.to_csv(cpu.get_memory());
+        assert!(
+            csv.starts_with("address,opcode,instruction,count\n0x0200,0x6000,\"LD V0, 0x00\",1\n")
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&profiler.to_json(cpu.get_memory())).unwrap();
+        assert_eq!(json["instructions"], 9);
+        assert_eq!(json["opcode_classes"]["7XNN"], 4);
+        assert_eq!(json["addresses"][1]["address"], 0x202);
+
+        let heat_map = profiler.heat_map(cpu.get_memory(), false);
+        assert_eq!(heat_map.lines().count(), 3);
+        assert!(heat_map.contains("JP 0x202"));
+        assert!(heat_map.contains("████"));
+    }
+
+    #[test]
+    fn test_wait_times() {
+        let mut cpu = Cpu::new();
+        // LD V0, K
+        let profiler = profile_rom(&[0xF0, 0x0A], 5, &mut cpu);
+        assert_eq!(profiler.instructions(), 1);
+        assert_eq!(profiler.key_wait_cycles(), 4);
+
+        let mut cpu = Cpu::new();
+        cpu.set_timing_model(TimingModel::CosmacVip);
+        // DRW V0, V0, 1; JP 0x200
+        let profiler = profile_rom(&[0xD0, 0x01, 0x12, 0x00], 4, &mut cpu);
+        assert!(profiler.display_wait_cycles() > 0);
+        assert_eq!(profiler.top_classes(2), vec![("1NNN", 2), ("DXYN", 2)]);
+    }
+}
@@ -0,0 +1,292 @@
+//! Fault report bundles.
+//!
+//! When a program faults (unknown opcode, stack fault, bad memory access),
+//! a report directory can be written that captures what led up to it: the
+//! recent instruction trace, registers and stacks, memory around PC and I,
+//! a snapshot of the display (as an image and as braille text), the latest
+//! log messages and the configuration in use.
+
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{backtrace_with_symbols, format_backtrace, SymbolTable};
+use crate::emulator::{Cpu, Memory};
+use crate::error::{EmulatorError, Result};
+use crate::frontend::{save_config, EmulatorConfig};
+use crate::graphics::{PixelRenderer, ScreenshotFormat};
+use crate::hardware::{braille_art, Hardware};
+use crate::logging::{self, APP};
+
+/// Instructions kept in the CPU trace when fault reports are enabled.
+pub const DEFAULT_TRACE_LENGTH: usize = 64;
+
+/// Log records included in a fault report.
+const REPORT_LOG_LENGTH: usize = 32;
+
+/// Bytes dumped either side of PC and I.
+const HEXDUMP_RADIUS: u16 = 64;
+
+/// Formats the text part of a fault report.
+///
+/// # Arguments
+/// * `error` - The fault that stopped execution
+/// * `cpu` - The CPU as it was when the fault happened
+/// * `symbols` - Names for addresses in the backtrace and trace (may be empty)
+pub fn format_fault_report<H: Hardware>(
+    error: &EmulatorError,
+    cpu: &Cpu<H>,
+    symbols: &SymbolTable,
+) -> String {
+    let state = cpu.get_state();
+    // PC has usually moved past the faulting instruction; the trace knows where it was
+    let fault_pc = cpu.trace().back().map_or(state.pc, |entry| entry.pc);
+    let mut out = String::new();
+
+    let _ = writeln!(out, "Fault: {}", error.root());
+    let _ = writeln!(out, "Faulting instruction: 0x{:04X}", fault_pc);
+    let _ = writeln!(
+        out,
+        "PC: 0x{:04X}  I: 0x{:04X}  SP: {}",
+        state.pc, state.i, state.sp
+    );
+    for (row, registers) in state.v.chunks(8).enumerate() {
+        let line: Vec<String> = registers
+            .iter()
+            .enumerate()
+            .map(|(i, v)| format!("V{:X}={:02X}", row * 8 + i, v))
+            .collect();
+        let _ = writeln!(out, "{}", line.join(" "));
+    }
+    let _ = writeln!(
+        out,
+        "Timers: delay {}, sound {}",
+        state.delay_timer, state.sound_timer
+    );
+    let _ = writeln!(out, "Instructions executed: {}", state.instruction_count);
+    let stack: Vec<String> = state
+        .stack_contents
+        .iter()
+        .map(|addr| format!("0x{:04X}", addr))
+        .collect();
+    let _ = writeln!(out, "Stack: [{}]", stack.join(", "));
+
+    out.push_str("\nBacktrace:\n");
+    out.push_str(&format_backtrace(&backtrace_with_symbols(cpu, symbols)));
+
+    out.push_str("\nRecent instructions (oldest first):\n");
+    if cpu.trace().is_empty() {
+        out.push_str("  (tracing disabled)\n");
+    }
+    for entry in cpu.trace() {
+        let location = if symbols.is_empty() {
+            String::new()
+        } else {
+            format!(" <{}>", symbols.describe(entry.pc))
+        };
+        let note = entry
+            .modified_code
+            .map(|address| format!("  ; overwrites code at 0x{:04X}", address))
+            .unwrap_or_default();
+        let _ = writeln!(
+            out,
+            "  0x{:04X}{}: {:04X}  {}{}",
+            entry.pc,
+            location,
+            entry.opcode,
+            symbols.disassemble(entry.opcode),
+            note
+        );
+    }
+
+    out.push_str("\nRecent log (oldest first):\n");
+    let records = logging::recent(REPORT_LOG_LENGTH);
+    if records.is_empty() {
+        out.push_str("  (nothing logged)\n");
+    }
+    for record in records {
+        let _ = writeln!(out, "  {}", record);
+    }
+
+    out.push_str("\nDisplay:\n");
+    out.push_str(&braille_art(cpu.get_display_buffer(), cpu.display_size().0));
+
+    let _ = writeln!(out, "\nMemory around 0x{:04X}:", fault_pc);
+    out.push_str(&hexdump(cpu.get_memory(), fault_pc, HEXDUMP_RADIUS));
+    let _ = writeln!(out, "\nMemory around I (0x{:04X}):", state.i);
+    out.push_str(&hexdump(cpu.get_memory(), state.i, HEXDUMP_RADIUS));
+
+    out
+}
+
+/// Formats memory around an address as a hex dump, 16 bytes per line.
+///
+/// The line containing `center` is marked with `>`. Addresses past the
+/// end of memory are left out.
+pub fn hexdump(memory: &Memory, center: u16, radius: u16) -> String {
+    let last = (memory.size() - 1) as u16;
+    let start = center.min(last).saturating_sub(radius) & !0xF;
+    let end = center.saturating_add(radius).min(last);
+    let mut out = String::new();
+
+    for line in (start..=end).step_by(16) {
+        let marker = if (line..line + 16).contains(&center) {
+            '>'
+        } else {
+            ' '
+        };
+        let bytes: Vec<String> = (line..(line + 16).min(last + 1))
+            .map(|addr| match memory.read_byte(addr) {
+                Ok(byte) => format!("{:02X}", byte),
+                Err(_) => "??".to_string(),
+            })
+            .collect();
+        let _ = writeln!(out, "{} 0x{:04X}: {}", marker, line, bytes.join(" "));
+    }
+    out
+}
+
+/// Writes a fault report bundle into a new directory under `dir`.
+///
+/// The bundle holds `report.txt`, a display snapshot (`display.png`, or
+/// `display.ppm` without PNG support) and `config.toml`.
+///
+/// # Returns
+/// The path of the bundle directory.
+pub fn write_fault_report<H: Hardware>(
+    dir: &Path,
+    error: &EmulatorError,
+    cpu: &Cpu<H>,
+    config: &EmulatorConfig,
+) -> Result<PathBuf> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let bundle = dir.join(format!(
+        "fault-{}-{:03}",
+        timestamp.as_secs(),
+        timestamp.subsec_millis()
+    ));
+    std::fs::create_dir_all(&bundle)?;
+
+    let symbols = match &config.debug.symbols_file {
+        Some(path) => SymbolTable::load(path).unwrap_or_else(|e| {
+            tracing::warn!(target: APP, "Failed to load symbols from {}: {}", path.display(), e);
+            SymbolTable::new()
+        }),
+        None => SymbolTable::new(),
+    };
+    std::fs::write(
+        bundle.join("report.txt"),
+        format_fault_report(error, cpu, &symbols),
+    )?;
+
+    let format = ScreenshotFormat::default();
+    let mut renderer = PixelRenderer::new(config.graphics.clone())?;
+    let (width, height) = cpu.display_size();
+    renderer.resize(width, height)?;
+    renderer.render(cpu.get_display_buffer())?;
+    renderer.save_frame(
+        &bundle.join(format!("display.{}", format.extension())),
+        format,
+    )?;
+
+    save_config(config, bundle.join("config.toml"))?;
+
+    Ok(bundle)
+}
+
+/// Writes a fault report if the config asks for one and the error is a CPU fault.
+///
+/// Failures to write the report are logged rather than returned so they
+/// don't hide the original error.
+///
+/// # Returns
+/// The path of the bundle directory, if one was written.
+pub fn report_fault<H: Hardware>(
+    error: &EmulatorError,
+    cpu: &Cpu<H>,
+    config: &EmulatorConfig,
+) -> Option<PathBuf> {
+    let dir = config.debug.fault_report_dir.as_ref()?;
+    if !error.is_cpu_fault() {
+        return None;
+    }
+
+    match write_fault_report(dir, error, cpu, config) {
+        Ok(bundle) => {
+            tracing::error!(target: APP, "Fault report written to {}", bundle.display());
+            Some(bundle)
+        }
+        Err(e) => {
+            tracing::warn!(target: APP, "Failed to write fault report: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn faulted_cpu() -> (Cpu, EmulatorError) {
+        let mut cpu = Cpu::new();
+        cpu.set_trace_length(DEFAULT_TRACE_LENGTH);
+        // LD I, 0x300; LD V3, 0x42; unknown
+        cpu.load_rom(&[0xA3, 0x00, 0x63, 0x42, 0xFF, 0xFF]).unwrap();
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        let error = cpu.cycle().unwrap_err();
+        (cpu, error)
+    }
+
+    #[test]
+    fn test_format_fault_report() {
+        let (cpu, error) = faulted_cpu();
+        let report = format_fault_report(&error, &cpu, &SymbolTable::new());
+
+        assert!(report.starts_with("Fault: Unknown instruction 0xffff\n"));
+        assert!(report.contains("Faulting instruction: 0x0204\n"));
+        assert!(report.contains("I: 0x0300"));
+        assert!(report.contains("V3=42"));
+        assert!(report.contains("  0x0202: 6342  LD V3, 0x42\n  0x0204: FFFF"));
+        assert!(report.contains("> 0x0200: A3 00 63 42 FF FF"));
+        assert!(report.contains("\nRecent log (oldest first):\n"));
+
+        let symbols = [("start".to_string(), 0x200), ("data".to_string(), 0x300)]
+            .into_iter()
+            .collect();
+        let report = format_fault_report(&error, &cpu, &symbols);
+        assert!(report.contains("#0  0x0206 in main: "));
+        assert!(report.contains("  0x0200 <start>: A300  LD I, data\n"));
+        assert!(report.contains("  0x0202 <start+0x2>: 6342  LD V3, 0x42\n"));
+    }
+
+    #[test]
+    fn test_hexdump_clamps_to_memory() {
+        let memory = Memory::new();
+        let dump = hexdump(&memory, 0xFFE, 32);
+
+        assert_eq!(dump.lines().count(), 3);
+        assert!(dump.ends_with("> 0x0FF0: 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00\n"));
+    }
+
+    #[test]
+    fn test_report_fault_writes_bundle() {
+        let dir = tempfile::tempdir().unwrap();
+        let (cpu, error) = faulted_cpu();
+        let mut config = EmulatorConfig::default();
+
+        // Nothing is written unless a directory is configured
+        assert!(report_fault(&error, &cpu, &config).is_none());
+
+        config.debug.fault_report_dir = Some(dir.path().to_path_buf());
+        assert!(report_fault(&EmulatorError::RomEmpty, &cpu, &config).is_none());
+
+        let bundle = report_fault(&error, &cpu, &config).unwrap();
+        let display = format!("display.{}", ScreenshotFormat::default().extension());
+        for file in ["report.txt", display.as_str(), "config.toml"] {
+            assert!(bundle.join(file).exists(), "missing {}", file);
+        }
+    }
+}
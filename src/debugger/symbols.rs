@@ -0,0 +1,264 @@
+//! Symbol maps for the disassembler and debugger.
+//!
+//! A symbol map names addresses, e.g. `draw_score = 0x2A4`, so that
+//! disassembly, traces, breakpoints and backtraces can show labels instead
+//! of raw addresses. Maps are TOML with a single `[symbols]` table:
+//!
+//! ```toml
+//! [symbols]
+//! main = 0x202
+//! draw_score = 0x2A4
+//! ```
+//!
+//! Files ending in `.json` are read as `{"symbols": {"main": 514}}`
+//! instead. The Octo compiler can produce a map from a program's labels.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::{disassemble, opcode_pattern};
+use crate::error::Result;
+
+/// On-disk layout of a symbol map.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SymbolFile {
+    /// Address of each name.
+    #[serde(default)]
+    symbols: BTreeMap<String, u16>,
+}
+
+/// Names for addresses, and addresses for names.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SymbolTable {
+    /// Address of each name.
+    addresses: BTreeMap<String, u16>,
+
+    /// Name shown for each address; the first name added wins.
+    names: BTreeMap<u16, String>,
+}
+
+impl SymbolTable {
+    /// Creates an empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads a symbol map, as JSON if the file ends in `.json` and TOML otherwise.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)?;
+        if is_json(path) {
+            Self::from_json(&content)
+        } else {
+            Self::from_toml(&content)
+        }
+    }
+
+    /// Saves the table, as JSON if the file ends in `.json` and TOML otherwise.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let content = if is_json(path) {
+            self.to_json()
+        } else {
+            self.to_toml()?
+        };
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Parses a TOML symbol map.
+    pub fn from_toml(content: &str) -> Result<Self> {
+        let file: SymbolFile = toml::from_str(content)?;
+        Ok(file.symbols.into_iter().collect())
+    }
+
+    /// Parses a JSON symbol map.
+    pub fn from_json(content: &str) -> Result<Self> {
+        let file: SymbolFile = serde_json::from_str(content).map_err(std::io::Error::from)?;
+        Ok(file.symbols.into_iter().collect())
+    }
+
+    /// Formats the table as a TOML symbol map.
+    pub fn to_toml(&self) -> Result<String> {
+        Ok(toml::to_string(&self.to_file())?)
+    }
+
+    /// Formats the table as a JSON symbol map.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.to_file()).expect("symbol maps always serialize")
+    }
+
+    /// Names an address.
+    pub fn insert(&mut self, name: impl Into<String>, address: u16) {
+        let name = name.into();
+        self.names.entry(address).or_insert_with(|| name.clone());
+        self.addresses.insert(name, address);
+    }
+
+    /// Gets the number of names.
+    pub fn len(&self) -> usize {
+        self.addresses.len()
+    }
+
+    /// Checks if the table has no names.
+    pub fn is_empty(&self) -> bool {
+        self.addresses.is_empty()
+    }
+
+    /// Gets the name of an address, if it has one.
+    pub fn name(&self, address: u16) -> Option<&str> {
+        self.names.get(&address).map(String::as_str)
+    }
+
+    /// Gets the address of a name.
+    pub fn address(&self, name: &str) -> Option<u16> {
+        self.addresses.get(name).copied()
+    }
+
+    /// Iterates over names and addresses in address order.
+    pub fn iter(&self) -> impl Iterator<Item = (u16, &str)> {
+        self.names
+            .iter()
+            .map(|(&address, name)| (address, name.as_str()))
+    }
+
+    /// Describes an address relative to the nearest symbol at or below it.
+    ///
+    /// # Returns
+    /// `draw_score`, `draw_score+0x4`, or `0x02A8` when no symbol precedes it.
+    pub fn describe(&self, address: u16) -> String {
+        match self.names.range(..=address).next_back() {
+            Some((&start, name)) if start == address => name.clone(),
+            Some((&start, name)) => format!("{}+0x{:X}", name, address - start),
+            None => format!("0x{:04X}", address),
+        }
+    }
+
+    /// Gets the name shown for a subroutine entry point in backtraces.
+    ///
+    /// Falls back to `super::symbol_name` for addresses without a symbol.
+    pub fn function_name(&self, function: Option<u16>) -> String {
+        function
+            .and_then(|address| self.name(address))
+            .map_or_else(|| super::symbol_name(function), str::to_string)
+    }
+
+    /// Disassembles an opcode, naming its address operand when it has a symbol.
+    pub fn disassemble(&self, opcode: u16) -> String {
+        let text = disassemble(opcode);
+        let target = opcode & 0x0FFF;
+        let has_address = matches!(
+            opcode_pattern(opcode).map(|(pattern, _)| pattern),
+            Some("0NNN" | "1NNN" | "2NNN" | "ANNN" | "BNNN")
+        );
+        match self.name(target) {
+            Some(name) if has_address => text.replace(&format!("0x{:03X}", target), name),
+            _ => text,
+        }
+    }
+
+    /// Resolves a location typed by the user: a symbol, optionally with a
+    /// `+offset`, or a hex address with or without `0x`.
+    pub fn resolve(&self, location: &str) -> Option<u16> {
+        let location = location.trim();
+        if let Some(address) = self.address(location) {
+            return Some(address);
+        }
+        if let Some((name, offset)) = location.split_once('+') {
+            let base = self.address(name.trim())?;
+            return base.checked_add(parse_hex(offset.trim())?);
+        }
+        parse_hex(location)
+    }
+
+    /// Converts the table to its on-disk layout.
+    fn to_file(&self) -> SymbolFile {
+        SymbolFile {
+            symbols: self.addresses.clone(),
+        }
+    }
+}
+
+impl FromIterator<(String, u16)> for SymbolTable {
+    fn from_iter<I: IntoIterator<Item = (String, u16)>>(iter: I) -> Self {
+        let mut table = Self::new();
+        for (name, address) in iter {
+            table.insert(name, address);
+        }
+        table
+    }
+}
+
+/// Checks if a symbol map path is JSON.
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"))
+}
+
+/// Parses a hex number with an optional `0x` prefix.
+fn parse_hex(text: &str) -> Option<u16> {
+    let digits = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .unwrap_or(text);
+    u16::from_str_radix(digits, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> SymbolTable {
+        SymbolTable::from_toml("[symbols]\nmain = 0x202\ndraw_score = 0x2A4\n").unwrap()
+    }
+
+    #[test]
+    fn test_lookups() {
+        let symbols = table();
+
+        assert_eq!(symbols.len(), 2);
+        assert_eq!(symbols.name(0x2A4), Some("draw_score"));
+        assert_eq!(symbols.address("main"), Some(0x202));
+        assert_eq!(symbols.describe(0x2A4), "draw_score");
+        assert_eq!(symbols.describe(0x2A8), "draw_score+0x4");
+        assert_eq!(symbols.describe(0x200), "0x0200");
+        assert_eq!(symbols.function_name(Some(0x2A4)), "draw_score");
+        assert_eq!(symbols.function_name(Some(0x300)), "sub_0300");
+        assert_eq!(symbols.function_name(None), "main");
+    }
+
+    #[test]
+    fn test_disassemble_and_resolve() {
+        let symbols = table();
+
+        assert_eq!(symbols.disassemble(0x22A4), "CALL draw_score");
+        assert_eq!(symbols.disassemble(0xA2A4), "LD I, draw_score");
+        assert_eq!(symbols.disassemble(0x12A6), "JP 0x2A6");
+        // Only address operands are named
+        assert_eq!(symbols.disassemble(0x62A4), "LD V2, 0xA4");
+
+        assert_eq!(symbols.resolve("draw_score"), Some(0x2A4));
+        assert_eq!(symbols.resolve("draw_score+6"), Some(0x2AA));
+        assert_eq!(symbols.resolve("0x2A6"), Some(0x2A6));
+        assert_eq!(symbols.resolve("2a6"), Some(0x2A6));
+        assert_eq!(symbols.resolve("nowhere"), None);
+    }
+
+    #[test]
+    fn test_load_and_save() {
+        let dir = tempfile::tempdir().unwrap();
+        let symbols = table();
+
+        for name in ["game.sym.toml", "game.sym.json"] {
+            let path = dir.path().join(name);
+            symbols.save(&path).unwrap();
+            assert_eq!(SymbolTable::load(&path).unwrap(), symbols);
+        }
+
+        let json = SymbolTable::from_json(r#"{"symbols": {"main": 514}}"#).unwrap();
+        assert_eq!(json.address("main"), Some(0x202));
+        assert!(SymbolTable::from_json("[1, 2]").is_err());
+    }
+}
@@ -0,0 +1,304 @@
+//! Run timelines in Chrome trace format.
+//!
+//! A `Timeline` collects what happened during a run (instructions and
+//! sprite draws per frame, beeps, key presses, markers such as state saves)
+//! and writes it as Chrome trace-event JSON, which `chrome://tracing` and
+//! Perfetto can open to show the run over time.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Instant;
+
+use serde::Serialize;
+
+use crate::emulator::{EmulatorEvent, Metrics};
+use crate::error::Result;
+
+/// Process ID used for every event.
+const PID: u32 = 1;
+
+/// Track for frames and CPU events.
+const CPU_TID: u32 = 1;
+
+/// Track for beeps.
+const AUDIO_TID: u32 = 2;
+
+/// Track for key presses and markers.
+const INPUT_TID: u32 = 3;
+
+/// One event in the Chrome trace-event format.
+#[derive(Debug, Clone, Serialize)]
+struct TraceEvent {
+    /// Event name.
+    name: String,
+
+    /// Category, used for filtering in the viewer.
+    cat: &'static str,
+
+    /// Phase: `X` complete, `B`/`E` begin/end, `i` instant, `C` counter, `M` metadata.
+    ph: &'static str,
+
+    /// Timestamp in microseconds since the start of the run.
+    ts: f64,
+
+    /// Duration in microseconds, for complete events.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dur: Option<f64>,
+
+    /// Process ID.
+    pid: u32,
+
+    /// Thread ID, i.e. the track the event is drawn on.
+    tid: u32,
+
+    /// Scope of instant events.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    s: Option<&'static str>,
+
+    /// Event arguments.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    args: BTreeMap<&'static str, serde_json::Value>,
+}
+
+/// Top-level trace file.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TraceFile<'a> {
+    trace_events: &'a [TraceEvent],
+    display_time_unit: &'static str,
+}
+
+/// Records a run for export as a Chrome trace.
+#[derive(Debug, Clone)]
+pub struct Timeline {
+    /// Time zero of the trace.
+    start: Instant,
+
+    /// End of the last recorded frame.
+    frame_start: Instant,
+
+    /// Counters at the end of the last recorded frame.
+    last: Metrics,
+
+    /// Whether a beep is in progress.
+    beeping: bool,
+
+    /// Recorded events.
+    events: Vec<TraceEvent>,
+}
+
+impl Timeline {
+    /// Creates a timeline starting at `start`.
+    pub fn new(start: Instant) -> Self {
+        let mut timeline = Self {
+            start,
+            frame_start: start,
+            last: Metrics::default(),
+            beeping: false,
+            events: Vec::new(),
+        };
+
+        for (tid, name) in [(CPU_TID, "CPU"), (AUDIO_TID, "Audio"), (INPUT_TID, "Input")] {
+            let mut event = timeline.event("thread_name", "__metadata", "M", start, tid);
+            event.args.insert("name", name.into());
+            timeline.events.push(event);
+        }
+        timeline
+    }
+
+    /// Records the end of a frame.
+    ///
+    /// The frame is drawn as a span from the end of the previous one, with
+    /// the instructions executed and sprites drawn during it as counters.
+    ///
+    /// # Arguments
+    /// * `metrics` - The CPU's counters at the end of the frame
+    /// * `now` - When the frame ended
+    pub fn record_frame(&mut self, metrics: &Metrics, now: Instant) {
+        let instructions = metrics.instructions.saturating_sub(self.last.instructions);
+        let draws = metrics
+            .sprites_drawn
+            .saturating_sub(self.last.sprites_drawn);
+
+        let mut frame = self.event("frame", "frame", "X", self.frame_start, CPU_TID);
+        frame.dur = Some(micros(now.saturating_duration_since(self.frame_start)));
+        frame.args.insert("instructions", instructions.into());
+        frame.args.insert("draws", draws.into());
+        self.events.push(frame);
+
+        for (name, value) in [
+            ("instructions per frame", instructions),
+            ("draw calls", draws),
+        ] {
+            let mut counter = self.event(name, "metrics", "C", now, CPU_TID);
+            counter.args.insert("value", value.into());
+            self.events.push(counter);
+        }
+
+        self.frame_start = now;
+        self.last = *metrics;
+    }
+
+    /// Records an emulator event.
+    ///
+    /// Beeps become spans on the audio track; breakpoints, halts and faults
+    /// become instant events. Display updates are counted per frame instead.
+    pub fn record_event(&mut self, event: &EmulatorEvent, now: Instant) {
+        let recorded = match *event {
+            EmulatorEvent::DisplayUpdated => None,
+            EmulatorEvent::BeepStarted if !self.beeping => {
+                self.beeping = true;
+                Some(self.event("beep", "audio", "B", now, AUDIO_TID))
+            }
+            EmulatorEvent::BeepStopped if self.beeping => {
+                self.beeping = false;
+                Some(self.event("beep", "audio", "E", now, AUDIO_TID))
+            }
+            EmulatorEvent::BeepStarted | EmulatorEvent::BeepStopped => None,
+            EmulatorEvent::Breakpoint { pc } => Some(self.instant("breakpoint", "cpu", pc, now)),
+            EmulatorEvent::UnknownOpcode { pc, .. } => {
+                Some(self.instant("unknown opcode", "cpu", pc, now))
+            }
+            EmulatorEvent::Halted { pc } => Some(self.instant("halted", "cpu", pc, now)),
+            EmulatorEvent::SelfModified { pc, .. } => {
+                Some(self.instant("self-modifying code", "cpu", pc, now))
+            }
+            EmulatorEvent::ProtectedWrite { pc, .. } => {
+                Some(self.instant("protected write", "cpu", pc, now))
+            }
+        };
+        self.events.extend(recorded);
+    }
+
+    /// Records a keypad key being pressed or released.
+    pub fn record_key(&mut self, key: u8, pressed: bool, now: Instant) {
+        let name = format!("key {:X} {}", key, if pressed { "down" } else { "up" });
+        let mut event = self.event(&name, "input", "i", now, INPUT_TID);
+        event.s = Some("t");
+        self.events.push(event);
+    }
+
+    /// Records a named marker, e.g. a state save.
+    pub fn mark(&mut self, name: &str, now: Instant) {
+        let mut event = self.event(name, "marker", "i", now, INPUT_TID);
+        event.s = Some("g");
+        self.events.push(event);
+    }
+
+    /// Closes a beep still in progress at the end of the run.
+    pub fn finish(&mut self, now: Instant) {
+        if self.beeping {
+            self.record_event(&EmulatorEvent::BeepStopped, now);
+        }
+    }
+
+    /// Gets the number of recorded events.
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Checks if nothing has been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Formats the timeline as Chrome trace JSON.
+    pub fn to_json(&self) -> String {
+        let file = TraceFile {
+            trace_events: &self.events,
+            display_time_unit: "ms",
+        };
+        serde_json::to_string(&file).expect("trace events always serialize")
+    }
+
+    /// Writes the timeline to a JSON file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        std::fs::write(path, self.to_json())?;
+        Ok(())
+    }
+
+    /// Creates an event without arguments.
+    fn event(
+        &self,
+        name: &str,
+        cat: &'static str,
+        ph: &'static str,
+        at: Instant,
+        tid: u32,
+    ) -> TraceEvent {
+        TraceEvent {
+            name: name.to_string(),
+            cat,
+            ph,
+            ts: micros(at.saturating_duration_since(self.start)),
+            dur: None,
+            pid: PID,
+            tid,
+            s: None,
+            args: BTreeMap::new(),
+        }
+    }
+
+    /// Creates an instant event on the CPU track tagged with a PC.
+    fn instant(&self, name: &str, cat: &'static str, pc: u16, at: Instant) -> TraceEvent {
+        let mut event = self.event(name, cat, "i", at, CPU_TID);
+        event.s = Some("t");
+        event.args.insert("pc", format!("0x{:03X}", pc).into());
+        event
+    }
+}
+
+/// Converts a duration to fractional microseconds.
+fn micros(duration: std::time::Duration) -> f64 {
+    duration.as_nanos() as f64 / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_timeline_export() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut timeline = Timeline::new(start);
+
+        let mut metrics = Metrics {
+            instructions: 11,
+            sprites_drawn: 2,
+            ..Default::default()
+        };
+        timeline.record_frame(&metrics, at(16));
+        timeline.record_event(&EmulatorEvent::BeepStarted, at(20));
+        timeline.record_event(&EmulatorEvent::DisplayUpdated, at(20));
+        timeline.record_key(0xA, true, at(25));
+        metrics.instructions = 22;
+        timeline.record_frame(&metrics, at(33));
+        timeline.mark("state saved", at(40));
+        timeline.finish(at(50));
+
+        let json: serde_json::Value = serde_json::from_str(&timeline.to_json()).unwrap();
+        let events = json["traceEvents"].as_array().unwrap();
+        let named = |name: &str| -> Vec<&serde_json::Value> {
+            events.iter().filter(|e| e["name"] == name).collect()
+        };
+
+        let frames = named("frame");
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[1]["ts"], 16000.0);
+        assert_eq!(frames[1]["dur"], 17000.0);
+        assert_eq!(frames[1]["args"]["instructions"], 11);
+        assert_eq!(frames[1]["args"]["draws"], 0);
+
+        let beeps = named("beep");
+        assert_eq!(beeps.len(), 2);
+        assert_eq!(beeps[0]["ph"], "B");
+        assert_eq!(beeps[1]["ph"], "E");
+        assert_eq!(beeps[1]["ts"], 50000.0);
+
+        assert_eq!(named("key A down")[0]["ts"], 25000.0);
+        assert_eq!(named("state saved").len(), 1);
+        assert_eq!(named("thread_name").len(), 3);
+    }
+}
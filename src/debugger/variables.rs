@@ -0,0 +1,320 @@
+//! Named memory locations for inspecting a program's state.
+//!
+//! Games keep their score, lives and the like at fixed addresses. A
+//! variable file beside the ROM names them, with an optional type that
+//! says how many bytes to read and how to show the value:
+//!
+//! ```toml
+//! [variables]
+//! score = "0x3A0 (bcd)"
+//! lives = 0x3A1
+//! player_x = "0x3A2 (u8)"
+//! speed = "0x3A4 (i16)"
+//! ```
+//!
+//! A bare address is a `u8`. The types are `u8`, `i8`, `u16` and `i16`
+//! (big-endian, like the Chip-8's own words), `bool`, `hex` (a byte shown
+//! in hex) and `bcd` (the three decimal digits FX33 stores). The monitor
+//! and debug panel show variables live, and expressions can use them by
+//! name, e.g. `break draw_hud if lives == 0`.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::emulator::Memory;
+use crate::error::{ConfigError, Result};
+
+/// Extension of the variable file beside a ROM, e.g. `pong.vars.toml`.
+pub const VARIABLES_EXTENSION: &str = "vars.toml";
+
+/// How a variable's bytes are read and shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VariableType {
+    /// Unsigned byte.
+    #[default]
+    U8,
+
+    /// Signed byte.
+    I8,
+
+    /// Unsigned big-endian word.
+    U16,
+
+    /// Signed big-endian word.
+    I16,
+
+    /// Byte that is either zero or not.
+    Bool,
+
+    /// Byte shown in hex.
+    Hex,
+
+    /// Three bytes holding one decimal digit each, as stored by FX33.
+    Bcd,
+}
+
+impl VariableType {
+    /// Finds a type by name, ignoring case.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let kind = match name.to_ascii_lowercase().as_str() {
+            "u8" => VariableType::U8,
+            "i8" => VariableType::I8,
+            "u16" => VariableType::U16,
+            "i16" => VariableType::I16,
+            "bool" => VariableType::Bool,
+            "hex" => VariableType::Hex,
+            "bcd" => VariableType::Bcd,
+            _ => return None,
+        };
+        Some(kind)
+    }
+
+    /// Gets the type's name.
+    pub fn name(self) -> &'static str {
+        match self {
+            VariableType::U8 => "u8",
+            VariableType::I8 => "i8",
+            VariableType::U16 => "u16",
+            VariableType::I16 => "i16",
+            VariableType::Bool => "bool",
+            VariableType::Hex => "hex",
+            VariableType::Bcd => "bcd",
+        }
+    }
+
+    /// Gets the number of bytes the type occupies.
+    pub fn size(self) -> usize {
+        match self {
+            VariableType::U16 | VariableType::I16 => 2,
+            VariableType::Bcd => 3,
+            _ => 1,
+        }
+    }
+
+    /// Decodes a value from the type's bytes.
+    fn decode(self, bytes: &[u8]) -> i64 {
+        match self {
+            VariableType::U8 | VariableType::Hex => bytes[0] as i64,
+            VariableType::I8 => bytes[0] as i8 as i64,
+            VariableType::U16 => u16::from_be_bytes([bytes[0], bytes[1]]) as i64,
+            VariableType::I16 => i16::from_be_bytes([bytes[0], bytes[1]]) as i64,
+            VariableType::Bool => (bytes[0] != 0) as i64,
+            VariableType::Bcd => bytes
+                .iter()
+                .fold(0, |value, &digit| value * 10 + digit as i64),
+        }
+    }
+
+    /// Formats a value of this type.
+    pub fn format(self, value: i64) -> String {
+        match self {
+            VariableType::Bool => (value != 0).to_string(),
+            VariableType::Hex => format!("0x{:02X}", value),
+            VariableType::Bcd => format!("{:03}", value),
+            _ => value.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for VariableType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+/// A named, typed memory location.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variable {
+    /// Name used in expressions and listings.
+    pub name: String,
+
+    /// Address of the first byte.
+    pub address: u16,
+
+    /// How the bytes are read and shown.
+    pub kind: VariableType,
+}
+
+impl Variable {
+    /// Parses a variable from its name and an `ADDR` or `ADDR (TYPE)` spec.
+    pub fn parse(name: &str, spec: &str) -> Result<Self> {
+        let invalid = |reason: &str| ConfigError::InvalidValue {
+            key: name.to_string(),
+            value: format!("'{}': {}", spec, reason),
+        };
+        let spec = spec.trim();
+        let (address, kind) = match spec.find(|c: char| c.is_whitespace() || c == '(') {
+            Some(split) => (&spec[..split], spec[split..].trim()),
+            None => (spec, ""),
+        };
+        let kind = kind.trim_start_matches('(').trim_end_matches(')').trim();
+        let kind = if kind.is_empty() {
+            VariableType::default()
+        } else {
+            VariableType::from_name(kind).ok_or_else(|| invalid("unknown type"))?
+        };
+        let address = match address
+            .strip_prefix("0x")
+            .or_else(|| address.strip_prefix("0X"))
+        {
+            Some(hex) => u16::from_str_radix(hex, 16),
+            None => address.parse(),
+        }
+        .map_err(|_| invalid("bad address"))?;
+        Ok(Self {
+            name: name.to_string(),
+            address,
+            kind,
+        })
+    }
+
+    /// Reads the variable's value.
+    ///
+    /// # Returns
+    /// The value, or `None` if it lies outside memory.
+    pub fn read(&self, memory: &Memory) -> Option<i64> {
+        let bytes = memory.get_slice(self.address, self.kind.size()).ok()?;
+        Some(self.kind.decode(bytes))
+    }
+
+    /// Reads the variable and formats its value, or `?` if it can't be read.
+    pub fn format(&self, memory: &Memory) -> String {
+        self.read(memory)
+            .map_or_else(|| "?".to_string(), |value| self.kind.format(value))
+    }
+}
+
+/// A variable as written in the file: an address, or an address and type.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum VariableSpec {
+    Address(u16),
+    Text(String),
+}
+
+/// On-disk layout of a variable file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct VariableFile {
+    /// Spec of each variable by name.
+    #[serde(default)]
+    variables: BTreeMap<String, VariableSpec>,
+}
+
+/// Named memory locations, in name order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VariableTable {
+    /// Variables by name.
+    variables: BTreeMap<String, Variable>,
+}
+
+impl VariableTable {
+    /// Creates an empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the path of the variable file for a ROM.
+    pub fn path_for(rom: &Path) -> PathBuf {
+        rom.with_extension(VARIABLES_EXTENSION)
+    }
+
+    /// Loads the variables for a ROM.
+    ///
+    /// # Returns
+    /// The variables, or `None` if the ROM has no variable file.
+    pub fn load_for(rom: &Path) -> Result<Option<Self>> {
+        let path = Self::path_for(rom);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        Self::load(path).map(Some)
+    }
+
+    /// Loads a variable file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parses the contents of a variable file.
+    pub fn parse(content: &str) -> Result<Self> {
+        let file: VariableFile = toml::from_str(content)?;
+        let mut table = Self::new();
+        for (name, spec) in file.variables {
+            let variable = match spec {
+                VariableSpec::Address(address) => Variable {
+                    name,
+                    address,
+                    kind: VariableType::default(),
+                },
+                VariableSpec::Text(spec) => Variable::parse(&name, &spec)?,
+            };
+            table.insert(variable);
+        }
+        Ok(table)
+    }
+
+    /// Adds a variable, replacing any with the same name.
+    pub fn insert(&mut self, variable: Variable) {
+        self.variables.insert(variable.name.clone(), variable);
+    }
+
+    /// Adds a variable, builder style.
+    pub fn with_variable(mut self, variable: Variable) -> Self {
+        self.insert(variable);
+        self
+    }
+
+    /// Finds a variable by name.
+    pub fn get(&self, name: &str) -> Option<&Variable> {
+        self.variables.get(name)
+    }
+
+    /// Gets the variables in name order.
+    pub fn iter(&self) -> impl Iterator<Item = &Variable> {
+        self.variables.values()
+    }
+
+    /// Gets the number of variables.
+    pub fn len(&self) -> usize {
+        self.variables.len()
+    }
+
+    /// Checks if there are no variables.
+    pub fn is_empty(&self) -> bool {
+        self.variables.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_variable_file() {
+        let table = VariableTable::parse(
+            "[variables]\nscore = \"0x3A0 (bcd)\"\nlives = 0x3A3\nspeed = \"0x3A4 i16\"\n",
+        )
+        .unwrap();
+        assert_eq!(table.len(), 3);
+        assert_eq!(table.get("lives").unwrap().kind, VariableType::U8);
+        assert_eq!(table.get("speed").unwrap().address, 0x3A4);
+
+        let mut memory = Memory::new();
+        for (offset, byte) in [1, 2, 3, 4, 0xFF, 0xFE].into_iter().enumerate() {
+            memory.write_byte(0x3A0 + offset as u16, byte).unwrap();
+        }
+        assert_eq!(table.get("score").unwrap().read(&memory), Some(123));
+        assert_eq!(table.get("score").unwrap().format(&memory), "123");
+        assert_eq!(table.get("lives").unwrap().format(&memory), "4");
+        assert_eq!(table.get("speed").unwrap().read(&memory), Some(-2));
+
+        let edge = Variable::parse("edge", "0xFFF (u16)").unwrap();
+        assert_eq!(edge.format(&memory), "?");
+        assert!(Variable::parse("bad", "0x3A0 (f32)").is_err());
+        assert!(Variable::parse("bad", "here").is_err());
+        assert!(VariableTable::parse("[variables]\nbad = \"0x3A0 (f32)\"").is_err());
+    }
+}
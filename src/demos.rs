@@ -0,0 +1,97 @@
+//! Demo ROMs built into the binary.
+//!
+//! Enabled with the `demo-roms` feature. The ROMs live in `assets/demos/`
+//! and are embedded with `include_bytes!`, so a fresh install has something
+//! to run and tests have real programs to execute.
+
+use std::path::{Path, PathBuf};
+
+/// A built-in ROM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DemoRom {
+    /// Name used on the command line.
+    pub name: &'static str,
+
+    /// One-line description.
+    pub description: &'static str,
+
+    /// ROM data.
+    pub data: &'static [u8],
+}
+
+impl DemoRom {
+    /// Writes the ROM to `<dir>/<name>.ch8`, creating the directory if needed.
+    ///
+    /// # Returns
+    /// The path written.
+    pub fn extract(&self, dir: &Path) -> std::io::Result<PathBuf> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(format!("{}.ch8", self.name));
+        std::fs::write(&path, self.data)?;
+        Ok(path)
+    }
+}
+
+/// All built-in ROMs.
+pub const DEMO_ROMS: &[DemoRom] = &[
+    DemoRom {
+        name: "ibm-logo",
+        description: "Draws the IBM logo; the classic first test of CLS, LD I, ADD and DRW",
+        data: include_bytes!("../assets/demos/ibm-logo.ch8"),
+    },
+    DemoRom {
+        name: "maze",
+        description: "Random maze of diagonal lines, redrawn every two seconds",
+        data: include_bytes!("../assets/demos/maze.ch8"),
+    },
+    DemoRom {
+        name: "pong",
+        description: "Pong against the computer; keys 1 and 4 move the left paddle",
+        data: include_bytes!("../assets/demos/pong.ch8"),
+    },
+];
+
+/// Finds a built-in ROM by name.
+pub fn find_demo(name: &str) -> Option<&'static DemoRom> {
+    DEMO_ROMS.iter().find(|demo| demo.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::memory::MAX_ROM_SIZE;
+    use crate::emulator::Cpu;
+    use crate::hardware::NullHardware;
+
+    #[test]
+    fn test_demos_run() {
+        for demo in DEMO_ROMS {
+            assert!(!demo.data.is_empty() && demo.data.len() <= MAX_ROM_SIZE);
+            assert_eq!(find_demo(demo.name), Some(demo));
+
+            let mut cpu = Cpu::with_hardware(NullHardware::new());
+            cpu.seed_rng(0);
+            cpu.set_realtime_timers(false);
+            cpu.load_rom(demo.data).unwrap();
+            for _ in 0..300 {
+                crate::netplay::run_frame_for(&mut cpu, 11).unwrap();
+            }
+            assert!(
+                cpu.get_display_buffer().contains(&true),
+                "{} drew nothing",
+                demo.name
+            );
+        }
+        assert_eq!(find_demo("tetris"), None);
+    }
+
+    #[test]
+    fn test_extract() {
+        let dir = tempfile::tempdir().unwrap();
+        let demo = find_demo("maze").unwrap();
+        let path = demo.extract(&dir.path().join("demos")).unwrap();
+
+        assert_eq!(path, dir.path().join("demos/maze.ch8"));
+        assert_eq!(std::fs::read(path).unwrap(), demo.data);
+    }
+}
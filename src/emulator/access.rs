@@ -0,0 +1,196 @@
+//! Per-address memory access counters.
+//!
+//! When access profiling is on, the CPU counts how often each byte is read
+//! as data, written, and fetched as an instruction. The counts can be
+//! rendered as a heat map image with one pixel per byte: red for writes,
+//! green for reads and blue for executes, each brighter the more often it
+//! happens. Code shows up blue, sprites and tables green, variables red or
+//! yellow, and bytes the program never touches stay black.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use serde::Serialize;
+
+/// Pixels per row of a heat map, so 4K of memory is a 64x64 image.
+pub const HEATMAP_WIDTH: usize = 64;
+
+/// How often one byte was accessed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct MemoryAccess {
+    /// Times the byte was read as data (sprites, FX65).
+    pub reads: u64,
+
+    /// Times the byte was written (FX33, FX55).
+    pub writes: u64,
+
+    /// Times the byte was fetched as part of an instruction.
+    pub executes: u64,
+}
+
+impl MemoryAccess {
+    /// Checks if the byte was never accessed.
+    pub fn is_untouched(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Access counts for every byte of memory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessCounts {
+    /// Counts by address.
+    counts: Vec<MemoryAccess>,
+}
+
+impl AccessCounts {
+    /// Creates zeroed counters for `size` bytes of memory.
+    pub fn new(size: usize) -> Self {
+        Self {
+            counts: vec![MemoryAccess::default(); size],
+        }
+    }
+
+    /// Gets the number of addresses counted.
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// Checks if no addresses are counted.
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Gets the counts of one address, or `None` if it is out of range.
+    pub fn get(&self, address: u16) -> Option<MemoryAccess> {
+        self.counts.get(address as usize).copied()
+    }
+
+    /// Gets the counts of every address, in address order.
+    pub fn counts(&self) -> &[MemoryAccess] {
+        &self.counts
+    }
+
+    /// Counts a data read.
+    pub fn record_read(&mut self, address: u16) {
+        if let Some(count) = self.counts.get_mut(address as usize) {
+            count.reads += 1;
+        }
+    }
+
+    /// Counts a write.
+    pub fn record_write(&mut self, address: u16) {
+        if let Some(count) = self.counts.get_mut(address as usize) {
+            count.writes += 1;
+        }
+    }
+
+    /// Counts an instruction fetch of the two bytes at `address`.
+    pub fn record_execute(&mut self, address: u16) {
+        let size = self.counts.len();
+        for byte in [address as usize, address as usize + 1] {
+            if let Some(count) = self.counts.get_mut(byte % size.max(1)) {
+                count.executes += 1;
+            }
+        }
+    }
+
+    /// Zeroes every counter.
+    pub fn clear(&mut self) {
+        self.counts.fill(MemoryAccess::default());
+    }
+
+    /// Renders the counts as an RGBA heat map, one pixel per byte.
+    ///
+    /// Each channel is scaled logarithmically against the busiest byte of
+    /// its kind, so rarely touched bytes stay visible next to hot loops.
+    /// Needs `std` for the logarithms.
+    ///
+    /// # Returns
+    /// The image width, height and pixels.
+    #[cfg(feature = "std")]
+    pub fn heatmap(&self, width: usize) -> (u32, u32, Vec<u8>) {
+        let width = width.max(1);
+        let height = self.counts.len().div_ceil(width);
+        let max = self
+            .counts
+            .iter()
+            .fold(MemoryAccess::default(), |max, c| MemoryAccess {
+                reads: max.reads.max(c.reads),
+                writes: max.writes.max(c.writes),
+                executes: max.executes.max(c.executes),
+            });
+
+        let mut rgba = vec![0; width * height * 4];
+        for (pixel, count) in rgba.chunks_exact_mut(4).zip(&self.counts) {
+            pixel[0] = intensity(count.writes, max.writes);
+            pixel[1] = intensity(count.reads, max.reads);
+            pixel[2] = intensity(count.executes, max.executes);
+        }
+        for pixel in rgba.chunks_exact_mut(4) {
+            pixel[3] = 0xFF;
+        }
+        (width as u32, height as u32, rgba)
+    }
+}
+
+/// Scales a count to a channel value on a log scale; zero stays black.
+#[cfg(feature = "std")]
+fn intensity(count: u64, max: u64) -> u8 {
+    if count == 0 || max == 0 {
+        return 0;
+    }
+    // Anything touched is at least dimly visible
+    let scale = ((count as f64).ln_1p() / (max as f64).ln_1p()).min(1.0);
+    (64.0 + scale * 191.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_access_counts() {
+        let mut counts = AccessCounts::new(8);
+        counts.record_execute(0);
+        counts.record_execute(7);
+        counts.record_read(3);
+        counts.record_write(3);
+        counts.record_write(100);
+
+        assert_eq!(counts.get(0).unwrap().executes, 2);
+        assert_eq!(counts.get(1).unwrap().executes, 1);
+        assert_eq!(
+            counts.get(3),
+            Some(MemoryAccess {
+                reads: 1,
+                writes: 1,
+                executes: 0
+            })
+        );
+        assert!(counts.get(5).unwrap().is_untouched());
+        assert_eq!(counts.get(100), None);
+
+        counts.clear();
+        assert!(counts.counts().iter().all(MemoryAccess::is_untouched));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_heatmap_channels() {
+        let mut counts = AccessCounts::new(6);
+        for _ in 0..9 {
+            counts.record_execute(0);
+        }
+        counts.record_read(2);
+        counts.record_write(3);
+
+        let (width, height, rgba) = counts.heatmap(4);
+        assert_eq!((width, height), (4, 2));
+        assert_eq!(rgba.len(), 4 * 2 * 4);
+        assert_eq!(&rgba[0..4], &[0, 0, 255, 255]);
+        assert_eq!(&rgba[8..12], &[0, 255, 0, 255]);
+        assert_eq!(&rgba[12..16], &[255, 0, 0, 255]);
+        // Untouched bytes and the padding past the end stay black
+        assert_eq!(&rgba[16..20], &[0, 0, 0, 255]);
+        assert_eq!(&rgba[28..32], &[0, 0, 0, 255]);
+    }
+}
@@ -3,12 +3,36 @@
 //! This module implements the central processing unit that executes Chip-8
 //! instructions and manages the system state.
 
-use crate::emulator::{Memory, Registers, Stack, Timers};
-use crate::error::{EmulatorError, Result};
-use crate::hardware::{Audio, ChipKey, Display, Input};
-use rand::{rngs::ThreadRng, Rng};
-use std::cell::RefCell;
-use std::rc::Rc;
+use crate::emulator::access::AccessCounts;
+use crate::emulator::events::{EmulatorEvent, EventBus};
+use crate::emulator::megachip::{MegaChipScreen, MEGA_HEIGHT, MEGA_WIDTH};
+use crate::emulator::metrics::Metrics;
+#[cfg(feature = "std")]
+use crate::emulator::random::ThreadRandom;
+use crate::emulator::random::{Pcg32, RandomSource};
+use crate::emulator::snapshot::{self, Snapshot};
+use crate::emulator::timing::{CostContext, FrameBudget, TimingModel};
+use crate::emulator::{
+    AddressMasking, CycleClock, Memory, MemoryProtection, Registers, Stack, TimeSource, Timers,
+    STACK_SIZE,
+};
+use crate::error::{EmulatorError, FaultContext, Result};
+use crate::hardware::{
+    Audio, ChipKey, ClipMode, Display, Hardware, HiresMode, Input, DISPLAY_HEIGHT, DISPLAY_WIDTH,
+};
+#[cfg(feature = "std")]
+use crate::hardware::{DynHardware, SharedInput};
+use crate::logging::{self, AUDIO, CPU, GFX};
+use alloc::boxed::Box;
+use alloc::collections::{BTreeSet, VecDeque};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::sync::mpsc::Receiver;
+#[cfg(feature = "std")]
+use std::time::Instant;
 
 /// CPU state for debugging and serialization.
 #[derive(Debug, Clone, PartialEq)]
@@ -23,11 +47,88 @@ pub struct CpuState {
     pub instruction_count: u64,
 }
 
+/// A subroutine call recorded on the shadow call stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CallFrame {
+    /// Address of the CALL instruction.
+    pub call_site: u16,
+
+    /// Address of the called subroutine.
+    pub target: u16,
+}
+
+/// An instruction recorded in the execution trace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceEntry {
+    /// Address the instruction was fetched from.
+    pub pc: u16,
+
+    /// The instruction word.
+    pub opcode: u16,
+
+    /// Address of already executed code this instruction overwrote.
+    pub modified_code: Option<u16>,
+}
+
+/// The last instruction executed and what it cost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InstructionInfo {
+    /// Address the instruction was fetched from.
+    pub pc: u16,
+
+    /// The instruction word.
+    pub opcode: u16,
+
+    /// Clock cycles charged for it under the timing model.
+    pub cost: u32,
+}
+
+impl InstructionInfo {
+    /// Gets the instruction's bytes in memory order.
+    pub fn bytes(&self) -> [u8; 2] {
+        self.opcode.to_be_bytes()
+    }
+
+    /// Decodes the instruction to its mnemonic.
+    pub fn disassembly(&self) -> String {
+        crate::analysis::disassemble(self.opcode)
+    }
+}
+
+/// Outcome of a single CPU cycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CpuEvent {
+    /// An instruction was executed.
+    Executed,
+
+    /// The CPU is blocked on FX0A and no instruction was executed.
+    ///
+    /// Frontends can stop cycling and block on input events, then either
+    /// keep cycling or call [`Cpu::resume_with_key`].
+    WaitingForKey,
+
+    /// A pending FX0A key wait completed during this cycle.
+    Resumed,
+
+    /// Execution stopped at a breakpoint; the next cycle runs the instruction.
+    Breakpoint,
+}
+
 /// Chip-8 CPU implementation.
 ///
 /// The CPU manages instruction execution, system state, and coordinates
-/// with memory, registers, stack, and timers.
-pub struct Cpu {
+/// with memory, registers, stack, and timers. All display, audio and input
+/// access goes through the [`Hardware`] it owns.
+///
+/// A `Cpu<H>` is `Send` whenever `H` is, so it can run on a background
+/// thread. [`NullHardware`](crate::hardware::NullHardware) and
+/// [`ThreadedHardware`](crate::hardware::ThreadedHardware) are `Send`;
+/// [`DynHardware`] is not, since the GUI's audio output can't leave the
+/// thread that opened it.
+pub struct Cpu<
+    #[cfg(feature = "std")] H: Hardware = DynHardware,
+    #[cfg(not(feature = "std"))] H: Hardware,
+> {
     /// Register set (V0-VF, I, PC, SP).
     registers: Registers,
 
@@ -40,12 +141,36 @@ pub struct Cpu {
     /// Delay and sound timers.
     timers: Timers,
 
-    /// Random number generator for RND instruction.
-    rng: ThreadRng,
+    /// Source of the random bytes for the RND instruction.
+    rng: Box<dyn RandomSource>,
+
+    /// Whether timers follow wall-clock time during `cycle()`.
+    realtime_timers: bool,
+
+    /// Clock fed with charged cycles when timers follow executed cycles.
+    cycle_clock: Option<CycleClock>,
 
     /// Total number of instructions executed.
     instruction_count: u64,
 
+    /// Performance counters, kept across resets.
+    metrics: Metrics,
+
+    /// How instructions are charged against the clock.
+    timing_model: TimingModel,
+
+    /// Clock cycles elapsed under the timing model.
+    elapsed_cycles: u64,
+
+    /// Clock cycles charged for the last cycle.
+    last_cost: u32,
+
+    /// The last instruction executed, if any since the reset.
+    last_instruction: Option<InstructionInfo>,
+
+    /// Cycles and instructions charged since the last frame was shown.
+    frame_budget: FrameBudget,
+
     /// Whether the CPU is waiting for a key press.
     waiting_for_key: bool,
 
@@ -55,75 +180,535 @@ pub struct Cpu {
     /// The key being waited for release (None if not waiting for release).
     waiting_for_key_release: Option<u8>,
 
-    /// Display system for rendering.
-    display: Option<Box<dyn Display>>,
+    /// Display, audio and input systems.
+    hardware: H,
+
+    /// Event subscribers.
+    events: EventBus,
+
+    /// Whether the sound timer was active on the previous cycle.
+    beeping: bool,
+
+    /// Addresses that stop execution before the instruction runs.
+    breakpoints: BTreeSet<u16>,
+
+    /// Breakpoint address that was just reported and should be stepped over.
+    skip_breakpoint: Option<u16>,
+
+    /// Whether the program is stuck in a jump-to-self loop.
+    halted: bool,
+
+    /// How writes to reserved memory are handled.
+    memory_protection: MemoryProtection,
+
+    /// How BNNN targets and I-relative addresses past 0xFFF are handled.
+    address_masking: AddressMasking,
+
+    /// Whether BNNN jumps to XNN + VX (SUPER-CHIP BXNN) instead of NNN + V0.
+    jump_with_vx: bool,
+
+    /// Whether 8XY6/8XYE shift VY into VX, as the COSMAC VIP does.
+    shift_uses_vy: bool,
+
+    /// Whether FX55/FX65 leave I past the last register, as the COSMAC VIP does.
+    load_store_increments_i: bool,
+
+    /// Whether 0011 may switch on Mega-Chip mode.
+    mega_chip_enabled: bool,
+
+    /// The color screen, while Mega-Chip mode is on.
+    mega_chip: Option<MegaChipScreen>,
+
+    /// Whether sprites past the screen edge are clipped or wrap around.
+    clip_mode: ClipMode,
+
+    /// Which hi-res screen, if any, programs get when they are loaded.
+    hires_mode: HiresMode,
 
-    /// Audio system for sound.
-    audio: Option<Box<dyn Audio>>,
+    /// Address of a protected write not yet seen by a debugger.
+    protection_violation: Option<u16>,
 
-    /// Input system for keyboard handling.
-    input: Option<Rc<RefCell<dyn Input>>>,
+    /// Active subroutine calls, outermost first.
+    call_stack: Vec<CallFrame>,
+
+    /// Most recently fetched instructions, oldest first.
+    trace: VecDeque<TraceEntry>,
+
+    /// Number of instructions kept in the trace (0 disables tracing).
+    trace_length: usize,
+
+    /// Bytes fetched as instructions, when self-modifying code is tracked.
+    executed: Option<Vec<bool>>,
+
+    /// Per-address read, write and execute counts, when access profiling is on.
+    access_counts: Option<AccessCounts>,
 }
 
-impl Cpu {
-    /// Creates a new CPU instance.
+#[cfg(feature = "std")]
+impl Cpu<DynHardware> {
+    /// Creates a new CPU instance with null hardware.
     pub fn new() -> Self {
-        Self {
-            registers: Registers::new(),
-            memory: Memory::new(),
-            stack: Stack::new(),
-            timers: Timers::new(),
-            rng: rand::thread_rng(),
-            instruction_count: 0,
-            waiting_for_key: false,
-            key_wait_register: 0,
-            waiting_for_key_release: None,
-            display: None,
-            audio: None,
-            input: None,
-        }
+        Self::with_hardware(DynHardware::default())
     }
 
     /// Creates a new CPU instance with specific behavior configuration.
     pub fn new_with_config(config: &crate::frontend::EmulatorBehaviorConfig) -> Self {
-        // Configure timers if needed
-        // Note: Timer frequency configuration would require timer system updates
+        let mut cpu = Self::new();
+        cpu.configure(config);
+        cpu
+    }
+
+    /// Creates a new CPU instance whose timers read time from a custom source.
+    pub fn new_with_time_source(source: impl TimeSource + Send + 'static) -> Self {
+        let mut cpu = Self::new();
+        cpu.set_time_source(source);
+        cpu
+    }
+
+    /// Sets the display system.
+    ///
+    /// The display picks up the CPU's clip mode.
+    pub fn set_display(&mut self, mut display: Box<dyn Display>) {
+        display.set_clip_mode(self.clip_mode);
+        self.hardware.set_display(display);
+    }
+
+    /// Sets the audio system.
+    pub fn set_audio(&mut self, audio: Box<dyn Audio>) {
+        self.hardware.set_audio(audio);
+    }
+
+    /// Sets the input system.
+    pub fn set_input(&mut self, input: SharedInput) {
+        self.hardware.set_input(input);
+    }
+}
 
+impl<H: Hardware> Cpu<H> {
+    /// Creates a new CPU instance that runs against the given hardware.
+    pub fn with_hardware(hardware: H) -> Self {
         Self {
             registers: Registers::new(),
-            memory: Memory::new_with_wraparound(config.memory_wraparound),
+            memory: Memory::new(),
             stack: Stack::new(),
             timers: Timers::new(),
-            rng: rand::thread_rng(),
+            #[cfg(feature = "std")]
+            rng: Box::new(ThreadRandom),
+            #[cfg(not(feature = "std"))]
+            rng: Box::new(Pcg32::new(0)),
+            realtime_timers: true,
+            cycle_clock: None,
             instruction_count: 0,
+            metrics: Metrics::default(),
+            timing_model: TimingModel::default(),
+            elapsed_cycles: 0,
+            last_cost: 0,
+            last_instruction: None,
+            frame_budget: FrameBudget::default(),
             waiting_for_key: false,
             key_wait_register: 0,
             waiting_for_key_release: None,
-            display: None,
-            audio: None,
-            input: None,
+            hardware,
+            events: EventBus::new(),
+            beeping: false,
+            breakpoints: BTreeSet::new(),
+            skip_breakpoint: None,
+            halted: false,
+            memory_protection: MemoryProtection::Off,
+            address_masking: AddressMasking::Off,
+            jump_with_vx: false,
+            shift_uses_vy: false,
+            load_store_increments_i: false,
+            mega_chip_enabled: false,
+            mega_chip: None,
+            clip_mode: ClipMode::default(),
+            hires_mode: HiresMode::default(),
+            protection_violation: None,
+            call_stack: Vec::new(),
+            trace: VecDeque::new(),
+            trace_length: 0,
+            executed: None,
+            access_counts: None,
         }
     }
 
     /// Configures the CPU with behavior settings.
+    #[cfg(feature = "std")]
     pub fn configure(&mut self, config: &crate::frontend::EmulatorBehaviorConfig) {
+        self.memory.set_layout(config.memory_layout);
         self.memory.set_wraparound(config.memory_wraparound);
-        // Additional configuration can be added here
+        self.set_timing_model(config.timing());
+        self.memory_protection = config.memory_protection;
+        self.address_masking = config.address_masking;
+        self.jump_with_vx = config.jump_with_vx;
+        self.shift_uses_vy = config.shift_uses_vy;
+        self.load_store_increments_i = config.load_store_increments_i;
+        self.set_mega_chip(config.mega_chip);
+        self.set_clip_mode(config.clip_mode);
+        self.hires_mode = config.hires;
+        self.timers.set_sound_mode(config.sound_timer_mode);
+        self.memory.load_font(config.font_set());
     }
 
-    /// Sets the display system.
-    pub fn set_display(&mut self, display: Box<dyn Display>) {
-        self.display = Some(display);
+    /// Sets how writes to reserved memory are handled.
+    pub fn set_memory_protection(&mut self, protection: MemoryProtection) {
+        self.memory_protection = protection;
     }
 
-    /// Sets the audio system.
-    pub fn set_audio(&mut self, audio: Box<dyn Audio>) {
-        self.audio = Some(audio);
+    /// Gets how writes to reserved memory are handled.
+    pub fn memory_protection(&self) -> MemoryProtection {
+        self.memory_protection
     }
 
-    /// Sets the input system.
-    pub fn set_input(&mut self, input: Rc<RefCell<dyn Input>>) {
-        self.input = Some(input);
+    /// Sets how BNNN targets and I-relative addresses past 0xFFF are handled.
+    pub fn set_address_masking(&mut self, masking: AddressMasking) {
+        self.address_masking = masking;
+    }
+
+    /// Gets how BNNN targets and I-relative addresses past 0xFFF are handled.
+    pub fn address_masking(&self) -> AddressMasking {
+        self.address_masking
+    }
+
+    /// Sets whether BNNN jumps to XNN + VX (SUPER-CHIP BXNN) instead of NNN + V0.
+    pub fn set_jump_with_vx(&mut self, enabled: bool) {
+        self.jump_with_vx = enabled;
+    }
+
+    /// Gets whether BNNN jumps to XNN + VX instead of NNN + V0.
+    pub fn jump_with_vx(&self) -> bool {
+        self.jump_with_vx
+    }
+
+    /// Sets whether 8XY6/8XYE shift VY into VX instead of shifting VX in place.
+    pub fn set_shift_uses_vy(&mut self, enabled: bool) {
+        self.shift_uses_vy = enabled;
+    }
+
+    /// Gets whether 8XY6/8XYE shift VY into VX instead of shifting VX in place.
+    pub fn shift_uses_vy(&self) -> bool {
+        self.shift_uses_vy
+    }
+
+    /// Sets whether FX55/FX65 advance I past the last register they touch.
+    pub fn set_load_store_increments_i(&mut self, enabled: bool) {
+        self.load_store_increments_i = enabled;
+    }
+
+    /// Gets whether FX55/FX65 advance I past the last register they touch.
+    pub fn load_store_increments_i(&self) -> bool {
+        self.load_store_increments_i
+    }
+
+    /// Sets whether programs may switch on Mega-Chip mode with 0011.
+    ///
+    /// Disabling it also leaves Mega-Chip mode if it is on.
+    pub fn set_mega_chip(&mut self, enabled: bool) {
+        self.mega_chip_enabled = enabled;
+        if !enabled {
+            self.mega_chip = None;
+        }
+    }
+
+    /// Gets whether programs may switch on Mega-Chip mode with 0011.
+    pub fn mega_chip(&self) -> bool {
+        self.mega_chip_enabled
+    }
+
+    /// Gets the color screen, or `None` outside Mega-Chip mode.
+    pub fn mega_chip_screen(&self) -> Option<&MegaChipScreen> {
+        self.mega_chip.as_ref()
+    }
+
+    /// Sets whether sprites past the screen edge are clipped or wrap around.
+    pub fn set_clip_mode(&mut self, mode: ClipMode) {
+        self.clip_mode = mode;
+        self.hardware.display().set_clip_mode(mode);
+    }
+
+    /// Gets whether sprites past the screen edge are clipped or wrap around.
+    pub fn clip_mode(&self) -> ClipMode {
+        self.clip_mode
+    }
+
+    /// Sets which hi-res screen programs get, taking effect on the next load.
+    pub fn set_hires_mode(&mut self, mode: HiresMode) {
+        self.hires_mode = mode;
+    }
+
+    /// Gets which hi-res screen programs get when they are loaded.
+    pub fn hires_mode(&self) -> HiresMode {
+        self.hires_mode
+    }
+
+    /// Gets the display resolution as (width, height) in pixels.
+    pub fn display_size(&self) -> (usize, usize) {
+        self.hardware.display_ref().size()
+    }
+
+    /// Checks if the display is in a hi-res CHIP-8 resolution.
+    pub fn is_hires(&self) -> bool {
+        self.display_size() != (DISPLAY_WIDTH, DISPLAY_HEIGHT)
+    }
+
+    /// Switches the display to the resolution the hi-res mode picks for a
+    /// program.
+    ///
+    /// Displays with a fixed resolution stay at 64x32, with a warning.
+    fn select_resolution(&mut self, first_instruction: u16) {
+        let (width, height) = self.hires_mode.resolution(first_instruction);
+        if (width, height) == self.display_size() {
+            return;
+        }
+        match self.hardware.display().resize(width, height) {
+            Ok(()) => self.events.emit(EmulatorEvent::DisplayUpdated),
+            Err(e) => logging::warn!(target: GFX, "Staying at {:?}: {}", self.display_size(), e),
+        }
+    }
+
+    /// Gets the active subroutine calls, outermost first.
+    ///
+    /// Unlike the hardware stack, this records where each call came from.
+    pub fn call_stack(&self) -> &[CallFrame] {
+        &self.call_stack
+    }
+
+    /// Sets how many recently fetched instructions are kept (0 disables tracing).
+    pub fn set_trace_length(&mut self, length: usize) {
+        self.trace_length = length;
+        while self.trace.len() > length {
+            self.trace.pop_front();
+        }
+    }
+
+    /// Gets the most recently fetched instructions, oldest first.
+    ///
+    /// The last entry is the instruction that ran (or faulted) most recently.
+    pub fn trace(&self) -> &VecDeque<TraceEntry> {
+        &self.trace
+    }
+
+    /// Turns tracking of self-modifying code on or off.
+    ///
+    /// While on, every fetched instruction is remembered, and a write to
+    /// one emits `EmulatorEvent::SelfModified` and marks the writing
+    /// instruction in the trace.
+    pub fn set_self_modification_tracking(&mut self, enabled: bool) {
+        self.executed = enabled.then(|| vec![false; self.memory.size()]);
+    }
+
+    /// Checks if self-modifying code is tracked.
+    pub fn self_modification_tracking(&self) -> bool {
+        self.executed.is_some()
+    }
+
+    /// Turns per-address access profiling on or off.
+    ///
+    /// While on, every data read, write and instruction fetch is counted
+    /// per byte; see [`access_counts`](Self::access_counts).
+    pub fn set_access_profiling(&mut self, enabled: bool) {
+        self.access_counts = enabled.then(|| AccessCounts::new(self.memory.size()));
+    }
+
+    /// Checks if memory accesses are counted.
+    pub fn access_profiling(&self) -> bool {
+        self.access_counts.is_some()
+    }
+
+    /// Gets the memory access counts, if access profiling is on.
+    pub fn access_counts(&self) -> Option<&AccessCounts> {
+        self.access_counts.as_ref()
+    }
+
+    /// Takes the address of the last protected write, if one happened.
+    ///
+    /// Debuggers use this to stop on stray writes even when they are ignored.
+    pub fn take_protection_violation(&mut self) -> Option<u16> {
+        self.protection_violation.take()
+    }
+
+    /// Sets the timing model.
+    pub fn set_timing_model(&mut self, model: TimingModel) {
+        self.timing_model = model;
+        let rate_changed = self
+            .cycle_clock
+            .as_ref()
+            .is_some_and(|clock| clock.cycles_per_second() != model.cycles_per_second());
+        if rate_changed {
+            self.use_cycle_clock();
+        }
+    }
+
+    /// Gets the timing model.
+    pub fn timing_model(&self) -> TimingModel {
+        self.timing_model
+    }
+
+    /// Gets the number of instructions executed.
+    pub fn instruction_count(&self) -> u64 {
+        self.instruction_count
+    }
+
+    /// Gets a snapshot of the performance counters.
+    pub fn metrics(&self) -> Metrics {
+        Metrics {
+            timer_underruns: self.timers.late_ticks(),
+            ..self.metrics
+        }
+    }
+
+    /// Counts a frame shown by the frontend.
+    ///
+    /// This also closes the frame's budget, which becomes the metrics'
+    /// `frame_budget`, and starts counting the next one.
+    pub fn record_frame(&mut self) {
+        self.metrics.frames += 1;
+        self.metrics.frame_budget = self.frame_budget();
+        self.frame_budget = FrameBudget::default();
+    }
+
+    /// Zeroes the performance counters.
+    ///
+    /// Timer underruns are counted by the timers and are not affected.
+    /// Input latency is measured by the input and reappears with the next
+    /// key the program reads.
+    pub fn reset_metrics(&mut self) {
+        self.metrics = Metrics::default();
+    }
+
+    /// Gets the clock cycles elapsed under the timing model.
+    pub fn elapsed_cycles(&self) -> u64 {
+        self.elapsed_cycles
+    }
+
+    /// Gets the clock cycles charged for the last cycle.
+    ///
+    /// Breakpoint stops cost nothing; waiting for a key costs one cycle.
+    pub fn last_cost(&self) -> u32 {
+        self.last_cost
+    }
+
+    /// Gets the last instruction executed by `cycle` and its cost.
+    pub fn last_instruction_info(&self) -> Option<InstructionInfo> {
+        self.last_instruction
+    }
+
+    /// Gets the clock cycles one timer frame allows under the timing model.
+    ///
+    /// This is the budget every frame-driven caller runs against: the
+    /// frontend, netplay, replays and the test harnesses.
+    pub fn cycles_per_frame(&self) -> u32 {
+        (self.timing_model.cycles_per_second() / self.timers.get_frequency().max(1)).max(1)
+    }
+
+    /// Gets the cycles and instructions charged so far this frame.
+    ///
+    /// The last complete frame's totals are in the metrics.
+    pub fn frame_budget(&self) -> FrameBudget {
+        FrameBudget {
+            cycles_per_second: self.timing_model.cycles_per_second(),
+            ..self.frame_budget
+        }
+    }
+
+    /// Seeds the random number generator so RND results are reproducible.
+    ///
+    /// The seeded generator is [`Pcg32`], so a seed gives the same bytes on
+    /// every build.
+    ///
+    /// With `std` RND starts from the thread-local generator. Without it every
+    /// machine starts from the same fixed seed, so a no_std host must call
+    /// this with entropy of its own (a hardware RNG, ADC noise, the time of
+    /// the first key press) or RND gives the same sequence on every boot.
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = Box::new(Pcg32::new(seed));
+    }
+
+    /// Sets where RND gets its random bytes, such as a recording to replay.
+    pub fn set_random_source(&mut self, source: Box<dyn RandomSource>) {
+        self.rng = source;
+    }
+
+    /// Sets whether `cycle()` advances timers from wall-clock time.
+    ///
+    /// Disable this for deterministic execution and drive the timers with
+    /// [`Timers::update_by_ticks`] instead.
+    pub fn set_realtime_timers(&mut self, enabled: bool) {
+        self.realtime_timers = enabled;
+    }
+
+    /// Checks if timers follow wall-clock time.
+    pub fn realtime_timers(&self) -> bool {
+        self.realtime_timers
+    }
+
+    /// Sets the time source the timers read during `cycle()`.
+    pub fn set_time_source(&mut self, source: impl TimeSource + Send + 'static) {
+        self.cycle_clock = None;
+        self.timers.set_time_source(source);
+    }
+
+    /// Drives the timers from executed cycles rather than the host clock.
+    ///
+    /// Every cycle charged under the timing model advances a [`CycleClock`]
+    /// at the model's rate, so timers tick deterministically however fast
+    /// the host runs. Replaced by [`set_time_source`](Self::set_time_source).
+    pub fn use_cycle_clock(&mut self) {
+        let clock = CycleClock::new(self.timing_model.cycles_per_second());
+        self.timers.set_time_source(clock.clone());
+        self.cycle_clock = Some(clock);
+    }
+
+    /// Registers a callback for emulator events.
+    pub fn subscribe(&mut self, callback: impl FnMut(&EmulatorEvent) + Send + 'static) {
+        self.events.subscribe(callback);
+    }
+
+    /// Creates a channel that receives emulator events.
+    #[cfg(feature = "std")]
+    pub fn subscribe_channel(&mut self) -> Receiver<EmulatorEvent> {
+        self.events.subscribe_channel()
+    }
+
+    /// Gets mutable access to the event bus.
+    pub fn events_mut(&mut self) -> &mut EventBus {
+        &mut self.events
+    }
+
+    /// Adds a breakpoint at the given address.
+    pub fn add_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.insert(addr);
+    }
+
+    /// Removes a breakpoint, returning whether it existed.
+    pub fn remove_breakpoint(&mut self, addr: u16) -> bool {
+        self.breakpoints.remove(&addr)
+    }
+
+    /// Removes all breakpoints.
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+        self.skip_breakpoint = None;
+    }
+
+    /// Gets the current breakpoints.
+    pub fn breakpoints(&self) -> &BTreeSet<u16> {
+        &self.breakpoints
+    }
+
+    /// Checks if the program has halted in a jump-to-self loop.
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Gets the hardware the CPU runs against.
+    pub fn hardware(&self) -> &H {
+        &self.hardware
+    }
+
+    /// Gets mutable access to the hardware.
+    pub fn hardware_mut(&mut self) -> &mut H {
+        &mut self.hardware
     }
 
     /// Resets the CPU to initial state.
@@ -133,9 +718,26 @@ impl Cpu {
         self.stack.reset();
         self.timers.reset();
         self.instruction_count = 0;
+        self.elapsed_cycles = 0;
+        self.last_cost = 0;
+        self.last_instruction = None;
+        self.frame_budget = FrameBudget::default();
         self.waiting_for_key = false;
         self.key_wait_register = 0;
         self.waiting_for_key_release = None;
+        self.beeping = false;
+        self.skip_breakpoint = None;
+        self.halted = false;
+        self.protection_violation = None;
+        self.call_stack.clear();
+        self.trace.clear();
+        self.mega_chip = None;
+        let _ = self
+            .hardware
+            .display()
+            .resize(DISPLAY_WIDTH, DISPLAY_HEIGHT);
+        self.forget_executed();
+        self.reset_access_counts();
     }
 
     /// Loads a ROM into memory.
@@ -145,10 +747,18 @@ impl Cpu {
     ///
     /// # Returns
     /// Ok(()) on success, or an error if the ROM is invalid.
+    /// A program starting with `1260` switches the display to 64x64 under
+    /// the default hi-res mode (see [`HiresMode`]).
     pub fn load_rom(&mut self, rom_data: &[u8]) -> Result<()> {
         self.memory.load_rom(rom_data)?;
-        self.registers
-            .set_pc(crate::emulator::memory::PROGRAM_START);
+        self.registers.set_pc(self.memory.program_start());
+        let first_instruction = match rom_data {
+            [high, low, ..] => u16::from_be_bytes([*high, *low]),
+            _ => 0,
+        };
+        self.select_resolution(first_instruction);
+        self.forget_executed();
+        self.reset_access_counts();
         Ok(())
     }
 
@@ -163,6 +773,8 @@ impl Cpu {
     pub fn load_rom_at(&mut self, rom_data: &[u8], start_address: u16) -> Result<()> {
         self.memory.load_rom_at(rom_data, start_address)?;
         self.registers.set_pc(start_address);
+        self.forget_executed();
+        self.reset_access_counts();
         Ok(())
     }
 
@@ -171,62 +783,194 @@ impl Cpu {
     /// This fetches, decodes, and executes one instruction, then updates timers.
     ///
     /// # Returns
-    /// Ok(()) on successful execution, or an error if something goes wrong.
-    pub fn cycle(&mut self) -> Result<()> {
+    /// The outcome of the cycle, or an error if something goes wrong.
+    /// Errors from fetching or executing an instruction carry its PC,
+    /// opcode and the registers at the time (see `EmulatorError::context`).
+    pub fn cycle(&mut self) -> Result<CpuEvent> {
         // Update timers first
-        self.timers.update();
+        if self.realtime_timers {
+            self.timers.update();
+        }
 
         // Handle audio based on sound timer
-        if let Some(audio) = &mut self.audio {
-            if self.timers.get_sound_timer() > 0 {
-                if let Err(e) = audio.play_beep() {
-                    log::warn!("Failed to play audio beep: {}", e);
-                }
-            } else if let Err(e) = audio.stop_beep() {
-                log::warn!("Failed to stop audio beep: {}", e);
+        let sound_timer = self.timers.get_sound_timer();
+        let beeping = self.timers.should_play_sound();
+        let audio = self.hardware.audio();
+        if let Err(e) = audio.set_sound_timer(sound_timer) {
+            logging::warn!(target: AUDIO, "Failed to update audio sound timer: {}", e);
+        }
+
+        if beeping {
+            if let Err(e) = audio.play_beep() {
+                logging::warn!(target: AUDIO, "Failed to play audio beep: {}", e);
             }
+        } else if let Err(e) = audio.stop_beep() {
+            logging::warn!(target: AUDIO, "Failed to stop audio beep: {}", e);
+        }
+        self.metrics.audio_underruns = audio.underruns();
+
+        if beeping != self.beeping {
+            self.beeping = beeping;
+            self.events.emit(if beeping {
+                EmulatorEvent::BeepStarted
+            } else {
+                EmulatorEvent::BeepStopped
+            });
         }
 
         // If waiting for key press or release, check for input but don't execute instructions
         if self.waiting_for_key {
-            if let Some(input) = &self.input {
-                if let Some(waiting_key) = self.waiting_for_key_release {
-                    // We're waiting for a specific key to be released
-                    let chip_key = crate::hardware::input::ChipKey::from_u8(waiting_key);
-                    if let Some(key) = chip_key {
-                        if !input.borrow().is_key_pressed(key) {
-                            // Key has been released, store it and continue
-                            self.registers.set_v(self.key_wait_register, waiting_key)?;
-                            self.waiting_for_key = false;
-                            self.waiting_for_key_release = None;
-                        }
-                    }
-                } else {
-                    // We're waiting for any key to be pressed
-                    if let Some(pressed_key) = input.borrow().get_first_pressed_key() {
-                        // Key pressed, now wait for it to be released
-                        self.waiting_for_key_release = Some(pressed_key.to_u8());
+            self.charge(1);
+            let input = self.hardware.input();
+            if let Some(waiting_key) = self.waiting_for_key_release {
+                // We're waiting for a specific key to be released
+                let chip_key = crate::hardware::input::ChipKey::from_u8(waiting_key);
+                if let Some(key) = chip_key {
+                    if !input.is_key_pressed(key) {
+                        // Key has been released, store it and continue
+                        self.registers.set_v(self.key_wait_register, waiting_key)?;
+                        self.waiting_for_key = false;
+                        self.waiting_for_key_release = None;
+                        return Ok(CpuEvent::Resumed);
                     }
                 }
+            } else {
+                // We're waiting for any key to be pressed
+                if let Some(pressed_key) = input.get_first_pressed_key() {
+                    // Key pressed, now wait for it to be released
+                    self.waiting_for_key_release = Some(pressed_key.to_u8());
+                    self.see_key(pressed_key);
+                }
             }
-            return Ok(());
+            return Ok(CpuEvent::WaitingForKey);
         }
 
-        // Fetch instruction
+        // Stop at breakpoints (once; the following cycle steps over it)
         let pc = self.registers.get_pc();
-        let instruction = self.memory.read_word(pc)?;
+        if self.skip_breakpoint.take() != Some(pc) && self.breakpoints.contains(&pc) {
+            self.skip_breakpoint = Some(pc);
+            self.last_cost = 0;
+            self.events.emit(EmulatorEvent::Breakpoint { pc });
+            return Ok(CpuEvent::Breakpoint);
+        }
+
+        // Fetch instruction
+        let instruction = self
+            .memory
+            .read_word(pc)
+            .map_err(|e| self.fault(e, pc, None))?;
+        if self.trace_length > 0 {
+            if self.trace.len() == self.trace_length {
+                self.trace.pop_front();
+            }
+            self.trace.push_back(TraceEntry {
+                pc,
+                opcode: instruction,
+                modified_code: None,
+            });
+        }
+        if let Some(executed) = &mut self.executed {
+            let size = executed.len();
+            executed[pc as usize % size] = true;
+            executed[(pc as usize + 1) % size] = true;
+        }
+        if let Some(counts) = &mut self.access_counts {
+            counts.record_execute(pc);
+        }
 
         // Increment PC before execution (some instructions modify PC)
         self.registers.increment_pc();
 
         // Decode and execute instruction
-        log::debug!("PC: {:#04x}, Instruction: {:#04x}", pc, instruction);
-        self.execute_instruction(instruction)?;
+        logging::debug!(target: CPU, "PC: {:#04x}, Instruction: {:#04x}", pc, instruction);
+        #[cfg(feature = "std")]
+        let started = Instant::now();
+        if let Err(e) = self.execute_instruction(instruction) {
+            if let EmulatorError::UnknownInstruction { opcode } = e {
+                self.events
+                    .emit(EmulatorEvent::UnknownOpcode { pc, opcode });
+            }
+            return Err(self.fault(e, pc, Some(instruction)));
+        }
+
+        let context = CostContext {
+            skipped: self.registers.get_pc() == pc.wrapping_add(4),
+            vx: self.registers.get_v(((instruction >> 8) & 0xF) as u8)?,
+            elapsed: self.elapsed_cycles,
+        };
+        self.charge(self.timing_model.instruction_cost(instruction, &context));
+        self.frame_budget.instructions += 1;
+        self.last_instruction = Some(InstructionInfo {
+            pc,
+            opcode: instruction,
+            cost: self.last_cost,
+        });
+
+        // A jump to itself is the conventional way for a program to stop
+        let halted = self.registers.get_pc() == pc;
+        if halted && !self.halted {
+            self.events.emit(EmulatorEvent::Halted { pc });
+        }
+        self.halted = halted;
 
         // Increment instruction counter
         self.instruction_count += 1;
+        self.metrics.instructions += 1;
+        #[cfg(feature = "std")]
+        {
+            self.metrics.cycle_time += started.elapsed();
+        }
 
-        Ok(())
+        if self.waiting_for_key {
+            return Ok(CpuEvent::WaitingForKey);
+        }
+
+        Ok(CpuEvent::Executed)
+    }
+
+    /// Executes an arbitrary instruction word as if it had been fetched at PC.
+    ///
+    /// This is the entry point for fuzzing: whatever the opcode and the
+    /// machine state, the instruction either runs or returns an error; it
+    /// never panics. PC advances past the instruction first, as in `cycle`,
+    /// but timers, breakpoints and pending key waits are left alone.
+    ///
+    /// # Returns
+    /// `WaitingForKey` if the instruction was FX0A, otherwise `Executed`.
+    pub fn execute_arbitrary(&mut self, opcode: u16) -> Result<CpuEvent> {
+        let pc = self.registers.get_pc();
+        self.registers.increment_pc();
+        if let Err(e) = self.execute_instruction(opcode) {
+            return Err(self.fault(e, pc, Some(opcode)));
+        }
+        self.instruction_count += 1;
+
+        if self.waiting_for_key {
+            return Ok(CpuEvent::WaitingForKey);
+        }
+        Ok(CpuEvent::Executed)
+    }
+
+    /// Attaches the current execution context to an error.
+    fn fault(&self, error: EmulatorError, pc: u16, opcode: Option<u16>) -> EmulatorError {
+        error.with_context(FaultContext {
+            pc,
+            opcode,
+            instruction_count: self.instruction_count,
+            v: *self.registers.get_all_v().try_into().unwrap_or(&[0; 16]),
+            i: self.registers.get_i(),
+            sp: self.registers.get_sp(),
+        })
+    }
+
+    /// Charges clock cycles for the current cycle.
+    fn charge(&mut self, cost: u32) {
+        self.last_cost = cost;
+        self.elapsed_cycles += cost as u64;
+        self.frame_budget.cycles += cost as u64;
+        if let Some(clock) = &self.cycle_clock {
+            clock.add_cycles(cost as u64);
+        }
     }
 
     /// Executes a single instruction.
@@ -255,29 +999,50 @@ impl Cpu {
             // 0NNN - System call (ignored in modern interpreters)
             (0x0, 0x0, 0xE, 0x0) => self.cls()?, // 00E0 - Clear screen
             (0x0, 0x0, 0xE, 0xE) => self.ret()?, // 00EE - Return from subroutine
-            (0x0, _, _, _) => {}                 // 0NNN - System call (ignored)
+
+            // Hi-res CHIP-8: the startup jump skips the original interpreter's
+            // patch and 0230 clears the larger screen
+            (0x1, 0x2, 0x6, 0x0) if self.is_hires_startup() => self.jp(0x2C0)?,
+            (0x0, 0x2, 0x3, 0x0) if self.is_hires() => self.cls()?,
+
+            // Mega-Chip extensions, only while the variant allows them
+            (0x0, 0x0, 0x1, 0x0) if self.mega_chip_enabled => self.mega_off(),
+            (0x0, 0x0, 0x1, 0x1) if self.mega_chip_enabled => self.mega_on(),
+            (0x0, 0x1, _, _) if self.mega_chip.is_some() => self.ldhi(nn)?,
+            (0x0, 0x2, _, _) if self.mega_chip.is_some() => self.ldpal(nn)?,
+            (0x0, 0x3..=0x9, _, _) if self.mega_chip.is_some() => self.mega_setting(nibbles.1, nn),
+
+            (0x0, _, _, _) => {} // 0NNN - System call (ignored)
 
             // 1NNN - Jump to address NNN
             (0x1, _, _, _) => self.jp(nnn)?,
 
             // 2NNN - Call subroutine at NNN
             (0x2, _, _, _) => {
-                log::debug!(
+                logging::debug!(
+                    target: CPU,
                     "CALL {:#04x} (PC before push: {:#04x}, SP: {})",
                     nnn,
                     self.registers.get_pc(),
                     self.stack.depth()
                 );
                 self.call(nnn)?;
-                log::debug!("Stack after CALL: {:?}", self.stack.get_contents());
+                logging::debug!(target: CPU, "Stack after CALL: {:?}", self.stack.get_contents());
             }
 
             // 3XNN - Skip next instruction if VX == NN
             (0x3, _, _, _) => {
                 let vx_val = self.registers.get_v(x)?;
-                log::debug!("SE V{:X}, {:#02x} (V{:X} = {:#02x})", x, nn, x, vx_val);
+                logging::debug!(
+                    target: CPU,
+                    "SE V{:X}, {:#02x} (V{:X} = {:#02x})",
+                    x,
+                    nn,
+                    x,
+                    vx_val
+                );
                 self.se_vx_nn(x, nn)?;
-                log::debug!("PC after SE: {:#04x}", self.registers.get_pc());
+                logging::debug!(target: CPU, "PC after SE: {:#04x}", self.registers.get_pc());
             }
 
             // 4XNN - Skip next instruction if VX != NN
@@ -311,13 +1076,13 @@ impl Cpu {
             (0x8, _, _, 0x5) => self.sub_vx_vy(x, y)?,
 
             // 8XY6 - Set VX = VX >> 1, VF = LSB
-            (0x8, _, _, 0x6) => self.shr_vx(x)?,
+            (0x8, _, _, 0x6) => self.shr_vx(x, y)?,
 
             // 8XY7 - Set VX = VY - VX, VF = NOT borrow
             (0x8, _, _, 0x7) => self.subn_vx_vy(x, y)?,
 
             // 8XYE - Set VX = VX << 1, VF = MSB
-            (0x8, _, _, 0xE) => self.shl_vx(x)?,
+            (0x8, _, _, 0xE) => self.shl_vx(x, y)?,
 
             // 9XY0 - Skip next instruction if VX != VY
             (0x9, _, _, 0x0) => self.sne_vx_vy(x, y)?,
@@ -325,8 +1090,8 @@ impl Cpu {
             // ANNN - Set I = NNN
             (0xA, _, _, _) => self.ld_i_nnn(nnn)?,
 
-            // BNNN - Jump to NNN + V0
-            (0xB, _, _, _) => self.jp_v0_nnn(nnn)?,
+            // BNNN - Jump to NNN + V0 (or XNN + VX with the SUPER-CHIP quirk)
+            (0xB, _, _, _) => self.jp_offset(x, nnn)?,
 
             // CXNN - Set VX = random byte AND NN
             (0xC, _, _, _) => self.rnd_vx_nn(x, nn)?,
@@ -367,14 +1132,15 @@ impl Cpu {
             // FX65 - Load V0 through VX from memory starting at I
             (0xF, _, 0x6, 0x5) => {
                 let i_val = self.registers.get_i();
-                log::debug!("LD V{:X}, [I] (I: {:#04x})", x, i_val);
+                logging::debug!(target: CPU, "LD V{:X}, [I] (I: {:#04x})", x, i_val);
                 self.ld_vx_i(x)?;
-                log::debug!(
+                logging::debug!(
+                    target: CPU,
                     "V0-V{:X} after LD: {:?}",
                     x,
                     self.registers.get_v_range(0, x as usize + 1)?
                 );
-                log::debug!("I after LD: {:#04x}", self.registers.get_i());
+                logging::debug!(target: CPU, "I after LD: {:#04x}", self.registers.get_i());
             }
 
             _ => {
@@ -407,6 +1173,24 @@ impl Cpu {
         Ok(())
     }
 
+    /// Completes a pending FX0A key wait with the given key.
+    ///
+    /// This lets event-driven frontends deliver the key directly instead
+    /// of cycling until the input reports a press and release.
+    ///
+    /// # Returns
+    /// `true` if the CPU was waiting and has resumed, `false` otherwise.
+    pub fn resume_with_key(&mut self, key: ChipKey) -> Result<bool> {
+        if !self.waiting_for_key {
+            return Ok(false);
+        }
+
+        self.registers.set_v(self.key_wait_register, key.to_u8())?;
+        self.waiting_for_key = false;
+        self.waiting_for_key_release = None;
+        Ok(true)
+    }
+
     /// Checks if the CPU is waiting for a key press.
     pub fn is_waiting_for_key(&self) -> bool {
         self.waiting_for_key
@@ -426,11 +1210,126 @@ impl Cpu {
         }
     }
 
+    /// Captures the machine state so it can be restored later.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            pc: self.registers.get_pc(),
+            i: self.registers.get_i(),
+            sp: self.registers.get_sp(),
+            v: *self.registers.get_all_v().try_into().unwrap_or(&[0; 16]),
+            stack: self.stack.get_contents(),
+            delay_timer: self.timers.get_delay_timer(),
+            sound_timer: self.timers.get_sound_timer(),
+            timer_phase_ns: self.timers.phase().as_nanos() as u64,
+            memory: self.memory.bytes().to_vec(),
+            display: snapshot::pack_pixels(self.hardware.display_ref().get_buffer()),
+            display_size: self.is_hires().then(|| {
+                let (width, height) = self.display_size();
+                (width as u16, height as u16)
+            }),
+            key_wait: self.waiting_for_key.then_some(self.key_wait_register),
+            key_release: self.waiting_for_key_release,
+            halted: self.halted,
+            call_stack: self.call_stack.clone(),
+            mega_chip: self.mega_chip.clone(),
+            instruction_count: self.instruction_count,
+            elapsed_cycles: self.elapsed_cycles,
+        }
+    }
+
+    /// Restores the machine state from a snapshot.
+    ///
+    /// The memory, stack and display sizes are checked before anything
+    /// changes, including the display resolution, so on error the current
+    /// state is left untouched. The instruction
+    /// trace starts over, since the snapshot doesn't record it.
+    ///
+    /// # Arguments
+    /// * `snapshot` - State captured by [`Cpu::snapshot`]
+    pub fn restore(&mut self, snapshot: &Snapshot) -> Result<()> {
+        if snapshot.memory.len() != self.memory.size() {
+            return Err(EmulatorError::SnapshotMismatch {
+                part: "memory",
+                expected: self.memory.size(),
+                found: snapshot.memory.len(),
+            });
+        }
+        let (width, height) = snapshot.display_size();
+        let pixels = width * height;
+        if snapshot.display.len() != pixels.div_ceil(8) {
+            return Err(EmulatorError::SnapshotMismatch {
+                part: "display",
+                expected: pixels.div_ceil(8),
+                found: snapshot.display.len(),
+            });
+        }
+        if snapshot.stack.len() > STACK_SIZE {
+            return Err(EmulatorError::StackOverflow);
+        }
+        if (width, height) != self.display_size() {
+            let current = self.hardware.display_ref().get_buffer().len();
+            self.hardware.display().resize(width, height).map_err(|_| {
+                EmulatorError::SnapshotMismatch {
+                    part: "display",
+                    expected: current.div_ceil(8),
+                    found: snapshot.display.len(),
+                }
+            })?;
+        }
+        self.memory.load_image(&snapshot.memory)?;
+        self.stack.load_values(&snapshot.stack)?;
+
+        self.registers.set_pc(snapshot.pc);
+        self.registers.set_i(snapshot.i);
+        self.registers.set_sp(snapshot.sp);
+        self.registers.set_v_range(0, &snapshot.v)?;
+
+        self.timers
+            .set_both_timers(snapshot.delay_timer, snapshot.sound_timer);
+        self.timers.sync_to_real_time();
+        self.timers
+            .set_phase(Duration::from_nanos(snapshot.timer_phase_ns));
+
+        let pixels = self.hardware.display().get_buffer_mut();
+        snapshot::unpack_pixels(&snapshot.display, pixels);
+        self.events.emit(EmulatorEvent::DisplayUpdated);
+
+        self.waiting_for_key = snapshot.key_wait.is_some();
+        self.key_wait_register = snapshot.key_wait.unwrap_or(0);
+        self.waiting_for_key_release = snapshot.key_release;
+        self.halted = snapshot.halted;
+        self.instruction_count = snapshot.instruction_count;
+        self.elapsed_cycles = snapshot.elapsed_cycles;
+
+        self.skip_breakpoint = None;
+        self.protection_violation = None;
+        self.call_stack = snapshot.call_stack.clone();
+        self.mega_chip = snapshot.mega_chip.clone();
+        self.trace.clear();
+        self.forget_executed();
+        Ok(())
+    }
+
     /// Gets the memory for external access (read-only).
     pub fn get_memory(&self) -> &Memory {
         &self.memory
     }
 
+    /// Gets mutable access to memory (for debuggers and tools).
+    pub fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+
+    /// Gets the registers for external access.
+    pub fn get_registers(&self) -> &Registers {
+        &self.registers
+    }
+
+    /// Gets mutable access to the registers (for debuggers and tools).
+    pub fn registers_mut(&mut self) -> &mut Registers {
+        &mut self.registers
+    }
+
     /// Gets the timers for external access.
     pub fn get_timers(&self) -> &Timers {
         &self.timers
@@ -448,32 +1347,45 @@ impl Cpu {
 
     /// Gets the current display buffer.
     pub fn get_display_buffer(&self) -> &[bool] {
-        self.display.as_ref().map_or(&[], |d| d.get_buffer())
+        self.hardware.display_ref().get_buffer()
     }
 
     // Instruction implementations
 
     fn cls(&mut self) -> Result<()> {
-        if let Some(display) = &mut self.display {
-            display.clear();
+        if let Some(screen) = &mut self.mega_chip {
+            screen.clear();
         }
+        self.hardware.display().clear();
+        self.events.emit(EmulatorEvent::DisplayUpdated);
         Ok(())
     }
 
     fn ret(&mut self) -> Result<()> {
         let addr = self.stack.pop()?;
         self.registers.set_pc(addr);
+        self.call_stack.pop();
         Ok(())
     }
 
+    /// Checks if a `1260` just fetched is a hi-res program's startup jump.
+    fn is_hires_startup(&self) -> bool {
+        self.is_hires() && self.registers.get_pc() == self.memory.program_start() + 2
+    }
+
     fn jp(&mut self, addr: u16) -> Result<()> {
         self.registers.set_pc(addr);
         Ok(())
     }
 
     fn call(&mut self, addr: u16) -> Result<()> {
-        self.stack.push(self.registers.get_pc())?;
+        let return_addr = self.registers.get_pc();
+        self.stack.push(return_addr)?;
         self.registers.set_pc(addr);
+        self.call_stack.push(CallFrame {
+            call_site: return_addr.wrapping_sub(2),
+            target: addr,
+        });
         Ok(())
     }
 
@@ -538,7 +1450,8 @@ impl Cpu {
         self.registers.sub_with_borrow(x, y)
     }
 
-    fn shr_vx(&mut self, x: u8) -> Result<()> {
+    fn shr_vx(&mut self, x: u8, y: u8) -> Result<()> {
+        self.load_shift_source(x, y)?;
         self.registers.shift_right(x)
     }
 
@@ -546,10 +1459,20 @@ impl Cpu {
         self.registers.sub_reverse_with_borrow(x, y)
     }
 
-    fn shl_vx(&mut self, x: u8) -> Result<()> {
+    fn shl_vx(&mut self, x: u8, y: u8) -> Result<()> {
+        self.load_shift_source(x, y)?;
         self.registers.shift_left(x)
     }
 
+    /// Copies VY into VX before a shift when shifts use VY.
+    fn load_shift_source(&mut self, x: u8, y: u8) -> Result<()> {
+        if self.shift_uses_vy {
+            let value = self.registers.get_v(y)?;
+            self.registers.set_v(x, value)?;
+        }
+        Ok(())
+    }
+
     fn sne_vx_vy(&mut self, x: u8, y: u8) -> Result<()> {
         if self.registers.get_v(x)? != self.registers.get_v(y)? {
             self.registers.skip_instruction();
@@ -562,62 +1485,169 @@ impl Cpu {
         Ok(())
     }
 
-    fn jp_v0_nnn(&mut self, nnn: u16) -> Result<()> {
-        let v0 = self.registers.get_v(0)? as u16;
-        self.registers.set_pc(nnn + v0);
+    fn jp_offset(&mut self, x: u8, nnn: u16) -> Result<()> {
+        let offset_register = if self.jump_with_vx { x } else { 0 };
+        let offset = self.registers.get_v(offset_register)? as u16;
+        self.registers.set_pc(self.offset_address(nnn, offset));
         Ok(())
     }
 
+    /// Computes an address relative to a base, applying address masking.
+    fn offset_address(&self, base: u16, offset: u16) -> u16 {
+        self.address_masking
+            .apply(base.wrapping_add(offset), self.memory.address_mask())
+    }
+
     fn rnd_vx_nn(&mut self, x: u8, nn: u8) -> Result<()> {
-        let random_byte: u8 = self.rng.gen();
+        let random_byte = self.rng.next_byte();
         self.registers.set_v(x, random_byte & nn)
     }
 
     fn drw(&mut self, x: u8, y: u8, n: u8) -> Result<()> {
         let x_pos = self.registers.get_v(x)? as usize;
         let y_pos = self.registers.get_v(y)? as usize;
-        let sprite_addr = self.registers.get_i() as usize;
-
-        // Default to no collision
-        let mut collision = false;
-
-        if let Some(display) = &mut self.display {
-            // Read sprite data from memory
-            let mut sprite_data = Vec::new();
-            for i in 0..n {
-                let addr = sprite_addr + i as usize;
-                if addr < 4096 {
-                    // MEMORY_SIZE
-                    sprite_data.push(self.memory.read_byte(addr as u16)?);
-                }
-            }
+        let sprite_addr = self.registers.get_i();
+
+        if self.mega_chip.is_some() {
+            return self.mega_drw(x_pos, y_pos, sprite_addr);
+        }
 
-            // Draw sprite and check for collision
-            collision = display
-                .draw_sprite(x_pos as u8, y_pos as u8, &sprite_data)
-                .unwrap_or(false);
+        // Read sprite data from memory; unmasked rows past the end are left out
+        let mut sprite_data = Vec::new();
+        for i in 0..n {
+            let addr = self.offset_address(sprite_addr, i as u16);
+            if (addr as usize) < self.memory.size() {
+                sprite_data.push(self.load_byte(addr)?);
+            }
         }
 
+        // Draw sprite and check for collision
+        let collision = self
+            .hardware
+            .display()
+            .draw_sprite(x_pos as u8, y_pos as u8, &sprite_data)
+            .unwrap_or(false);
+
         // Set VF flag based on collision
         self.registers.set_flag(if collision { 1 } else { 0 });
+        self.metrics.sprites_drawn += 1;
+        self.metrics.collisions += collision as u64;
+        self.events.emit(EmulatorEvent::DisplayUpdated);
+        Ok(())
+    }
+
+    /// 0010 - Leaves Mega-Chip mode.
+    fn mega_off(&mut self) {
+        self.mega_chip = None;
+        self.events.emit(EmulatorEvent::DisplayUpdated);
+    }
+
+    /// 0011 - Enters Mega-Chip mode with a blank color screen.
+    fn mega_on(&mut self) {
+        self.mega_chip = Some(MegaChipScreen::new());
+        self.events.emit(EmulatorEvent::DisplayUpdated);
+    }
+
+    /// 01NN NNNN - Loads a 24-bit address into I.
+    ///
+    /// I is 16 bits wide, so addresses past 64KB are an error.
+    fn ldhi(&mut self, nn: u8) -> Result<()> {
+        let pc = self.registers.get_pc();
+        let low = self.memory.read_word(pc)?;
+        self.registers.set_pc(pc.wrapping_add(2));
+
+        let address = (nn as u32) << 16 | low as u32;
+        let i = u16::try_from(address).map_err(|_| EmulatorError::AddressOutOfRange { address })?;
+        self.registers.set_i(i);
+        Ok(())
+    }
+
+    /// 02NN - Loads NN palette colors from I, four ARGB bytes each.
+    fn ldpal(&mut self, nn: u8) -> Result<()> {
+        let i = self.registers.get_i();
+        let mut colors = Vec::with_capacity(nn as usize * 4);
+        for offset in 0..nn as u16 * 4 {
+            colors.push(self.load_byte(self.offset_address(i, offset))?);
+        }
+        if let Some(screen) = &mut self.mega_chip {
+            screen.load_palette(&colors);
+        }
+        Ok(())
+    }
+
+    /// 03NN-09NN - Changes a Mega-Chip drawing setting.
+    ///
+    /// 03NN and 04NN set the sprite width and height, 05NN the alpha,
+    /// 080N the blend mode and 09NN the collision index. The 060N/0700
+    /// sample player is not supported, so those only log.
+    fn mega_setting(&mut self, kind: u8, nn: u8) {
+        let Some(screen) = &mut self.mega_chip else {
+            return;
+        };
+        match kind {
+            0x3 => screen.set_sprite_width(nn),
+            0x4 => screen.set_sprite_height(nn),
+            0x5 => screen.set_alpha(nn),
+            0x8 => screen.set_blend_mode(nn & 0xF),
+            0x9 => screen.set_collision_index(nn),
+            _ => {
+                logging::debug!(
+                    target: CPU,
+                    "Mega-Chip sound {:X}{:02X} is not supported",
+                    kind,
+                    nn
+                )
+            }
+        }
+    }
+
+    /// DXYN in Mega-Chip mode - Draws a color sprite of palette indices from I.
+    fn mega_drw(&mut self, x: usize, y: usize, sprite_addr: u16) -> Result<()> {
+        let Some((width, height)) = self.mega_chip.as_ref().map(MegaChipScreen::sprite_size) else {
+            return Ok(());
+        };
+
+        // Only the rows and columns that land on screen are read
+        let mut sprite_data = Vec::with_capacity(width * height);
+        for offset in 0..width * height {
+            let (column, row) = (offset % width, offset / width);
+            let addr = self.offset_address(sprite_addr, offset as u16);
+            if x + column < MEGA_WIDTH
+                && y + row < MEGA_HEIGHT
+                && (addr as usize) < self.memory.size()
+            {
+                sprite_data.push(self.load_byte(addr)?);
+            } else {
+                sprite_data.push(0);
+            }
+        }
+
+        let collision = self
+            .mega_chip
+            .as_mut()
+            .is_some_and(|screen| screen.draw_sprite(x, y, &sprite_data));
+        self.registers.set_flag(if collision { 1 } else { 0 });
+        self.metrics.sprites_drawn += 1;
+        self.metrics.collisions += collision as u64;
+        self.events.emit(EmulatorEvent::DisplayUpdated);
         Ok(())
     }
 
     fn skp_vx(&mut self, x: u8) -> Result<()> {
         let key_value = self.registers.get_v(x)?;
 
-        if let Some(input) = &self.input {
-            if let Some(chip_key) = ChipKey::from_u8(key_value) {
-                let is_pressed = input.borrow().is_key_pressed(chip_key);
-                log::debug!(
-                    "SKP V{:X} (key {:#X}): is_pressed = {}",
-                    x,
-                    key_value,
-                    is_pressed
-                );
-                if is_pressed {
-                    self.registers.set_pc(self.registers.get_pc() + 2);
-                }
+        if let Some(chip_key) = ChipKey::from_u8(key_value) {
+            let is_pressed = self.hardware.input().is_key_pressed(chip_key);
+            logging::debug!(
+                target: CPU,
+                "SKP V{:X} (key {:#X}): is_pressed = {}",
+                x,
+                key_value,
+                is_pressed
+            );
+            if is_pressed {
+                self.see_key(chip_key);
+                self.registers.skip_instruction();
             }
         }
 
@@ -627,22 +1657,20 @@ impl Cpu {
     fn sknp_vx(&mut self, x: u8) -> Result<()> {
         let key_value = self.registers.get_v(x)?;
 
-        if let Some(input) = &self.input {
-            if let Some(chip_key) = ChipKey::from_u8(key_value) {
-                let is_pressed = input.borrow().is_key_pressed(chip_key);
-                log::debug!(
-                    "SKNP V{:X} (key {:#X}): is_pressed = {}",
-                    x,
-                    key_value,
-                    is_pressed
-                );
-                if !is_pressed {
-                    self.registers.set_pc(self.registers.get_pc() + 2);
-                }
+        if let Some(chip_key) = ChipKey::from_u8(key_value) {
+            let is_pressed = self.hardware.input().is_key_pressed(chip_key);
+            logging::debug!(
+                target: CPU,
+                "SKNP V{:X} (key {:#X}): is_pressed = {}",
+                x,
+                key_value,
+                is_pressed
+            );
+            if is_pressed {
+                self.see_key(chip_key);
+            } else {
+                self.registers.skip_instruction();
             }
-        } else {
-            // If no input system, treat as key not pressed
-            self.registers.set_pc(self.registers.get_pc() + 2);
         }
 
         Ok(())
@@ -653,6 +1681,13 @@ impl Cpu {
         self.registers.set_v(x, dt)
     }
 
+    /// Reports a key the program has read as pressed, for input latency.
+    fn see_key(&mut self, key: ChipKey) {
+        let input = self.hardware.input();
+        input.key_seen(key);
+        self.metrics.input_latency = input.latency();
+    }
+
     fn ld_vx_k(&mut self, x: u8) -> Result<()> {
         self.waiting_for_key = true;
         self.key_wait_register = x;
@@ -679,15 +1714,86 @@ impl Cpu {
         // Set VF flag if there's an overflow beyond 0x0FFF (12-bit address space)
         self.registers.set_flag(if result > 0x0FFF { 1 } else { 0 });
 
-        self.registers.set_i(result);
+        self.registers.set_i(
+            self.address_masking
+                .apply(result, self.memory.address_mask()),
+        );
+        Ok(())
+    }
+
+    fn ld_f_vx(&mut self, x: u8) -> Result<()> {
+        let vx = self.registers.get_v(x)?;
+        let font_addr = self.memory.get_font_address(vx & 0xF)?;
+        self.registers.set_i(font_addr);
         Ok(())
     }
 
-    fn ld_f_vx(&mut self, x: u8) -> Result<()> {
-        let vx = self.registers.get_v(x)?;
-        let font_addr = self.memory.get_font_address(vx & 0xF)?;
-        self.registers.set_i(font_addr);
-        Ok(())
+    /// Writes a byte on behalf of the running program, applying memory protection.
+    fn store_byte(&mut self, address: u16, value: u8) -> Result<()> {
+        if self.memory_protection != MemoryProtection::Off {
+            if let Some(region) = self.memory.reserved_region(address) {
+                // PC has already moved past the storing instruction
+                let pc = self.registers.get_pc().wrapping_sub(2);
+                self.protection_violation = Some(address);
+                self.events.emit(EmulatorEvent::ProtectedWrite {
+                    pc,
+                    address,
+                    region,
+                });
+
+                return match self.memory_protection {
+                    MemoryProtection::Fault => Err(EmulatorError::ProtectedMemoryWrite { address }),
+                    _ => Ok(()),
+                };
+            }
+        }
+
+        self.memory.write_byte(address, value)?;
+        if let Some(counts) = &mut self.access_counts {
+            counts.record_write(address);
+        }
+        if self
+            .executed
+            .as_ref()
+            .is_some_and(|executed| executed.get(address as usize) == Some(&true))
+        {
+            let pc = self.registers.get_pc().wrapping_sub(2);
+            logging::info!(
+                target: CPU,
+                "Self-modifying code: {:#05x} overwrote code at {:#05x}",
+                pc,
+                address
+            );
+            self.events
+                .emit(EmulatorEvent::SelfModified { pc, address });
+            if let Some(entry) = self.trace.back_mut() {
+                entry.modified_code = Some(address);
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a byte on behalf of the running program, counting the access.
+    fn load_byte(&mut self, address: u16) -> Result<u8> {
+        let value = self.memory.read_byte(address)?;
+        if let Some(counts) = &mut self.access_counts {
+            counts.record_read(address);
+        }
+        Ok(value)
+    }
+
+    /// Forgets which bytes ran as code, e.g. after loading a new program.
+    fn forget_executed(&mut self) {
+        if self.executed.is_some() {
+            self.executed = Some(vec![false; self.memory.size()]);
+        }
+    }
+
+    /// Zeroes the access counts, e.g. after loading a new program.
+    fn reset_access_counts(&mut self) {
+        if self.access_counts.is_some() {
+            self.access_counts = Some(AccessCounts::new(self.memory.size()));
+        }
     }
 
     fn ld_b_vx(&mut self, x: u8) -> Result<()> {
@@ -698,9 +1804,9 @@ impl Cpu {
         let tens = (vx / 10) % 10;
         let ones = vx % 10;
 
-        self.memory.write_byte(i, hundreds)?;
-        self.memory.write_byte(i + 1, tens)?;
-        self.memory.write_byte(i + 2, ones)?;
+        self.store_byte(self.offset_address(i, 0), hundreds)?;
+        self.store_byte(self.offset_address(i, 1), tens)?;
+        self.store_byte(self.offset_address(i, 2), ones)?;
 
         Ok(())
     }
@@ -710,9 +1816,10 @@ impl Cpu {
 
         for reg in 0..=x {
             let value = self.registers.get_v(reg)?;
-            self.memory.write_byte(i + reg as u16, value)?;
+            self.store_byte(self.offset_address(i, reg as u16), value)?;
         }
 
+        self.advance_i_past(i, x);
         Ok(())
     }
 
@@ -720,24 +1827,43 @@ impl Cpu {
         let i = self.registers.get_i();
 
         for reg in 0..=x {
-            let value = self.memory.read_byte(i + reg as u16)?;
+            let value = self.load_byte(self.offset_address(i, reg as u16))?;
             self.registers.set_v(reg, value)?;
         }
 
-        // Original Chip-8 behavior: don't modify I register
+        self.advance_i_past(i, x);
         Ok(())
     }
+
+    /// Leaves I after register X of a load/store when load/store increments I.
+    fn advance_i_past(&mut self, i: u16, x: u8) {
+        if self.load_store_increments_i {
+            self.registers.set_i(i.wrapping_add(x as u16 + 1));
+        }
+    }
 }
 
-impl Default for Cpu {
+#[cfg(feature = "std")]
+impl Default for Cpu<DynHardware> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-#[cfg(test)]
+// Every test drives the default `DynHardware`, which needs `std`.
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use super::*;
+    use crate::emulator::ReservedRegion;
+
+    #[test]
+    fn test_cpu_is_send() {
+        fn assert_send<T: Send>() {}
+
+        assert_send::<Cpu<crate::hardware::NullHardware>>();
+        assert_send::<Cpu<crate::hardware::ThreadedHardware>>();
+        assert_send::<SharedInput>();
+    }
 
     #[test]
     fn test_cpu_creation() {
@@ -917,11 +2043,10 @@ mod tests {
     #[test]
     fn test_key_wait() {
         use crate::hardware::input::{ChipKey, SoftwareInput};
-        use std::cell::RefCell;
-        use std::rc::Rc;
+        use std::sync::{Arc, Mutex};
 
         let mut cpu = Cpu::new();
-        let input = Rc::new(RefCell::new(SoftwareInput::new()));
+        let input = Arc::new(Mutex::new(SoftwareInput::new()));
         cpu.set_input(input.clone());
 
         // Test FX0A - Wait for key
@@ -929,22 +2054,163 @@ mod tests {
         assert!(cpu.is_waiting_for_key());
 
         // Initially no keys pressed - should still be waiting
-        cpu.cycle().unwrap();
+        assert_eq!(cpu.cycle().unwrap(), CpuEvent::WaitingForKey);
         assert!(cpu.is_waiting_for_key());
 
         // Press a key - should transition to waiting for release
-        input.borrow_mut().press_key(ChipKey::Key5);
-        cpu.cycle().unwrap();
+        input.lock().unwrap().press_key(ChipKey::Key5);
+        assert_eq!(cpu.cycle().unwrap(), CpuEvent::WaitingForKey);
         assert!(cpu.is_waiting_for_key()); // Still waiting for release
 
         // Release the key - should complete the operation
-        input.borrow_mut().release_key(ChipKey::Key5);
-        cpu.cycle().unwrap();
+        input.lock().unwrap().release_key(ChipKey::Key5);
+        assert_eq!(cpu.cycle().unwrap(), CpuEvent::Resumed);
 
         assert!(!cpu.is_waiting_for_key());
         assert_eq!(cpu.registers.get_v(1).unwrap(), 0x5);
     }
 
+    #[test]
+    fn test_key_read_completes_latency() {
+        use crate::hardware::input::{ChipKey, SoftwareInput};
+        use crate::input::latency::KeyStamps;
+        use std::sync::{Arc, Mutex};
+
+        let mut cpu = Cpu::new();
+        let input = Arc::new(Mutex::new(SoftwareInput::new()));
+        cpu.set_input(input.clone());
+        cpu.registers.set_v(0, 0x7).unwrap();
+
+        let stamps = KeyStamps::mapped_now(Instant::now());
+        input
+            .lock()
+            .unwrap()
+            .press_key_stamped(ChipKey::Key7, stamps);
+        assert_eq!(cpu.metrics().input_latency.key(ChipKey::Key7).samples, 0);
+
+        // SKNP V0 reads the held key, which completes the measurement
+        cpu.execute_instruction(0xE0A1).unwrap();
+        cpu.execute_instruction(0xE0A1).unwrap();
+        let latency = cpu.metrics().input_latency;
+        assert_eq!(latency.key(ChipKey::Key7).samples, 1);
+        assert_eq!(latency.last_key, Some(ChipKey::Key7));
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        // LD I, 0x50; DRW V0, V0, 5; CALL 0x208; 0x208: LD DT, V1; JP 0x20A
+        let rom = [
+            0xA0, 0x50, 0xD0, 0x05, 0x22, 0x08, 0x00, 0x00, 0xF1, 0x15, 0x12, 0x0A,
+        ];
+        let mut cpu = Cpu::new();
+        cpu.load_rom(&rom).unwrap();
+        cpu.registers.set_v(1, 42).unwrap();
+        cpu.set_realtime_timers(false);
+        for _ in 0..3 {
+            cpu.cycle().unwrap();
+        }
+
+        let snapshot = cpu.snapshot();
+        assert_eq!(snapshot.stack, vec![0x206]);
+        assert_eq!(snapshot.display[0], 0xF0);
+
+        cpu.cycle().unwrap();
+        cpu.execute_instruction(0x00E0).unwrap();
+        cpu.memory.write_byte(0x300, 0xAA).unwrap();
+        assert_ne!(cpu.snapshot(), snapshot);
+
+        cpu.restore(&snapshot).unwrap();
+        assert_eq!(cpu.snapshot(), snapshot);
+        assert_eq!(cpu.get_timers().get_delay_timer(), 0);
+        assert!(cpu.get_display_buffer()[0]);
+
+        // A snapshot from a bigger machine is refused without changes
+        let mut bigger = snapshot.clone();
+        bigger.memory.push(0);
+        bigger.pc = 0x400;
+        assert!(matches!(
+            cpu.restore(&bigger),
+            Err(EmulatorError::SnapshotMismatch { part: "memory", .. })
+        ));
+        assert_eq!(cpu.snapshot(), snapshot);
+
+        // So is a hi-res one, without switching the display resolution
+        let mut hires = bigger.clone();
+        hires.display_size = Some((64, 64));
+        hires.display = vec![0; 64 * 64 / 8];
+        assert!(cpu.restore(&hires).is_err());
+        hires.memory.pop();
+        hires.stack = vec![0x200; STACK_SIZE + 1];
+        assert!(matches!(
+            cpu.restore(&hires),
+            Err(EmulatorError::StackOverflow)
+        ));
+        assert_eq!(cpu.display_size(), (64, 32));
+        assert_eq!(cpu.snapshot(), snapshot);
+    }
+
+    #[test]
+    fn test_access_profiling() {
+        // LD I, 0x300; LD B, V0; LD V1, [I]; DRW V0, V0, 2; JP 0x208
+        let rom = [0xA3, 0x00, 0xF0, 0x33, 0xF1, 0x65, 0xD0, 0x02, 0x12, 0x08];
+        let mut cpu = Cpu::new();
+        cpu.load_rom(&rom).unwrap();
+        assert!(cpu.access_counts().is_none());
+        cpu.set_access_profiling(true);
+        for _ in 0..6 {
+            cpu.cycle().unwrap();
+        }
+
+        let counts = cpu.access_counts().unwrap();
+        assert_eq!(counts.get(0x200).unwrap().executes, 1);
+        assert_eq!(counts.get(0x209).unwrap().executes, 2);
+        assert_eq!(counts.get(0x300).unwrap().writes, 1);
+        assert_eq!(counts.get(0x302).unwrap().writes, 1);
+        assert_eq!(counts.get(0x300).unwrap().reads, 2);
+        assert_eq!(counts.get(0x301).unwrap().reads, 2);
+        assert_eq!(counts.get(0x302).unwrap().reads, 0);
+        assert!(counts.get(0x400).unwrap().is_untouched());
+
+        cpu.load_rom(&rom).unwrap();
+        assert!(cpu
+            .access_counts()
+            .unwrap()
+            .get(0x200)
+            .unwrap()
+            .is_untouched());
+    }
+
+    #[test]
+    fn test_instruction_info_and_frame_budget() {
+        let mut cpu = Cpu::new();
+        cpu.set_timing_model(TimingModel::CosmacVip);
+        cpu.load_rom(&[0x60, 0x12, 0x12, 0x02]).unwrap(); // LD V0, 0x12; JP 0x202
+        assert!(cpu.last_instruction_info().is_none());
+
+        cpu.cycle().unwrap();
+        let info = cpu.last_instruction_info().unwrap();
+        assert_eq!((info.pc, info.opcode, info.cost), (0x200, 0x6012, 6));
+        assert_eq!(info.bytes(), [0x60, 0x12]);
+        assert_eq!(info.disassembly(), crate::analysis::disassemble(0x6012));
+
+        let mut charged = info.cost as u64;
+        for _ in 0..3 {
+            cpu.cycle().unwrap();
+            charged += cpu.last_instruction_info().unwrap().cost as u64;
+        }
+        let budget = cpu.frame_budget();
+        assert_eq!((budget.cycles, budget.instructions), (charged, 4));
+        assert_eq!(
+            budget.cycles_per_second,
+            TimingModel::CosmacVip.cycles_per_second()
+        );
+
+        // Showing a frame closes its budget and starts the next one
+        cpu.record_frame();
+        assert_eq!(cpu.metrics().frame_budget, budget);
+        assert_eq!(cpu.frame_budget().cycles, 0);
+    }
+
     #[test]
     fn test_font_instruction() {
         let mut cpu = Cpu::new();
@@ -1004,4 +2270,618 @@ mod tests {
         assert_eq!(state.v[5], 0);
         assert_eq!(state.instruction_count, 0);
     }
+
+    #[test]
+    fn test_drw_without_wired_display() {
+        let mut cpu = Cpu::new();
+
+        // Draw the '0' glyph twice; the second draw must report a collision
+        cpu.execute_instruction(0xA050).unwrap(); // I = font '0'
+        cpu.execute_instruction(0xD005).unwrap();
+        assert_eq!(cpu.registers.get_v(0xF).unwrap(), 0);
+        assert!(cpu.get_display_buffer().iter().any(|&p| p));
+
+        cpu.execute_instruction(0xD005).unwrap();
+        assert_eq!(cpu.registers.get_v(0xF).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_cpu_generic_over_hardware() {
+        use crate::hardware::NullHardware;
+
+        let mut cpu = Cpu::with_hardware(NullHardware::new());
+        cpu.load_rom(&[0x60, 0x10, 0xF0, 0x18]).unwrap(); // V0 = 0x10; ST = V0
+
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+
+        assert!(cpu.hardware_mut().audio().is_playing());
+    }
+
+    #[test]
+    fn test_resume_with_key() {
+        let mut cpu = Cpu::new();
+        cpu.load_rom(&[0xF3, 0x0A, 0x60, 0x01]).unwrap(); // LD V3, K; LD V0, 1
+
+        // Not waiting yet, so nothing to resume
+        assert!(!cpu.resume_with_key(ChipKey::Key1).unwrap());
+
+        assert_eq!(cpu.cycle().unwrap(), CpuEvent::WaitingForKey);
+        assert_eq!(cpu.cycle().unwrap(), CpuEvent::WaitingForKey);
+        assert_eq!(cpu.get_state().instruction_count, 1);
+
+        assert!(cpu.resume_with_key(ChipKey::KeyB).unwrap());
+        assert!(!cpu.is_waiting_for_key());
+        assert_eq!(cpu.registers.get_v(3).unwrap(), 0xB);
+
+        assert_eq!(cpu.cycle().unwrap(), CpuEvent::Executed);
+        assert_eq!(cpu.registers.get_v(0).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_emulator_events() {
+        let mut cpu = Cpu::new();
+        let events = cpu.subscribe_channel();
+
+        // CLS; LD V0, 10; LD ST, V0; JP 0x206 (self)
+        cpu.load_rom(&[0x00, 0xE0, 0x60, 0x0A, 0xF0, 0x18, 0x12, 0x06])
+            .unwrap();
+        for _ in 0..6 {
+            cpu.cycle().unwrap();
+        }
+
+        let received: Vec<EmulatorEvent> = events.try_iter().collect();
+        assert_eq!(
+            received,
+            vec![
+                EmulatorEvent::DisplayUpdated,
+                EmulatorEvent::BeepStarted,
+                EmulatorEvent::Halted { pc: 0x206 },
+            ]
+        );
+        assert!(cpu.is_halted());
+    }
+
+    #[test]
+    fn test_sound_timer_mode_reaches_buzzer() {
+        let config = crate::frontend::EmulatorBehaviorConfig::cosmac_vip();
+        let mut cpu = Cpu::new_with_config(&config);
+        cpu.set_realtime_timers(false);
+        let events = cpu.subscribe_channel();
+
+        // LD V0, 1; LD ST, V0; LD V0, 2; LD ST, V0
+        cpu.load_rom(&[0x60, 0x01, 0xF0, 0x18, 0x60, 0x02, 0xF0, 0x18])
+            .unwrap();
+        for _ in 0..3 {
+            cpu.cycle().unwrap();
+        }
+        assert_eq!(cpu.timers.get_sound_timer(), 1);
+        assert!(events.try_iter().next().is_none());
+
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            vec![EmulatorEvent::BeepStarted]
+        );
+    }
+
+    #[test]
+    fn test_breakpoint_event() {
+        let mut cpu = Cpu::new();
+        let events = cpu.subscribe_channel();
+        cpu.load_rom(&[0x60, 0x01, 0x61, 0x02]).unwrap();
+        cpu.add_breakpoint(0x202);
+
+        assert_eq!(cpu.cycle().unwrap(), CpuEvent::Executed);
+        assert_eq!(cpu.cycle().unwrap(), CpuEvent::Breakpoint);
+        assert_eq!(cpu.registers.get_v(1).unwrap(), 0);
+
+        // The next cycle steps over the breakpoint
+        assert_eq!(cpu.cycle().unwrap(), CpuEvent::Executed);
+        assert_eq!(cpu.registers.get_v(1).unwrap(), 2);
+        assert_eq!(
+            events.try_recv(),
+            Ok(EmulatorEvent::Breakpoint { pc: 0x202 })
+        );
+    }
+
+    #[test]
+    fn test_unknown_opcode_event() {
+        use std::sync::{Arc, Mutex};
+
+        let mut cpu = Cpu::new();
+        let seen = Arc::new(Mutex::new(None));
+        let sink = Arc::clone(&seen);
+        cpu.subscribe(move |event| {
+            if let EmulatorEvent::UnknownOpcode { pc, opcode } = event {
+                *sink.lock().unwrap() = Some((*pc, *opcode));
+            }
+        });
+
+        cpu.load_rom(&[0xFF, 0xFF]).unwrap();
+        assert!(cpu.cycle().is_err());
+        assert_eq!(*seen.lock().unwrap(), Some((0x200, 0xFFFF)));
+    }
+
+    #[test]
+    fn test_execution_trace() {
+        let mut cpu = Cpu::new();
+        // LD V0, 1; LD V1, 2; LD V2, 3; unknown
+        cpu.load_rom(&[0x60, 0x01, 0x61, 0x02, 0x62, 0x03, 0xFF, 0xFF])
+            .unwrap();
+
+        cpu.cycle().unwrap();
+        assert!(cpu.trace().is_empty());
+
+        cpu.set_trace_length(2);
+        for _ in 0..2 {
+            cpu.cycle().unwrap();
+        }
+        assert!(cpu.cycle().is_err());
+
+        let trace: Vec<_> = cpu.trace().iter().copied().collect();
+        assert_eq!(
+            trace,
+            vec![
+                TraceEntry {
+                    pc: 0x204,
+                    opcode: 0x6203,
+                    modified_code: None,
+                },
+                TraceEntry {
+                    pc: 0x206,
+                    opcode: 0xFFFF,
+                    modified_code: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_seeded_rng_is_deterministic() {
+        let rom = [0xC0, 0xFF, 0xC1, 0xFF, 0xC2, 0xFF]; // RND V0-V2, 0xFF
+        let run = |seed| {
+            let mut cpu = Cpu::new();
+            cpu.seed_rng(seed);
+            cpu.load_rom(&rom).unwrap();
+            for _ in 0..3 {
+                cpu.cycle().unwrap();
+            }
+            cpu.get_state().v
+        };
+
+        assert_eq!(run(42), run(42));
+        assert_ne!(run(42), run(43));
+    }
+
+    #[test]
+    fn test_shadow_call_stack() {
+        let mut cpu = Cpu::new();
+        // CALL 0x206; JP 0x202; -; CALL 0x20A; RET; RET
+        cpu.load_rom(&[
+            0x22, 0x06, 0x12, 0x02, 0x00, 0x00, 0x22, 0x0A, 0x00, 0xEE, 0x00, 0xEE,
+        ])
+        .unwrap();
+
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        assert_eq!(
+            cpu.call_stack(),
+            &[
+                CallFrame {
+                    call_site: 0x200,
+                    target: 0x206
+                },
+                CallFrame {
+                    call_site: 0x206,
+                    target: 0x20A
+                },
+            ]
+        );
+
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        assert!(cpu.call_stack().is_empty());
+        assert_eq!(cpu.get_state().pc, 0x202);
+    }
+
+    #[test]
+    fn test_clip_mode_reaches_display() {
+        let config = crate::frontend::EmulatorBehaviorConfig {
+            clip_mode: ClipMode::Clip,
+            ..Default::default()
+        };
+        let mut cpu = Cpu::new_with_config(&config);
+        assert_eq!(cpu.hardware().display_ref().clip_mode(), ClipMode::Clip);
+
+        // A replacement display picks up the mode
+        cpu.set_display(Box::new(crate::hardware::display::SoftwareDisplay::new()));
+        assert_eq!(cpu.hardware().display_ref().clip_mode(), ClipMode::Clip);
+
+        // LD V0, 0x3F; LD I, font 0; DRW V0, V0, 5
+        cpu.load_rom(&[0x60, 0x3F, 0xA0, 0x50, 0xD0, 0x05]).unwrap();
+        for _ in 0..3 {
+            cpu.cycle().unwrap();
+        }
+        let lit = cpu.get_display_buffer().iter().filter(|&&p| p).count();
+        assert_eq!(lit, 1);
+    }
+
+    #[test]
+    fn test_configure_loads_font() {
+        let config = crate::frontend::EmulatorBehaviorConfig {
+            font: crate::emulator::FontSet::Eti660,
+            ..Default::default()
+        };
+        let mut cpu = Cpu::new_with_config(&config);
+        assert_eq!(cpu.get_memory().read_byte(0x55).unwrap(), 0x20);
+
+        // The font stays after a reset clears memory
+        cpu.reset();
+        assert_eq!(cpu.get_memory().read_byte(0x55).unwrap(), 0x20);
+    }
+
+    #[test]
+    fn test_xo_chip_memory_layout() {
+        let config = crate::frontend::EmulatorBehaviorConfig {
+            memory_layout: crate::emulator::MemoryLayout::XoChip64K,
+            address_masking: AddressMasking::Mask,
+            ..Default::default()
+        };
+        let mut cpu = Cpu::new_with_config(&config);
+        assert_eq!(cpu.get_memory().size(), 0x10000);
+
+        // LD V0, 0xFF; JP V0, 0xFFF lands past 0xFFF instead of being masked
+        cpu.load_rom(&[0x60, 0xFF, 0xBF, 0xFF]).unwrap();
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.registers.get_pc(), 0x10FE);
+
+        // LD V0, 5 above 4KB runs like anywhere else
+        cpu.memory_mut().write_word(0x10FE, 0x6005).unwrap();
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.registers.get_v(0).unwrap(), 5);
+    }
+
+    #[test]
+    fn test_jump_with_offset_at_edges() {
+        // LD V0, 0xFF; LD V2, 0x10; JP V0, 0xFFF
+        let rom = [0x60, 0xFF, 0x62, 0x10, 0xBF, 0xFF];
+        let mut cpu = Cpu::new();
+        cpu.load_rom(&rom).unwrap();
+        for _ in 0..3 {
+            cpu.cycle().unwrap();
+        }
+        assert_eq!(cpu.registers.get_pc(), 0x10FE);
+        // Unmasked, the target lies outside memory
+        assert!(matches!(
+            cpu.cycle().unwrap_err().root(),
+            EmulatorError::InvalidMemoryAccess { address: 0x10FE }
+        ));
+
+        let mut cpu = Cpu::new();
+        cpu.set_address_masking(AddressMasking::Mask);
+        cpu.load_rom(&rom).unwrap();
+        for _ in 0..3 {
+            cpu.cycle().unwrap();
+        }
+        assert_eq!(cpu.registers.get_pc(), 0x0FE);
+
+        // SUPER-CHIP BXNN: BF FF jumps to 0xFFF + VF, and B2 30 to 0x230 + V2
+        let mut cpu = Cpu::new();
+        cpu.set_jump_with_vx(true);
+        cpu.load_rom(&[0x62, 0x10, 0xB2, 0x30]).unwrap();
+        for _ in 0..2 {
+            cpu.cycle().unwrap();
+        }
+        assert_eq!(cpu.registers.get_pc(), 0x240);
+    }
+
+    #[test]
+    fn test_mega_chip_mode() {
+        let mut rom = vec![
+            0x00, 0x11, // MEGAON
+            0x01, 0x00, 0x03, 0x00, // LDHI 0x000300
+            0x02, 0x02, // LDPAL 2
+            0x03, 0x02, // SPRW 2
+            0x04, 0x01, // SPRH 1
+            0x09, 0x02, // COLLIDE 2
+            0x01, 0x00, 0x03, 0x08, // LDHI 0x000308
+            0xD0, 0x00, // DRW V0, V0
+            0xD0, 0x00, // DRW V0, V0
+            0x01, 0x01, 0x00, 0x00, // LDHI 0x010000
+        ];
+        rom.resize(0x100, 0);
+        rom.extend_from_slice(&[0xFF, 0xFF, 0x00, 0x00, 0xFF, 0x00, 0x00, 0xFF, 0x01, 0x02]);
+
+        // Without the variant, 0011 is an ignored system call
+        let mut cpu = Cpu::new();
+        cpu.load_rom(&rom).unwrap();
+        cpu.cycle().unwrap();
+        assert!(cpu.mega_chip_screen().is_none());
+
+        let mut cpu = Cpu::new();
+        cpu.set_mega_chip(true);
+        cpu.load_rom(&rom).unwrap();
+        for _ in 0..8 {
+            cpu.cycle().unwrap();
+        }
+        let screen = cpu.mega_chip_screen().unwrap();
+        assert_eq!(screen.palette()[1], 0xFFFF_0000);
+        assert_eq!((screen.pixel(0, 0), screen.pixel(1, 0)), (Some(1), Some(2)));
+        assert_eq!(cpu.registers.get_v(0xF).unwrap(), 0);
+
+        // Drawing again covers the collision index
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.registers.get_v(0xF).unwrap(), 1);
+        assert_eq!(cpu.snapshot().mega_chip.as_ref(), cpu.mega_chip_screen());
+
+        let err = cpu.cycle().unwrap_err();
+        assert!(err.to_string().contains("0x010000"));
+
+        cpu.reset();
+        assert!(cpu.mega_chip_screen().is_none());
+    }
+
+    #[test]
+    fn test_hires_mode() {
+        let mut rom = vec![0x12, 0x60]; // JP 0x260, the hi-res startup jump
+        rom.resize(0xC0, 0);
+        rom.extend_from_slice(&[
+            0x60, 0x3C, // LD V0, 60
+            0xD0, 0x01, // DRW V0, V0, 1
+            0x02, 0x30, // Hi-res CLS
+        ]);
+
+        let mut cpu = Cpu::new();
+        cpu.load_rom(&rom).unwrap();
+        assert!(cpu.is_hires());
+        assert_eq!(cpu.display_size(), (64, 64));
+
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.registers.get_pc(), 0x2C0);
+        cpu.registers.set_i(0x50); // Top row of the "0" glyph
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        assert!(cpu.hardware.display_ref().get_pixel(60, 60).unwrap());
+
+        // Snapshots carry the resolution
+        let snapshot = cpu.snapshot();
+        assert_eq!(snapshot.display_size(), (64, 64));
+        cpu.reset();
+        assert_eq!(cpu.display_size(), (64, 32));
+        cpu.restore(&snapshot).unwrap();
+        assert_eq!(cpu.display_size(), (64, 64));
+        assert!(cpu.hardware.display_ref().get_pixel(60, 60).unwrap());
+
+        cpu.cycle().unwrap();
+        assert!(cpu.get_display_buffer().iter().all(|&p| !p));
+
+        // Forced modes ignore the first instruction
+        let mut cpu = Cpu::new();
+        cpu.set_hires_mode(HiresMode::Off);
+        cpu.load_rom(&rom).unwrap();
+        assert!(!cpu.is_hires());
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.registers.get_pc(), 0x260);
+
+        cpu.set_hires_mode(HiresMode::FourPage);
+        cpu.load_rom(&[0x00, 0xE0]).unwrap();
+        assert_eq!(cpu.display_size(), (64, 128));
+    }
+
+    #[test]
+    fn test_shift_and_load_store_quirks() {
+        // LD V1, 0x81; SHR V0, V1; SHL V2, V1; LD I, 0x300; LD [I], V2
+        let rom = [0x61, 0x81, 0x80, 0x16, 0x82, 0x1E, 0xA3, 0x00, 0xF2, 0x55];
+
+        let mut cpu = Cpu::new();
+        cpu.load_rom(&rom).unwrap();
+        for _ in 0..5 {
+            cpu.cycle().unwrap();
+        }
+        assert_eq!(cpu.registers.get_v(0).unwrap(), 0);
+        assert_eq!(cpu.registers.get_v(2).unwrap(), 0);
+        assert_eq!(cpu.registers.get_i(), 0x300);
+
+        // COSMAC VIP: shifts read VY and load/store leaves I past VX
+        let mut cpu = Cpu::new();
+        cpu.set_shift_uses_vy(true);
+        cpu.set_load_store_increments_i(true);
+        cpu.load_rom(&rom).unwrap();
+        for _ in 0..5 {
+            cpu.cycle().unwrap();
+        }
+        assert_eq!(cpu.registers.get_v(0).unwrap(), 0x40);
+        assert_eq!(cpu.registers.get_v(2).unwrap(), 0x02);
+        assert_eq!(cpu.registers.get_v(0xF).unwrap(), 1);
+        assert_eq!(cpu.registers.get_i(), 0x303);
+    }
+
+    #[test]
+    fn test_index_arithmetic_at_edges() {
+        // LD I, 0xFFF; LD V0, 1; ADD I, V0
+        let rom = [0xAF, 0xFF, 0x60, 0x01, 0xF0, 0x1E];
+        let mut cpu = Cpu::new();
+        cpu.load_rom(&rom).unwrap();
+        for _ in 0..3 {
+            cpu.cycle().unwrap();
+        }
+        assert_eq!(cpu.registers.get_i(), 0x1000);
+        assert_eq!(cpu.registers.get_v(0xF).unwrap(), 1);
+
+        let mut cpu = Cpu::new();
+        cpu.set_address_masking(AddressMasking::Mask);
+        cpu.load_rom(&rom).unwrap();
+        for _ in 0..3 {
+            cpu.cycle().unwrap();
+        }
+        assert_eq!(cpu.registers.get_i(), 0x000);
+        assert_eq!(cpu.registers.get_v(0xF).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_index_relative_access_at_edges() {
+        // LD V0, 1; LD V1, 2; LD V2, 3; LD I, 0xFFE; LD [I], V2
+        let rom = [0x60, 0x01, 0x61, 0x02, 0x62, 0x03, 0xAF, 0xFE, 0xF2, 0x55];
+        let mut cpu = Cpu::new();
+        cpu.load_rom(&rom).unwrap();
+        for _ in 0..4 {
+            cpu.cycle().unwrap();
+        }
+        assert!(matches!(
+            cpu.cycle().unwrap_err().root(),
+            EmulatorError::InvalidMemoryAccess { address: 0x1000 }
+        ));
+
+        // Masked, the third byte lands at 0x000
+        let mut cpu = Cpu::new();
+        cpu.set_address_masking(AddressMasking::Mask);
+        cpu.load_rom(&rom).unwrap();
+        for _ in 0..5 {
+            cpu.cycle().unwrap();
+        }
+        assert_eq!(cpu.memory.read_byte(0xFFE).unwrap(), 1);
+        assert_eq!(cpu.memory.read_byte(0xFFF).unwrap(), 2);
+        assert_eq!(cpu.memory.read_byte(0x000).unwrap(), 3);
+
+        // LD V2, [I] and a two-row sprite read back across the edge too
+        cpu.execute_arbitrary(0x6200).unwrap();
+        cpu.execute_arbitrary(0xF265).unwrap();
+        assert_eq!(cpu.registers.get_v(2).unwrap(), 3);
+        cpu.execute_arbitrary(0xAFFF).unwrap();
+        cpu.execute_arbitrary(0x6000).unwrap();
+        cpu.execute_arbitrary(0xD002).unwrap();
+        assert!(cpu.get_display_buffer()[6]); // 0x02 on the first row
+        assert!(cpu.get_display_buffer()[64 + 6]); // 0x03 from 0x000 on the second
+        assert!(cpu.get_display_buffer()[64 + 7]);
+    }
+
+    #[test]
+    fn test_memory_protection() {
+        // LD I, 0x050; LD V0, 0xAA; LD [I], V0
+        let rom = [0xA0, 0x50, 0x60, 0xAA, 0xF0, 0x55];
+
+        let mut cpu = Cpu::new();
+        cpu.load_rom(&rom).unwrap();
+        (0..3).for_each(|_| {
+            cpu.cycle().unwrap();
+        });
+        assert_eq!(cpu.memory.read_byte(0x050).unwrap(), 0xAA);
+        assert_eq!(cpu.take_protection_violation(), None);
+
+        let mut cpu = Cpu::new();
+        cpu.set_memory_protection(MemoryProtection::Ignore);
+        let events = cpu.subscribe_channel();
+        cpu.load_rom(&rom).unwrap();
+        (0..3).for_each(|_| {
+            cpu.cycle().unwrap();
+        });
+        assert_eq!(cpu.memory.read_byte(0x050).unwrap(), 0xF0);
+        assert_eq!(cpu.take_protection_violation(), Some(0x050));
+        assert!(events.try_iter().any(|e| e
+            == EmulatorEvent::ProtectedWrite {
+                pc: 0x204,
+                address: 0x050,
+                region: ReservedRegion::Font,
+            }));
+
+        let mut cpu = Cpu::new();
+        cpu.set_memory_protection(MemoryProtection::Fault);
+        cpu.load_rom(&rom).unwrap();
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+        let error = cpu.cycle().unwrap_err();
+        assert!(matches!(
+            error.root(),
+            EmulatorError::ProtectedMemoryWrite { address: 0x050 }
+        ));
+        assert_eq!(error.context().unwrap().pc, 0x204);
+    }
+
+    #[test]
+    fn test_self_modifying_code() {
+        // LD I, 0x208; LD V0, 0x61; LD [I], V0; JP 0x208; LD V0, 0x07
+        let rom = [0xA2, 0x08, 0x60, 0x61, 0xF0, 0x55, 0x12, 0x08, 0x60, 0x07];
+
+        let mut cpu = Cpu::new();
+        cpu.set_trace_length(4);
+        cpu.set_self_modification_tracking(true);
+        let events = cpu.subscribe_channel();
+        cpu.load_rom(&rom).unwrap();
+
+        // Writing to code that hasn't run yet is not self-modification
+        (0..5).for_each(|_| {
+            cpu.cycle().unwrap();
+        });
+        assert_eq!(cpu.get_registers().get_v(1).unwrap(), 0x07);
+        assert!(events
+            .try_iter()
+            .all(|e| !matches!(e, EmulatorEvent::SelfModified { .. })));
+
+        // Rewriting the first instruction after it ran is
+        cpu.registers_mut().set_i(0x200);
+        cpu.registers_mut().set_pc(0x204);
+        cpu.cycle().unwrap();
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            vec![EmulatorEvent::SelfModified {
+                pc: 0x204,
+                address: 0x200
+            }]
+        );
+        assert_eq!(cpu.trace().back().unwrap().modified_code, Some(0x200));
+
+        // Loading a program forgets what ran
+        cpu.load_rom(&rom).unwrap();
+        cpu.registers_mut().set_i(0x200);
+        cpu.registers_mut().set_pc(0x204);
+        cpu.cycle().unwrap();
+        assert_eq!(events.try_iter().count(), 0);
+    }
+
+    #[test]
+    fn test_cycle_clock_timers() {
+        // LD V0, 0x10; LD DT, V0; JP 0x204
+        let mut cpu = Cpu::new();
+        cpu.set_timing_model(TimingModel::Simple(600));
+        cpu.use_cycle_clock();
+        cpu.load_rom(&[0x60, 0x10, 0xF0, 0x15, 0x12, 0x04]).unwrap();
+
+        // Two cycles to set DT, then 10 per 60Hz tick at 600 cycles per second
+        for _ in 0..2 + 10 * 3 {
+            cpu.cycle().unwrap();
+        }
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.get_timers().get_delay_timer(), 0x0D);
+
+        // Switching to a custom source stops following cycles
+        let clock = crate::emulator::MockClock::new();
+        cpu.set_time_source(clock.clone());
+        for _ in 0..100 {
+            cpu.cycle().unwrap();
+        }
+        assert_eq!(cpu.get_timers().get_delay_timer(), 0x0D);
+        clock.advance(std::time::Duration::from_millis(17));
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.get_timers().get_delay_timer(), 0x0C);
+    }
+
+    #[test]
+    fn test_manual_timers() {
+        let mut cpu = Cpu::new();
+        cpu.set_realtime_timers(false);
+        cpu.load_rom(&[0x60, 0x10, 0xF0, 0x15, 0x12, 0x04]).unwrap(); // DT = 0x10
+        cpu.cycle().unwrap();
+        cpu.cycle().unwrap();
+
+        std::thread::sleep(std::time::Duration::from_millis(40));
+        cpu.cycle().unwrap();
+        assert_eq!(cpu.get_timers().get_delay_timer(), 0x10);
+
+        cpu.timers_mut().update_by_ticks(1);
+        assert_eq!(cpu.get_timers().get_delay_timer(), 0x0F);
+    }
 }
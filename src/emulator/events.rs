@@ -0,0 +1,163 @@
+//! Emulator event notifications.
+//!
+//! The CPU emits structured events (display updates, beeps, breakpoints,
+//! faults) so frontends and tools can react to them instead of polling.
+//! Callbacks work everywhere; channel subscriptions need `std`.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use crate::emulator::memory::ReservedRegion;
+
+/// Events emitted by the emulator core.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmulatorEvent {
+    /// The display contents changed (CLS or DRW executed).
+    DisplayUpdated,
+
+    /// The sound timer became non-zero.
+    BeepStarted,
+
+    /// The sound timer reached zero.
+    BeepStopped,
+
+    /// Execution stopped at a breakpoint before running the instruction at `pc`.
+    Breakpoint { pc: u16 },
+
+    /// An unknown instruction was encountered.
+    UnknownOpcode { pc: u16, opcode: u16 },
+
+    /// The program halted by jumping to itself.
+    Halted { pc: u16 },
+
+    /// The instruction at `pc` overwrote code that had already run.
+    SelfModified { pc: u16, address: u16 },
+
+    /// The instruction at `pc` wrote to protected memory.
+    ProtectedWrite {
+        pc: u16,
+        address: u16,
+        region: ReservedRegion,
+    },
+}
+
+/// Callback invoked for every emitted event.
+pub type EventCallback = Box<dyn FnMut(&EmulatorEvent) + Send>;
+
+/// Dispatches emulator events to callbacks and channels.
+#[derive(Default)]
+pub struct EventBus {
+    /// Registered callbacks.
+    callbacks: Vec<EventCallback>,
+
+    /// Registered channel senders.
+    #[cfg(feature = "std")]
+    senders: Vec<Sender<EmulatorEvent>>,
+}
+
+impl EventBus {
+    /// Creates an event bus with no subscribers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a callback for all future events.
+    pub fn subscribe(&mut self, callback: impl FnMut(&EmulatorEvent) + Send + 'static) {
+        self.callbacks.push(Box::new(callback));
+    }
+
+    /// Creates a channel that receives all future events.
+    ///
+    /// The channel is unsubscribed automatically when the receiver is dropped.
+    #[cfg(feature = "std")]
+    pub fn subscribe_channel(&mut self) -> Receiver<EmulatorEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.senders.push(sender);
+        receiver
+    }
+
+    /// Checks if anything is subscribed.
+    pub fn has_subscribers(&self) -> bool {
+        #[cfg(feature = "std")]
+        if !self.senders.is_empty() {
+            return true;
+        }
+        !self.callbacks.is_empty()
+    }
+
+    /// Removes all subscribers.
+    pub fn clear(&mut self) {
+        self.callbacks.clear();
+        #[cfg(feature = "std")]
+        self.senders.clear();
+    }
+
+    /// Emits an event to all subscribers.
+    pub fn emit(&mut self, event: EmulatorEvent) {
+        for callback in &mut self.callbacks {
+            callback(&event);
+        }
+
+        // Drop channels whose receivers have gone away
+        #[cfg(feature = "std")]
+        self.senders.retain(|sender| sender.send(event).is_ok());
+    }
+}
+
+impl core::fmt::Debug for EventBus {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let mut debug = f.debug_struct("EventBus");
+        debug.field("callbacks", &self.callbacks.len());
+        #[cfg(feature = "std")]
+        debug.field("senders", &self.senders.len());
+        debug.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_event_bus_callback() {
+        let mut bus = EventBus::new();
+        let received = Arc::new(Mutex::new(Vec::new()));
+
+        let sink = Arc::clone(&received);
+        bus.subscribe(move |event| sink.lock().unwrap().push(*event));
+        assert!(bus.has_subscribers());
+
+        bus.emit(EmulatorEvent::BeepStarted);
+        bus.emit(EmulatorEvent::BeepStopped);
+
+        assert_eq!(
+            *received.lock().unwrap(),
+            vec![EmulatorEvent::BeepStarted, EmulatorEvent::BeepStopped]
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_event_bus_channel() {
+        let mut bus = EventBus::new();
+        let receiver = bus.subscribe_channel();
+
+        bus.emit(EmulatorEvent::DisplayUpdated);
+        assert_eq!(receiver.try_recv(), Ok(EmulatorEvent::DisplayUpdated));
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_event_bus_drops_closed_channels() {
+        let mut bus = EventBus::new();
+        let receiver = bus.subscribe_channel();
+        drop(receiver);
+
+        bus.emit(EmulatorEvent::Halted { pc: 0x200 });
+        assert!(!bus.has_subscribers());
+    }
+}
@@ -0,0 +1,235 @@
+//! Mega-Chip color screen.
+//!
+//! Mega-Chip extends Chip-8 with a 256x192 screen of palette indices.
+//! Sprites are rectangles of index bytes, one per pixel, with index 0
+//! transparent; the palette maps indices to ARGB colors and is loaded from
+//! memory by the program. A draw collides when it covers a pixel of the
+//! program's chosen collision index.
+//!
+//! Support is partial: pixels keep their index rather than their color,
+//! so loading a new palette recolors what is already on screen, and the
+//! alpha and blend mode settings are recorded but draws are always opaque.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+use crate::emulator::snapshot::hex_bytes;
+
+/// Screen width in Mega-Chip mode.
+pub const MEGA_WIDTH: usize = 256;
+
+/// Screen height in Mega-Chip mode.
+pub const MEGA_HEIGHT: usize = 192;
+
+/// Number of palette entries.
+pub const PALETTE_SIZE: usize = 256;
+
+/// Screen, palette and drawing settings of Mega-Chip mode.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MegaChipScreen {
+    /// Palette index of each pixel, row by row.
+    #[serde(with = "hex_bytes")]
+    pixels: Vec<u8>,
+
+    /// ARGB color of each palette index.
+    palette: Vec<u32>,
+
+    /// Width of the sprites DXYN draws, in pixels.
+    sprite_width: usize,
+
+    /// Height of the sprites DXYN draws, in pixels.
+    sprite_height: usize,
+
+    /// Palette index whose pixels cause a collision when drawn over.
+    collision_index: u8,
+
+    /// Sprite alpha set by 05NN.
+    alpha: u8,
+
+    /// Blend mode set by 080N.
+    blend_mode: u8,
+}
+
+impl MegaChipScreen {
+    /// Creates a blank screen with a black palette and 256x256 sprites.
+    pub fn new() -> Self {
+        Self {
+            pixels: vec![0; MEGA_WIDTH * MEGA_HEIGHT],
+            palette: vec![0xFF00_0000; PALETTE_SIZE],
+            sprite_width: 256,
+            sprite_height: 256,
+            collision_index: 0,
+            alpha: 0xFF,
+            blend_mode: 0,
+        }
+    }
+
+    /// Gets the palette index of every pixel, row by row.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Gets the palette index of one pixel, or `None` if it is off screen.
+    pub fn pixel(&self, x: usize, y: usize) -> Option<u8> {
+        if x >= MEGA_WIDTH || y >= MEGA_HEIGHT {
+            return None;
+        }
+        Some(self.pixels[y * MEGA_WIDTH + x])
+    }
+
+    /// Gets the ARGB color of every palette index.
+    pub fn palette(&self) -> &[u32] {
+        &self.palette
+    }
+
+    /// Gets the size of the sprites DXYN draws.
+    pub fn sprite_size(&self) -> (usize, usize) {
+        (self.sprite_width, self.sprite_height)
+    }
+
+    /// Gets the palette index that causes collisions.
+    pub fn collision_index(&self) -> u8 {
+        self.collision_index
+    }
+
+    /// Gets the sprite alpha and blend mode, which are not applied yet.
+    pub fn blending(&self) -> (u8, u8) {
+        (self.alpha, self.blend_mode)
+    }
+
+    /// Clears every pixel to index 0.
+    pub fn clear(&mut self) {
+        self.pixels.fill(0);
+    }
+
+    /// Loads palette entries 1 onwards from ARGB bytes, four per color.
+    ///
+    /// Index 0 is always transparent and is never loaded.
+    pub fn load_palette(&mut self, argb: &[u8]) {
+        for (entry, color) in self.palette[1..].iter_mut().zip(argb.chunks_exact(4)) {
+            *entry = u32::from_be_bytes([color[0], color[1], color[2], color[3]]);
+        }
+    }
+
+    /// Sets the sprite width; 0 means 256.
+    pub fn set_sprite_width(&mut self, width: u8) {
+        self.sprite_width = if width == 0 { 256 } else { width as usize };
+    }
+
+    /// Sets the sprite height; 0 means 256.
+    pub fn set_sprite_height(&mut self, height: u8) {
+        self.sprite_height = if height == 0 { 256 } else { height as usize };
+    }
+
+    /// Sets the palette index that causes collisions.
+    pub fn set_collision_index(&mut self, index: u8) {
+        self.collision_index = index;
+    }
+
+    /// Records the sprite alpha.
+    pub fn set_alpha(&mut self, alpha: u8) {
+        self.alpha = alpha;
+    }
+
+    /// Records the blend mode.
+    pub fn set_blend_mode(&mut self, mode: u8) {
+        self.blend_mode = mode;
+    }
+
+    /// Draws a sprite of palette indices with its top left corner at (x, y).
+    ///
+    /// Index 0 is transparent and never collides. Pixels past the right
+    /// and bottom edges are clipped.
+    ///
+    /// # Arguments
+    /// * `x` - Left edge in pixels
+    /// * `y` - Top edge in pixels
+    /// * `sprite` - Indices row by row, `sprite_size()` wide; missing bytes
+    ///   are transparent
+    ///
+    /// # Returns
+    /// True if a pixel of the collision index was drawn over.
+    pub fn draw_sprite(&mut self, x: usize, y: usize, sprite: &[u8]) -> bool {
+        let mut collision = false;
+        for row in 0..self.sprite_height {
+            let screen_y = y + row;
+            if screen_y >= MEGA_HEIGHT {
+                break;
+            }
+            for column in 0..self.sprite_width {
+                let screen_x = x + column;
+                if screen_x >= MEGA_WIDTH {
+                    break;
+                }
+                let index = sprite
+                    .get(row * self.sprite_width + column)
+                    .copied()
+                    .unwrap_or(0);
+                if index == 0 {
+                    continue;
+                }
+                let pixel = &mut self.pixels[screen_y * MEGA_WIDTH + screen_x];
+                if *pixel == self.collision_index && self.collision_index != 0 {
+                    collision = true;
+                }
+                *pixel = index;
+            }
+        }
+        collision
+    }
+
+    /// Renders the screen through the palette as RGBA pixels.
+    pub fn to_rgba(&self) -> Vec<u8> {
+        let mut rgba = Vec::with_capacity(self.pixels.len() * 4);
+        for &index in &self.pixels {
+            let [_, r, g, b] = self.palette[index as usize].to_be_bytes();
+            rgba.extend_from_slice(&[r, g, b, 0xFF]);
+        }
+        rgba
+    }
+}
+
+impl Default for MegaChipScreen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_palette_and_sprites() {
+        let mut screen = MegaChipScreen::new();
+        screen.load_palette(&[0xFF, 0xFF, 0x00, 0x00, 0xFF, 0x00, 0x00, 0xFF]);
+        assert_eq!(
+            &screen.palette()[..3],
+            &[0xFF00_0000, 0xFFFF_0000, 0xFF00_00FF]
+        );
+
+        screen.set_sprite_width(2);
+        screen.set_sprite_height(2);
+        screen.set_collision_index(2);
+        assert!(!screen.draw_sprite(10, 20, &[1, 0, 2, 1]));
+        assert_eq!(screen.pixel(10, 20), Some(1));
+        assert_eq!(screen.pixel(11, 20), Some(0));
+        assert_eq!(screen.pixel(10, 21), Some(2));
+
+        // Drawing over the collision index collides; transparent pixels don't
+        assert!(screen.draw_sprite(9, 20, &[0, 0, 0, 1]));
+        assert!(!screen.draw_sprite(10, 20, &[0, 0, 0, 0]));
+
+        // Sprites are clipped at the edges
+        assert!(!screen.draw_sprite(MEGA_WIDTH - 1, MEGA_HEIGHT - 1, &[1, 1, 1, 1]));
+        assert_eq!(screen.pixel(MEGA_WIDTH - 1, MEGA_HEIGHT - 1), Some(1));
+
+        let rgba = screen.to_rgba();
+        let offset = (20 * MEGA_WIDTH + 10) * 4;
+        assert_eq!(&rgba[offset..offset + 4], &[0xFF, 0, 0, 0xFF]);
+
+        screen.clear();
+        assert!(screen.pixels().iter().all(|&index| index == 0));
+    }
+}
@@ -1,11 +1,16 @@
 //! Memory management for the Chip-8 emulator.
 //!
-//! This module implements the 4KB memory system with proper bounds checking,
-//! font data initialization, and ROM loading functionality.
+//! This module implements the memory system with proper bounds checking,
+//! font data initialization, and ROM loading functionality. The bytes live
+//! in a `MemoryMap`, 4KB for classic Chip-8.
 
+use alloc::boxed::Box;
+use serde::{Deserialize, Serialize};
+
+use super::memory_map::{Chip8_4K, MemoryLayout, MemoryMap};
 use crate::error::{EmulatorError, Result};
 
-/// Total memory size for Chip-8 system (4KB).
+/// Total memory size for the classic Chip-8 system (4KB).
 pub const MEMORY_SIZE: usize = 4096;
 
 /// Starting address for most programs (512 bytes).
@@ -23,9 +28,12 @@ pub const FONT_SIZE: usize = 80;
 /// Maximum ROM size (MEMORY_SIZE - PROGRAM_START).
 pub const MAX_ROM_SIZE: usize = MEMORY_SIZE - PROGRAM_START as usize;
 
-/// Built-in hexadecimal font set (0-F).
+/// Mask that keeps an address inside the classic 12-bit address space.
+pub const ADDRESS_MASK: u16 = 0x0FFF;
+
+/// Octo's hexadecimal font (0-F), the emulator's original font.
 /// Each character is 4 pixels wide and 5 pixels tall.
-const FONT_SET: [u8; FONT_SIZE] = [
+const OCTO_FONT: [u8; FONT_SIZE] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
     0x20, 0x60, 0x20, 0x20, 0x70, // 1
     0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
@@ -44,26 +52,210 @@ const FONT_SET: [u8; FONT_SIZE] = [
     0xF0, 0x80, 0xF0, 0x80, 0x80, // F
 ];
 
+/// The font in the COSMAC VIP interpreter ROM.
+const COSMAC_VIP_FONT: [u8; FONT_SIZE] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x60, 0x20, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0xA0, 0xA0, 0xF0, 0x20, 0x20, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x10, 0x10, 0x10, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xF0, 0x50, 0x70, 0x50, 0xF0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xF0, 0x50, 0x50, 0x50, 0xF0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+/// The 3-pixel-wide font of the Dream 6800's CHIPOS.
+const DREAM_6800_FONT: [u8; FONT_SIZE] = [
+    0xE0, 0xA0, 0xA0, 0xA0, 0xE0, // 0
+    0x40, 0x40, 0x40, 0x40, 0x40, // 1
+    0xE0, 0x20, 0xE0, 0x80, 0xE0, // 2
+    0xE0, 0x20, 0xE0, 0x20, 0xE0, // 3
+    0x80, 0xA0, 0xA0, 0xE0, 0x20, // 4
+    0xE0, 0x80, 0xE0, 0x20, 0xE0, // 5
+    0xE0, 0x80, 0xE0, 0xA0, 0xE0, // 6
+    0xE0, 0x20, 0x20, 0x20, 0x20, // 7
+    0xE0, 0xA0, 0xE0, 0xA0, 0xE0, // 8
+    0xE0, 0xA0, 0xE0, 0x20, 0xE0, // 9
+    0xE0, 0xA0, 0xE0, 0xA0, 0xA0, // A
+    0xC0, 0xA0, 0xE0, 0xA0, 0xC0, // B
+    0xE0, 0x80, 0x80, 0x80, 0xE0, // C
+    0xC0, 0xA0, 0xA0, 0xA0, 0xC0, // D
+    0xE0, 0x80, 0xE0, 0x80, 0xE0, // E
+    0xE0, 0x80, 0xC0, 0x80, 0x80, // F
+];
+
+/// The 3-pixel-wide font of the ETI-660, with lowercase b and d.
+const ETI_660_FONT: [u8; FONT_SIZE] = [
+    0xE0, 0xA0, 0xA0, 0xA0, 0xE0, // 0
+    0x20, 0x20, 0x20, 0x20, 0x20, // 1
+    0xE0, 0x20, 0xE0, 0x80, 0xE0, // 2
+    0xE0, 0x20, 0xE0, 0x20, 0xE0, // 3
+    0xA0, 0xA0, 0xE0, 0x20, 0x20, // 4
+    0xE0, 0x80, 0xE0, 0x20, 0xE0, // 5
+    0xE0, 0x80, 0xE0, 0xA0, 0xE0, // 6
+    0xE0, 0x20, 0x20, 0x20, 0x20, // 7
+    0xE0, 0xA0, 0xE0, 0xA0, 0xE0, // 8
+    0xE0, 0xA0, 0xE0, 0x20, 0xE0, // 9
+    0xE0, 0xA0, 0xE0, 0xA0, 0xA0, // A
+    0x80, 0x80, 0xE0, 0xA0, 0xE0, // b
+    0xE0, 0x80, 0x80, 0x80, 0xE0, // C
+    0x20, 0x20, 0xE0, 0xA0, 0xE0, // d
+    0xE0, 0x80, 0xE0, 0x80, 0xE0, // E
+    0xE0, 0x80, 0xE0, 0x80, 0x80, // F
+];
+
+/// The hexadecimal font loaded at `FONT_START`.
+///
+/// Only the small 5-byte digits are covered; SUPER-CHIP's FX30 would also
+/// need a set of 10-byte large digits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FontSet {
+    /// Octo's font, used by most modern interpreters.
+    #[default]
+    Octo,
+
+    /// The original COSMAC VIP font.
+    CosmacVip,
+
+    /// The Dream 6800 font.
+    #[serde(rename = "dream-6800")]
+    Dream6800,
+
+    /// The ETI-660 font.
+    #[serde(rename = "eti-660")]
+    Eti660,
+
+    /// A user-provided font, set through `custom_font` in the config file.
+    #[serde(skip)]
+    Custom([u8; FONT_SIZE]),
+}
+
+impl FontSet {
+    /// Creates a user-provided font.
+    ///
+    /// # Returns
+    /// The font, or `None` unless `data` is exactly `FONT_SIZE` bytes.
+    pub fn custom(data: &[u8]) -> Option<Self> {
+        data.try_into().ok().map(FontSet::Custom)
+    }
+
+    /// Gets the 16 glyphs of the font, 5 bytes each.
+    pub fn data(&self) -> &[u8; FONT_SIZE] {
+        match self {
+            FontSet::Octo => &OCTO_FONT,
+            FontSet::CosmacVip => &COSMAC_VIP_FONT,
+            FontSet::Dream6800 => &DREAM_6800_FONT,
+            FontSet::Eti660 => &ETI_660_FONT,
+            FontSet::Custom(data) => data,
+        }
+    }
+}
+
+/// Reserved areas below the program start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReservedRegion {
+    /// The built-in font data.
+    Font,
+
+    /// The rest of the interpreter area below 0x200.
+    Interpreter,
+}
+
+/// What happens when a program writes to reserved memory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MemoryProtection {
+    /// Writes go through (original interpreter behavior).
+    #[default]
+    Off,
+
+    /// Writes fail with an error.
+    Fault,
+
+    /// Writes are silently dropped.
+    Ignore,
+}
+
+/// How computed addresses past 0xFFF are handled.
+///
+/// This covers BNNN jump targets and I-relative addresses (FX1E, and the
+/// bytes FX33, FX55, FX65 and DXYN reach from I).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AddressMasking {
+    /// Addresses keep all 16 bits, so using one past 0xFFF faults or wraps
+    /// according to memory wraparound.
+    #[default]
+    Off,
+
+    /// Addresses are masked to the address space (12 bits on 4KB memory)
+    /// and always land inside memory.
+    Mask,
+}
+
+impl AddressMasking {
+    /// Applies the masking to a computed address.
+    ///
+    /// # Arguments
+    /// * `address` - The computed address
+    /// * `mask` - Mask of the address space, from `Memory::address_mask`
+    pub fn apply(self, address: u16, mask: u16) -> u16 {
+        match self {
+            AddressMasking::Off => address,
+            AddressMasking::Mask => address & mask,
+        }
+    }
+}
+
 /// Chip-8 memory system.
 ///
 /// The memory layout is:
 /// - 0x000-0x1FF: Reserved for interpreter (font data at 0x50-0x9F)
-/// - 0x200-0xFFF: Program area (most ROMs start here)
-/// - 0x600-0xFFF: ETI 660 program area (some ROMs start here)
+/// - 0x200-end: Program area (most ROMs start here)
+/// - 0x600-end: ETI 660 program area (some ROMs start here)
+///
+/// The end is 0xFFF with the default `Chip8_4K` map and 0xFFFF with
+/// `XoChip64K`.
 pub struct Memory {
-    /// Raw memory data.
-    data: [u8; MEMORY_SIZE],
+    /// Raw memory data and its layout.
+    map: Box<dyn MemoryMap>,
 
     /// Enable memory wraparound for out-of-bounds access.
     wraparound_enabled: bool,
+
+    /// Font loaded at `FONT_START`, restored by `clear`.
+    font: FontSet,
 }
 
 impl Memory {
-    /// Creates a new memory instance with font data pre-loaded.
+    /// Creates a new 4KB memory instance with font data pre-loaded.
     pub fn new() -> Self {
+        Self::with_map(Box::new(Chip8_4K::new()))
+    }
+
+    /// Creates a new 4KB memory instance with wraparound enabled.
+    pub fn new_with_wraparound(wraparound: bool) -> Self {
+        let mut memory = Self::new();
+        memory.set_wraparound(wraparound);
+        memory
+    }
+
+    /// Creates a memory instance on a variant's memory map, with font data
+    /// pre-loaded.
+    pub fn with_map(map: Box<dyn MemoryMap>) -> Self {
         let mut memory = Self {
-            data: [0; MEMORY_SIZE],
+            map,
             wraparound_enabled: false,
+            font: FontSet::default(),
         };
 
         // Load font data into memory
@@ -72,17 +264,30 @@ impl Memory {
         memory
     }
 
-    /// Creates a new memory instance with wraparound enabled.
-    pub fn new_with_wraparound(wraparound: bool) -> Self {
-        let mut memory = Self {
-            data: [0; MEMORY_SIZE],
-            wraparound_enabled: wraparound,
-        };
+    /// Switches to the memory map of another layout.
+    ///
+    /// Memory is cleared when the size changes; switching to the layout
+    /// already in use keeps the contents.
+    pub fn set_layout(&mut self, layout: MemoryLayout) {
+        if self.size() != layout.size() {
+            self.map = layout.memory_map();
+            self.load_font_data();
+        }
+    }
 
-        // Load font data into memory
-        memory.load_font_data();
+    /// Gets the size of the address space in bytes.
+    pub fn size(&self) -> usize {
+        self.map.size()
+    }
 
-        memory
+    /// Gets the address programs are loaded at.
+    pub fn program_start(&self) -> u16 {
+        self.map.program_start()
+    }
+
+    /// Gets the mask that keeps an address inside the address space.
+    pub fn address_mask(&self) -> u16 {
+        self.map.address_mask()
     }
 
     /// Sets the memory wraparound behavior.
@@ -95,11 +300,41 @@ impl Memory {
         self.wraparound_enabled
     }
 
-    /// Loads the built-in font data into memory at the standard location.
+    /// Replaces the font at `FONT_START`.
+    ///
+    /// The font is kept across `clear`, so it only needs loading once.
+    pub fn load_font(&mut self, font: FontSet) {
+        self.font = font;
+        self.load_font_data();
+    }
+
+    /// Gets the loaded font.
+    pub fn font(&self) -> FontSet {
+        self.font
+    }
+
+    /// Loads the current font data into memory at the standard location.
     fn load_font_data(&mut self) {
         let start = FONT_START as usize;
         let end = start + FONT_SIZE;
-        self.data[start..end].copy_from_slice(&FONT_SET);
+        self.map.bytes_mut()[start..end].copy_from_slice(self.font.data());
+    }
+
+    /// Gets the reserved region an address falls in, if any.
+    pub fn reserved_region(&self, address: u16) -> Option<ReservedRegion> {
+        let addr = if self.wraparound_enabled {
+            (address as usize % self.size()) as u16
+        } else {
+            address
+        };
+
+        if (FONT_START..FONT_START + FONT_SIZE as u16).contains(&addr) {
+            Some(ReservedRegion::Font)
+        } else if addr < PROGRAM_START {
+            Some(ReservedRegion::Interpreter)
+        } else {
+            None
+        }
     }
 
     /// Reads a byte from memory at the specified address.
@@ -111,16 +346,16 @@ impl Memory {
     /// The byte value at the specified address, or an error if the address is invalid.
     pub fn read_byte(&self, address: u16) -> Result<u8> {
         let addr = if self.wraparound_enabled {
-            (address as usize) % MEMORY_SIZE
+            (address as usize) % self.size()
         } else {
             let addr = address as usize;
-            if addr >= MEMORY_SIZE {
+            if addr >= self.size() {
                 return Err(EmulatorError::InvalidMemoryAccess { address });
             }
             addr
         };
 
-        Ok(self.data[addr])
+        Ok(self.map.bytes()[addr])
     }
 
     /// Writes a byte to memory at the specified address.
@@ -133,16 +368,16 @@ impl Memory {
     /// Ok(()) on success, or an error if the address is invalid.
     pub fn write_byte(&mut self, address: u16, value: u8) -> Result<()> {
         let addr = if self.wraparound_enabled {
-            (address as usize) % MEMORY_SIZE
+            (address as usize) % self.size()
         } else {
             let addr = address as usize;
-            if addr >= MEMORY_SIZE {
+            if addr >= self.size() {
                 return Err(EmulatorError::InvalidMemoryAccess { address });
             }
             addr
         };
 
-        self.data[addr] = value;
+        self.map.bytes_mut()[addr] = value;
         Ok(())
     }
 
@@ -157,7 +392,7 @@ impl Memory {
     /// The 16-bit word value, or an error if the address is invalid.
     pub fn read_word(&self, address: u16) -> Result<u16> {
         let high_byte = self.read_byte(address)?;
-        let low_byte = self.read_byte(address + 1)?;
+        let low_byte = self.read_byte(address.wrapping_add(1))?;
 
         Ok((high_byte as u16) << 8 | low_byte as u16)
     }
@@ -177,12 +412,12 @@ impl Memory {
         let low_byte = (value & 0xFF) as u8;
 
         self.write_byte(address, high_byte)?;
-        self.write_byte(address + 1, low_byte)?;
+        self.write_byte(address.wrapping_add(1), low_byte)?;
 
         Ok(())
     }
 
-    /// Loads ROM data into memory starting at the standard program address.
+    /// Loads ROM data into memory starting at the memory map's program address.
     ///
     /// # Arguments
     /// * `rom_data` - The ROM data to load
@@ -190,7 +425,7 @@ impl Memory {
     /// # Returns
     /// Ok(()) on success, or an error if the ROM is too large or empty.
     pub fn load_rom(&mut self, rom_data: &[u8]) -> Result<()> {
-        self.load_rom_at(rom_data, PROGRAM_START)
+        self.load_rom_at(rom_data, self.program_start())
     }
 
     /// Loads ROM data into memory starting at the specified address.
@@ -207,7 +442,7 @@ impl Memory {
         }
 
         let start = start_address as usize;
-        let available_space = MEMORY_SIZE - start;
+        let available_space = self.size().saturating_sub(start);
 
         if rom_data.len() > available_space {
             return Err(EmulatorError::RomTooLarge {
@@ -217,13 +452,12 @@ impl Memory {
         }
 
         // Clear existing program area
-        for addr in start..MEMORY_SIZE {
-            self.data[addr] = 0;
-        }
+        let data = self.map.bytes_mut();
+        data[start..].fill(0);
 
         // Load ROM data
         let end = start + rom_data.len();
-        self.data[start..end].copy_from_slice(rom_data);
+        data[start..end].copy_from_slice(rom_data);
 
         Ok(())
     }
@@ -231,7 +465,7 @@ impl Memory {
     /// Clears all memory except font data.
     pub fn clear(&mut self) {
         // Clear everything
-        self.data.fill(0);
+        self.map.bytes_mut().fill(0);
 
         // Reload font data
         self.load_font_data();
@@ -266,11 +500,11 @@ impl Memory {
         let start_addr = start as usize;
         let end_addr = start_addr + length;
 
-        if end_addr > MEMORY_SIZE {
+        if end_addr > self.size() {
             return Err(EmulatorError::InvalidMemoryAccess { address: start });
         }
 
-        Ok(&self.data[start_addr..end_addr])
+        Ok(&self.map.bytes()[start_addr..end_addr])
     }
 
     /// Copies data from one memory location to another.
@@ -288,20 +522,46 @@ impl Memory {
         let src_end = src_start + length;
         let dst_end = dst_start + length;
 
-        if src_end > MEMORY_SIZE {
+        if src_end > self.size() {
             return Err(EmulatorError::InvalidMemoryAccess { address: source });
         }
 
-        if dst_end > MEMORY_SIZE {
+        if dst_end > self.size() {
             return Err(EmulatorError::InvalidMemoryAccess { address: dest });
         }
 
-        // Use a temporary buffer to handle overlapping regions safely
-        let temp: Vec<u8> = self.data[src_start..src_end].to_vec();
-        self.data[dst_start..dst_end].copy_from_slice(&temp);
+        // copy_within handles overlapping regions safely
+        self.map
+            .bytes_mut()
+            .copy_within(src_start..src_end, dst_start);
 
         Ok(())
     }
+
+    /// Gets the whole address space.
+    pub fn bytes(&self) -> &[u8] {
+        self.map.bytes()
+    }
+
+    /// Replaces the whole address space, font included.
+    ///
+    /// # Arguments
+    /// * `image` - New memory contents, exactly `size()` bytes
+    ///
+    /// # Returns
+    /// Ok(()) on success, or an error if the image is the wrong size.
+    pub fn load_image(&mut self, image: &[u8]) -> Result<()> {
+        if image.len() != self.size() {
+            return Err(EmulatorError::SnapshotMismatch {
+                part: "memory",
+                expected: self.size(),
+                found: image.len(),
+            });
+        }
+
+        self.map.bytes_mut().copy_from_slice(image);
+        Ok(())
+    }
 }
 
 impl Default for Memory {
@@ -453,11 +713,65 @@ mod tests {
             // Verify against original font set
             let start_idx = (i * 5) as usize;
             for j in 0..5 {
-                assert_eq!(font_slice[j], FONT_SET[start_idx + j]);
+                assert_eq!(font_slice[j], OCTO_FONT[start_idx + j]);
             }
         }
     }
 
+    #[test]
+    fn test_load_font() {
+        let mut memory = Memory::new();
+        assert_eq!(memory.font(), FontSet::Octo);
+
+        memory.load_font(FontSet::CosmacVip);
+        let seven = memory.get_font_address(7).unwrap();
+        assert_eq!(
+            memory.get_slice(seven, 5).unwrap(),
+            &[0xF0, 0x10, 0x10, 0x10, 0x10]
+        );
+
+        // The font survives a clear
+        memory.clear();
+        assert_eq!(
+            memory.get_slice(FONT_START, FONT_SIZE).unwrap(),
+            &COSMAC_VIP_FONT
+        );
+
+        let custom = FontSet::custom(&[0xAA; FONT_SIZE]).unwrap();
+        memory.load_font(custom);
+        assert_eq!(memory.read_byte(FONT_START + 79).unwrap(), 0xAA);
+        assert!(FontSet::custom(&[0xAA; 75]).is_none());
+    }
+
+    #[test]
+    fn test_memory_layouts() {
+        let mut memory = Memory::with_map(MemoryLayout::XoChip64K.memory_map());
+        assert_eq!(memory.size(), 0x10000);
+        assert_eq!(memory.address_mask(), 0xFFFF);
+
+        memory.write_byte(0x8000, 0x42).unwrap();
+        assert_eq!(memory.read_byte(0xFFFF).unwrap(), 0x00);
+        assert_eq!(memory.read_byte(0x8000).unwrap(), 0x42);
+        assert_eq!(memory.get_slice(FONT_START, FONT_SIZE).unwrap(), &OCTO_FONT);
+
+        // A ROM can fill everything above 0x200
+        assert!(memory.load_rom(&vec![0x12; 0x10000 - 0x200]).is_ok());
+        assert!(memory.load_rom(&vec![0x12; 0x10000 - 0x1FF]).is_err());
+
+        // Wraparound wraps at 64KB rather than 4KB
+        memory.set_wraparound(true);
+        assert_eq!(memory.reserved_region(0x1050), None);
+
+        // The same layout keeps its contents, a different one starts fresh
+        memory.set_layout(MemoryLayout::XoChip64K);
+        assert_eq!(memory.read_byte(0x8000).unwrap(), 0x12);
+        memory.set_layout(MemoryLayout::Chip8_4K);
+        memory.set_wraparound(false);
+        assert_eq!(memory.size(), MEMORY_SIZE);
+        assert!(memory.read_byte(0x8000).is_err());
+        assert_eq!(memory.read_byte(0x200).unwrap(), 0x00);
+    }
+
     #[test]
     fn test_memory_wraparound_disabled() {
         let memory = Memory::new();
@@ -605,4 +919,27 @@ mod tests {
             assert!(memory.get_font_address(invalid).is_err());
         }
     }
+
+    #[test]
+    fn test_reserved_regions() {
+        let mut memory = Memory::new();
+        assert_eq!(
+            memory.reserved_region(0x000),
+            Some(ReservedRegion::Interpreter)
+        );
+        assert_eq!(
+            memory.reserved_region(FONT_START),
+            Some(ReservedRegion::Font)
+        );
+        assert_eq!(memory.reserved_region(0x9F), Some(ReservedRegion::Font));
+        assert_eq!(
+            memory.reserved_region(0xA0),
+            Some(ReservedRegion::Interpreter)
+        );
+        assert_eq!(memory.reserved_region(PROGRAM_START), None);
+        assert_eq!(memory.reserved_region(0x1050), None);
+
+        memory.set_wraparound(true);
+        assert_eq!(memory.reserved_region(0x1050), Some(ReservedRegion::Font));
+    }
 }
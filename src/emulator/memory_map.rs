@@ -0,0 +1,161 @@
+//! Variant-specific address spaces.
+//!
+//! `Memory` keeps its bytes in a `MemoryMap`, so the size of the address
+//! space and where programs load are properties of the variant instead of
+//! constants. Classic Chip-8 has 4KB; XO-CHIP programs can use 64KB.
+
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+use super::memory::{MEMORY_SIZE, PROGRAM_START};
+
+/// Size of the XO-CHIP address space (64KB).
+pub const XO_CHIP_MEMORY_SIZE: usize = 0x10000;
+
+/// Storage and layout of a variant's address space.
+pub trait MemoryMap: Send {
+    /// Gets the size of the address space in bytes.
+    fn size(&self) -> usize;
+
+    /// Gets the address programs are loaded at.
+    fn program_start(&self) -> u16 {
+        PROGRAM_START
+    }
+
+    /// Gets the mask that keeps an address inside the address space.
+    fn address_mask(&self) -> u16 {
+        (self.size() - 1) as u16
+    }
+
+    /// Gets the raw memory.
+    fn bytes(&self) -> &[u8];
+
+    /// Gets the raw memory for writing.
+    fn bytes_mut(&mut self) -> &mut [u8];
+}
+
+/// The 4KB address space of the COSMAC VIP and most interpreters.
+pub struct Chip8_4K {
+    /// Raw memory data.
+    data: Box<[u8; MEMORY_SIZE]>,
+}
+
+impl Chip8_4K {
+    /// Creates a zeroed 4KB address space.
+    pub fn new() -> Self {
+        Self {
+            data: Box::new([0; MEMORY_SIZE]),
+        }
+    }
+}
+
+impl Default for Chip8_4K {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryMap for Chip8_4K {
+    fn size(&self) -> usize {
+        MEMORY_SIZE
+    }
+
+    fn bytes(&self) -> &[u8] {
+        &self.data[..]
+    }
+
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.data[..]
+    }
+}
+
+/// The 64KB address space of XO-CHIP, reached with `F000 NNNN`.
+pub struct XoChip64K {
+    /// Raw memory data.
+    data: Vec<u8>,
+}
+
+impl XoChip64K {
+    /// Creates a zeroed 64KB address space.
+    pub fn new() -> Self {
+        Self {
+            data: vec![0; XO_CHIP_MEMORY_SIZE],
+        }
+    }
+}
+
+impl Default for XoChip64K {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryMap for XoChip64K {
+    fn size(&self) -> usize {
+        XO_CHIP_MEMORY_SIZE
+    }
+
+    fn bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+}
+
+/// Which memory map a variant uses, as chosen in the configuration.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MemoryLayout {
+    /// 4KB, programs at 0x200.
+    #[default]
+    #[serde(rename = "chip8-4k")]
+    Chip8_4K,
+
+    /// 64KB, programs at 0x200.
+    #[serde(rename = "xo-chip-64k")]
+    XoChip64K,
+}
+
+impl MemoryLayout {
+    /// Gets the size of the address space in bytes.
+    pub fn size(self) -> usize {
+        match self {
+            MemoryLayout::Chip8_4K => MEMORY_SIZE,
+            MemoryLayout::XoChip64K => XO_CHIP_MEMORY_SIZE,
+        }
+    }
+
+    /// Creates an empty memory map with this layout.
+    pub fn memory_map(self) -> Box<dyn MemoryMap> {
+        match self {
+            MemoryLayout::Chip8_4K => Box::new(Chip8_4K::new()),
+            MemoryLayout::XoChip64K => Box::new(XoChip64K::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layouts() {
+        let chip8 = MemoryLayout::Chip8_4K.memory_map();
+        assert_eq!(chip8.size(), 4096);
+        assert_eq!(chip8.bytes().len(), 4096);
+        assert_eq!(chip8.address_mask(), 0x0FFF);
+        assert_eq!(chip8.program_start(), 0x200);
+
+        let xo_chip = MemoryLayout::XoChip64K.memory_map();
+        assert_eq!(xo_chip.size(), 0x10000);
+        assert_eq!(xo_chip.bytes().len(), 0x10000);
+        assert_eq!(xo_chip.address_mask(), 0xFFFF);
+
+        let layout: MemoryLayout = serde_json::from_str("\"xo-chip-64k\"").unwrap();
+        assert_eq!(layout, MemoryLayout::XoChip64K);
+    }
+}
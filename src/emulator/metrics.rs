@@ -0,0 +1,229 @@
+//! Performance counters collected by the core.
+//!
+//! The CPU counts what it executes and draws as it runs; frontends add the
+//! frames they show. `Cpu::metrics` takes a snapshot, which can be printed
+//! as a summary or shown counter by counter on the OSD.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use core::fmt;
+use core::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::emulator::timing::FrameBudget;
+use crate::input::latency::InputLatency;
+
+/// Snapshot of the performance counters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Metrics {
+    /// Instructions executed.
+    pub instructions: u64,
+
+    /// Frames shown by the frontend.
+    pub frames: u64,
+
+    /// DXYN sprite draws.
+    pub sprites_drawn: u64,
+
+    /// Sprite draws that turned off a lit pixel.
+    pub collisions: u64,
+
+    /// 60Hz timer ticks applied late because the host fell behind.
+    pub timer_underruns: u64,
+
+    /// Audio output buffers that ran out of samples.
+    pub audio_underruns: u64,
+
+    /// Host time spent executing instructions (always zero without `std`).
+    pub cycle_time: Duration,
+
+    /// Time from key events to the program reading the keys.
+    pub input_latency: InputLatency,
+
+    /// Clock cycles charged during the last frame shown.
+    pub frame_budget: FrameBudget,
+}
+
+impl Metrics {
+    /// Gets the average host time per executed instruction.
+    pub fn average_cycle_time(&self) -> Duration {
+        if self.instructions == 0 {
+            return Duration::ZERO;
+        }
+        let nanos = self.cycle_time.as_nanos() / self.instructions as u128;
+        Duration::from_nanos(nanos as u64)
+    }
+
+    /// Gets a counter's value formatted for display.
+    pub fn value(&self, counter: MetricCounter) -> String {
+        match counter {
+            MetricCounter::Instructions => self.instructions.to_string(),
+            MetricCounter::Frames => self.frames.to_string(),
+            MetricCounter::SpritesDrawn => self.sprites_drawn.to_string(),
+            MetricCounter::Collisions => self.collisions.to_string(),
+            MetricCounter::TimerUnderruns => self.timer_underruns.to_string(),
+            MetricCounter::AudioUnderruns => self.audio_underruns.to_string(),
+            MetricCounter::CycleTime => {
+                format!("{}NS", self.average_cycle_time().as_nanos())
+            }
+            MetricCounter::InputLatency => {
+                let overall = self.input_latency.overall();
+                format!(
+                    "{:.1}MS {:.1}MS",
+                    overall.last.as_secs_f64() * 1000.0,
+                    overall.average().as_secs_f64() * 1000.0
+                )
+            }
+            MetricCounter::FrameBudget => {
+                format!("{:.0}%", self.frame_budget.used() * 100.0)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Instructions executed: {}", self.instructions)?;
+        writeln!(f, "Frames rendered:       {}", self.frames)?;
+        writeln!(f, "Sprites drawn:         {}", self.sprites_drawn)?;
+        writeln!(f, "Collisions:            {}", self.collisions)?;
+        writeln!(f, "Timer underruns:       {}", self.timer_underruns)?;
+        writeln!(f, "Audio underruns:       {}", self.audio_underruns)?;
+        let latency = self.input_latency.overall();
+        writeln!(
+            f,
+            "Input latency:         {:.2} ms average, {:.2} ms max over {} presses",
+            latency.average().as_secs_f64() * 1000.0,
+            latency.max.as_secs_f64() * 1000.0,
+            latency.samples
+        )?;
+        writeln!(
+            f,
+            "Last frame budget:     {} of {:.0} cycles ({:.0}%)",
+            self.frame_budget.cycles,
+            self.frame_budget.available(),
+            self.frame_budget.used() * 100.0
+        )?;
+        write!(
+            f,
+            "Average cycle time:    {} ns",
+            self.average_cycle_time().as_nanos()
+        )
+    }
+}
+
+/// A counter that can be shown on the OSD.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MetricCounter {
+    /// Instructions executed.
+    Instructions,
+
+    /// Frames shown.
+    Frames,
+
+    /// Sprite draws.
+    SpritesDrawn,
+
+    /// Sprite collisions.
+    Collisions,
+
+    /// Late timer ticks.
+    TimerUnderruns,
+
+    /// Audio buffer underruns.
+    AudioUnderruns,
+
+    /// Average host time per instruction.
+    CycleTime,
+
+    /// Last and average input latency.
+    InputLatency,
+
+    /// Share of the last frame's cycle budget used.
+    FrameBudget,
+}
+
+impl MetricCounter {
+    /// Gets the short uppercase label drawn before the value on the OSD.
+    pub fn label(self) -> &'static str {
+        match self {
+            MetricCounter::Instructions => "INS",
+            MetricCounter::Frames => "FRM",
+            MetricCounter::SpritesDrawn => "SPR",
+            MetricCounter::Collisions => "COL",
+            MetricCounter::TimerUnderruns => "TMR",
+            MetricCounter::AudioUnderruns => "AUD",
+            MetricCounter::CycleTime => "CYC",
+            MetricCounter::InputLatency => "LAT",
+            MetricCounter::FrameBudget => "BGT",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics_formatting() {
+        let metrics = Metrics {
+            instructions: 4,
+            sprites_drawn: 2,
+            collisions: 1,
+            cycle_time: Duration::from_nanos(1000),
+            ..Default::default()
+        };
+
+        assert_eq!(metrics.average_cycle_time(), Duration::from_nanos(250));
+        assert_eq!(metrics.value(MetricCounter::CycleTime), "250NS");
+        assert_eq!(metrics.value(MetricCounter::Collisions), "1");
+        assert_eq!(Metrics::default().average_cycle_time(), Duration::ZERO);
+
+        let summary = metrics.to_string();
+        assert!(summary.contains("Sprites drawn:         2"));
+        assert!(summary.ends_with("250 ns"));
+
+        let counter: MetricCounter = serde_json::from_str("\"sprites-drawn\"").unwrap();
+        assert_eq!(counter.label(), "SPR");
+    }
+
+    #[test]
+    fn test_input_latency_counter() {
+        use crate::hardware::ChipKey;
+
+        let mut metrics = Metrics::default();
+        metrics.input_latency.keys[ChipKey::Key5.to_u8() as usize].record(Duration::from_millis(4));
+        metrics.input_latency.keys[ChipKey::Key6.to_u8() as usize].record(Duration::from_millis(2));
+        metrics.input_latency.last_key = Some(ChipKey::Key6);
+
+        assert_eq!(metrics.value(MetricCounter::InputLatency), "2.0MS 3.0MS");
+        assert!(metrics
+            .to_string()
+            .contains("Input latency:         3.00 ms average, 4.00 ms max over 2 presses"));
+
+        let counter: MetricCounter = serde_json::from_str("\"input-latency\"").unwrap();
+        assert_eq!(counter, MetricCounter::InputLatency);
+    }
+
+    #[test]
+    fn test_frame_budget_counter() {
+        let metrics = Metrics {
+            frame_budget: FrameBudget {
+                cycles: 9,
+                instructions: 9,
+                cycles_per_second: 720,
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(metrics.value(MetricCounter::FrameBudget), "75%");
+        assert!(metrics
+            .to_string()
+            .contains("Last frame budget:     9 of 12 cycles (75%)"));
+
+        let counter: MetricCounter = serde_json::from_str("\"frame-budget\"").unwrap();
+        assert_eq!(counter.label(), "BGT");
+    }
+}
@@ -1,17 +1,48 @@
 //! Emulator core module.
 //!
 //! This module contains the core emulation components for the Chip-8 system,
-//! including CPU, memory, registers, stack, and timers.
+//! including CPU, memory, registers, stack, and timers, plus snapshots of
+//! the machine state and a rewind history built from them.
+//!
+//! The core builds without `std` (with `alloc`); the parts that need it,
+//! such as the wall clock and event channels, are noted where they appear.
 
+pub mod access;
 pub mod cpu;
+pub mod events;
+pub mod megachip;
 pub mod memory;
+pub mod memory_map;
+pub mod metrics;
+pub mod random;
 pub mod registers;
+pub mod rewind;
+pub mod snapshot;
 pub mod stack;
 pub mod timers;
+pub mod timing;
+pub mod watchdog;
 
 // Re-export commonly used types
-pub use cpu::{Cpu, CpuState};
-pub use memory::{Memory, FONT_START, MEMORY_SIZE, PROGRAM_START};
+pub use access::{AccessCounts, MemoryAccess, HEATMAP_WIDTH};
+pub use cpu::{CallFrame, Cpu, CpuEvent, CpuState, InstructionInfo, TraceEntry};
+pub use events::{EmulatorEvent, EventBus};
+pub use megachip::{MegaChipScreen, MEGA_HEIGHT, MEGA_WIDTH, PALETTE_SIZE};
+pub use memory::{
+    AddressMasking, FontSet, Memory, MemoryProtection, ReservedRegion, ADDRESS_MASK, FONT_SIZE,
+    FONT_START, MEMORY_SIZE, PROGRAM_START,
+};
+pub use memory_map::{Chip8_4K, MemoryLayout, MemoryMap, XoChip64K, XO_CHIP_MEMORY_SIZE};
+pub use metrics::{MetricCounter, Metrics};
+pub use random::{Pcg32, RandomSource, RecordedRandom};
+#[cfg(feature = "std")]
+pub use random::{RandomLog, RandomRecorder, ThreadRandom};
 pub use registers::{Registers, FLAG_REGISTER, NUM_REGISTERS};
+pub use rewind::{RewindBuffer, DEFAULT_REWIND_DEPTH};
+pub use snapshot::Snapshot;
 pub use stack::{Stack, STACK_SIZE};
-pub use timers::{Timers, TIMER_FREQUENCY};
+#[cfg(feature = "std")]
+pub use timers::SystemClock;
+pub use timers::{CycleClock, MockClock, SoundTimerMode, TimeSource, Timers, TIMER_FREQUENCY};
+pub use timing::{FrameBudget, TimingModel};
+pub use watchdog::{Watchdog, WatchdogConfig};
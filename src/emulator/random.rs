@@ -0,0 +1,239 @@
+//! Random number sources for CXNN.
+//!
+//! The CPU draws RND bytes from a [`RandomSource`]. [`ThreadRandom`] is the
+//! unseeded default, [`Pcg32`] gives a reproducible sequence from a seed,
+//! and [`RandomRecorder`] logs the bytes another source produces so a
+//! replay can store them. [`RecordedRandom`] plays logged bytes back,
+//! which keeps a replay in sync even if the seeded generator changes.
+//!
+//! [`ThreadRandom`] and [`RandomRecorder`] need `std`; without it there is
+//! no entropy source, so the CPU starts from a fixed [`Pcg32`] seed until
+//! the host calls `Cpu::seed_rng`.
+
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::sync::{Arc, Mutex, PoisonError};
+
+#[cfg(feature = "std")]
+use rand::Rng;
+
+/// A source of the random bytes RND masks.
+pub trait RandomSource: Send {
+    /// Produces the next random byte.
+    fn next_byte(&mut self) -> u8;
+}
+
+#[cfg(feature = "std")]
+/// Unseeded bytes from the thread-local generator.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ThreadRandom;
+
+#[cfg(feature = "std")]
+impl RandomSource for ThreadRandom {
+    fn next_byte(&mut self) -> u8 {
+        rand::thread_rng().gen()
+    }
+}
+
+/// PCG-XSH-RR generator with 64-bit state and 32-bit output.
+///
+/// Implemented here rather than taken from `rand` so a seed gives the same
+/// sequence whatever `rand` version the emulator is built with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pcg32 {
+    /// Current state.
+    state: u64,
+
+    /// Stream increment, always odd.
+    increment: u64,
+}
+
+impl Pcg32 {
+    /// LCG multiplier.
+    const MULTIPLIER: u64 = 6_364_136_223_846_793_005;
+
+    /// Stream used by `new`.
+    const DEFAULT_STREAM: u64 = 0xDA3E_39CB_94B9_5BDB;
+
+    /// Creates a generator seeded with `seed` on the default stream.
+    pub fn new(seed: u64) -> Self {
+        Self::with_stream(seed, Self::DEFAULT_STREAM)
+    }
+
+    /// Creates a generator seeded with `seed` on one of 2^63 streams.
+    pub fn with_stream(seed: u64, stream: u64) -> Self {
+        let mut pcg = Self {
+            state: 0,
+            increment: (stream << 1) | 1,
+        };
+        pcg.next_u32();
+        pcg.state = pcg.state.wrapping_add(seed);
+        pcg.next_u32();
+        pcg
+    }
+
+    /// Produces the next 32-bit output.
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old
+            .wrapping_mul(Self::MULTIPLIER)
+            .wrapping_add(self.increment);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+}
+
+impl RandomSource for Pcg32 {
+    fn next_byte(&mut self) -> u8 {
+        // The high bits are the best distributed
+        (self.next_u32() >> 24) as u8
+    }
+}
+
+#[cfg(feature = "std")]
+/// Bytes logged by a [`RandomRecorder`], readable while the CPU owns it.
+#[derive(Debug, Clone, Default)]
+pub struct RandomLog {
+    /// Bytes produced so far, oldest first.
+    bytes: Arc<Mutex<Vec<u8>>>,
+}
+
+#[cfg(feature = "std")]
+impl RandomLog {
+    /// Copies the bytes produced so far.
+    pub fn bytes(&self) -> Vec<u8> {
+        self.bytes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Gets the number of bytes produced so far.
+    pub fn len(&self) -> usize {
+        self.bytes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    /// Checks if no bytes have been produced.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(feature = "std")]
+/// Passes bytes through from another source, logging each one.
+pub struct RandomRecorder<R> {
+    /// Source the bytes come from.
+    source: R,
+
+    /// Log shared with readers.
+    log: RandomLog,
+}
+
+#[cfg(feature = "std")]
+impl<R: RandomSource> RandomRecorder<R> {
+    /// Creates a recorder around a source.
+    pub fn new(source: R) -> Self {
+        Self {
+            source,
+            log: RandomLog::default(),
+        }
+    }
+
+    /// Gets a handle to the log.
+    pub fn log(&self) -> RandomLog {
+        self.log.clone()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: RandomSource> RandomSource for RandomRecorder<R> {
+    fn next_byte(&mut self) -> u8 {
+        let byte = self.source.next_byte();
+        self.log
+            .bytes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(byte);
+        byte
+    }
+}
+
+/// Plays back recorded bytes, then continues from a seeded generator.
+#[derive(Debug, Clone)]
+pub struct RecordedRandom {
+    /// Bytes to play back.
+    bytes: Vec<u8>,
+
+    /// Index of the next byte to play back.
+    position: usize,
+
+    /// Source used once the recording runs out.
+    fallback: Pcg32,
+}
+
+impl RecordedRandom {
+    /// Creates a source playing `bytes`, then bytes seeded with `seed`.
+    pub fn new(bytes: Vec<u8>, seed: u64) -> Self {
+        Self {
+            bytes,
+            position: 0,
+            fallback: Pcg32::new(seed),
+        }
+    }
+
+    /// Gets the number of recorded bytes not yet played back.
+    pub fn remaining(&self) -> usize {
+        self.bytes.len() - self.position
+    }
+}
+
+impl RandomSource for RecordedRandom {
+    fn next_byte(&mut self) -> u8 {
+        match self.bytes.get(self.position) {
+            Some(&byte) => {
+                self.position += 1;
+                byte
+            }
+            None => self.fallback.next_byte(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pcg32_reference_output() {
+        // First outputs of the PCG reference demo, seeded with 42 on stream 54
+        let mut pcg = Pcg32::with_stream(42, 54);
+        let outputs: Vec<u32> = (0..3).map(|_| pcg.next_u32()).collect();
+        assert_eq!(outputs, [0xa15c02b7, 0x7b47f409, 0xba1d3330]);
+
+        let bytes = |seed| {
+            let mut pcg = Pcg32::new(seed);
+            (0..8).map(|_| pcg.next_byte()).collect::<Vec<_>>()
+        };
+        assert_eq!(bytes(7), bytes(7));
+        assert_ne!(bytes(7), bytes(8));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_record_and_play_back() {
+        let mut recorder = RandomRecorder::new(Pcg32::new(3));
+        let log = recorder.log();
+        let produced: Vec<u8> = (0..4).map(|_| recorder.next_byte()).collect();
+        assert_eq!(log.bytes(), produced);
+
+        // Playback ignores the seed until the recording runs out
+        let mut playback = RecordedRandom::new(log.bytes(), 99);
+        let played: Vec<u8> = (0..4).map(|_| playback.next_byte()).collect();
+        assert_eq!(played, produced);
+        assert_eq!(playback.remaining(), 0);
+        assert_eq!(playback.next_byte(), Pcg32::new(99).next_byte());
+    }
+}
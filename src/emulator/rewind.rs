@@ -0,0 +1,102 @@
+//! Bounded history of machine snapshots.
+//!
+//! A [`RewindBuffer`] keeps the most recent [`Snapshot`]s so execution can
+//! be walked backwards one step at a time. Once it is full, recording a new
+//! snapshot forgets the oldest one.
+
+use alloc::collections::VecDeque;
+
+use crate::emulator::Snapshot;
+
+/// Number of snapshots kept by default.
+pub const DEFAULT_REWIND_DEPTH: usize = 1024;
+
+/// Most recent snapshots, oldest first.
+#[derive(Debug, Clone)]
+pub struct RewindBuffer {
+    /// Recorded snapshots, oldest at the front.
+    snapshots: VecDeque<Snapshot>,
+
+    /// Most snapshots kept at once.
+    capacity: usize,
+}
+
+impl Default for RewindBuffer {
+    fn default() -> Self {
+        Self::new(DEFAULT_REWIND_DEPTH)
+    }
+}
+
+impl RewindBuffer {
+    /// Creates an empty buffer keeping up to `capacity` snapshots.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            snapshots: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// Gets the most snapshots kept at once.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Gets the number of snapshots recorded.
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    /// Checks if there is nothing to rewind to.
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    /// Records a snapshot, forgetting the oldest one if the buffer is full.
+    pub fn push(&mut self, snapshot: Snapshot) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.snapshots.len() == self.capacity {
+            self.snapshots.pop_front();
+        }
+        self.snapshots.push_back(snapshot);
+    }
+
+    /// Removes and returns the most recent snapshot.
+    pub fn pop(&mut self) -> Option<Snapshot> {
+        self.snapshots.pop_back()
+    }
+
+    /// Forgets every snapshot.
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::Cpu;
+
+    #[test]
+    fn test_rewind_buffer_drops_oldest() {
+        let mut cpu = Cpu::new();
+        cpu.load_rom(&[0x70, 0x01, 0x12, 0x00]).unwrap(); // ADD V0, 1; JP 0x200
+
+        let mut buffer = RewindBuffer::new(2);
+        for _ in 0..3 {
+            buffer.push(cpu.snapshot());
+            cpu.cycle().unwrap();
+        }
+        assert_eq!(buffer.len(), 2);
+
+        assert_eq!(buffer.pop().unwrap().instruction_count, 2);
+        assert_eq!(buffer.pop().unwrap().instruction_count, 1);
+        assert!(buffer.pop().is_none());
+        assert!(buffer.is_empty());
+
+        let mut disabled = RewindBuffer::new(0);
+        disabled.push(cpu.snapshot());
+        assert!(disabled.is_empty());
+    }
+}
@@ -0,0 +1,216 @@
+//! Snapshots of the machine state.
+//!
+//! A [`Snapshot`] holds everything a program can observe: memory, registers,
+//! the stack, timers, the display (and the Mega-Chip color screen while it
+//! is on) and a pending FX0A key wait. Restoring
+//! one with [`Cpu::restore`](crate::Cpu::restore) puts the program back
+//! exactly where it was, down to the progress towards the next timer tick
+//! and the debugger's call stack. Settings such as quirks, breakpoints and
+//! the RNG are not part of the machine state and are left alone.
+
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+use crate::emulator::{CallFrame, MegaChipScreen};
+use crate::hardware::{DisplaySnapshot, DISPLAY_HEIGHT, DISPLAY_WIDTH};
+
+/// The machine state at one point in time.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Program counter.
+    pub pc: u16,
+
+    /// Index register.
+    pub i: u16,
+
+    /// Stack pointer register.
+    pub sp: u8,
+
+    /// General purpose registers V0-VF.
+    pub v: [u8; 16],
+
+    /// Return addresses on the stack, bottom to top.
+    pub stack: Vec<u16>,
+
+    /// Delay timer value.
+    pub delay_timer: u8,
+
+    /// Sound timer value.
+    pub sound_timer: u8,
+
+    /// Time accumulated towards the next timer tick, in nanoseconds.
+    #[serde(default)]
+    pub timer_phase_ns: u64,
+
+    /// The whole address space, font included.
+    #[serde(with = "hex_bytes")]
+    pub memory: Vec<u8>,
+
+    /// Display pixels, row by row, packed eight to a byte.
+    #[serde(with = "hex_bytes")]
+    pub display: Vec<u8>,
+
+    /// Display resolution, if it was a hi-res one rather than 64x32.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_size: Option<(u16, u16)>,
+
+    /// Register an FX0A key wait stores the key in, if one is pending.
+    #[serde(default)]
+    pub key_wait: Option<u8>,
+
+    /// Key an FX0A wait is waiting to be released, if any.
+    #[serde(default)]
+    pub key_release: Option<u8>,
+
+    /// Whether the program was stuck in a jump-to-self loop.
+    #[serde(default)]
+    pub halted: bool,
+
+    /// Active subroutine calls, outermost first.
+    #[serde(default)]
+    pub call_stack: Vec<CallFrame>,
+
+    /// The color screen, while Mega-Chip mode is on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mega_chip: Option<MegaChipScreen>,
+
+    /// Instructions executed so far.
+    pub instruction_count: u64,
+
+    /// Clock cycles elapsed under the timing model.
+    pub elapsed_cycles: u64,
+}
+
+impl Snapshot {
+    /// Gets the display resolution as (width, height) in pixels.
+    pub fn display_size(&self) -> (usize, usize) {
+        self.display_size
+            .map_or((DISPLAY_WIDTH, DISPLAY_HEIGHT), |(width, height)| {
+                (width as usize, height as usize)
+            })
+    }
+
+    /// Gets the saved screen as a portable display snapshot.
+    pub fn display_snapshot(&self) -> DisplaySnapshot {
+        let (width, height) = self.display_size();
+        DisplaySnapshot {
+            width: width as u16,
+            height: height as u16,
+            rle: false,
+            bits: self.display.clone(),
+        }
+    }
+}
+
+/// Packs display pixels eight to a byte, most significant bit first.
+pub fn pack_pixels(pixels: &[bool]) -> Vec<u8> {
+    pixels
+        .chunks(8)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .fold(0, |byte, (bit, &on)| byte | ((on as u8) << (7 - bit)))
+        })
+        .collect()
+}
+
+/// Unpacks display pixels packed by [`pack_pixels`] into `pixels`.
+///
+/// # Returns
+/// False if `packed` doesn't hold exactly enough bytes for `pixels`.
+pub fn unpack_pixels(packed: &[u8], pixels: &mut [bool]) -> bool {
+    if packed.len() != pixels.len().div_ceil(8) {
+        return false;
+    }
+
+    for (index, pixel) in pixels.iter_mut().enumerate() {
+        *pixel = packed[index / 8] & (0x80 >> (index % 8)) != 0;
+    }
+    true
+}
+
+/// Serializes bytes as a hex string, which keeps text files compact.
+pub(crate) mod hex_bytes {
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use core::fmt::Write;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut text = String::with_capacity(bytes.len() * 2);
+        for byte in bytes {
+            let _ = write!(text, "{:02x}", byte);
+        }
+        serializer.serialize_str(&text)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        if text.len() % 2 != 0 {
+            return Err(serde::de::Error::custom("odd number of hex digits"));
+        }
+
+        (0..text.len())
+            .step_by(2)
+            .map(|start| {
+                text.get(start..start + 2)
+                    .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                    .ok_or_else(|| serde::de::Error::custom("invalid hex digit"))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_pixels_round_trip() {
+        let pixels: Vec<bool> = (0..20).map(|index| index % 3 == 0).collect();
+        let packed = pack_pixels(&pixels);
+        assert_eq!(packed, vec![0b1001_0010, 0b0100_1001, 0b0010_0000]);
+
+        let mut unpacked = vec![false; 20];
+        assert!(unpack_pixels(&packed, &mut unpacked));
+        assert_eq!(unpacked, pixels);
+        assert!(!unpack_pixels(&packed[..2], &mut unpacked));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_snapshot_toml_round_trip() {
+        let snapshot = Snapshot {
+            pc: 0x204,
+            i: 0x300,
+            sp: 0,
+            v: [7; 16],
+            stack: vec![0x202],
+            delay_timer: 30,
+            sound_timer: 0,
+            timer_phase_ns: 5_000_000,
+            memory: vec![0x00, 0xAB, 0xFF],
+            display: vec![0x80],
+            display_size: Some((64, 64)),
+            key_wait: Some(3),
+            key_release: None,
+            halted: false,
+            call_stack: vec![CallFrame {
+                call_site: 0x200,
+                target: 0x208,
+            }],
+            mega_chip: Some(MegaChipScreen::new()),
+            instruction_count: 12,
+            elapsed_cycles: 12,
+        };
+
+        let text = toml::to_string(&snapshot).unwrap();
+        assert!(text.contains(r#"memory = "00abff""#));
+        assert_eq!(toml::from_str::<Snapshot>(&text).unwrap(), snapshot);
+        assert_eq!(snapshot.display_size(), (64, 64));
+
+        let bad = text.replace("00abff", "00abf");
+        assert!(toml::from_str::<Snapshot>(&bad).is_err());
+    }
+}
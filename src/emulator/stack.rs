@@ -4,6 +4,9 @@
 //! and returns in the Chip-8 system.
 
 use crate::error::{EmulatorError, Result};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 
 /// Maximum stack depth (16 levels).
 pub const STACK_SIZE: usize = 16;
@@ -172,7 +175,7 @@ impl Default for Stack {
 /// Iterates from bottom to top (oldest to newest entries).
 impl<'a> IntoIterator for &'a Stack {
     type Item = &'a u16;
-    type IntoIter = std::slice::Iter<'a, u16>;
+    type IntoIter = core::slice::Iter<'a, u16>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.data[..self.sp].iter()
@@ -2,8 +2,18 @@
 //!
 //! This module implements the delay timer and sound timer that decrement
 //! at 60Hz when non-zero, providing timing functionality for games.
-
-use std::time::{Duration, Instant};
+//! Real-time updates read time from a [`TimeSource`], so hosts without
+//! `std::time::Instant` (or tests) can supply their own time source.
+//! Without `std` there is no `SystemClock`: timers start on a
+//! [`MockClock`] that stands still until the host advances or replaces it.
+
+use alloc::boxed::Box;
+use core::time::Duration;
+use portable_atomic::{AtomicU64, Ordering};
+use portable_atomic_util::Arc;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "std")]
+use std::time::Instant;
 
 /// Timer frequency in Hz (60 Hz standard for Chip-8).
 pub const TIMER_FREQUENCY: u32 = 60;
@@ -11,6 +21,162 @@ pub const TIMER_FREQUENCY: u32 = 60;
 /// Timer period in milliseconds (1000ms / 60Hz ≈ 16.67ms).
 pub const TIMER_PERIOD_MS: u64 = 1000 / TIMER_FREQUENCY as u64;
 
+/// A monotonic time source for real-time timer updates.
+pub trait TimeSource {
+    /// Gets the time elapsed since an arbitrary fixed starting point.
+    fn now(&self) -> Duration;
+}
+
+#[cfg(feature = "std")]
+/// Clock backed by the host's monotonic `Instant`.
+#[derive(Debug, Clone, Copy)]
+pub struct SystemClock {
+    /// Starting point that times are measured from.
+    origin: Instant,
+}
+
+#[cfg(feature = "std")]
+impl SystemClock {
+    /// Creates a clock starting now.
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl TimeSource for SystemClock {
+    fn now(&self) -> Duration {
+        self.origin.elapsed()
+    }
+}
+
+/// Clock that only moves when told to, for tests and embedded hosts.
+///
+/// Clones share the same time, so a host can keep one handle and advance it
+/// from a hardware timer interrupt or a test.
+#[derive(Debug, Clone, Default)]
+pub struct MockClock {
+    /// Current time in nanoseconds.
+    nanos: Arc<AtomicU64>,
+}
+
+impl MockClock {
+    /// Creates a clock at time zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Moves the clock forward.
+    pub fn advance(&self, duration: Duration) {
+        self.nanos
+            .fetch_add(duration.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    /// Sets the current time.
+    pub fn set(&self, time: Duration) {
+        self.nanos.store(time.as_nanos() as u64, Ordering::Relaxed);
+    }
+}
+
+impl TimeSource for MockClock {
+    fn now(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::Relaxed))
+    }
+}
+
+/// Clock that follows executed CPU cycles instead of wall-clock time.
+///
+/// Time advances by one period of `cycles_per_second` for each cycle
+/// added, so 60Hz timer ticks line up with the instructions that ran
+/// regardless of how fast the host executes them. Clones share the same
+/// count; [`Cpu::use_cycle_clock`](crate::emulator::Cpu::use_cycle_clock)
+/// installs one and feeds it as instructions are charged.
+#[derive(Debug, Clone)]
+pub struct CycleClock {
+    /// Elapsed time in nanoseconds.
+    nanos: Arc<AtomicU64>,
+
+    /// Cycles that make up one second.
+    cycles_per_second: u32,
+}
+
+impl CycleClock {
+    /// Creates a clock at time zero.
+    ///
+    /// # Arguments
+    /// * `cycles_per_second` - Clock rate the cycles are counted at
+    pub fn new(cycles_per_second: u32) -> Self {
+        Self {
+            nanos: Arc::new(AtomicU64::new(0)),
+            cycles_per_second: cycles_per_second.max(1),
+        }
+    }
+
+    /// Gets the clock rate.
+    pub fn cycles_per_second(&self) -> u32 {
+        self.cycles_per_second
+    }
+
+    /// Moves the clock forward by a number of cycles.
+    pub fn add_cycles(&self, cycles: u64) {
+        let nanos = cycles as u128 * 1_000_000_000 / self.cycles_per_second as u128;
+        self.nanos.fetch_add(nanos as u64, Ordering::Relaxed);
+    }
+}
+
+impl TimeSource for CycleClock {
+    fn now(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::Relaxed))
+    }
+}
+
+/// How the sound timer's value turns into an audible beep.
+///
+/// In TOML: `"exact"`, `"skip-if-1"` or `{ minimum-duration-ms = 50 }`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SoundTimerMode {
+    /// Beep for exactly as many ticks as the timer is set to.
+    #[default]
+    Exact,
+
+    /// Ignore the timer being set to 1, as the COSMAC VIP interpreter does,
+    /// since a single tick is too short to hear.
+    #[serde(rename = "skip-if-1")]
+    SkipIf1,
+
+    /// Keep each beep going for at least this many milliseconds, even if
+    /// the timer runs out or is cleared sooner.
+    MinimumDurationMs(u32),
+}
+
+impl SoundTimerMode {
+    /// Gets the fewest ticks a beep lasts.
+    fn minimum_ticks(self) -> u32 {
+        match self {
+            SoundTimerMode::MinimumDurationMs(ms) => {
+                (u64::from(ms) * u64::from(TIMER_FREQUENCY)).div_ceil(1000) as u32
+            }
+            _ => 0,
+        }
+    }
+}
+
+/// Clock timers use unless given another time source.
+#[cfg(feature = "std")]
+type DefaultClock = SystemClock;
+#[cfg(not(feature = "std"))]
+type DefaultClock = MockClock;
+
 /// Chip-8 timer system.
 ///
 /// The Chip-8 has two 8-bit timers:
@@ -23,29 +189,78 @@ pub struct Timers {
     /// Sound timer value (8-bit).
     sound_timer: u8,
 
-    /// Last time the timers were updated.
-    last_update: Instant,
+    /// Time source for real-time updates.
+    time_source: Box<dyn TimeSource + Send>,
+
+    /// Source time of the last update.
+    last_update: Duration,
 
     /// Accumulated time since last timer decrement.
     accumulated_time: Duration,
+
+    /// Ticks applied late because an update came more than a period after
+    /// the previous one.
+    late_ticks: u64,
+
+    /// How the sound timer turns into a beep.
+    sound_mode: SoundTimerMode,
+
+    /// Whether the buzzer should be sounding.
+    sounding: bool,
+
+    /// Ticks the current beep keeps sounding for, whatever the timer says.
+    sound_hold: u32,
 }
 
 impl Timers {
-    /// Creates a new timer system.
+    /// Creates a new timer system driven by the system clock.
+    ///
+    /// Without `std` the clock stands still until replaced with
+    /// [`set_time_source`](Self::set_time_source).
     pub fn new() -> Self {
+        Self::with_time_source(DefaultClock::default())
+    }
+
+    /// Creates a new timer system driven by a custom time source.
+    pub fn with_time_source(source: impl TimeSource + Send + 'static) -> Self {
+        let last_update = source.now();
+
         Self {
             delay_timer: 0,
             sound_timer: 0,
-            last_update: Instant::now(),
+            time_source: Box::new(source),
+            last_update,
             accumulated_time: Duration::new(0, 0),
+            late_ticks: 0,
+            sound_mode: SoundTimerMode::Exact,
+            sounding: false,
+            sound_hold: 0,
         }
     }
 
+    /// Sets how the sound timer turns into a beep.
+    pub fn set_sound_mode(&mut self, mode: SoundTimerMode) {
+        self.sound_mode = mode;
+    }
+
+    /// Gets how the sound timer turns into a beep.
+    pub fn sound_mode(&self) -> SoundTimerMode {
+        self.sound_mode
+    }
+
+    /// Replaces the time source used for real-time updates, restarting timing from it.
+    pub fn set_time_source(&mut self, source: impl TimeSource + Send + 'static) {
+        self.time_source = Box::new(source);
+        self.sync_to_real_time();
+    }
+
     /// Resets both timers to zero and timing state.
     pub fn reset(&mut self) {
         self.delay_timer = 0;
         self.sound_timer = 0;
-        self.last_update = Instant::now();
+        self.sounding = false;
+        self.sound_hold = 0;
+        self.last_update = self.time_source.now();
         self.accumulated_time = Duration::new(0, 0);
     }
 
@@ -73,11 +288,43 @@ impl Timers {
     /// * `value` - Timer value (0-255)
     pub fn set_sound_timer(&mut self, value: u8) {
         self.sound_timer = value;
+        let starts = match self.sound_mode {
+            SoundTimerMode::SkipIf1 => value > 1,
+            _ => value > 0,
+        };
+        if starts && !self.sounding {
+            self.sound_hold = self.sound_mode.minimum_ticks();
+        }
+        // A beep already under way carries on when the timer is set to 1
+        self.sounding = starts || (self.sounding && (value > 0 || self.sound_hold > 0));
     }
 
-    /// Checks if sound should be playing (sound timer > 0).
+    /// Checks if sound should be playing.
+    ///
+    /// That is while the sound timer is above 0, apart from the exceptions
+    /// the [`SoundTimerMode`] makes.
     pub fn should_play_sound(&self) -> bool {
-        self.sound_timer > 0
+        self.sounding
+    }
+
+    /// Counts down both timers by one tick.
+    ///
+    /// # Returns
+    /// True if either timer was decremented, false otherwise.
+    fn tick(&mut self) -> bool {
+        let mut timers_decremented = false;
+        if self.delay_timer > 0 {
+            self.delay_timer -= 1;
+            timers_decremented = true;
+        }
+
+        if self.sound_timer > 0 {
+            self.sound_timer -= 1;
+            timers_decremented = true;
+        }
+        self.sound_hold = self.sound_hold.saturating_sub(1);
+        self.sounding &= self.sound_timer > 0 || self.sound_hold > 0;
+        timers_decremented
     }
 
     /// Updates both timers based on elapsed time.
@@ -88,33 +335,35 @@ impl Timers {
     /// # Returns
     /// True if either timer was decremented, false otherwise.
     pub fn update(&mut self) -> bool {
-        let now = Instant::now();
-        let elapsed = now.duration_since(self.last_update);
+        let now = self.time_source.now();
+        let elapsed = now.saturating_sub(self.last_update);
         self.last_update = now;
 
         self.accumulated_time += elapsed;
 
         let timer_period = Duration::from_millis(TIMER_PERIOD_MS);
         let mut timers_decremented = false;
+        let mut ticks = 0u64;
 
         // Decrement timers for each complete timer period that has elapsed
         while self.accumulated_time >= timer_period {
-            if self.delay_timer > 0 {
-                self.delay_timer -= 1;
-                timers_decremented = true;
-            }
-
-            if self.sound_timer > 0 {
-                self.sound_timer -= 1;
-                timers_decremented = true;
-            }
-
+            ticks += 1;
+            timers_decremented |= self.tick();
             self.accumulated_time -= timer_period;
         }
+        self.late_ticks += ticks.saturating_sub(1);
 
         timers_decremented
     }
 
+    /// Gets the number of ticks applied late, counted since creation.
+    ///
+    /// A tick is late when `update` runs more than a timer period after the
+    /// previous update and has to catch up on several ticks at once.
+    pub fn late_ticks(&self) -> u64 {
+        self.late_ticks
+    }
+
     /// Updates timers by a specific number of ticks.
     ///
     /// This is useful for testing or when you want to manually control
@@ -129,15 +378,7 @@ impl Timers {
         let mut timers_decremented = false;
 
         for _ in 0..ticks {
-            if self.delay_timer > 0 {
-                self.delay_timer -= 1;
-                timers_decremented = true;
-            }
-
-            if self.sound_timer > 0 {
-                self.sound_timer -= 1;
-                timers_decremented = true;
-            }
+            timers_decremented |= self.tick();
         }
 
         timers_decremented
@@ -162,11 +403,7 @@ impl Timers {
     /// This is calculated based on elapsed real time and can be useful for
     /// performance monitoring and debugging.
     pub fn total_ticks_elapsed(&self) -> u64 {
-        let total_time = self.last_update.duration_since(
-            self.last_update - self.accumulated_time - Duration::from_secs(0), // Approximation
-        );
-
-        total_time.as_millis() as u64 / TIMER_PERIOD_MS
+        self.accumulated_time.as_millis() as u64 / TIMER_PERIOD_MS
     }
 
     /// Synchronizes the timer update rate to maintain 60Hz accuracy.
@@ -174,10 +411,21 @@ impl Timers {
     /// This method can be used to keep the timers synchronized with
     /// external timing sources or to correct for drift.
     pub fn sync_to_real_time(&mut self) {
-        self.last_update = Instant::now();
+        self.last_update = self.time_source.now();
         self.accumulated_time = Duration::new(0, 0);
     }
 
+    /// Gets the time accumulated towards the next tick.
+    pub fn phase(&self) -> Duration {
+        self.accumulated_time
+    }
+
+    /// Sets the time accumulated towards the next tick, e.g. when restoring
+    /// a snapshot, so the next tick lands where it would have.
+    pub fn set_phase(&mut self, phase: Duration) {
+        self.accumulated_time = phase;
+    }
+
     /// Gets both timer values as a tuple for convenience.
     ///
     /// # Returns
@@ -193,7 +441,9 @@ impl Timers {
     /// * `sound_value` - Sound timer value
     pub fn set_both_timers(&mut self, delay_value: u8, sound_value: u8) {
         self.delay_timer = delay_value;
-        self.sound_timer = sound_value;
+        self.sounding = false;
+        self.sound_hold = 0;
+        self.set_sound_timer(sound_value);
     }
 
     /// Checks if both timers are zero (inactive).
@@ -239,7 +489,6 @@ pub struct TimingStats {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::thread;
 
     #[test]
     fn test_timer_creation() {
@@ -369,6 +618,7 @@ mod tests {
         assert_eq!(stats.period_ms, 16);
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn test_real_time_update() {
         let mut timers = Timers::new();
@@ -376,7 +626,7 @@ mod tests {
         timers.set_sound_timer(30); // Should last about 0.5 seconds at 60Hz
 
         // Sleep for a short time to allow some timer updates
-        thread::sleep(Duration::from_millis(50)); // Sleep for 50ms
+        std::thread::sleep(Duration::from_millis(50)); // Sleep for 50ms
 
         let updated = timers.update();
 
@@ -390,6 +640,59 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_mock_clock_update() {
+        let clock = MockClock::new();
+        let mut timers = Timers::with_time_source(clock.clone());
+        timers.set_both_timers(10, 2);
+
+        clock.advance(Duration::from_millis(10));
+        assert!(!timers.update());
+
+        // 10ms + 30ms covers two 16ms periods
+        clock.advance(Duration::from_millis(30));
+        assert!(timers.update());
+        assert_eq!(timers.get_both_timers(), (8, 0));
+        assert_eq!(timers.time_until_next_update(), Duration::from_millis(8));
+        // The second of the two ticks came late
+        assert_eq!(timers.late_ticks(), 1);
+
+        // Time spent elsewhere isn't counted after a sync
+        clock.advance(Duration::from_secs(1));
+        timers.sync_to_real_time();
+        assert!(!timers.update());
+        assert_eq!(timers.get_delay_timer(), 8);
+        assert_eq!(timers.late_ticks(), 1);
+    }
+
+    #[test]
+    fn test_cycle_clock() {
+        let clock = CycleClock::new(600);
+        let mut timers = Timers::with_time_source(clock.clone());
+        timers.set_delay_timer(10);
+
+        // 600 cycles per second is 10 cycles per 60Hz tick
+        clock.add_cycles(9);
+        assert!(!timers.update());
+        clock.add_cycles(1);
+        assert!(timers.update());
+        assert_eq!(timers.get_delay_timer(), 9);
+        assert_eq!(clock.now(), Duration::from_nanos(16_666_666));
+    }
+
+    #[test]
+    fn test_set_time_source() {
+        let mut timers = Timers::new();
+        let clock = MockClock::new();
+        clock.set(Duration::from_secs(5));
+        timers.set_time_source(clock.clone());
+        timers.set_delay_timer(3);
+
+        clock.advance(Duration::from_millis(TIMER_PERIOD_MS));
+        timers.update();
+        assert_eq!(timers.get_delay_timer(), 2);
+    }
+
     #[test]
     fn test_time_until_next_update() {
         let mut timers = Timers::new();
@@ -442,4 +745,75 @@ mod tests {
         assert_eq!(timers.get_delay_timer(), 5);
         assert_eq!(timers.get_sound_timer(), 0);
     }
+
+    /// Counts how many of the next ticks sound, up to `limit`.
+    fn audible_ticks(timers: &mut Timers, limit: u32) -> u32 {
+        (0..limit)
+            .filter(|_| {
+                let sounding = timers.should_play_sound();
+                timers.update_by_ticks(1);
+                sounding
+            })
+            .count() as u32
+    }
+
+    #[test]
+    fn test_exact_sound_mode() {
+        let mut timers = Timers::new();
+        assert_eq!(timers.sound_mode(), SoundTimerMode::Exact);
+        timers.set_sound_timer(1);
+        assert_eq!(audible_ticks(&mut timers, 10), 1);
+        timers.set_sound_timer(4);
+        assert_eq!(audible_ticks(&mut timers, 10), 4);
+
+        // Clearing the timer stops the beep at once
+        timers.set_sound_timer(4);
+        timers.set_sound_timer(0);
+        assert!(!timers.should_play_sound());
+    }
+
+    #[test]
+    fn test_skip_if_1_sound_mode() {
+        let mut timers = Timers::new();
+        timers.set_sound_mode(SoundTimerMode::SkipIf1);
+        timers.set_sound_timer(1);
+        assert!(!timers.should_play_sound());
+        assert_eq!(audible_ticks(&mut timers, 10), 0);
+
+        // Longer beeps still sound down to their last tick, and setting 1
+        // while one is under way doesn't cut it off
+        timers.set_sound_timer(3);
+        assert_eq!(audible_ticks(&mut timers, 10), 3);
+        timers.set_sound_timer(3);
+        timers.update_by_ticks(1);
+        timers.set_sound_timer(1);
+        assert_eq!(audible_ticks(&mut timers, 10), 1);
+    }
+
+    #[test]
+    fn test_minimum_duration_sound_mode() {
+        let mut timers = Timers::new();
+        // 50 ms is three 60 Hz ticks
+        timers.set_sound_mode(SoundTimerMode::MinimumDurationMs(50));
+        timers.set_sound_timer(1);
+        assert_eq!(audible_ticks(&mut timers, 10), 3);
+        timers.set_sound_timer(5);
+        assert_eq!(audible_ticks(&mut timers, 10), 5);
+
+        // Clearing the timer early still lets the minimum play out
+        timers.set_sound_timer(5);
+        timers.set_sound_timer(0);
+        assert_eq!(audible_ticks(&mut timers, 10), 3);
+
+        #[derive(Deserialize)]
+        struct Wrapper {
+            mode: SoundTimerMode,
+        }
+        let parse = |text| toml::from_str::<Wrapper>(text).unwrap().mode;
+        assert_eq!(
+            parse("mode = { minimum-duration-ms = 50 }"),
+            SoundTimerMode::MinimumDurationMs(50)
+        );
+        assert_eq!(parse(r#"mode = "skip-if-1""#), SoundTimerMode::SkipIf1);
+    }
 }
@@ -0,0 +1,215 @@
+//! Instruction timing models.
+//!
+//! The simple model treats every instruction as taking the same time. The
+//! COSMAC VIP model charges each instruction the machine cycles the original
+//! interpreter spent on it, measured against the VIP's 1.76 MHz clock, so
+//! programs run at the speed they did on real hardware.
+
+use serde::{Deserialize, Serialize};
+
+use crate::emulator::timers::TIMER_FREQUENCY;
+
+/// COSMAC VIP clock frequency in Hz.
+pub const VIP_CLOCK_HZ: u32 = 1_760_640;
+
+/// CDP1802 clock cycles per machine cycle.
+pub const VIP_CLOCKS_PER_MACHINE_CYCLE: u32 = 8;
+
+/// VIP machine cycles per second.
+pub const VIP_MACHINE_CYCLES_PER_SECOND: u32 = VIP_CLOCK_HZ / VIP_CLOCKS_PER_MACHINE_CYCLE;
+
+/// VIP machine cycles per 60 Hz display frame.
+pub const VIP_MACHINE_CYCLES_PER_FRAME: u32 = VIP_MACHINE_CYCLES_PER_SECOND / 60;
+
+/// How long instructions take to execute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TimingModel {
+    /// Every instruction costs one cycle, at the given instructions per second.
+    Simple(u32),
+
+    /// Instructions cost their COSMAC VIP machine cycles at 1.76 MHz.
+    CosmacVip,
+}
+
+impl Default for TimingModel {
+    fn default() -> Self {
+        TimingModel::Simple(700)
+    }
+}
+
+impl TimingModel {
+    /// Gets how many cycles of this model's clock run per second.
+    pub fn cycles_per_second(&self) -> u32 {
+        match self {
+            TimingModel::Simple(cps) => *cps,
+            TimingModel::CosmacVip => VIP_MACHINE_CYCLES_PER_SECOND,
+        }
+    }
+
+    /// Gets the cost of an executed instruction in this model's cycles.
+    ///
+    /// # Arguments
+    /// * `opcode` - The instruction that ran
+    /// * `context` - Details of how it ran
+    pub fn instruction_cost(&self, opcode: u16, context: &CostContext) -> u32 {
+        match self {
+            TimingModel::Simple(_) => 1,
+            TimingModel::CosmacVip => vip_machine_cycles(opcode, context),
+        }
+    }
+}
+
+/// Clock cycles charged during one frame, against what the frame allows.
+///
+/// A frame's budget is the timing model's cycles per second divided by the
+/// 60 Hz timer rate. Programs that use more than the whole budget would
+/// slow down on the original hardware.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameBudget {
+    /// Clock cycles charged during the frame.
+    pub cycles: u64,
+
+    /// Instructions executed during the frame.
+    pub instructions: u64,
+
+    /// Clock cycles per second of the timing model in use.
+    pub cycles_per_second: u32,
+}
+
+impl FrameBudget {
+    /// Gets the clock cycles available in one frame.
+    pub fn available(&self) -> f64 {
+        self.cycles_per_second as f64 / TIMER_FREQUENCY as f64
+    }
+
+    /// Gets the share of the budget used, where 1.0 is the whole frame.
+    pub fn used(&self) -> f64 {
+        let available = self.available();
+        if available == 0.0 {
+            return 0.0;
+        }
+        self.cycles as f64 / available
+    }
+}
+
+/// Execution details that affect an instruction's cost.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CostContext {
+    /// Whether a conditional skip was taken.
+    pub skipped: bool,
+
+    /// Value of VX after the instruction.
+    pub vx: u8,
+
+    /// Machine cycles elapsed before the instruction, for frame alignment.
+    pub elapsed: u64,
+}
+
+/// Gets the machine cycles the COSMAC VIP interpreter spends on an instruction.
+///
+/// Counts follow published analyses of the original interpreter and include
+/// its fetch/decode overhead. DXYN waits for the next display interrupt
+/// before drawing, so its cost depends on where in the frame it starts.
+pub fn vip_machine_cycles(opcode: u16, context: &CostContext) -> u32 {
+    let skip = if context.skipped { 4 } else { 0 };
+    let x = ((opcode >> 8) & 0xF) as u32;
+    let n = (opcode & 0xF) as u32;
+
+    match opcode & 0xF000 {
+        0x0000 if opcode == 0x00E0 => 24,
+        0x1000 => 12,
+        0x2000 => 26,
+        0x3000 | 0x4000 => 10 + skip,
+        0x5000 | 0x9000 => 14 + skip,
+        0x6000 => 6,
+        0x7000 => 10,
+        0x8000 => 44,
+        0xA000 => 12,
+        0xB000 => 22,
+        0xC000 => 36,
+        0xD000 => {
+            // Unaligned sprites straddle two bytes per row
+            let per_row = if context.vx.is_multiple_of(8) { 46 } else { 66 };
+            vip_display_wait(context.elapsed) + 68 + n * per_row
+        }
+        0xE000 => 14 + skip,
+        0xF000 => match opcode & 0x00FF {
+            0x1E => 16,
+            0x29 => 20,
+            0x33 => {
+                let v = context.vx as u32;
+                84 + 16 * (v / 100 + v / 10 % 10 + v % 10)
+            }
+            0x55 | 0x65 => 14 + 14 * (x + 1),
+            _ => 10,
+        },
+        _ => 10,
+    }
+}
+
+/// Gets the machine cycles a VIP draw waits for the next display interrupt.
+///
+/// # Arguments
+/// * `elapsed` - Machine cycles elapsed when the draw starts
+pub fn vip_display_wait(elapsed: u64) -> u32 {
+    let frame = VIP_MACHINE_CYCLES_PER_FRAME as u64;
+    (frame - elapsed % frame) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simple_model() {
+        let model = TimingModel::Simple(500);
+        assert_eq!(model.cycles_per_second(), 500);
+        assert_eq!(model.instruction_cost(0xD015, &CostContext::default()), 1);
+    }
+
+    #[test]
+    fn test_frame_budget() {
+        let budget = FrameBudget {
+            cycles: VIP_MACHINE_CYCLES_PER_FRAME as u64 / 2,
+            instructions: 10,
+            cycles_per_second: TimingModel::CosmacVip.cycles_per_second(),
+        };
+        assert_eq!(budget.available(), 3668.0);
+        assert!((budget.used() - 0.5).abs() < 1e-9);
+        assert_eq!(FrameBudget::default().used(), 0.0);
+    }
+
+    #[test]
+    fn test_vip_costs() {
+        let model = TimingModel::CosmacVip;
+        let context = CostContext::default();
+        assert_eq!(model.cycles_per_second(), 220_080);
+
+        assert_eq!(model.instruction_cost(0x6012, &context), 6);
+        assert_eq!(model.instruction_cost(0x8124, &context), 44);
+        assert_eq!(model.instruction_cost(0xF355, &context), 70);
+
+        let skipped = CostContext {
+            skipped: true,
+            ..context
+        };
+        assert_eq!(model.instruction_cost(0x3000, &skipped), 14);
+    }
+
+    #[test]
+    fn test_vip_draw_waits_for_frame() {
+        let frame = VIP_MACHINE_CYCLES_PER_FRAME;
+        let start = CostContext::default();
+        let late = CostContext {
+            elapsed: (frame - 100) as u64,
+            ..start
+        };
+
+        assert_eq!(vip_machine_cycles(0xD015, &start), frame + 68 + 5 * 46);
+        assert_eq!(vip_machine_cycles(0xD015, &late), 100 + 68 + 5 * 46);
+
+        let unaligned = CostContext { vx: 3, ..late };
+        assert_eq!(vip_machine_cycles(0xD015, &unaligned), 100 + 68 + 5 * 66);
+    }
+}
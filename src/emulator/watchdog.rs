@@ -0,0 +1,200 @@
+//! Detection of runaway and stuck programs.
+//!
+//! Headless runs (tests, CI, `SimpleEmulator::run_cycles`) have nobody to
+//! close the window when a ROM gets stuck. The watchdog looks at the CPU
+//! after every cycle and reports a spin loop when the program jumps to
+//! itself, or when the PC stays inside a small range for too long with no
+//! display, timer or input activity.
+
+use serde::{Deserialize, Serialize};
+
+use super::Cpu;
+use crate::error::Halted;
+use crate::hardware::{Hardware, Input};
+
+/// Thresholds used by the [`Watchdog`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchdogConfig {
+    /// Whether a jump to itself (`JP self`) stops the run right away.
+    pub jump_to_self: bool,
+
+    /// Cycles without progress or activity before the run is stopped.
+    pub stall_cycles: u64,
+
+    /// Largest PC range, in bytes, that counts as not progressing.
+    pub loop_span: u16,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            jump_to_self: true,
+            stall_cycles: 100_000,
+            loop_span: 16,
+        }
+    }
+}
+
+impl WatchdogConfig {
+    /// Creates the default thresholds.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether a jump to itself stops the run right away.
+    pub fn with_jump_to_self(mut self, enabled: bool) -> Self {
+        self.jump_to_self = enabled;
+        self
+    }
+
+    /// Sets how many cycles without progress stop the run.
+    pub fn with_stall_cycles(mut self, cycles: u64) -> Self {
+        self.stall_cycles = cycles.max(1);
+        self
+    }
+
+    /// Sets the largest PC range that counts as not progressing.
+    pub fn with_loop_span(mut self, span: u16) -> Self {
+        self.loop_span = span;
+        self
+    }
+}
+
+/// Watches a running CPU for spin loops.
+#[derive(Debug, Clone)]
+pub struct Watchdog {
+    /// Detection thresholds.
+    config: WatchdogConfig,
+
+    /// Lowest PC seen since the last progress.
+    low: u16,
+
+    /// Highest PC seen since the last progress.
+    high: u16,
+
+    /// Cycles since the last progress or activity.
+    quiet_cycles: u64,
+
+    /// Sprite count at the last check.
+    sprites_drawn: u64,
+}
+
+impl Watchdog {
+    /// Creates a watchdog with the given thresholds.
+    pub fn new(config: WatchdogConfig) -> Self {
+        Self {
+            config,
+            low: 0,
+            high: 0,
+            quiet_cycles: 0,
+            sprites_drawn: 0,
+        }
+    }
+
+    /// Gets the detection thresholds.
+    pub fn config(&self) -> &WatchdogConfig {
+        &self.config
+    }
+
+    /// Forgets what was seen so far, e.g. after loading a ROM.
+    pub fn reset(&mut self) {
+        self.quiet_cycles = 0;
+        self.low = 0;
+        self.high = 0;
+    }
+
+    /// Checks the CPU after a cycle.
+    ///
+    /// # Returns
+    /// The reason to stop, if the program is stuck.
+    pub fn check<H: Hardware>(&mut self, cpu: &mut Cpu<H>) -> Option<Halted> {
+        let pc = cpu.get_registers().get_pc();
+        if self.config.jump_to_self && cpu.is_halted() {
+            return Some(Halted::SpinLoop {
+                pc,
+                cycles: self.quiet_cycles,
+            });
+        }
+
+        let sprites_drawn = cpu.metrics().sprites_drawn;
+        let timers = cpu.get_timers();
+        let active = sprites_drawn != self.sprites_drawn
+            || timers.get_delay_timer() > 0
+            || timers.get_sound_timer() > 0
+            || cpu.hardware_mut().input().any_key_pressed();
+        self.sprites_drawn = sprites_drawn;
+
+        let low = self.low.min(pc);
+        let high = self.high.max(pc);
+        if active || self.quiet_cycles == 0 || high - low > self.config.loop_span {
+            // Progress: start a new window at the current PC
+            self.low = pc;
+            self.high = pc;
+            self.quiet_cycles = 1;
+            return None;
+        }
+
+        self.low = low;
+        self.high = high;
+        self.quiet_cycles += 1;
+        (self.quiet_cycles >= self.config.stall_cycles).then_some(Halted::SpinLoop {
+            pc,
+            cycles: self.quiet_cycles,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_jump_to_self() {
+        let mut cpu = Cpu::new();
+        cpu.load_rom(&[0x60, 0x01, 0x12, 0x02]).unwrap(); // LD V0, 1; JP 0x202
+        let mut watchdog = Watchdog::new(WatchdogConfig::default());
+
+        cpu.cycle().unwrap();
+        assert_eq!(watchdog.check(&mut cpu), None);
+        cpu.cycle().unwrap();
+        assert_eq!(
+            watchdog.check(&mut cpu),
+            Some(Halted::SpinLoop {
+                pc: 0x202,
+                cycles: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_detects_tight_loop_without_activity() {
+        // LD V0, 0; ADD V0, 1; JP 0x202
+        let mut cpu = Cpu::new();
+        cpu.load_rom(&[0x60, 0x00, 0x70, 0x01, 0x12, 0x02]).unwrap();
+        let mut watchdog = Watchdog::new(WatchdogConfig::new().with_stall_cycles(50));
+
+        let halted = (0..100).find_map(|_| {
+            cpu.cycle().unwrap();
+            watchdog.check(&mut cpu)
+        });
+        assert!(matches!(halted, Some(Halted::SpinLoop { cycles: 50, .. })));
+    }
+
+    #[test]
+    fn test_running_timer_is_activity() {
+        // LD V0, 60; LD DT, V0; LD V1, DT; SE V1, 0; JP 0x204; JP 0x20A
+        let mut cpu = Cpu::new();
+        cpu.set_realtime_timers(false);
+        cpu.load_rom(&[
+            0x60, 0x3C, 0xF0, 0x15, 0xF1, 0x07, 0x31, 0x00, 0x12, 0x04, 0x12, 0x0A,
+        ])
+        .unwrap();
+        let mut watchdog = Watchdog::new(WatchdogConfig::new().with_stall_cycles(50));
+
+        // The delay timer never ticks here, so the loop keeps waiting on it
+        for _ in 0..200 {
+            cpu.cycle().unwrap();
+            assert_eq!(watchdog.check(&mut cpu), None);
+        }
+    }
+}
@@ -2,7 +2,17 @@
 //!
 //! This module defines all the error types used throughout the emulator,
 //! providing clear error messages and proper error propagation.
+//!
+//! Variants wrapping errors from `std` or host libraries (I/O, TOML,
+//! pixels, netplay) only exist with the `std` feature.
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
 
+#[cfg(feature = "std")]
 use pixels::Error as PixelsError;
 use thiserror::Error;
 
@@ -12,6 +22,12 @@ pub enum EmulatorError {
     #[error("Invalid memory access at address {address:#04x}")]
     InvalidMemoryAccess { address: u16 },
 
+    #[error("Write to protected memory at address {address:#04x}")]
+    ProtectedMemoryWrite { address: u16 },
+
+    #[error("Address {address:#08x} is beyond the 16-bit index register")]
+    AddressOutOfRange { address: u32 },
+
     #[error("Unknown instruction {opcode:#04x}")]
     UnknownInstruction { opcode: u16 },
 
@@ -21,6 +37,9 @@ pub enum EmulatorError {
     #[error("ROM file is empty")]
     RomEmpty,
 
+    #[error("ROM failed sanity checks: {reason}")]
+    RomRejected { reason: String },
+
     #[error("Stack overflow")]
     StackOverflow,
 
@@ -30,6 +49,13 @@ pub enum EmulatorError {
     #[error("Invalid register index: {index}")]
     InvalidRegister { index: u8 },
 
+    #[error("Snapshot {part} is {found} bytes, expected {expected}")]
+    SnapshotMismatch {
+        part: &'static str,
+        expected: usize,
+        found: usize,
+    },
+
     #[error("Audio system error: {0}")]
     AudioError(#[from] AudioError),
 
@@ -39,22 +65,156 @@ pub enum EmulatorError {
     #[error("Input system error: {0}")]
     InputError(#[from] InputError),
 
+    #[cfg(feature = "std")]
     #[error("I/O error: {0}")]
     IoError(#[from] std::io::Error),
 
     #[error("Configuration error: {0}")]
     ConfigError(#[from] ConfigError),
 
+    #[cfg(feature = "std")]
     #[error("TOML parsing error: {0}")]
     TomlParseError(#[from] toml::de::Error),
 
+    #[cfg(feature = "std")]
     #[error("TOML serialization error: {0}")]
     TomlSerializeError(#[from] toml::ser::Error),
 
+    #[cfg(feature = "std")]
     #[error("Pixels error: {0}")]
     PixelsError(String),
+
+    #[cfg(feature = "std")]
+    #[error("Netplay error: {0}")]
+    Netplay(#[from] NetplayError),
+
+    #[error("Replay error: {0}")]
+    Replay(#[from] ReplayError),
+
+    #[error("Reference dump error: {0}")]
+    Reference(#[from] ReferenceError),
+
+    #[error("{failed} environment check(s) failed")]
+    ChecksFailed { failed: usize },
+
+    #[error("Save state error: {0}")]
+    SaveState(#[from] SaveStateError),
+
+    #[error("Octo error: {0}")]
+    Octo(#[from] OctoError),
+
+    #[error("Expression error: {0}")]
+    Expression(#[from] ExpressionError),
+
+    #[error("Cheat error: {0}")]
+    Cheat(#[from] CheatError),
+
+    #[error("ROM patch error: {0}")]
+    Patch(#[from] PatchError),
+
+    #[error("Program halted: {0}")]
+    Halted(#[from] Halted),
+
+    #[error("{source}\n{context}")]
+    Execution {
+        source: Box<EmulatorError>,
+        context: Box<FaultContext>,
+    },
+}
+
+/// Where the CPU was when an error stopped an instruction.
+///
+/// Errors returned by `Cpu::cycle` carry this so a message alone is
+/// enough to locate the fault in the ROM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FaultContext {
+    /// Address of the instruction.
+    pub pc: u16,
+
+    /// The instruction word, or `None` if it couldn't be fetched.
+    pub opcode: Option<u16>,
+
+    /// Instructions completed before this one.
+    pub instruction_count: u64,
+
+    /// General purpose registers V0-VF.
+    pub v: [u8; 16],
+
+    /// Index register.
+    pub i: u16,
+
+    /// Stack depth.
+    pub sp: u8,
 }
 
+impl fmt::Display for FaultContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let opcode = self
+            .opcode
+            .map_or_else(|| "????".to_string(), |opcode| format!("{:04X}", opcode));
+        writeln!(
+            f,
+            "  at PC 0x{:04X} (opcode {}) after {} instructions",
+            self.pc, opcode, self.instruction_count
+        )?;
+        for (row, registers) in self.v.chunks(8).enumerate() {
+            let line: Vec<String> = registers
+                .iter()
+                .enumerate()
+                .map(|(i, v)| format!("V{:X}={:02X}", row * 8 + i, v))
+                .collect();
+            writeln!(f, "  {}", line.join(" "))?;
+        }
+        write!(f, "  I=0x{:04X} SP={}", self.i, self.sp)
+    }
+}
+
+impl EmulatorError {
+    /// Attaches execution context, unless the error already has some.
+    pub fn with_context(self, context: FaultContext) -> Self {
+        match self {
+            EmulatorError::Execution { .. } => self,
+            source => EmulatorError::Execution {
+                source: Box::new(source),
+                context: Box::new(context),
+            },
+        }
+    }
+
+    /// Gets the underlying error, without any execution context.
+    pub fn root(&self) -> &EmulatorError {
+        match self {
+            EmulatorError::Execution { source, .. } => source.root(),
+            _ => self,
+        }
+    }
+
+    /// Gets the execution context, if the error came from running an instruction.
+    pub fn context(&self) -> Option<&FaultContext> {
+        match self {
+            EmulatorError::Execution { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// Checks if the error is a fault in the running program rather than the host.
+    ///
+    /// These are the errors worth a fault report: unknown opcodes, stack
+    /// faults and bad memory accesses.
+    pub fn is_cpu_fault(&self) -> bool {
+        matches!(
+            self.root(),
+            EmulatorError::InvalidMemoryAccess { .. }
+                | EmulatorError::ProtectedMemoryWrite { .. }
+                | EmulatorError::UnknownInstruction { .. }
+                | EmulatorError::StackOverflow
+                | EmulatorError::StackUnderflow
+                | EmulatorError::InvalidRegister { .. }
+        )
+    }
+}
+
+#[cfg(feature = "std")]
 impl From<PixelsError> for EmulatorError {
     fn from(err: PixelsError) -> Self {
         EmulatorError::PixelsError(err.to_string())
@@ -104,6 +264,9 @@ pub enum GraphicsError {
     #[error("Pixels initialization failed: {0}")]
     PixelsInitializationFailed(String),
 
+    #[error("Display backend failed: {0}")]
+    BackendFailed(String),
+
     #[error("Window resize failed: {0}")]
     ResizeFailed(String),
 
@@ -115,6 +278,12 @@ pub enum GraphicsError {
 
     #[error("Invalid buffer size: expected {expected}, got {actual}")]
     InvalidBufferSize { expected: usize, actual: usize },
+
+    #[error("Unsupported display resolution: {width}x{height}")]
+    UnsupportedResolution { width: usize, height: usize },
+
+    #[error("Screenshot failed: {0}")]
+    ScreenshotFailed(String),
 }
 
 /// Configuration-specific error types.
@@ -126,11 +295,16 @@ pub enum ConfigError {
     #[error("Missing required configuration key: {key}")]
     MissingKey { key: String },
 
+    #[cfg(feature = "std")]
     #[error("Configuration file format error: {0}")]
     FormatError(#[from] toml::de::Error),
 
+    #[cfg(feature = "std")]
     #[error("Configuration serialization error: {0}")]
     SerializationError(#[from] toml::ser::Error),
+
+    #[error("Config version {found} is newer than this build supports ({supported})")]
+    UnsupportedVersion { found: u32, supported: u32 },
 }
 
 /// Input-specific error types.
@@ -146,20 +320,210 @@ pub enum InputError {
     DeviceNotAvailable,
 }
 
+/// Netplay-specific error types.
+#[cfg(feature = "std")]
+#[derive(Error, Debug)]
+pub enum NetplayError {
+    #[error("Network error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Protocol version mismatch: local {local}, remote {remote}")]
+    VersionMismatch { local: u16, remote: u16 },
+
+    #[error("ROM mismatch: both players must load the same ROM")]
+    RomMismatch,
+
+    #[error("Emulator settings differ between players: {0}")]
+    SettingsMismatch(String),
+
+    #[error("Session rejected by peer: {0}")]
+    Rejected(String),
+
+    #[error("Malformed message: {0}")]
+    Protocol(String),
+
+    #[error("Peer disconnected")]
+    Disconnected,
+}
+
+/// Replay-specific error types.
+#[derive(Error, Debug)]
+pub enum ReplayError {
+    #[error("Not a replay file")]
+    NotAReplay,
+
+    #[error("Unsupported replay version {found} (this build reads up to {supported})")]
+    UnsupportedVersion { found: u32, supported: u32 },
+
+    #[error("ROM mismatch: the replay was recorded with ROM {expected}, not {found}")]
+    RomMismatch { expected: String, found: String },
+}
+
+/// Reference dump error types.
+#[derive(Error, Debug)]
+pub enum ReferenceError {
+    #[error("Invalid reference dump: {0}")]
+    InvalidDump(String),
+
+    #[error("Invalid reference screen for frame {frame}: {reason}")]
+    InvalidFrame { frame: u32, reason: String },
+
+    #[error("{rom} scored {score:.1}%, below the minimum of {minimum:.1}%")]
+    BelowMinimum {
+        rom: String,
+        score: f64,
+        minimum: f64,
+    },
+}
+
+/// Save state error types.
+#[derive(Error, Debug)]
+pub enum SaveStateError {
+    #[error("Not a save state file")]
+    NotASaveState,
+
+    #[error("Unsupported save state version {found} (this build reads up to {supported})")]
+    UnsupportedVersion { found: u32, supported: u32 },
+
+    #[error("Save state was written by core {found}, which is incompatible with {current}")]
+    IncompatibleCore { found: String, current: String },
+
+    #[error("ROM mismatch: the state was saved with ROM {expected}, not {found}")]
+    RomMismatch { expected: String, found: String },
+
+    #[error("Slot {slot} is empty")]
+    EmptySlot { slot: u8 },
+
+    #[error("No auto-save for this ROM")]
+    NoAutosave,
+
+    #[error("Invalid slot {slot} (slots are 0-{max})")]
+    InvalidSlot { slot: u8, max: u8 },
+}
+
+/// Octo compiler error types.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum OctoError {
+    #[error("Line {line}: {message}")]
+    Syntax { line: usize, message: String },
+
+    #[error("Line {line}: undefined name '{name}'")]
+    UndefinedName { line: usize, name: String },
+
+    #[error("Line {line}: '{name}' is already defined")]
+    Redefined { line: usize, name: String },
+
+    #[error("Line {line}: value {value} is out of range")]
+    OutOfRange { line: usize, value: i64 },
+
+    #[error("Line {line}: '{construct}' is never closed")]
+    Unterminated { line: usize, construct: String },
+
+    #[error("Unexpected end of source")]
+    UnexpectedEnd,
+
+    #[error("Program has no ': main' label")]
+    MissingMain,
+}
+
+/// Debugger expression error types.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ExpressionError {
+    #[error("Unexpected '{found}' at column {column}")]
+    UnexpectedToken { found: String, column: usize },
+
+    #[error("Unexpected end of expression")]
+    UnexpectedEnd,
+
+    #[error("Invalid number '{text}'")]
+    InvalidNumber { text: String },
+
+    #[error("Division by zero")]
+    DivisionByZero,
+
+    #[error("Can't read variable '{name}'")]
+    UnreadableVariable { name: String },
+}
+
+/// Cheat error types.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum CheatError {
+    #[error("Invalid cheat code '{code}': {reason}")]
+    InvalidCode { code: String, reason: String },
+
+    #[error("No cheat named '{name}'")]
+    UnknownCheat { name: String },
+}
+
+/// IPS and BPS patch error types.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PatchError {
+    #[error("Not an IPS or BPS patch")]
+    UnknownFormat,
+
+    #[error("Patch ends early")]
+    Truncated,
+
+    #[error("Malformed patch: {0}")]
+    Malformed(String),
+
+    #[error("Patch expects a {expected}-byte ROM, not {found} bytes")]
+    SourceSize { expected: usize, found: usize },
+
+    #[error("{what} CRC-32 is {found:08x}, expected {expected:08x}")]
+    ChecksumMismatch {
+        what: &'static str,
+        expected: u32,
+        found: u32,
+    },
+}
+
+/// Reasons the watchdog stops a headless run.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Halted {
+    #[error("spin loop at {pc:#05x} after {cycles} cycles without progress")]
+    SpinLoop { pc: u16, cycles: u64 },
+}
+
 /// Alias for Result with EmulatorError.
-pub type Result<T> = std::result::Result<T, EmulatorError>;
+pub type Result<T> = core::result::Result<T, EmulatorError>;
 
 /// Alias for Result with AudioError.
-pub type AudioResult<T> = std::result::Result<T, AudioError>;
+pub type AudioResult<T> = core::result::Result<T, AudioError>;
 
 /// Alias for Result with GraphicsError.
-pub type GraphicsResult<T> = std::result::Result<T, GraphicsError>;
+pub type GraphicsResult<T> = core::result::Result<T, GraphicsError>;
 
 /// Alias for Result with ConfigError.
-pub type ConfigResult<T> = std::result::Result<T, ConfigError>;
+pub type ConfigResult<T> = core::result::Result<T, ConfigError>;
 
 /// Alias for Result with InputError.
-pub type InputResult<T> = std::result::Result<T, InputError>;
+pub type InputResult<T> = core::result::Result<T, InputError>;
+
+/// Alias for Result with NetplayError.
+#[cfg(feature = "std")]
+pub type NetplayResult<T> = core::result::Result<T, NetplayError>;
+
+/// Alias for Result with ReplayError.
+pub type ReplayResult<T> = core::result::Result<T, ReplayError>;
+
+/// Alias for Result with ReferenceError.
+pub type ReferenceResult<T> = core::result::Result<T, ReferenceError>;
+
+/// Alias for Result with SaveStateError.
+pub type SaveStateResult<T> = core::result::Result<T, SaveStateError>;
+
+/// Alias for Result with OctoError.
+pub type OctoResult<T> = core::result::Result<T, OctoError>;
+
+/// Alias for Result with ExpressionError.
+pub type ExpressionResult<T> = core::result::Result<T, ExpressionError>;
+
+/// Alias for Result with CheatError.
+pub type CheatResult<T> = core::result::Result<T, CheatError>;
+
+/// Alias for Result with PatchError.
+pub type PatchResult<T> = core::result::Result<T, PatchError>;
 
 #[cfg(test)]
 mod tests {
@@ -189,6 +553,48 @@ mod tests {
         assert_eq!(error.to_string(), "Unknown instruction 0xabcd");
     }
 
+    #[test]
+    fn test_execution_context() {
+        let context = FaultContext {
+            pc: 0x204,
+            opcode: Some(0xFFFF),
+            instruction_count: 2,
+            v: [0; 16],
+            i: 0x300,
+            sp: 1,
+        };
+        let error =
+            EmulatorError::UnknownInstruction { opcode: 0xFFFF }.with_context(context.clone());
+
+        assert!(matches!(
+            error.root(),
+            EmulatorError::UnknownInstruction { opcode: 0xFFFF }
+        ));
+        assert_eq!(error.context(), Some(&context));
+        assert!(error.is_cpu_fault());
+        assert_eq!(
+            error.to_string(),
+            "Unknown instruction 0xffff\n\
+             \x20 at PC 0x0204 (opcode FFFF) after 2 instructions\n\
+             \x20 V0=00 V1=00 V2=00 V3=00 V4=00 V5=00 V6=00 V7=00\n\
+             \x20 V8=00 V9=00 VA=00 VB=00 VC=00 VD=00 VE=00 VF=00\n\
+             \x20 I=0x0300 SP=1"
+        );
+
+        // Context is only attached once
+        let mut outer = context;
+        outer.pc = 0x300;
+        assert_eq!(error.with_context(outer).context().unwrap().pc, 0x204);
+    }
+
+    #[test]
+    fn test_cpu_fault_classification() {
+        assert!(EmulatorError::StackOverflow.is_cpu_fault());
+        assert!(EmulatorError::UnknownInstruction { opcode: 0xFFFF }.is_cpu_fault());
+        assert!(!EmulatorError::RomEmpty.is_cpu_fault());
+        assert!(!EmulatorError::Graphics(GraphicsError::WindowClosed).is_cpu_fault());
+    }
+
     #[test]
     fn test_audio_error_conversion() {
         let audio_err = AudioError::InitializationFailed;
@@ -0,0 +1,556 @@
+//! ROM browser for the GUI frontend.
+//!
+//! The browser lists the `.ch8` files in a directory along with what static
+//! analysis can tell about each one (size, SHA-1 and the Chip-8 variant it
+//! appears to target), and draws itself into an RGBA frame using the
+//! bitmap font.
+//!
+//! [`RomBrowser::scan`] lists the files straight away and analyzes them on
+//! a worker thread, taking results from a [`RomCache`] where it can, so
+//! large collections open without waiting for every ROM to be hashed.
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+use super::rom_cache::{CachedRom, FileStamp, RomCache};
+use super::FrontendResult;
+use crate::analysis::{analyze_rom, InstructionSet};
+use crate::graphics::font::{draw_text, fill_rect, CHAR_ADVANCE, LINE_HEIGHT};
+use crate::graphics::Color;
+use crate::logging::APP;
+
+/// Width of the browser screen in pixels.
+pub const BROWSER_WIDTH: usize = 256;
+
+/// Height of the browser screen in pixels.
+pub const BROWSER_HEIGHT: usize = 128;
+
+/// Top of the first list row.
+const LIST_TOP: usize = 10;
+
+/// Number of ROMs visible at once.
+const VISIBLE_ROWS: usize = 15;
+
+/// Characters that fit on one line.
+const LINE_CHARS: usize = BROWSER_WIDTH / CHAR_ADVANCE - 1;
+
+/// A ROM found by the browser.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomEntry {
+    /// Path to the ROM file.
+    pub path: PathBuf,
+
+    /// Display name (the file name without extension).
+    pub name: String,
+
+    /// ROM size in bytes.
+    pub size: usize,
+
+    /// SHA-1 digest as a lowercase hex string.
+    pub sha1: String,
+
+    /// Instruction sets the ROM appears to use, beyond base Chip-8.
+    pub variant_hints: Vec<InstructionSet>,
+
+    /// Whether the SHA-1 and variant hints are known yet.
+    pub analyzed: bool,
+}
+
+impl RomEntry {
+    /// Reads and analyzes a ROM file.
+    pub fn load(path: &Path) -> FrontendResult<Self> {
+        let rom = std::fs::read(path)?;
+        let analysis = analyze_rom(&rom);
+
+        Ok(Self {
+            size: analysis.size,
+            sha1: analysis.sha1,
+            variant_hints: analysis.variant_hints,
+            analyzed: true,
+            ..Self::pending(path, 0)
+        })
+    }
+
+    /// Creates an entry for a ROM that hasn't been analyzed yet.
+    pub fn pending(path: &Path, size: usize) -> Self {
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        Self {
+            path: path.to_path_buf(),
+            name,
+            size,
+            sha1: String::new(),
+            variant_hints: Vec::new(),
+            analyzed: false,
+        }
+    }
+
+    /// Creates an entry from a cached analysis.
+    fn cached(path: &Path, cached: &CachedRom) -> Self {
+        Self {
+            sha1: cached.sha1.clone(),
+            variant_hints: cached.variant_hints.clone(),
+            analyzed: true,
+            ..Self::pending(path, cached.stamp.size as usize)
+        }
+    }
+
+    /// Gets the name of the platform the ROM appears to target.
+    pub fn platform(&self) -> &'static str {
+        if self.variant_hints.contains(&InstructionSet::XoChip) {
+            "XO-CHIP"
+        } else if self.variant_hints.contains(&InstructionSet::SuperChip) {
+            "SUPER-CHIP"
+        } else {
+            "CHIP-8"
+        }
+    }
+}
+
+/// Analysis results streamed from the worker thread: a ROM's path and its
+/// entry, or `None` if it couldn't be read.
+type ScanResult = (PathBuf, Option<RomEntry>);
+
+/// A navigable list of the ROMs in a directory.
+#[derive(Debug)]
+pub struct RomBrowser {
+    /// Directory being browsed.
+    directory: PathBuf,
+
+    /// ROMs found, sorted by name.
+    entries: Vec<RomEntry>,
+
+    /// Index of the highlighted entry.
+    selected: usize,
+
+    /// Results from the worker thread, while it is still analyzing.
+    scan: Option<Receiver<ScanResult>>,
+}
+
+impl RomBrowser {
+    /// Lists the `.ch8` files in a directory.
+    ///
+    /// Files that can't be read are logged and left out.
+    pub fn open(directory: &Path) -> FrontendResult<Self> {
+        let mut entries = Vec::new();
+        for (path, _) in list_roms(directory)? {
+            match RomEntry::load(&path) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => tracing::warn!(target: APP, "Skipping {}: {}", path.display(), e),
+            }
+        }
+        entries.sort_by_key(|entry| entry.name.to_lowercase());
+
+        Ok(Self::with_entries(directory, entries))
+    }
+
+    /// Lists the `.ch8` files in a directory and analyzes them in the
+    /// background.
+    ///
+    /// ROMs whose analysis is in the cache file at `cache_path` are ready
+    /// at once; the rest are analyzed on a worker thread, which saves the
+    /// cache when it is done. Call [`poll`](Self::poll) to take its results.
+    pub fn scan(directory: &Path, cache_path: &Path) -> FrontendResult<Self> {
+        // Key the cache by absolute path so it works from any directory
+        let root = std::fs::canonicalize(directory)?;
+        let roms = list_roms(&root)?;
+        let mut cache = RomCache::load_or_default(cache_path);
+        let present: Vec<PathBuf> = roms.iter().map(|(path, _)| path.clone()).collect();
+        let pruned = cache.prune(&root, &present);
+
+        let mut entries = Vec::with_capacity(roms.len());
+        let mut pending = Vec::new();
+        for (path, stamp) in roms {
+            match cache.get(&path, stamp) {
+                Some(cached) => entries.push(RomEntry::cached(&path, cached)),
+                None => {
+                    entries.push(RomEntry::pending(&path, stamp.size as usize));
+                    pending.push((path, stamp));
+                }
+            }
+        }
+        entries.sort_by_key(|entry| entry.name.to_lowercase());
+        tracing::debug!(
+            target: APP,
+            "{} of {} ROMs in {} cached",
+            entries.len() - pending.len(),
+            entries.len(),
+            directory.display()
+        );
+
+        let mut browser = Self::with_entries(directory, entries);
+        if !pending.is_empty() {
+            browser.scan = Some(spawn_scan(pending, cache, cache_path.to_path_buf())?);
+        } else if pruned > 0 {
+            if let Err(e) = cache.save(cache_path) {
+                tracing::warn!(target: APP, "Failed to save ROM cache: {}", e);
+            }
+        }
+        Ok(browser)
+    }
+
+    /// Creates a browser over an existing list of ROMs.
+    pub fn with_entries(directory: &Path, entries: Vec<RomEntry>) -> Self {
+        Self {
+            directory: directory.to_path_buf(),
+            entries,
+            selected: 0,
+            scan: None,
+        }
+    }
+
+    /// Takes the analysis results the worker thread has finished.
+    ///
+    /// ROMs that turned out to be unreadable are removed from the list.
+    ///
+    /// # Returns
+    /// The number of entries updated or removed.
+    pub fn poll(&mut self) -> usize {
+        let Some(scan) = self.scan.take() else {
+            return 0;
+        };
+        let mut updated = 0;
+        loop {
+            match scan.try_recv() {
+                Ok((path, result)) => {
+                    let Some(index) = self.entries.iter().position(|e| e.path == path) else {
+                        continue;
+                    };
+                    match result {
+                        Some(entry) => self.entries[index] = entry,
+                        None => {
+                            self.entries.remove(index);
+                            if self.selected > index {
+                                self.selected -= 1;
+                            }
+                            self.move_selection(0);
+                        }
+                    }
+                    updated += 1;
+                }
+                Err(TryRecvError::Empty) => {
+                    self.scan = Some(scan);
+                    break;
+                }
+                Err(TryRecvError::Disconnected) => break,
+            }
+        }
+        updated
+    }
+
+    /// Checks if the worker thread is still analyzing ROMs.
+    pub fn is_scanning(&self) -> bool {
+        self.scan.is_some()
+    }
+
+    /// Gets the number of ROMs not analyzed yet.
+    pub fn pending(&self) -> usize {
+        self.entries.iter().filter(|entry| !entry.analyzed).count()
+    }
+
+    /// Gets the directory being browsed.
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Gets the ROMs found, in display order.
+    pub fn entries(&self) -> &[RomEntry] {
+        &self.entries
+    }
+
+    /// Gets the index of the highlighted ROM.
+    pub fn selected_index(&self) -> usize {
+        self.selected
+    }
+
+    /// Gets the highlighted ROM, if there are any.
+    pub fn selected(&self) -> Option<&RomEntry> {
+        self.entries.get(self.selected)
+    }
+
+    /// Moves the highlight by a number of rows, stopping at either end.
+    pub fn move_selection(&mut self, rows: isize) {
+        let last = self.entries.len().saturating_sub(1);
+        self.selected = self.selected.saturating_add_signed(rows).min(last);
+    }
+
+    /// Moves the highlight by a page of rows.
+    pub fn move_page(&mut self, pages: isize) {
+        self.move_selection(pages * VISIBLE_ROWS as isize);
+    }
+
+    /// Highlights the first ROM.
+    pub fn select_first(&mut self) {
+        self.selected = 0;
+    }
+
+    /// Highlights the last ROM.
+    pub fn select_last(&mut self) {
+        self.selected = self.entries.len().saturating_sub(1);
+    }
+
+    /// Draws the browser into an RGBA frame of `BROWSER_WIDTH` by `BROWSER_HEIGHT` pixels.
+    ///
+    /// # Arguments
+    /// * `frame` - RGBA pixel data to draw into
+    /// * `foreground` - Text color
+    /// * `background` - Screen color
+    pub fn render(&self, frame: &mut [u8], foreground: Color, background: Color) {
+        for pixel in frame.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&background.to_rgba());
+        }
+
+        let title = match self.pending() {
+            0 => format!("ROMS IN {}", self.directory.display()),
+            pending => format!("ROMS IN {} ({} LEFT)", self.directory.display(), pending),
+        };
+        draw_text(frame, BROWSER_WIDTH, 2, 2, &truncate(&title), foreground);
+
+        if self.entries.is_empty() {
+            draw_text(
+                frame,
+                BROWSER_WIDTH,
+                2,
+                LIST_TOP,
+                "NO .CH8 FILES FOUND",
+                foreground,
+            );
+        }
+
+        // Keep the highlighted row on screen
+        let first = (self.selected + 1).saturating_sub(VISIBLE_ROWS);
+        for (row, entry) in self
+            .entries
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(VISIBLE_ROWS)
+        {
+            let y = LIST_TOP + (row - first) * LINE_HEIGHT;
+            let color = if row == self.selected {
+                fill_rect(
+                    frame,
+                    BROWSER_WIDTH,
+                    0,
+                    y - 1,
+                    BROWSER_WIDTH,
+                    LINE_HEIGHT + 1,
+                    foreground,
+                );
+                background
+            } else {
+                foreground
+            };
+            draw_text(frame, BROWSER_WIDTH, 2, y, &truncate(&entry.name), color);
+        }
+
+        let footer = LIST_TOP + VISIBLE_ROWS * LINE_HEIGHT + 2;
+        if let Some(entry) = self.selected() {
+            let details = if !entry.analyzed {
+                format!("{} BYTES  ANALYZING...", entry.size)
+            } else {
+                format!(
+                    "{} BYTES  {}  SHA-1 {}",
+                    entry.size,
+                    entry.platform(),
+                    &entry.sha1[..12.min(entry.sha1.len())]
+                )
+            };
+            draw_text(
+                frame,
+                BROWSER_WIDTH,
+                2,
+                footer,
+                &truncate(&details),
+                foreground,
+            );
+        }
+        draw_text(
+            frame,
+            BROWSER_WIDTH,
+            2,
+            BROWSER_HEIGHT - LINE_HEIGHT - 1,
+            "UP/DOWN SELECT  ENTER PLAY  F8 RESET  ESC MENU",
+            foreground,
+        );
+    }
+}
+
+/// Lists the `.ch8` files in a directory with their stamps.
+fn list_roms(directory: &Path) -> std::io::Result<Vec<(PathBuf, FileStamp)>> {
+    let mut roms = Vec::new();
+    for item in std::fs::read_dir(directory)? {
+        let path = item?.path();
+        let is_rom = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("ch8"));
+        if !is_rom {
+            continue;
+        }
+        match std::fs::metadata(&path) {
+            Ok(metadata) if metadata.is_file() => {
+                roms.push((path, FileStamp::from_metadata(&metadata)))
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!(target: APP, "Skipping {}: {}", path.display(), e),
+        }
+    }
+    Ok(roms)
+}
+
+/// Starts a worker thread that analyzes ROMs, adds them to the cache and
+/// saves it once done.
+fn spawn_scan(
+    roms: Vec<(PathBuf, FileStamp)>,
+    mut cache: RomCache,
+    cache_path: PathBuf,
+) -> std::io::Result<Receiver<ScanResult>> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::Builder::new()
+        .name("rom-scan".to_string())
+        .spawn(move || {
+            for (path, stamp) in roms {
+                let entry = match RomEntry::load(&path) {
+                    Ok(entry) => {
+                        cache.insert(stamp, &entry);
+                        Some(entry)
+                    }
+                    Err(e) => {
+                        tracing::warn!(target: APP, "Skipping {}: {}", path.display(), e);
+                        None
+                    }
+                };
+                // Stop early if the browser is gone, keeping what was done
+                if sender.send((path, entry)).is_err() {
+                    break;
+                }
+            }
+            if let Err(e) = cache.save(&cache_path) {
+                tracing::warn!(target: APP, "Failed to save ROM cache: {}", e);
+            }
+        })?;
+    Ok(receiver)
+}
+
+/// Shortens text to fit on one line of the browser.
+fn truncate(text: &str) -> String {
+    if text.chars().count() <= LINE_CHARS {
+        return text.to_string();
+    }
+    let mut short: String = text.chars().take(LINE_CHARS - 3).collect();
+    short.push_str("...");
+    short
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_lists_roms() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("pong.ch8"), [0x00, 0xE0]).unwrap();
+        std::fs::write(dir.path().join("Blitz.CH8"), [0x00, 0xFF]).unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "not a rom").unwrap();
+        std::fs::create_dir(dir.path().join("more.ch8")).unwrap();
+
+        let browser = RomBrowser::open(dir.path()).unwrap();
+        let names: Vec<&str> = browser.entries().iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["Blitz", "pong"]);
+
+        let blitz = browser.selected().unwrap();
+        assert_eq!(blitz.size, 2);
+        assert_eq!(blitz.platform(), "SUPER-CHIP");
+        assert_eq!(browser.entries()[1].platform(), "CHIP-8");
+    }
+
+    #[test]
+    fn test_navigation() {
+        let entries = (0..20)
+            .map(|i| RomEntry {
+                path: PathBuf::from(format!("{}.ch8", i)),
+                name: i.to_string(),
+                size: 2,
+                sha1: String::new(),
+                variant_hints: Vec::new(),
+                analyzed: true,
+            })
+            .collect();
+        let mut browser = RomBrowser::with_entries(Path::new("roms"), entries);
+
+        browser.move_selection(-1);
+        assert_eq!(browser.selected_index(), 0);
+        browser.move_selection(3);
+        assert_eq!(browser.selected_index(), 3);
+        browser.move_page(1);
+        assert_eq!(browser.selected_index(), 18);
+        browser.move_selection(5);
+        assert_eq!(browser.selected_index(), 19);
+        browser.select_first();
+        assert_eq!(browser.selected().unwrap().name, "0");
+        browser.select_last();
+        assert_eq!(browser.selected().unwrap().name, "19");
+
+        // Rendering with the last entry highlighted scrolls it into view
+        let mut frame = vec![0; BROWSER_WIDTH * BROWSER_HEIGHT * 4];
+        browser.render(&mut frame, Color::WHITE, Color::BLACK);
+        let last_row = LIST_TOP + (VISIBLE_ROWS - 1) * LINE_HEIGHT;
+        assert_eq!(frame[last_row * BROWSER_WIDTH * 4], 255);
+    }
+
+    #[test]
+    fn test_scan_uses_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let roms = dir.path().join("roms");
+        std::fs::create_dir(&roms).unwrap();
+        std::fs::write(roms.join("pong.ch8"), [0x00, 0xE0]).unwrap();
+        std::fs::write(roms.join("blitz.ch8"), [0x00, 0xFF]).unwrap();
+        let cache_path = dir.path().join("cache.json");
+
+        // The first scan lists everything at once and analyzes it in the background
+        let mut browser = RomBrowser::scan(&roms, &cache_path).unwrap();
+        assert_eq!(browser.entries().len(), 2);
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        while browser.is_scanning() && std::time::Instant::now() < deadline {
+            browser.poll();
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert_eq!(browser.pending(), 0);
+        assert_eq!(browser.selected().unwrap().platform(), "SUPER-CHIP");
+
+        // The next scan is answered from the cache, except for changed files
+        std::fs::write(roms.join("pong.ch8"), [0x00, 0xE0, 0x00, 0xFD]).unwrap();
+        let browser = RomBrowser::scan(&roms, &cache_path).unwrap();
+        let blitz = &browser.entries()[0];
+        assert!(blitz.analyzed);
+        assert_eq!(blitz.platform(), "SUPER-CHIP");
+        assert_eq!(blitz.sha1.len(), 40);
+        let pong = &browser.entries()[1];
+        assert!(!pong.analyzed);
+        assert_eq!(pong.size, 4);
+    }
+
+    #[test]
+    fn test_empty_browser() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut browser = RomBrowser::open(dir.path()).unwrap();
+
+        browser.move_selection(1);
+        browser.select_last();
+        assert!(browser.selected().is_none());
+
+        let mut frame = vec![0; BROWSER_WIDTH * BROWSER_HEIGHT * 4];
+        browser.render(&mut frame, Color::WHITE, Color::BLACK);
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("short"), "short");
+        let long = "x".repeat(100);
+        assert_eq!(truncate(&long).chars().count(), LINE_CHARS);
+        assert!(truncate(&long).ends_with("..."));
+    }
+}
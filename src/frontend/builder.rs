@@ -0,0 +1,227 @@
+//! Fluent construction of emulator instances.
+//!
+//! Setting up a `SimpleEmulator` by hand means knowing the right order of
+//! `set_display`/`set_input`/`load_rom` calls. `EmulatorBuilder` collects
+//! the variant, quirks, hardware backends, RNG seed, configuration and ROM
+//! source, and wires them together in `build`:
+//!
+//! ```
+//! use chip8::analysis::InstructionSet;
+//! use chip8::frontend::EmulatorBuilder;
+//!
+//! let emulator = EmulatorBuilder::new()
+//!     .variant(InstructionSet::SuperChip)
+//!     .seed(42)
+//!     .rom_bytes(&[0x60, 0x05])
+//!     .build()
+//!     .unwrap();
+//! assert!(emulator.cpu().jump_with_vx());
+//! ```
+
+use std::path::PathBuf;
+
+use super::{
+    read_rom, EmulatorBehaviorConfig, EmulatorConfig, FrontendResult, SimpleEmulator, Variant,
+};
+use crate::emulator::WatchdogConfig;
+use crate::hardware::{Audio, Display, SharedInput};
+
+/// Where the builder gets the program from.
+#[derive(Debug, Clone)]
+enum RomSource {
+    /// ROM bytes held in memory.
+    Bytes(Vec<u8>),
+
+    /// A ROM file read at build time.
+    File(PathBuf),
+}
+
+/// Builds a ready-to-run `SimpleEmulator`.
+#[derive(Default)]
+pub struct EmulatorBuilder {
+    /// Configuration the emulator is created with.
+    config: EmulatorConfig,
+
+    /// Display backend, if not the default.
+    display: Option<Box<dyn Display>>,
+
+    /// Audio backend, if not the default.
+    audio: Option<Box<dyn Audio>>,
+
+    /// Input backend, if not the default.
+    input: Option<SharedInput>,
+
+    /// RNG seed for reproducible runs.
+    seed: Option<u64>,
+
+    /// Program to load.
+    rom: Option<RomSource>,
+
+    /// Spin loop detection thresholds.
+    watchdog: Option<WatchdogConfig>,
+}
+
+impl EmulatorBuilder {
+    /// Creates a builder with the default configuration and null hardware.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the whole configuration, replacing earlier variant and quirk settings.
+    pub fn config(mut self, config: EmulatorConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Uses the behavior settings of a Chip-8 variant.
+    ///
+    /// Takes a [`Variant`], or the
+    /// [`InstructionSet`](crate::analysis::InstructionSet) a ROM was detected to use.
+    pub fn variant(mut self, variant: impl Into<Variant>) -> Self {
+        self.config.behavior = EmulatorBehaviorConfig::for_variant(variant);
+        self
+    }
+
+    /// Adjusts individual quirks on top of the variant.
+    ///
+    /// # Arguments
+    /// * `adjust` - Called with the behavior settings to change
+    pub fn quirks(mut self, adjust: impl FnOnce(&mut EmulatorBehaviorConfig)) -> Self {
+        adjust(&mut self.config.behavior);
+        self
+    }
+
+    /// Sets the display backend.
+    pub fn display(mut self, display: impl Display + 'static) -> Self {
+        self.display = Some(Box::new(display));
+        self
+    }
+
+    /// Sets the audio backend.
+    pub fn audio(mut self, audio: impl Audio + 'static) -> Self {
+        self.audio = Some(Box::new(audio));
+        self
+    }
+
+    /// Sets the input backend.
+    pub fn input(mut self, input: SharedInput) -> Self {
+        self.input = Some(input);
+        self
+    }
+
+    /// Seeds the RNG so RND results are reproducible.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Loads a ROM from memory.
+    pub fn rom_bytes(mut self, rom_data: &[u8]) -> Self {
+        self.rom = Some(RomSource::Bytes(rom_data.to_vec()));
+        self
+    }
+
+    /// Loads a ROM file when the emulator is built.
+    pub fn rom_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.rom = Some(RomSource::File(path.into()));
+        self
+    }
+
+    /// Stops runs stuck in a spin loop (see `SimpleEmulator::set_watchdog`).
+    pub fn watchdog(mut self, config: WatchdogConfig) -> Self {
+        self.watchdog = Some(config);
+        self
+    }
+
+    /// Creates the emulator.
+    ///
+    /// # Returns
+    /// The emulator with its hardware attached and the ROM loaded, or an
+    /// error if the configuration is invalid or the ROM can't be loaded.
+    pub fn build(self) -> FrontendResult<SimpleEmulator> {
+        self.config.validate()?;
+
+        let mut emulator = SimpleEmulator::new_with_config(&self.config);
+        let cpu = emulator.cpu_mut();
+        if let Some(display) = self.display {
+            cpu.set_display(display);
+        }
+        if let Some(audio) = self.audio {
+            cpu.set_audio(audio);
+        }
+        if let Some(input) = self.input {
+            cpu.set_input(input);
+        }
+        if let Some(seed) = self.seed {
+            cpu.seed_rng(seed);
+        }
+
+        match self.rom {
+            Some(RomSource::Bytes(rom_data)) => emulator.load_rom_bytes(&rom_data)?,
+            Some(RomSource::File(path)) => emulator.load_rom_bytes(&read_rom(path)?)?,
+            None => {}
+        }
+        emulator.set_watchdog(self.watchdog);
+        Ok(emulator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::InstructionSet;
+    use crate::error::EmulatorError;
+    use crate::hardware::display::SoftwareDisplay;
+    use crate::hardware::input::{ChipKey, SoftwareInput};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_builder_wires_everything() {
+        // RND V0, 0xFF; LD F, V0; DRW V1, V1, 5; SKP V2
+        let rom = [0xC0, 0xFF, 0xF0, 0x29, 0xD1, 0x15, 0xE2, 0x9E];
+        let input = Arc::new(Mutex::new(SoftwareInput::new()));
+        input.lock().unwrap().press_key(ChipKey::Key0);
+
+        let build = || {
+            EmulatorBuilder::new()
+                .variant(InstructionSet::XoChip)
+                .quirks(|behavior| behavior.jump_with_vx = true)
+                .display(SoftwareDisplay::new())
+                .input(input.clone())
+                .seed(42)
+                .rom_bytes(&rom)
+                .build()
+                .unwrap()
+        };
+
+        let mut emulator = build();
+        emulator.run_cycles(4).unwrap();
+        assert_eq!(emulator.cpu().get_memory().size(), 0x10000);
+        assert!(emulator.cpu().jump_with_vx());
+        assert!(emulator.get_display_buffer().iter().any(|&pixel| pixel));
+        // The pressed key was seen, so SKP skipped
+        assert_eq!(emulator.cpu().get_registers().get_pc(), 0x20A);
+
+        // The same seed gives the same random numbers
+        let mut again = build();
+        again.run_cycles(1).unwrap();
+        assert_eq!(
+            again.cpu().get_registers().get_v(0).unwrap(),
+            emulator.cpu().get_registers().get_v(0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_builder_errors() {
+        let result = EmulatorBuilder::new().rom_file("missing.ch8").build();
+        assert!(result.is_err());
+
+        let mut config = EmulatorConfig::default();
+        config.graphics.scale_factor = 0;
+        let result = EmulatorBuilder::new().config(config).build();
+        assert!(matches!(result, Err(EmulatorError::ConfigError(_))));
+
+        let result = EmulatorBuilder::new().rom_bytes(&[]).build();
+        assert!(matches!(result, Err(EmulatorError::RomEmpty)));
+    }
+}
@@ -3,18 +3,45 @@
 //! This module provides a comprehensive CLI using clap for running
 //! and configuring the Chip-8 emulator.
 
-use clap::{Parser, Subcommand, builder::RangedU64ValueParser};
+use clap::{builder::RangedU64ValueParser, ArgGroup, Parser, Subcommand};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use super::{FrontendResult, SimpleEmulator};
-use crate::error::EmulatorError;
-use crate::graphics::{Color, GraphicsConfig, PixelRenderer};
+use super::browser::RomBrowser;
+use super::dump::{run_dump, DumpOptions};
+use super::gallery::{GalleryOptions, DEFAULT_GALLERY_SPEED, DEFAULT_IDLE_TIMEOUT};
+use super::gui::{run_av_sync, run_gallery, run_gui, run_kiosk, RunOptions, RunReports};
+use super::kiosk::PlaylistEntry;
+use super::quirks::{detect_quirks, DEFAULT_CONFIDENCE_THRESHOLD, DEFAULT_DETECT_CYCLES};
+use super::{
+    load_symbols, load_variables, read_rom, ConfigPatch, EmulatorConfig, FrontendResult,
+    RomOverrides, SimpleEmulator,
+};
+use crate::analysis::{analyze_rom, build_cfg, InstructionSet, RomLoader, Severity};
+use crate::avsync::{sync_rom, DEFAULT_BEEP_FRAMES, DEFAULT_SYNC_BEEPS, DEFAULT_SYNC_INTERVAL};
+use crate::bench::{run_benchmarks, BenchOptions, BenchReport, SYNTHETIC_NAME, SYNTHETIC_ROM};
+use crate::compat::{compare, CompatReport, ReferenceDump};
+#[cfg(feature = "line-editor")]
+use crate::debugger::monitor::HISTORY_FILE;
+use crate::debugger::{
+    backtrace_with_symbols, format_backtrace, report_fault, CallProfiler, Debugger, GdbServer,
+    InstructionProfiler, Monitor, RunDiff,
+};
+use crate::emulator::{Pcg32, WatchdogConfig, HEATMAP_WIDTH};
+use crate::error::{ConfigError, EmulatorError, ReferenceError};
+use crate::graphics::screenshot::save_rgba;
+use crate::graphics::{Color, GraphicsConfig, PixelRenderer, ScreenshotFormat};
 use crate::hardware::display::SoftwareDisplay;
+use crate::netplay::NetplayRole;
+#[cfg(feature = "line-editor")]
+use crate::paths::Paths;
+use crate::replay::ReplayMode;
+use crate::CpuEvent;
 
 /// Modern Chip-8 emulator written in Rust.
 ///
 /// By default, providing a ROM file launches the GUI.
-/// Use subcommands like 'run' for batch execution mode.
+/// Use subcommands for ROM tools, or 'run --watch' to reload a ROM as it is rebuilt.
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 #[command(propagate_version = true)]
@@ -31,17 +58,131 @@ pub struct CliApp {
     #[arg(long, value_name = "PATH")]
     pub config: Option<PathBuf>,
 
-    /// Configuration profile (classic, modern, gaming, development, retro)
+    /// Configuration profile (classic, modern, gaming, development, retro) or variant (chip-48, ...)
     #[arg(long, value_name = "PROFILE")]
     pub profile: Option<String>,
 
+    /// Override a config setting, e.g. graphics.scale_factor=14 (repeatable)
+    #[arg(long = "set", global = true, value_name = "KEY=VALUE")]
+    pub settings: Vec<String>,
+
+    /// Host a two-player netplay session on this TCP port
+    #[arg(long, value_name = "PORT", conflicts_with = "join")]
+    pub host: Option<u16>,
+
+    /// Join a netplay session at HOST:PORT
+    #[arg(long, value_name = "ADDRESS")]
+    pub join: Option<String>,
+
+    /// Print performance counters when the emulator exits
+    #[arg(long, global = true)]
+    pub stats: bool,
+
+    /// Write a Chrome trace (chrome://tracing) of the run to FILE on exit
+    #[arg(long, global = true, value_name = "FILE")]
+    pub timeline: Option<PathBuf>,
+
+    /// Turn on a cheat from the ROM's .cheats.toml file (repeatable)
+    #[arg(long, global = true, value_name = "NAME")]
+    pub cheat: Vec<String>,
+
+    /// Turn off the cheats the ROM's .cheats.toml file turns on
+    #[arg(long, global = true)]
+    pub no_cheats: bool,
+
+    /// Apply an IPS or BPS patch to the ROM as it loads
+    #[arg(long, global = true, value_name = "PATH")]
+    pub patch: Option<PathBuf>,
+
     #[command(subcommand)]
     pub command: Option<Commands>,
 }
 
+impl CliApp {
+    /// Gets the netplay role requested with --host or --join.
+    pub fn netplay_role(&self) -> Option<NetplayRole> {
+        match (&self.host, &self.join) {
+            (Some(port), _) => Some(NetplayRole::Host { port: *port }),
+            (None, Some(address)) => Some(NetplayRole::Join {
+                address: address.clone(),
+            }),
+            (None, None) => None,
+        }
+    }
+}
+
 /// Available CLI commands.
 #[derive(Subcommand)]
 pub enum Commands {
+    /// Run a ROM in the GUI
+    #[command(group(ArgGroup::new("dump").multiple(true)))]
+    Run {
+        /// ROM file (or directory of ROMs) to run
+        #[arg(required_unless_present = "demo")]
+        rom_file: Option<PathBuf>,
+
+        /// Run a built-in demo ROM instead (see 'chip8 demos')
+        #[arg(long, value_name = "NAME", conflicts_with_all = ["rom_file", "watch"])]
+        demo: Option<String>,
+
+        /// Reload and restart the ROM whenever the file changes
+        #[arg(long)]
+        watch: bool,
+
+        /// Record a deterministic replay of the run to this file
+        #[arg(long, value_name = "PATH", conflicts_with_all = ["replay", "watch"])]
+        record: Option<PathBuf>,
+
+        /// Play back a replay recorded with --record
+        #[arg(long, value_name = "PATH", conflicts_with = "watch")]
+        replay: Option<PathBuf>,
+
+        /// Run without a window and write every frame to PATH as Y4M video ('-' for stdout)
+        #[arg(long, value_name = "PATH", group = "dump", conflicts_with_all = ["record", "watch"])]
+        dump_video: Option<PathBuf>,
+
+        /// Run without a window and write the buzzer to PATH as raw PCM, with a
+        /// JSON manifest beside it
+        #[arg(long, value_name = "PATH", group = "dump", conflicts_with_all = ["record", "watch"])]
+        dump_audio: Option<PathBuf>,
+
+        /// Scale dumped frames up by this factor (1-20)
+        #[arg(long, default_value_t = 1, value_parser = RangedU64ValueParser::<u32>::new().range(1..=20))]
+        dump_scale: u32,
+
+        /// Frames to dump (default: the whole replay, or 600)
+        #[arg(long, value_name = "N", requires = "dump")]
+        frames: Option<u64>,
+    },
+
+    /// Tile several ROMs in one window; click one to play it
+    Gallery {
+        /// ROM files or directories of ROMs (default: the built-in demos)
+        roms: Vec<PathBuf>,
+
+        /// Speed of the tiles relative to normal while none is being played
+        #[arg(long, default_value_t = DEFAULT_GALLERY_SPEED)]
+        speed: f32,
+
+        /// Seconds without input before a played ROM goes back to the grid (0 never)
+        #[arg(long, value_name = "SECONDS", default_value_t = DEFAULT_IDLE_TIMEOUT.as_secs())]
+        idle_timeout: u64,
+    },
+
+    /// Show demos of ROMs while no one is playing; any key starts the one on screen
+    Kiosk {
+        /// ROM files or directories of ROMs (default: the playlist in the config)
+        roms: Vec<PathBuf>,
+
+        /// Seconds without input before the demos start again
+        #[arg(long, value_name = "SECONDS")]
+        idle_secs: Option<u64>,
+
+        /// Seconds each ROM is shown before moving on
+        #[arg(long, value_name = "SECONDS")]
+        rotate_secs: Option<u64>,
+    },
+
     /// Display information about a ROM file
     Info {
         /// ROM file to analyze
@@ -59,10 +200,14 @@ pub enum Commands {
         /// ROM file to run
         rom_file: PathBuf,
 
-        /// Output PNG file path
+        /// Output image file path
         #[arg(short, long, default_value = "screenshot.png")]
         output: PathBuf,
 
+        /// Image format: png or ppm (default: from the output extension)
+        #[arg(long)]
+        format: Option<ScreenshotFormat>,
+
         /// Number of CPU cycles to execute before capture
         #[arg(short, long, default_value_t = 1000)]
         cycles: u32,
@@ -79,23 +224,427 @@ pub enum Commands {
         #[arg(long, default_value = "000000")]
         background: String,
     },
+
+    /// Statically analyze a ROM's control flow
+    Analyze {
+        /// ROM file to analyze
+        rom_file: PathBuf,
+
+        /// Print the control-flow graph in Graphviz DOT format
+        #[arg(long)]
+        cfg: bool,
+
+        /// Write the DOT graph to a file instead of stdout (implies --cfg)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Run a ROM under a GDB remote protocol server
+    Gdb {
+        /// ROM file to debug
+        rom_file: PathBuf,
+
+        /// TCP port to listen on
+        #[arg(short, long, default_value_t = 1234)]
+        port: u16,
+
+        /// Symbol map naming addresses (Octo source provides its own labels)
+        #[arg(long, value_name = "PATH")]
+        symbols: Option<PathBuf>,
+    },
+
+    /// Debug a ROM from an interactive command prompt
+    Monitor {
+        /// ROM file to debug
+        rom_file: PathBuf,
+
+        /// Symbol map naming addresses (Octo source provides its own labels)
+        #[arg(long, value_name = "PATH")]
+        symbols: Option<PathBuf>,
+
+        /// Variable file naming memory locations (default: the ROM's .vars.toml)
+        #[arg(long, value_name = "PATH")]
+        vars: Option<PathBuf>,
+    },
+
+    /// Run a ROM headlessly and report hot subroutines and instructions
+    Profile {
+        /// ROM file to profile
+        rom_file: PathBuf,
+
+        /// Maximum number of CPU cycles to run
+        #[arg(short, long, default_value_t = 100_000)]
+        cycles: u64,
+
+        /// Number of subroutines and opcode classes to list
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+
+        /// Print a heat map of executed instructions over the disassembly
+        #[arg(long)]
+        heatmap: bool,
+
+        /// Write per-instruction counts as JSON to this file
+        #[arg(long, value_name = "PATH")]
+        json: Option<PathBuf>,
+
+        /// Write per-address counts as CSV to this file
+        #[arg(long, value_name = "PATH")]
+        csv: Option<PathBuf>,
+
+        /// Write a memory access heat map image (PNG or PPM, by extension)
+        #[arg(long, value_name = "PATH")]
+        heatmap_image: Option<PathBuf>,
+
+        /// Symbol map naming addresses (Octo source provides its own labels)
+        #[arg(long, value_name = "PATH")]
+        symbols: Option<PathBuf>,
+    },
+
+    /// Run a ROM twice under different settings and show where the runs differ
+    DiffRuns {
+        /// ROM file to run
+        rom_file: PathBuf,
+
+        /// Maximum number of instructions to compare
+        #[arg(short, long, default_value_t = 100_000)]
+        cycles: u64,
+
+        /// Configuration file for run A (default: --config or --profile)
+        #[arg(long, value_name = "PATH", conflicts_with = "profile_a")]
+        config_a: Option<PathBuf>,
+
+        /// Configuration file for run B (default: --config or --profile)
+        #[arg(long, value_name = "PATH", conflicts_with = "profile_b")]
+        config_b: Option<PathBuf>,
+
+        /// Configuration profile or variant for run A
+        #[arg(long, value_name = "PROFILE")]
+        profile_a: Option<String>,
+
+        /// Configuration profile or variant for run B
+        #[arg(long, value_name = "PROFILE")]
+        profile_b: Option<String>,
+
+        /// Override a behavior setting for run A, e.g. shift_uses_vy=true (repeatable)
+        #[arg(long, value_name = "KEY=VALUE")]
+        set_a: Vec<String>,
+
+        /// Override a behavior setting for run B, e.g. shift_uses_vy=true (repeatable)
+        #[arg(long, value_name = "KEY=VALUE")]
+        set_b: Vec<String>,
+
+        /// Random number seed for run A
+        #[arg(long, default_value_t = 0)]
+        seed_a: u64,
+
+        /// Random number seed for run B
+        #[arg(long, default_value_t = 0)]
+        seed_b: u64,
+    },
+
+    /// Guess which interpreter's quirks a ROM expects
+    DetectQuirks {
+        /// ROM file to test
+        rom_file: PathBuf,
+
+        /// Number of instructions to run under each variant
+        #[arg(short, long, default_value_t = DEFAULT_DETECT_CYCLES)]
+        cycles: u64,
+
+        /// Write the suggested variant to the ROM's override file
+        #[arg(long)]
+        apply: bool,
+
+        /// Confidence (0 to 1) needed before --apply writes anything
+        #[arg(long, default_value_t = DEFAULT_CONFIDENCE_THRESHOLD)]
+        threshold: f32,
+    },
+
+    /// Diagnose the audio, graphics, config and input environment
+    Doctor,
+
+    /// List the built-in demo ROMs
+    Demos,
+
+    /// Benchmark the CPU, renderer and buzzer headlessly
+    Bench {
+        /// ROM file to execute (default: a built-in synthetic workload)
+        rom_file: Option<PathBuf>,
+
+        /// Number of instructions to execute
+        #[arg(short, long, default_value_t = BenchOptions::default().instructions)]
+        instructions: u64,
+
+        /// Number of frames to render
+        #[arg(long, default_value_t = BenchOptions::default().frames)]
+        frames: u32,
+
+        /// Number of audio samples to generate
+        #[arg(long, default_value_t = BenchOptions::default().samples)]
+        samples: usize,
+
+        /// Print the results as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Measure how far the sound lands from the picture
+    AvSync {
+        /// Number of beeps to measure
+        #[arg(long, default_value_t = DEFAULT_SYNC_BEEPS, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+        beeps: usize,
+
+        /// Write the flash-and-beep ROM to this file instead of measuring
+        #[arg(long, value_name = "PATH")]
+        save_rom: Option<PathBuf>,
+    },
+
+    /// Score ROMs' screens against frame dumps from a reference interpreter
+    Compare {
+        /// Reference dump files (JSON), each naming its ROM
+        #[arg(required = true)]
+        references: Vec<PathBuf>,
+
+        /// ROM to run instead of the one each dump names
+        #[arg(long, value_name = "PATH")]
+        rom: Option<PathBuf>,
+
+        /// Fail unless every ROM scores at least this percentage
+        #[arg(long, value_name = "PERCENT")]
+        min_score: Option<f64>,
+    },
 }
 
 /// Runs the CLI application.
 pub fn run_cli() -> FrontendResult<()> {
     let args = CliApp::parse();
+    let patch = ConfigPatch::parse(&args.settings)?;
 
     match &args.command {
+        Some(Commands::Run {
+            rom_file,
+            demo,
+            replay,
+            dump_video,
+            dump_audio,
+            dump_scale,
+            frames,
+            ..
+        }) if dump_video.is_some() || dump_audio.is_some() => run_dump(
+            match demo {
+                Some(name) => demo_rom_path(name)?,
+                None => rom_file.clone().unwrap_or_default(),
+            },
+            args.config.as_ref(),
+            args.profile.as_ref(),
+            &patch,
+            RunOptions {
+                replay_mode: replay_mode(None, replay.as_ref()),
+                cheats: args.cheat.clone(),
+                no_cheats: args.no_cheats,
+                patch: args.patch.clone(),
+                ..RunOptions::default()
+            },
+            DumpOptions {
+                video: dump_video.clone(),
+                scale: *dump_scale,
+                audio: dump_audio.clone(),
+                frames: *frames,
+            },
+        ),
+        Some(Commands::Run {
+            rom_file,
+            demo,
+            watch,
+            record,
+            replay,
+            ..
+        }) => run_gui(
+            match demo {
+                Some(name) => demo_rom_path(name)?,
+                None => rom_file.clone().unwrap_or_default(),
+            },
+            args.config.as_ref(),
+            args.profile.as_ref(),
+            &patch,
+            args.netplay_role(),
+            RunOptions {
+                watch: *watch,
+                replay_mode: replay_mode(record.as_ref(), replay.as_ref()),
+                cheats: args.cheat.clone(),
+                no_cheats: args.no_cheats,
+                patch: args.patch.clone(),
+            },
+            RunReports {
+                stats: args.stats,
+                timeline: args.timeline.clone(),
+            },
+        ),
+        Some(Commands::Gallery {
+            roms,
+            speed,
+            idle_timeout,
+        }) => run_gallery(
+            gallery_roms(roms)?,
+            args.config.as_ref(),
+            args.profile.as_ref(),
+            &patch,
+            GalleryOptions {
+                speed: *speed,
+                idle_timeout: (*idle_timeout > 0).then(|| Duration::from_secs(*idle_timeout)),
+            },
+        ),
+        Some(Commands::Kiosk {
+            roms,
+            idle_secs,
+            rotate_secs,
+        }) => run_kiosk(
+            kiosk_playlist(roms)?,
+            args.config.as_ref(),
+            args.profile.as_ref(),
+            &patch,
+            *idle_secs,
+            *rotate_secs,
+        ),
         Some(Commands::Info { rom_file }) => show_rom_info(rom_file),
         Some(Commands::Validate { rom_file }) => validate_rom(rom_file),
         Some(Commands::Screenshot {
             rom_file,
             output,
+            format,
             cycles,
             scale,
             foreground,
             background,
-        }) => capture_screenshot(rom_file, output, *cycles, *scale, foreground, background),
+        }) => capture_screenshot(
+            rom_file, output, *format, *cycles, *scale, foreground, background,
+        ),
+        Some(Commands::Analyze {
+            rom_file,
+            cfg,
+            output,
+        }) => analyze_control_flow(rom_file, *cfg, output.as_deref()),
+        Some(Commands::Gdb {
+            rom_file,
+            port,
+            symbols,
+        }) => run_gdb_server(
+            rom_file,
+            *port,
+            symbols.as_deref(),
+            command_config(args.config.as_ref(), &patch)?,
+        ),
+        Some(Commands::Monitor {
+            rom_file,
+            symbols,
+            vars,
+        }) => run_monitor(
+            rom_file,
+            symbols.as_deref(),
+            vars.as_deref(),
+            command_config(args.config.as_ref(), &patch)?,
+        ),
+        Some(Commands::Profile {
+            rom_file,
+            cycles,
+            top,
+            heatmap,
+            json,
+            csv,
+            heatmap_image,
+            symbols,
+        }) => profile_rom(
+            rom_file,
+            &ProfileOptions {
+                cycles: *cycles,
+                top: *top,
+                heatmap: *heatmap,
+                json_path: json.as_deref(),
+                csv_path: csv.as_deref(),
+                heatmap_image_path: heatmap_image.as_deref(),
+                symbols_path: symbols.as_deref(),
+            },
+            command_config(args.config.as_ref(), &patch)?.unwrap_or_default(),
+        ),
+        Some(Commands::DiffRuns {
+            rom_file,
+            cycles,
+            config_a,
+            config_b,
+            profile_a,
+            profile_b,
+            set_a,
+            set_b,
+            seed_a,
+            seed_b,
+        }) => {
+            let base = DiffSide {
+                config_path: args.config.as_deref(),
+                profile: args.profile.as_deref(),
+                patch: &patch,
+                overrides: &[],
+                seed: 0,
+            };
+            diff_runs(
+                rom_file,
+                *cycles,
+                [
+                    DiffSide {
+                        config_path: config_a.as_deref(),
+                        profile: profile_a.as_deref(),
+                        patch: &ConfigPatch::new(),
+                        overrides: set_a,
+                        seed: *seed_a,
+                    },
+                    DiffSide {
+                        config_path: config_b.as_deref(),
+                        profile: profile_b.as_deref(),
+                        patch: &ConfigPatch::new(),
+                        overrides: set_b,
+                        seed: *seed_b,
+                    },
+                ],
+                &base,
+            )
+        }
+        Some(Commands::DetectQuirks {
+            rom_file,
+            cycles,
+            apply,
+            threshold,
+        }) => detect_rom_quirks(rom_file, *cycles, apply.then_some(*threshold)),
+        Some(Commands::Doctor) => run_doctor(args.config.as_ref()),
+        Some(Commands::Demos) => list_demos(),
+        Some(Commands::Bench {
+            rom_file,
+            instructions,
+            frames,
+            samples,
+            json,
+        }) => run_bench(
+            rom_file.as_deref(),
+            &BenchOptions {
+                instructions: *instructions,
+                frames: *frames,
+                samples: *samples,
+            },
+            *json,
+        ),
+        Some(Commands::AvSync { beeps, save_rom }) => match save_rom {
+            Some(path) => save_sync_rom(path),
+            None => run_av_sync(args.config.as_ref(), args.profile.as_ref(), &patch, *beeps),
+        },
+        Some(Commands::Compare {
+            references,
+            rom,
+            min_score,
+        }) => run_compare(
+            references,
+            rom.as_deref(),
+            *min_score,
+            command_config(args.config.as_ref(), &patch)?,
+        ),
         None => {
             // Show help or usage
             println!("Chip-8 Emulator");
@@ -103,9 +652,26 @@ pub fn run_cli() -> FrontendResult<()> {
             println!("       chip8 <COMMAND>     - Run subcommand");
             println!();
             println!("Commands:");
+            println!("  run        Run a ROM in the GUI (--watch reloads it on change,");
+            println!("             --record/--replay save and play back a deterministic run,");
+            println!("             --dump-video/--dump-audio write every frame as Y4M video");
+            println!("             and PCM audio without a window)");
+            println!("  gallery    Tile several ROMs in one window, e.g. for a kiosk");
+            println!("  kiosk      Show demos of ROMs until someone presses a key");
             println!("  info       Show ROM information");
             println!("  validate   Validate a ROM file");
             println!("  screenshot Capture a screenshot after running ROM");
+            println!("  analyze    Analyze ROM control flow");
+            println!("  gdb        Debug a ROM with a GDB remote protocol client");
+            println!("  monitor    Debug a ROM from an interactive prompt");
+            println!("  profile    Profile subroutines and instructions in a headless run");
+            println!("  diff-runs  Find where two runs of a ROM with different settings diverge");
+            println!("  detect-quirks  Guess which interpreter's quirks a ROM expects");
+            println!("  doctor     Diagnose environment problems");
+            println!("  demos      List the built-in demo ROMs");
+            println!("  bench      Benchmark the CPU, renderer and audio");
+            println!("  av-sync    Measure the offset between the picture and the sound");
+            println!("  compare    Score screens against a reference interpreter's frame dumps");
             println!();
             println!("Run 'chip8 --help' for more information.");
             Ok(())
@@ -113,9 +679,225 @@ pub fn run_cli() -> FrontendResult<()> {
     }
 }
 
+/// Scores ROMs against reference frame dumps and prints a line per ROM.
+///
+/// # Arguments
+/// * `references` - Reference dump files
+/// * `rom` - ROM to run instead of the one each dump names
+/// * `min_score` - Lowest acceptable score, as a percentage
+/// * `config` - Configuration with the quirks to run under, or the defaults
+fn run_compare(
+    references: &[PathBuf],
+    rom: Option<&Path>,
+    min_score: Option<f64>,
+    config: Option<EmulatorConfig>,
+) -> FrontendResult<()> {
+    let behavior = config.unwrap_or_default().behavior;
+    let mut lowest: Option<CompatReport> = None;
+    for path in references {
+        let dump = ReferenceDump::load(path)?;
+        let rom_path = rom
+            .map(Path::to_path_buf)
+            .or_else(|| dump.rom_path())
+            .ok_or_else(|| {
+                ReferenceError::InvalidDump(format!(
+                    "{} doesn't name its ROM; pass --rom",
+                    path.display()
+                ))
+            })?;
+        let name = rom_path.file_name().map_or_else(
+            || rom_path.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+        let report = compare(&read_rom(&rom_path)?, &name, &dump, &behavior)?;
+
+        println!("{}", report);
+        for frame in report.frames.iter().filter(|frame| !frame.is_exact()) {
+            println!(
+                "  frame {}: {} of {} pixels differ",
+                frame.frame,
+                frame.total - frame.matching,
+                frame.total
+            );
+        }
+        if lowest
+            .as_ref()
+            .is_none_or(|lowest| report.score() < lowest.score())
+        {
+            lowest = Some(report);
+        }
+    }
+
+    match (lowest, min_score) {
+        (Some(lowest), Some(minimum)) if lowest.score() < minimum => {
+            Err(ReferenceError::BelowMinimum {
+                score: lowest.score(),
+                rom: lowest.rom,
+                minimum,
+            }
+            .into())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Writes the A/V sync ROM, for measuring other emulators or hardware.
+fn save_sync_rom(path: &Path) -> FrontendResult<()> {
+    std::fs::write(path, sync_rom(DEFAULT_SYNC_INTERVAL, DEFAULT_BEEP_FRAMES))?;
+    println!(
+        "✅ Saved {}: a flash and a beep together every {} frames",
+        path.display(),
+        DEFAULT_SYNC_INTERVAL
+    );
+    Ok(())
+}
+
+/// Prints the built-in demo ROMs.
+#[cfg(feature = "demo-roms")]
+fn list_demos() -> FrontendResult<()> {
+    println!("Built-in demo ROMs:");
+    for demo in crate::demos::DEMO_ROMS {
+        println!("  {:<10} {}", demo.name, demo.description);
+    }
+    println!();
+    println!("Run one with 'chip8 run --demo NAME'.");
+    Ok(())
+}
+
+#[cfg(not(feature = "demo-roms"))]
+fn list_demos() -> FrontendResult<()> {
+    println!("This build has no demo ROMs (enable the 'demo-roms' feature).");
+    Ok(())
+}
+
+/// Reads the ROMs for the gallery, named after their files.
+///
+/// Directories contribute their `.ch8` files in name order. With no paths,
+/// the built-in demos are used.
+fn gallery_roms(paths: &[PathBuf]) -> FrontendResult<Vec<(String, Vec<u8>)>> {
+    if paths.is_empty() {
+        return demo_roms();
+    }
+    let mut roms = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            for entry in RomBrowser::open(path)?.entries() {
+                roms.push((entry.name.clone(), read_rom(&entry.path)?));
+            }
+        } else {
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            roms.push((name.into_owned(), read_rom(path)?));
+        }
+    }
+    Ok(roms)
+}
+
+/// Makes a kiosk playlist of ROM files and the ROMs in directories.
+fn kiosk_playlist(paths: &[PathBuf]) -> FrontendResult<Vec<PlaylistEntry>> {
+    let mut playlist = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            for entry in RomBrowser::open(path)?.entries() {
+                playlist.push(PlaylistEntry::new(&entry.path));
+            }
+        } else {
+            playlist.push(PlaylistEntry::new(path));
+        }
+    }
+    Ok(playlist)
+}
+
+/// Gets the built-in demo ROMs by name.
+#[cfg(feature = "demo-roms")]
+fn demo_roms() -> FrontendResult<Vec<(String, Vec<u8>)>> {
+    Ok(crate::demos::DEMO_ROMS
+        .iter()
+        .map(|demo| (demo.name.to_string(), demo.data.to_vec()))
+        .collect())
+}
+
+#[cfg(not(feature = "demo-roms"))]
+fn demo_roms() -> FrontendResult<Vec<(String, Vec<u8>)>> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        "this build has no demo ROMs; give the gallery ROM files or directories",
+    )
+    .into())
+}
+
+/// Writes a built-in demo ROM to the data directory so it runs like a file.
+///
+/// # Returns
+/// The path of the written ROM.
+#[cfg(feature = "demo-roms")]
+fn demo_rom_path(name: &str) -> FrontendResult<PathBuf> {
+    let demo = crate::demos::find_demo(name).ok_or_else(|| unknown_demo(name))?;
+    Ok(demo.extract(&crate::paths::Paths::new().demos_dir())?)
+}
+
+#[cfg(not(feature = "demo-roms"))]
+fn demo_rom_path(name: &str) -> FrontendResult<PathBuf> {
+    Err(unknown_demo(name))
+}
+
+/// Makes the error for a demo name that isn't built in.
+fn unknown_demo(name: &str) -> EmulatorError {
+    EmulatorError::ConfigError(ConfigError::InvalidValue {
+        key: "demo".to_string(),
+        value: format!("{} (see 'chip8 demos')", name),
+    })
+}
+
+/// Runs the benchmarks and prints the results as text or JSON.
+fn run_bench(rom_file: Option<&Path>, options: &BenchOptions, json: bool) -> FrontendResult<()> {
+    let report = match rom_file {
+        Some(path) => {
+            let name = path.file_name().map_or_else(
+                || path.display().to_string(),
+                |name| name.to_string_lossy().into_owned(),
+            );
+            run_benchmarks(&read_rom(path)?, &name, options)?
+        }
+        None => run_benchmarks(&SYNTHETIC_ROM, SYNTHETIC_NAME, options)?,
+    };
+
+    if json {
+        println!("{}", bench_json(&report));
+    } else {
+        println!("Benchmark: {}", report.rom);
+        println!(
+            "  CPU:    {:>14.0} instructions/s ({} instructions)",
+            report.instructions_per_second, report.instructions
+        );
+        println!(
+            "  Render: {:>14.0} frames/s ({} frames)",
+            report.frames_per_second, report.frames
+        );
+        println!(
+            "  Audio:  {:>14.0} samples/s ({} samples)",
+            report.samples_per_second, report.samples
+        );
+    }
+    Ok(())
+}
+
+/// Formats a benchmark report as JSON.
+fn bench_json(report: &BenchReport) -> String {
+    serde_json::to_string_pretty(report).unwrap_or_default()
+}
+
+/// Gets the replay mode requested with --record or --replay.
+fn replay_mode(record: Option<&PathBuf>, replay: Option<&PathBuf>) -> Option<ReplayMode> {
+    match (record, replay) {
+        (Some(path), _) => Some(ReplayMode::Record(path.clone())),
+        (None, Some(path)) => Some(ReplayMode::Play(path.clone())),
+        (None, None) => None,
+    }
+}
+
 /// Shows information about a ROM file.
 fn show_rom_info(rom_file: &Path) -> FrontendResult<()> {
-    let rom_data = std::fs::read(rom_file)?;
+    let rom_data = read_rom(rom_file)?;
 
     println!("ROM Information:");
     println!("File: {}", rom_file.display());
@@ -144,12 +926,40 @@ fn show_rom_info(rom_file: &Path) -> FrontendResult<()> {
         println!();
     }
 
+    let analysis = analyze_rom(&rom_data);
+    println!("\nSHA-1: {}", analysis.sha1);
+
+    println!("\nVariant hints:");
+    if analysis.variant_hints.is_empty() {
+        println!("✅ Standard Chip-8 (no extended opcodes found)");
+    }
+    for set in &analysis.variant_hints {
+        match set {
+            InstructionSet::SuperChip => println!("⚠️  Uses SUPER-CHIP opcodes"),
+            InstructionSet::XoChip => println!("⚠️  Uses XO-CHIP opcodes or address space"),
+            InstructionSet::Chip8 => {}
+        }
+    }
+
+    println!("\nOpcode histogram (top 10):");
+    for (pattern, count) in analysis.top_opcodes(10) {
+        println!("  {}: {}", pattern, count);
+    }
+    if analysis.unknown_words > 0 {
+        println!("  data/unknown: {}", analysis.unknown_words);
+    }
+
+    println!("\nEntry point disassembly:");
+    for line in &analysis.entry_preview {
+        println!("  {}", line);
+    }
+
     Ok(())
 }
 
 /// Validates a ROM file.
 fn validate_rom(rom_file: &Path) -> FrontendResult<()> {
-    let rom_data = std::fs::read(rom_file)?;
+    let rom_data = read_rom(rom_file)?;
 
     println!("Validating ROM: {}", rom_file.display());
 
@@ -165,22 +975,17 @@ fn validate_rom(rom_file: &Path) -> FrontendResult<()> {
         valid = false;
     }
 
-    // Check for common patterns
-    let mut has_instructions = false;
-    for chunk in rom_data.chunks(2) {
-        if chunk.len() == 2 {
-            let instruction = (chunk[0] as u16) << 8 | chunk[1] as u16;
-            if instruction != 0x0000 {
-                has_instructions = true;
-                break;
+    let report = RomLoader::new().check(&rom_data)?;
+    for issue in &report.issues {
+        match issue.severity() {
+            Severity::Error => {
+                println!("❌ Error: {}", issue);
+                valid = false;
             }
+            Severity::Warning => println!("⚠️  Warning: {}", issue),
         }
     }
 
-    if !has_instructions {
-        println!("⚠️  Warning: ROM appears to contain only zeros");
-    }
-
     if valid {
         println!("✅ ROM validation passed");
     } else {
@@ -190,10 +995,452 @@ fn validate_rom(rom_file: &Path) -> FrontendResult<()> {
     Ok(())
 }
 
+/// Builds a ROM's control-flow graph and prints a summary or DOT export.
+fn analyze_control_flow(rom_file: &Path, dot: bool, output: Option<&Path>) -> FrontendResult<()> {
+    let rom_data = read_rom(rom_file)?;
+    let cfg = build_cfg(&rom_data);
+
+    if let Some(output) = output {
+        std::fs::write(output, cfg.to_dot())?;
+        println!("✅ Control-flow graph saved to: {}", output.display());
+        return Ok(());
+    }
+    if dot {
+        print!("{}", cfg.to_dot());
+        return Ok(());
+    }
+
+    println!("Control Flow Analysis: {}", rom_file.display());
+    println!("Basic blocks: {}", cfg.blocks.len());
+    println!("Edges: {}", cfg.edges.len());
+
+    println!("\nSubroutines: {}", cfg.subroutines.len());
+    for (entry, callers) in &cfg.subroutines {
+        let callers: Vec<String> = callers.iter().map(|c| format!("0x{:04X}", c)).collect();
+        println!("  0x{:04X} (called from {})", entry, callers.join(", "));
+    }
+
+    println!("\nUnreachable regions: {}", cfg.unreachable.len());
+    for (start, end) in &cfg.unreachable {
+        println!(
+            "  0x{:04X}-0x{:04X} ({} bytes)",
+            start,
+            end - 1,
+            end - start
+        );
+    }
+
+    for pc in &cfg.indirect_jumps {
+        println!("⚠️  Indirect jump at 0x{:04X} depends on V0", pc);
+    }
+    for write in &cfg.self_modifying {
+        println!(
+            "⚠️  Self-modifying write at 0x{:04X} overwrites code at 0x{:04X}",
+            write.pc, write.target
+        );
+    }
+    for pc in &cfg.invalid {
+        println!("❌ Unknown opcode reached at 0x{:04X}", pc);
+    }
+
+    Ok(())
+}
+
+/// Loads a ROM and serves it to a GDB remote protocol client.
+fn run_gdb_server(
+    rom_file: &Path,
+    port: u16,
+    symbols_path: Option<&Path>,
+    config: Option<EmulatorConfig>,
+) -> FrontendResult<()> {
+    let rom_data = read_rom(rom_file)?;
+    let symbols = load_symbols(rom_file, symbols_path)?;
+    let mut cpu = match config {
+        Some(config) => crate::Cpu::new_with_config(&config.behavior),
+        None => crate::Cpu::new(),
+    };
+    cpu.load_rom(&rom_data)?;
+
+    let server = GdbServer::bind(("127.0.0.1", port))?.with_symbols(symbols.clone());
+    println!("Debugging {}", rom_file.display());
+    if !symbols.is_empty() {
+        println!(
+            "Loaded {} symbols (monitor break NAME sets a breakpoint)",
+            symbols.len()
+        );
+    }
+    println!("Waiting for a GDB client on 127.0.0.1:{}...", port);
+    println!("  e.g. gdb -ex 'target remote :{}'", port);
+
+    server.serve(&mut cpu)?;
+    println!("✅ Debugging session ended");
+    Ok(())
+}
+
+/// Runs a ROM under the interactive monitor on stdin and stdout.
+fn run_monitor(
+    rom_file: &Path,
+    symbols_path: Option<&Path>,
+    variables_path: Option<&Path>,
+    config: Option<EmulatorConfig>,
+) -> FrontendResult<()> {
+    let rom_data = read_rom(rom_file)?;
+    let symbols = load_symbols(rom_file, symbols_path)?;
+    let variables = load_variables(rom_file, variables_path)?;
+    #[cfg(feature = "line-editor")]
+    let paths = config
+        .as_ref()
+        .map_or_else(Paths::new, |config| Paths::for_config(&config.paths));
+    let mut cpu = match config {
+        Some(config) => crate::Cpu::new_with_config(&config.behavior),
+        None => crate::Cpu::new(),
+    };
+    cpu.load_rom(&rom_data)?;
+
+    println!("Debugging {}", rom_file.display());
+    if !symbols.is_empty() {
+        println!("Loaded {} symbols", symbols.len());
+    }
+    if !variables.is_empty() {
+        println!("Loaded {} variables", variables.len());
+    }
+    println!("Type 'help' for commands, 'quit' to leave.");
+
+    let mut debugger = Debugger::with_symbols(symbols);
+    debugger.set_variables(variables);
+    let mut monitor = Monitor::new(debugger);
+    #[cfg(feature = "line-editor")]
+    if std::io::IsTerminal::is_terminal(&std::io::stdin()) {
+        let history = paths.data_dir().join(HISTORY_FILE);
+        monitor.run_interactive(&mut cpu, Some(&history))?;
+        return Ok(());
+    }
+    monitor.run(&mut cpu, std::io::stdin().lock(), std::io::stdout())?;
+    Ok(())
+}
+
+/// Runs a ROM under each variant and prints which quirks it seems to expect.
+///
+/// With an `apply` threshold, a suggestion at least that confident is saved
+/// as the variant in the ROM's override file.
+fn detect_rom_quirks(rom_file: &Path, cycles: u64, apply: Option<f32>) -> FrontendResult<()> {
+    let rom_data = read_rom(rom_file)?;
+    println!("🔎 Detecting quirks for {}", rom_file.display());
+    let report = detect_quirks(&rom_data, cycles);
+    print!("{}", report);
+    println!();
+
+    let confidence = report.confidence();
+    let Some(suggested) = report.suggested() else {
+        return Ok(());
+    };
+    if confidence == 0.0 {
+        println!("Every variant behaves alike; the ROM doesn't need a quirks preset.");
+        return Ok(());
+    }
+    let alternatives: Vec<&str> = report
+        .best()
+        .skip(1)
+        .map(|score| score.variant.name())
+        .collect();
+    print!("Suggested variant: {}", suggested.name());
+    if !alternatives.is_empty() {
+        print!(" (also fine: {})", alternatives.join(", "));
+    }
+    println!(", confidence {:.2}", confidence);
+
+    if let Some(threshold) = apply {
+        let path = RomOverrides::path_for(rom_file);
+        match report.confident(threshold) {
+            Some(variant) => {
+                let mut overrides = RomOverrides::load_for(rom_file)?.unwrap_or_default();
+                overrides.variant = Some(variant);
+                overrides.save_for(rom_file)?;
+                println!("✅ Saved variant {} to {}", variant.name(), path.display());
+            }
+            None => println!(
+                "⚠️  Confidence is below {:.2}; {} left unchanged",
+                threshold,
+                path.display()
+            ),
+        }
+    }
+    Ok(())
+}
+
+/// Settings for one side of `chip8 diff-runs`.
+struct DiffSide<'a> {
+    /// Configuration file to start from.
+    config_path: Option<&'a Path>,
+
+    /// Profile or variant to start from, if there is no file.
+    profile: Option<&'a str>,
+
+    /// Settings from `--set`, applied before the overrides.
+    patch: &'a ConfigPatch,
+
+    /// `KEY=VALUE` behavior overrides.
+    overrides: &'a [String],
+
+    /// Random number seed.
+    seed: u64,
+}
+
+impl DiffSide<'_> {
+    /// Loads the side's configuration, starting from `base`'s when the side
+    /// names no file or profile of its own.
+    fn load_config(&self, base: &DiffSide) -> FrontendResult<EmulatorConfig> {
+        let (config_path, profile) = if self.config_path.is_some() || self.profile.is_some() {
+            (self.config_path, self.profile)
+        } else {
+            (base.config_path, base.profile)
+        };
+        let mut config = match (config_path, profile) {
+            (Some(path), _) => super::load_config(path)?,
+            (None, Some(name)) => super::config::ConfigProfiles::from_name(name)?,
+            (None, None) => EmulatorConfig::default(),
+        };
+        base.patch.apply(&mut config)?;
+        self.patch.apply(&mut config)?;
+        for setting in self.overrides {
+            override_behavior(&mut config, setting)?;
+        }
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+/// Loads the config file given with `--config`, if any, and applies `--set`.
+///
+/// # Returns
+/// The configuration, or `None` if there is neither, so commands can keep
+/// their own defaults.
+fn command_config(
+    config_path: Option<&PathBuf>,
+    patch: &ConfigPatch,
+) -> FrontendResult<Option<EmulatorConfig>> {
+    if config_path.is_none() && patch.is_empty() {
+        return Ok(None);
+    }
+    let mut config = match config_path {
+        Some(path) => super::load_config(path)?,
+        None => EmulatorConfig::default(),
+    };
+    patch.apply(&mut config)?;
+    config.validate()?;
+    crate::logging::set_levels(&config.debug.log_levels);
+    Ok(Some(config))
+}
+
+/// Applies a `KEY=VALUE` override to a behavior setting.
+///
+/// The value is read as TOML, falling back to a plain string, so both
+/// `cpu_speed=1000` and `clip_mode=wrap` work.
+fn override_behavior(config: &mut EmulatorConfig, setting: &str) -> FrontendResult<()> {
+    ConfigPatch::new()
+        .with_setting(&format!("behavior.{}", setting.trim()))?
+        .apply(config)?;
+    Ok(())
+}
+
+/// Runs a ROM under two sets of settings in lockstep and prints where the
+/// runs first differ.
+fn diff_runs(
+    rom_file: &Path,
+    cycles: u64,
+    sides: [DiffSide; 2],
+    base: &DiffSide,
+) -> FrontendResult<()> {
+    let rom_data = read_rom(rom_file)?;
+    let mut cpus = Vec::with_capacity(2);
+    let mut rates = Vec::with_capacity(2);
+    for side in &sides {
+        let config = side.load_config(base)?;
+        let behavior = &config.behavior;
+        let mut cpu = crate::Cpu::new_with_config(behavior);
+        cpu.load_rom(&rom_data)?;
+        cpu.set_random_source(Box::new(Pcg32::new(side.seed)));
+        cpus.push(cpu);
+        rates.push((behavior.cpu_speed / behavior.timer_frequency.max(1)).max(1) as u64);
+    }
+    let (Some(b), Some(a)) = (cpus.pop(), cpus.pop()) else {
+        unreachable!("two runs are always created");
+    };
+
+    println!("🔀 Comparing two runs of {}", rom_file.display());
+    let outcome = RunDiff::new(a, b)
+        .with_timer_rates(rates[0], rates[1])
+        .run(cycles);
+    print!("{}", outcome);
+    Ok(())
+}
+
+/// Options for `chip8 profile`.
+struct ProfileOptions<'a> {
+    /// Maximum number of CPU cycles to run.
+    cycles: u64,
+
+    /// Number of subroutines and opcode classes to list.
+    top: usize,
+
+    /// Whether to print a heat map.
+    heatmap: bool,
+
+    /// File to write per-instruction counts to as JSON.
+    json_path: Option<&'a Path>,
+
+    /// File to write per-address counts to as CSV.
+    csv_path: Option<&'a Path>,
+
+    /// File to write the memory access heat map image to.
+    heatmap_image_path: Option<&'a Path>,
+
+    /// Symbol map naming subroutines.
+    symbols_path: Option<&'a Path>,
+}
+
+/// Runs a ROM without a window and prints call and instruction profiles.
+///
+/// Timers are ticked from the cycle count so results are reproducible.
+/// The run ends early if the program halts, faults or waits for a key.
+fn profile_rom(
+    rom_file: &Path,
+    options: &ProfileOptions,
+    config: EmulatorConfig,
+) -> FrontendResult<()> {
+    let &ProfileOptions {
+        cycles,
+        top,
+        heatmap,
+        json_path,
+        csv_path,
+        heatmap_image_path,
+        symbols_path,
+    } = options;
+    let symbols = load_symbols(rom_file, symbols_path)?;
+    let behavior = &config.behavior;
+    let rom_data = read_rom(rom_file)?;
+    let mut cpu = crate::Cpu::new_with_config(behavior);
+    if config.debug.fault_report_dir.is_some() {
+        cpu.set_trace_length(crate::debugger::report::DEFAULT_TRACE_LENGTH);
+    }
+    cpu.load_rom(&rom_data)?;
+    cpu.set_realtime_timers(false);
+    cpu.set_access_profiling(true);
+    let cycles_per_tick = (behavior.cpu_speed / behavior.timer_frequency.max(1)).max(1) as u64;
+
+    let mut profiler = CallProfiler::new();
+    let mut instructions = InstructionProfiler::new();
+    let mut executed = 0u64;
+    let mut stopped = None;
+    while executed < cycles {
+        let pc = cpu.get_state().pc;
+        let result = cpu.cycle();
+        instructions.record(&cpu, pc);
+        match result {
+            Ok(CpuEvent::WaitingForKey) => {
+                stopped = Some("waiting for a key".to_string());
+                break;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                if let Some(bundle) = report_fault(&e, &cpu, &config) {
+                    println!("⚠️  Fault report written to {}", bundle.display());
+                }
+                stopped = Some(format!("fault: {}", e));
+                break;
+            }
+        }
+        profiler.record(&cpu, cpu.last_cost());
+        executed += 1;
+        if executed.is_multiple_of(cycles_per_tick) {
+            cpu.timers_mut().update_by_ticks(1);
+        }
+        if cpu.is_halted() {
+            stopped = Some("halted".to_string());
+            break;
+        }
+    }
+
+    println!("Profile: {}", rom_file.display());
+    match &stopped {
+        Some(reason) => println!("Instructions executed: {} (stopped: {})", executed, reason),
+        None => println!("Instructions executed: {}", executed),
+    }
+    println!("Clock cycles: {}", profiler.total_cycles());
+    println!("Max call depth: {}", profiler.max_depth());
+
+    let hot = profiler.hot_subroutines(top);
+    println!("\nHot subroutines: {}", profiler.subroutines().len());
+    for (entry, stats) in hot {
+        let share = stats.cycles as f64 * 100.0 / profiler.total_cycles().max(1) as f64;
+        println!(
+            "  {}  calls {:>8}  cycles {:>10} ({:5.1}%)",
+            symbols.function_name(Some(entry)),
+            stats.calls,
+            stats.cycles,
+            share
+        );
+    }
+
+    println!("\nTop opcode classes:");
+    for (class, count) in instructions.top_classes(top) {
+        let share = count as f64 * 100.0 / instructions.instructions().max(1) as f64;
+        println!("  {}  {:>10} ({:5.1}%)", class, count, share);
+    }
+    println!(
+        "\nWaiting: {} cycles on FX0A, {} cycles on display",
+        instructions.key_wait_cycles(),
+        instructions.display_wait_cycles()
+    );
+
+    println!("\nBacktrace:");
+    print!(
+        "{}",
+        format_backtrace(&backtrace_with_symbols(&cpu, &symbols))
+    );
+
+    if heatmap {
+        use std::io::IsTerminal;
+        let color = std::io::stdout().is_terminal();
+        println!("\nHeat map:");
+        print!("{}", instructions.heat_map(cpu.get_memory(), color));
+    }
+    if let Some(path) = json_path {
+        std::fs::write(path, instructions.to_json(cpu.get_memory()))?;
+        println!("✅ Instruction profile written to {}", path.display());
+    }
+    if let Some(path) = csv_path {
+        std::fs::write(path, instructions.to_csv(cpu.get_memory()))?;
+        println!("✅ Instruction profile written to {}", path.display());
+    }
+    if let (Some(path), Some(counts)) = (heatmap_image_path, cpu.access_counts()) {
+        let (width, height, rgba) = counts.heatmap(HEATMAP_WIDTH);
+        let format = ScreenshotFormat::from_path(path).unwrap_or_default();
+        save_rgba(path, width, height, &rgba, format).map_err(EmulatorError::Graphics)?;
+        println!(
+            "✅ Memory heat map written to {} (red: writes, green: reads, blue: executes)",
+            path.display()
+        );
+    }
+
+    Ok(())
+}
+
+/// Runs environment diagnostics and prints the report.
+fn run_doctor(config_path: Option<&PathBuf>) -> FrontendResult<()> {
+    let report = super::doctor::run_diagnostics(config_path);
+    print!("{}", report.format());
+
+    if report.has_failures() {
+        println!("\nSome checks failed; see the suggestions above.");
+    }
+
+    report.result()
+}
+
 /// Parses a hex color string (e.g., "FF0000" or "#FF0000") into a Color.
 fn parse_hex_color(hex: &str) -> Result<Color, EmulatorError> {
-    use crate::error::ConfigError;
-
     let hex = hex.trim_start_matches('#');
 
     let make_error = || {
@@ -218,6 +1465,7 @@ fn parse_hex_color(hex: &str) -> Result<Color, EmulatorError> {
 fn capture_screenshot(
     rom_file: &Path,
     output: &Path,
+    format: Option<ScreenshotFormat>,
     cycles: u32,
     scale: u32,
     foreground: &str,
@@ -228,14 +1476,23 @@ fn capture_screenshot(
 
     println!("Loading ROM: {}", rom_file.display());
 
-    let mut emulator = SimpleEmulator::new();
+    let mut emulator = SimpleEmulator::new().with_watchdog(WatchdogConfig::default());
     emulator
         .cpu_mut()
         .set_display(Box::new(SoftwareDisplay::new()));
     emulator.load_rom(rom_file)?;
 
     println!("Running for {} cycles...", cycles);
-    emulator.run_cycles(cycles)?;
+    match emulator.run_until_blocked(cycles) {
+        Ok((ran, CpuEvent::WaitingForKey)) => println!(
+            "⚠️  ROM is waiting for a key press; stopped after {} cycles",
+            ran
+        ),
+        Ok(_) => {}
+        // A finished or stuck program has nothing more to draw
+        Err(EmulatorError::Halted(halted)) => println!("⚠️  Stopped early: {}", halted),
+        Err(e) => return Err(e),
+    }
 
     let display_buffer = emulator.get_display_buffer();
 
@@ -244,22 +1501,23 @@ fn capture_screenshot(
         .with_background_color(bg_color)
         .with_scale_factor(scale);
 
+    let (display_width, display_height) = emulator.display_size();
     let mut renderer = PixelRenderer::new(graphics_config).map_err(EmulatorError::Graphics)?;
+    renderer
+        .resize(display_width, display_height)
+        .map_err(EmulatorError::Graphics)?;
     renderer
         .render(display_buffer)
         .map_err(EmulatorError::Graphics)?;
 
+    let format = format
+        .or_else(|| ScreenshotFormat::from_path(output))
+        .unwrap_or_default();
+    renderer
+        .save_frame(output, format)
+        .map_err(EmulatorError::Graphics)?;
+
     let (width, height) = renderer.frame_size();
-    image::save_buffer(
-        output,
-        renderer.frame_buffer(),
-        width,
-        height,
-        image::ColorType::Rgba8,
-    )
-    .map_err(|e| {
-        EmulatorError::IoError(std::io::Error::other(format!("Failed to save PNG: {}", e)))
-    })?;
 
     println!("Screenshot saved to: {}", output.display());
     println!(
@@ -283,6 +1541,14 @@ mod tests {
             verbose: true,
             config: None,
             profile: None,
+            settings: Vec::new(),
+            host: None,
+            join: None,
+            stats: false,
+            timeline: None,
+            cheat: Vec::new(),
+            no_cheats: false,
+            patch: None,
             command: None,
         };
 
@@ -293,6 +1559,23 @@ mod tests {
         assert!(args.profile.is_none());
     }
 
+    #[test]
+    fn test_netplay_flags() {
+        let args = CliApp::parse_from(["chip8", "pong.ch8", "--host", "7000"]);
+        assert_eq!(args.netplay_role(), Some(NetplayRole::Host { port: 7000 }));
+
+        let args = CliApp::parse_from(["chip8", "pong.ch8", "--join", "10.0.0.2:7000"]);
+        assert_eq!(
+            args.netplay_role(),
+            Some(NetplayRole::Join {
+                address: "10.0.0.2:7000".to_string()
+            })
+        );
+
+        assert!(CliApp::try_parse_from(["chip8", "--host", "1", "--join", "a:1"]).is_err());
+        assert_eq!(CliApp::parse_from(["chip8"]).netplay_role(), None);
+    }
+
     #[test]
     fn test_parse_hex_color_valid() {
         let white = parse_hex_color("FFFFFF").unwrap();
@@ -341,9 +1624,18 @@ mod tests {
             verbose: false,
             config: None,
             profile: None,
+            settings: Vec::new(),
+            host: None,
+            join: None,
+            stats: false,
+            timeline: None,
+            cheat: Vec::new(),
+            no_cheats: false,
+            patch: None,
             command: Some(Commands::Screenshot {
                 rom_file: PathBuf::from("test.ch8"),
                 output: PathBuf::from("output.png"),
+                format: None,
                 cycles: 2000,
                 scale: 5,
                 foreground: "00FF00".to_string(),
@@ -358,4 +1650,328 @@ mod tests {
             panic!("Expected Screenshot command");
         }
     }
+
+    #[test]
+    fn test_run_command_parsing() {
+        let args = CliApp::parse_from(["chip8", "--profile", "classic", "run", "--watch", "a.ch8"]);
+        assert_eq!(args.profile.as_deref(), Some("classic"));
+        assert!(!args.stats);
+        assert!(CliApp::parse_from(["chip8", "run", "a.ch8", "--stats"]).stats);
+        assert_eq!(
+            CliApp::parse_from(["chip8", "--timeline", "out.json", "run", "a.ch8"]).timeline,
+            Some(PathBuf::from("out.json"))
+        );
+        match args.command {
+            Some(Commands::Run {
+                rom_file, watch, ..
+            }) => {
+                assert_eq!(rom_file, Some(PathBuf::from("a.ch8")));
+                assert!(watch);
+            }
+            _ => panic!("Expected Run command"),
+        }
+
+        let args = CliApp::parse_from(["chip8", "run", "a.ch8"]);
+        assert!(matches!(
+            args.command,
+            Some(Commands::Run { watch: false, .. })
+        ));
+    }
+
+    #[test]
+    fn test_replay_flags() {
+        let args = CliApp::parse_from(["chip8", "run", "--record", "a.c8replay", "a.ch8"]);
+        match args.command {
+            Some(Commands::Run { record, replay, .. }) => assert_eq!(
+                replay_mode(record.as_ref(), replay.as_ref()),
+                Some(ReplayMode::Record(PathBuf::from("a.c8replay")))
+            ),
+            _ => panic!("Expected Run command"),
+        }
+
+        let args = CliApp::parse_from(["chip8", "run", "--replay", "a.c8replay", "a.ch8"]);
+        match args.command {
+            Some(Commands::Run { record, replay, .. }) => assert_eq!(
+                replay_mode(record.as_ref(), replay.as_ref()),
+                Some(ReplayMode::Play(PathBuf::from("a.c8replay")))
+            ),
+            _ => panic!("Expected Run command"),
+        }
+
+        // Recording and playing back at once makes no sense
+        assert!(CliApp::try_parse_from([
+            "chip8", "run", "--record", "a", "--replay", "b", "a.ch8"
+        ])
+        .is_err());
+
+        let args = CliApp::parse_from([
+            "chip8",
+            "run",
+            "--replay",
+            "a.c8replay",
+            "--dump-video",
+            "-",
+            "--dump-scale",
+            "4",
+            "--dump-audio",
+            "a.pcm",
+            "a.ch8",
+        ]);
+        match args.command {
+            Some(Commands::Run {
+                dump_video,
+                dump_audio,
+                dump_scale,
+                frames,
+                ..
+            }) => {
+                assert_eq!(dump_video, Some(PathBuf::from("-")));
+                assert_eq!(dump_audio, Some(PathBuf::from("a.pcm")));
+                assert_eq!(dump_scale, 4);
+                assert_eq!(frames, None);
+            }
+            _ => panic!("Expected Run command"),
+        }
+        assert!(CliApp::try_parse_from(["chip8", "run", "--frames", "10", "a.ch8"]).is_err());
+        assert!(CliApp::try_parse_from([
+            "chip8",
+            "run",
+            "--dump-audio",
+            "a.pcm",
+            "--frames",
+            "10",
+            "a.ch8"
+        ])
+        .is_ok());
+    }
+
+    #[test]
+    fn test_bench_command() {
+        let args = CliApp::parse_from(["chip8", "bench", "--instructions", "500", "--json"]);
+        match args.command {
+            Some(Commands::Bench {
+                rom_file,
+                instructions,
+                frames,
+                json,
+                ..
+            }) => {
+                assert_eq!(rom_file, None);
+                assert_eq!(instructions, 500);
+                assert_eq!(frames, BenchOptions::default().frames);
+                assert!(json);
+            }
+            _ => panic!("Expected Bench command"),
+        }
+
+        let options = BenchOptions {
+            instructions: 500,
+            frames: 1,
+            samples: 10,
+        };
+        let report = run_benchmarks(&SYNTHETIC_ROM, SYNTHETIC_NAME, &options).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&bench_json(&report)).unwrap();
+        assert_eq!(json["rom"], "synthetic");
+        assert_eq!(json["instructions"], 500);
+    }
+
+    #[test]
+    fn test_gallery_command() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("b.ch8"), [0x12, 0x00]).unwrap();
+        std::fs::write(dir.path().join("a.ch8"), [0x00, 0xE0]).unwrap();
+        let single = dir.path().join("single.c8");
+        std::fs::write(&single, [0x00, 0xE0]).unwrap();
+
+        let args = CliApp::parse_from([
+            "chip8".as_ref(),
+            "gallery".as_ref(),
+            dir.path().as_os_str(),
+            single.as_os_str(),
+            "--idle-timeout".as_ref(),
+            "0".as_ref(),
+        ]);
+        match args.command {
+            Some(Commands::Gallery {
+                roms,
+                speed,
+                idle_timeout,
+            }) => {
+                assert_eq!(speed, DEFAULT_GALLERY_SPEED);
+                assert_eq!(idle_timeout, 0);
+                let names: Vec<String> = gallery_roms(&roms)
+                    .unwrap()
+                    .into_iter()
+                    .map(|(name, _)| name)
+                    .collect();
+                assert_eq!(names, ["a", "b", "single"]);
+            }
+            _ => panic!("Expected Gallery command"),
+        }
+    }
+
+    #[test]
+    fn test_set_option() {
+        let args = CliApp::parse_from([
+            "chip8",
+            "profile",
+            "game.ch8",
+            "--set",
+            "graphics.scale_factor=14",
+            "--set",
+            "behavior.shift_uses_vy=true",
+        ]);
+        assert_eq!(
+            args.settings,
+            ["graphics.scale_factor=14", "behavior.shift_uses_vy=true"]
+        );
+        let patch = ConfigPatch::parse(&args.settings).unwrap();
+        let config = command_config(None, &patch).unwrap().unwrap();
+        assert_eq!(config.graphics.scale_factor, 14);
+        assert!(config.behavior.shift_uses_vy);
+        assert!(command_config(None, &ConfigPatch::new()).unwrap().is_none());
+
+        // Range checks still apply
+        let patch = ConfigPatch::parse(&["graphics.scale_factor=99"]).unwrap();
+        assert!(command_config(None, &patch).is_err());
+    }
+
+    #[test]
+    fn test_av_sync_command() {
+        let args = CliApp::parse_from(["chip8", "av-sync"]);
+        match args.command {
+            Some(Commands::AvSync { beeps, save_rom }) => {
+                assert_eq!(beeps, DEFAULT_SYNC_BEEPS);
+                assert_eq!(save_rom, None);
+            }
+            _ => panic!("Expected AvSync command"),
+        }
+        assert!(CliApp::try_parse_from(["chip8", "av-sync", "--beeps", "0"]).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sync.ch8");
+        save_sync_rom(&path).unwrap();
+        assert_eq!(
+            std::fs::read(&path).unwrap(),
+            sync_rom(DEFAULT_SYNC_INTERVAL, DEFAULT_BEEP_FRAMES)
+        );
+    }
+
+    #[test]
+    fn test_compare_command() {
+        let dir = tempfile::tempdir().unwrap();
+        // Clears the screen and loops
+        std::fs::write(dir.path().join("blank.ch8"), [0x00, 0xE0, 0x12, 0x02]).unwrap();
+        let rows = vec![".".repeat(64); 32];
+        let mut lit = rows.clone();
+        lit[0] = "#".repeat(64);
+        for (name, rows) in [("blank.json", &rows), ("lit.json", &lit)] {
+            let dump = serde_json::json!({
+                "rom": "blank.ch8",
+                "frames": [{ "frame": 2, "rows": rows }],
+            });
+            std::fs::write(dir.path().join(name), dump.to_string()).unwrap();
+        }
+
+        let args = CliApp::parse_from([
+            "chip8".as_ref(),
+            "compare".as_ref(),
+            dir.path().join("blank.json").as_os_str(),
+            dir.path().join("lit.json").as_os_str(),
+            "--min-score".as_ref(),
+            "99".as_ref(),
+        ]);
+        let Some(Commands::Compare {
+            references,
+            rom,
+            min_score,
+        }) = args.command
+        else {
+            panic!("Expected Compare command");
+        };
+        assert_eq!(references.len(), 2);
+        assert_eq!(rom, None);
+
+        // The lit row is 64 of 2048 pixels, so that ROM scores 96.9%
+        let error = run_compare(&references, None, min_score, None).unwrap_err();
+        assert!(error.to_string().contains("96.9%"), "{}", error);
+        run_compare(&references[..1], None, min_score, None).unwrap();
+        assert!(CliApp::try_parse_from(["chip8", "compare"]).is_err());
+    }
+
+    #[test]
+    fn test_kiosk_command() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("b.ch8"), [0x12, 0x00]).unwrap();
+        std::fs::write(dir.path().join("a.ch8"), [0x00, 0xE0]).unwrap();
+
+        let args = CliApp::parse_from([
+            "chip8".as_ref(),
+            "kiosk".as_ref(),
+            dir.path().as_os_str(),
+            "--rotate-secs".as_ref(),
+            "20".as_ref(),
+        ]);
+        match args.command {
+            Some(Commands::Kiosk {
+                roms,
+                idle_secs,
+                rotate_secs,
+            }) => {
+                assert_eq!(idle_secs, None);
+                assert_eq!(rotate_secs, Some(20));
+                let playlist = kiosk_playlist(&roms).unwrap();
+                let names: Vec<String> = playlist.iter().map(PlaylistEntry::name).collect();
+                assert_eq!(names, ["a", "b"]);
+            }
+            _ => panic!("Expected Kiosk command"),
+        }
+    }
+
+    #[test]
+    fn test_demo_flag() {
+        let args = CliApp::parse_from(["chip8", "run", "--demo", "maze"]);
+        match args.command {
+            Some(Commands::Run { rom_file, demo, .. }) => {
+                assert_eq!(rom_file, None);
+                assert_eq!(demo.as_deref(), Some("maze"));
+            }
+            _ => panic!("Expected Run command"),
+        }
+
+        // A ROM or a demo is required, but not both
+        assert!(CliApp::try_parse_from(["chip8", "run"]).is_err());
+        assert!(CliApp::try_parse_from(["chip8", "run", "--demo", "maze", "a.ch8"]).is_err());
+        assert!(demo_rom_path("tetris").is_err());
+    }
+
+    #[test]
+    fn test_diff_runs_overrides() {
+        let args = CliApp::parse_from([
+            "chip8",
+            "diff-runs",
+            "a.ch8",
+            "--set-b",
+            "shift_uses_vy=true",
+            "--seed-b",
+            "7",
+        ]);
+        match args.command {
+            Some(Commands::DiffRuns { set_b, seed_b, .. }) => {
+                assert_eq!(set_b, vec!["shift_uses_vy=true"]);
+                assert_eq!(seed_b, 7);
+            }
+            _ => panic!("Expected DiffRuns command"),
+        }
+
+        let mut config = EmulatorConfig::default();
+        config.behavior.shift_uses_vy = false;
+        override_behavior(&mut config, "shift_uses_vy = true").unwrap();
+        override_behavior(&mut config, "cpu_speed=1000").unwrap();
+        assert!(config.behavior.shift_uses_vy);
+        assert_eq!(config.behavior.cpu_speed, 1000);
+        assert!(override_behavior(&mut config, "no_such_quirk=1").is_err());
+        assert!(override_behavior(&mut config, "cpu_speed=fast").is_err());
+        assert!(override_behavior(&mut config, "cpu_speed").is_err());
+    }
 }
@@ -4,12 +4,32 @@
 //! for the emulator settings.
 
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "hot-reload")]
+use std::sync::mpsc::{self, Receiver};
+#[cfg(feature = "hot-reload")]
+use std::time::{Duration, Instant, SystemTime};
 
+#[cfg(feature = "hot-reload")]
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::analysis::InstructionSet;
 use crate::audio::BuzzerConfig;
+use crate::emulator::{
+    AddressMasking, FontSet, MemoryLayout, MemoryProtection, SoundTimerMode, TimingModel, FONT_SIZE,
+};
 use crate::error::{ConfigError, EmulatorError};
-use crate::graphics::GraphicsConfig;
-use crate::input::KeyboardConfig;
+use crate::frontend::kiosk::KioskConfig;
+use crate::frontend::recovery::AutosaveConfig;
+use crate::frontend::shortcuts::ShortcutsConfig;
+use crate::frontend::window::WindowConfig;
+use crate::graphics::{Color, GraphicsConfig};
+use crate::hardware::{ClipMode, HiresMode};
+use crate::input::virtual_keypad::{MAX_KEY_SIZE, MIN_KEY_SIZE};
+use crate::input::{KeyboardConfig, VirtualKeypadConfig};
+use crate::logging::APP;
+use crate::logging::{LogLevel, LogLevels};
+use crate::paths::{Paths, PathsConfig};
 
 /// Debug configuration.
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -22,10 +42,116 @@ pub struct DebugConfig {
 
     /// Log CPU instructions.
     pub log_instructions: bool,
+
+    /// Directory to write a diagnostic bundle to when the CPU faults.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fault_report_dir: Option<PathBuf>,
+
+    /// Symbol map naming addresses in fault reports.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbols_file: Option<PathBuf>,
+
+    /// Report writes to code that has already run.
+    #[serde(default)]
+    pub track_self_modifying_code: bool,
+
+    /// How much each subsystem logs.
+    #[serde(default)]
+    pub log_levels: LogLevels,
+}
+
+/// A Chip-8 interpreter whose quirks can be emulated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Variant {
+    /// Classic Chip-8 with this emulator's defaults.
+    #[serde(rename = "chip-8")]
+    Chip8,
+
+    /// The original COSMAC VIP interpreter, at its original speed.
+    #[serde(rename = "cosmac-vip")]
+    CosmacVip,
+
+    /// CHIP-48 on the HP 48 calculators.
+    #[serde(rename = "chip-48")]
+    Chip48,
+
+    /// SUPER-CHIP 1.1.
+    #[serde(rename = "super-chip")]
+    SuperChip,
+
+    /// XO-CHIP.
+    #[serde(rename = "xo-chip")]
+    XoChip,
+
+    /// Mega-Chip, partially: the color screen and palette.
+    #[serde(rename = "mega-chip")]
+    MegaChip,
+}
+
+impl Variant {
+    /// Gets every variant, in the order they appeared.
+    pub fn all() -> [Variant; 6] {
+        [
+            Variant::Chip8,
+            Variant::CosmacVip,
+            Variant::Chip48,
+            Variant::SuperChip,
+            Variant::XoChip,
+            Variant::MegaChip,
+        ]
+    }
+
+    /// Gets the name used in configuration files.
+    pub fn name(self) -> &'static str {
+        match self {
+            Variant::Chip8 => "chip-8",
+            Variant::CosmacVip => "cosmac-vip",
+            Variant::Chip48 => "chip-48",
+            Variant::SuperChip => "super-chip",
+            Variant::XoChip => "xo-chip",
+            Variant::MegaChip => "mega-chip",
+        }
+    }
+
+    /// Finds a variant by its configuration name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all()
+            .into_iter()
+            .find(|variant| variant.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Sets a behavior config's quirks to this variant's.
+    ///
+    /// The CPU speed, timer frequency, memory guards, screen mode and font
+    /// are left alone. The COSMAC VIP brings its own timing model; the
+    /// other variants run at the config's `cpu_speed`.
+    pub fn apply_quirks(self, behavior: &mut EmulatorBehaviorConfig) {
+        let quirks = EmulatorBehaviorConfig::for_variant(self);
+        behavior.memory_wraparound = quirks.memory_wraparound;
+        behavior.strict_bounds = quirks.strict_bounds;
+        behavior.memory_layout = quirks.memory_layout;
+        behavior.jump_with_vx = quirks.jump_with_vx;
+        behavior.shift_uses_vy = quirks.shift_uses_vy;
+        behavior.load_store_increments_i = quirks.load_store_increments_i;
+        behavior.mega_chip = quirks.mega_chip;
+        behavior.clip_mode = quirks.clip_mode;
+        behavior.timing_model = quirks.timing_model;
+        behavior.sound_timer_mode = quirks.sound_timer_mode;
+    }
+}
+
+impl From<InstructionSet> for Variant {
+    fn from(set: InstructionSet) -> Self {
+        match set {
+            InstructionSet::Chip8 => Variant::Chip8,
+            InstructionSet::SuperChip => Variant::SuperChip,
+            InstructionSet::XoChip => Variant::XoChip,
+        }
+    }
 }
 
 /// Emulator behavior configuration for compatibility.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct EmulatorBehaviorConfig {
     /// CPU speed in instructions per second.
     pub cpu_speed: u32,
@@ -36,8 +162,64 @@ pub struct EmulatorBehaviorConfig {
     /// Strict bounds checking (disable for classic compatibility).
     pub strict_bounds: bool,
 
+    /// Size and layout of the address space: 4KB, or 64KB for XO-CHIP.
+    #[serde(default)]
+    pub memory_layout: MemoryLayout,
+
     /// Timer frequency in Hz (classic CHIP-8 uses 60Hz).
     pub timer_frequency: u32,
+
+    /// How writes below 0x200 are handled.
+    #[serde(default)]
+    pub memory_protection: MemoryProtection,
+
+    /// How BNNN targets and I-relative addresses past 0xFFF are handled.
+    #[serde(default)]
+    pub address_masking: AddressMasking,
+
+    /// Jump to XNN + VX for BXNN, as SUPER-CHIP does, instead of NNN + V0.
+    #[serde(default)]
+    pub jump_with_vx: bool,
+
+    /// Shift VY into VX for 8XY6/8XYE, as the COSMAC VIP does, instead of
+    /// shifting VX in place.
+    #[serde(default)]
+    pub shift_uses_vy: bool,
+
+    /// Leave I past the last register after FX55/FX65, as the COSMAC VIP
+    /// does, instead of unchanged.
+    #[serde(default)]
+    pub load_store_increments_i: bool,
+
+    /// Let programs switch on Mega-Chip's 256x192 color mode with 0011.
+    #[serde(default)]
+    pub mega_chip: bool,
+
+    /// Whether sprites past the screen edge are clipped or wrap around.
+    #[serde(default)]
+    pub clip_mode: ClipMode,
+
+    /// Hi-res CHIP-8 screen: `auto` switches to 64x64 for programs that
+    /// start with 1260.
+    #[serde(default)]
+    pub hires: HiresMode,
+
+    /// Instruction timing model (defaults to simple timing at `cpu_speed`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing_model: Option<TimingModel>,
+
+    /// Built-in font loaded at 0x50.
+    #[serde(default)]
+    pub font: FontSet,
+
+    /// User-provided font of 80 bytes, 5 per digit, replacing `font`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_font: Option<Vec<u8>>,
+
+    /// How the sound timer turns into a beep: exactly, ignoring a timer of
+    /// 1, or lasting a minimum time.
+    #[serde(default)]
+    pub sound_timer_mode: SoundTimerMode,
 }
 
 impl Default for EmulatorBehaviorConfig {
@@ -46,7 +228,20 @@ impl Default for EmulatorBehaviorConfig {
             cpu_speed: 700,
             memory_wraparound: false,
             strict_bounds: true,
+            memory_layout: MemoryLayout::Chip8_4K,
             timer_frequency: 60,
+            memory_protection: MemoryProtection::Off,
+            address_masking: AddressMasking::Off,
+            jump_with_vx: false,
+            shift_uses_vy: false,
+            load_store_increments_i: false,
+            mega_chip: false,
+            clip_mode: ClipMode::Wrap,
+            hires: HiresMode::Auto,
+            timing_model: None,
+            font: FontSet::Octo,
+            custom_font: None,
+            sound_timer_mode: SoundTimerMode::Exact,
         }
     }
 }
@@ -58,7 +253,32 @@ impl EmulatorBehaviorConfig {
             cpu_speed: 500,
             memory_wraparound: true,
             strict_bounds: false,
+            memory_layout: MemoryLayout::Chip8_4K,
             timer_frequency: 60,
+            memory_protection: MemoryProtection::Off,
+            address_masking: AddressMasking::Off,
+            jump_with_vx: false,
+            shift_uses_vy: false,
+            load_store_increments_i: false,
+            mega_chip: false,
+            clip_mode: ClipMode::Wrap,
+            hires: HiresMode::Auto,
+            timing_model: None,
+            font: FontSet::Octo,
+            custom_font: None,
+            sound_timer_mode: SoundTimerMode::Exact,
+        }
+    }
+
+    /// Creates a configuration with the original COSMAC VIP interpreter's
+    /// speed, shifts and load/store behavior.
+    pub fn cosmac_vip() -> Self {
+        Self {
+            timing_model: Some(TimingModel::CosmacVip),
+            shift_uses_vy: true,
+            load_store_increments_i: true,
+            sound_timer_mode: SoundTimerMode::SkipIf1,
+            ..Self::classic()
         }
     }
 
@@ -68,14 +288,82 @@ impl EmulatorBehaviorConfig {
             cpu_speed: 700,
             memory_wraparound: false,
             strict_bounds: true,
+            memory_layout: MemoryLayout::Chip8_4K,
             timer_frequency: 60,
+            memory_protection: MemoryProtection::Off,
+            address_masking: AddressMasking::Off,
+            jump_with_vx: false,
+            shift_uses_vy: false,
+            load_store_increments_i: false,
+            mega_chip: false,
+            clip_mode: ClipMode::Wrap,
+            hires: HiresMode::Auto,
+            timing_model: None,
+            font: FontSet::Octo,
+            custom_font: None,
+            sound_timer_mode: SoundTimerMode::Exact,
         }
     }
+
+    /// Creates a configuration with the quirks of a Chip-8 variant.
+    ///
+    /// The COSMAC VIP shifts VY and advances I on load/store; CHIP-48 shifts
+    /// VX in place, leaves I alone and jumps with VX; SUPER-CHIP also clips
+    /// sprites at the screen edge; XO-CHIP gets the 64KB address space, and
+    /// Mega-Chip gets it too along with its color mode.
+    pub fn for_variant(variant: impl Into<Variant>) -> Self {
+        match variant.into() {
+            Variant::Chip8 => Self::classic(),
+            Variant::CosmacVip => Self::cosmac_vip(),
+            Variant::Chip48 => Self {
+                jump_with_vx: true,
+                ..Self::classic()
+            },
+            Variant::SuperChip => Self {
+                jump_with_vx: true,
+                clip_mode: ClipMode::Clip,
+                ..Self::modern()
+            },
+            Variant::XoChip => Self {
+                memory_layout: MemoryLayout::XoChip64K,
+                ..Self::modern()
+            },
+            Variant::MegaChip => Self {
+                memory_layout: MemoryLayout::XoChip64K,
+                mega_chip: true,
+                ..Self::modern()
+            },
+        }
+    }
+
+    /// Gets the effective timing model.
+    pub fn timing(&self) -> TimingModel {
+        self.timing_model
+            .unwrap_or(TimingModel::Simple(self.cpu_speed))
+    }
+
+    /// Gets the effective font: the custom font if it is valid, else `font`.
+    pub fn font_set(&self) -> FontSet {
+        self.custom_font
+            .as_deref()
+            .and_then(FontSet::custom)
+            .unwrap_or(self.font)
+    }
 }
 
+/// Version of the config file format written by this build.
+///
+/// Bump it with a step in `MIGRATIONS` whenever a key is renamed or moved,
+/// or a section becomes required, so older files are upgraded on load.
+pub const CONFIG_VERSION: u32 = 1;
+
 /// Complete emulator configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EmulatorConfig {
+    /// Version of the file format; files without one predate versioning.
+    #[serde(default)]
+    pub version: u32,
+
     /// Emulator behavior configuration.
     pub behavior: EmulatorBehaviorConfig,
 
@@ -90,16 +378,47 @@ pub struct EmulatorConfig {
 
     /// Debug configuration.
     pub debug: DebugConfig,
+
+    /// On-screen keypad configuration.
+    #[serde(default)]
+    pub virtual_keypad: VirtualKeypadConfig,
+
+    /// Window geometry, saved by the GUI when it closes.
+    #[serde(default)]
+    pub window: WindowConfig,
+
+    /// Auto-save and crash recovery configuration.
+    #[serde(default)]
+    pub autosave: AutosaveConfig,
+
+    /// Key bindings of the emulator shortcuts.
+    #[serde(default)]
+    pub shortcuts: ShortcutsConfig,
+
+    /// Attract mode timing and playlist.
+    #[serde(default)]
+    pub kiosk: KioskConfig,
+
+    /// Data and cache directories replacing the platform's.
+    #[serde(default)]
+    pub paths: PathsConfig,
 }
 
 impl Default for EmulatorConfig {
     fn default() -> Self {
         Self {
+            version: CONFIG_VERSION,
             behavior: EmulatorBehaviorConfig::default(),
             graphics: GraphicsConfig::classic_green(),
             audio: BuzzerConfig::classic(),
             keyboard: KeyboardConfig::desktop(),
             debug: DebugConfig::default(),
+            virtual_keypad: VirtualKeypadConfig::default(),
+            window: WindowConfig::default(),
+            autosave: AutosaveConfig::default(),
+            shortcuts: ShortcutsConfig::default(),
+            kiosk: KioskConfig::default(),
+            paths: PathsConfig::default(),
         }
     }
 }
@@ -113,28 +432,43 @@ impl EmulatorConfig {
     /// Creates a classic CHIP-8 compatible configuration.
     pub fn classic() -> Self {
         Self {
+            version: CONFIG_VERSION,
             behavior: EmulatorBehaviorConfig::classic(),
             graphics: GraphicsConfig::classic_green().with_scale_factor(10),
             audio: BuzzerConfig::classic().with_volume(0.3),
             keyboard: KeyboardConfig::desktop(),
             debug: DebugConfig::default(),
+            virtual_keypad: VirtualKeypadConfig::default(),
+            window: WindowConfig::default(),
+            autosave: AutosaveConfig::default(),
+            shortcuts: ShortcutsConfig::default(),
+            kiosk: KioskConfig::default(),
+            paths: PathsConfig::default(),
         }
     }
 
     /// Creates a modern interpretation configuration.
     pub fn modern() -> Self {
         Self {
+            version: CONFIG_VERSION,
             behavior: EmulatorBehaviorConfig::modern(),
             graphics: GraphicsConfig::high_contrast().with_scale_factor(12),
             audio: BuzzerConfig::new().with_volume(0.4).with_frequency(440.0),
             keyboard: KeyboardConfig::gaming(),
             debug: DebugConfig::default(),
+            virtual_keypad: VirtualKeypadConfig::default(),
+            window: WindowConfig::default(),
+            autosave: AutosaveConfig::default(),
+            shortcuts: ShortcutsConfig::default(),
+            kiosk: KioskConfig::default(),
+            paths: PathsConfig::default(),
         }
     }
 
     /// Creates a gaming-optimized configuration.
     pub fn gaming() -> Self {
         Self {
+            version: CONFIG_VERSION,
             behavior: EmulatorBehaviorConfig::modern(),
             graphics: GraphicsConfig::high_contrast()
                 .with_scale_factor(12)
@@ -145,37 +479,67 @@ impl EmulatorConfig {
                 enabled: false,
                 break_on_error: false,
                 log_instructions: false,
+                fault_report_dir: None,
+                symbols_file: None,
+                track_self_modifying_code: false,
+                log_levels: LogLevels::default(),
             },
+            virtual_keypad: VirtualKeypadConfig::default(),
+            window: WindowConfig::default(),
+            autosave: AutosaveConfig::default(),
+            shortcuts: ShortcutsConfig::default(),
+            kiosk: KioskConfig::default(),
+            paths: PathsConfig::default(),
         }
     }
 
     /// Creates a development-friendly configuration.
     pub fn development() -> Self {
         Self {
+            version: CONFIG_VERSION,
             behavior: EmulatorBehaviorConfig::modern(),
             graphics: GraphicsConfig::classic_green()
                 .with_scale_factor(8)
-                .with_smooth_scaling(true),
+                .with_smooth_scaling(true)
+                .with_osd(true, true),
             audio: BuzzerConfig::new().with_volume(0.2).with_frequency(440.0),
             keyboard: KeyboardConfig::desktop(),
             debug: DebugConfig {
                 enabled: true,
                 break_on_error: true,
                 log_instructions: true,
+                fault_report_dir: Some(PathBuf::from("fault-reports")),
+                symbols_file: None,
+                track_self_modifying_code: false,
+                log_levels: LogLevels::uniform(LogLevel::Debug),
             },
+            virtual_keypad: VirtualKeypadConfig::default(),
+            window: WindowConfig::default(),
+            autosave: AutosaveConfig::default(),
+            shortcuts: ShortcutsConfig::default(),
+            kiosk: KioskConfig::default(),
+            paths: PathsConfig::default(),
         }
     }
 
     /// Creates a classic retro configuration.
     pub fn retro() -> Self {
         Self {
+            version: CONFIG_VERSION,
             behavior: EmulatorBehaviorConfig::classic(),
             graphics: GraphicsConfig::classic_amber()
                 .with_scale_factor(10)
-                .with_smooth_scaling(false),
+                .with_smooth_scaling(false)
+                .with_integer_scaling(true),
             audio: BuzzerConfig::classic().with_volume(0.5),
             keyboard: KeyboardConfig::desktop(),
             debug: DebugConfig::default(),
+            virtual_keypad: VirtualKeypadConfig::default(),
+            window: WindowConfig::default(),
+            autosave: AutosaveConfig::default(),
+            shortcuts: ShortcutsConfig::default(),
+            kiosk: KioskConfig::default(),
+            paths: PathsConfig::default(),
         }
     }
 
@@ -189,6 +553,21 @@ impl EmulatorConfig {
             });
         }
 
+        let pixel_aspect_ratio = self.graphics.pixel_aspect_ratio;
+        if !(0.25..=4.0).contains(&pixel_aspect_ratio) {
+            return Err(ConfigError::InvalidValue {
+                key: "graphics.pixel_aspect_ratio".to_string(),
+                value: pixel_aspect_ratio.to_string(),
+            });
+        }
+
+        if let Some(theme) = self.graphics.themes.iter().find(|theme| !theme.is_valid()) {
+            return Err(ConfigError::InvalidValue {
+                key: "graphics.themes".to_string(),
+                value: format!("{:?}", theme),
+            });
+        }
+
         // Validate audio settings
         if self.audio.volume < 0.0 || self.audio.volume > 1.0 {
             return Err(ConfigError::InvalidValue {
@@ -204,6 +583,73 @@ impl EmulatorConfig {
             });
         }
 
+        if !self.audio.envelope.is_valid() {
+            return Err(ConfigError::InvalidValue {
+                key: "audio.envelope".to_string(),
+                value: format!("{:?}", self.audio.envelope),
+            });
+        }
+
+        // Validate behavior settings
+        if self.behavior.timing().cycles_per_second() == 0 {
+            return Err(ConfigError::InvalidValue {
+                key: "behavior.timing_model".to_string(),
+                value: format!("{:?}", self.behavior.timing()),
+            });
+        }
+
+        if let Some(font) = &self.behavior.custom_font {
+            if font.len() != FONT_SIZE {
+                return Err(ConfigError::InvalidValue {
+                    key: "behavior.custom_font".to_string(),
+                    value: format!("{} bytes", font.len()),
+                });
+            }
+        }
+
+        // Validate virtual keypad settings
+        let key_size = self.virtual_keypad.key_size;
+        if !(MIN_KEY_SIZE..=MAX_KEY_SIZE).contains(&key_size) {
+            return Err(ConfigError::InvalidValue {
+                key: "virtual_keypad.key_size".to_string(),
+                value: key_size.to_string(),
+            });
+        }
+
+        // Validate window settings
+        for (key, value) in [
+            ("window.width", self.window.width),
+            ("window.height", self.window.height),
+        ] {
+            if value == Some(0) {
+                return Err(ConfigError::InvalidValue {
+                    key: key.to_string(),
+                    value: "0".to_string(),
+                });
+            }
+        }
+
+        // Validate auto-save settings
+        if self.autosave.enabled && self.autosave.interval_secs == 0 {
+            return Err(ConfigError::InvalidValue {
+                key: "autosave.interval_secs".to_string(),
+                value: "0".to_string(),
+            });
+        }
+
+        // Validate kiosk settings
+        for (key, value) in [
+            ("kiosk.idle_secs", self.kiosk.idle_secs),
+            ("kiosk.rotate_secs", self.kiosk.rotate_secs),
+        ] {
+            if value == 0 {
+                return Err(ConfigError::InvalidValue {
+                    key: key.to_string(),
+                    value: "0".to_string(),
+                });
+            }
+        }
+
         // Validate keyboard settings
         if self.keyboard.polling_rate == 0 || self.keyboard.polling_rate > 1000 {
             return Err(ConfigError::InvalidValue {
@@ -222,15 +668,174 @@ impl EmulatorConfig {
         self.audio = other.audio.clone();
         self.keyboard = other.keyboard.clone();
         self.debug = other.debug.clone();
+        self.virtual_keypad = other.virtual_keypad.clone();
+        self.window = other.window;
+        self.autosave = other.autosave.clone();
+        self.kiosk = other.kiosk.clone();
+        self.paths = other.paths.clone();
+    }
+}
+
+/// Sections every config file needed before they could be left out.
+const ORIGINAL_SECTIONS: [&str; 5] = ["behavior", "graphics", "audio", "keyboard", "debug"];
+
+/// A step upgrading a config file from one version to the next.
+struct Migration {
+    /// Version the step upgrades from.
+    from: u32,
+
+    /// What the step changes, for the log.
+    description: &'static str,
+
+    /// Changes the file's contents in place.
+    apply: fn(&mut toml::Table) -> Result<(), EmulatorError>,
+}
+
+/// Steps upgrading older config files, in version order.
+const MIGRATIONS: &[Migration] = &[Migration {
+    from: 0,
+    description: "added missing settings with their default values",
+    apply: fill_original_sections,
+}];
+
+/// Adds the sections and keys that unversioned files had to give, with
+/// their default values, so partial files written by hand still load.
+fn fill_original_sections(table: &mut toml::Table) -> Result<(), EmulatorError> {
+    let defaults = toml::Value::try_from(EmulatorConfig::default())?;
+    for name in ORIGINAL_SECTIONS {
+        let Some(toml::Value::Table(default)) = defaults.get(name) else {
+            continue;
+        };
+        let section = table
+            .entry(name)
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+        // A section that isn't a table is left for loading to report
+        if let toml::Value::Table(section) = section {
+            for (key, value) in default {
+                section.entry(key).or_insert_with(|| value.clone());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Upgrades the contents of a config file to [`CONFIG_VERSION`].
+///
+/// # Returns
+/// What each step that ran changed; empty if the file was already current.
+pub fn migrate_config(table: &mut toml::Table) -> Result<Vec<&'static str>, EmulatorError> {
+    let version = config_version(table)?;
+    if version > CONFIG_VERSION {
+        return Err(ConfigError::UnsupportedVersion {
+            found: version,
+            supported: CONFIG_VERSION,
+        }
+        .into());
+    }
+
+    let mut applied = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|step| step.from >= version) {
+        (migration.apply)(table)?;
+        applied.push(migration.description);
+    }
+    table.insert(
+        "version".to_string(),
+        toml::Value::Integer(CONFIG_VERSION as i64),
+    );
+    Ok(applied)
+}
+
+/// Gets the version of a config file's contents; 0 if it has none.
+fn config_version(table: &toml::Table) -> Result<u32, EmulatorError> {
+    let version = match table.get("version") {
+        None => 0,
+        Some(toml::Value::Integer(version)) => {
+            u32::try_from(*version).map_err(|_| ConfigError::InvalidValue {
+                key: "version".to_string(),
+                value: version.to_string(),
+            })?
+        }
+        Some(other) => {
+            return Err(ConfigError::InvalidValue {
+                key: "version".to_string(),
+                value: other.to_string(),
+            }
+            .into())
+        }
+    };
+    Ok(version)
+}
+
+/// Reads a TOML config file, upgrading it in memory if it is older.
+///
+/// # Returns
+/// The configuration, and what upgrading it changed.
+pub fn read_config<P: AsRef<Path>>(
+    path: P,
+) -> Result<(EmulatorConfig, Vec<&'static str>), EmulatorError> {
+    let parsed = ParsedConfig::parse(&std::fs::read_to_string(path.as_ref())?)?;
+    Ok((parsed.config, parsed.applied))
+}
+
+/// A config file's contents, upgraded to the current version.
+struct ParsedConfig {
+    /// The configuration.
+    config: EmulatorConfig,
+
+    /// The upgraded contents, as they would be written back.
+    table: toml::Table,
+
+    /// Version of the file before upgrading.
+    version: u32,
+
+    /// What upgrading it changed.
+    applied: Vec<&'static str>,
+}
+
+impl ParsedConfig {
+    /// Parses, upgrades and validates a config file's contents.
+    fn parse(content: &str) -> Result<Self, EmulatorError> {
+        let mut table: toml::Table = toml::from_str(content)?;
+        let version = config_version(&table)?;
+        let applied = migrate_config(&mut table)?;
+        let config: EmulatorConfig = toml::Value::Table(table.clone()).try_into()?;
+        config.validate()?;
+        Ok(Self {
+            config,
+            table,
+            version,
+            applied,
+        })
     }
 }
 
 /// Loads configuration from a TOML file.
+///
+/// An older file is upgraded and written back, with the original kept
+/// beside it as e.g. `chip8.toml.v0.bak`. Failing to write it back is
+/// logged, and the upgraded settings are used anyway.
 pub fn load_config<P: AsRef<Path>>(path: P) -> Result<EmulatorConfig, EmulatorError> {
-    let content = std::fs::read_to_string(path.as_ref())?;
-    let config: EmulatorConfig = toml::from_str(&content)?;
-    config.validate()?;
-    Ok(config)
+    let path = path.as_ref();
+    let content = std::fs::read_to_string(path)?;
+    let parsed = ParsedConfig::parse(&content)?;
+    if !parsed.applied.is_empty() {
+        for step in &parsed.applied {
+            tracing::info!(target: APP, "Upgraded {}: {}", path.display(), step);
+        }
+        if let Err(e) = write_migrated(path, &content, &parsed) {
+            tracing::warn!(target: APP, "Failed to save upgraded {}: {}", path.display(), e);
+        }
+    }
+    Ok(parsed.config)
+}
+
+/// Backs up a config file's old contents and writes the upgraded ones.
+fn write_migrated(path: &Path, old: &str, parsed: &ParsedConfig) -> Result<(), EmulatorError> {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(format!(".v{}.bak", parsed.version));
+    std::fs::write(&backup, old)?;
+    std::fs::write(path, toml::to_string_pretty(&parsed.table)?)?;
+    Ok(())
 }
 
 /// Saves configuration to a TOML file.
@@ -241,20 +846,383 @@ pub fn save_config<P: AsRef<Path>>(config: &EmulatorConfig, path: P) -> Result<(
     Ok(())
 }
 
-/// Loads configuration from the default locations.
+/// Loads configuration from `chip8.toml` in the config directory (see
+/// [`Paths`]), or the defaults if there isn't one or it is invalid.
 pub fn load_default_config() -> EmulatorConfig {
-    let config_paths = ["chip8.toml", "config/chip8.toml", ".config/chip8.toml"];
+    let Some(path) = Paths::new().find_config_file() else {
+        return EmulatorConfig::default();
+    };
+    match load_config(&path) {
+        Ok(config) => config,
+        Err(e) => {
+            tracing::warn!(target: APP, "Failed to load config from {}: {}", path.display(), e);
+            EmulatorConfig::default()
+        }
+    }
+}
+
+/// Settings a single ROM overrides, read from a `.toml` file beside it.
+///
+/// `pong.toml` next to `pong.ch8` might set the speed and quirks the game
+/// needs; anything left out keeps the value from the main configuration.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RomOverrides {
+    /// Variant whose quirks the ROM expects, applied before the other
+    /// overrides. Settings that aren't quirks, such as the speed and font,
+    /// are kept.
+    pub variant: Option<Variant>,
+
+    /// CPU speed in instructions per second, replacing any timing model.
+    pub cpu_speed: Option<u32>,
+
+    /// Memory wraparound for out-of-bounds access.
+    pub memory_wraparound: Option<bool>,
+
+    /// Size and layout of the address space.
+    pub memory_layout: Option<MemoryLayout>,
+
+    /// How writes below 0x200 are handled.
+    pub memory_protection: Option<MemoryProtection>,
+
+    /// How BNNN targets and I-relative addresses past 0xFFF are handled.
+    pub address_masking: Option<AddressMasking>,
+
+    /// Whether BXNN jumps to XNN + VX.
+    pub jump_with_vx: Option<bool>,
+
+    /// Whether sprites past the screen edge are clipped or wrap around.
+    pub clip_mode: Option<ClipMode>,
+
+    /// Hi-res CHIP-8 screen.
+    pub hires: Option<HiresMode>,
+
+    /// Built-in font, replacing any custom font.
+    pub font: Option<FontSet>,
+
+    /// How the sound timer turns into a beep.
+    pub sound_timer_mode: Option<SoundTimerMode>,
+
+    /// Color for "on" pixels.
+    pub foreground_color: Option<Color>,
+
+    /// Color for "off" pixels.
+    pub background_color: Option<Color>,
+}
+
+impl RomOverrides {
+    /// Gets the path overrides for a ROM are read from.
+    pub fn path_for(rom: &Path) -> PathBuf {
+        rom.with_extension("toml")
+    }
+
+    /// Loads the overrides for a ROM.
+    ///
+    /// # Returns
+    /// The overrides, or `None` if the ROM has no override file.
+    pub fn load_for(rom: &Path) -> Result<Option<Self>, EmulatorError> {
+        let path = Self::path_for(rom);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(path)?;
+        Ok(Some(toml::from_str(&content)?))
+    }
+
+    /// Saves the overrides for a ROM, replacing its override file.
+    pub fn save_for(&self, rom: &Path) -> Result<(), EmulatorError> {
+        std::fs::write(Self::path_for(rom), toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Applies the overrides on top of a configuration.
+    pub fn apply(&self, config: &mut EmulatorConfig) {
+        if let Some(variant) = self.variant {
+            variant.apply_quirks(&mut config.behavior);
+        }
+        if let Some(cpu_speed) = self.cpu_speed {
+            config.behavior.cpu_speed = cpu_speed;
+            config.behavior.timing_model = None;
+        }
+        if let Some(wraparound) = self.memory_wraparound {
+            config.behavior.memory_wraparound = wraparound;
+        }
+        if let Some(layout) = self.memory_layout {
+            config.behavior.memory_layout = layout;
+        }
+        if let Some(protection) = self.memory_protection {
+            config.behavior.memory_protection = protection;
+        }
+        if let Some(masking) = self.address_masking {
+            config.behavior.address_masking = masking;
+        }
+        if let Some(jump_with_vx) = self.jump_with_vx {
+            config.behavior.jump_with_vx = jump_with_vx;
+        }
+        if let Some(clip_mode) = self.clip_mode {
+            config.behavior.clip_mode = clip_mode;
+        }
+        if let Some(hires) = self.hires {
+            config.behavior.hires = hires;
+        }
+        if let Some(font) = self.font {
+            config.behavior.font = font;
+            config.behavior.custom_font = None;
+        }
+        if let Some(mode) = self.sound_timer_mode {
+            config.behavior.sound_timer_mode = mode;
+        }
+        if let Some(color) = self.foreground_color {
+            config.graphics.foreground_color = color;
+        }
+        if let Some(color) = self.background_color {
+            config.graphics.background_color = color;
+        }
+    }
+}
+
+/// Changes between two configurations.
+///
+/// Settings that can be applied to a running emulator are broken out into
+/// fields; anything else that changed is listed in `restart_required`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigDelta {
+    /// New CPU speed in instructions per second.
+    pub cpu_speed: Option<u32>,
+
+    /// New foreground color.
+    pub foreground_color: Option<Color>,
+
+    /// New background color.
+    pub background_color: Option<Color>,
+
+    /// New audio volume.
+    pub volume: Option<f32>,
+
+    /// New buzzer frequency in Hz.
+    pub frequency: Option<f32>,
+
+    /// New log levels.
+    pub log_levels: Option<LogLevels>,
+
+    /// Sections with changes that only take effect after a restart.
+    pub restart_required: Vec<String>,
+}
+
+impl ConfigDelta {
+    /// Computes the changes from `old` to `new`.
+    pub fn between(old: &EmulatorConfig, new: &EmulatorConfig) -> Self {
+        fn changed<T: PartialEq + Copy>(old: T, new: T) -> Option<T> {
+            (old != new).then_some(new)
+        }
+
+        let mut delta = Self {
+            cpu_speed: match (old.behavior.timing(), new.behavior.timing()) {
+                (TimingModel::Simple(old), TimingModel::Simple(new)) => changed(old, new),
+                _ => None,
+            },
+            foreground_color: changed(old.graphics.foreground_color, new.graphics.foreground_color),
+            background_color: changed(old.graphics.background_color, new.graphics.background_color),
+            volume: changed(old.audio.volume, new.audio.volume),
+            frequency: changed(old.audio.frequency, new.audio.frequency),
+            log_levels: changed(old.debug.log_levels, new.debug.log_levels),
+            restart_required: Vec::new(),
+        };
+
+        // Compare each section with its hot-safe fields masked out
+        let mut behavior = new.behavior.clone();
+        if delta.cpu_speed.is_some() {
+            behavior.cpu_speed = old.behavior.cpu_speed;
+            behavior.timing_model = old.behavior.timing_model;
+        }
+        let mut graphics = new.graphics.clone();
+        graphics.foreground_color = old.graphics.foreground_color;
+        graphics.background_color = old.graphics.background_color;
+        let mut audio = new.audio.clone();
+        audio.volume = old.audio.volume;
+        audio.frequency = old.audio.frequency;
+        let mut debug = new.debug.clone();
+        debug.log_levels = old.debug.log_levels;
+
+        let sections = [
+            ("behavior", section_differs(&old.behavior, &behavior)),
+            ("graphics", section_differs(&old.graphics, &graphics)),
+            ("audio", section_differs(&old.audio, &audio)),
+            ("keyboard", section_differs(&old.keyboard, &new.keyboard)),
+            ("debug", section_differs(&old.debug, &debug)),
+            (
+                "virtual_keypad",
+                section_differs(&old.virtual_keypad, &new.virtual_keypad),
+            ),
+            ("window", old.window != new.window),
+            ("autosave", old.autosave != new.autosave),
+            ("kiosk", old.kiosk != new.kiosk),
+            ("paths", old.paths != new.paths),
+        ];
+        for (name, differs) in sections {
+            if differs {
+                delta.restart_required.push(name.to_string());
+            }
+        }
+
+        delta
+    }
+
+    /// Checks if any live-applicable setting changed.
+    pub fn has_live_changes(&self) -> bool {
+        self.cpu_speed.is_some()
+            || self.foreground_color.is_some()
+            || self.background_color.is_some()
+            || self.volume.is_some()
+            || self.frequency.is_some()
+            || self.log_levels.is_some()
+    }
+
+    /// Checks if nothing changed.
+    pub fn is_empty(&self) -> bool {
+        !self.has_live_changes() && self.restart_required.is_empty()
+    }
+}
+
+/// Compares two config sections by their serialized form.
+fn section_differs<T: Serialize>(old: &T, new: &T) -> bool {
+    toml::Value::try_from(old).ok() != toml::Value::try_from(new).ok()
+}
+
+/// Watches a configuration file and reports changes.
+///
+/// The file's directory is watched with `notify`, so `poll` is cheap
+/// enough to call every frame and only re-reads the file after the OS
+/// reports a change to it. Editors often replace the file instead of
+/// writing it in place, which is why the directory is watched. If the OS
+/// can't watch the directory, the modification time is polled instead.
+#[cfg(feature = "hot-reload")]
+pub struct ConfigWatcher {
+    /// Watched file.
+    path: PathBuf,
+
+    /// File system events for the file's directory, if it could be watched.
+    events: Option<(RecommendedWatcher, Receiver<notify::Result<notify::Event>>)>,
+
+    /// Last successfully loaded configuration.
+    current: EmulatorConfig,
+
+    /// Modification time of the last load attempt.
+    last_modified: Option<SystemTime>,
+
+    /// Minimum time between modification checks without `notify`.
+    interval: Duration,
+
+    /// When the file was last checked.
+    last_check: Option<Instant>,
+}
+
+#[cfg(feature = "hot-reload")]
+impl ConfigWatcher {
+    /// Creates a watcher for a file whose contents are already loaded as `current`.
+    pub fn new<P: AsRef<Path>>(path: P, current: EmulatorConfig) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let last_modified = Self::modified(&path);
+        let events = Self::watch(&path);
+
+        Self {
+            path,
+            events,
+            current,
+            last_modified,
+            interval: Duration::from_millis(500),
+            last_check: None,
+        }
+    }
+
+    /// Sets how often the file is checked when it can't be watched.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Gets the watched path.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
 
-    for path in &config_paths {
-        if Path::new(path).exists() {
-            match load_config(path) {
-                Ok(config) => return config,
-                Err(e) => log::warn!("Failed to load config from {}: {}", path, e),
+    /// Gets the last successfully loaded configuration.
+    pub fn current(&self) -> &EmulatorConfig {
+        &self.current
+    }
+
+    /// Checks for changes if the OS reported any, or if the polling
+    /// interval has elapsed when the file can't be watched.
+    ///
+    /// # Returns
+    /// The changes if the file was modified, or an error if the new file
+    /// is invalid (the previous configuration stays in effect).
+    pub fn poll(&mut self) -> Result<Option<ConfigDelta>, EmulatorError> {
+        if let Some((_, events)) = &self.events {
+            let name = self.path.file_name();
+            let touched = events
+                .try_iter()
+                .filter_map(Result::ok)
+                .any(|event| event.paths.iter().any(|p| p.file_name() == name));
+            return if touched { self.check_now() } else { Ok(None) };
+        }
+
+        let now = Instant::now();
+        if self
+            .last_check
+            .is_some_and(|last| now.duration_since(last) < self.interval)
+        {
+            return Ok(None);
+        }
+        self.last_check = Some(now);
+        self.check_now()
+    }
+
+    /// Checks the file for changes immediately.
+    pub fn check_now(&mut self) -> Result<Option<ConfigDelta>, EmulatorError> {
+        let modified = Self::modified(&self.path);
+        if modified == self.last_modified {
+            return Ok(None);
+        }
+        // Remember the attempt so a broken file is only reported once
+        self.last_modified = modified;
+
+        let config = load_config(&self.path)?;
+        let delta = ConfigDelta::between(&self.current, &config);
+        self.current = config;
+
+        Ok((!delta.is_empty()).then_some(delta))
+    }
+
+    /// Starts watching the directory containing `path`.
+    fn watch(path: &Path) -> Option<(RecommendedWatcher, Receiver<notify::Result<notify::Event>>)> {
+        let dir = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let (sender, receiver) = mpsc::channel();
+        let watched = notify::recommended_watcher(sender).and_then(|mut watcher| {
+            watcher.watch(dir, RecursiveMode::NonRecursive)?;
+            Ok(watcher)
+        });
+        match watched {
+            Ok(watcher) => Some((watcher, receiver)),
+            Err(e) => {
+                tracing::warn!(
+                    target: APP,
+                    "Can't watch {} for changes, polling instead: {}",
+                    dir.display(),
+                    e
+                );
+                None
             }
         }
     }
 
-    EmulatorConfig::default()
+    /// Gets a file's modification time, if it exists.
+    fn modified(path: &Path) -> Option<SystemTime> {
+        std::fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
 }
 
 /// Creates a sample configuration file with comments.
@@ -262,6 +1230,27 @@ pub fn create_sample_config() -> String {
     r#"# Chip-8 Emulator Configuration File
 # This file contains all configurable options for the emulator
 
+# Version of the file format, used to upgrade older files
+version = 1
+
+[behavior]
+# Instructions per second
+cpu_speed = 700
+
+# Wrap memory accesses past the end of memory around to the start
+memory_wraparound = false
+
+# Stop on out-of-bounds memory accesses (disable for classic compatibility)
+strict_bounds = true
+
+# Timer frequency in Hz
+timer_frequency = 60
+
+# How the sound timer beeps: "exact", "skip-if-1" (a timer of 1 is silent,
+# as on the COSMAC VIP) or { minimum-duration-ms = 50 } so short beeps
+# can be heard
+sound_timer_mode = "exact"
+
 [graphics]
 # Foreground color for "on" pixels (RGB values)
 foreground_color = { r = 0, g = 255, b = 0, a = 255 }  # Green
@@ -278,6 +1267,35 @@ smooth_scaling = false
 # Maintain aspect ratio when resizing
 maintain_aspect_ratio = true
 
+# Only scale by whole numbers, with bars around the display
+integer_scaling = false
+
+# On-screen display: speed counters and keypad state (F2/F3 toggle them)
+show_fps = false
+show_keypad = false
+
+# Show the latency of the last key press, end to end and by stage
+show_latency = false
+
+# Graph recent frame times by phase (F7 toggles)
+show_frame_times = false
+
+# How frames reach the display: vsync, no-vsync, fifo, mailbox or immediate
+present_mode = "vsync"
+
+# What draws the window: pixels (GPU), or softbuffer (CPU, for broken GPU
+# drivers; needs the softbuffer feature)
+backend = "pixels"
+
+# Performance counters shown on the OSD: instructions, frames,
+# sprites-drawn, collisions, timer-underruns, audio-underruns, cycle-time,
+# input-latency, frame-budget
+osd_counters = []
+
+# GUI theme: classic, green-phosphor, amber, lcd, paper, cosmac or one
+# defined in [[graphics.themes]]; leave out to use the colors above
+# theme = "green-phosphor"
+
 [audio]
 # Buzzer frequency in Hz
 frequency = 440.0
@@ -291,6 +1309,31 @@ sample_rate = 44100
 # Waveform type: "Sine", "Square", "Sawtooth", or "Triangle"
 waveform = "Square"
 
+# Beep pattern: "Continuous", { Pulse = { on_ms = 50.0, off_ms = 50.0 } },
+# or { TimerPitch = { hz_per_tick = 5.0 } }
+pattern = "Continuous"
+
+# Pace emulation by wall-clock time instead of the audio output clock
+wall_clock_pacing = false
+
+# Output backend: "Cpal", or "Rodio" (needs the rodio Cargo feature)
+backend = "Cpal"
+
+[audio.envelope]
+# Envelope timings in milliseconds; short ramps avoid clicks
+attack_ms = 2.0
+decay_ms = 0.0
+sustain_level = 1.0
+release_ms = 5.0
+
+[audio.mixer]
+# Master volume on top of the buzzer volume (M mutes, +/- adjust in the GUI)
+master_volume = 1.0
+muted = false
+
+# Fade length in milliseconds when muting, pausing or resuming
+fade_ms = 10.0
+
 [keyboard]
 # Enable keyboard event capture
 capture_enabled = true
@@ -307,6 +1350,10 @@ repeat_delay = 500
 # Use raw keyboard input (may reduce latency)
 use_raw_input = false
 
+# Keys registered at once: "Unlimited", "Single" (like the original
+# keypad), or { Max = 2 }
+rollover = "Unlimited"
+
 [debug]
 # Enable debug mode
 enabled = false
@@ -316,6 +1363,73 @@ break_on_error = false
 
 # Log CPU instructions to console
 log_instructions = false
+
+# Log writes to code that has already run
+track_self_modifying_code = false
+
+[debug.log_levels]
+# Per-subsystem log levels: off, error, warn, info, debug or trace
+cpu = "info"
+input = "info"
+audio = "info"
+gfx = "info"
+app = "info"
+
+# Windowing, GPU and other libraries
+other = "warn"
+
+[virtual_keypad]
+# Show a clickable keypad over the game (F4 toggles it)
+visible = false
+
+# Key arrangement: "cosmac" (1 2 3 C / 4 5 6 D / ...) or "sequential"
+layout = "cosmac"
+
+# Corner: "top-left", "top-right", "bottom-left" or "bottom-right"
+position = "bottom-right"
+
+# Key size in pixels of the 256x128 window buffer (9-30)
+key_size = 12
+
+[window]
+# Start in fullscreen (F11 toggles it)
+fullscreen = false
+
+# Window size and position in logical pixels. Leave these out to size the
+# window from graphics.scale_factor; the GUI saves them here on exit when
+# started with --config.
+# width = 640
+# height = 320
+# x = 100
+# y = 100
+
+[autosave]
+# Save the running ROM's state periodically, so a session that crashes or
+# is killed can be resumed on the next launch of the same ROM
+enabled = true
+
+# Seconds between auto-saves
+interval_secs = 30
+
+[paths]
+# Directories replacing the platform's (CHIP8_DATA_DIR and CHIP8_CACHE_DIR
+# take precedence). Save states, screenshots and extracted demos go in the
+# data directory, and the ROM browser's analysis cache in the cache directory.
+# data_dir = "/home/me/chip8"
+# cache_dir = "/tmp/chip8-cache"
+
+[kiosk]
+# Seconds without input before 'chip8 kiosk' starts showing demos
+idle_secs = 60
+
+# Seconds each ROM is shown before moving on
+rotate_secs = 30
+
+# ROMs to show, each with an optional replay to play as a demo (a
+# .c8replay file beside the ROM is used if none is given)
+# [[kiosk.playlist]]
+# rom = "roms/pong.ch8"
+# replay = "replays/pong.c8replay"
 "#
     .to_string()
 }
@@ -329,6 +1443,17 @@ impl ConfigProfiles {
         vec!["default", "gaming", "development", "retro"]
     }
 
+    /// Creates the default configuration with a variant's quirks.
+    ///
+    /// Variant names (`chip-48`, `cosmac-vip`, ...) can be given wherever a
+    /// profile name is expected.
+    pub fn for_variant(variant: Variant) -> EmulatorConfig {
+        EmulatorConfig {
+            behavior: EmulatorBehaviorConfig::for_variant(variant),
+            ..EmulatorConfig::default()
+        }
+    }
+
     /// Creates a configuration from a profile name.
     pub fn from_name(name: &str) -> Result<EmulatorConfig, ConfigError> {
         match name.to_lowercase().as_str() {
@@ -336,10 +1461,12 @@ impl ConfigProfiles {
             "gaming" => Ok(EmulatorConfig::gaming()),
             "development" | "dev" => Ok(EmulatorConfig::development()),
             "retro" | "classic" => Ok(EmulatorConfig::retro()),
-            _ => Err(ConfigError::InvalidValue {
-                key: "profile".to_string(),
-                value: name.to_string(),
-            }),
+            other => Variant::from_name(other)
+                .map(Self::for_variant)
+                .ok_or_else(|| ConfigError::InvalidValue {
+                    key: "profile".to_string(),
+                    value: name.to_string(),
+                }),
         }
     }
 
@@ -361,8 +1488,17 @@ pub struct EnvConfig;
 impl EnvConfig {
     /// Loads configuration overrides from environment variables.
     pub fn apply_env_overrides(config: &mut EmulatorConfig) {
+        Self::apply_overrides(config, |name| std::env::var(name).ok());
+    }
+
+    /// Loads configuration overrides from variables looked up by name.
+    ///
+    /// The environment is shared by the whole process, so a host running
+    /// several emulators can give each its own overrides through `lookup`
+    /// instead of setting environment variables.
+    pub fn apply_overrides(config: &mut EmulatorConfig, lookup: impl Fn(&str) -> Option<String>) {
         // Graphics overrides
-        if let Ok(scale) = std::env::var("CHIP8_SCALE") {
+        if let Some(scale) = lookup("CHIP8_SCALE") {
             if let Ok(scale_value) = scale.parse::<u32>() {
                 if scale_value > 0 && scale_value <= 20 {
                     config.graphics.scale_factor = scale_value;
@@ -371,7 +1507,7 @@ impl EnvConfig {
         }
 
         // Audio overrides
-        if let Ok(volume) = std::env::var("CHIP8_VOLUME") {
+        if let Some(volume) = lookup("CHIP8_VOLUME") {
             if let Ok(volume_value) = volume.parse::<f32>() {
                 if (0.0..=1.0).contains(&volume_value) {
                     config.audio.volume = volume_value;
@@ -379,7 +1515,7 @@ impl EnvConfig {
             }
         }
 
-        if let Ok(freq) = std::env::var("CHIP8_FREQUENCY") {
+        if let Some(freq) = lookup("CHIP8_FREQUENCY") {
             if let Ok(freq_value) = freq.parse::<f32>() {
                 if freq_value >= 0.0 {
                     config.audio.frequency = freq_value;
@@ -388,11 +1524,11 @@ impl EnvConfig {
         }
 
         // Debug overrides
-        if std::env::var("CHIP8_DEBUG").is_ok() {
+        if lookup("CHIP8_DEBUG").is_some() {
             config.debug.enabled = true;
         }
 
-        if std::env::var("CHIP8_VERBOSE").is_ok() {
+        if lookup("CHIP8_VERBOSE").is_some() {
             config.debug.log_instructions = true;
         }
     }
@@ -409,9 +1545,113 @@ impl EnvConfig {
     }
 }
 
+/// Settings given on the command line, applied on top of a loaded config.
+///
+/// Each setting is a dotted key and a value, as in
+/// `graphics.scale_factor=14`. The value is read as TOML, falling back to a
+/// plain string, so both `behavior.cpu_speed=1000` and
+/// `graphics.theme=amber` work.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigPatch {
+    /// Keys and values, in the order given.
+    settings: Vec<(String, toml::Value)>,
+}
+
+impl ConfigPatch {
+    /// Creates an empty patch.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses `KEY=VALUE` settings.
+    pub fn parse<S: AsRef<str>>(settings: &[S]) -> Result<Self, ConfigError> {
+        settings.iter().try_fold(Self::new(), |patch, setting| {
+            patch.with_setting(setting.as_ref())
+        })
+    }
+
+    /// Adds a `KEY=VALUE` setting, applied after those already added.
+    pub fn with_setting(mut self, setting: &str) -> Result<Self, ConfigError> {
+        let (key, value) = setting
+            .split_once('=')
+            .ok_or_else(|| ConfigError::InvalidValue {
+                key: setting.to_string(),
+                value: "expected KEY=VALUE".to_string(),
+            })?;
+        let (key, value) = (key.trim(), value.trim());
+        if key.split('.').any(str::is_empty) {
+            return Err(ConfigError::InvalidValue {
+                key: key.to_string(),
+                value: "expected a dotted key like graphics.scale_factor".to_string(),
+            });
+        }
+        let value = toml::from_str::<toml::Table>(&format!("value = {}", value))
+            .ok()
+            .and_then(|mut table| table.remove("value"))
+            .unwrap_or_else(|| toml::Value::String(value.to_string()));
+        self.settings.push((key.to_string(), value));
+        Ok(self)
+    }
+
+    /// Checks if the patch has no settings.
+    pub fn is_empty(&self) -> bool {
+        self.settings.is_empty()
+    }
+
+    /// Applies the settings to a configuration, in order.
+    ///
+    /// Fails on the first key the configuration doesn't have or value of
+    /// the wrong type, naming the key; the configuration is left unchanged.
+    /// Range checks are left to [`EmulatorConfig::validate`].
+    pub fn apply(&self, config: &mut EmulatorConfig) -> Result<(), ConfigError> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let mut table: toml::Table = toml::Value::try_from(&*config)?.try_into()?;
+        let mut patched = config.clone();
+        for (key, value) in &self.settings {
+            let invalid = |reason: &str| ConfigError::InvalidValue {
+                key: key.clone(),
+                value: format!("{} ({})", value, reason),
+            };
+            let mut path: Vec<&str> = key.split('.').collect();
+            let last = path.pop().unwrap_or_default();
+            let mut section = &mut table;
+            for name in path {
+                // Sections that are unset serialize as nothing, so add them
+                let entry = section
+                    .entry(name)
+                    .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+                section = entry
+                    .as_table_mut()
+                    .ok_or_else(|| invalid(&format!("{} isn't a section", name)))?;
+            }
+            section.insert(last.to_string(), value.clone());
+
+            patched = toml::Value::Table(table.clone())
+                .try_into()
+                .map_err(|e: toml::de::Error| invalid(e.message()))?;
+            // Unknown keys are dropped on the way in, so they're missing on
+            // the way out
+            let updated = toml::Value::try_from(&patched)?;
+            let known = key
+                .split('.')
+                .try_fold(&updated, |value, name| value.get(name))
+                .is_some();
+            if !known {
+                return Err(invalid("unknown setting"));
+            }
+        }
+        *config = patched;
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::graphics::Theme;
+    use std::collections::HashMap;
     use tempfile::NamedTempFile;
 
     #[test]
@@ -448,6 +1688,11 @@ mod tests {
         config = EmulatorConfig::default();
         config.audio.volume = 2.0; // Set directly to bypass with_volume clamping
         assert!(config.validate().is_err());
+
+        // Test invalid envelope sustain level
+        config = EmulatorConfig::default();
+        config.audio.envelope.sustain_level = 1.5;
+        assert!(config.validate().is_err());
     }
 
     #[test]
@@ -463,6 +1708,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_theme_serialization() {
+        let mut config = EmulatorConfig::default();
+        config.graphics.theme = Some("night".to_string());
+        config.graphics.themes = vec![Theme::new("night", Color::AMBER, Color::BLACK)
+            .with_border(Color::gray(20))
+            .with_ghosting(0.5)];
+        let toml_str = toml::to_string(&config).unwrap();
+        let deserialized: EmulatorConfig = toml::from_str(&toml_str).unwrap();
+        assert_eq!(deserialized.graphics.theme.as_deref(), Some("night"));
+        assert_eq!(deserialized.graphics.themes, config.graphics.themes);
+        assert!(deserialized.validate().is_ok());
+
+        config.graphics.themes[0].ghosting = 1.0;
+        assert!(config.validate().is_err());
+    }
+
     #[test]
     fn test_config_file_operations() {
         let config = EmulatorConfig::gaming();
@@ -479,6 +1741,102 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_config_migration() {
+        // Files from before versioning could leave out whole sections
+        let mut table: toml::Table =
+            toml::from_str("[graphics]\nscale_factor = 7\n[audio]\nvolume = 0.25\n").unwrap();
+        let applied = migrate_config(&mut table).unwrap();
+        assert_eq!(applied.len(), 1);
+        assert_eq!(table["version"].as_integer(), Some(CONFIG_VERSION as i64));
+        let config: EmulatorConfig = toml::Value::Table(table.clone()).try_into().unwrap();
+        assert_eq!(config.graphics.scale_factor, 7);
+        assert_eq!(config.audio.volume, 0.25);
+        assert_eq!(
+            config.behavior.cpu_speed,
+            EmulatorConfig::default().behavior.cpu_speed
+        );
+        assert!(migrate_config(&mut table).unwrap().is_empty());
+
+        let mut newer: toml::Table = toml::from_str("version = 99").unwrap();
+        assert!(matches!(
+            migrate_config(&mut newer),
+            Err(EmulatorError::ConfigError(
+                ConfigError::UnsupportedVersion { found: 99, .. }
+            ))
+        ));
+        let mut invalid: toml::Table = toml::from_str("version = \"one\"").unwrap();
+        assert!(migrate_config(&mut invalid).is_err());
+    }
+
+    #[test]
+    fn test_load_config_upgrades_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chip8.toml");
+        let old = "[graphics]\nscale_factor = 9\n";
+        std::fs::write(&path, old).unwrap();
+
+        let (config, applied) = read_config(&path).unwrap();
+        assert_eq!(config.graphics.scale_factor, 9);
+        assert!(!applied.is_empty());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), old);
+
+        let config = load_config(&path).unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("chip8.toml.v0.bak")).unwrap(),
+            old
+        );
+        let (upgraded, applied) = read_config(&path).unwrap();
+        assert!(applied.is_empty());
+        assert_eq!(upgraded.graphics.scale_factor, 9);
+    }
+
+    #[test]
+    fn test_config_patch() {
+        let patch = ConfigPatch::parse(&[
+            "graphics.scale_factor = 14",
+            "graphics.theme=amber",
+            "audio.volume=1",
+            "behavior.shift_uses_vy=true",
+            "window.width=800",
+        ])
+        .unwrap();
+        let mut config = EmulatorConfig::default();
+        patch.apply(&mut config).unwrap();
+        assert_eq!(config.graphics.scale_factor, 14);
+        assert_eq!(config.graphics.theme.as_deref(), Some("amber"));
+        assert_eq!(config.audio.volume, 1.0);
+        assert!(config.behavior.shift_uses_vy);
+        assert_eq!(config.window.width, Some(800));
+
+        let error = |setting: &str| {
+            let mut config = EmulatorConfig::default();
+            let patch = ConfigPatch::parse(&[setting])?;
+            patch.apply(&mut config).map(|_| config)
+        };
+        for setting in [
+            "graphics.scale_factor=big",
+            "graphics.no_such_key=1",
+            "graphics.scale_factor.x=1",
+            "graphics..scale_factor=1",
+            "scale_factor",
+        ] {
+            match error(setting) {
+                Err(ConfigError::InvalidValue { key, .. }) => {
+                    assert!(setting.starts_with(&key), "{} named {}", setting, key)
+                }
+                other => panic!("{}: {:?}", setting, other),
+            }
+        }
+
+        // A bad setting leaves the configuration as it was
+        let mut config = EmulatorConfig::default();
+        let patch = ConfigPatch::parse(&["graphics.scale_factor=14", "audio.volume=loud"]).unwrap();
+        assert!(patch.apply(&mut config).is_err());
+        assert_eq!(config.graphics.scale_factor, 10);
+    }
+
     #[test]
     fn test_profile_creation() {
         let gaming = ConfigProfiles::from_name("gaming").unwrap();
@@ -505,6 +1863,12 @@ mod tests {
     #[test]
     fn test_sample_config_generation() {
         let sample = create_sample_config();
+        let value: toml::Value = toml::from_str(&sample).unwrap();
+        let audio: BuzzerConfig = value["audio"].clone().try_into().unwrap();
+        assert!(audio.envelope.is_valid());
+        let parsed = ParsedConfig::parse(&sample).unwrap();
+        assert_eq!(parsed.version, CONFIG_VERSION);
+        assert!(parsed.applied.is_empty());
         assert!(sample.contains("[graphics]"));
         assert!(sample.contains("[audio]"));
         assert!(sample.contains("[keyboard]"));
@@ -595,6 +1959,7 @@ mod tests {
         assert!(development.debug.log_instructions);
         assert_eq!(development.graphics.scale_factor, 8);
         assert!(development.graphics.smooth_scaling);
+        assert!(development.graphics.show_fps && development.graphics.show_keypad);
         assert_eq!(development.audio.volume, 0.2); // Quieter for development
         assert!(development.validate().is_ok());
 
@@ -625,6 +1990,18 @@ mod tests {
         config.graphics.scale_factor = 21; // Invalid
         assert!(config.validate().is_err());
 
+        // Test pixel aspect ratios
+        config = EmulatorConfig::default();
+
+        config.graphics.pixel_aspect_ratio = 1.2; // Valid
+        assert!(config.validate().is_ok());
+
+        config.graphics.pixel_aspect_ratio = 0.0; // Invalid
+        assert!(config.validate().is_err());
+
+        config.graphics.pixel_aspect_ratio = f32::NAN; // Invalid
+        assert!(config.validate().is_err());
+
         // Test boundary values for audio volume
         config = EmulatorConfig::default();
 
@@ -666,6 +2043,15 @@ mod tests {
 
         config.keyboard.polling_rate = 1001; // Invalid
         assert!(config.validate().is_err());
+
+        // Test window geometry
+        config = EmulatorConfig::default();
+
+        config.window.width = Some(1); // Valid minimum
+        assert!(config.validate().is_ok());
+
+        config.window.height = Some(0); // Invalid
+        assert!(config.validate().is_err());
     }
 
     #[test]
@@ -775,6 +2161,235 @@ mod tests {
         assert!(ConfigProfiles::from_name("Development").is_ok());
     }
 
+    #[test]
+    fn test_timing_model_config() {
+        // Files without a timing model keep using cpu_speed
+        let behavior: EmulatorBehaviorConfig = toml::from_str(
+            "cpu_speed = 500\nmemory_wraparound = true\nstrict_bounds = false\ntimer_frequency = 60",
+        )
+        .unwrap();
+        assert_eq!(behavior.timing(), TimingModel::Simple(500));
+
+        let mut config = EmulatorConfig {
+            behavior: EmulatorBehaviorConfig::cosmac_vip(),
+            ..Default::default()
+        };
+        let toml_str = toml::to_string(&config).unwrap();
+        assert!(toml_str.contains("timing_model = \"cosmac-vip\""));
+        let parsed: EmulatorConfig = toml::from_str(&toml_str).unwrap();
+        assert_eq!(parsed.behavior.timing(), TimingModel::CosmacVip);
+
+        config.behavior.timing_model = Some(TimingModel::Simple(0));
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_config_delta() {
+        let old = EmulatorConfig::default();
+        assert!(ConfigDelta::between(&old, &old).is_empty());
+
+        let mut new = old.clone();
+        new.behavior.cpu_speed = 1000;
+        new.audio.volume = 0.9;
+        new.graphics.foreground_color = Color::new(255, 0, 0, 255);
+        let delta = ConfigDelta::between(&old, &new);
+
+        assert_eq!(delta.cpu_speed, Some(1000));
+        assert_eq!(delta.volume, Some(0.9));
+        assert_eq!(delta.foreground_color, Some(Color::new(255, 0, 0, 255)));
+        assert_eq!(delta.background_color, None);
+        assert!(delta.restart_required.is_empty());
+
+        new.graphics.scale_factor = 3;
+        new.behavior.memory_wraparound = !old.behavior.memory_wraparound;
+        let delta = ConfigDelta::between(&old, &new);
+        assert_eq!(delta.restart_required, vec!["behavior", "graphics"]);
+    }
+
+    #[test]
+    fn test_rom_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        let rom = dir.path().join("pong.ch8");
+        assert_eq!(RomOverrides::path_for(&rom), dir.path().join("pong.toml"));
+        assert_eq!(RomOverrides::load_for(&rom).unwrap(), None);
+
+        std::fs::write(
+            dir.path().join("pong.toml"),
+            "cpu_speed = 1000\naddress_masking = \"mask\"\nclip_mode = \"clip\"\n\
+             font = \"dream-6800\"\nmemory_layout = \"xo-chip-64k\"\nforeground_color = { r = 255, g = 0, b = 0, a = 255 }\n",
+        )
+        .unwrap();
+        let overrides = RomOverrides::load_for(&rom).unwrap().unwrap();
+
+        let mut config = EmulatorConfig::classic();
+        config.behavior.timing_model = Some(TimingModel::Simple(500));
+        config.behavior.custom_font = Some(vec![0xFF; 80]);
+        overrides.apply(&mut config);
+        assert_eq!(config.behavior.font_set(), FontSet::Dream6800);
+        assert_eq!(config.behavior.memory_layout, MemoryLayout::XoChip64K);
+        assert_eq!(config.behavior.timing(), TimingModel::Simple(1000));
+        assert_eq!(config.graphics.foreground_color, Color::new(255, 0, 0, 255));
+        assert_eq!(config.behavior.address_masking, AddressMasking::Mask);
+        assert_eq!(config.behavior.clip_mode, ClipMode::Clip);
+        assert_eq!(
+            config.behavior.memory_wraparound,
+            EmulatorConfig::classic().behavior.memory_wraparound
+        );
+
+        std::fs::write(dir.path().join("pong.toml"), "cpu_speed = \"fast\"").unwrap();
+        assert!(RomOverrides::load_for(&rom).is_err());
+    }
+
+    #[test]
+    fn test_rom_overrides_variant_keeps_settings() {
+        let mut config = EmulatorConfig::classic();
+        config.behavior.cpu_speed = 900;
+        config.behavior.hires = HiresMode::Off;
+        config.behavior.custom_font = Some(vec![0xFF; 80]);
+        config.behavior.memory_protection = MemoryProtection::Fault;
+
+        let overrides = RomOverrides {
+            variant: Some(Variant::Chip48),
+            ..Default::default()
+        };
+        overrides.apply(&mut config);
+        assert!(config.behavior.jump_with_vx);
+        assert_eq!(config.behavior.timing(), TimingModel::Simple(900));
+        assert_eq!(config.behavior.hires, HiresMode::Off);
+        assert_eq!(config.behavior.custom_font, Some(vec![0xFF; 80]));
+        assert_eq!(config.behavior.memory_protection, MemoryProtection::Fault);
+
+        // The variant goes first, so an explicit speed replaces its timing model
+        let overrides = RomOverrides {
+            variant: Some(Variant::CosmacVip),
+            cpu_speed: Some(1000),
+            ..Default::default()
+        };
+        overrides.apply(&mut config);
+        assert!(config.behavior.shift_uses_vy && config.behavior.load_store_increments_i);
+        assert!(!config.behavior.jump_with_vx);
+        assert_eq!(config.behavior.sound_timer_mode, SoundTimerMode::SkipIf1);
+        assert_eq!(config.behavior.timing(), TimingModel::Simple(1000));
+        assert_eq!(config.behavior.hires, HiresMode::Off);
+        assert_eq!(config.behavior.custom_font, Some(vec![0xFF; 80]));
+    }
+
+    #[test]
+    fn test_chip48_variant() {
+        let chip48 = EmulatorBehaviorConfig::for_variant(Variant::Chip48);
+        let vip = EmulatorBehaviorConfig::for_variant(Variant::CosmacVip);
+        let schip = EmulatorBehaviorConfig::for_variant(InstructionSet::SuperChip);
+        assert!(chip48.jump_with_vx && !chip48.shift_uses_vy && !chip48.load_store_increments_i);
+        assert!(!vip.jump_with_vx && vip.shift_uses_vy && vip.load_store_increments_i);
+        assert_eq!(vip.timing(), TimingModel::CosmacVip);
+        assert_eq!(
+            (chip48.clip_mode, schip.clip_mode),
+            (ClipMode::Wrap, ClipMode::Clip)
+        );
+
+        // Selected by profile name or from a ROM's overrides
+        let profile = ConfigProfiles::from_name("CHIP-48").unwrap();
+        assert!(profile.behavior.jump_with_vx);
+        let overrides: RomOverrides =
+            toml::from_str("variant = \"chip-48\"\nclip_mode = \"clip\"").unwrap();
+        let mut config = EmulatorConfig::default();
+        overrides.apply(&mut config);
+        assert!(config.behavior.jump_with_vx);
+        assert_eq!(config.behavior.clip_mode, ClipMode::Clip);
+
+        assert_eq!(Variant::from_name("cosmac-vip"), Some(Variant::CosmacVip));
+        let mega = EmulatorBehaviorConfig::for_variant(Variant::MegaChip);
+        assert!(mega.mega_chip && !chip48.mega_chip);
+        assert_eq!(mega.memory_layout, MemoryLayout::XoChip64K);
+        assert!(ConfigProfiles::from_name("chip-49").is_err());
+    }
+
+    #[test]
+    fn test_font_settings() {
+        let config: EmulatorConfig = toml::from_str(
+            &toml::to_string(&EmulatorConfig::default())
+                .unwrap()
+                .replace("font = \"octo\"", "font = \"cosmac-vip\""),
+        )
+        .unwrap();
+        assert_eq!(config.behavior.font_set(), FontSet::CosmacVip);
+
+        let mut config = EmulatorConfig::default();
+        config.behavior.custom_font = Some((0..80).collect());
+        assert!(config.validate().is_ok());
+        assert_eq!(config.behavior.font_set().data()[79], 79);
+
+        config.behavior.custom_font = Some(vec![0xF0; 75]);
+        assert!(config.validate().is_err());
+        assert_eq!(config.behavior.font_set(), FontSet::Octo);
+    }
+
+    #[cfg(feature = "hot-reload")]
+    #[test]
+    fn test_config_watcher() {
+        use std::time::{Duration, SystemTime};
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chip8.toml");
+        let config = EmulatorConfig::default();
+        save_config(&config, &path).unwrap();
+
+        let mut watcher = ConfigWatcher::new(&path, config.clone());
+        assert!(watcher.check_now().unwrap().is_none());
+
+        // Rewrite with a new volume and a clearly newer timestamp
+        let mut changed = config.clone();
+        changed.audio.volume = 0.75;
+        save_config(&changed, &path).unwrap();
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(5))
+            .unwrap();
+
+        let delta = watcher.check_now().unwrap().unwrap();
+        assert_eq!(delta.volume, Some(0.75));
+        assert_eq!(watcher.current().audio.volume, 0.75);
+
+        // Broken files are reported and the last good config is kept
+        std::fs::write(&path, "not = [valid").unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+        assert!(watcher.check_now().is_err());
+        assert_eq!(watcher.current().audio.volume, 0.75);
+        assert!(watcher.check_now().unwrap().is_none());
+    }
+
+    #[cfg(feature = "hot-reload")]
+    #[test]
+    fn test_config_watcher_notices_writes() {
+        use std::time::{Duration, Instant};
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chip8.toml");
+        let config = EmulatorConfig::default();
+        save_config(&config, &path).unwrap();
+
+        let mut watcher = ConfigWatcher::new(&path, config.clone());
+        assert!(watcher.poll().unwrap().is_none());
+
+        // Writes to other files in the directory are ignored
+        std::fs::write(dir.path().join("other.txt"), "unrelated").unwrap();
+
+        let mut changed = config.clone();
+        changed.audio.volume = 0.25;
+        save_config(&changed, &path).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let delta = loop {
+            if let Some(delta) = watcher.poll().unwrap() {
+                break delta;
+            }
+            assert!(Instant::now() < deadline, "no change reported");
+            std::thread::sleep(Duration::from_millis(10));
+        };
+        assert_eq!(delta.volume, Some(0.25));
+        assert_eq!(watcher.current().audio.volume, 0.25);
+    }
+
     #[test]
     fn test_environment_variable_overrides() {
         use std::env;
@@ -820,4 +2435,15 @@ mod tests {
             env::set_var("CHIP8_VOLUME", volume);
         }
     }
+
+    #[test]
+    fn test_lookup_overrides() {
+        let vars = HashMap::from([("CHIP8_SCALE", "4"), ("CHIP8_FREQUENCY", "880")]);
+        let mut config = EmulatorConfig::default();
+        EnvConfig::apply_overrides(&mut config, |name| vars.get(name).map(|v| v.to_string()));
+
+        assert_eq!(config.graphics.scale_factor, 4);
+        assert_eq!(config.audio.frequency, 880.0);
+        assert!(!config.debug.enabled);
+    }
 }
@@ -0,0 +1,465 @@
+//! Environment diagnostics for the Chip-8 emulator.
+//!
+//! This module implements the `chip8 doctor` command, which probes the
+//! host environment (audio, windowing, configuration, data directory and
+//! keyboard support) and reports problems along with suggested fixes.
+
+use std::path::{Path, PathBuf};
+
+use super::config::{read_config, EmulatorConfig};
+use crate::audio::stream::{get_default_device_info, list_audio_devices};
+use crate::error::EmulatorError;
+use crate::graphics::DisplayBackend;
+use crate::hardware::ChipKey;
+use crate::input::mapper::{create_mapper_from_config, get_available_mappers};
+use crate::paths::Paths;
+
+/// Outcome of a single diagnostic check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    /// The check passed.
+    Pass,
+
+    /// The check found a non-fatal problem.
+    Warn,
+
+    /// The check found a problem that will prevent normal operation.
+    Fail,
+}
+
+impl CheckStatus {
+    /// Gets the status marker used in the printed report.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            CheckStatus::Pass => "✅",
+            CheckStatus::Warn => "⚠️ ",
+            CheckStatus::Fail => "❌",
+        }
+    }
+}
+
+/// Result of a single diagnostic check.
+#[derive(Debug, Clone)]
+pub struct DiagnosticCheck {
+    /// Short name of the subsystem that was checked.
+    pub name: String,
+
+    /// Outcome of the check.
+    pub status: CheckStatus,
+
+    /// Human-readable description of what was found.
+    pub message: String,
+
+    /// Suggested fix, if the check did not pass.
+    pub suggestion: Option<String>,
+}
+
+impl DiagnosticCheck {
+    /// Creates a passing check.
+    pub fn pass(name: &str, message: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status: CheckStatus::Pass,
+            message: message.into(),
+            suggestion: None,
+        }
+    }
+
+    /// Creates a warning check with a suggested fix.
+    pub fn warn(name: &str, message: impl Into<String>, suggestion: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status: CheckStatus::Warn,
+            message: message.into(),
+            suggestion: Some(suggestion.into()),
+        }
+    }
+
+    /// Creates a failing check with a suggested fix.
+    pub fn fail(name: &str, message: impl Into<String>, suggestion: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status: CheckStatus::Fail,
+            message: message.into(),
+            suggestion: Some(suggestion.into()),
+        }
+    }
+}
+
+/// Collection of diagnostic check results.
+#[derive(Debug, Clone, Default)]
+pub struct DoctorReport {
+    /// All checks in the order they were run.
+    pub checks: Vec<DiagnosticCheck>,
+}
+
+impl DoctorReport {
+    /// Creates an empty report.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a check result to the report.
+    pub fn push(&mut self, check: DiagnosticCheck) {
+        self.checks.push(check);
+    }
+
+    /// Counts the checks with the given status.
+    pub fn count(&self, status: CheckStatus) -> usize {
+        self.checks.iter().filter(|c| c.status == status).count()
+    }
+
+    /// Checks if any check failed.
+    pub fn has_failures(&self) -> bool {
+        self.count(CheckStatus::Fail) > 0
+    }
+
+    /// Turns failed checks into an error, so `chip8 doctor` exits non-zero.
+    pub fn result(&self) -> Result<(), EmulatorError> {
+        match self.count(CheckStatus::Fail) {
+            0 => Ok(()),
+            failed => Err(EmulatorError::ChecksFailed { failed }),
+        }
+    }
+
+    /// Formats the report for printing.
+    pub fn format(&self) -> String {
+        let mut out = String::from("Chip-8 Environment Diagnostics\n\n");
+
+        for check in &self.checks {
+            out.push_str(&format!(
+                "{} {}: {}\n",
+                check.status.symbol(),
+                check.name,
+                check.message
+            ));
+            if let Some(suggestion) = &check.suggestion {
+                out.push_str(&format!("   → {}\n", suggestion));
+            }
+        }
+
+        out.push_str(&format!(
+            "\nSummary: {} passed, {} warnings, {} failed\n",
+            self.count(CheckStatus::Pass),
+            self.count(CheckStatus::Warn),
+            self.count(CheckStatus::Fail)
+        ));
+
+        out
+    }
+}
+
+/// Runs all diagnostic checks.
+///
+/// # Arguments
+/// * `config_path` - Explicit configuration file to validate, if any
+pub fn run_diagnostics(config_path: Option<&PathBuf>) -> DoctorReport {
+    let mut report = DoctorReport::new();
+
+    report.push(check_audio());
+    report.push(check_window_backend());
+    report.push(check_gpu_backend());
+    report.push(check_config(config_path));
+    report.push(check_data_dir(configured_paths(config_path).data_dir()));
+    report.push(check_keyboard());
+
+    report
+}
+
+/// Probes the audio output devices.
+pub fn check_audio() -> DiagnosticCheck {
+    let name = "Audio";
+
+    match get_default_device_info() {
+        Ok(device) => {
+            let count = list_audio_devices().map(|d| d.len()).unwrap_or(0);
+            DiagnosticCheck::pass(
+                name,
+                format!("default output '{}' ({} device(s) found)", device, count),
+            )
+        }
+        Err(e) => DiagnosticCheck::warn(
+            name,
+            format!("no default output device ({})", e),
+            audio_suggestion(),
+        ),
+    }
+}
+
+fn audio_suggestion() -> &'static str {
+    if cfg!(target_os = "linux") {
+        "Install libasound2 and check that PulseAudio/PipeWire is running; the emulator will run silently otherwise"
+    } else {
+        "Check that an output device is connected and enabled; the emulator will run silently otherwise"
+    }
+}
+
+/// Checks that a windowing system is reachable.
+pub fn check_window_backend() -> DiagnosticCheck {
+    let name = "Window system";
+
+    if !cfg!(target_os = "linux") {
+        return DiagnosticCheck::pass(name, "native windowing available");
+    }
+
+    let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some();
+    let x11 = std::env::var_os("DISPLAY").is_some();
+
+    match (wayland, x11) {
+        (true, _) => DiagnosticCheck::pass(name, "Wayland display detected"),
+        (false, true) => DiagnosticCheck::pass(name, "X11 display detected"),
+        (false, false) => DiagnosticCheck::fail(
+            name,
+            "neither WAYLAND_DISPLAY nor DISPLAY is set",
+            "Run from a graphical session, or use headless subcommands such as 'screenshot'",
+        ),
+    }
+}
+
+/// Checks that at least one GPU adapter is usable by the renderer.
+pub fn check_gpu_backend() -> DiagnosticCheck {
+    use pixels::wgpu;
+
+    let name = "GPU backend";
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+    let adapters: Vec<_> = instance
+        .enumerate_adapters(wgpu::Backends::all())
+        .map(|a| a.get_info())
+        .collect();
+
+    match adapters.first() {
+        Some(info) => DiagnosticCheck::pass(
+            name,
+            format!(
+                "{} via {:?} ({} adapter(s))",
+                info.name,
+                info.backend,
+                adapters.len()
+            ),
+        ),
+        None => DiagnosticCheck::fail(
+            name,
+            "no Vulkan, Metal, DX12 or GL adapter found",
+            if DisplayBackend::Softbuffer.is_available() {
+                "Install up-to-date graphics drivers (or Mesa on Linux), or set graphics.backend = \"softbuffer\" to draw without the GPU"
+            } else {
+                "Install up-to-date graphics drivers (or Mesa on Linux) to enable the GUI"
+            },
+        ),
+    }
+}
+
+/// Validates the configuration file that the GUI would load.
+///
+/// # Arguments
+/// * `config_path` - Explicit configuration file, or None to search the default locations
+pub fn check_config(config_path: Option<&PathBuf>) -> DiagnosticCheck {
+    let name = "Configuration";
+
+    let path = match config_path {
+        Some(path) => {
+            if !path.exists() {
+                return DiagnosticCheck::fail(
+                    name,
+                    format!("{} does not exist", path.display()),
+                    "Check the path passed to --config",
+                );
+            }
+            path.clone()
+        }
+        None => match Paths::new().find_config_file() {
+            Some(path) => path,
+            None => {
+                return DiagnosticCheck::pass(
+                    name,
+                    format!(
+                        "no config file at {}, using defaults",
+                        Paths::new().config_file().display()
+                    ),
+                );
+            }
+        },
+    };
+
+    match read_config(&path) {
+        Ok((_, applied)) if !applied.is_empty() => DiagnosticCheck::warn(
+            name,
+            format!("{} is from an older version", path.display()),
+            "Run the emulator once to upgrade it; the old file is kept as a backup",
+        ),
+        Ok(_) => DiagnosticCheck::pass(name, format!("{} is valid", path.display())),
+        Err(e) => DiagnosticCheck::fail(
+            name,
+            format!("{} is invalid: {}", path.display(), e),
+            "Fix the reported key, or regenerate a sample config and copy your settings across",
+        ),
+    }
+}
+
+/// Gets the directories the GUI would use, with any the config file sets.
+fn configured_paths(config_path: Option<&PathBuf>) -> Paths {
+    let path = config_path
+        .cloned()
+        .or_else(|| Paths::new().find_config_file());
+    match path.and_then(|path| read_config(path).ok()) {
+        Some((config, _)) => Paths::for_config(&config.paths),
+        None => Paths::new(),
+    }
+}
+
+/// Checks that the data directory exists (or can be created) and is writable.
+///
+/// # Arguments
+/// * `dir` - The data directory to probe
+pub fn check_data_dir(dir: &Path) -> DiagnosticCheck {
+    let name = "Data directory";
+
+    if !dir.exists() {
+        let parent = dir
+            .ancestors()
+            .skip(1)
+            .find(|p| p.exists())
+            .unwrap_or_else(|| Path::new("."));
+
+        return if is_writable(parent) {
+            DiagnosticCheck::pass(
+                name,
+                format!("{} will be created on first use", dir.display()),
+            )
+        } else {
+            DiagnosticCheck::fail(
+                name,
+                format!("cannot create {}", dir.display()),
+                format!(
+                    "Make {} writable or set CHIP8_DATA_DIR to a writable location",
+                    parent.display()
+                ),
+            )
+        };
+    }
+
+    if is_writable(dir) {
+        DiagnosticCheck::pass(name, format!("{} is writable", dir.display()))
+    } else {
+        DiagnosticCheck::fail(
+            name,
+            format!("{} is not writable", dir.display()),
+            "Fix the directory permissions or set CHIP8_DATA_DIR to a writable location",
+        )
+    }
+}
+
+/// Tests whether a file can be created in a directory.
+fn is_writable(dir: &Path) -> bool {
+    let probe = dir.join(format!(".chip8-doctor-{}", std::process::id()));
+    match std::fs::write(&probe, b"") {
+        Ok(()) => {
+            let _ = std::fs::remove_file(&probe);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+/// Checks that every built-in key mapper covers the full keypad.
+pub fn check_keyboard() -> DiagnosticCheck {
+    let name = "Keyboard";
+    let mut incomplete = Vec::new();
+
+    for mapper_name in get_available_mappers() {
+        match create_mapper_from_config(mapper_name) {
+            Ok(mapper) => {
+                let mapped: std::collections::HashSet<ChipKey> = mapper
+                    .get_physical_mappings()
+                    .into_iter()
+                    .map(|(_, chip)| chip)
+                    .collect();
+                if mapped.len() < ChipKey::all_keys().len() {
+                    incomplete.push(mapper_name);
+                }
+            }
+            Err(_) => incomplete.push(mapper_name),
+        }
+    }
+
+    if incomplete.is_empty() {
+        let raw = if EmulatorConfig::default().keyboard.use_raw_input {
+            "raw input"
+        } else {
+            "event-based input"
+        };
+        DiagnosticCheck::pass(
+            name,
+            format!(
+                "{} layout(s) available, {}",
+                get_available_mappers().len(),
+                raw
+            ),
+        )
+    } else {
+        DiagnosticCheck::warn(
+            name,
+            format!("incomplete key layouts: {}", incomplete.join(", ")),
+            "Use the 'qwerty' layout or a custom mapping that covers keys 0-F",
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::{NamedTempFile, TempDir};
+
+    #[test]
+    fn test_report_counts() {
+        let mut report = DoctorReport::new();
+        report.push(DiagnosticCheck::pass("a", "ok"));
+        report.push(DiagnosticCheck::warn("b", "meh", "fix b"));
+        assert!(report.result().is_ok());
+        report.push(DiagnosticCheck::fail("c", "bad", "fix c"));
+
+        assert_eq!(report.count(CheckStatus::Pass), 1);
+        assert_eq!(report.count(CheckStatus::Warn), 1);
+        assert!(report.has_failures());
+        assert!(matches!(
+            report.result(),
+            Err(EmulatorError::ChecksFailed { failed: 1 })
+        ));
+
+        let text = report.format();
+        assert!(text.contains("fix c"));
+        assert!(text.contains("1 passed, 1 warnings, 1 failed"));
+    }
+
+    #[test]
+    fn test_config_check() {
+        let missing = PathBuf::from("definitely_missing_chip8.toml");
+        assert_eq!(check_config(Some(&missing)).status, CheckStatus::Fail);
+
+        let bad = NamedTempFile::new().unwrap();
+        std::fs::write(bad.path(), "graphics = 12").unwrap();
+        let bad_path = bad.path().to_path_buf();
+        assert_eq!(check_config(Some(&bad_path)).status, CheckStatus::Fail);
+
+        let good = NamedTempFile::new().unwrap();
+        crate::frontend::save_config(&EmulatorConfig::default(), good.path()).unwrap();
+        let good_path = good.path().to_path_buf();
+        assert_eq!(check_config(Some(&good_path)).status, CheckStatus::Pass);
+    }
+
+    #[test]
+    fn test_data_dir_check() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(check_data_dir(dir.path()).status, CheckStatus::Pass);
+
+        let nested = dir.path().join("sub/chip8");
+        let check = check_data_dir(&nested);
+        assert_eq!(check.status, CheckStatus::Pass);
+        assert!(check.message.contains("will be created"));
+    }
+
+    #[test]
+    fn test_keyboard_check() {
+        assert_eq!(check_keyboard().status, CheckStatus::Pass);
+    }
+}
@@ -0,0 +1,584 @@
+//! Frame-stepped dumps of a run for encoding pipelines.
+//!
+//! A dump runs a ROM without a window, one 60Hz frame at a time, as fast as
+//! the host allows: the same frames a replay runs, with keys from a replay
+//! file or none at all. The screen after each frame is written as Y4M
+//! video, to a file or to stdout for piping into an encoder:
+//!
+//! ```text
+//! chip8 run game.ch8 --replay run.c8replay --dump-video - | ffmpeg -i - game.mp4
+//! ```
+//!
+//! The buzzer can be dumped alongside as raw PCM, rendered offline from the
+//! sound state of every CPU cycle so each frame gets exactly its share of
+//! samples. An [`AudioManifest`] written next to it records the sample
+//! format and where each frame starts, for muxing the two in sync.
+
+use std::cell::RefCell;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+
+use super::gui::{load_configuration, rom_config, start_rom, RunOptions};
+use super::{ConfigPatch, EmulatorConfig, FrontendResult, SimpleEmulator};
+use crate::audio::{AudioBuzzer, BuzzerConfig, Mixer};
+use crate::emulator::TIMER_FREQUENCY;
+use crate::error::EmulatorError;
+use crate::graphics::y4m::{scale_nearest, Y4mWriter};
+use crate::graphics::{GraphicsConfig, PixelRenderer};
+use crate::hardware::audio::SoftwareAudio;
+use crate::hardware::display::SoftwareDisplay;
+use crate::hardware::{Audio, AudioResult};
+use crate::patch::RomPatch;
+use crate::replay::{Replay, ReplayMode};
+
+/// Frames dumped without a replay or a frame count: ten seconds.
+pub const DEFAULT_DUMP_FRAMES: u64 = 600;
+
+/// Sample format of dumped audio, as ffmpeg names it.
+pub const PCM_FORMAT: &str = "s16le";
+
+/// What a dump writes.
+#[derive(Debug, Clone)]
+pub struct DumpOptions {
+    /// File to write Y4M video to, or `-` for stdout.
+    pub video: Option<PathBuf>,
+
+    /// Factor the screen is scaled up by before it is written.
+    pub scale: u32,
+
+    /// File to write the buzzer to as raw PCM.
+    pub audio: Option<PathBuf>,
+
+    /// Frames to dump, if not the whole replay or [`DEFAULT_DUMP_FRAMES`].
+    pub frames: Option<u64>,
+}
+
+impl Default for DumpOptions {
+    fn default() -> Self {
+        Self {
+            video: None,
+            scale: 1,
+            audio: None,
+            frames: None,
+        }
+    }
+}
+
+/// Writes the screen after each frame as Y4M video.
+///
+/// The video's size is set by the first frame, at the renderer's scale.
+/// If the program changes resolution later, its frames are scaled to fit.
+pub struct VideoDump<W: Write> {
+    renderer: PixelRenderer,
+    writer: Option<W>,
+    stream: Option<Y4mWriter<W>>,
+}
+
+impl<W: Write> VideoDump<W> {
+    /// Creates a dump that draws frames with `graphics`' colors, scale and
+    /// orientation.
+    pub fn new(writer: W, graphics: GraphicsConfig) -> FrontendResult<Self> {
+        Ok(Self {
+            renderer: PixelRenderer::new(graphics)?,
+            writer: Some(writer),
+            stream: None,
+        })
+    }
+
+    /// Writes a frame of the screen.
+    ///
+    /// # Arguments
+    /// * `pixels` - Display pixels in row-major order
+    /// * `size` - Display resolution as (width, height)
+    pub fn write_frame(&mut self, pixels: &[bool], size: (usize, usize)) -> FrontendResult<()> {
+        if self.renderer.display_size() != size {
+            self.renderer.resize(size.0, size.1)?;
+        }
+        self.renderer.render(pixels)?;
+        let (width, height) = self.renderer.frame_size();
+        let frame_size = (width as usize, height as usize);
+        let stream = match (&mut self.stream, self.writer.take()) {
+            (Some(stream), _) => stream,
+            (None, Some(writer)) => {
+                self.stream
+                    .insert(Y4mWriter::new(writer, frame_size.0, frame_size.1)?)
+            }
+            (None, None) => unreachable!("the writer moves into the stream"),
+        };
+        if stream.size() == frame_size {
+            stream.write_frame(self.renderer.frame_buffer())?;
+        } else {
+            let frame = scale_nearest(self.renderer.frame_buffer(), frame_size, stream.size());
+            stream.write_frame(&frame)?;
+        }
+        Ok(())
+    }
+
+    /// Gets the number of frames written.
+    pub fn frames(&self) -> u64 {
+        self.stream.as_ref().map_or(0, Y4mWriter::frames)
+    }
+
+    /// Flushes the video.
+    pub fn finish(self) -> io::Result<()> {
+        match self.stream {
+            Some(stream) => stream.finish().map(drop),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Sound state after a CPU cycle: the sound timer and whether the tone is on.
+type CycleSound = (u8, bool);
+
+/// Audio output that logs the sound state of every CPU cycle for an
+/// [`AudioDump`].
+struct SoundLog {
+    audio: SoftwareAudio,
+    timer: u8,
+    cycles: Rc<RefCell<Vec<CycleSound>>>,
+}
+
+impl SoundLog {
+    fn log(&mut self) {
+        let sound = (self.timer, self.audio.is_playing());
+        self.cycles.borrow_mut().push(sound);
+    }
+}
+
+impl Audio for SoundLog {
+    fn play_beep(&mut self) -> AudioResult<()> {
+        self.audio.play_beep()?;
+        self.log();
+        Ok(())
+    }
+
+    fn stop_beep(&mut self) -> AudioResult<()> {
+        self.audio.stop_beep()?;
+        self.log();
+        Ok(())
+    }
+
+    fn is_playing(&self) -> bool {
+        self.audio.is_playing()
+    }
+
+    fn set_volume(&mut self, volume: f32) -> AudioResult<()> {
+        self.audio.set_volume(volume)
+    }
+
+    fn get_volume(&self) -> f32 {
+        self.audio.get_volume()
+    }
+
+    fn set_frequency(&mut self, frequency: f32) -> AudioResult<()> {
+        self.audio.set_frequency(frequency)
+    }
+
+    fn get_frequency(&self) -> f32 {
+        self.audio.get_frequency()
+    }
+
+    fn set_sound_timer(&mut self, value: u8) -> AudioResult<()> {
+        self.timer = value;
+        Ok(())
+    }
+}
+
+/// Writes the buzzer as raw PCM, signed 16-bit little-endian mono.
+///
+/// The CPU plays into [`AudioDump::audio`], and each frame renders the
+/// cycles it ran through the configured buzzer and mixer, spread evenly
+/// across the frame's samples. Frame `n` starts at sample
+/// `n * sample_rate / 60`, so the audio never drifts from the video.
+pub struct AudioDump<W: Write> {
+    writer: W,
+    buzzer: AudioBuzzer,
+    mixer: Mixer,
+    cycles: Rc<RefCell<Vec<CycleSound>>>,
+    frames: u64,
+    samples: u64,
+}
+
+impl<W: Write> AudioDump<W> {
+    /// Creates a dump that renders the buzzer with `config`'s tone, envelope,
+    /// mixer and sample rate.
+    pub fn new(writer: W, config: &BuzzerConfig) -> FrontendResult<Self> {
+        let config = config.clone().with_sample_rate(config.sample_rate.max(1));
+        Ok(Self {
+            writer,
+            mixer: Mixer::new(config.mixer, config.sample_rate),
+            buzzer: AudioBuzzer::new(config)?,
+            cycles: Rc::default(),
+            frames: 0,
+            samples: 0,
+        })
+    }
+
+    /// Creates the audio output to give the CPU, which logs what the dump
+    /// renders.
+    pub fn audio(&self) -> Box<dyn Audio> {
+        Box::new(SoundLog {
+            audio: SoftwareAudio::new(),
+            timer: 0,
+            cycles: Rc::clone(&self.cycles),
+        })
+    }
+
+    /// Gets the sample rate in Hz.
+    pub fn sample_rate(&self) -> u32 {
+        self.buzzer.config().sample_rate
+    }
+
+    /// Gets the sample a frame starts at.
+    pub fn frame_offset(&self, frame: u64) -> u64 {
+        frame * u64::from(self.sample_rate()) / u64::from(TIMER_FREQUENCY)
+    }
+
+    /// Renders and writes the frame the CPU has just run.
+    pub fn write_frame(&mut self) -> io::Result<()> {
+        let cycles = std::mem::take(&mut *self.cycles.borrow_mut());
+        let count = self.frame_offset(self.frames + 1) - self.frame_offset(self.frames);
+        if cycles.is_empty() {
+            // Nothing ran, so the tone carries on as it was
+            self.render(count)?;
+        }
+        let mut rendered = 0;
+        for (i, &(timer, playing)) in cycles.iter().enumerate() {
+            // Same order as the CPU: report the timer, then gate the tone on it
+            self.buzzer.set_sound_timer(timer);
+            let _ = if playing {
+                self.buzzer.start_tone()
+            } else {
+                self.buzzer.stop_tone()
+            };
+            let end = count * (i as u64 + 1) / cycles.len() as u64;
+            self.render(end - rendered)?;
+            rendered = end;
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Renders samples from the buzzer's current state and writes them.
+    fn render(&mut self, count: u64) -> io::Result<()> {
+        let sample_rate = self.sample_rate();
+        let mut bytes = Vec::with_capacity(count as usize * 2);
+        for sample in self.buzzer.render_samples(count as usize, sample_rate) {
+            let sample = self.mixer.process(sample).clamp(-1.0, 1.0);
+            bytes.extend_from_slice(&((sample * i16::MAX as f32).round() as i16).to_le_bytes());
+        }
+        self.samples += count;
+        self.writer.write_all(&bytes)
+    }
+
+    /// Gets the number of frames written.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Gets the number of samples written.
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+
+    /// Describes the audio written so far.
+    ///
+    /// # Arguments
+    /// * `audio` - Where the audio is written
+    /// * `video` - Where the matching video is written, if anywhere
+    pub fn manifest(&self, audio: &Path, video: Option<&Path>) -> AudioManifest {
+        let sample_rate = self.sample_rate();
+        let input = format!("-f s16le -ar {} -ac 1 -i {}", sample_rate, audio.display());
+        let ffmpeg = match video {
+            Some(video) => format!(
+                "ffmpeg -i {} {} -c:v libx264 -c:a aac -shortest out.mp4",
+                video.display(),
+                input
+            ),
+            None => format!("ffmpeg {} out.wav", input),
+        };
+        AudioManifest {
+            format: PCM_FORMAT.to_string(),
+            sample_rate,
+            channels: 1,
+            frame_rate: TIMER_FREQUENCY,
+            frames: self.frames,
+            samples: self.samples,
+            frame_offsets: (0..self.frames)
+                .map(|frame| self.frame_offset(frame))
+                .collect(),
+            ffmpeg,
+        }
+    }
+
+    /// Flushes the audio and gives back the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Description of a raw PCM audio dump, saved as JSON beside it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AudioManifest {
+    /// Sample format, as ffmpeg's `-f` names it.
+    pub format: String,
+
+    /// Samples per second.
+    pub sample_rate: u32,
+
+    /// Interleaved channels.
+    pub channels: u16,
+
+    /// Frames per second of the matching video.
+    pub frame_rate: u32,
+
+    /// Frames dumped.
+    pub frames: u64,
+
+    /// Samples dumped per channel.
+    pub samples: u64,
+
+    /// The sample each frame starts at.
+    pub frame_offsets: Vec<u64>,
+
+    /// An ffmpeg command that muxes or converts the dump.
+    pub ffmpeg: String,
+}
+
+impl AudioManifest {
+    /// Gets where the manifest for an audio dump goes: the dump's path
+    /// with `.json` appended.
+    pub fn path_for(audio: &Path) -> PathBuf {
+        let mut path = OsString::from(audio);
+        path.push(".json");
+        PathBuf::from(path)
+    }
+
+    /// Saves the manifest as JSON.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)? + "\n")
+    }
+}
+
+/// Runs frames and writes each one to the dumps.
+///
+/// The emulator must be driven by a replay, recording or playing, and play
+/// into the audio dump's [`AudioDump::audio`] if there is one.
+///
+/// # Returns
+/// The number of frames run, which is less than `frames` if a replay ends.
+pub fn dump_frames<W: Write, A: Write>(
+    emulator: &mut SimpleEmulator,
+    frames: u64,
+    video: &mut Option<VideoDump<W>>,
+    audio: &mut Option<AudioDump<A>>,
+) -> FrontendResult<u64> {
+    for frame in 0..frames {
+        if !emulator.run_replay_frame()? {
+            return Ok(frame);
+        }
+        if let Some(video) = video {
+            video.write_frame(emulator.get_display_buffer(), emulator.display_size())?;
+        }
+        if let Some(audio) = audio {
+            audio.write_frame()?;
+        }
+    }
+    Ok(frames)
+}
+
+/// Runs a ROM without a window and dumps its frames.
+///
+/// A replay in `options` supplies the keys, settings and, unless `dump`
+/// says otherwise, the number of frames; without one the ROM runs with no
+/// keys held. Status is printed to stderr, so stdout can carry the video.
+/// Audio is written to a file, with its [`AudioManifest`] beside it.
+pub fn run_dump(
+    rom_file: PathBuf,
+    config_path: Option<&PathBuf>,
+    profile_name: Option<&String>,
+    settings: &ConfigPatch,
+    mut options: RunOptions,
+    dump: DumpOptions,
+) -> Result<(), EmulatorError> {
+    if dump.audio.as_deref() == Some(Path::new("-")) {
+        return Err(invalid_input("audio can't be dumped to stdout"));
+    }
+    if let Some(ReplayMode::Record(_)) = options.replay_mode {
+        return Err(invalid_input("a dump can't record a replay"));
+    }
+    if options.replay_mode.is_some() {
+        // Cheats would desync the replay
+        if !options.cheats.is_empty() {
+            return Err(invalid_input("cheats can't be used with replays"));
+        }
+        options.no_cheats = true;
+    }
+    let config = load_configuration(config_path, profile_name, settings)?;
+    let patch = options.patch.as_ref().map(RomPatch::load).transpose()?;
+
+    let mut emulator = SimpleEmulator::new_with_config(&config);
+    emulator
+        .cpu_mut()
+        .set_display(Box::new(SoftwareDisplay::new()));
+    let rom_data = start_rom(&mut emulator, &rom_file, &config, &options, patch.as_ref())?;
+    let frames = match &options.replay_mode {
+        Some(ReplayMode::Play(path)) => {
+            let replay = Replay::load(path)?;
+            let frames = dump.frames.unwrap_or(u64::from(replay.frames));
+            emulator.play_replay(replay, &rom_data)?;
+            frames
+        }
+        _ => {
+            let behavior = rom_config(&rom_file, &config)?.behavior;
+            emulator.record_replay(&rom_data, &behavior, 0)?;
+            dump.frames.unwrap_or(DEFAULT_DUMP_FRAMES)
+        }
+    };
+
+    let mut video = match &dump.video {
+        Some(path) => Some(VideoDump::new(
+            open_output(path)?,
+            video_graphics(&config, dump.scale),
+        )?),
+        None => None,
+    };
+    let mut audio = match &dump.audio {
+        Some(path) => Some(AudioDump::new(open_output(path)?, &config.audio)?),
+        None => None,
+    };
+    if let Some(audio) = &audio {
+        emulator.cpu_mut().set_audio(audio.audio());
+    }
+    let ran = dump_frames(&mut emulator, frames, &mut video, &mut audio)?;
+    if let (Some(video), Some(path)) = (video, &dump.video) {
+        video.finish()?;
+        eprintln!("✅ Wrote {} frames of video to {}", ran, path.display());
+    }
+    if let (Some(audio), Some(path)) = (audio, &dump.audio) {
+        let manifest = audio.manifest(path, dump.video.as_deref());
+        audio.finish()?;
+        let manifest_path = AudioManifest::path_for(path);
+        manifest.save(&manifest_path)?;
+        eprintln!(
+            "✅ Wrote {} frames of audio to {} (manifest: {})",
+            ran,
+            path.display(),
+            manifest_path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Gets the graphics settings frames are drawn with: the configured colors
+/// and orientation, scaled by `scale`.
+fn video_graphics(config: &EmulatorConfig, scale: u32) -> GraphicsConfig {
+    config.graphics.clone().with_scale_factor(scale.max(1))
+}
+
+/// Opens a dump file for writing, or stdout for `-`.
+fn open_output(path: &Path) -> io::Result<Box<dyn Write>> {
+    if path == Path::new("-") {
+        Ok(Box::new(BufWriter::new(io::stdout().lock())))
+    } else {
+        Ok(Box::new(BufWriter::new(File::create(path)?)))
+    }
+}
+
+/// Creates the error for an option the dump can't honor.
+fn invalid_input(message: &str) -> EmulatorError {
+    io::Error::new(io::ErrorKind::InvalidInput, message.to_string()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::Envelope;
+
+    #[test]
+    fn test_dump_frames_writes_video() {
+        // Draw the 0 glyph, then spin
+        let rom = [0x00, 0xE0, 0x60, 0x00, 0xF0, 0x29, 0xD0, 0x05, 0x12, 0x08];
+        let mut emulator = SimpleEmulator::new();
+        emulator
+            .cpu_mut()
+            .set_display(Box::new(SoftwareDisplay::new()));
+        emulator
+            .record_replay(&rom, &EmulatorConfig::default().behavior, 0)
+            .unwrap();
+
+        let graphics = GraphicsConfig::new().with_scale_factor(2);
+        let mut video = Some(VideoDump::new(Vec::new(), graphics).unwrap());
+        let mut audio = None::<AudioDump<Vec<u8>>>;
+        assert_eq!(
+            dump_frames(&mut emulator, 3, &mut video, &mut audio).unwrap(),
+            3
+        );
+
+        let video = video.unwrap();
+        assert_eq!(video.frames(), 3);
+        let stream = video.stream.unwrap().finish().unwrap();
+        let header = b"YUV4MPEG2 W128 H64 F60:1 Ip A1:1 C444\n";
+        assert_eq!(&stream[..header.len()], header);
+        let frame_bytes = 6 + 128 * 64 * 3;
+        assert_eq!(stream.len(), header.len() + 3 * frame_bytes);
+        // The glyph's top-left pixel is lit, two pixels wide
+        let luma = &stream[header.len() + 6..];
+        assert_eq!(&luma[..3], [235, 235, 235]);
+    }
+
+    #[test]
+    fn test_audio_dump_follows_sound_timer() {
+        // Beep for 6 ticks, then spin
+        let rom = [0x60, 0x06, 0xF0, 0x18, 0x12, 0x04];
+        let mut emulator = SimpleEmulator::new();
+        emulator
+            .record_replay(&rom, &EmulatorConfig::default().behavior, 0)
+            .unwrap();
+
+        let config = BuzzerConfig::new()
+            .with_volume(1.0)
+            .with_envelope(Envelope::none())
+            .with_sample_rate(48_000);
+        let mut audio = Some(AudioDump::new(Vec::new(), &config).unwrap());
+        emulator
+            .cpu_mut()
+            .set_audio(audio.as_ref().unwrap().audio());
+        let mut video = None::<VideoDump<Vec<u8>>>;
+        assert_eq!(
+            dump_frames(&mut emulator, 8, &mut video, &mut audio).unwrap(),
+            8
+        );
+
+        // 800 samples a frame; the beep starts a few cycles into the first
+        // and the timer runs out after the sixth
+        let audio = audio.unwrap();
+        let manifest = audio.manifest(Path::new("out.pcm"), Some(Path::new("out.y4m")));
+        assert_eq!(manifest.samples, 6400);
+        assert_eq!(manifest.frame_offsets[..3], [0, 800, 1600]);
+        assert!(manifest
+            .ffmpeg
+            .starts_with("ffmpeg -i out.y4m -f s16le -ar 48000"));
+        let bytes = audio.finish().unwrap();
+        let samples: Vec<i16> = bytes
+            .chunks_exact(2)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        assert_eq!(samples.len(), 6400);
+        assert_eq!(samples[0], 0);
+        assert!(samples[799] != 0);
+        assert!(samples[800..4800].iter().all(|&s| s != 0));
+        assert!(samples[4800..].iter().all(|&s| s == 0));
+        assert_eq!(
+            AudioManifest::path_for(Path::new("out.pcm")),
+            Path::new("out.pcm.json")
+        );
+    }
+}
@@ -0,0 +1,451 @@
+//! Several ROMs running side by side in one window.
+//!
+//! A [`Gallery`] tiles 4 to 16 emulators in a grid, each running its own
+//! ROM at a reduced speed with the sound off, for exhibitions and kiosks.
+//! Focusing a tile pauses the others and gives it the whole window, full
+//! speed, its sound and the keyboard until it is left again.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::{EmulatorBuilder, EmulatorConfig, FrontendResult, SimpleEmulator};
+use crate::audio::AudioBus;
+use crate::error::{ConfigError, EmulatorError};
+use crate::graphics::Theme;
+use crate::hardware::input::{Input, SoftwareInput};
+use crate::hardware::{Audio, Hardware};
+use crate::logging::APP;
+
+/// Fewest tiles shown; fewer ROMs are repeated to fill them.
+pub const MIN_TILES: usize = 4;
+
+/// Most tiles shown; ROMs past this are left out.
+pub const MAX_TILES: usize = 16;
+
+/// Default speed of the tiles relative to normal while none is focused.
+pub const DEFAULT_GALLERY_SPEED: f32 = 0.5;
+
+/// Default time without input before a focused tile goes back to the grid.
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Canvas pixels between and around the tiles.
+const TILE_GAP: usize = 8;
+
+/// How a gallery runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GalleryOptions {
+    /// Speed of the tiles relative to normal while none is focused.
+    pub speed: f32,
+
+    /// Time without input before a focused tile goes back to the grid.
+    pub idle_timeout: Option<Duration>,
+}
+
+impl Default for GalleryOptions {
+    fn default() -> Self {
+        Self {
+            speed: DEFAULT_GALLERY_SPEED,
+            idle_timeout: Some(DEFAULT_IDLE_TIMEOUT),
+        }
+    }
+}
+
+/// Where the tiles go on the canvas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GalleryLayout {
+    /// Number of tiles across.
+    pub columns: usize,
+
+    /// Number of tiles down.
+    pub rows: usize,
+
+    /// Size of the canvas the tiles are drawn on.
+    pub canvas: (usize, usize),
+}
+
+impl GalleryLayout {
+    /// Lays out a number of tiles in the squarest grid that holds them.
+    pub fn new(tiles: usize, canvas: (usize, usize)) -> Self {
+        let tiles = tiles.clamp(1, MAX_TILES);
+        let columns = (1..=tiles).find(|c| c * c >= tiles).unwrap_or(1);
+        Self {
+            columns,
+            rows: tiles.div_ceil(columns),
+            canvas,
+        }
+    }
+
+    /// Gets a tile's position and size as (x, y, width, height).
+    ///
+    /// Tiles keep the 2:1 shape of the Chip-8 display and are centered in
+    /// their cell.
+    ///
+    /// # Returns
+    /// The rectangle, or `None` if the grid has no cell `index`.
+    pub fn tile_rect(&self, index: usize) -> Option<(usize, usize, usize, usize)> {
+        if index >= self.columns * self.rows {
+            return None;
+        }
+        let cell_width = self.canvas.0.saturating_sub(TILE_GAP * (self.columns + 1)) / self.columns;
+        let cell_height = self.canvas.1.saturating_sub(TILE_GAP * (self.rows + 1)) / self.rows;
+        let width = cell_width.min(cell_height * 2);
+        let height = width / 2;
+        let (column, row) = (index % self.columns, index / self.columns);
+        let x = TILE_GAP + column * (cell_width + TILE_GAP) + (cell_width - width) / 2;
+        let y = TILE_GAP + row * (cell_height + TILE_GAP) + (cell_height - height) / 2;
+        Some((x, y, width, height))
+    }
+
+    /// Finds the cell under a canvas position.
+    pub fn tile_at(&self, (x, y): (usize, usize)) -> Option<usize> {
+        (0..self.columns * self.rows).find(|&index| {
+            self.tile_rect(index)
+                .is_some_and(|(left, top, width, height)| {
+                    (left..left + width).contains(&x) && (top..top + height).contains(&y)
+                })
+        })
+    }
+}
+
+/// One emulator in the gallery.
+pub struct GalleryTile {
+    /// Name shown when the tile is focused.
+    name: String,
+
+    /// The emulator running the tile's ROM.
+    emulator: SimpleEmulator,
+
+    /// Keys the tile reads, fed from the keyboard while it is focused.
+    input: Arc<Mutex<SoftwareInput>>,
+
+    /// Speed the tile runs at when focused.
+    full_cps: u32,
+
+    /// Error that stopped the tile, if one did.
+    fault: Option<String>,
+}
+
+impl GalleryTile {
+    /// Gets the tile's name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the tile's emulator.
+    pub fn emulator(&self) -> &SimpleEmulator {
+        &self.emulator
+    }
+
+    /// Gets the error that stopped the tile, if one did.
+    pub fn fault(&self) -> Option<&str> {
+        self.fault.as_deref()
+    }
+
+    /// Mutes or unmutes the tile's sound.
+    fn set_muted(&mut self, muted: bool) {
+        if let Err(e) = self
+            .emulator
+            .cpu_mut()
+            .hardware_mut()
+            .audio()
+            .set_muted(muted)
+        {
+            tracing::warn!(target: APP, "Failed to mute {}: {}", self.name, e);
+        }
+    }
+}
+
+/// A grid of emulators, one of which can be focused.
+pub struct Gallery {
+    /// The tiles, in grid order.
+    tiles: Vec<GalleryTile>,
+
+    /// Where the tiles go on the canvas.
+    layout: GalleryLayout,
+
+    /// Index of the focused tile.
+    focused: Option<usize>,
+
+    /// Speed of the tiles relative to normal while none is focused.
+    speed: f32,
+}
+
+impl Gallery {
+    /// Creates a gallery of named ROMs.
+    ///
+    /// ROMs are repeated to fill [`MIN_TILES`] tiles and left out past
+    /// [`MAX_TILES`]. With a `bus`, each tile gets a muted voice on it.
+    ///
+    /// # Arguments
+    /// * `roms` - Name and data of each ROM
+    /// * `config` - Configuration every tile is created with
+    /// * `speed` - Speed of the tiles while none is focused, above 0 and at most 1
+    /// * `canvas` - Size of the canvas the grid is drawn on
+    /// * `bus` - Shared audio output, if there is one
+    pub fn new(
+        roms: &[(String, Vec<u8>)],
+        config: &EmulatorConfig,
+        speed: f32,
+        canvas: (usize, usize),
+        bus: Option<&AudioBus>,
+    ) -> FrontendResult<Self> {
+        if roms.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "the gallery needs at least one ROM",
+            )
+            .into());
+        }
+        if !(speed > 0.0 && speed <= 1.0) {
+            return Err(EmulatorError::ConfigError(ConfigError::InvalidValue {
+                key: "speed".to_string(),
+                value: format!("{} (expected above 0 and at most 1)", speed),
+            }));
+        }
+        if roms.len() > MAX_TILES {
+            tracing::warn!(target: APP, "Showing the first {} of {} ROMs", MAX_TILES, roms.len());
+        }
+
+        let count = roms.len().clamp(MIN_TILES, MAX_TILES);
+        let mut tiles = Vec::with_capacity(count);
+        for (name, data) in roms.iter().cycle().take(count) {
+            let input = Arc::new(Mutex::new(SoftwareInput::new()));
+            let mut builder = EmulatorBuilder::new()
+                .config(config.clone())
+                .input(input.clone())
+                .rom_bytes(data);
+            if let Some(bus) = bus {
+                let mut voice = bus.voice(config.audio.clone())?;
+                voice.set_muted(true)?;
+                builder = builder.audio(voice);
+            }
+            let mut emulator = builder.build()?;
+            let full_cps = emulator.target_cps();
+            emulator.set_target_cps(reduced_cps(full_cps, speed));
+            tiles.push(GalleryTile {
+                name: name.clone(),
+                emulator,
+                input,
+                full_cps,
+                fault: None,
+            });
+        }
+
+        Ok(Self {
+            layout: GalleryLayout::new(tiles.len(), canvas),
+            tiles,
+            focused: None,
+            speed,
+        })
+    }
+
+    /// Gets the tiles in grid order.
+    pub fn tiles(&self) -> &[GalleryTile] {
+        &self.tiles
+    }
+
+    /// Gets where the tiles go on the canvas.
+    pub fn layout(&self) -> &GalleryLayout {
+        &self.layout
+    }
+
+    /// Finds the tile under a canvas position.
+    pub fn tile_at(&self, position: (usize, usize)) -> Option<usize> {
+        self.layout
+            .tile_at(position)
+            .filter(|&index| index < self.tiles.len())
+    }
+
+    /// Gets the focused tile.
+    pub fn focused(&self) -> Option<&GalleryTile> {
+        self.focused.map(|index| &self.tiles[index])
+    }
+
+    /// Gets the keys of the focused tile, for feeding from the keyboard.
+    pub fn focused_input(&self) -> Option<Arc<Mutex<SoftwareInput>>> {
+        self.focused().map(|tile| tile.input.clone())
+    }
+
+    /// Focuses a tile: it runs at full speed with sound and the others pause.
+    ///
+    /// # Returns
+    /// `false` if there is no such tile.
+    pub fn focus(&mut self, index: usize) -> bool {
+        if index >= self.tiles.len() {
+            return false;
+        }
+        self.unfocus();
+        for (i, tile) in self.tiles.iter_mut().enumerate() {
+            if i == index {
+                tile.emulator.set_target_cps(tile.full_cps);
+                tile.set_muted(false);
+            } else {
+                tile.emulator.pause();
+            }
+        }
+        self.focused = Some(index);
+        true
+    }
+
+    /// Goes back to the grid, with every tile running at the reduced speed.
+    pub fn unfocus(&mut self) {
+        let Some(index) = self.focused.take() else {
+            return;
+        };
+        let focused = &mut self.tiles[index];
+        focused.input.lock().unwrap().release_all_keys();
+        focused.set_muted(true);
+        for tile in &mut self.tiles {
+            tile.emulator
+                .set_target_cps(reduced_cps(tile.full_cps, self.speed));
+            tile.emulator.resume();
+        }
+    }
+
+    /// Runs one timer frame of every tile that isn't paused or stopped.
+    ///
+    /// A tile that faults is stopped and keeps showing its last frame.
+    pub fn run_frame(&mut self) {
+        for tile in &mut self.tiles {
+            if tile.fault.is_some() || tile.emulator.is_paused() {
+                continue;
+            }
+            let result = tile
+                .emulator
+                .advance_frame()
+                .and_then(|_| Ok(tile.input.lock().unwrap().update()?));
+            if let Err(e) = result {
+                tracing::warn!(target: APP, "{} stopped: {}", tile.name, e);
+                tile.fault = Some(e.to_string());
+            }
+        }
+    }
+
+    /// Draws the grid of tiles on an RGBA canvas of the layout's size.
+    ///
+    /// # Arguments
+    /// * `canvas` - RGBA canvas
+    /// * `theme` - Colors of the tiles and the space around them
+    /// * `highlight` - Tile to outline, e.g. the one under the pointer
+    pub fn render(&self, canvas: &mut [u8], theme: &Theme, highlight: Option<usize>) {
+        let canvas_width = self.layout.canvas.0;
+        let border = theme.border.to_rgba();
+        for pixel in canvas.chunks_exact_mut(4) {
+            pixel.copy_from_slice(&border);
+        }
+
+        for (index, tile) in self.tiles.iter().enumerate() {
+            let Some((left, top, width, height)) = self.layout.tile_rect(index) else {
+                continue;
+            };
+            let buffer = tile.emulator.get_display_buffer();
+            let (display_width, display_height) = tile.emulator.display_size();
+            for y in 0..height {
+                let row = (top + y) * canvas_width;
+                let source_row = y * display_height / height.max(1) * display_width;
+                for x in 0..width {
+                    let lit = buffer
+                        .get(source_row + x * display_width / width.max(1))
+                        .copied()
+                        .unwrap_or(false);
+                    let offset = (row + left + x) * 4;
+                    canvas[offset..offset + 4].copy_from_slice(&theme.shade(lit as u8 * 255));
+                }
+            }
+            if highlight == Some(index) {
+                outline(canvas, canvas_width, (left, top, width, height), theme);
+            }
+        }
+    }
+}
+
+/// Gets the speed of a tile in the grid.
+fn reduced_cps(full_cps: u32, speed: f32) -> u32 {
+    ((full_cps as f32 * speed).round() as u32).max(1)
+}
+
+/// Draws a frame just outside a tile in the theme's foreground color.
+fn outline(
+    canvas: &mut [u8],
+    canvas_width: usize,
+    (left, top, width, height): (usize, usize, usize, usize),
+    theme: &Theme,
+) {
+    let color = theme.foreground.to_rgba();
+    let canvas_height = canvas.len() / 4 / canvas_width.max(1);
+    let (left, top) = (left.saturating_sub(2), top.saturating_sub(2));
+    let (right, bottom) = (
+        (left + width + 4).min(canvas_width),
+        (top + height + 4).min(canvas_height),
+    );
+    for y in top..bottom {
+        for x in left..right {
+            let edge = x < left + 2 || x >= right - 2 || y < top + 2 || y >= bottom - 2;
+            if edge {
+                let offset = (y * canvas_width + x) * 4;
+                canvas[offset..offset + 4].copy_from_slice(&color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout() {
+        let layout = GalleryLayout::new(4, (640, 320));
+        assert_eq!((layout.columns, layout.rows), (2, 2));
+        assert_eq!(GalleryLayout::new(5, (640, 320)).columns, 3);
+        assert_eq!(GalleryLayout::new(40, (640, 320)).rows, 4);
+
+        let (x, y, width, height) = layout.tile_rect(3).unwrap();
+        assert_eq!(width, height * 2);
+        assert!(x + width <= 640 && y + height <= 320);
+        assert_eq!(layout.tile_at((x + 1, y + height - 1)), Some(3));
+        assert_eq!(layout.tile_at((1, 1)), None);
+        assert_eq!(layout.tile_rect(4), None);
+    }
+
+    #[test]
+    fn test_focus() {
+        // Count up in V0 forever
+        let rom = vec![0x70, 0x01, 0x12, 0x00];
+        let roms = [("one".to_string(), rom.clone()), ("two".to_string(), rom)];
+        let config = EmulatorConfig::default();
+        let mut gallery = Gallery::new(&roms, &config, 0.5, (640, 320), None).unwrap();
+        assert_eq!(gallery.tiles().len(), MIN_TILES);
+        assert_eq!(gallery.tiles()[2].name(), "one");
+        let full_cps = gallery.tiles()[0].full_cps;
+        assert_eq!(gallery.tiles()[0].emulator().target_cps(), full_cps / 2);
+
+        gallery.run_frame();
+        assert!(gallery
+            .tiles()
+            .iter()
+            .all(|tile| tile.emulator().cpu().get_state().v[0] > 0));
+
+        let (x, y, _, _) = gallery.layout().tile_rect(1).unwrap();
+        assert!(gallery.focus(gallery.tile_at((x, y)).unwrap()));
+        assert_eq!(gallery.focused().unwrap().emulator().target_cps(), full_cps);
+        assert!(gallery.focused_input().is_some());
+        let counts: Vec<u8> = gallery
+            .tiles()
+            .iter()
+            .map(|tile| tile.emulator().cpu().get_state().v[0])
+            .collect();
+        gallery.run_frame();
+        for (index, tile) in gallery.tiles().iter().enumerate() {
+            let moved = tile.emulator().cpu().get_state().v[0] != counts[index];
+            assert_eq!(moved, index == 1);
+        }
+
+        gallery.unfocus();
+        assert!(gallery.focused().is_none());
+        assert!(gallery.tiles().iter().all(
+            |tile| !tile.emulator().is_paused() && tile.emulator().target_cps() == full_cps / 2
+        ));
+        assert!(!gallery.focus(MIN_TILES));
+    }
+}
@@ -1,36 +1,394 @@
 //! GUI implementation for the Chip-8 emulator.
 //!
-//! This module provides a graphical user interface using `pixels` and `winit`.
-
-use log::debug;
-use pixels::{Pixels, SurfaceTexture};
-use std::cell::RefCell;
-use std::path::PathBuf;
-use std::rc::Rc;
-use std::time::{Duration, Instant};
+//! This module provides a graphical user interface using `winit`, drawing
+//! through the configured [`PresentBackend`](present::PresentBackend).
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use winit::{
-    event::{ElementState, Event, WindowEvent},
+    dpi::{LogicalPosition, LogicalSize},
+    event::{
+        ElementState, Event, ModifiersState, MouseButton, TouchPhase, VirtualKeyCode, WindowEvent,
+    },
     event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder,
+    window::{Fullscreen, WindowBuilder},
 };
 
+use crate::audio::mixer::MAX_MASTER_VOLUME;
 use crate::audio::stream::StreamConfig;
-use crate::audio::AudioSystem;
-use crate::error::EmulatorError;
+use crate::audio::{AudioBus, AudioSystem, MixerConfig};
+use crate::avsync::{sync_rom, AvSyncProbe, DEFAULT_BEEP_FRAMES, DEFAULT_SYNC_INTERVAL};
+use crate::cheats::CheatList;
+#[cfg(feature = "debug-panel")]
+use crate::debugger::panel::{DebugPanel, PanelButton, LOG_LINES, PANEL_WIDTH};
+use crate::debugger::{report_fault, Timeline};
+#[cfg(feature = "debug-panel")]
+use crate::debugger::{Debugger, StopReason, VariableTable};
+use crate::emulator::{MegaChipScreen, Snapshot, MEGA_HEIGHT, MEGA_WIDTH};
+use crate::error::{EmulatorError, SaveStateError};
+use crate::frontend::browser::{RomBrowser, BROWSER_WIDTH};
+#[cfg(feature = "hot-reload")]
+use crate::frontend::config::ConfigWatcher;
 use crate::frontend::config::{
-    load_config, load_default_config, ConfigProfiles, EmulatorConfig, EnvConfig,
+    load_config, load_default_config, save_config, ConfigDelta, ConfigPatch, ConfigProfiles,
+    EmulatorConfig, EnvConfig, RomOverrides,
+};
+use crate::frontend::gallery::{Gallery, GalleryOptions};
+use crate::frontend::kiosk::{Kiosk, KioskAction, PlaylistEntry};
+use crate::frontend::present;
+use crate::frontend::recovery::{AutosaveConfig, Autosaver, MANIFEST_FILE};
+use crate::frontend::rom_cache::CACHE_FILE;
+use crate::frontend::shortcuts::{ShortcutAction, Shortcuts};
+use crate::frontend::throttle::Throttle;
+use crate::frontend::watch::RomWatcher;
+use crate::frontend::window::{Viewport, WindowConfig};
+use crate::frontend::{read_rom, SimpleEmulator};
+use crate::graphics::{
+    FrameTimes, FrameTiming, GraphicsConfig, GraphicsDisplay, GraphicsResult, Orientation, Osd,
+    Phosphor, PixelRenderer, ScreenshotFormat, Theme, ThemeManager,
 };
-use crate::frontend::SimpleEmulator;
-use crate::graphics::GraphicsDisplay;
+use crate::hardware::audio::NullAudio;
 use crate::hardware::input::Input;
 use crate::hardware::input::SoftwareInput;
-use crate::hardware::{DISPLAY_HEIGHT, DISPLAY_WIDTH};
-use crate::input::mapper::{KeyMapper, QwertyMapper};
+use crate::hardware::{Hardware, DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use crate::input::latency::KeyStamps;
+use crate::input::mapper::{KeyMapper, KeyboardLayout, LayoutDetector, LayoutMapper};
+use crate::input::virtual_keypad::{Pointer, VirtualKeypad};
+use crate::logging::{self, APP, AUDIO, CPU, GFX, INPUT};
+use crate::netplay::{self, Handshake, NetplayRole};
+use crate::patch::RomPatch;
+use crate::paths::Paths;
+use crate::replay::{Replay, ReplayMode};
+use crate::savestate::{SaveStateManager, SLOT_COUNT};
+use crate::CpuEvent;
+
+/// Number keys that select a save slot while Shift is held, by slot.
+const SLOT_KEYS: [VirtualKeyCode; SLOT_COUNT as usize] = [
+    VirtualKeyCode::Key0,
+    VirtualKeyCode::Key1,
+    VirtualKeyCode::Key2,
+    VirtualKeyCode::Key3,
+    VirtualKeyCode::Key4,
+    VirtualKeyCode::Key5,
+    VirtualKeyCode::Key6,
+    VirtualKeyCode::Key7,
+    VirtualKeyCode::Key8,
+    VirtualKeyCode::Key9,
+];
+
+/// How many times faster than the configured speed turbo runs.
+const TURBO_FACTOR: u32 = 4;
+
+/// Master volume change per volume key press.
+const VOLUME_STEP: f32 = 0.1;
+
+/// Window pixels per Chip-8 pixel.
+///
+/// The game is drawn scaled up so overlay text is finer than the game's
+/// pixels, and the browser shares the same buffer size.
+const FRAME_SCALE: usize = BROWSER_WIDTH / DISPLAY_WIDTH;
+
+/// Width of the canvas frames are composed on.
+const FRAME_WIDTH: usize = DISPLAY_WIDTH * FRAME_SCALE;
+
+/// Height of the canvas frames are composed on.
+const FRAME_HEIGHT: usize = DISPLAY_HEIGHT * FRAME_SCALE;
+
+/// Size of the canvas frames are composed on.
+const FRAME_SIZE: (usize, usize) = (FRAME_WIDTH, FRAME_HEIGHT);
+
+/// Size of the canvas with the debug panel beside the game.
+#[cfg(feature = "debug-panel")]
+const PANEL_CANVAS_SIZE: (usize, usize) = (FRAME_WIDTH + PANEL_WIDTH, FRAME_HEIGHT);
+
+/// What the GUI reports when it closes.
+#[derive(Debug, Clone, Default)]
+pub struct RunReports {
+    /// Print a summary of the performance counters.
+    pub stats: bool,
+
+    /// Write a Chrome trace of the run to this file.
+    pub timeline: Option<PathBuf>,
+}
+
+/// How the GUI runs its ROMs.
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// Reload and restart the running ROM whenever its file changes.
+    pub watch: bool,
+
+    /// Record a replay of the run, or play one back.
+    pub replay_mode: Option<ReplayMode>,
+
+    /// Cheats to turn on by name, on top of those on in the cheat file.
+    pub cheats: Vec<String>,
+
+    /// Turn off the cheats the cheat file turns on.
+    pub no_cheats: bool,
+
+    /// IPS or BPS patch to apply to the ROM as it loads.
+    pub patch: Option<PathBuf>,
+}
+
+impl RunOptions {
+    /// Loads the cheats for a ROM and turns them on or off as asked.
+    ///
+    /// Names that aren't in the ROM's cheat file are logged and skipped,
+    /// since the browser applies the same names to every ROM.
+    pub fn cheats_for(&self, rom: &Path) -> Result<CheatList, EmulatorError> {
+        let mut cheats = CheatList::load_for(rom)?;
+        if self.no_cheats {
+            cheats.disable_all();
+        }
+        for name in &self.cheats {
+            if let Err(e) = cheats.set_enabled(name, true) {
+                tracing::warn!(target: APP, "{} in {}", e, CheatList::path_for(rom).display());
+            }
+        }
+        if cheats.active() > 0 {
+            tracing::info!(target: APP, "{} of {} cheats on", cheats.active(), cheats.len());
+        }
+        Ok(cheats)
+    }
+}
+
+/// What the window is showing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GuiState {
+    /// The ROM browser.
+    Menu,
+
+    /// A game.
+    Running,
+}
+
+/// Pauses or resumes the emulator, noting it on the on-screen display.
+fn toggle_pause(emulator: &mut SimpleEmulator, throttle: &mut Throttle, osd: &mut Osd) {
+    let now = Instant::now();
+    if emulator.is_paused() {
+        emulator.resume();
+        throttle.reset(now);
+        osd.push_message("RESUMED", now);
+    } else {
+        emulator.pause();
+        osd.push_message("PAUSED", now);
+    }
+}
+
+/// Saves the machine state to the selected slot, returning the OSD message.
+fn save_state(emulator: &SimpleEmulator, save_states: &SaveStateManager) -> String {
+    match save_states.save(emulator.cpu().snapshot()) {
+        Ok(path) => {
+            tracing::info!(target: APP, "Saved state to {}", path.display());
+            format!("SAVED SLOT {}", save_states.slot())
+        }
+        Err(e) => {
+            tracing::warn!(target: APP, "Failed to save state: {}", e);
+            "SAVE FAILED".to_string()
+        }
+    }
+}
+
+/// Loads the machine state from the selected slot, returning the OSD message.
+fn load_state(emulator: &mut SimpleEmulator, save_states: &SaveStateManager) -> String {
+    let result = save_states
+        .load()
+        .and_then(|snapshot| emulator.cpu_mut().restore(&snapshot));
+    match result {
+        Ok(()) => format!("LOADED SLOT {}", save_states.slot()),
+        Err(EmulatorError::SaveState(SaveStateError::EmptySlot { slot })) => {
+            format!("SLOT {} IS EMPTY", slot)
+        }
+        Err(e) => {
+            tracing::warn!(target: APP, "Failed to load state: {}", e);
+            match e {
+                EmulatorError::SaveState(SaveStateError::RomMismatch { .. }) => {
+                    "STATE IS FOR ANOTHER ROM".to_string()
+                }
+                EmulatorError::SaveState(
+                    SaveStateError::IncompatibleCore { .. }
+                    | SaveStateError::UnsupportedVersion { .. },
+                ) => "STATE IS FROM ANOTHER VERSION".to_string(),
+                _ => "LOAD FAILED".to_string(),
+            }
+        }
+    }
+}
+
+/// Starts auto-saving a newly started ROM.
+///
+/// # Returns
+/// The auto-save of the ROM's last session, if that session crashed or
+/// was killed, to offer for resuming.
+fn start_autosave(
+    autosaver: &mut Autosaver,
+    rom_path: &Path,
+    save_states: &SaveStateManager,
+    osd: &mut Osd,
+    now: Instant,
+) -> Option<Snapshot> {
+    let recovery = autosaver.recover(save_states);
+    if let Err(e) = autosaver.start(Some(rom_path), save_states, now) {
+        tracing::warn!(target: APP, "Failed to start auto-save: {}", e);
+    }
+    if recovery.is_some() {
+        println!("The last session of this ROM didn't exit cleanly; press Enter to resume it");
+        osd.push_message("ENTER RESUMES LAST SESSION", now);
+    }
+    recovery
+}
+
+/// Saves the display, `size` pixels across and down, as a timestamped
+/// screenshot in `dir`.
+fn save_screenshot(
+    display_buffer: &[bool],
+    size: (usize, usize),
+    graphics: GraphicsConfig,
+    dir: &Path,
+) -> GraphicsResult<PathBuf> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let format = ScreenshotFormat::default();
+    std::fs::create_dir_all(dir)
+        .map_err(|e| crate::error::GraphicsError::ScreenshotFailed(e.to_string()))?;
+    let path = dir.join(format!("screenshot-{}.{}", timestamp, format.extension()));
+
+    let mut renderer = PixelRenderer::new(graphics)?;
+    renderer.resize(size.0, size.1)?;
+    renderer.render(display_buffer)?;
+    renderer.save_frame(&path, format)?;
+    Ok(path)
+}
+
+/// Loads a ROM file in place of the running program.
+///
+/// Overrides from a `.toml` file beside the ROM are applied on top of
+/// `config`; a ROM without one runs with `config` as is. `patch`, if given,
+/// is applied to the ROM first, and the ROM's cheats are set up from
+/// `options`.
+///
+/// # Returns
+/// The ROM data, patched, kept for resets.
+pub(super) fn start_rom(
+    emulator: &mut SimpleEmulator,
+    path: &Path,
+    config: &EmulatorConfig,
+    options: &RunOptions,
+    patch: Option<&RomPatch>,
+) -> Result<Vec<u8>, EmulatorError> {
+    let mut data = read_rom(path)?;
+    if let Some(patch) = patch {
+        data = patch.apply(&data)?;
+        tracing::info!(
+            target: APP,
+            "Applied {} patch to {}",
+            patch.format().name(),
+            path.display()
+        );
+    }
+    let effective = rom_config(path, config)?;
+
+    emulator.set_cheats(options.cheats_for(path)?);
+    emulator.swap_rom(&data)?;
+    emulator.configure(&effective);
+    Ok(data)
+}
+
+/// Gets the configuration a ROM runs with, after its overrides file is applied.
+pub(super) fn rom_config(
+    path: &Path,
+    config: &EmulatorConfig,
+) -> Result<EmulatorConfig, EmulatorError> {
+    let mut effective = config.clone();
+    if let Some(overrides) = RomOverrides::load_for(path)? {
+        tracing::info!(
+            target: APP,
+            "Applying overrides from {}",
+            RomOverrides::path_for(path).display()
+        );
+        overrides.apply(&mut effective);
+    }
+    Ok(effective)
+}
+
+/// Loads the variables the debug panel shows for a ROM.
+#[cfg(feature = "debug-panel")]
+fn rom_variables(path: &Path) -> VariableTable {
+    super::load_variables(path, None).unwrap_or_else(|e| {
+        tracing::warn!(target: APP, "Ignoring variables for {}: {}", path.display(), e);
+        VariableTable::new()
+    })
+}
+
+/// Records the window's geometry, mixer settings and theme in a config
+/// file, keeping its other settings.
+fn save_session_settings(
+    path: &PathBuf,
+    window: WindowConfig,
+    mixer: MixerConfig,
+    theme: Option<&str>,
+) -> Result<(), EmulatorError> {
+    let mut config = load_config(path)?;
+    config.window = window;
+    config.audio.mixer = mixer;
+    config.graphics.theme = theme.map(str::to_string);
+    save_config(&config, path)
+}
+
+/// Scales a speed by the turbo factor while turbo is on.
+fn turbo_cps(target_cps: u32, turbo: bool) -> u32 {
+    if turbo {
+        target_cps.saturating_mul(TURBO_FACTOR)
+    } else {
+        target_cps
+    }
+}
+
+/// Applies a master volume or mute change from a shortcut.
+///
+/// # Returns
+/// The on-screen message describing the new state.
+fn adjust_mixer(
+    emulator: &mut SimpleEmulator,
+    mixer: &mut MixerConfig,
+    action: ShortcutAction,
+) -> String {
+    let audio = emulator.cpu_mut().hardware_mut().audio();
+    let result = if action == ShortcutAction::Mute {
+        mixer.muted = !mixer.muted;
+        audio.set_muted(mixer.muted)
+    } else {
+        let step = if action == ShortcutAction::VolumeUp {
+            VOLUME_STEP
+        } else {
+            -VOLUME_STEP
+        };
+        // Round so repeated steps land on whole percentages
+        let volume = ((mixer.master_volume + step) * 10.0).round() / 10.0;
+        mixer.master_volume = volume.clamp(0.0, MAX_MASTER_VOLUME);
+        audio.set_master_volume(mixer.master_volume)
+    };
+    if let Err(e) = result {
+        tracing::warn!(target: AUDIO, "Failed to adjust audio: {}", e);
+    }
+
+    if action == ShortcutAction::Mute {
+        if mixer.muted { "MUTED" } else { "UNMUTED" }.to_string()
+    } else {
+        format!("VOLUME {:.0}%", mixer.master_volume * 100.0)
+    }
+}
 
 /// Loads configuration from CLI arguments.
-fn load_configuration(
+///
+/// Settings from `--set` are applied last, over the environment's.
+pub(super) fn load_configuration(
     config_path: Option<&PathBuf>,
     profile_name: Option<&String>,
+    settings: &ConfigPatch,
 ) -> Result<EmulatorConfig, EmulatorError> {
     let mut config = if let Some(path) = config_path {
         // Load from specific file
@@ -45,37 +403,168 @@ fn load_configuration(
 
     // Apply environment variable overrides
     EnvConfig::apply_env_overrides(&mut config);
+    settings.apply(&mut config)?;
 
     // Validate configuration
     config.validate()?;
+    logging::set_levels(&config.debug.log_levels);
 
     Ok(config)
 }
 
 /// Runs the GUI application.
+///
+/// With a netplay role, the GUI first connects to the peer and then runs in
+/// lockstep: input from both players is combined and applied frame by frame.
+/// If `rom_file` is a directory, the GUI opens a ROM browser over it instead
+/// and games are picked from there. `options` can reload and restart the
+/// running ROM whenever its file changes, record the run to a replay file
+/// or play one back in lockstep frames like netplay, and pick cheats.
+/// `reports` selects what is written on exit: a summary of the performance
+/// counters and a run timeline.
 pub fn run_gui(
     rom_file: PathBuf,
     config_path: Option<&PathBuf>,
     profile_name: Option<&String>,
+    settings: &ConfigPatch,
+    netplay_role: Option<NetplayRole>,
+    mut options: RunOptions,
+    reports: RunReports,
 ) -> Result<(), EmulatorError> {
+    let watch = options.watch;
+    let replay_mode = options.replay_mode.clone();
+    // Restarting the program would desync the peer
+    if watch && netplay_role.is_some() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "--watch can't be used with netplay",
+        )
+        .into());
+    }
+    if replay_mode.is_some() && (netplay_role.is_some() || rom_file.is_dir()) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "replays need a single ROM file and can't be used with netplay",
+        )
+        .into());
+    }
+    // Cheats would desync the peer or the recording
+    if netplay_role.is_some() || replay_mode.is_some() {
+        if !options.cheats.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "cheats can't be used with netplay or replays",
+            )
+            .into());
+        }
+        options.no_cheats = true;
+    }
+    // A patch is made for one ROM, so it isn't applied to others
+    let patch = match &options.patch {
+        Some(_) if watch || rom_file.is_dir() => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "--patch needs a single ROM file and can't be used with --watch",
+            )
+            .into());
+        }
+        Some(path) => Some(RomPatch::load(path)?),
+        None => None,
+    };
+
+    // Load configuration
+    let mut config = load_configuration(config_path, profile_name, settings)?;
+    let paths = Paths::for_config(&config.paths);
+
     let event_loop = EventLoop::new();
-    let window = WindowBuilder::new()
+    let scale = config.graphics.scale_factor;
+    let orientation = config.graphics.orientation();
+    let (min_width, min_height) = orientation.output_size((DISPLAY_WIDTH, DISPLAY_HEIGHT));
+    let (width, height) = config.window.size().unwrap_or_else(|| {
+        let width = min_width as f32 * config.graphics.shown_pixel_aspect_ratio();
+        (width.round() as u32 * scale, min_height as u32 * scale)
+    });
+    let mut builder = WindowBuilder::new()
         .with_title("Chip-8 Emulator")
-        .build(&event_loop)
-        .map_err(|e| {
-            EmulatorError::Graphics(crate::error::GraphicsError::WindowCreationFailed(
-                e.to_string(),
-            ))
-        })?;
+        .with_inner_size(LogicalSize::new(width, height))
+        .with_min_inner_size(LogicalSize::new(min_width as u32, min_height as u32));
+    if let Some((x, y)) = config.window.position() {
+        builder = builder.with_position(LogicalPosition::new(x, y));
+    }
+    if config.window.fullscreen {
+        builder = builder.with_fullscreen(Some(Fullscreen::Borderless(None)));
+    }
+    let window = builder.build(&event_loop).map_err(|e| {
+        EmulatorError::Graphics(crate::error::GraphicsError::WindowCreationFailed(
+            e.to_string(),
+        ))
+    })?;
 
-    let mut pixels = {
-        let window_size = window.inner_size();
-        let surface_texture = SurfaceTexture::new(window_size.width, window_size.height, &window);
-        Pixels::new(DISPLAY_WIDTH as u32, DISPLAY_HEIGHT as u32, surface_texture)?
+    let mut browser = if rom_file.is_dir() {
+        if netplay_role.is_some() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "netplay needs a ROM file, not a directory",
+            )
+            .into());
+        }
+        Some(RomBrowser::scan(
+            &rom_file,
+            &paths.cache_dir().join(CACHE_FILE),
+        )?)
+    } else {
+        None
     };
+    let mut state = if browser.is_some() {
+        GuiState::Menu
+    } else {
+        GuiState::Running
+    };
+    // The ROM being played, kept for resets
+    let mut rom_data = Vec::new();
+    // A ROM picked in the browser or dropped on the window, started next frame
+    let mut pending_rom: Option<PathBuf> = None;
+    // Whether entering the browser paused the game, so leaving it should resume
+    let mut paused_by_menu = false;
 
-    // Load configuration
-    let config = load_configuration(config_path, profile_name)?;
+    // Frames are composed on a fixed-size canvas, then scaled into a
+    // buffer the size of the window
+    let mut canvas = vec![0; FRAME_WIDTH * FRAME_HEIGHT * 4];
+    // Size of the canvas on screen, for mapping pointer positions back to it
+    #[cfg_attr(not(feature = "debug-panel"), allow(unused_mut))]
+    let mut canvas_size = FRAME_SIZE;
+    // The canvas turned and mirrored for the window, when it isn't shown as drawn
+    let mut oriented = Vec::new();
+    let window_size = window.inner_size();
+    let mut surface_size = (window_size.width as usize, window_size.height as usize);
+    let mut present_mode = config.graphics.present_mode;
+    let mut surface = present::open(&window, config.graphics.backend, present_mode)?;
+    // How long the last frames spent emulating, rendering and presenting
+    let mut frame_times = FrameTimes::new();
+    let integer_scaling = config.graphics.integer_scaling;
+    let maintain_aspect_ratio = config.graphics.maintain_aspect_ratio;
+    let pixel_aspect_ratio = config.graphics.shown_pixel_aspect_ratio();
+    let mut viewport = Viewport::fit(
+        surface_size,
+        orientation.output_size(canvas_size),
+        integer_scaling,
+        maintain_aspect_ratio,
+        pixel_aspect_ratio,
+    );
+    // Registers and disassembly drawn beside the game, shown at startup in debug mode
+    #[cfg(feature = "debug-panel")]
+    let mut debug_panel = DebugPanel::new();
+    #[cfg(feature = "debug-panel")]
+    let mut debugger = Debugger::new();
+    #[cfg(feature = "debug-panel")]
+    let mut show_debug_panel = config.debug.enabled;
+    #[cfg(feature = "debug-panel")]
+    let mut panel_canvas = vec![0; PANEL_CANVAS_SIZE.0 * PANEL_CANVAS_SIZE.1 * 4];
+
+    // Geometry to save on exit, updated as the window moves and resizes
+    let mut geometry = config.window;
+    let mut mixer = config.audio.mixer;
+    let geometry_path = config_path.cloned();
 
     // Initialize emulator with configuration
     let mut emulator = SimpleEmulator::new_with_config(&config);
@@ -85,121 +574,1893 @@ pub fn run_gui(
         GraphicsDisplay::with_config(config.graphics.clone()).map_err(EmulatorError::Graphics)?;
     emulator.cpu_mut().set_display(Box::new(graphics_display));
 
-    // Initialize audio with configuration
-    let mut audio_system = AudioSystem::with_config(config.audio.clone(), StreamConfig::default())?;
-    audio_system.initialize_with_defaults()?;
-    emulator.cpu_mut().set_audio(Box::new(audio_system));
+    // Initialize audio with configuration, carrying on silently without a working backend
+    let stream_config = StreamConfig::default().with_backend(config.audio.backend);
+    let mut audio_system = AudioSystem::with_config(config.audio.clone(), stream_config)?;
+    let audio_clock = match audio_system.initialize_with_defaults() {
+        Ok(()) => {
+            let audio_clock = (!config.audio.wall_clock_pacing).then(|| audio_system.clock());
+            emulator.cpu_mut().set_audio(Box::new(audio_system));
+            audio_clock
+        }
+        Err(e) => {
+            tracing::warn!(
+                target: AUDIO,
+                "No {} audio output ({}), continuing without sound",
+                config.audio.backend.name(),
+                e
+            );
+            emulator.cpu_mut().set_audio(Box::new(NullAudio::new()));
+            None
+        }
+    };
 
-    // Initialize input (QwertyMapper doesn't need config)
-    let software_input = Rc::new(RefCell::new(SoftwareInput::new()));
-    let qwerty_mapper = QwertyMapper::new();
+    // Initialize input, with the keypad on the same physical keys in any layout
+    let software_input = Arc::new(Mutex::new(SoftwareInput::new()));
+    software_input
+        .lock()
+        .unwrap()
+        .set_rollover(config.keyboard.rollover);
+    let mut mapper = LayoutMapper::new(config.keyboard.layout);
+    tracing::info!(target: INPUT, "Using the {} keyboard layout", mapper.name());
+    // Presses correct the guess when the layout is left to auto-detection
+    let mut layout_detector = (config.keyboard.layout == KeyboardLayout::Auto)
+        .then(|| LayoutDetector::new(mapper.layout()));
+    // Shortcuts that would take a key from the keypad are left to the keypad
+    let mut shortcuts = Shortcuts::new(&config.shortcuts, &mapper);
+    for conflict in shortcuts.conflicts() {
+        tracing::warn!(target: INPUT, "{}", conflict);
+    }
+    let mut turbo = false;
     emulator.cpu_mut().set_input(software_input.clone());
 
-    emulator.load_rom(&rom_file)?;
+    if state == GuiState::Running {
+        rom_data = start_rom(&mut emulator, &rom_file, &config, &options, patch.as_ref())?;
+        #[cfg(feature = "debug-panel")]
+        debugger.set_variables(rom_variables(&rom_file));
+    }
+    // Save slots for the running ROM, switched along with it
+    let mut save_states = SaveStateManager::new(paths.states_dir(), &rom_data);
+    let mut modifiers = ModifiersState::empty();
+    // Follows the running ROM's file in watch mode
+    let mut rom_watcher =
+        (watch && state == GuiState::Running).then(|| RomWatcher::new(&rom_file, rom_data.clone()));
+
+    // In netplay the CPU reads the combined keys of both players instead
+    let mut netplay_session = match &netplay_role {
+        Some(role) => {
+            let handshake = Handshake::new(&rom_data, &config.behavior);
+            match role {
+                NetplayRole::Host { port } => println!("Waiting for a player on port {}...", port),
+                NetplayRole::Join { address } => println!("Connecting to {}...", address),
+            }
+            let session = role.connect(&handshake)?;
+            println!("✅ Connected as player {:?}", session.player());
+
+            session.prepare(emulator.cpu_mut());
+            Some(session)
+        }
+        None => None,
+    };
+    let netplay_input = Arc::new(Mutex::new(SoftwareInput::new()));
+    if netplay_session.is_some() {
+        emulator.cpu_mut().set_input(netplay_input.clone());
+    }
+    let frame_interval =
+        Duration::from_secs_f64(1.0 / config.behavior.timer_frequency.max(1) as f64);
+    let mut last_netplay_frame = Instant::now();
+
+    // Replays run in lockstep frames too, keyed from the live input or the file
+    let record_path = match &replay_mode {
+        Some(ReplayMode::Record(path)) => {
+            let behavior = rom_config(&rom_file, &config)?.behavior;
+            emulator.record_replay(&rom_data, &behavior, rand::random())?;
+            Some(path.clone())
+        }
+        Some(ReplayMode::Play(path)) => {
+            emulator.play_replay(Replay::load(path)?, &rom_data)?;
+            None
+        }
+        None => None,
+    };
+    // Anything that restarts or steps the program would break lockstep
+    let lockstep = netplay_session.is_some() || replay_mode.is_some();
+
+    // Frames, beeps and key presses are collected for the timeline
+    let mut timeline = reports
+        .timeline
+        .as_ref()
+        .map(|_| (Timeline::new(Instant::now()), emulator.subscribe_events()));
+
+    let mut osd = Osd::new()
+        .with_stats(config.graphics.show_fps)
+        .with_counters(config.graphics.osd_counters.clone())
+        .with_keypad(config.graphics.show_keypad)
+        .with_latency(config.graphics.show_latency)
+        .with_frame_times(config.graphics.show_frame_times);
+    if state == GuiState::Running {
+        let name = rom_file.file_stem().unwrap_or_default().to_string_lossy();
+        osd.push_message(format!("LOADED {}", name), Instant::now());
+    }
+
+    // Auto-saves are offline only, since resuming one would break lockstep
+    let autosave = AutosaveConfig {
+        enabled: config.autosave.enabled && !lockstep,
+        ..config.autosave.clone()
+    };
+    let mut autosaver = Autosaver::new(&autosave, paths.data_dir().join(MANIFEST_FILE));
+    // State of a crashed session, offered until resumed or auto-saved over
+    let mut recovery = None;
+    if state == GuiState::Running {
+        recovery = start_autosave(
+            &mut autosaver,
+            &rom_file,
+            &save_states,
+            &mut osd,
+            Instant::now(),
+        );
+    }
+
+    // Clicks and touches on the keypad press keys alongside the keyboard
+    let mut virtual_keypad =
+        VirtualKeypad::new(config.virtual_keypad.clone(), FRAME_WIDTH, FRAME_HEIGHT);
+    let mut cursor = None;
 
-    let mut last_frame_time = Instant::now();
+    let mut throttle = Throttle::new(emulator.target_cps());
+    throttle.set_audio_clock(audio_clock);
+    let mut last_stats = throttle.stats();
     let mut last_timer_update = Instant::now();
     let timer_update_interval = Duration::from_secs_f64(1.0 / 60.0);
 
-    // Store colors for rendering
-    let foreground_color = config.graphics.foreground_color;
-    let background_color = config.graphics.background_color;
+    // Themes start from the configured colors and switch with hotkeys;
+    // the colors in use are kept for rendering
+    let mut themes = ThemeManager::new(&config.graphics);
+    let (mut foreground_color, mut background_color) = themes.current().colors();
+    let mut phosphor = Phosphor::new();
+
+    // Watch the config file so edits apply without a restart. The watcher
+    // compares against the file itself, not the env-overridden config.
+    #[cfg(feature = "hot-reload")]
+    let mut config_watcher = match config_path {
+        Some(path) => Some(ConfigWatcher::new(path, load_config(path)?)),
+        None => None,
+    };
 
     event_loop.run(move |event, _, control_flow| {
-        *control_flow = ControlFlow::Poll;
+        *control_flow = event_control_flow(&event, *control_flow);
 
         match event {
             Event::WindowEvent {
                 event: WindowEvent::CloseRequested,
                 ..
-            } => *control_flow = ControlFlow::Exit,
+            } => {
+                let theme = themes.selected();
+                let changed = geometry != config.window
+                    || mixer != config.audio.mixer
+                    || theme != config.graphics.theme.as_deref();
+                if let Some(path) = geometry_path.as_ref().filter(|_| changed) {
+                    if let Err(e) = save_session_settings(path, geometry, mixer, theme) {
+                        tracing::warn!(
+                            target: APP,
+                            "Failed to save window, mixer and theme settings: {}",
+                            e
+                        );
+                    }
+                }
+                *control_flow = ControlFlow::Exit;
+            }
+            Event::WindowEvent {
+                event: WindowEvent::Resized(size),
+                ..
+            }
+            | Event::WindowEvent {
+                event:
+                    WindowEvent::ScaleFactorChanged {
+                        new_inner_size: &mut size,
+                        ..
+                    },
+                ..
+            } => {
+                // Minimized windows report a zero size
+                if size.width == 0 || size.height == 0 {
+                    return;
+                }
+                if let Err(e) = surface.resize(size.width, size.height) {
+                    tracing::error!(target: GFX, "Failed to resize window: {}", e);
+                    *control_flow = ControlFlow::Exit;
+                    return;
+                }
+                surface_size = (size.width as usize, size.height as usize);
+                viewport = Viewport::fit(
+                    surface_size,
+                    orientation.output_size(canvas_size),
+                    integer_scaling,
+                    maintain_aspect_ratio,
+                    pixel_aspect_ratio,
+                );
+                if window.fullscreen().is_none() {
+                    let logical: LogicalSize<u32> = size.to_logical(window.scale_factor());
+                    geometry.width = Some(logical.width);
+                    geometry.height = Some(logical.height);
+                }
+            }
+            Event::WindowEvent {
+                event: WindowEvent::Moved(position),
+                ..
+            } if window.fullscreen().is_none() => {
+                let logical: LogicalPosition<i32> = position.to_logical(window.scale_factor());
+                geometry.x = Some(logical.x);
+                geometry.y = Some(logical.y);
+            }
+            Event::WindowEvent {
+                event: WindowEvent::DroppedFile(path),
+                ..
+            } => {
+                // Swapping programs would desync the peer or the replay
+                if lockstep {
+                    tracing::warn!(
+                        target: APP,
+                        "Ignoring {} during netplay or a replay",
+                        path.display()
+                    );
+                    return;
+                }
+                let extension = path
+                    .extension()
+                    .map(|extension| extension.to_ascii_lowercase());
+                match extension.as_ref().and_then(|extension| extension.to_str()) {
+                    Some("ch8") => pending_rom = Some(path),
+                    Some("toml") => match load_config(&path) {
+                        Ok(new_config) => {
+                            let delta = ConfigDelta::between(&config, &new_config);
+                            if let Err(e) = emulator.apply_config_delta(&delta) {
+                                tracing::warn!(
+                                    target: APP,
+                                    "Failed to apply config changes: {}",
+                                    e
+                                );
+                            }
+                            themes.set_palette(emulator.palette());
+                            (foreground_color, background_color) = themes.current().colors();
+                            throttle.set_target_cps(emulator.target_cps(), Instant::now());
+                            config = new_config;
+                            tracing::info!(target: APP, "Applied {}", path.display());
+                            osd.push_message("CONFIG APPLIED", Instant::now());
+                            if !delta.restart_required.is_empty() {
+                                osd.push_message("SOME CHANGES NEED A RESTART", Instant::now());
+                            }
+                        }
+                        Err(e) => {
+                            tracing::warn!(
+                                target: APP,
+                                "Ignoring invalid config {}: {}",
+                                path.display(),
+                                e
+                            );
+                            osd.push_message("INVALID CONFIG", Instant::now());
+                        }
+                    },
+                    _ => tracing::warn!(
+                        target: APP,
+                        "Can't open {}: expected a .ch8 or .toml file",
+                        path.display()
+                    ),
+                }
+            }
+            Event::WindowEvent {
+                event: WindowEvent::CursorMoved { position, .. },
+                ..
+            } => {
+                cursor = surface
+                    .window_pos_to_pixel((position.x as f32, position.y as f32))
+                    .and_then(|position| to_canvas(&viewport, orientation, canvas_size, position));
+                if state == GuiState::Running {
+                    let mut input = software_input.lock().unwrap();
+                    virtual_keypad.pointer_moved(Pointer::Mouse, cursor, &mut input);
+                }
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::MouseInput {
+                        state: button_state,
+                        button: MouseButton::Left,
+                        ..
+                    },
+                ..
+            } if state == GuiState::Running => {
+                #[cfg(feature = "debug-panel")]
+                if show_debug_panel && button_state == ElementState::Pressed && !lockstep {
+                    let button = cursor
+                        .and_then(|(x, y)| debug_panel.button_at(x.checked_sub(FRAME_WIDTH)?, y));
+                    match button {
+                        Some(PanelButton::Pause) => {
+                            toggle_pause(&mut emulator, &mut throttle, &mut osd);
+                            return;
+                        }
+                        Some(PanelButton::Step) => {
+                            emulator.pause();
+                            if debugger.step(emulator.cpu_mut()) == StopReason::Fault {
+                                tracing::error!(
+                                    target: CPU,
+                                    "Fault at 0x{:04X}",
+                                    emulator.cpu().get_state().pc
+                                );
+                                osd.push_message("FAULT", Instant::now());
+                            }
+                            return;
+                        }
+                        Some(PanelButton::Back) => {
+                            emulator.pause();
+                            match debugger.step_back(emulator.cpu_mut()) {
+                                Ok(true) => {}
+                                Ok(false) => osd.push_message("NO HISTORY", Instant::now()),
+                                Err(e) => tracing::error!(target: CPU, "Step back failed: {}", e),
+                            }
+                            return;
+                        }
+                        Some(PanelButton::Cheats) => {
+                            debug_panel.next_view();
+                            return;
+                        }
+                        Some(PanelButton::Cheat(index)) => {
+                            if let Some(enabled) = emulator.toggle_cheat(index) {
+                                let name = &emulator.cheats().cheats()[index].name;
+                                let state = if enabled { "ON" } else { "OFF" };
+                                osd.push_message(format!("CHEAT {} {}", state, name), Instant::now());
+                            }
+                            return;
+                        }
+                        None => {}
+                    }
+                }
+
+                let mut input = software_input.lock().unwrap();
+                match button_state {
+                    ElementState::Pressed => {
+                        virtual_keypad.pointer_down(Pointer::Mouse, cursor, &mut input);
+                    }
+                    ElementState::Released => virtual_keypad.pointer_up(Pointer::Mouse, &mut input),
+                }
+            }
+            Event::WindowEvent {
+                event: WindowEvent::Touch(touch),
+                ..
+            } if state == GuiState::Running => {
+                let pointer = Pointer::Touch(touch.id);
+                let position = surface
+                    .window_pos_to_pixel((touch.location.x as f32, touch.location.y as f32))
+                    .and_then(|position| to_canvas(&viewport, orientation, canvas_size, position));
+                let mut input = software_input.lock().unwrap();
+                match touch.phase {
+                    TouchPhase::Started => {
+                        virtual_keypad.pointer_down(pointer, position, &mut input);
+                    }
+                    TouchPhase::Moved => {
+                        virtual_keypad.pointer_moved(pointer, position, &mut input)
+                    }
+                    TouchPhase::Ended | TouchPhase::Cancelled => {
+                        virtual_keypad.pointer_up(pointer, &mut input)
+                    }
+                }
+            }
+            Event::WindowEvent {
+                event: WindowEvent::ModifiersChanged(changed),
+                ..
+            } => modifiers = changed,
             Event::WindowEvent {
                 event: WindowEvent::KeyboardInput { input, .. },
                 ..
             } => {
-                debug!("KeyboardInput event: {:?}", input);
+                tracing::debug!(target: INPUT, "KeyboardInput event: {:?}", input);
+                let received = Instant::now();
                 if let Some(virtual_keycode) = input.virtual_keycode {
-                    debug!("VirtualKeyCode: {:?}", virtual_keycode);
-                    if let Some(chip_key) = qwerty_mapper.map_virtual_keycode(virtual_keycode) {
-                        debug!("Mapped ChipKey: {:?}", chip_key);
+                    tracing::debug!(target: INPUT, "VirtualKeyCode: {:?}", virtual_keycode);
+                    let pressed = input.state == ElementState::Pressed;
+                    if let Some(detector) = layout_detector.as_mut().filter(|_| pressed) {
+                        if let Some(layout) = detector.observe(input.scancode, virtual_keycode) {
+                            tracing::info!(
+                                target: INPUT,
+                                "Detected the {} keyboard layout",
+                                layout.name()
+                            );
+                            mapper = LayoutMapper::new(layout);
+                            shortcuts = Shortcuts::new(&config.shortcuts, &mapper);
+                            for conflict in shortcuts.conflicts() {
+                                tracing::warn!(target: INPUT, "{}", conflict);
+                            }
+                        }
+                    }
+                    if let (GuiState::Menu, Some(browser)) = (state, browser.as_mut()) {
+                        if !pressed {
+                            return;
+                        }
+                        match virtual_keycode {
+                            VirtualKeyCode::Up => browser.move_selection(-1),
+                            VirtualKeyCode::Down => browser.move_selection(1),
+                            VirtualKeyCode::PageUp => browser.move_page(-1),
+                            VirtualKeyCode::PageDown => browser.move_page(1),
+                            VirtualKeyCode::Home => browser.select_first(),
+                            VirtualKeyCode::End => browser.select_last(),
+                            VirtualKeyCode::Return => {
+                                pending_rom = browser.selected().map(|entry| entry.path.clone());
+                            }
+                            key if !rom_data.is_empty()
+                                && shortcuts.action(key, modifiers)
+                                    == Some(ShortcutAction::Menu) =>
+                            {
+                                state = GuiState::Running;
+                                if paused_by_menu {
+                                    emulator.resume();
+                                    throttle.reset(Instant::now());
+                                }
+                                last_stats = Default::default();
+                            }
+                            _ => {}
+                        }
+                        return;
+                    }
+
+                    let action = if pressed {
+                        shortcuts.action(virtual_keycode, modifiers)
+                    } else {
+                        None
+                    };
+                    match action {
+                        Some(ShortcutAction::Screenshot) => {
+                            let graphics = config
+                                .graphics
+                                .clone()
+                                .with_foreground_color(foreground_color)
+                                .with_background_color(background_color);
+                            let cpu = emulator.cpu();
+                            match save_screenshot(
+                                cpu.get_display_buffer(),
+                                cpu.display_size(),
+                                graphics,
+                                &paths.screenshots_dir(),
+                            ) {
+                                Ok(path) => {
+                                    println!("✅ Screenshot saved to {}", path.display());
+                                    osd.push_message("SCREENSHOT SAVED", Instant::now());
+                                }
+                                Err(e) => tracing::warn!(
+                                    target: GFX,
+                                    "Failed to save screenshot: {}",
+                                    e
+                                ),
+                            }
+                            return;
+                        }
+                        Some(ShortcutAction::OsdStats) => {
+                            osd.toggle_stats();
+                            return;
+                        }
+                        Some(ShortcutAction::OsdKeypad) => {
+                            osd.toggle_keypad();
+                            return;
+                        }
+                        Some(ShortcutAction::OsdFrameTimes) => {
+                            osd.toggle_frame_times();
+                            frame_times.clear();
+                            return;
+                        }
+                        Some(ShortcutAction::Vsync) => {
+                            // The present mode is fixed when the surface is built
+                            match present::open(&window, surface.kind(), present_mode.toggled()) {
+                                Ok(rebuilt) => {
+                                    surface = rebuilt;
+                                    let size = window.inner_size();
+                                    surface_size = (size.width as usize, size.height as usize);
+                                    present_mode = present_mode.toggled();
+                                    frame_times.clear();
+                                    let state = if present_mode.is_synced() { "ON" } else { "OFF" };
+                                    osd.push_message(format!("VSYNC {}", state), Instant::now());
+                                }
+                                Err(e) => tracing::warn!(
+                                    target: GFX,
+                                    "Failed to change present mode: {}",
+                                    e
+                                ),
+                            }
+                            return;
+                        }
+                        Some(
+                            action @ (ShortcutAction::Mute
+                            | ShortcutAction::VolumeUp
+                            | ShortcutAction::VolumeDown),
+                        ) => {
+                            let message = adjust_mixer(&mut emulator, &mut mixer, action);
+                            osd.push_message(message, Instant::now());
+                            return;
+                        }
+                        Some(
+                            action @ (ShortcutAction::NextTheme | ShortcutAction::PreviousTheme),
+                        ) => {
+                            let theme = if action == ShortcutAction::NextTheme {
+                                themes.select_next()
+                            } else {
+                                themes.select_previous()
+                            };
+                            (foreground_color, background_color) = theme.colors();
+                            osd.push_message(
+                                format!("THEME {}", theme.name.to_uppercase()),
+                                Instant::now(),
+                            );
+                            return;
+                        }
+                        Some(ShortcutAction::Fullscreen) => {
+                            let fullscreen = window.fullscreen().is_none();
+                            window
+                                .set_fullscreen(fullscreen.then_some(Fullscreen::Borderless(None)));
+                            geometry.fullscreen = fullscreen;
+                            return;
+                        }
+                        #[cfg(feature = "debug-panel")]
+                        Some(ShortcutAction::DebugPanel) => {
+                            show_debug_panel = !show_debug_panel;
+                            return;
+                        }
+                        Some(ShortcutAction::VirtualKeypad) => {
+                            let visible = !virtual_keypad.is_visible();
+                            virtual_keypad
+                                .set_visible(visible, &mut software_input.lock().unwrap());
+                            return;
+                        }
+                        _ => {}
+                    }
+                    // Anything that pauses, restarts or speeds up is only offered offline
+                    if pressed && !lockstep {
+                        match action {
+                            Some(ShortcutAction::Menu) if browser.is_some() => {
+                                paused_by_menu = !emulator.is_paused();
+                                emulator.pause();
+                                software_input.lock().unwrap().release_all_keys();
+                                state = GuiState::Menu;
+                                return;
+                            }
+                            Some(ShortcutAction::Resume) if recovery.is_some() => {
+                                let restored = recovery
+                                    .take()
+                                    .map(|snapshot| emulator.cpu_mut().restore(&snapshot));
+                                if let Some(Err(e)) = restored {
+                                    tracing::warn!(
+                                        target: APP,
+                                        "Failed to resume the last session: {}",
+                                        e
+                                    );
+                                    osd.push_message("RESUME FAILED", Instant::now());
+                                } else {
+                                    osd.push_message("RESUMED LAST SESSION", Instant::now());
+                                }
+                                throttle.reset(Instant::now());
+                                last_timer_update = Instant::now();
+                                return;
+                            }
+                            Some(ShortcutAction::SaveState) => {
+                                osd.push_message(
+                                    save_state(&emulator, &save_states),
+                                    Instant::now(),
+                                );
+                                return;
+                            }
+                            Some(ShortcutAction::LoadState) => {
+                                let message = load_state(&mut emulator, &save_states);
+                                osd.push_message(message, Instant::now());
+                                throttle.reset(Instant::now());
+                                last_timer_update = Instant::now();
+                                return;
+                            }
+                            Some(ShortcutAction::Reset) => {
+                                match emulator.swap_rom(&rom_data) {
+                                    Ok(()) => osd.push_message("RESET", Instant::now()),
+                                    Err(e) => tracing::warn!(target: APP, "Failed to reset: {}", e),
+                                }
+                                throttle.reset(Instant::now());
+                                last_timer_update = Instant::now();
+                                return;
+                            }
+                            Some(ShortcutAction::Pause) => {
+                                toggle_pause(&mut emulator, &mut throttle, &mut osd);
+                                return;
+                            }
+                            Some(ShortcutAction::FrameAdvance) if emulator.is_paused() => {
+                                if let Err(e) = emulator.advance_frame() {
+                                    tracing::error!(target: CPU, "Emulator error: {}", e);
+                                    report_fault(&e, emulator.cpu(), &config);
+                                    *control_flow = ControlFlow::Exit;
+                                }
+                                return;
+                            }
+                            Some(ShortcutAction::Turbo) => {
+                                turbo = !turbo;
+                                let now = Instant::now();
+                                throttle
+                                    .set_target_cps(turbo_cps(emulator.target_cps(), turbo), now);
+                                let message = if turbo { "TURBO ON" } else { "TURBO OFF" };
+                                osd.push_message(message, now);
+                                return;
+                            }
+                            _ => {}
+                        }
+                        if modifiers.shift() && SLOT_KEYS.contains(&virtual_keycode) {
+                            let slot = SLOT_KEYS
+                                .iter()
+                                .position(|&k| k == virtual_keycode)
+                                .unwrap_or(0);
+                            if save_states.select_slot(slot as u8).is_ok() {
+                                osd.push_message(format!("SLOT {}", slot), Instant::now());
+                            }
+                            return;
+                        }
+                    }
+                    if let Some(chip_key) = mapper.map_virtual_keycode(virtual_keycode) {
+                        let chip_key = if config.graphics.rotate_input {
+                            orientation.source_key(chip_key)
+                        } else {
+                            chip_key
+                        };
+                        let stamps = KeyStamps::mapped_now(received);
+                        tracing::debug!(target: INPUT, "Mapped ChipKey: {:?}", chip_key);
                         match input.state {
                             ElementState::Pressed => {
-                                debug!("Pressing ChipKey: {:?}", chip_key);
-                                software_input.borrow_mut().press_key(chip_key);
+                                tracing::debug!(target: INPUT, "Pressing ChipKey: {:?}", chip_key);
+                                software_input
+                                    .lock()
+                                    .unwrap()
+                                    .press_key_stamped(chip_key, stamps);
                             }
                             ElementState::Released => {
-                                debug!("Releasing ChipKey: {:?}", chip_key);
-                                software_input.borrow_mut().release_key(chip_key);
+                                tracing::debug!(target: INPUT, "Releasing ChipKey: {:?}", chip_key);
+                                software_input.lock().unwrap().release_key(chip_key);
                             }
                         }
+                        if let Some((timeline, _)) = &mut timeline {
+                            timeline.record_key(
+                                chip_key.to_u8(),
+                                input.state == ElementState::Pressed,
+                                Instant::now(),
+                            );
+                        }
                     }
                 }
             }
             Event::MainEventsCleared => {
                 let now = Instant::now();
-                let delta_time = now.duration_since(last_frame_time);
-                last_frame_time = now;
 
-                let cycles_to_execute =
-                    (delta_time.as_secs_f64() * emulator.target_cps() as f64) as usize;
+                if let Some(path) = pending_rom.take() {
+                    match start_rom(&mut emulator, &path, &config, &options, None) {
+                        Ok(data) => {
+                            tracing::info!(target: APP, "Starting {}", path.display());
+                            #[cfg(feature = "debug-panel")]
+                            debugger.set_variables(rom_variables(&path));
+                            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+                            osd.push_message(format!("LOADED {}", name), now);
+                            if watch {
+                                rom_watcher = Some(RomWatcher::new(&path, data.clone()));
+                            }
+                            save_states.set_rom(&data);
+                            recovery =
+                                start_autosave(&mut autosaver, &path, &save_states, &mut osd, now);
+                            rom_data = data;
+                            paused_by_menu = false;
+                            state = GuiState::Running;
+                            software_input.lock().unwrap().release_all_keys();
+                            themes.set_palette(emulator.palette());
+                            (foreground_color, background_color) = themes.current().colors();
+                            throttle.set_target_cps(turbo_cps(emulator.target_cps(), turbo), now);
+                            throttle.reset(now);
+                            last_timer_update = now;
+                            last_stats = Default::default();
+                        }
+                        Err(e) => {
+                            tracing::warn!(target: APP, "Failed to load {}: {}", path.display(), e);
+                            osd.push_message("FAILED TO LOAD ROM", now);
+                        }
+                    }
+                }
+
+                if let Some(watcher) = rom_watcher.as_mut().filter(|_| state == GuiState::Running) {
+                    match watcher.poll() {
+                        Ok(Some(data)) => match emulator.swap_rom(&data) {
+                            Ok(()) => {
+                                tracing::info!(
+                                    target: APP,
+                                    "Reloaded {}",
+                                    watcher.path().display()
+                                );
+                                osd.push_message("ROM RELOADED", now);
+                                save_states.set_rom(&data);
+                                recovery = None;
+                                if let Err(e) =
+                                    autosaver.start(Some(watcher.path()), &save_states, now)
+                                {
+                                    tracing::warn!(
+                                        target: APP,
+                                        "Failed to restart auto-save: {}",
+                                        e
+                                    );
+                                }
+                                rom_data = data;
+                                throttle.reset(now);
+                                last_timer_update = now;
+                            }
+                            Err(e) => tracing::warn!(target: APP, "Failed to reload ROM: {}", e),
+                        },
+                        Ok(None) => {}
+                        Err(e) => {
+                            tracing::warn!(
+                                target: APP,
+                                "Ignoring {}: {}",
+                                watcher.path().display(),
+                                e
+                            );
+                            osd.push_message("ROM RELOAD FAILED", now);
+                        }
+                    }
+                }
+
+                if state == GuiState::Running && !emulator.is_paused() && autosaver.is_due(now) {
+                    // The offered session's auto-save is about to be replaced
+                    recovery = None;
+                    if let Err(e) = autosaver.save(emulator.cpu().snapshot(), &save_states, now) {
+                        tracing::warn!(target: APP, "Failed to auto-save: {}", e);
+                    }
+                }
+
+                #[cfg(feature = "hot-reload")]
+                if let Some(watcher) = config_watcher.as_mut() {
+                    match watcher.poll() {
+                        Ok(Some(delta)) => {
+                            tracing::info!(target: APP, "Reloaded {}", watcher.path().display());
+                            osd.push_message("CONFIG RELOADED", now);
+                            if let Err(e) = emulator.apply_config_delta(&delta) {
+                                tracing::warn!(
+                                    target: APP,
+                                    "Failed to apply config changes: {}",
+                                    e
+                                );
+                            }
+                            themes.set_palette(emulator.palette());
+                            (foreground_color, background_color) = themes.current().colors();
+                            if delta.cpu_speed.is_some() {
+                                throttle
+                                    .set_target_cps(turbo_cps(emulator.target_cps(), turbo), now);
+                            }
+                        }
+                        Ok(None) => {}
+                        Err(e) => tracing::warn!(target: APP, "Ignoring invalid config: {}", e),
+                    }
+                }
 
-                // Update emulator state
-                for _ in 0..cycles_to_execute {
-                    if let Err(e) = emulator.step() {
-                        log::error!("Emulator error: {}", e);
+                if let (GuiState::Menu, Some(browser)) = (state, browser.as_mut()) {
+                    browser.poll();
+                    browser.render(&mut canvas, foreground_color, background_color);
+                    canvas_size = FRAME_SIZE;
+                    let (shown, shown_size) =
+                        orient(orientation, &canvas, canvas_size, &mut oriented);
+                    viewport = Viewport::fit(
+                        surface_size,
+                        shown_size,
+                        integer_scaling,
+                        maintain_aspect_ratio,
+                        pixel_aspect_ratio,
+                    );
+                    viewport.blit(
+                        shown,
+                        shown_size,
+                        surface.frame_mut(),
+                        surface_size.0,
+                        themes.current().border,
+                    );
+                    if surface.present().is_err() {
                         *control_flow = ControlFlow::Exit;
                         return;
                     }
+                    window.set_title("Chip-8 Emulator - ROM Browser");
+                    *control_flow = ControlFlow::Wait;
+                    return;
                 }
 
-                // Update Chip-8 timers at 60Hz
-                if now.duration_since(last_timer_update) >= timer_update_interval {
-                    emulator.cpu_mut().timers_mut().update();
-                    last_timer_update = now;
+                let mut waiting_for_key = false;
+                if emulator.is_paused() {
+                    // Frozen: only redraw in response to events
+                } else if let Some(session) = netplay_session.as_mut() {
+                    // Lockstep: run whole frames with both players' keys
+                    let mut frames = 0;
+                    while now.duration_since(last_netplay_frame) >= frame_interval && frames < 4 {
+                        last_netplay_frame += frame_interval;
+                        frames += 1;
+
+                        let local_keys = netplay::keys_to_mask(&*software_input.lock().unwrap());
+                        let result = session
+                            .exchange(local_keys)
+                            .map_err(EmulatorError::from)
+                            .and_then(|keys| {
+                                netplay::apply_mask(&mut netplay_input.lock().unwrap(), keys);
+                                netplay::run_frame(emulator.cpu_mut())?;
+                                netplay_input.lock().unwrap().update()?;
+                                Ok(())
+                            });
+                        if let Err(e) = result {
+                            tracing::error!(target: APP, "Netplay error: {}", e);
+                            *control_flow = ControlFlow::Exit;
+                            return;
+                        }
+                    }
+                    // Don't try to catch up after a long stall
+                    if now.duration_since(last_netplay_frame) >= frame_interval {
+                        last_netplay_frame = now;
+                    }
+                } else if lockstep {
+                    // Replays run whole frames, so playback matches the recording
+                    let mut frames = 0;
+                    while now.duration_since(last_netplay_frame) >= frame_interval && frames < 4 {
+                        last_netplay_frame += frame_interval;
+                        frames += 1;
+
+                        match emulator.run_replay_frame() {
+                            Ok(true) => {}
+                            Ok(false) => {
+                                emulator.pause();
+                                osd.push_message("REPLAY FINISHED", now);
+                                break;
+                            }
+                            Err(e) => {
+                                tracing::error!(target: CPU, "Emulator error: {}", e);
+                                report_fault(&e, emulator.cpu(), &config);
+                                *control_flow = ControlFlow::Exit;
+                                return;
+                            }
+                        }
+                    }
+                    if now.duration_since(last_netplay_frame) >= frame_interval {
+                        last_netplay_frame = now;
+                    }
+                } else {
+                    // Update emulator state
+                    match emulator.run_for_cycles(throttle.cycles_due(now)) {
+                        Ok((ran, event)) => {
+                            throttle.record_cycles(ran);
+                            if event == CpuEvent::WaitingForKey {
+                                waiting_for_key = true;
+                                // Time spent blocked on a key isn't owed afterwards
+                                throttle.reset(now);
+                            }
+                        }
+                        Err(e) => {
+                            tracing::error!(target: CPU, "Emulator error: {}", e);
+                            report_fault(&e, emulator.cpu(), &config);
+                            *control_flow = ControlFlow::Exit;
+                            return;
+                        }
+                    }
+
+                    // Update Chip-8 timers at 60Hz
+                    let timer_interval = if turbo {
+                        timer_update_interval / TURBO_FACTOR
+                    } else {
+                        timer_update_interval
+                    };
+                    if now.duration_since(last_timer_update) >= timer_interval {
+                        emulator.cpu_mut().timers_mut().update();
+                        last_timer_update = now;
+                    }
                 }
 
-                if let Err(e) = software_input.borrow_mut().update() {
-                    log::warn!("Input update error: {}", e);
+                let emulated = Instant::now();
+
+                if let Err(e) = software_input.lock().unwrap().update() {
+                    tracing::warn!(target: INPUT, "Input update error: {}", e);
                     *control_flow = ControlFlow::Exit;
                     return;
                 }
 
-                // Draw the screen
-                let frame = emulator.cpu().get_display_buffer();
-                draw_frame(
-                    frame,
-                    pixels.frame_mut(),
+                // Draw the screen with the overlay on top
+                if let Some(screen) = emulator.cpu().mega_chip_screen() {
+                    draw_mega_chip_frame(screen, &mut canvas, themes.current().border);
+                } else {
+                    let cpu = emulator.cpu();
+                    let theme = themes.current();
+                    let levels = phosphor.update(cpu.get_display_buffer(), theme.ghosting);
+                    draw_frame(levels, cpu.display_size(), &mut canvas, theme);
+                }
+                let held_keys = if netplay_session.is_some() {
+                    netplay_input.lock().unwrap().get_pressed_keys()
+                } else if emulator.is_playing_replay() {
+                    emulator
+                        .cpu()
+                        .hardware()
+                        .input_handle()
+                        .lock()
+                        .unwrap()
+                        .get_pressed_keys()
+                } else {
+                    software_input.lock().unwrap().get_pressed_keys()
+                };
+                virtual_keypad.render(
+                    &mut canvas,
+                    FRAME_WIDTH,
+                    &held_keys,
+                    foreground_color,
+                    background_color,
+                );
+                osd.expire(now);
+                osd.set_keys(&held_keys);
+                osd.set_metrics(emulator.metrics());
+                osd.set_frame_times(&frame_times);
+                osd.render(
+                    &mut canvas,
+                    FRAME_WIDTH,
+                    FRAME_HEIGHT,
                     foreground_color,
                     background_color,
                 );
-                if pixels.render().is_err() {
+
+                // With the debug panel open, the game is copied beside it
+                #[cfg(feature = "debug-panel")]
+                let shown: &[u8] = if show_debug_panel {
+                    debug_panel.update(emulator.cpu(), emulator.is_paused());
+                    debug_panel.update_cheats(emulator.cheats());
+                    debug_panel.update_variables(debugger.variables(), emulator.cpu());
+                    debug_panel.update_log(&logging::recent(LOG_LINES));
+                    let panel_rows = panel_canvas.chunks_exact_mut(PANEL_CANVAS_SIZE.0 * 4);
+                    for (row, game_row) in panel_rows.zip(canvas.chunks_exact(FRAME_WIDTH * 4)) {
+                        row[..game_row.len()].copy_from_slice(game_row);
+                    }
+                    debug_panel.render(
+                        &mut panel_canvas,
+                        PANEL_CANVAS_SIZE.0,
+                        FRAME_WIDTH,
+                        foreground_color,
+                        background_color,
+                    );
+                    canvas_size = PANEL_CANVAS_SIZE;
+                    &panel_canvas
+                } else {
+                    canvas_size = FRAME_SIZE;
+                    &canvas
+                };
+                #[cfg(not(feature = "debug-panel"))]
+                let shown: &[u8] = &canvas;
+
+                let (shown, shown_size) = orient(orientation, shown, canvas_size, &mut oriented);
+                viewport = Viewport::fit(
+                    surface_size,
+                    shown_size,
+                    integer_scaling,
+                    maintain_aspect_ratio,
+                    pixel_aspect_ratio,
+                );
+                viewport.blit(
+                    shown,
+                    shown_size,
+                    surface.frame_mut(),
+                    surface_size.0,
+                    themes.current().border,
+                );
+                let rendered = Instant::now();
+                if surface.present().is_err() {
+                    *control_flow = ControlFlow::Exit;
+                    return;
+                }
+                frame_times.record(FrameTiming {
+                    emulation: emulated - now,
+                    render: rendered - emulated,
+                    present: rendered.elapsed(),
+                });
+
+                if emulator.is_paused() {
+                    window.set_title("Chip-8 Emulator - Paused");
+                    last_stats = Default::default();
+                    // Wake up to clear messages and check for rebuilds even if no events arrive
+                    *control_flow = if osd.has_messages() || rom_watcher.is_some() {
+                        ControlFlow::WaitUntil(now + timer_update_interval)
+                    } else {
+                        ControlFlow::Wait
+                    };
+                    return;
+                }
+
+                throttle.record_frame(Instant::now());
+                emulator.cpu_mut().record_frame();
+                if let Some((timeline, events)) = &mut timeline {
+                    let now = Instant::now();
+                    for event in events.try_iter() {
+                        timeline.record_event(&event, now);
+                    }
+                    timeline.record_frame(&emulator.metrics(), now);
+                }
+                let stats = throttle.stats();
+                if stats != last_stats {
+                    last_stats = stats;
+                    osd.set_stats(stats.ips, stats.fps);
+                    window.set_title(&format!(
+                        "Chip-8 Emulator - {:.0} IPS, {:.0} FPS",
+                        stats.ips, stats.fps
+                    ));
+                    if throttle.is_lagging() {
+                        tracing::warn!(
+                            target: CPU,
+                            "Host can't keep up: dropped {} cycles",
+                            stats.dropped_cycles
+                        );
+                    }
+                }
+
+                // Sleep until the next timer tick (or an input event) while blocked on FX0A
+                if waiting_for_key {
+                    *control_flow = ControlFlow::WaitUntil(now + timer_update_interval);
+                } else {
+                    throttle.wait_until(now + timer_update_interval);
+                    window.request_redraw();
+                }
+            }
+            Event::LoopDestroyed => {
+                if let Err(e) = autosaver.finish() {
+                    tracing::warn!(target: APP, "Failed to record a clean exit: {}", e);
+                }
+                // Save the recording however the window was closed
+                if let Some((path, replay)) = record_path.as_ref().zip(emulator.stop_replay()) {
+                    match replay.save(path) {
+                        Ok(()) => println!("✅ Replay saved to {}", path.display()),
+                        Err(e) => tracing::error!(target: APP, "Failed to save replay: {}", e),
+                    }
+                }
+                if reports.stats {
+                    println!("{}", emulator.metrics());
+                    if let Some(phase) = frame_times.bottleneck() {
+                        let average = frame_times.average();
+                        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+                        println!(
+                            "Frame times (last {}): emulation {:.2} ms, render {:.2} ms, present {:.2} ms; slowest: {:?}",
+                            frame_times.len(),
+                            ms(average.emulation),
+                            ms(average.render),
+                            ms(average.present),
+                            phase
+                        );
+                    }
+                }
+                if let Some((path, (timeline, _))) =
+                    reports.timeline.as_ref().zip(timeline.as_mut())
+                {
+                    timeline.finish(Instant::now());
+                    match timeline.save(path) {
+                        Ok(()) => println!("✅ Timeline saved to {}", path.display()),
+                        Err(e) => tracing::error!(target: APP, "Failed to save timeline: {}", e),
+                    }
+                }
+            }
+            _ => (),
+        }
+    });
+}
+
+/// Gets the control flow to start handling an event with.
+///
+/// Frames run on `MainEventsCleared`, which polls unless the frame decides
+/// to sleep (paused, or blocked on FX0A). Every other event keeps what the
+/// last frame chose, so its `Wait` survives the `RedrawEventsCleared` that
+/// always follows.
+fn event_control_flow<T>(event: &Event<T>, current: ControlFlow) -> ControlFlow {
+    match event {
+        Event::MainEventsCleared => ControlFlow::Poll,
+        _ => current,
+    }
+}
+
+/// Runs several ROMs side by side in one window.
+///
+/// The ROMs are tiled in a grid at a reduced speed with the sound off.
+/// Clicking a tile gives it the window, full speed, its sound and the
+/// keyboard; the menu shortcut (Escape by default), or no input for the
+/// idle timeout, goes back to the grid.
+pub fn run_gallery(
+    roms: Vec<(String, Vec<u8>)>,
+    config_path: Option<&PathBuf>,
+    profile_name: Option<&String>,
+    settings: &ConfigPatch,
+    options: GalleryOptions,
+) -> Result<(), EmulatorError> {
+    let config = load_configuration(config_path, profile_name, settings)?;
+
+    let event_loop = EventLoop::new();
+    let scale = config.graphics.scale_factor;
+    let (width, height) = config
+        .window
+        .size()
+        .unwrap_or((DISPLAY_WIDTH as u32 * scale, DISPLAY_HEIGHT as u32 * scale));
+    let mut builder = WindowBuilder::new()
+        .with_title("Chip-8 Gallery")
+        .with_inner_size(LogicalSize::new(width, height))
+        .with_min_inner_size(LogicalSize::new(
+            DISPLAY_WIDTH as u32,
+            DISPLAY_HEIGHT as u32,
+        ));
+    if config.window.fullscreen {
+        builder = builder.with_fullscreen(Some(Fullscreen::Borderless(None)));
+    }
+    let window = builder.build(&event_loop).map_err(|e| {
+        EmulatorError::Graphics(crate::error::GraphicsError::WindowCreationFailed(
+            e.to_string(),
+        ))
+    })?;
+    let window_size = window.inner_size();
+    let mut surface_size = (window_size.width as usize, window_size.height as usize);
+    let mut surface = present::open(
+        &window,
+        config.graphics.backend,
+        config.graphics.present_mode,
+    )?;
+    let integer_scaling = config.graphics.integer_scaling;
+    let maintain_aspect_ratio = config.graphics.maintain_aspect_ratio;
+    let fit = move |surface_size| {
+        Viewport::fit(
+            surface_size,
+            FRAME_SIZE,
+            integer_scaling,
+            maintain_aspect_ratio,
+            1.0,
+        )
+    };
+    let mut viewport = fit(surface_size);
+
+    // Every tile plays through one output, carrying on silently without one
+    let mut bus = AudioBus::new(config.audio.sample_rate).with_backend(config.audio.backend);
+    let bus = match bus.initialize_with_defaults() {
+        Ok(()) => Some(bus),
+        Err(e) => {
+            tracing::warn!(target: AUDIO, "No audio output ({}), continuing without sound", e);
+            None
+        }
+    };
+    let mut gallery = Gallery::new(&roms, &config, options.speed, FRAME_SIZE, bus.as_ref())?;
+
+    let themes = ThemeManager::new(&config.graphics);
+    let mapper = LayoutMapper::new(config.keyboard.layout);
+    let shortcuts = Shortcuts::new(&config.shortcuts, &mapper);
+    let mut modifiers = ModifiersState::empty();
+    let mut osd = Osd::new();
+    let mut phosphor = Phosphor::new();
+    let mut canvas = vec![0; FRAME_WIDTH * FRAME_HEIGHT * 4];
+    let mut cursor = None;
+    let mut last_input = Instant::now();
+    let frame_interval =
+        Duration::from_secs_f64(1.0 / config.behavior.timer_frequency.max(1) as f64);
+    let mut next_frame = Instant::now();
+
+    event_loop.run(move |event, _, control_flow| {
+        match event {
+            Event::WindowEvent {
+                event: WindowEvent::CloseRequested,
+                ..
+            } => *control_flow = ControlFlow::Exit,
+            Event::WindowEvent {
+                event: WindowEvent::Resized(size),
+                ..
+            }
+            | Event::WindowEvent {
+                event:
+                    WindowEvent::ScaleFactorChanged {
+                        new_inner_size: &mut size,
+                        ..
+                    },
+                ..
+            } => {
+                if size.width == 0 || size.height == 0 {
+                    return;
+                }
+                if let Err(e) = surface.resize(size.width, size.height) {
+                    tracing::error!(target: GFX, "Failed to resize window: {}", e);
+                    *control_flow = ControlFlow::Exit;
+                    return;
+                }
+                surface_size = (size.width as usize, size.height as usize);
+                viewport = fit(surface_size);
+            }
+            Event::WindowEvent {
+                event: WindowEvent::CursorMoved { position, .. },
+                ..
+            } => {
+                cursor = surface
+                    .window_pos_to_pixel((position.x as f32, position.y as f32))
+                    .and_then(|position| viewport.to_canvas(FRAME_SIZE, position));
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::MouseInput {
+                        state: ElementState::Pressed,
+                        button: MouseButton::Left,
+                        ..
+                    },
+                ..
+            } if gallery.focused().is_none() => {
+                if let Some(index) = cursor.and_then(|position| gallery.tile_at(position)) {
+                    gallery.focus(index);
+                    last_input = Instant::now();
+                    let name = gallery.tiles()[index].name().to_uppercase();
+                    osd.push_message(format!("PLAYING {}", name), Instant::now());
+                }
+            }
+            Event::WindowEvent {
+                event: WindowEvent::ModifiersChanged(changed),
+                ..
+            } => modifiers = changed,
+            Event::WindowEvent {
+                event: WindowEvent::KeyboardInput { input, .. },
+                ..
+            } => {
+                let (Some(key), Some(keys)) = (input.virtual_keycode, gallery.focused_input())
+                else {
+                    return;
+                };
+                last_input = Instant::now();
+                let pressed = input.state == ElementState::Pressed;
+                if pressed && shortcuts.action(key, modifiers) == Some(ShortcutAction::Menu) {
+                    gallery.unfocus();
+                    return;
+                }
+                if let Some(chip_key) = mapper.map_virtual_keycode(key) {
+                    let mut keys = keys.lock().unwrap();
+                    if pressed {
+                        keys.press_key(chip_key);
+                    } else {
+                        keys.release_key(chip_key);
+                    }
+                }
+            }
+            Event::MainEventsCleared => {
+                let now = Instant::now();
+                let idle = options
+                    .idle_timeout
+                    .is_some_and(|timeout| now.duration_since(last_input) >= timeout);
+                if gallery.focused().is_some() && idle {
+                    gallery.unfocus();
+                }
+
+                // Run whole frames, without catching up after a long stall
+                let mut frames = 0;
+                while now >= next_frame && frames < 4 {
+                    gallery.run_frame();
+                    next_frame += frame_interval;
+                    frames += 1;
+                }
+                if now >= next_frame {
+                    next_frame = now + frame_interval;
+                }
+
+                let theme = themes.current();
+                match gallery.focused() {
+                    Some(tile) => {
+                        let cpu = tile.emulator().cpu();
+                        if let Some(screen) = cpu.mega_chip_screen() {
+                            draw_mega_chip_frame(screen, &mut canvas, theme.border);
+                        } else {
+                            let levels = phosphor.update(cpu.get_display_buffer(), theme.ghosting);
+                            draw_frame(levels, cpu.display_size(), &mut canvas, theme);
+                        }
+                    }
+                    None => {
+                        let hovered = cursor.and_then(|position| gallery.tile_at(position));
+                        gallery.render(&mut canvas, theme, hovered);
+                    }
+                }
+                let (foreground, background) = theme.colors();
+                osd.expire(now);
+                osd.render(
+                    &mut canvas,
+                    FRAME_WIDTH,
+                    FRAME_HEIGHT,
+                    foreground,
+                    background,
+                );
+
+                viewport.blit(
+                    &canvas,
+                    FRAME_SIZE,
+                    surface.frame_mut(),
+                    surface_size.0,
+                    theme.border,
+                );
+                if surface.present().is_err() {
                     *control_flow = ControlFlow::Exit;
+                    return;
                 }
-                window.request_redraw();
+                *control_flow = ControlFlow::WaitUntil(next_frame);
             }
             _ => (),
         }
     });
 }
 
-/// Draws the frame to the pixel buffer.
-fn draw_frame(
-    frame: &[bool],
+/// Runs a kiosk: demos of a playlist of ROMs while no one is playing.
+///
+/// The demos start straight away and rotate through the playlist, playing
+/// each ROM's replay if it has one. Any key press or click hands the ROM
+/// on screen to the player from the start; after the configured time
+/// without input the demos start again. `roms` replaces the configured
+/// playlist if it isn't empty, and `idle_secs` and `rotate_secs` replace
+/// the configured times.
+pub fn run_kiosk(
+    roms: Vec<PlaylistEntry>,
+    config_path: Option<&PathBuf>,
+    profile_name: Option<&String>,
+    settings: &ConfigPatch,
+    idle_secs: Option<u64>,
+    rotate_secs: Option<u64>,
+) -> Result<(), EmulatorError> {
+    let mut config = load_configuration(config_path, profile_name, settings)?;
+    if !roms.is_empty() {
+        config.kiosk.playlist = roms;
+    }
+    config.kiosk.idle_secs = idle_secs.unwrap_or(config.kiosk.idle_secs);
+    config.kiosk.rotate_secs = rotate_secs.unwrap_or(config.kiosk.rotate_secs);
+    config.validate()?;
+    if config.kiosk.playlist.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "kiosk mode needs ROMs, given on the command line or in [kiosk] playlist",
+        )
+        .into());
+    }
+
+    let event_loop = EventLoop::new();
+    let scale = config.graphics.scale_factor;
+    let (width, height) = config
+        .window
+        .size()
+        .unwrap_or((DISPLAY_WIDTH as u32 * scale, DISPLAY_HEIGHT as u32 * scale));
+    let mut builder = WindowBuilder::new()
+        .with_title("Chip-8 Kiosk")
+        .with_inner_size(LogicalSize::new(width, height))
+        .with_min_inner_size(LogicalSize::new(
+            DISPLAY_WIDTH as u32,
+            DISPLAY_HEIGHT as u32,
+        ));
+    if config.window.fullscreen {
+        builder = builder.with_fullscreen(Some(Fullscreen::Borderless(None)));
+    }
+    let window = builder.build(&event_loop).map_err(|e| {
+        EmulatorError::Graphics(crate::error::GraphicsError::WindowCreationFailed(
+            e.to_string(),
+        ))
+    })?;
+    let window_size = window.inner_size();
+    let mut surface_size = (window_size.width as usize, window_size.height as usize);
+    let mut surface = present::open(
+        &window,
+        config.graphics.backend,
+        config.graphics.present_mode,
+    )?;
+    let integer_scaling = config.graphics.integer_scaling;
+    let maintain_aspect_ratio = config.graphics.maintain_aspect_ratio;
+    let fit = move |surface_size| {
+        Viewport::fit(
+            surface_size,
+            FRAME_SIZE,
+            integer_scaling,
+            maintain_aspect_ratio,
+            1.0,
+        )
+    };
+    let mut viewport = fit(surface_size);
+
+    let mut emulator = SimpleEmulator::new_with_config(&config);
+    let stream_config = StreamConfig::default().with_backend(config.audio.backend);
+    let mut audio_system = AudioSystem::with_config(config.audio.clone(), stream_config)?;
+    match audio_system.initialize_with_defaults() {
+        Ok(()) => emulator.cpu_mut().set_audio(Box::new(audio_system)),
+        Err(e) => {
+            tracing::warn!(target: AUDIO, "No audio output ({}), continuing without sound", e);
+            emulator.cpu_mut().set_audio(Box::new(NullAudio::new()));
+        }
+    }
+    let software_input = Arc::new(Mutex::new(SoftwareInput::new()));
+    emulator.cpu_mut().set_input(software_input.clone());
+
+    let mut kiosk = Kiosk::new(&config.kiosk, config.kiosk.playlist.clone(), Instant::now());
+    let themes = ThemeManager::new(&config.graphics);
+    let mapper = LayoutMapper::new(config.keyboard.layout);
+    let mut osd = Osd::new();
+    let mut phosphor = Phosphor::new();
+    let mut canvas = vec![0; FRAME_WIDTH * FRAME_HEIGHT * 4];
+    let frame_interval =
+        Duration::from_secs_f64(1.0 / config.behavior.timer_frequency.max(1) as f64);
+    let mut next_frame = Instant::now();
+    // The demos start straight away rather than after a blank idle timeout
+    let mut pending = kiosk.attract(Instant::now());
+
+    event_loop.run(move |event, _, control_flow| {
+        match event {
+            Event::WindowEvent {
+                event: WindowEvent::CloseRequested,
+                ..
+            } => *control_flow = ControlFlow::Exit,
+            Event::WindowEvent {
+                event: WindowEvent::Resized(size),
+                ..
+            }
+            | Event::WindowEvent {
+                event:
+                    WindowEvent::ScaleFactorChanged {
+                        new_inner_size: &mut size,
+                        ..
+                    },
+                ..
+            } => {
+                if size.width == 0 || size.height == 0 {
+                    return;
+                }
+                if let Err(e) = surface.resize(size.width, size.height) {
+                    tracing::error!(target: GFX, "Failed to resize window: {}", e);
+                    *control_flow = ControlFlow::Exit;
+                    return;
+                }
+                surface_size = (size.width as usize, size.height as usize);
+                viewport = fit(surface_size);
+            }
+            Event::WindowEvent {
+                event:
+                    WindowEvent::MouseInput {
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            }
+            | Event::WindowEvent {
+                event:
+                    WindowEvent::Touch(winit::event::Touch {
+                        phase: TouchPhase::Started,
+                        ..
+                    }),
+                ..
+            } => pending = pending.or(kiosk.input(Instant::now())),
+            Event::WindowEvent {
+                event: WindowEvent::KeyboardInput { input, .. },
+                ..
+            } => {
+                let pressed = input.state == ElementState::Pressed;
+                if pressed {
+                    // The key that ends a demo only hands over the game
+                    if let Some(action) = kiosk.input(Instant::now()) {
+                        pending = Some(action);
+                        return;
+                    }
+                }
+                let Some(chip_key) = input
+                    .virtual_keycode
+                    .and_then(|key| mapper.map_virtual_keycode(key))
+                else {
+                    return;
+                };
+                let mut keys = software_input.lock().unwrap();
+                if pressed {
+                    keys.press_key(chip_key);
+                } else {
+                    keys.release_key(chip_key);
+                }
+            }
+            Event::MainEventsCleared => {
+                let now = Instant::now();
+                if let Some(action) = pending.take().or_else(|| kiosk.update(now)) {
+                    let (index, demo) = match action {
+                        KioskAction::Show(index) => (index, true),
+                        KioskAction::Play(index) => (index, false),
+                    };
+                    let entry = &kiosk.playlist()[index];
+                    software_input.lock().unwrap().release_all_keys();
+                    match start_kiosk_entry(&mut emulator, entry, &config, demo) {
+                        Ok(()) => {
+                            let verb = if demo { "DEMO" } else { "PLAYING" };
+                            osd.push_message(
+                                format!("{} {}", verb, entry.name().to_uppercase()),
+                                now,
+                            );
+                            next_frame = now;
+                        }
+                        Err(e) => {
+                            tracing::warn!(
+                                target: APP,
+                                "Failed to start {}: {}",
+                                entry.rom.display(),
+                                e
+                            );
+                            emulator.pause();
+                            // Move on rather than showing a stopped screen
+                            pending = kiosk.demo_finished(now);
+                        }
+                    }
+                }
+
+                // Run whole frames, without catching up after a long stall
+                let mut frames = 0;
+                while now >= next_frame && frames < 4 && !emulator.is_paused() {
+                    let result = if emulator.is_playing_replay() {
+                        emulator.run_replay_frame().map(|more| {
+                            if !more {
+                                pending = kiosk.demo_finished(now);
+                            }
+                        })
+                    } else {
+                        emulator
+                            .advance_frame()
+                            .and_then(|_| Ok(software_input.lock().unwrap().update()?))
+                    };
+                    if let Err(e) = result {
+                        tracing::warn!(target: CPU, "Emulator error: {}", e);
+                        emulator.pause();
+                        pending = kiosk.demo_finished(now);
+                    }
+                    next_frame += frame_interval;
+                    frames += 1;
+                    if pending.is_some() {
+                        break;
+                    }
+                }
+                if now >= next_frame {
+                    next_frame = now + frame_interval;
+                }
+
+                let theme = themes.current();
+                let cpu = emulator.cpu();
+                if let Some(screen) = cpu.mega_chip_screen() {
+                    draw_mega_chip_frame(screen, &mut canvas, theme.border);
+                } else {
+                    let levels = phosphor.update(cpu.get_display_buffer(), theme.ghosting);
+                    draw_frame(levels, cpu.display_size(), &mut canvas, theme);
+                }
+                let (foreground, background) = theme.colors();
+                osd.expire(now);
+                osd.render(
+                    &mut canvas,
+                    FRAME_WIDTH,
+                    FRAME_HEIGHT,
+                    foreground,
+                    background,
+                );
+
+                viewport.blit(
+                    &canvas,
+                    FRAME_SIZE,
+                    surface.frame_mut(),
+                    surface_size.0,
+                    theme.border,
+                );
+                if surface.present().is_err() {
+                    *control_flow = ControlFlow::Exit;
+                    return;
+                }
+                *control_flow = if pending.is_some() {
+                    ControlFlow::Poll
+                } else {
+                    ControlFlow::WaitUntil(next_frame)
+                };
+            }
+            _ => (),
+        }
+    });
+}
+
+/// Starts a kiosk playlist entry, as a demo or for the player.
+///
+/// A demo plays the entry's replay if it has one, without cheats so the
+/// replay stays in step.
+fn start_kiosk_entry(
+    emulator: &mut SimpleEmulator,
+    entry: &PlaylistEntry,
+    config: &EmulatorConfig,
+    demo: bool,
+) -> Result<(), EmulatorError> {
+    // A fault paused the last entry, sound included
+    emulator.resume();
+    emulator.stop_replay();
+    let options = RunOptions {
+        no_cheats: demo,
+        ..RunOptions::default()
+    };
+    let data = start_rom(emulator, &entry.rom, config, &options, None)?;
+    if let Some(path) = entry.replay_path().filter(|_| demo) {
+        emulator.play_replay(Replay::load(path)?, &data)?;
+    }
+    Ok(())
+}
+
+/// Measures how far the sound lands from the picture.
+///
+/// Runs the [`sync_rom`] flash-and-beep ROM with the configured display
+/// and audio settings. Each flash is timed as its frame is handed to the
+/// GPU, and its beep as the audio stream reports the first sample playing,
+/// output latency included. The offsets are shown as they are measured,
+/// and a summary is printed after `beeps` of them or when the window is
+/// closed.
+pub fn run_av_sync(
+    config_path: Option<&PathBuf>,
+    profile_name: Option<&String>,
+    settings: &ConfigPatch,
+    beeps: usize,
+) -> Result<(), EmulatorError> {
+    let config = load_configuration(config_path, profile_name, settings)?;
+
+    let event_loop = EventLoop::new();
+    let scale = config.graphics.scale_factor;
+    let (width, height) = config
+        .window
+        .size()
+        .unwrap_or((DISPLAY_WIDTH as u32 * scale, DISPLAY_HEIGHT as u32 * scale));
+    let window = WindowBuilder::new()
+        .with_title("Chip-8 A/V Sync")
+        .with_inner_size(LogicalSize::new(width, height))
+        .build(&event_loop)
+        .map_err(|e| {
+            EmulatorError::Graphics(crate::error::GraphicsError::WindowCreationFailed(
+                e.to_string(),
+            ))
+        })?;
+    let window_size = window.inner_size();
+    let mut surface_size = (window_size.width as usize, window_size.height as usize);
+    let mut surface = present::open(
+        &window,
+        config.graphics.backend,
+        config.graphics.present_mode,
+    )?;
+    let integer_scaling = config.graphics.integer_scaling;
+    let maintain_aspect_ratio = config.graphics.maintain_aspect_ratio;
+    let fit = move |surface_size| {
+        Viewport::fit(
+            surface_size,
+            FRAME_SIZE,
+            integer_scaling,
+            maintain_aspect_ratio,
+            1.0,
+        )
+    };
+    let mut viewport = fit(surface_size);
+
+    // Without an output stream there is nothing to measure
+    let mut emulator = SimpleEmulator::new_with_config(&config);
+    let stream_config = StreamConfig::default().with_backend(config.audio.backend);
+    let mut audio_system = AudioSystem::with_config(config.audio.clone(), stream_config)?;
+    audio_system.initialize_with_defaults()?;
+    let clock = audio_system.clock();
+    emulator.cpu_mut().set_audio(Box::new(audio_system));
+    emulator.swap_rom(&sync_rom(DEFAULT_SYNC_INTERVAL, DEFAULT_BEEP_FRAMES))?;
+
+    let theme = ThemeManager::new(&config.graphics).current().clone();
+    let mut osd = Osd::new();
+    let mut phosphor = Phosphor::new();
+    let mut probe = AvSyncProbe::new();
+    let mut canvas = vec![0; FRAME_WIDTH * FRAME_HEIGHT * 4];
+    let frame_interval =
+        Duration::from_secs_f64(1.0 / config.behavior.timer_frequency.max(1) as f64);
+    let mut next_frame = Instant::now();
+    println!(
+        "Measuring {} beeps; positive offsets mean the sound comes after the picture",
+        beeps
+    );
+
+    event_loop.run(move |event, _, control_flow| match event {
+        Event::WindowEvent {
+            event: WindowEvent::CloseRequested,
+            ..
+        } => {
+            print_av_sync_report(&probe, clock.output_latency());
+            *control_flow = ControlFlow::Exit;
+        }
+        Event::WindowEvent {
+            event: WindowEvent::Resized(size),
+            ..
+        } => {
+            if size.width == 0 || size.height == 0 {
+                return;
+            }
+            if let Err(e) = surface.resize(size.width, size.height) {
+                tracing::error!(target: GFX, "Failed to resize window: {}", e);
+                *control_flow = ControlFlow::Exit;
+                return;
+            }
+            surface_size = (size.width as usize, size.height as usize);
+            viewport = fit(surface_size);
+        }
+        Event::MainEventsCleared => {
+            let now = Instant::now();
+            let mut frames = 0;
+            while now >= next_frame && frames < 4 {
+                if let Err(e) = emulator.advance_frame() {
+                    tracing::error!(target: CPU, "Emulator error: {}", e);
+                    *control_flow = ControlFlow::Exit;
+                    return;
+                }
+                next_frame += frame_interval;
+                frames += 1;
+            }
+            if now >= next_frame {
+                next_frame = now + frame_interval;
+            }
+
+            let cpu = emulator.cpu();
+            let flash = cpu.get_display_buffer().iter().any(|&pixel| pixel);
+            // No ghosting, so the flash ends on the frame it is cleared
+            let levels = phosphor.update(cpu.get_display_buffer(), 0.0);
+            draw_frame(levels, cpu.display_size(), &mut canvas, &theme);
+            let (foreground, background) = theme.colors();
+            osd.expire(now);
+            osd.render(
+                &mut canvas,
+                FRAME_WIDTH,
+                FRAME_HEIGHT,
+                foreground,
+                background,
+            );
+            viewport.blit(
+                &canvas,
+                FRAME_SIZE,
+                surface.frame_mut(),
+                surface_size.0,
+                theme.border,
+            );
+            if surface.present().is_err() {
+                *control_flow = ControlFlow::Exit;
+                return;
+            }
+            probe.frame_presented(flash, Instant::now());
+
+            probe.tone_started(clock.tone_start());
+            if let Some(offset) = probe.update(|sample| clock.played_at(sample)) {
+                println!("  {:+.1} ms", offset);
+                osd.push_message(format!("{:+.1} MS", offset), now);
+                if probe.offsets().len() >= beeps {
+                    print_av_sync_report(&probe, clock.output_latency());
+                    *control_flow = ControlFlow::Exit;
+                    return;
+                }
+            }
+            *control_flow = ControlFlow::WaitUntil(next_frame);
+        }
+        _ => (),
+    });
+}
+
+/// Prints the summary of an A/V sync measurement.
+fn print_av_sync_report(probe: &AvSyncProbe, output_latency: Duration) {
+    match probe.report() {
+        Some(report) => println!("{}", report),
+        None => println!("No beeps were measured"),
+    }
+    println!(
+        "Output latency reported by the audio stream: {:.1} ms",
+        output_latency.as_secs_f64() * 1000.0
+    );
+}
+
+/// Turns and mirrors a composed canvas for the window.
+///
+/// # Returns
+/// The pixels to show and their size; the canvas itself if it is shown as drawn.
+fn orient<'a>(
+    orientation: Orientation,
+    canvas: &'a [u8],
+    size: (usize, usize),
+    buffer: &'a mut Vec<u8>,
+) -> (&'a [u8], (usize, usize)) {
+    if orientation.is_identity() {
+        return (canvas, size);
+    }
+    buffer.resize(canvas.len(), 0);
+    orientation.apply(canvas, size, buffer);
+    (buffer, orientation.output_size(size))
+}
+
+/// Converts a window buffer position to a position on the canvas as drawn.
+fn to_canvas(
+    viewport: &Viewport,
+    orientation: Orientation,
+    canvas_size: (usize, usize),
+    position: (usize, usize),
+) -> Option<(usize, usize)> {
+    let position = viewport.to_canvas(orientation.output_size(canvas_size), position)?;
+    Some(orientation.source_position(position, canvas_size))
+}
+
+/// Draws the Mega-Chip color screen to the canvas, scaled to fit and centered
+/// on the border color.
+fn draw_mega_chip_frame(
+    screen: &MegaChipScreen,
     buffer: &mut [u8],
-    foreground: crate::graphics::Color,
-    background: crate::graphics::Color,
+    border: crate::graphics::Color,
 ) {
+    let width = (FRAME_HEIGHT * MEGA_WIDTH / MEGA_HEIGHT).min(FRAME_WIDTH);
+    let height = width * MEGA_HEIGHT / MEGA_WIDTH;
+    let left = (FRAME_WIDTH - width) / 2;
+    let top = (FRAME_HEIGHT - height) / 2;
+    let rgba = screen.to_rgba();
+
     for (i, pixel) in buffer.chunks_exact_mut(4).enumerate() {
-        let x = i % DISPLAY_WIDTH;
-        let y = i / DISPLAY_WIDTH;
+        let (x, y) = (i % FRAME_WIDTH, i / FRAME_WIDTH);
+        if x < left || x >= left + width || y < top || y >= top + height {
+            pixel.copy_from_slice(&border.to_rgba());
+            continue;
+        }
+        let source_x = (x - left) * MEGA_WIDTH / width;
+        let source_y = (y - top) * MEGA_HEIGHT / height;
+        let index = (source_y * MEGA_WIDTH + source_x) * 4;
+        pixel.copy_from_slice(&rgba[index..index + 4]);
+    }
+}
 
-        let index = y * DISPLAY_WIDTH + x;
-        let color = if frame[index] {
-            [foreground.r, foreground.g, foreground.b, foreground.a]
-        } else {
-            [background.r, background.g, background.b, background.a]
+/// Draws a frame of `size` pixels to the canvas, scaled up by the largest
+/// whole factor that fits and centered, in the theme's colors.
+///
+/// The standard 64x32 display fills the canvas at `FRAME_SCALE`; smaller
+/// displays get the theme's border around them.
+///
+/// # Arguments
+/// * `levels` - Glow of each pixel, from off (0) to on (255)
+/// * `size` - Display size in pixels
+/// * `buffer` - RGBA canvas
+/// * `theme` - Colors, border and scanlines
+fn draw_frame(levels: &[u8], size: (usize, usize), buffer: &mut [u8], theme: &Theme) {
+    let (width, height) = size;
+    let scale = (FRAME_WIDTH / width.max(1))
+        .min(FRAME_HEIGHT / height.max(1))
+        .max(1);
+    let left = FRAME_WIDTH.saturating_sub(width * scale) / 2;
+    let top = FRAME_HEIGHT.saturating_sub(height * scale) / 2;
+    // The bottom quarter of each row of pixels is a scanline
+    let scanline_rows = (scale / 4).max(1);
+    let border = theme.border.to_rgba();
+
+    for (i, pixel) in buffer.chunks_exact_mut(4).enumerate() {
+        let (canvas_x, canvas_y) = (i % FRAME_WIDTH, i / FRAME_WIDTH);
+        let position = canvas_x
+            .checked_sub(left)
+            .zip(canvas_y.checked_sub(top))
+            .filter(|&(x, y)| x < width * scale && y < height * scale);
+        let color = match position {
+            Some((x, y)) => {
+                let level = levels.get(y / scale * width + x / scale).copied();
+                let color = theme.shade(level.unwrap_or(0));
+                if theme.scanlines > 0.0 && y % scale >= scale - scanline_rows {
+                    theme.scanline(color)
+                } else {
+                    color
+                }
+            }
+            None => border,
         };
 
         pixel.copy_from_slice(&color);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use winit::event::StartCause;
+
+    #[test]
+    fn test_idle_frames_keep_waiting() {
+        // A frame blocked on FX0A sleeps until the next timer tick...
+        let mut flow = event_control_flow(&Event::<()>::MainEventsCleared, ControlFlow::Wait);
+        assert_eq!(flow, ControlFlow::Poll);
+        let tick = Instant::now() + Duration::from_millis(16);
+        flow = ControlFlow::WaitUntil(tick);
+
+        // ...and the events after it leave that alone
+        flow = event_control_flow(&Event::<()>::RedrawEventsCleared, flow);
+        assert_eq!(flow, ControlFlow::WaitUntil(tick));
+        flow = event_control_flow(&Event::<()>::NewEvents(StartCause::Poll), flow);
+        assert_eq!(flow, ControlFlow::WaitUntil(tick));
+    }
+
+    #[test]
+    fn test_paused_frames_keep_waiting() {
+        // A paused frame waits for input, and the redraw after it doesn't
+        // turn that back into polling
+        let flow = event_control_flow(&Event::<()>::RedrawEventsCleared, ControlFlow::Wait);
+        assert_eq!(flow, ControlFlow::Wait);
+
+        // Unpausing is handled by the next frame, which polls again
+        let flow = event_control_flow(&Event::<()>::MainEventsCleared, flow);
+        assert_eq!(flow, ControlFlow::Poll);
+    }
+}
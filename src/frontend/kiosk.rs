@@ -0,0 +1,286 @@
+//! Attract mode for unattended machines.
+//!
+//! In kiosk mode the frontend watches for input. Once there has been none
+//! for a while, a [`Kiosk`] starts cycling through a playlist of ROMs,
+//! showing each for a set time. A ROM with a replay plays it back as a
+//! demo, and moves on early when the replay ends; one without just runs.
+//! Any key press ends the demo and hands the ROM on screen to the player,
+//! from the start.
+//!
+//! The playlist lives in the `[kiosk]` section of the config:
+//!
+//! ```toml
+//! [kiosk]
+//! idle_secs = 60
+//! rotate_secs = 30
+//!
+//! [[kiosk.playlist]]
+//! rom = "roms/pong.ch8"
+//! replay = "replays/pong.c8replay"
+//!
+//! [[kiosk.playlist]]
+//! rom = "roms/tetris.ch8"
+//! ```
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::input::IdleTracker;
+use crate::replay::REPLAY_EXTENSION;
+
+/// Default seconds without input before the demos start.
+pub const DEFAULT_KIOSK_IDLE_SECS: u64 = 60;
+
+/// Default seconds each ROM is shown before moving on.
+pub const DEFAULT_KIOSK_ROTATE_SECS: u64 = 30;
+
+/// A ROM in the kiosk playlist.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlaylistEntry {
+    /// ROM file to show.
+    pub rom: PathBuf,
+
+    /// Replay to play as a demo; a replay beside the ROM is used if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replay: Option<PathBuf>,
+}
+
+impl PlaylistEntry {
+    /// Creates an entry for a ROM.
+    pub fn new(rom: impl Into<PathBuf>) -> Self {
+        Self {
+            rom: rom.into(),
+            replay: None,
+        }
+    }
+
+    /// Sets the replay played as a demo.
+    pub fn with_replay(mut self, replay: impl Into<PathBuf>) -> Self {
+        self.replay = Some(replay.into());
+        self
+    }
+
+    /// Finds the replay to play: the one set, or `ROM.c8replay` if it exists.
+    pub fn replay_path(&self) -> Option<PathBuf> {
+        self.replay.clone().or_else(|| {
+            let beside = self.rom.with_extension(REPLAY_EXTENSION);
+            beside.is_file().then_some(beside)
+        })
+    }
+
+    /// Gets the name shown for the entry.
+    pub fn name(&self) -> String {
+        let name = self.rom.file_stem().unwrap_or_default();
+        name.to_string_lossy().into_owned()
+    }
+}
+
+/// Kiosk mode configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KioskConfig {
+    /// Seconds without input before the demos start.
+    pub idle_secs: u64,
+
+    /// Seconds each ROM is shown before moving on.
+    pub rotate_secs: u64,
+
+    /// ROMs shown, in order.
+    pub playlist: Vec<PlaylistEntry>,
+}
+
+impl Default for KioskConfig {
+    fn default() -> Self {
+        Self {
+            idle_secs: DEFAULT_KIOSK_IDLE_SECS,
+            rotate_secs: DEFAULT_KIOSK_ROTATE_SECS,
+            playlist: Vec::new(),
+        }
+    }
+}
+
+impl KioskConfig {
+    /// Gets the time without input before the demos start.
+    pub fn idle_timeout(&self) -> Duration {
+        Duration::from_secs(self.idle_secs)
+    }
+
+    /// Gets the time each ROM is shown.
+    pub fn rotate_interval(&self) -> Duration {
+        Duration::from_secs(self.rotate_secs)
+    }
+}
+
+/// What the frontend should do next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KioskAction {
+    /// Start showing a playlist entry as a demo.
+    Show(usize),
+
+    /// Hand a playlist entry to the player, from the start.
+    Play(usize),
+}
+
+/// Decides when to start, rotate and end the demos.
+#[derive(Debug, Clone)]
+pub struct Kiosk {
+    /// ROMs to show.
+    playlist: Vec<PlaylistEntry>,
+
+    /// When the player last did anything.
+    idle: IdleTracker,
+
+    /// Time without input before the demos start.
+    idle_timeout: Duration,
+
+    /// Time each ROM is shown.
+    rotate_interval: Duration,
+
+    /// Entry on show and when it started, while the demos run.
+    showing: Option<(usize, Instant)>,
+
+    /// Entry shown next.
+    next: usize,
+}
+
+impl Kiosk {
+    /// Creates a kiosk that counts as having seen input at `now`.
+    pub fn new(config: &KioskConfig, playlist: Vec<PlaylistEntry>, now: Instant) -> Self {
+        Self {
+            playlist,
+            idle: IdleTracker::new(now),
+            idle_timeout: config.idle_timeout(),
+            rotate_interval: config.rotate_interval(),
+            showing: None,
+            next: 0,
+        }
+    }
+
+    /// Gets the playlist.
+    pub fn playlist(&self) -> &[PlaylistEntry] {
+        &self.playlist
+    }
+
+    /// Gets the entry on show, while the demos run.
+    pub fn showing(&self) -> Option<usize> {
+        self.showing.map(|(entry, _)| entry)
+    }
+
+    /// Checks if the demos are running.
+    pub fn is_attracting(&self) -> bool {
+        self.showing.is_some()
+    }
+
+    /// Notes input from the player.
+    ///
+    /// # Returns
+    /// The entry to hand over if a demo was running.
+    pub fn input(&mut self, now: Instant) -> Option<KioskAction> {
+        self.idle.touch(now);
+        let (entry, _) = self.showing.take()?;
+        Some(KioskAction::Play(entry))
+    }
+
+    /// Starts the demos once idle and rotates them when their time is up.
+    pub fn update(&mut self, now: Instant) -> Option<KioskAction> {
+        match self.showing {
+            None if self.idle.is_idle(now, self.idle_timeout) => self.attract(now),
+            Some((_, since)) if now.saturating_duration_since(since) >= self.rotate_interval => {
+                self.attract(now)
+            }
+            _ => None,
+        }
+    }
+
+    /// Moves on from a demo that ended or couldn't start.
+    pub fn demo_finished(&mut self, now: Instant) -> Option<KioskAction> {
+        self.showing.and_then(|_| self.attract(now))
+    }
+
+    /// Shows the next entry, starting the demos if they aren't running.
+    pub fn attract(&mut self, now: Instant) -> Option<KioskAction> {
+        if self.playlist.is_empty() {
+            return None;
+        }
+        let entry = self.next % self.playlist.len();
+        self.next = entry + 1;
+        self.showing = Some((entry, now));
+        Some(KioskAction::Show(entry))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kiosk(now: Instant) -> Kiosk {
+        let config = KioskConfig {
+            idle_secs: 10,
+            rotate_secs: 5,
+            ..KioskConfig::default()
+        };
+        let playlist = vec![PlaylistEntry::new("a.ch8"), PlaylistEntry::new("b.ch8")];
+        Kiosk::new(&config, playlist, now)
+    }
+
+    #[test]
+    fn test_attract_cycle() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut kiosk = kiosk(start);
+        assert_eq!(kiosk.update(at(9)), None);
+        assert_eq!(kiosk.input(at(9)), None);
+
+        // Idle from 9s, so the demos start at 19s and rotate every 5s
+        assert_eq!(kiosk.update(at(18)), None);
+        assert_eq!(kiosk.update(at(19)), Some(KioskAction::Show(0)));
+        assert!(kiosk.is_attracting());
+        assert_eq!(kiosk.update(at(23)), None);
+        assert_eq!(kiosk.update(at(24)), Some(KioskAction::Show(1)));
+        assert_eq!(kiosk.demo_finished(at(26)), Some(KioskAction::Show(0)));
+        assert_eq!(kiosk.showing(), Some(0));
+
+        // A key hands over the ROM on show and waits for idle again
+        assert_eq!(kiosk.input(at(27)), Some(KioskAction::Play(0)));
+        assert!(!kiosk.is_attracting());
+        assert_eq!(kiosk.demo_finished(at(28)), None);
+        assert_eq!(kiosk.update(at(36)), None);
+        assert_eq!(kiosk.update(at(37)), Some(KioskAction::Show(1)));
+    }
+
+    #[test]
+    fn test_playlist_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let rom = dir.path().join("pong.ch8");
+        std::fs::write(&rom, [0x12, 0x00]).unwrap();
+        let entry = PlaylistEntry::new(&rom);
+        assert_eq!(entry.name(), "pong");
+        assert_eq!(entry.replay_path(), None);
+        std::fs::write(rom.with_extension(REPLAY_EXTENSION), "").unwrap();
+        assert_eq!(entry.replay_path(), Some(dir.path().join("pong.c8replay")));
+        let entry = entry.with_replay("demo.c8replay");
+        assert_eq!(entry.replay_path(), Some(PathBuf::from("demo.c8replay")));
+
+        let config: KioskConfig = toml::from_str(
+            "idle_secs = 90\n[[playlist]]\nrom = \"a.ch8\"\nreplay = \"a.c8replay\"\n[[playlist]]\nrom = \"b.ch8\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.idle_timeout(), Duration::from_secs(90));
+        assert_eq!(config.rotate_secs, DEFAULT_KIOSK_ROTATE_SECS);
+        assert_eq!(
+            config.playlist,
+            [
+                PlaylistEntry::new("a.ch8").with_replay("a.c8replay"),
+                PlaylistEntry::new("b.ch8")
+            ]
+        );
+        // Without a playlist the demos never start
+        let mut kiosk = Kiosk::new(&config, Vec::new(), Instant::now());
+        assert_eq!(
+            kiosk.update(Instant::now() + Duration::from_secs(100)),
+            None
+        );
+    }
+}
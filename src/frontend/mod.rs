@@ -3,16 +3,64 @@
 //! This module provides command-line and user interface components
 //! for interacting with the Chip-8 emulator.
 
+pub mod browser;
+pub mod builder;
 pub mod cli;
 pub mod config;
+pub mod doctor;
+pub mod dump;
+pub mod gallery;
 pub mod gui;
+pub mod kiosk;
+pub mod present;
+pub mod quirks;
+pub mod recovery;
+pub mod rom_cache;
+pub mod runner;
+pub mod shortcuts;
+pub mod throttle;
+pub mod watch;
+pub mod window;
 
 // Re-export commonly used types
+pub use browser::{RomBrowser, RomEntry};
+pub use builder::EmulatorBuilder;
 pub use cli::{run_cli, CliApp, Commands};
-pub use config::{load_config, save_config, EmulatorBehaviorConfig, EmulatorConfig};
+#[cfg(feature = "hot-reload")]
+pub use config::ConfigWatcher;
+pub use config::{
+    load_config, save_config, ConfigDelta, ConfigPatch, EmulatorBehaviorConfig, EmulatorConfig,
+    RomOverrides, Variant,
+};
+pub use gallery::{Gallery, GalleryLayout, GalleryOptions, GalleryTile};
+pub use kiosk::{Kiosk, KioskAction, KioskConfig, PlaylistEntry};
+pub use present::PresentBackend;
+pub use quirks::{detect_quirks, QuirkReport, QuirkScore};
+pub use recovery::{AutosaveConfig, Autosaver, SessionManifest};
+pub use rom_cache::RomCache;
+pub use runner::{AppRunner, FrameHook, FrameInfo, RunnerControl};
+pub use shortcuts::{KeyChord, ShortcutAction, ShortcutConflict, Shortcuts, ShortcutsConfig};
+pub use throttle::{Throttle, ThrottleStats};
+pub use watch::RomWatcher;
+pub use window::{Viewport, WindowConfig};
 
+use crate::analysis::RomLoader;
+use crate::cheats::CheatList;
+use crate::debugger::{SymbolTable, VariableTable};
+use crate::emulator::{
+    Memory, Metrics, Pcg32, RandomLog, RandomRecorder, ThreadRandom, TimingModel, Watchdog,
+    WatchdogConfig,
+};
 use crate::error::EmulatorError;
-use crate::Cpu;
+use crate::graphics::Color;
+use crate::hardware::input::{Input, SoftwareInput};
+use crate::hardware::{Hardware, SharedInput};
+use crate::logging::{APP, AUDIO};
+use crate::netplay;
+use crate::replay::Replay;
+use crate::{Cpu, CpuEvent, EmulatorEvent};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
 
 /// Result type for frontend operations.
 pub type FrontendResult<T> = Result<T, EmulatorError>;
@@ -25,53 +73,460 @@ pub struct SimpleEmulator {
     /// Whether the emulator is running.
     running: bool,
 
-    /// Target cycles per second.
+    /// Target clock cycles per second under the CPU's timing model.
     target_cps: u32,
+
+    /// Display colors as (foreground, background).
+    palette: (Color, Color),
+
+    /// Whether execution is paused.
+    paused: bool,
+
+    /// Replay being recorded or played back.
+    replay: Option<ReplayState>,
+
+    /// Spin loop detection for headless runs.
+    watchdog: Option<Watchdog>,
+
+    /// Cheats for the running ROM.
+    cheats: CheatList,
+}
+
+/// A replay in progress.
+enum ReplayState {
+    /// Keys read from the live input are appended each frame.
+    Recording {
+        replay: Replay,
+
+        /// Bytes RND has produced since recording started.
+        random: RandomLog,
+    },
+
+    /// Keys come from the replay instead of the live input.
+    Playing {
+        replay: Replay,
+
+        /// Next frame to play.
+        frame: u32,
+
+        /// Input the CPU reads during playback.
+        input: Arc<Mutex<SoftwareInput>>,
+
+        /// Live input to restore when playback stops.
+        live_input: SharedInput,
+    },
 }
 
 impl SimpleEmulator {
     /// Creates a new simple emulator.
     pub fn new() -> Self {
         let cpu = Cpu::new();
+        let graphics = EmulatorConfig::default().graphics;
 
         Self {
             cpu,
             running: false,
             target_cps: 700,
+            palette: (graphics.foreground_color, graphics.background_color),
+            paused: false,
+            replay: None,
+            watchdog: None,
+            cheats: CheatList::new(),
         }
     }
 
     /// Creates a new simple emulator with configuration.
     pub fn new_with_config(config: &EmulatorConfig) -> Self {
-        let cpu = Cpu::new_with_config(&config.behavior);
+        let mut cpu = Cpu::new_with_config(&config.behavior);
+        cpu.set_trace_length(trace_length(config));
+        cpu.set_self_modification_tracking(config.debug.track_self_modifying_code);
 
         Self {
             cpu,
             running: false,
-            target_cps: config.behavior.cpu_speed,
+            target_cps: config.behavior.timing().cycles_per_second(),
+            palette: (
+                config.graphics.foreground_color,
+                config.graphics.background_color,
+            ),
+            paused: false,
+            replay: None,
+            watchdog: None,
+            cheats: CheatList::new(),
         }
     }
 
     /// Configures the emulator with new settings.
     pub fn configure(&mut self, config: &EmulatorConfig) {
         self.cpu.configure(&config.behavior);
-        self.target_cps = config.behavior.cpu_speed;
+        self.cpu.set_trace_length(trace_length(config));
+        if self.cpu.self_modification_tracking() != config.debug.track_self_modifying_code {
+            self.cpu
+                .set_self_modification_tracking(config.debug.track_self_modifying_code);
+        }
+        self.target_cps = config.behavior.timing().cycles_per_second();
+        self.palette = (
+            config.graphics.foreground_color,
+            config.graphics.background_color,
+        );
+    }
+
+    /// Applies the hot-safe parts of a configuration change while running.
+    ///
+    /// CPU speed, display colors, audio volume/frequency and log levels take
+    /// effect immediately; other changed sections are logged as needing a restart.
+    /// A new CPU speed only applies under simple timing: the COSMAC VIP
+    /// model keeps running at the VIP's clock.
+    pub fn apply_config_delta(&mut self, delta: &ConfigDelta) -> FrontendResult<()> {
+        if let Some(cpu_speed) = delta.cpu_speed {
+            match self.cpu.timing_model() {
+                TimingModel::Simple(_) => self.set_target_cps(cpu_speed),
+                TimingModel::CosmacVip => tracing::debug!(
+                    target: APP,
+                    "Keeping COSMAC VIP timing; cpu_speed {} applies to simple timing",
+                    cpu_speed
+                ),
+            }
+        }
+        if let Some(foreground) = delta.foreground_color {
+            self.palette.0 = foreground;
+        }
+        if let Some(background) = delta.background_color {
+            self.palette.1 = background;
+        }
+
+        let audio = self.cpu.hardware_mut().audio();
+        if let Some(volume) = delta.volume {
+            audio.set_volume(volume)?;
+        }
+        if let Some(frequency) = delta.frequency {
+            audio.set_frequency(frequency)?;
+        }
+        if let Some(levels) = &delta.log_levels {
+            crate::logging::set_levels(levels);
+        }
+
+        for section in &delta.restart_required {
+            tracing::warn!(target: APP, "Changes to [{}] take effect after a restart", section);
+        }
+
+        Ok(())
+    }
+
+    /// Pauses execution.
+    ///
+    /// Timers stop counting down while paused, the buzzer is silenced and
+    /// the display is marked clean so frontends can skip redrawing.
+    pub fn pause(&mut self) {
+        if self.paused {
+            return;
+        }
+        self.paused = true;
+        self.running = false;
+
+        let hardware = self.cpu.hardware_mut();
+        if let Err(e) = hardware.audio().stop_beep() {
+            tracing::warn!(target: AUDIO, "Failed to stop audio: {}", e);
+        }
+        if let Err(e) = hardware.audio().set_paused(true) {
+            tracing::warn!(target: AUDIO, "Failed to pause audio: {}", e);
+        }
+        hardware.display().mark_clean();
+    }
+
+    /// Resumes execution, without counting the paused time against the timers.
+    pub fn resume(&mut self) {
+        if !self.paused {
+            return;
+        }
+        self.paused = false;
+        self.cpu.timers_mut().sync_to_real_time();
+        if let Err(e) = self.cpu.hardware_mut().audio().set_paused(false) {
+            tracing::warn!(target: AUDIO, "Failed to resume audio: {}", e);
+        }
+    }
+
+    /// Checks if execution is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Runs exactly one timer frame: a frame's worth of clock cycles and one timer tick.
+    ///
+    /// Stops early if the CPU blocks on a key. Timers only advance by the one
+    /// tick regardless of how long the frame takes, and when paused the
+    /// buzzer stays silent afterwards.
+    ///
+    /// # Returns
+    /// The clock cycles used.
+    pub fn advance_frame(&mut self) -> FrontendResult<u32> {
+        let cycles_per_frame = self.cpu.cycles_per_frame();
+
+        let realtime = self.cpu.realtime_timers();
+        self.cpu.set_realtime_timers(false);
+        let result = self.run_for_cycles(cycles_per_frame);
+        self.cpu.set_realtime_timers(realtime);
+        let (ran, _) = result?;
+
+        self.cpu.timers_mut().update_by_ticks(1);
+        self.cpu.timers_mut().sync_to_real_time();
+        self.cpu.record_frame();
+
+        if self.paused {
+            self.running = false;
+            if let Err(e) = self.cpu.hardware_mut().audio().stop_beep() {
+                tracing::warn!(target: AUDIO, "Failed to stop audio: {}", e);
+            }
+        }
+
+        Ok(ran)
+    }
+
+    /// Gets the display colors as (foreground, background).
+    pub fn palette(&self) -> (Color, Color) {
+        self.palette
     }
 
     /// Loads a ROM file into the emulator.
+    ///
+    /// The ROM is sanity-checked first and any issues are logged as
+    /// warnings; use `RomLoader` directly to reject bad ROMs instead.
     pub fn load_rom<P: AsRef<std::path::Path>>(&mut self, path: P) -> FrontendResult<()> {
-        let rom_data = std::fs::read(path)?;
-        self.cpu.load_rom(&rom_data)?;
+        self.load_rom_bytes(&read_rom(path)?)
+    }
+
+    /// Loads a ROM from memory, checking it like `load_rom`.
+    pub fn load_rom_bytes(&mut self, rom_data: &[u8]) -> FrontendResult<()> {
+        let report = RomLoader::new().load(&mut self.cpu, rom_data)?;
+        for issue in &report.issues {
+            tracing::warn!(target: APP, "ROM check: {}", issue);
+        }
+        self.cheats.apply_patches(self.cpu.memory_mut());
+        if let Some(watchdog) = &mut self.watchdog {
+            watchdog.reset();
+        }
         Ok(())
     }
 
-    /// Executes a single CPU cycle.
-    pub fn step(&mut self) -> FrontendResult<()> {
+    /// Replaces the running program with another ROM, resetting the machine.
+    ///
+    /// Memory, registers, timers and the display are cleared and the
+    /// buzzer is silenced. The ROM is checked first, so on error the
+    /// current program is left untouched.
+    pub fn swap_rom(&mut self, rom_data: &[u8]) -> FrontendResult<()> {
+        Memory::new().load_rom(rom_data)?;
+
+        self.cpu.reset();
+        self.cpu.load_rom(rom_data)?;
+        self.cheats.apply_patches(self.cpu.memory_mut());
+        let hardware = self.cpu.hardware_mut();
+        hardware.display().clear();
+        if let Err(e) = hardware.audio().stop_beep() {
+            tracing::warn!(target: AUDIO, "Failed to stop audio: {}", e);
+        }
+
+        self.running = false;
+        self.paused = false;
+        if let Some(watchdog) = &mut self.watchdog {
+            watchdog.reset();
+        }
+        Ok(())
+    }
+
+    /// Restarts a ROM and starts recording a replay of it.
+    ///
+    /// The run becomes deterministic: the RNG is seeded with `seed` and
+    /// timers only tick once per frame. Drive it with `run_replay_frame`.
+    ///
+    /// # Arguments
+    /// * `rom_data` - ROM to run
+    /// * `behavior` - Emulator settings to run and record with
+    /// * `seed` - RNG seed
+    pub fn record_replay(
+        &mut self,
+        rom_data: &[u8],
+        behavior: &EmulatorBehaviorConfig,
+        seed: u64,
+    ) -> FrontendResult<()> {
+        self.start_deterministic(rom_data, behavior, seed)?;
+        let recorder = RandomRecorder::new(Pcg32::new(seed));
+        let random = recorder.log();
+        self.cpu.set_random_source(Box::new(recorder));
+        self.replay = Some(ReplayState::Recording {
+            replay: Replay::new(rom_data, behavior, seed),
+            random,
+        });
+        Ok(())
+    }
+
+    /// Restarts a ROM and plays back a replay of it.
+    ///
+    /// The replay's settings and seed are used, and the CPU reads keys from
+    /// the replay until playback stops. Drive it with `run_replay_frame`.
+    ///
+    /// # Arguments
+    /// * `replay` - Replay to play
+    /// * `rom_data` - ROM the replay was recorded with
+    pub fn play_replay(&mut self, replay: Replay, rom_data: &[u8]) -> FrontendResult<()> {
+        replay.check_rom(rom_data)?;
+        self.start_deterministic(rom_data, &replay.behavior, replay.seed)?;
+        if !replay.random.is_empty() {
+            self.cpu.set_random_source(Box::new(replay.random_source()));
+        }
+
+        let input = Arc::new(Mutex::new(SoftwareInput::new()));
+        let live_input = self.cpu.hardware().input_handle();
+        self.cpu.set_input(input.clone());
+        self.replay = Some(ReplayState::Playing {
+            replay,
+            frame: 0,
+            input,
+            live_input,
+        });
+        Ok(())
+    }
+
+    /// Runs one replay frame: a frame's worth of clock cycles, then one timer tick.
+    ///
+    /// # Returns
+    /// `false` if there is no replay or playback has reached the end.
+    pub fn run_replay_frame(&mut self) -> FrontendResult<bool> {
+        match self.replay.as_mut() {
+            None => return Ok(false),
+            Some(ReplayState::Recording { replay, .. }) => {
+                let input = self.cpu.hardware().input_handle();
+                replay.record(netplay::keys_to_mask(&input));
+            }
+            Some(ReplayState::Playing {
+                replay,
+                frame,
+                input,
+                ..
+            }) => {
+                if *frame >= replay.frames {
+                    return Ok(false);
+                }
+                netplay::apply_mask(&mut input.lock().unwrap(), replay.keys_at(*frame));
+                *frame += 1;
+            }
+        }
+
         self.running = true;
-        self.cpu.cycle()?;
+        netplay::run_frame(&mut self.cpu)?;
+        if let Some(ReplayState::Playing { input, .. }) = &self.replay {
+            input.lock().unwrap().update()?;
+        }
+        Ok(true)
+    }
+
+    /// Stops recording or playback, returning to real-time timers and live input.
+    ///
+    /// # Returns
+    /// The replay, with every recorded frame if it was being recorded.
+    pub fn stop_replay(&mut self) -> Option<Replay> {
+        let replay = match self.replay.take()? {
+            ReplayState::Recording { mut replay, random } => {
+                // Stop logging; the live session doesn't need reproducible bytes
+                replay.random = random.bytes();
+                self.cpu.set_random_source(Box::new(ThreadRandom));
+                replay
+            }
+            ReplayState::Playing {
+                replay, live_input, ..
+            } => {
+                self.cpu.set_input(live_input);
+                replay
+            }
+        };
+        self.cpu.set_realtime_timers(true);
+        self.cpu.timers_mut().sync_to_real_time();
+        Some(replay)
+    }
+
+    /// Checks if a replay is being recorded.
+    pub fn is_recording_replay(&self) -> bool {
+        matches!(self.replay, Some(ReplayState::Recording { .. }))
+    }
+
+    /// Checks if a replay is being played back.
+    pub fn is_playing_replay(&self) -> bool {
+        matches!(self.replay, Some(ReplayState::Playing { .. }))
+    }
+
+    /// Restarts a ROM with seeded RNG and frame-driven timers.
+    fn start_deterministic(
+        &mut self,
+        rom_data: &[u8],
+        behavior: &EmulatorBehaviorConfig,
+        seed: u64,
+    ) -> FrontendResult<()> {
+        self.stop_replay();
+        self.swap_rom(rom_data)?;
+        self.cpu.configure(behavior);
+        self.target_cps = behavior.timing().cycles_per_second();
+        self.cpu.seed_rng(seed);
+        self.cpu.set_realtime_timers(false);
         Ok(())
     }
 
+    /// Turns spin loop detection on or off.
+    ///
+    /// With a watchdog set, running into a spin loop returns
+    /// `EmulatorError::Halted` instead of cycling forever.
+    pub fn set_watchdog(&mut self, config: Option<WatchdogConfig>) {
+        self.watchdog = config.map(Watchdog::new);
+    }
+
+    /// Turns spin loop detection on with the given thresholds.
+    pub fn with_watchdog(mut self, config: WatchdogConfig) -> Self {
+        self.set_watchdog(Some(config));
+        self
+    }
+
+    /// Sets the cheats, which take effect when the next ROM is loaded.
+    ///
+    /// Patch cheats are applied whenever a ROM is loaded or swapped in, and
+    /// freeze cheats after every `run_for_cycles`.
+    pub fn set_cheats(&mut self, cheats: CheatList) {
+        self.cheats = cheats;
+    }
+
+    /// Gets the cheats.
+    pub fn cheats(&self) -> &CheatList {
+        &self.cheats
+    }
+
+    /// Turns a cheat on or off in the running program.
+    ///
+    /// # Returns
+    /// Whether the cheat is now on, or `None` if there is no such cheat.
+    pub fn toggle_cheat(&mut self, index: usize) -> Option<bool> {
+        self.cheats.toggle(index, self.cpu.memory_mut())
+    }
+
+    /// Executes a single CPU cycle.
+    ///
+    /// This also runs while paused, like a debugger's single step, and so
+    /// do the `run_*` methods built on it. A paused step leaves the timers
+    /// frozen and the emulator paused; frontends check `is_paused` before
+    /// running the program on their own.
+    pub fn step(&mut self) -> FrontendResult<CpuEvent> {
+        let event = if self.paused {
+            let realtime = self.cpu.realtime_timers();
+            self.cpu.set_realtime_timers(false);
+            let event = self.cpu.cycle();
+            self.cpu.set_realtime_timers(realtime);
+            event?
+        } else {
+            self.running = true;
+            self.cpu.cycle()?
+        };
+        if let Some(halted) = self.watchdog.as_mut().and_then(|w| w.check(&mut self.cpu)) {
+            return Err(halted.into());
+        }
+        Ok(event)
+    }
+
     /// Runs the emulator for a specified number of cycles.
     pub fn run_cycles(&mut self, cycles: u32) -> FrontendResult<()> {
         for _ in 0..cycles {
@@ -80,6 +535,48 @@ impl SimpleEmulator {
         Ok(())
     }
 
+    /// Runs up to a number of cycles, stopping early if the CPU blocks on a key.
+    ///
+    /// # Returns
+    /// The number of cycles run and the last event.
+    pub fn run_until_blocked(&mut self, cycles: u32) -> FrontendResult<(u32, CpuEvent)> {
+        let mut last = CpuEvent::Executed;
+        for ran in 0..cycles {
+            last = self.step()?;
+            if last == CpuEvent::WaitingForKey {
+                return Ok((ran + 1, last));
+            }
+        }
+        Ok((cycles, last))
+    }
+
+    /// Runs instructions until a clock-cycle budget is used up.
+    ///
+    /// Each instruction is charged according to the CPU's timing model, so
+    /// under simple timing this runs `budget` instructions. Stops early if
+    /// the CPU blocks on a key.
+    ///
+    /// # Returns
+    /// The clock cycles used and the last event.
+    pub fn run_for_cycles(&mut self, budget: u32) -> FrontendResult<(u32, CpuEvent)> {
+        let mut used = 0u32;
+        let mut last = CpuEvent::Executed;
+        while used < budget {
+            last = self.step()?;
+            used = used.saturating_add(self.cpu.last_cost());
+            if last == CpuEvent::WaitingForKey {
+                break;
+            }
+        }
+        self.cheats.enforce(self.cpu.memory_mut());
+        Ok((used, last))
+    }
+
+    /// Creates a channel that receives emulator events.
+    pub fn subscribe_events(&mut self) -> Receiver<EmulatorEvent> {
+        self.cpu.subscribe_channel()
+    }
+
     /// Gets the CPU.
     pub fn cpu(&self) -> &Cpu {
         &self.cpu
@@ -90,12 +587,18 @@ impl SimpleEmulator {
         &mut self.cpu
     }
 
-    /// Sets the target cycles per second.
+    /// Sets the target instructions per second, switching to simple timing.
     pub fn set_target_cps(&mut self, cps: u32) {
         self.target_cps = cps;
+        self.cpu.set_timing_model(TimingModel::Simple(cps));
+    }
+
+    /// Gets a snapshot of the performance counters.
+    pub fn metrics(&self) -> Metrics {
+        self.cpu.metrics()
     }
 
-    /// Gets the target cycles per second.
+    /// Gets the target clock cycles per second.
     pub fn target_cps(&self) -> u32 {
         self.target_cps
     }
@@ -114,6 +617,80 @@ impl SimpleEmulator {
     pub fn get_display_buffer(&self) -> &[bool] {
         self.cpu.get_display_buffer()
     }
+
+    /// Gets the display resolution as (width, height) in pixels.
+    pub fn display_size(&self) -> (usize, usize) {
+        self.cpu.display_size()
+    }
+}
+
+/// Gets the CPU trace length a config needs (only fault reports use the trace).
+fn trace_length(config: &EmulatorConfig) -> usize {
+    if config.debug.fault_report_dir.is_some() {
+        crate::debugger::report::DEFAULT_TRACE_LENGTH
+    } else {
+        0
+    }
+}
+
+/// Checks if a path names Octo source (`.8o`).
+pub fn is_octo_source(path: &std::path::Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("8o"))
+}
+
+/// Gets the variables for a ROM: from a variable file if one is given,
+/// otherwise from the `.vars.toml` file beside the ROM if there is one.
+pub fn load_variables(
+    rom_file: &std::path::Path,
+    variables_file: Option<&std::path::Path>,
+) -> FrontendResult<VariableTable> {
+    match variables_file {
+        Some(path) => VariableTable::load(path),
+        None => Ok(VariableTable::load_for(rom_file)?.unwrap_or_default()),
+    }
+}
+
+/// Gets the symbols for a ROM: from a symbol map if one is given, otherwise
+/// from the labels of Octo source (when the `octo` feature is enabled).
+pub fn load_symbols(
+    rom_file: &std::path::Path,
+    symbols_file: Option<&std::path::Path>,
+) -> FrontendResult<SymbolTable> {
+    if let Some(path) = symbols_file {
+        return SymbolTable::load(path);
+    }
+    if is_octo_source(rom_file) {
+        #[cfg(feature = "octo")]
+        {
+            let source = std::fs::read_to_string(rom_file)?;
+            return Ok(crate::octo::compile_with_symbols(&source)?.1);
+        }
+    }
+    Ok(SymbolTable::new())
+}
+
+/// Reads a ROM file.
+///
+/// Octo source (`.8o`) is compiled first when the `octo` feature is
+/// enabled, so everything that loads ROMs accepts it.
+pub fn read_rom<P: AsRef<std::path::Path>>(path: P) -> FrontendResult<Vec<u8>> {
+    let path = path.as_ref();
+    if !is_octo_source(path) {
+        return Ok(std::fs::read(path)?);
+    }
+
+    #[cfg(feature = "octo")]
+    {
+        let source = std::fs::read_to_string(path)?;
+        Ok(crate::octo::compile(&source)?)
+    }
+    #[cfg(not(feature = "octo"))]
+    Err(std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        "Octo source needs a build with the `octo` feature",
+    )
+    .into())
 }
 
 impl Default for SimpleEmulator {
@@ -125,6 +702,8 @@ impl Default for SimpleEmulator {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::Halted;
+    use crate::hardware::input::ChipKey;
 
     #[test]
     fn test_simple_emulator_creation() {
@@ -167,4 +746,325 @@ mod tests {
         // Clean up
         std::fs::remove_file(&temp_file).unwrap();
     }
+
+    #[test]
+    fn test_run_until_blocked() {
+        let mut emulator = SimpleEmulator::new();
+        emulator
+            .cpu_mut()
+            .load_rom(&[0x60, 0x05, 0xF1, 0x0A, 0x60, 0x06])
+            .unwrap(); // LD V0, 5; LD V1, K; LD V0, 6
+
+        let (ran, event) = emulator.run_until_blocked(100).unwrap();
+        assert_eq!(ran, 2);
+        assert_eq!(event, CpuEvent::WaitingForKey);
+        assert!(emulator.cpu().is_waiting_for_key());
+    }
+
+    #[test]
+    fn test_watchdog_stops_spin_loop() {
+        let mut emulator = SimpleEmulator::new().with_watchdog(
+            WatchdogConfig::new()
+                .with_jump_to_self(false)
+                .with_stall_cycles(20),
+        );
+        emulator.cpu_mut().load_rom(&[0x12, 0x00]).unwrap(); // JP 0x200
+
+        match emulator.run_cycles(1000) {
+            Err(EmulatorError::Halted(Halted::SpinLoop { pc, cycles })) => {
+                assert_eq!(pc, 0x200);
+                assert_eq!(cycles, 20);
+            }
+            other => panic!("Expected a spin loop, got {:?}", other),
+        }
+        assert_eq!(emulator.cpu().instruction_count(), 20);
+
+        // Without a watchdog the loop just keeps running
+        emulator.set_watchdog(None);
+        emulator.run_cycles(1000).unwrap();
+    }
+
+    #[test]
+    fn test_pause_freezes_timers() {
+        let mut emulator = SimpleEmulator::new();
+        emulator.cpu_mut().timers_mut().set_both_timers(10, 10);
+        emulator
+            .cpu_mut()
+            .hardware_mut()
+            .audio()
+            .play_beep()
+            .unwrap();
+
+        emulator.pause();
+        assert!(emulator.is_paused());
+        assert!(!emulator.cpu_mut().hardware_mut().audio().is_playing());
+
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        emulator.resume();
+        assert!(!emulator.is_paused());
+
+        // The paused time is not applied when timers next update
+        emulator.cpu_mut().timers_mut().update();
+        assert_eq!(emulator.cpu().get_timers().get_delay_timer(), 10);
+    }
+
+    #[test]
+    fn test_step_while_paused() {
+        let mut emulator = SimpleEmulator::new();
+        emulator
+            .cpu_mut()
+            .load_rom(&[0x70, 0x01, 0x12, 0x00]) // ADD V0, 1; JP 0x200
+            .unwrap();
+        emulator.cpu_mut().timers_mut().set_both_timers(10, 10);
+        emulator.pause();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+
+        emulator.step().unwrap();
+        emulator.run_for_cycles(3).unwrap();
+        assert_eq!(emulator.cpu().get_state().v[0], 2);
+        assert_eq!(emulator.cpu().get_timers().get_both_timers(), (10, 10));
+        assert!(emulator.cpu().realtime_timers());
+        assert!(emulator.is_paused());
+        assert!(!emulator.is_running());
+
+        // Neither the steps nor the pause count against the timers
+        emulator.resume();
+        emulator.cpu_mut().timers_mut().update();
+        assert_eq!(emulator.cpu().get_timers().get_delay_timer(), 10);
+    }
+
+    #[test]
+    fn test_metrics() {
+        let mut emulator = SimpleEmulator::new();
+        emulator.set_target_cps(240);
+        // LD I, font 0; DRW V0, V0, 5 twice, the second erasing the first; JP 0x206
+        emulator
+            .cpu_mut()
+            .load_rom(&[0xA0, 0x50, 0xD0, 0x05, 0xD0, 0x05, 0x12, 0x06])
+            .unwrap();
+
+        emulator.advance_frame().unwrap();
+        let metrics = emulator.metrics();
+        assert_eq!(metrics.instructions, 4);
+        assert_eq!(metrics.frames, 1);
+        assert_eq!(metrics.sprites_drawn, 2);
+        assert_eq!(metrics.collisions, 1);
+        assert_eq!(metrics.audio_underruns, 0);
+
+        // Counters survive a reset until cleared
+        emulator.cpu_mut().reset();
+        assert_eq!(emulator.metrics().instructions, 4);
+        emulator.cpu_mut().reset_metrics();
+        assert_eq!(emulator.metrics().instructions, 0);
+    }
+
+    #[test]
+    fn test_advance_frame() {
+        let mut emulator = SimpleEmulator::new();
+        emulator.set_target_cps(600);
+        emulator
+            .cpu_mut()
+            .load_rom(&[0x70, 0x01, 0x12, 0x00]) // ADD V0, 1; JP 0x200
+            .unwrap();
+        emulator.cpu_mut().timers_mut().set_both_timers(5, 5);
+        emulator.pause();
+
+        assert_eq!(emulator.advance_frame().unwrap(), 10);
+        assert_eq!(emulator.cpu().get_state().v[0], 5);
+        assert_eq!(emulator.cpu().get_timers().get_both_timers(), (4, 4));
+        assert!(emulator.cpu().realtime_timers());
+        assert!(!emulator.cpu_mut().hardware_mut().audio().is_playing());
+        assert!(emulator.is_paused());
+    }
+
+    #[test]
+    fn test_swap_rom() {
+        let mut emulator = SimpleEmulator::new();
+        // LD V0, 5; LD F, V0; DRW V1, V1, 5
+        emulator
+            .cpu_mut()
+            .load_rom(&[0x60, 0x05, 0xF0, 0x29, 0xD1, 0x15])
+            .unwrap();
+        emulator.run_cycles(3).unwrap();
+        emulator.pause();
+        assert!(emulator.get_display_buffer().contains(&true));
+
+        // A bad ROM leaves the current program alone
+        assert!(emulator.swap_rom(&[]).is_err());
+        assert_eq!(emulator.cpu().get_state().v[0], 5);
+
+        emulator.swap_rom(&[0x61, 0x07]).unwrap();
+        let state = emulator.cpu().get_state();
+        assert_eq!((state.pc, state.v[0]), (0x200, 0));
+        assert!(!emulator.get_display_buffer().contains(&true));
+        assert!(!emulator.is_paused());
+
+        emulator.step().unwrap();
+        assert_eq!(emulator.cpu().get_state().v[1], 7);
+    }
+
+    #[test]
+    fn test_cheats() {
+        use crate::cheats::{Cheat, CheatKind};
+
+        let mut emulator = SimpleEmulator::new();
+        emulator.set_cheats(
+            CheatList::new()
+                .with_cheat(
+                    Cheat::new("Seven", "201:07", CheatKind::Patch)
+                        .unwrap()
+                        .with_enabled(true),
+                )
+                .with_cheat(Cheat::new("Pin", "300:42", CheatKind::Freeze).unwrap()),
+        );
+
+        // LD V1, 5 becomes LD V1, 7 as the ROM loads
+        emulator.swap_rom(&[0x61, 0x05, 0x12, 0x02]).unwrap();
+        emulator.step().unwrap();
+        assert_eq!(emulator.cpu().get_state().v[1], 7);
+
+        assert_eq!(emulator.toggle_cheat(1), Some(true));
+        emulator.run_for_cycles(2).unwrap();
+        assert_eq!(emulator.cpu().get_memory().read_byte(0x300).unwrap(), 0x42);
+        assert_eq!(emulator.toggle_cheat(2), None);
+    }
+
+    #[test]
+    fn test_swap_rom_keeps_config() {
+        let mut config = EmulatorConfig::classic();
+        config.behavior.memory_wraparound = true;
+        config.behavior.cpu_speed = 900;
+        let mut emulator = SimpleEmulator::new_with_config(&config);
+        emulator.cpu_mut().load_rom(&[0x00, 0xE0]).unwrap();
+
+        emulator.swap_rom(&[0x60, 0x01]).unwrap();
+        assert!(emulator.cpu().get_memory().get_wraparound());
+        assert_eq!(emulator.target_cps(), 900);
+    }
+
+    #[test]
+    fn test_replay_reproduces_run() {
+        // RND V0, 0xFF; SKP V1; ADD V2, 1; JP 0x200
+        let rom = [0xC0, 0xFF, 0xE1, 0x9E, 0x72, 0x01, 0x12, 0x00];
+        let behavior = EmulatorBehaviorConfig::default();
+        let live_input = Arc::new(Mutex::new(SoftwareInput::new()));
+        let mut emulator = SimpleEmulator::new();
+        emulator.cpu_mut().set_input(live_input.clone());
+
+        emulator.record_replay(&rom, &behavior, 42).unwrap();
+        assert!(emulator.is_recording_replay());
+        for frame in 0..30 {
+            if frame == 10 {
+                live_input.lock().unwrap().press_key(ChipKey::Key0);
+            }
+            if frame == 20 {
+                live_input.lock().unwrap().release_key(ChipKey::Key0);
+            }
+            assert!(emulator.run_replay_frame().unwrap());
+        }
+        let recorded = emulator.cpu().get_state();
+        let replay = emulator.stop_replay().unwrap();
+        assert_eq!(replay.frames, 30);
+        assert_eq!(replay.inputs.len(), 2);
+        assert!(!replay.random.is_empty());
+
+        // Played back from a file with the live key held, on another emulator.
+        // The recorded RND bytes are used rather than the seed.
+        let mut replay = Replay::from_toml(&replay.to_toml().unwrap()).unwrap();
+        replay.seed ^= 1;
+        let mut emulator = SimpleEmulator::new();
+        emulator.cpu_mut().set_input(live_input.clone());
+        live_input.lock().unwrap().press_key(ChipKey::Key0);
+        emulator.play_replay(replay, &rom).unwrap();
+        while emulator.run_replay_frame().unwrap() {}
+
+        let played = emulator.cpu().get_state();
+        assert_eq!(played.v, recorded.v);
+        assert_eq!(played.pc, recorded.pc);
+        assert_eq!(played.delay_timer, recorded.delay_timer);
+
+        // Stopping hands the CPU back to the live input
+        emulator.stop_replay();
+        assert!(!emulator.is_playing_replay());
+        assert!(emulator
+            .cpu()
+            .hardware()
+            .input_handle()
+            .lock()
+            .unwrap()
+            .is_key_pressed(ChipKey::Key0));
+    }
+
+    #[test]
+    fn test_play_replay_rejects_other_rom() {
+        let replay = Replay::new(&[0x12, 0x00], &EmulatorBehaviorConfig::default(), 1);
+        let mut emulator = SimpleEmulator::new();
+        assert!(matches!(
+            emulator.play_replay(replay, &[0x00, 0xE0]),
+            Err(EmulatorError::Replay(_))
+        ));
+        assert!(!emulator.is_playing_replay());
+    }
+
+    #[test]
+    fn test_run_for_cycles_with_vip_timing() {
+        let config = EmulatorConfig {
+            behavior: EmulatorBehaviorConfig::cosmac_vip(),
+            ..Default::default()
+        };
+        let mut emulator = SimpleEmulator::new_with_config(&config);
+        assert_eq!(emulator.target_cps(), 220_080);
+
+        emulator
+            .cpu_mut()
+            .load_rom(&[0x60, 0x05, 0x70, 0x01, 0x12, 0x02]) // LD V0, 5; ADD V0, 1; JP 0x202
+            .unwrap();
+
+        // 6 + 10 + 12 + 10 + 12 machine cycles
+        let (used, _) = emulator.run_for_cycles(50).unwrap();
+        assert_eq!(used, 50);
+        assert_eq!(emulator.cpu().get_state().v[0], 7);
+        assert_eq!(emulator.cpu().elapsed_cycles(), 50);
+    }
+
+    #[test]
+    fn test_apply_config_delta() {
+        let mut emulator = SimpleEmulator::new();
+        let red = Color::new(255, 0, 0, 255);
+        let delta = ConfigDelta {
+            cpu_speed: Some(1200),
+            foreground_color: Some(red),
+            volume: Some(0.25),
+            frequency: Some(880.0),
+            restart_required: vec!["keyboard".to_string()],
+            ..Default::default()
+        };
+
+        emulator.apply_config_delta(&delta).unwrap();
+
+        assert_eq!(emulator.target_cps(), 1200);
+        assert_eq!(emulator.palette().0, red);
+        let audio = emulator.cpu_mut().hardware_mut().audio();
+        assert_eq!(audio.get_volume(), 0.25);
+        assert_eq!(audio.get_frequency(), 880.0);
+    }
+
+    #[test]
+    fn test_apply_config_delta_keeps_vip_timing() {
+        let config = EmulatorConfig {
+            behavior: EmulatorBehaviorConfig::cosmac_vip(),
+            ..Default::default()
+        };
+        let mut emulator = SimpleEmulator::new_with_config(&config);
+        let delta = ConfigDelta {
+            cpu_speed: Some(1200),
+            ..Default::default()
+        };
+
+        emulator.apply_config_delta(&delta).unwrap();
+
+        assert_eq!(emulator.cpu().timing_model(), TimingModel::CosmacVip);
+        assert_eq!(emulator.target_cps(), 220_080);
+        assert_eq!(emulator.cpu().cycles_per_frame(), 220_080 / 60);
+    }
 }
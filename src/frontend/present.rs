@@ -0,0 +1,301 @@
+//! Display backends that put finished frames on the window.
+//!
+//! The GUI composes each frame into an RGBA buffer the size of the window
+//! and leaves showing it to a [`PresentBackend`]. `pixels` (wgpu) is always
+//! built in; the CPU-only softbuffer backend is available with the
+//! `softbuffer` Cargo feature, for machines whose GPU drivers don't work.
+
+use pixels::{wgpu, Pixels, PixelsBuilder, SurfaceTexture};
+use winit::window::Window;
+
+use crate::error::{GraphicsError, GraphicsResult};
+use crate::graphics::{DisplayBackend, PresentMode};
+#[cfg(feature = "softbuffer")]
+use crate::logging::GFX;
+
+/// A window surface that shows RGBA frames.
+pub trait PresentBackend {
+    /// Gets the kind of backend.
+    fn kind(&self) -> DisplayBackend;
+
+    /// Gets the size of the frame in pixels, which matches the window.
+    fn size(&self) -> (usize, usize);
+
+    /// Gets the frame to draw the next picture into, as RGBA bytes.
+    fn frame_mut(&mut self) -> &mut [u8];
+
+    /// Resizes the surface and the frame to a new window size.
+    fn resize(&mut self, width: u32, height: u32) -> GraphicsResult<()>;
+
+    /// Shows the frame in the window.
+    fn present(&mut self) -> GraphicsResult<()>;
+
+    /// Maps a position in the window to a pixel of the frame.
+    ///
+    /// # Returns
+    /// The pixel, or `None` if the position is outside the frame.
+    fn window_pos_to_pixel(&self, position: (f32, f32)) -> Option<(usize, usize)> {
+        let (width, height) = self.size();
+        let (x, y) = position;
+        (x >= 0.0 && y >= 0.0 && (x as usize) < width && (y as usize) < height)
+            .then_some((x as usize, y as usize))
+    }
+}
+
+/// Opens the backend selected in the config on a window.
+///
+/// If the GPU surface can't be created and softbuffer is built in, the
+/// window falls back to it rather than failing to start.
+///
+/// # Arguments
+/// * `window` - Window to draw to
+/// * `backend` - Backend to open
+/// * `present_mode` - How frames wait for the display's refresh, where the
+///   backend supports it
+pub fn open(
+    window: &Window,
+    backend: DisplayBackend,
+    present_mode: PresentMode,
+) -> GraphicsResult<Box<dyn PresentBackend>> {
+    match backend {
+        DisplayBackend::Pixels => match PixelsBackend::new(window, present_mode) {
+            Ok(pixels) => Ok(Box::new(pixels)),
+            #[cfg(feature = "softbuffer")]
+            Err(e) => {
+                tracing::warn!(target: GFX, "{}; falling back to the softbuffer backend", e);
+                Ok(Box::new(SoftbufferBackend::new(window)?))
+            }
+            #[cfg(not(feature = "softbuffer"))]
+            Err(e) => Err(e),
+        },
+        #[cfg(feature = "softbuffer")]
+        DisplayBackend::Softbuffer => Ok(Box::new(SoftbufferBackend::new(window)?)),
+        #[cfg(not(feature = "softbuffer"))]
+        DisplayBackend::Softbuffer => Err(GraphicsError::BackendFailed(format!(
+            "the {} display backend is not available in this build (enable the '{}' feature)",
+            backend.name(),
+            backend.name()
+        ))),
+    }
+}
+
+/// GPU surface drawn through `pixels`, with a frame the size of the window.
+pub struct PixelsBackend {
+    pixels: Pixels,
+    size: (usize, usize),
+}
+
+impl PixelsBackend {
+    /// Creates the window's pixel buffer and surface.
+    ///
+    /// The present mode can't be changed afterwards, so toggling vsync
+    /// builds a new one.
+    pub fn new(window: &Window, present_mode: PresentMode) -> GraphicsResult<Self> {
+        let size = window.inner_size();
+        let surface_texture = SurfaceTexture::new(size.width, size.height, window);
+        let mode = match present_mode {
+            PresentMode::Vsync => wgpu::PresentMode::AutoVsync,
+            PresentMode::NoVsync => wgpu::PresentMode::AutoNoVsync,
+            PresentMode::Fifo => wgpu::PresentMode::Fifo,
+            PresentMode::Mailbox => wgpu::PresentMode::Mailbox,
+            PresentMode::Immediate => wgpu::PresentMode::Immediate,
+        };
+        let pixels = PixelsBuilder::new(size.width, size.height, surface_texture)
+            .present_mode(mode)
+            .build()
+            .map_err(|e| GraphicsError::PixelsInitializationFailed(e.to_string()))?;
+        Ok(Self {
+            pixels,
+            size: (size.width as usize, size.height as usize),
+        })
+    }
+}
+
+impl PresentBackend for PixelsBackend {
+    fn kind(&self) -> DisplayBackend {
+        DisplayBackend::Pixels
+    }
+
+    fn size(&self) -> (usize, usize) {
+        self.size
+    }
+
+    fn frame_mut(&mut self) -> &mut [u8] {
+        self.pixels.frame_mut()
+    }
+
+    fn resize(&mut self, width: u32, height: u32) -> GraphicsResult<()> {
+        self.pixels
+            .resize_surface(width, height)
+            .and_then(|_| self.pixels.resize_buffer(width, height))
+            .map_err(|e| GraphicsError::ResizeFailed(e.to_string()))?;
+        self.size = (width as usize, height as usize);
+        Ok(())
+    }
+
+    fn present(&mut self) -> GraphicsResult<()> {
+        self.pixels
+            .render()
+            .map_err(|e| GraphicsError::RenderFailed(e.to_string()))
+    }
+
+    fn window_pos_to_pixel(&self, position: (f32, f32)) -> Option<(usize, usize)> {
+        self.pixels.window_pos_to_pixel(position).ok()
+    }
+}
+
+/// CPU-only window surface drawn through `softbuffer`.
+///
+/// Frames are drawn as RGBA like the other backends and packed into the
+/// surface's 32-bit pixels when presented. Presenting doesn't wait for
+/// the display's refresh, so the present mode has no effect.
+#[cfg(feature = "softbuffer")]
+pub struct SoftbufferBackend {
+    // Dropped after the surface that was created from it
+    surface: softbuffer::Surface,
+    _context: softbuffer::Context,
+    frame: Vec<u8>,
+    size: (usize, usize),
+}
+
+#[cfg(feature = "softbuffer")]
+impl SoftbufferBackend {
+    /// Creates a surface for the window.
+    pub fn new(window: &Window) -> GraphicsResult<Self> {
+        let failed = |e: softbuffer::SoftBufferError| {
+            GraphicsError::BackendFailed(format!("softbuffer: {}", e))
+        };
+        // SAFETY: the GUI keeps the window alive for as long as the event
+        // loop, which owns the backend
+        let context = unsafe { softbuffer::Context::new(window) }.map_err(failed)?;
+        let surface = unsafe { softbuffer::Surface::new(&context, window) }.map_err(failed)?;
+        let mut backend = Self {
+            surface,
+            _context: context,
+            frame: Vec::new(),
+            size: (0, 0),
+        };
+        let size = window.inner_size();
+        backend.resize(size.width, size.height)?;
+        Ok(backend)
+    }
+}
+
+#[cfg(feature = "softbuffer")]
+impl PresentBackend for SoftbufferBackend {
+    fn kind(&self) -> DisplayBackend {
+        DisplayBackend::Softbuffer
+    }
+
+    fn size(&self) -> (usize, usize) {
+        self.size
+    }
+
+    fn frame_mut(&mut self) -> &mut [u8] {
+        &mut self.frame
+    }
+
+    fn resize(&mut self, width: u32, height: u32) -> GraphicsResult<()> {
+        let (Some(surface_width), Some(surface_height)) = (
+            std::num::NonZeroU32::new(width),
+            std::num::NonZeroU32::new(height),
+        ) else {
+            return Err(GraphicsError::ResizeFailed(format!(
+                "window size {}x{} is empty",
+                width, height
+            )));
+        };
+        self.surface
+            .resize(surface_width, surface_height)
+            .map_err(|e| GraphicsError::ResizeFailed(e.to_string()))?;
+        self.size = (width as usize, height as usize);
+        self.frame.resize(self.size.0 * self.size.1 * 4, 0);
+        Ok(())
+    }
+
+    fn present(&mut self) -> GraphicsResult<()> {
+        let mut buffer = self
+            .surface
+            .buffer_mut()
+            .map_err(|e| GraphicsError::RenderFailed(e.to_string()))?;
+        pack_rgba(&self.frame, &mut buffer);
+        buffer
+            .present()
+            .map_err(|e| GraphicsError::RenderFailed(e.to_string()))
+    }
+}
+
+/// Packs RGBA bytes into `0RGB` words, the layout CPU window surfaces use.
+#[cfg_attr(not(any(feature = "softbuffer", test)), allow(dead_code))]
+fn pack_rgba(rgba: &[u8], words: &mut [u32]) {
+    for (word, pixel) in words.iter_mut().zip(rgba.chunks_exact(4)) {
+        *word = u32::from(pixel[0]) << 16 | u32::from(pixel[1]) << 8 | u32::from(pixel[2]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Frame {
+        size: (usize, usize),
+    }
+
+    impl PresentBackend for Frame {
+        fn kind(&self) -> DisplayBackend {
+            DisplayBackend::Softbuffer
+        }
+
+        fn size(&self) -> (usize, usize) {
+            self.size
+        }
+
+        fn frame_mut(&mut self) -> &mut [u8] {
+            &mut []
+        }
+
+        fn resize(&mut self, width: u32, height: u32) -> GraphicsResult<()> {
+            self.size = (width as usize, height as usize);
+            Ok(())
+        }
+
+        fn present(&mut self) -> GraphicsResult<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_display_backend_config() {
+        #[derive(serde::Deserialize)]
+        struct Wrapper {
+            backend: DisplayBackend,
+        }
+
+        let parsed: Wrapper = toml::from_str(r#"backend = "softbuffer""#).unwrap();
+        assert_eq!(parsed.backend, DisplayBackend::Softbuffer);
+        assert_eq!(DisplayBackend::default(), DisplayBackend::Pixels);
+        assert!(DisplayBackend::Pixels.is_available());
+        assert_eq!(
+            DisplayBackend::Softbuffer.is_available(),
+            cfg!(feature = "softbuffer")
+        );
+
+        let mut words = [0; 2];
+        pack_rgba(
+            &[0x12, 0x34, 0x56, 0xFF, 0xFF, 0x00, 0x80, 0x00],
+            &mut words,
+        );
+        assert_eq!(words, [0x123456, 0xFF0080]);
+    }
+
+    #[test]
+    fn test_window_pos_to_pixel() {
+        let mut frame = Frame { size: (0, 0) };
+        frame.resize(640, 320).unwrap();
+        assert_eq!(frame.window_pos_to_pixel((0.0, 0.0)), Some((0, 0)));
+        assert_eq!(frame.window_pos_to_pixel((639.9, 10.5)), Some((639, 10)));
+        assert_eq!(frame.window_pos_to_pixel((640.0, 10.0)), None);
+        assert_eq!(frame.window_pos_to_pixel((-1.0, 10.0)), None);
+        assert_eq!(frame.window_pos_to_pixel((10.0, 320.0)), None);
+    }
+}
@@ -0,0 +1,280 @@
+//! Guessing which interpreter a ROM was written for.
+//!
+//! Most ROMs don't say which quirks they expect. [`detect_quirks`] runs a
+//! ROM briefly under each [`Variant`] with no one at the keyboard, and
+//! scores each run on what a working program usually does: it doesn't
+//! fault, it draws something and keeps changing the screen, it reaches a
+//! good part of its code and it doesn't lock up in a jump to itself before
+//! drawing anything. The best scoring variant is the suggestion, and the
+//! gap to the variants that did worse is the confidence.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use super::{EmulatorBehaviorConfig, Variant};
+use crate::emulator::{CpuEvent, Pcg32};
+use crate::hardware::ChipKey;
+
+/// Default number of instructions each variant runs for.
+pub const DEFAULT_DETECT_CYCLES: u64 = 50_000;
+
+/// Default confidence needed before a suggestion is applied.
+pub const DEFAULT_CONFIDENCE_THRESHOLD: f32 = 0.25;
+
+/// Scores this close to the best count as a tie.
+const TIE_MARGIN: f32 = 0.05;
+
+/// Frames of screen changes that count as fully active.
+const ACTIVE_FRAMES: u32 = 10;
+
+/// What happened when a ROM ran under one variant.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuirkScore {
+    /// The variant whose quirks were used.
+    pub variant: Variant,
+
+    /// How sensible the run looked, from 0 to 1.
+    pub score: f32,
+
+    /// Number of instructions executed.
+    pub instructions: u64,
+
+    /// Number of frames in which the screen changed.
+    pub frames_changed: u32,
+
+    /// Number of distinct instruction addresses reached.
+    pub addresses: usize,
+
+    /// The fault that stopped the run, if one did.
+    pub fault: Option<String>,
+
+    /// Whether the run stopped in a jump to itself before drawing.
+    pub spin_locked: bool,
+}
+
+impl QuirkScore {
+    /// Runs a ROM under a variant and scores the run.
+    pub fn measure(rom: &[u8], variant: Variant, cycles: u64) -> Self {
+        let behavior = EmulatorBehaviorConfig::for_variant(variant);
+        let cycles_per_tick = (behavior.cpu_speed / behavior.timer_frequency.max(1)).max(1) as u64;
+        let mut cpu = crate::Cpu::new_with_config(&behavior);
+        cpu.set_realtime_timers(false);
+        cpu.set_random_source(Box::new(Pcg32::new(0)));
+
+        let mut result = Self {
+            variant,
+            score: 0.0,
+            instructions: 0,
+            frames_changed: 0,
+            addresses: 0,
+            fault: None,
+            spin_locked: false,
+        };
+        if let Err(e) = cpu.load_rom(rom) {
+            result.fault = Some(e.to_string());
+            return result;
+        }
+
+        let mut addresses = HashSet::new();
+        let mut screen = display_hash(cpu.get_display_buffer());
+        let mut keys = 0u8;
+        while result.instructions < cycles {
+            addresses.insert(cpu.get_state().pc);
+            match cpu.cycle() {
+                Ok(CpuEvent::WaitingForKey) => {
+                    // Press the keys in turn to get past title screens
+                    let key = ChipKey::from_u8(keys % 16).unwrap_or(ChipKey::Key0);
+                    keys = keys.wrapping_add(1);
+                    if let Err(e) = cpu.resume_with_key(key) {
+                        result.fault = Some(e.to_string());
+                        break;
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    result.fault = Some(e.to_string());
+                    break;
+                }
+            }
+            result.instructions += 1;
+            if result.instructions.is_multiple_of(cycles_per_tick) {
+                cpu.timers_mut().update_by_ticks(1);
+                let hash = display_hash(cpu.get_display_buffer());
+                if hash != screen {
+                    result.frames_changed += 1;
+                    screen = hash;
+                }
+            }
+            if cpu.is_halted() {
+                if display_hash(cpu.get_display_buffer()) != screen {
+                    result.frames_changed += 1;
+                }
+                result.spin_locked = result.frames_changed == 0;
+                break;
+            }
+        }
+        result.addresses = addresses.len();
+        result.score = result.rate(rom.len());
+        result
+    }
+
+    /// Works out the score of a finished run.
+    fn rate(&self, rom_size: usize) -> f32 {
+        if self.fault.is_some() {
+            return 0.0;
+        }
+        let mut score = 0.0;
+        if !self.spin_locked {
+            score += 0.3;
+        }
+        if self.frames_changed > 0 {
+            score += 0.4;
+        }
+        score += 0.15 * (self.frames_changed.min(ACTIVE_FRAMES) as f32 / ACTIVE_FRAMES as f32);
+        // Much of a ROM is data, so reaching half its words counts as full
+        let words = (rom_size / 4).max(1);
+        score += 0.15 * (self.addresses.min(words) as f32 / words as f32);
+        score
+    }
+}
+
+/// Scores for every variant, best first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuirkReport {
+    /// Scores, best first; ties keep the order of [`Variant::all`].
+    pub scores: Vec<QuirkScore>,
+}
+
+impl QuirkReport {
+    /// Gets the variants that scored as well as the best.
+    pub fn best(&self) -> impl Iterator<Item = &QuirkScore> {
+        let top = self.top_score();
+        self.scores
+            .iter()
+            .take_while(move |score| score.score >= top - TIE_MARGIN)
+    }
+
+    /// Gets the suggested variant: the first of the best.
+    pub fn suggested(&self) -> Option<Variant> {
+        self.best().next().map(|score| score.variant)
+    }
+
+    /// Gets how much better the best variants did than the rest, from 0 to 1.
+    ///
+    /// This is 0 when every variant did equally well, since the ROM then
+    /// doesn't depend on any of the quirks that were tried.
+    pub fn confidence(&self) -> f32 {
+        let top = self.top_score();
+        let rest = self
+            .scores
+            .iter()
+            .map(|score| score.score)
+            .find(|&score| score < top - TIE_MARGIN);
+        rest.map_or(0.0, |rest| top - rest)
+    }
+
+    /// Gets the suggestion if the confidence reaches `threshold`.
+    pub fn confident(&self, threshold: f32) -> Option<Variant> {
+        self.suggested().filter(|_| self.confidence() >= threshold)
+    }
+
+    /// Gets the best score.
+    fn top_score(&self) -> f32 {
+        self.scores.first().map_or(0.0, |score| score.score)
+    }
+}
+
+impl fmt::Display for QuirkReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "  {:<12} {:>5} {:>8} {:>7} {:>9}  Notes",
+            "Variant", "Score", "Instrs", "Frames", "Addresses"
+        )?;
+        for score in &self.scores {
+            let note = match (&score.fault, score.spin_locked) {
+                (Some(fault), _) => format!("fault: {}", fault),
+                (None, true) => "stuck before drawing".to_string(),
+                (None, false) => String::new(),
+            };
+            writeln!(
+                f,
+                "  {:<12} {:>5.2} {:>8} {:>7} {:>9}  {}",
+                score.variant.name(),
+                score.score,
+                score.instructions,
+                score.frames_changed,
+                score.addresses,
+                note
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs a ROM under every variant for up to `cycles` instructions each and
+/// scores the runs.
+pub fn detect_quirks(rom: &[u8], cycles: u64) -> QuirkReport {
+    let mut scores: Vec<QuirkScore> = Variant::all()
+        .into_iter()
+        .map(|variant| QuirkScore::measure(rom, variant, cycles))
+        .collect();
+    // A stable sort keeps the variant order among ties
+    scores.sort_by(|a, b| b.score.total_cmp(&a.score));
+    QuirkReport { scores }
+}
+
+/// Hashes the display so frames can be compared cheaply.
+fn display_hash(buffer: &[bool]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    buffer.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_quirks() {
+        // BXNN lands on a loop that keeps drawing, but with the
+        // jump_with_vx quirk it lands on a jump to itself instead
+        let rom = [
+            0x60, 0x00, // 200: LD V0, 0
+            0x62, 0x04, // 202: LD V2, 4
+            0xB2, 0x0A, // 204: JP V0, 0x20A
+            0x00, 0x00, //
+            0x00, 0x00, //
+            0x12, 0x10, // 20A: JP 0x210
+            0x00, 0x00, //
+            0x12, 0x0E, // 20E: JP 0x20E
+            0xA2, 0x18, // 210: LD I, 0x218
+            0xD0, 0x01, // 212: DRW V0, V0, 1
+            0x70, 0x01, // 214: ADD V0, 1
+            0x12, 0x12, // 216: JP 0x212
+            0xFF, 0x00, // 218: sprite
+        ];
+        let report = detect_quirks(&rom, 2_000);
+        assert_eq!(report.scores.len(), Variant::all().len());
+        assert_eq!(report.suggested(), Some(Variant::Chip8));
+        assert_eq!(
+            report.confident(DEFAULT_CONFIDENCE_THRESHOLD),
+            Some(Variant::Chip8)
+        );
+        let chip48 = report
+            .scores
+            .iter()
+            .find(|score| score.variant == Variant::Chip48)
+            .unwrap();
+        assert!(chip48.spin_locked);
+        assert!(report.best().all(|score| score.frames_changed > 0));
+
+        // A ROM that spins straight away does the same everywhere
+        let report = detect_quirks(&[0x12, 0x00], 1_000);
+        assert!(report.scores.iter().all(|score| score.spin_locked));
+        assert_eq!(report.confidence(), 0.0);
+        assert_eq!(report.confident(0.1), None);
+    }
+}
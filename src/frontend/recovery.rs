@@ -0,0 +1,308 @@
+//! Auto-save and crash recovery.
+//!
+//! While a ROM runs, the GUI periodically writes its state to the ROM's
+//! auto-save file and keeps a small session manifest in the data
+//! directory. The manifest is marked clean when the GUI exits normally, so
+//! a manifest that isn't clean on the next launch means the frontend
+//! crashed or was killed, and its auto-save can be offered for resuming.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::emulator::Snapshot;
+use crate::error::Result;
+use crate::logging::APP;
+use crate::savestate::SaveStateManager;
+
+/// File name of the session manifest in the data directory.
+pub const MANIFEST_FILE: &str = "session.toml";
+
+/// Auto-save configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutosaveConfig {
+    /// Whether the running ROM's state is saved periodically.
+    pub enabled: bool,
+
+    /// Seconds between auto-saves.
+    pub interval_secs: u32,
+}
+
+impl Default for AutosaveConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_secs: 30,
+        }
+    }
+}
+
+impl AutosaveConfig {
+    /// Gets the time between auto-saves, or `None` if auto-save is off.
+    pub fn interval(&self) -> Option<Duration> {
+        (self.enabled && self.interval_secs > 0)
+            .then(|| Duration::from_secs(self.interval_secs as u64))
+    }
+}
+
+/// Record of the session a GUI is running.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionManifest {
+    /// SHA-1 of the running ROM, as lowercase hex.
+    pub rom_sha1: String,
+
+    /// Where the ROM was loaded from.
+    #[serde(default)]
+    pub rom_path: Option<PathBuf>,
+
+    /// When the session started, in seconds since the Unix epoch.
+    #[serde(default)]
+    pub started_at: u64,
+
+    /// When the ROM was last auto-saved, in seconds since the Unix epoch.
+    #[serde(default)]
+    pub autosaved_at: Option<u64>,
+
+    /// Whether the GUI exited normally.
+    #[serde(default)]
+    pub clean_exit: bool,
+}
+
+impl SessionManifest {
+    /// Gets the manifest's location in the default data directory.
+    pub fn default_path() -> PathBuf {
+        crate::paths::Paths::new().data_dir().join(MANIFEST_FILE)
+    }
+
+    /// Loads a manifest file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(toml::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    /// Saves the manifest to a file, creating its directory if needed.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        if let Some(dir) = path.as_ref().parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/// Auto-saves the running ROM and finds sessions that didn't exit cleanly.
+#[derive(Debug)]
+pub struct Autosaver {
+    /// Time between auto-saves, or `None` if auto-save is off.
+    interval: Option<Duration>,
+
+    /// Where the manifest is kept.
+    manifest_path: PathBuf,
+
+    /// Manifest left by the previous launch, if it ended badly.
+    previous: Option<SessionManifest>,
+
+    /// Manifest of the running session.
+    current: Option<SessionManifest>,
+
+    /// When the state was last saved or the session started.
+    last_save: Instant,
+}
+
+impl Autosaver {
+    /// Creates an autosaver, reading the manifest the previous launch left.
+    ///
+    /// # Arguments
+    /// * `config` - Auto-save settings
+    /// * `manifest_path` - Where the session manifest is kept
+    pub fn new(config: &AutosaveConfig, manifest_path: impl Into<PathBuf>) -> Self {
+        let manifest_path = manifest_path.into();
+        let previous = SessionManifest::load(&manifest_path)
+            .ok()
+            .filter(|manifest| !manifest.clean_exit && manifest.autosaved_at.is_some());
+
+        Self {
+            interval: config.interval(),
+            manifest_path,
+            previous,
+            current: None,
+            last_save: Instant::now(),
+        }
+    }
+
+    /// Checks if auto-save is on.
+    pub fn is_enabled(&self) -> bool {
+        self.interval.is_some()
+    }
+
+    /// Takes the auto-save of a crashed session of the running ROM.
+    ///
+    /// The previous session is only offered once, and only if it ran the
+    /// same ROM and its auto-save still loads.
+    pub fn recover(&mut self, states: &SaveStateManager) -> Option<Snapshot> {
+        if !self.is_enabled() {
+            return None;
+        }
+        self.previous
+            .take_if(|manifest| manifest.rom_sha1 == states.rom_sha1())?;
+
+        match states.load_autosave() {
+            Ok(snapshot) => Some(snapshot),
+            Err(e) => {
+                tracing::warn!(target: APP, "Can't recover the previous session: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Starts a session for the ROM `states` manages, marking it unclean
+    /// until [`finish`](Self::finish) is called.
+    ///
+    /// # Arguments
+    /// * `rom_path` - Where the ROM was loaded from
+    /// * `states` - Save states of the ROM
+    /// * `now` - Current time
+    pub fn start(
+        &mut self,
+        rom_path: Option<&Path>,
+        states: &SaveStateManager,
+        now: Instant,
+    ) -> Result<()> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+
+        let manifest = SessionManifest {
+            rom_sha1: states.rom_sha1().to_string(),
+            rom_path: rom_path.map(Path::to_path_buf),
+            started_at: unix_time(),
+            autosaved_at: None,
+            clean_exit: false,
+        };
+        manifest.save(&self.manifest_path)?;
+        self.current = Some(manifest);
+        self.last_save = now;
+        Ok(())
+    }
+
+    /// Checks if the running session is due for an auto-save.
+    pub fn is_due(&self, now: Instant) -> bool {
+        match (self.interval, &self.current) {
+            (Some(interval), Some(_)) => now.duration_since(self.last_save) >= interval,
+            _ => false,
+        }
+    }
+
+    /// Writes the ROM's auto-save and records it in the manifest.
+    ///
+    /// # Returns
+    /// The path of the auto-save file.
+    pub fn save(
+        &mut self,
+        snapshot: Snapshot,
+        states: &SaveStateManager,
+        now: Instant,
+    ) -> Result<PathBuf> {
+        self.last_save = now;
+        let path = states.save_autosave(snapshot)?;
+        if let Some(manifest) = self.current.as_mut() {
+            manifest.autosaved_at = Some(unix_time());
+            manifest.save(&self.manifest_path)?;
+        }
+        Ok(path)
+    }
+
+    /// Marks the running session as having exited cleanly.
+    pub fn finish(&mut self) -> Result<()> {
+        if let Some(mut manifest) = self.current.take() {
+            manifest.clean_exit = true;
+            manifest.save(&self.manifest_path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Gets the current time in seconds since the Unix epoch.
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Cpu;
+
+    const ROM: [u8; 4] = [0x60, 0x2A, 0x12, 0x02];
+
+    #[test]
+    fn test_autosave_interval() {
+        let config = AutosaveConfig::default();
+        assert_eq!(config.interval(), Some(Duration::from_secs(30)));
+
+        let off = AutosaveConfig {
+            enabled: false,
+            ..config.clone()
+        };
+        assert_eq!(off.interval(), None);
+        let zero = AutosaveConfig {
+            interval_secs: 0,
+            ..config
+        };
+        assert_eq!(zero.interval(), None);
+    }
+
+    #[test]
+    fn test_crashed_session_is_recovered_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest_path = dir.path().join(MANIFEST_FILE);
+        let states = SaveStateManager::new(dir.path().join("states"), &ROM);
+        let config = AutosaveConfig::default();
+
+        let mut cpu = Cpu::new();
+        cpu.load_rom(&ROM).unwrap();
+        cpu.cycle().unwrap();
+
+        // A session that auto-saved and never finished
+        let start = Instant::now();
+        let mut crashed = Autosaver::new(&config, &manifest_path);
+        crashed.start(None, &states, start).unwrap();
+        assert!(!crashed.is_due(start + Duration::from_secs(29)));
+        assert!(crashed.is_due(start + Duration::from_secs(30)));
+        crashed
+            .save(cpu.snapshot(), &states, start + Duration::from_secs(30))
+            .unwrap();
+        assert!(!crashed.is_due(start + Duration::from_secs(31)));
+        drop(crashed);
+
+        // Another ROM doesn't get the offer
+        let mut next = Autosaver::new(&config, &manifest_path);
+        let other = SaveStateManager::new(dir.path().join("states"), &[0x00, 0xE0]);
+        assert!(next.recover(&other).is_none());
+
+        let mut next = Autosaver::new(&config, &manifest_path);
+        assert_eq!(next.recover(&states), Some(cpu.snapshot()));
+        assert!(next.recover(&states).is_none());
+    }
+
+    #[test]
+    fn test_clean_exit_is_not_recovered() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest_path = dir.path().join(MANIFEST_FILE);
+        let states = SaveStateManager::new(dir.path(), &ROM);
+        let config = AutosaveConfig::default();
+
+        let mut session = Autosaver::new(&config, &manifest_path);
+        session.start(None, &states, Instant::now()).unwrap();
+        session
+            .save(Cpu::new().snapshot(), &states, Instant::now())
+            .unwrap();
+        session.finish().unwrap();
+        assert!(SessionManifest::load(&manifest_path).unwrap().clean_exit);
+
+        let mut next = Autosaver::new(&config, &manifest_path);
+        assert!(next.recover(&states).is_none());
+    }
+}
@@ -0,0 +1,208 @@
+//! Cache of ROM analysis for the browser.
+//!
+//! Hashing and analyzing every ROM in a large directory takes a while, so
+//! the browser keeps the results in a JSON file in the data directory. An
+//! entry is reused as long as the file's modification time and size are
+//! unchanged; anything else is analyzed again in the background.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+
+use super::browser::RomEntry;
+use super::FrontendResult;
+use crate::analysis::InstructionSet;
+use crate::logging::APP;
+
+/// Name of the cache file in the data directory.
+pub const CACHE_FILE: &str = "rom-cache.json";
+
+/// What identifies a version of a file: its modification time and size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct FileStamp {
+    /// Modification time in nanoseconds since the Unix epoch, or 0 if the
+    /// platform doesn't report it.
+    pub modified: u64,
+
+    /// File size in bytes.
+    pub size: u64,
+}
+
+impl FileStamp {
+    /// Gets the stamp of a file from its metadata.
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |since| since.as_nanos() as u64);
+        Self {
+            modified,
+            size: metadata.len(),
+        }
+    }
+}
+
+/// Analysis of one ROM, as stored in the cache.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CachedRom {
+    /// Version of the file the analysis is for.
+    pub stamp: FileStamp,
+
+    /// SHA-1 digest as a lowercase hex string.
+    pub sha1: String,
+
+    /// Instruction sets the ROM appears to use, beyond base Chip-8.
+    #[serde(default)]
+    pub variant_hints: Vec<InstructionSet>,
+}
+
+/// ROM analysis results keyed by path.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RomCache {
+    /// Results by ROM path.
+    #[serde(default)]
+    roms: BTreeMap<PathBuf, CachedRom>,
+}
+
+impl RomCache {
+    /// Creates an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the cache's location in the default cache directory.
+    pub fn default_path() -> PathBuf {
+        crate::paths::Paths::new().cache_dir().join(CACHE_FILE)
+    }
+
+    /// Loads a cache file.
+    ///
+    /// # Returns
+    /// The cache, or an empty one if the file doesn't exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> FrontendResult<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => Ok(serde_json::from_str(&content).map_err(std::io::Error::from)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Loads a cache file, starting afresh if it can't be read.
+    pub fn load_or_default<P: AsRef<Path>>(path: P) -> Self {
+        Self::load(&path).unwrap_or_else(|e| {
+            tracing::warn!(target: APP, "Ignoring ROM cache {}: {}", path.as_ref().display(), e);
+            Self::new()
+        })
+    }
+
+    /// Saves the cache to a file, creating its directory if needed.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> FrontendResult<()> {
+        if let Some(dir) = path.as_ref().parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string(self).map_err(std::io::Error::from)?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+
+    /// Gets the number of cached ROMs.
+    pub fn len(&self) -> usize {
+        self.roms.len()
+    }
+
+    /// Checks if nothing is cached.
+    pub fn is_empty(&self) -> bool {
+        self.roms.is_empty()
+    }
+
+    /// Gets the analysis of a ROM, if the cached one is for this version of
+    /// the file.
+    pub fn get(&self, path: &Path, stamp: FileStamp) -> Option<&CachedRom> {
+        self.roms.get(path).filter(|cached| cached.stamp == stamp)
+    }
+
+    /// Stores the analysis of a ROM.
+    pub fn insert(&mut self, stamp: FileStamp, entry: &RomEntry) {
+        self.roms.insert(
+            entry.path.clone(),
+            CachedRom {
+                stamp,
+                sha1: entry.sha1.clone(),
+                variant_hints: entry.variant_hints.clone(),
+            },
+        );
+    }
+
+    /// Forgets the ROMs in `directory` that aren't in `present`.
+    ///
+    /// # Returns
+    /// The number of entries removed.
+    pub fn prune(&mut self, directory: &Path, present: &[PathBuf]) -> usize {
+        let present: HashSet<&PathBuf> = present.iter().collect();
+        let before = self.roms.len();
+        self.roms
+            .retain(|path, _| path.parent() != Some(directory) || present.contains(path));
+        before - self.roms.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache").join(CACHE_FILE);
+        assert!(RomCache::load(&path).unwrap().is_empty());
+
+        let rom = dir.path().join("pong.ch8");
+        let stamp = FileStamp {
+            modified: 42,
+            size: 2,
+        };
+        let entry = RomEntry {
+            path: rom.clone(),
+            name: "pong".to_string(),
+            size: 2,
+            sha1: "abc".to_string(),
+            variant_hints: vec![InstructionSet::SuperChip],
+            analyzed: true,
+        };
+        let mut cache = RomCache::new();
+        cache.insert(stamp, &entry);
+        cache.save(&path).unwrap();
+
+        let cache = RomCache::load(&path).unwrap();
+        assert_eq!(cache.get(&rom, stamp).unwrap().sha1, "abc");
+        assert_eq!(
+            cache.get(&rom, stamp).unwrap().variant_hints,
+            vec![InstructionSet::SuperChip]
+        );
+
+        // A changed file misses
+        let touched = FileStamp {
+            modified: 43,
+            ..stamp
+        };
+        assert!(cache.get(&rom, touched).is_none());
+
+        // Deleted ROMs are pruned, ROMs elsewhere are kept
+        let mut cache = cache;
+        let other = RomEntry {
+            path: PathBuf::from("elsewhere/blitz.ch8"),
+            ..entry
+        };
+        cache.insert(stamp, &other);
+        assert_eq!(cache.prune(dir.path(), &[]), 1);
+        assert_eq!(cache.len(), 1);
+
+        std::fs::write(&path, "not json").unwrap();
+        assert!(RomCache::load(&path).is_err());
+        assert!(RomCache::load_or_default(&path).is_empty());
+    }
+}
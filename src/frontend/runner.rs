@@ -0,0 +1,322 @@
+//! Frontend-agnostic main loop.
+//!
+//! `AppRunner` owns a CPU and does the pacing every frontend needs: it runs
+//! the cycles the throttle says are due, and once per frame ticks the
+//! timers, updates the hardware and calls the registered frame hooks. A
+//! frontend then only draws and feeds input from its hooks. Frontends that
+//! own their event loop (e.g. winit) call `tick` from it; headless ones
+//! call `run`, or `run_frame` when they want deterministic frames.
+
+use std::time::{Duration, Instant};
+
+use super::{FrontendResult, Throttle};
+use crate::emulator::{Cpu, CpuEvent};
+use crate::hardware::Hardware;
+
+/// Default frame rate, matching the 60Hz timers.
+pub const DEFAULT_FRAME_RATE: u32 = 60;
+
+/// Most frames caught up on in one `tick` after the host stalls.
+const MAX_CATCH_UP_FRAMES: u32 = 4;
+
+/// What a frame hook sees at the end of each frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameInfo {
+    /// Frame number, starting at 0.
+    pub frame: u64,
+
+    /// Instructions run during the frame.
+    pub cycles: u32,
+
+    /// Whether the CPU is blocked on FX0A.
+    pub waiting_for_key: bool,
+}
+
+/// Whether the runner keeps going after a frame hook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunnerControl {
+    /// Keep running.
+    Continue,
+
+    /// Stop after this frame.
+    Stop,
+}
+
+/// Callback run at the end of every frame.
+pub type FrameHook<H> = Box<dyn FnMut(&mut Cpu<H>, &FrameInfo) -> RunnerControl>;
+
+/// Runs a CPU in real time, one frame at a time.
+pub struct AppRunner<H: Hardware> {
+    /// The CPU being run.
+    cpu: Cpu<H>,
+
+    /// Paces instructions to the CPU's speed.
+    throttle: Throttle,
+
+    /// Time between frames.
+    frame_interval: Duration,
+
+    /// When the next frame ends.
+    next_frame: Instant,
+
+    /// Number of frames completed.
+    frame: u64,
+
+    /// Instructions run in the current frame.
+    frame_cycles: u32,
+
+    /// Whether the CPU is blocked on FX0A.
+    waiting_for_key: bool,
+
+    /// Whether a hook or `stop` asked the runner to stop.
+    stopped: bool,
+
+    /// Callbacks run at the end of every frame.
+    hooks: Vec<FrameHook<H>>,
+}
+
+impl<H: Hardware> AppRunner<H> {
+    /// Creates a runner for a CPU at its timing model's speed.
+    ///
+    /// The runner ticks the timers once per frame, so the CPU's realtime
+    /// timers are turned off.
+    pub fn new(mut cpu: Cpu<H>) -> Self {
+        cpu.set_realtime_timers(false);
+        let throttle = Throttle::new(cpu.timing_model().cycles_per_second());
+        let frame_interval = frame_interval(DEFAULT_FRAME_RATE);
+
+        Self {
+            cpu,
+            throttle,
+            frame_interval,
+            next_frame: Instant::now() + frame_interval,
+            frame: 0,
+            frame_cycles: 0,
+            waiting_for_key: false,
+            stopped: false,
+            hooks: Vec::new(),
+        }
+    }
+
+    /// Sets the number of frames per second.
+    pub fn with_frame_rate(mut self, frames_per_second: u32) -> Self {
+        self.frame_interval = frame_interval(frames_per_second);
+        self.next_frame = Instant::now() + self.frame_interval;
+        self
+    }
+
+    /// Registers a callback run at the end of every frame, after the
+    /// timers tick and the hardware updates.
+    pub fn on_frame(
+        mut self,
+        hook: impl FnMut(&mut Cpu<H>, &FrameInfo) -> RunnerControl + 'static,
+    ) -> Self {
+        self.hooks.push(Box::new(hook));
+        self
+    }
+
+    /// Gets the CPU.
+    pub fn cpu(&self) -> &Cpu<H> {
+        &self.cpu
+    }
+
+    /// Gets mutable access to the CPU.
+    pub fn cpu_mut(&mut self) -> &mut Cpu<H> {
+        &mut self.cpu
+    }
+
+    /// Consumes the runner, returning the CPU.
+    pub fn into_cpu(self) -> Cpu<H> {
+        self.cpu
+    }
+
+    /// Gets the throttle, e.g. for its measured rates.
+    pub fn throttle(&self) -> &Throttle {
+        &self.throttle
+    }
+
+    /// Gets the number of frames completed.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Asks the runner to stop.
+    pub fn stop(&mut self) {
+        self.stopped = true;
+    }
+
+    /// Checks if the runner was stopped.
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// Runs whatever is due at `now`: instructions, then any finished frames.
+    ///
+    /// # Returns
+    /// The number of frames completed.
+    pub fn tick(&mut self, now: Instant) -> FrontendResult<u32> {
+        let due = self.throttle.cycles_due(now);
+        self.run_budget(due)?;
+        if self.waiting_for_key {
+            // Time spent blocked on a key isn't owed afterwards
+            self.throttle.reset(now);
+        }
+
+        let mut frames = 0;
+        while now >= self.next_frame && frames < MAX_CATCH_UP_FRAMES && !self.stopped {
+            self.next_frame += self.frame_interval;
+            self.end_frame(now)?;
+            frames += 1;
+        }
+        if now >= self.next_frame {
+            // Too far behind; drop the missed frames
+            self.next_frame = now + self.frame_interval;
+        }
+        Ok(frames)
+    }
+
+    /// Runs one frame's worth of instructions without looking at the clock.
+    ///
+    /// Results only depend on the CPU state and input, so this suits tests,
+    /// benchmarks and recordings.
+    pub fn run_frame(&mut self) -> FrontendResult<FrameInfo> {
+        let frames_per_second = (Duration::from_secs(1).as_nanos()
+            / self.frame_interval.as_nanos().max(1))
+        .max(1) as u32;
+        let budget = (self.throttle.target_cps() / frames_per_second).max(1);
+        self.run_budget(budget)?;
+        self.end_frame(Instant::now())
+    }
+
+    /// Runs until a hook or `stop` stops the runner, sleeping between batches.
+    pub fn run(&mut self) -> FrontendResult<()> {
+        self.throttle.reset(Instant::now());
+        self.next_frame = Instant::now() + self.frame_interval;
+
+        while !self.stopped {
+            self.tick(Instant::now())?;
+            let deadline = self.next_frame.min(self.throttle.next_deadline());
+            self.throttle.wait_until(deadline);
+        }
+        Ok(())
+    }
+
+    /// Runs instructions until a clock-cycle budget is used up or the CPU
+    /// blocks on a key.
+    fn run_budget(&mut self, budget: u32) -> FrontendResult<()> {
+        let mut used = 0u32;
+        let mut ran = 0u32;
+        while used < budget {
+            let event = self.cpu.cycle()?;
+            ran += 1;
+            used = used.saturating_add(self.cpu.last_cost().max(1));
+            self.waiting_for_key = event == CpuEvent::WaitingForKey;
+            if self.waiting_for_key {
+                break;
+            }
+        }
+        self.throttle.record_cycles(used);
+        self.frame_cycles += ran;
+        Ok(())
+    }
+
+    /// Ticks the timers, updates the hardware and calls the frame hooks.
+    fn end_frame(&mut self, now: Instant) -> FrontendResult<FrameInfo> {
+        self.cpu.timers_mut().update_by_ticks(1);
+        self.cpu.hardware_mut().update()?;
+        self.cpu.record_frame();
+        self.throttle.record_frame(now);
+
+        let info = FrameInfo {
+            frame: self.frame,
+            cycles: self.frame_cycles,
+            waiting_for_key: self.waiting_for_key,
+        };
+        for hook in &mut self.hooks {
+            if hook(&mut self.cpu, &info) == RunnerControl::Stop {
+                self.stopped = true;
+            }
+        }
+
+        self.frame += 1;
+        self.frame_cycles = 0;
+        Ok(info)
+    }
+}
+
+/// Converts a frame rate to the time between frames.
+fn frame_interval(frames_per_second: u32) -> Duration {
+    Duration::from_secs(1) / frames_per_second.max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hardware::{Display, NullHardware};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    /// LD V0, 30; LD DT, V0; DRW V1, V1, 1; JP 0x206
+    const ROM: [u8; 8] = [0x60, 0x1E, 0xF0, 0x15, 0xD1, 0x11, 0x12, 0x06];
+
+    fn runner() -> AppRunner<NullHardware> {
+        let mut cpu = Cpu::with_hardware(NullHardware::new());
+        cpu.load_rom(&ROM).unwrap();
+        AppRunner::new(cpu)
+    }
+
+    #[test]
+    fn test_run_frame_ticks_timers_and_hooks() {
+        let frames = Rc::new(Cell::new(0));
+        let seen = frames.clone();
+        let mut runner = runner().on_frame(move |cpu, info| {
+            seen.set(seen.get() + 1);
+            // The hardware updated, so the display is clean again
+            assert!(!cpu.hardware().display_ref().is_dirty());
+            assert_eq!(info.cycles, 700 / DEFAULT_FRAME_RATE);
+            RunnerControl::Continue
+        });
+
+        let info = runner.run_frame().unwrap();
+        assert_eq!(info.frame, 0);
+        assert!(!info.waiting_for_key);
+        assert_eq!(runner.cpu().get_timers().get_delay_timer(), 29);
+
+        runner.run_frame().unwrap();
+        assert_eq!(frames.get(), 2);
+        assert_eq!(runner.frame(), 2);
+    }
+
+    #[test]
+    fn test_tick_follows_the_clock() {
+        let mut runner = runner();
+        let start = Instant::now();
+        runner.throttle.reset(start);
+        runner.next_frame = start + runner.frame_interval;
+
+        // Not a full frame yet
+        assert_eq!(runner.tick(start + Duration::from_millis(10)).unwrap(), 0);
+        assert_eq!(runner.cpu().instruction_count(), 7);
+
+        assert_eq!(runner.tick(start + Duration::from_millis(34)).unwrap(), 2);
+        assert_eq!(runner.cpu().get_timers().get_delay_timer(), 28);
+
+        // A long stall catches up a few frames, then drops the rest
+        let frames = runner.tick(start + Duration::from_secs(5)).unwrap();
+        assert_eq!(frames, MAX_CATCH_UP_FRAMES);
+    }
+
+    #[test]
+    fn test_hook_stops_run() {
+        let mut runner = runner()
+            .with_frame_rate(1000)
+            .on_frame(|_, info| match info.frame {
+                2 => RunnerControl::Stop,
+                _ => RunnerControl::Continue,
+            });
+
+        runner.run().unwrap();
+        assert!(runner.is_stopped());
+        assert_eq!(runner.frame(), 3);
+    }
+}
@@ -0,0 +1,684 @@
+//! Emulator shortcuts and their key bindings.
+//!
+//! Each [`ShortcutAction`] is bound to one or more [`KeyChord`]s in the
+//! `[shortcuts]` config section, written like `"F5"` or `"Ctrl+Shift+S"`.
+//! [`Shortcuts`] resolves key presses to actions for the GUI event loop.
+//!
+//! A chord without modifiers on a key the keypad mapping uses would take
+//! that key away from the game, so the keypad wins: such a binding never
+//! fires and is reported as a [`ShortcutConflict`] at startup, along with
+//! chords bound to more than one action.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use winit::event::{ModifiersState, VirtualKeyCode};
+
+use crate::hardware::ChipKey;
+use crate::input::KeyMapper;
+
+/// Keys that can be bound, by the name used in chords.
+const KEY_NAMES: &[(&str, VirtualKeyCode)] = &[
+    ("Key0", VirtualKeyCode::Key0),
+    ("Key1", VirtualKeyCode::Key1),
+    ("Key2", VirtualKeyCode::Key2),
+    ("Key3", VirtualKeyCode::Key3),
+    ("Key4", VirtualKeyCode::Key4),
+    ("Key5", VirtualKeyCode::Key5),
+    ("Key6", VirtualKeyCode::Key6),
+    ("Key7", VirtualKeyCode::Key7),
+    ("Key8", VirtualKeyCode::Key8),
+    ("Key9", VirtualKeyCode::Key9),
+    ("A", VirtualKeyCode::A),
+    ("B", VirtualKeyCode::B),
+    ("C", VirtualKeyCode::C),
+    ("D", VirtualKeyCode::D),
+    ("E", VirtualKeyCode::E),
+    ("F", VirtualKeyCode::F),
+    ("G", VirtualKeyCode::G),
+    ("H", VirtualKeyCode::H),
+    ("I", VirtualKeyCode::I),
+    ("J", VirtualKeyCode::J),
+    ("K", VirtualKeyCode::K),
+    ("L", VirtualKeyCode::L),
+    ("M", VirtualKeyCode::M),
+    ("N", VirtualKeyCode::N),
+    ("O", VirtualKeyCode::O),
+    ("P", VirtualKeyCode::P),
+    ("Q", VirtualKeyCode::Q),
+    ("R", VirtualKeyCode::R),
+    ("S", VirtualKeyCode::S),
+    ("T", VirtualKeyCode::T),
+    ("U", VirtualKeyCode::U),
+    ("V", VirtualKeyCode::V),
+    ("W", VirtualKeyCode::W),
+    ("X", VirtualKeyCode::X),
+    ("Y", VirtualKeyCode::Y),
+    ("Z", VirtualKeyCode::Z),
+    ("F1", VirtualKeyCode::F1),
+    ("F2", VirtualKeyCode::F2),
+    ("F3", VirtualKeyCode::F3),
+    ("F4", VirtualKeyCode::F4),
+    ("F5", VirtualKeyCode::F5),
+    ("F6", VirtualKeyCode::F6),
+    ("F7", VirtualKeyCode::F7),
+    ("F8", VirtualKeyCode::F8),
+    ("F9", VirtualKeyCode::F9),
+    ("F10", VirtualKeyCode::F10),
+    ("F11", VirtualKeyCode::F11),
+    ("F12", VirtualKeyCode::F12),
+    ("Escape", VirtualKeyCode::Escape),
+    ("Return", VirtualKeyCode::Return),
+    ("Space", VirtualKeyCode::Space),
+    ("Tab", VirtualKeyCode::Tab),
+    ("Back", VirtualKeyCode::Back),
+    ("Insert", VirtualKeyCode::Insert),
+    ("Delete", VirtualKeyCode::Delete),
+    ("Home", VirtualKeyCode::Home),
+    ("End", VirtualKeyCode::End),
+    ("PageUp", VirtualKeyCode::PageUp),
+    ("PageDown", VirtualKeyCode::PageDown),
+    ("Up", VirtualKeyCode::Up),
+    ("Down", VirtualKeyCode::Down),
+    ("Left", VirtualKeyCode::Left),
+    ("Right", VirtualKeyCode::Right),
+    ("Pause", VirtualKeyCode::Pause),
+    ("Grave", VirtualKeyCode::Grave),
+    ("Minus", VirtualKeyCode::Minus),
+    ("Equals", VirtualKeyCode::Equals),
+    ("Plus", VirtualKeyCode::Plus),
+    ("Comma", VirtualKeyCode::Comma),
+    ("Period", VirtualKeyCode::Period),
+    ("Slash", VirtualKeyCode::Slash),
+    ("Backslash", VirtualKeyCode::Backslash),
+    ("Semicolon", VirtualKeyCode::Semicolon),
+    ("Apostrophe", VirtualKeyCode::Apostrophe),
+    ("LBracket", VirtualKeyCode::LBracket),
+    ("RBracket", VirtualKeyCode::RBracket),
+    ("Numpad0", VirtualKeyCode::Numpad0),
+    ("Numpad1", VirtualKeyCode::Numpad1),
+    ("Numpad2", VirtualKeyCode::Numpad2),
+    ("Numpad3", VirtualKeyCode::Numpad3),
+    ("Numpad4", VirtualKeyCode::Numpad4),
+    ("Numpad5", VirtualKeyCode::Numpad5),
+    ("Numpad6", VirtualKeyCode::Numpad6),
+    ("Numpad7", VirtualKeyCode::Numpad7),
+    ("Numpad8", VirtualKeyCode::Numpad8),
+    ("Numpad9", VirtualKeyCode::Numpad9),
+    ("NumpadAdd", VirtualKeyCode::NumpadAdd),
+    ("NumpadSubtract", VirtualKeyCode::NumpadSubtract),
+    ("NumpadMultiply", VirtualKeyCode::NumpadMultiply),
+    ("NumpadDivide", VirtualKeyCode::NumpadDivide),
+    ("NumpadEnter", VirtualKeyCode::NumpadEnter),
+];
+
+/// Something a shortcut does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShortcutAction {
+    /// Toggle pause.
+    Pause,
+    /// Run a single frame while paused.
+    FrameAdvance,
+    /// Restart the current ROM.
+    Reset,
+    /// Save the machine state to the selected slot.
+    SaveState,
+    /// Load the machine state from the selected slot.
+    LoadState,
+    /// Resume a session that didn't exit cleanly, while it's offered.
+    Resume,
+    /// Save a screenshot to the working directory.
+    Screenshot,
+    /// Toggle running faster than the configured speed.
+    Turbo,
+    /// Toggle fullscreen.
+    Fullscreen,
+    /// Switch between a game and the ROM browser.
+    Menu,
+    /// Toggle the speed counters in the on-screen display.
+    OsdStats,
+    /// Toggle the keypad in the on-screen display.
+    OsdKeypad,
+    /// Toggle the frame time graph in the on-screen display.
+    OsdFrameTimes,
+    /// Toggle waiting for the display's refresh.
+    Vsync,
+    /// Toggle the clickable on-screen keypad.
+    VirtualKeypad,
+    /// Toggle the debug panel.
+    DebugPanel,
+    /// Switch to the next visual theme.
+    NextTheme,
+    /// Switch to the previous visual theme.
+    PreviousTheme,
+    /// Mute or unmute audio.
+    Mute,
+    /// Raise the master volume.
+    VolumeUp,
+    /// Lower the master volume.
+    VolumeDown,
+}
+
+impl ShortcutAction {
+    /// Every action, in config order.
+    pub const ALL: [ShortcutAction; 21] = [
+        ShortcutAction::Pause,
+        ShortcutAction::FrameAdvance,
+        ShortcutAction::Reset,
+        ShortcutAction::SaveState,
+        ShortcutAction::LoadState,
+        ShortcutAction::Resume,
+        ShortcutAction::Screenshot,
+        ShortcutAction::Turbo,
+        ShortcutAction::Fullscreen,
+        ShortcutAction::Menu,
+        ShortcutAction::OsdStats,
+        ShortcutAction::OsdKeypad,
+        ShortcutAction::OsdFrameTimes,
+        ShortcutAction::Vsync,
+        ShortcutAction::VirtualKeypad,
+        ShortcutAction::DebugPanel,
+        ShortcutAction::NextTheme,
+        ShortcutAction::PreviousTheme,
+        ShortcutAction::Mute,
+        ShortcutAction::VolumeUp,
+        ShortcutAction::VolumeDown,
+    ];
+
+    /// Gets the action's key in the `[shortcuts]` config section.
+    pub fn name(self) -> &'static str {
+        match self {
+            ShortcutAction::Pause => "pause",
+            ShortcutAction::FrameAdvance => "frame_advance",
+            ShortcutAction::Reset => "reset",
+            ShortcutAction::SaveState => "save_state",
+            ShortcutAction::LoadState => "load_state",
+            ShortcutAction::Resume => "resume",
+            ShortcutAction::Screenshot => "screenshot",
+            ShortcutAction::Turbo => "turbo",
+            ShortcutAction::Fullscreen => "fullscreen",
+            ShortcutAction::Menu => "menu",
+            ShortcutAction::OsdStats => "osd_stats",
+            ShortcutAction::OsdKeypad => "osd_keypad",
+            ShortcutAction::OsdFrameTimes => "osd_frame_times",
+            ShortcutAction::Vsync => "vsync",
+            ShortcutAction::VirtualKeypad => "virtual_keypad",
+            ShortcutAction::DebugPanel => "debug_panel",
+            ShortcutAction::NextTheme => "next_theme",
+            ShortcutAction::PreviousTheme => "previous_theme",
+            ShortcutAction::Mute => "mute",
+            ShortcutAction::VolumeUp => "volume_up",
+            ShortcutAction::VolumeDown => "volume_down",
+        }
+    }
+}
+
+/// A key pressed with a set of modifiers, like `Ctrl+Shift+S`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct KeyChord {
+    /// Main key.
+    pub key: VirtualKeyCode,
+
+    /// Whether Ctrl must be held.
+    pub ctrl: bool,
+
+    /// Whether Alt must be held.
+    pub alt: bool,
+
+    /// Whether Shift must be held.
+    pub shift: bool,
+}
+
+impl KeyChord {
+    /// Creates a chord for a key on its own.
+    pub const fn key(key: VirtualKeyCode) -> Self {
+        Self {
+            key,
+            ctrl: false,
+            alt: false,
+            shift: false,
+        }
+    }
+
+    /// Requires Ctrl to be held.
+    pub const fn with_ctrl(mut self) -> Self {
+        self.ctrl = true;
+        self
+    }
+
+    /// Requires Alt to be held.
+    pub const fn with_alt(mut self) -> Self {
+        self.alt = true;
+        self
+    }
+
+    /// Requires Shift to be held.
+    pub const fn with_shift(mut self) -> Self {
+        self.shift = true;
+        self
+    }
+
+    /// Checks if the chord needs any modifier held.
+    pub fn has_modifiers(&self) -> bool {
+        self.ctrl || self.alt || self.shift
+    }
+
+    /// Checks if a key press with the given modifiers is this chord.
+    ///
+    /// Modifiers must match exactly; the logo key is ignored.
+    pub fn matches(&self, key: VirtualKeyCode, modifiers: ModifiersState) -> bool {
+        self.key == key
+            && self.ctrl == modifiers.ctrl()
+            && self.alt == modifiers.alt()
+            && self.shift == modifiers.shift()
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        // "Ctrl++" binds the Plus key
+        if s.ends_with("++") {
+            parts.truncate(parts.len() - 2);
+            parts.push("Plus");
+        }
+        let key_name = parts.pop().filter(|name| !name.is_empty());
+        let key = key_name
+            .and_then(|name| {
+                KEY_NAMES
+                    .iter()
+                    .find(|(known, _)| known.eq_ignore_ascii_case(name))
+                    // Digits can be written bare
+                    .or_else(|| {
+                        KEY_NAMES
+                            .iter()
+                            .find(|(known, _)| known.strip_prefix("Key") == Some(name))
+                    })
+            })
+            .map(|&(_, key)| key)
+            .ok_or_else(|| format!("unknown key in shortcut '{}'", s))?;
+
+        let mut chord = KeyChord::key(key);
+        for modifier in parts {
+            match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => chord.ctrl = true,
+                "alt" => chord.alt = true,
+                "shift" => chord.shift = true,
+                _ => return Err(format!("unknown modifier '{}' in '{}'", modifier, s)),
+            }
+        }
+        Ok(chord)
+    }
+}
+
+impl TryFrom<String> for KeyChord {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<KeyChord> for String {
+    fn from(chord: KeyChord) -> Self {
+        chord.to_string()
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (held, name) in [
+            (self.ctrl, "Ctrl"),
+            (self.alt, "Alt"),
+            (self.shift, "Shift"),
+        ] {
+            if held {
+                write!(f, "{}+", name)?;
+            }
+        }
+        match KEY_NAMES.iter().find(|(_, key)| *key == self.key) {
+            Some((name, _)) => f.write_str(name),
+            None => write!(f, "{:?}", self.key),
+        }
+    }
+}
+
+/// Key chords bound to each shortcut action.
+///
+/// An empty list leaves the action unbound.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShortcutsConfig {
+    /// Toggles pause.
+    pub pause: Vec<KeyChord>,
+
+    /// Runs a single frame while paused.
+    pub frame_advance: Vec<KeyChord>,
+
+    /// Restarts the current ROM.
+    pub reset: Vec<KeyChord>,
+
+    /// Saves the machine state to the selected slot.
+    pub save_state: Vec<KeyChord>,
+
+    /// Loads the machine state from the selected slot.
+    pub load_state: Vec<KeyChord>,
+
+    /// Resumes a session that didn't exit cleanly, while it's offered.
+    pub resume: Vec<KeyChord>,
+
+    /// Saves a screenshot to the working directory.
+    pub screenshot: Vec<KeyChord>,
+
+    /// Toggles running faster than the configured speed.
+    pub turbo: Vec<KeyChord>,
+
+    /// Toggles fullscreen.
+    pub fullscreen: Vec<KeyChord>,
+
+    /// Switches between a game and the ROM browser.
+    pub menu: Vec<KeyChord>,
+
+    /// Toggles the speed counters in the on-screen display.
+    pub osd_stats: Vec<KeyChord>,
+
+    /// Toggles the keypad in the on-screen display.
+    pub osd_keypad: Vec<KeyChord>,
+
+    /// Toggles the frame time graph in the on-screen display.
+    pub osd_frame_times: Vec<KeyChord>,
+
+    /// Toggles waiting for the display's refresh.
+    pub vsync: Vec<KeyChord>,
+
+    /// Toggles the clickable on-screen keypad.
+    pub virtual_keypad: Vec<KeyChord>,
+
+    /// Toggles the debug panel.
+    pub debug_panel: Vec<KeyChord>,
+
+    /// Switches to the next visual theme.
+    pub next_theme: Vec<KeyChord>,
+
+    /// Switches to the previous visual theme.
+    pub previous_theme: Vec<KeyChord>,
+
+    /// Mutes or unmutes audio.
+    pub mute: Vec<KeyChord>,
+
+    /// Raises the master volume.
+    pub volume_up: Vec<KeyChord>,
+
+    /// Lowers the master volume.
+    pub volume_down: Vec<KeyChord>,
+}
+
+impl Default for ShortcutsConfig {
+    fn default() -> Self {
+        let keys = |keys: &[VirtualKeyCode]| keys.iter().map(|&key| KeyChord::key(key)).collect();
+        Self {
+            pause: keys(&[VirtualKeyCode::P]),
+            frame_advance: keys(&[VirtualKeyCode::N]),
+            reset: keys(&[VirtualKeyCode::F8]),
+            save_state: keys(&[VirtualKeyCode::F5]),
+            load_state: keys(&[VirtualKeyCode::F9]),
+            resume: keys(&[VirtualKeyCode::Return]),
+            screenshot: keys(&[VirtualKeyCode::F12]),
+            turbo: keys(&[VirtualKeyCode::Tab]),
+            fullscreen: keys(&[VirtualKeyCode::F11]),
+            menu: keys(&[VirtualKeyCode::Escape]),
+            osd_stats: keys(&[VirtualKeyCode::F2]),
+            osd_keypad: keys(&[VirtualKeyCode::F3]),
+            osd_frame_times: keys(&[VirtualKeyCode::F7]),
+            vsync: vec![KeyChord::key(VirtualKeyCode::F7).with_shift()],
+            virtual_keypad: keys(&[VirtualKeyCode::F4]),
+            debug_panel: keys(&[VirtualKeyCode::F10]),
+            next_theme: keys(&[VirtualKeyCode::F6]),
+            previous_theme: vec![KeyChord::key(VirtualKeyCode::F6).with_shift()],
+            mute: keys(&[VirtualKeyCode::M]),
+            volume_up: keys(&[
+                VirtualKeyCode::Equals,
+                VirtualKeyCode::Plus,
+                VirtualKeyCode::NumpadAdd,
+            ]),
+            volume_down: keys(&[VirtualKeyCode::Minus, VirtualKeyCode::NumpadSubtract]),
+        }
+    }
+}
+
+impl ShortcutsConfig {
+    /// Gets the chords bound to an action.
+    pub fn chords(&self, action: ShortcutAction) -> &[KeyChord] {
+        match action {
+            ShortcutAction::Pause => &self.pause,
+            ShortcutAction::FrameAdvance => &self.frame_advance,
+            ShortcutAction::Reset => &self.reset,
+            ShortcutAction::SaveState => &self.save_state,
+            ShortcutAction::LoadState => &self.load_state,
+            ShortcutAction::Resume => &self.resume,
+            ShortcutAction::Screenshot => &self.screenshot,
+            ShortcutAction::Turbo => &self.turbo,
+            ShortcutAction::Fullscreen => &self.fullscreen,
+            ShortcutAction::Menu => &self.menu,
+            ShortcutAction::OsdStats => &self.osd_stats,
+            ShortcutAction::OsdKeypad => &self.osd_keypad,
+            ShortcutAction::OsdFrameTimes => &self.osd_frame_times,
+            ShortcutAction::Vsync => &self.vsync,
+            ShortcutAction::VirtualKeypad => &self.virtual_keypad,
+            ShortcutAction::DebugPanel => &self.debug_panel,
+            ShortcutAction::NextTheme => &self.next_theme,
+            ShortcutAction::PreviousTheme => &self.previous_theme,
+            ShortcutAction::Mute => &self.mute,
+            ShortcutAction::VolumeUp => &self.volume_up,
+            ShortcutAction::VolumeDown => &self.volume_down,
+        }
+    }
+}
+
+/// A binding that can't work as configured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShortcutConflict {
+    /// The chord is a keypad key on its own, so the keypad keeps it.
+    Keypad {
+        /// Action that won't fire.
+        action: ShortcutAction,
+        /// Chord bound to it.
+        chord: KeyChord,
+        /// Keypad key the chord presses instead.
+        chip_key: ChipKey,
+    },
+
+    /// The chord is bound to more than one action, so the first one keeps it.
+    Duplicate {
+        /// Chord bound twice.
+        chord: KeyChord,
+        /// Action that fires.
+        kept: ShortcutAction,
+        /// Action that won't fire.
+        dropped: ShortcutAction,
+    },
+}
+
+impl fmt::Display for ShortcutConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShortcutConflict::Keypad {
+                action,
+                chord,
+                chip_key,
+            } => write!(
+                f,
+                "Shortcut {} = \"{}\" is keypad key {:X}; the keypad keeps it",
+                action.name(),
+                chord,
+                chip_key.to_u8()
+            ),
+            ShortcutConflict::Duplicate {
+                chord,
+                kept,
+                dropped,
+            } => write!(
+                f,
+                "Shortcut \"{}\" is bound to both {} and {}; {} keeps it",
+                chord,
+                kept.name(),
+                dropped.name(),
+                kept.name()
+            ),
+        }
+    }
+}
+
+/// Resolves key presses to shortcut actions.
+#[derive(Debug, Clone, Default)]
+pub struct Shortcuts {
+    /// Bindings that can fire, in config order.
+    bindings: Vec<(KeyChord, ShortcutAction)>,
+
+    /// Bindings that were dropped, and why.
+    conflicts: Vec<ShortcutConflict>,
+}
+
+impl Shortcuts {
+    /// Builds the bindings, dropping those that clash with the keypad or
+    /// an earlier action.
+    ///
+    /// # Arguments
+    /// * `config` - Chords bound to each action
+    /// * `keypad` - Keypad mapping the shortcuts share the keyboard with
+    pub fn new(config: &ShortcutsConfig, keypad: &dyn KeyMapper) -> Self {
+        let mut shortcuts = Self::default();
+        for action in ShortcutAction::ALL {
+            for &chord in config.chords(action) {
+                let chip_key = keypad.map_virtual_keycode(chord.key);
+                if let Some(chip_key) = chip_key.filter(|_| !chord.has_modifiers()) {
+                    shortcuts.conflicts.push(ShortcutConflict::Keypad {
+                        action,
+                        chord,
+                        chip_key,
+                    });
+                } else if let Some(&(_, kept)) =
+                    shortcuts.bindings.iter().find(|(bound, _)| *bound == chord)
+                {
+                    if kept != action {
+                        shortcuts.conflicts.push(ShortcutConflict::Duplicate {
+                            chord,
+                            kept,
+                            dropped: action,
+                        });
+                    }
+                } else {
+                    shortcuts.bindings.push((chord, action));
+                }
+            }
+        }
+        shortcuts
+    }
+
+    /// Gets the action a key press triggers, if any.
+    pub fn action(&self, key: VirtualKeyCode, modifiers: ModifiersState) -> Option<ShortcutAction> {
+        self.bindings
+            .iter()
+            .find(|(chord, _)| chord.matches(key, modifiers))
+            .map(|&(_, action)| action)
+    }
+
+    /// Gets the bindings that were dropped, to warn about at startup.
+    pub fn conflicts(&self) -> &[ShortcutConflict] {
+        &self.conflicts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::QwertyMapper;
+
+    #[test]
+    fn test_parse_chords() {
+        let chord: KeyChord = "Ctrl+Shift+s".parse().unwrap();
+        assert_eq!(
+            chord,
+            KeyChord::key(VirtualKeyCode::S).with_ctrl().with_shift()
+        );
+        assert_eq!(chord.to_string(), "Ctrl+Shift+S");
+        assert_eq!(
+            "Alt+7".parse(),
+            Ok(KeyChord::key(VirtualKeyCode::Key7).with_alt())
+        );
+        assert_eq!(
+            "Ctrl++".parse(),
+            Ok(KeyChord::key(VirtualKeyCode::Plus).with_ctrl())
+        );
+        assert!("Hyper+S".parse::<KeyChord>().is_err());
+        assert!("Ctrl+".parse::<KeyChord>().is_err());
+
+        let config: ShortcutsConfig = toml::from_str("pause = [\"Space\", \"Ctrl+P\"]").unwrap();
+        assert_eq!(
+            config.pause,
+            [
+                KeyChord::key(VirtualKeyCode::Space),
+                KeyChord::key(VirtualKeyCode::P).with_ctrl()
+            ]
+        );
+        // Unlisted actions keep their default keys
+        assert_eq!(config.reset, ShortcutsConfig::default().reset);
+        assert!(toml::to_string(&config).unwrap().contains("\"Ctrl+P\""));
+    }
+
+    #[test]
+    fn test_keypad_and_duplicate_conflicts() {
+        let mapper = QwertyMapper::new();
+        let defaults = Shortcuts::new(&ShortcutsConfig::default(), &mapper);
+        assert!(defaults.conflicts().is_empty());
+        let none = ModifiersState::empty();
+        assert_eq!(
+            defaults.action(VirtualKeyCode::F5, none),
+            Some(ShortcutAction::SaveState)
+        );
+        // Modifiers must match exactly
+        assert_eq!(
+            defaults.action(VirtualKeyCode::F5, ModifiersState::CTRL),
+            None
+        );
+
+        let config = ShortcutsConfig {
+            pause: vec!["Q".parse().unwrap(), "Ctrl+Q".parse().unwrap()],
+            reset: vec!["F5".parse().unwrap()],
+            ..ShortcutsConfig::default()
+        };
+        let shortcuts = Shortcuts::new(&config, &mapper);
+        assert_eq!(
+            shortcuts.conflicts(),
+            [
+                ShortcutConflict::Keypad {
+                    action: ShortcutAction::Pause,
+                    chord: KeyChord::key(VirtualKeyCode::Q),
+                    chip_key: ChipKey::Key4,
+                },
+                ShortcutConflict::Duplicate {
+                    chord: KeyChord::key(VirtualKeyCode::F5),
+                    kept: ShortcutAction::Reset,
+                    dropped: ShortcutAction::SaveState,
+                },
+            ]
+        );
+        // Q stays on the keypad, Ctrl+Q pauses
+        assert_eq!(shortcuts.action(VirtualKeyCode::Q, none), None);
+        assert_eq!(
+            shortcuts.action(VirtualKeyCode::Q, ModifiersState::CTRL),
+            Some(ShortcutAction::Pause)
+        );
+        assert_eq!(
+            shortcuts.action(VirtualKeyCode::F5, none),
+            Some(ShortcutAction::Reset)
+        );
+        assert!(shortcuts.conflicts()[0]
+            .to_string()
+            .contains("pause = \"Q\" is keypad key 4"));
+    }
+}
@@ -0,0 +1,469 @@
+//! Execution speed throttling.
+//!
+//! This module paces emulation to a target number of instructions per
+//! second. Callers ask how many cycles are due, run them, and report back;
+//! waiting between batches uses a coarse sleep followed by a short spin so
+//! deadlines are hit more precisely than `thread::sleep` alone allows.
+//!
+//! With an audio clock attached, the pace follows the samples consumed by
+//! the output device instead of wall-clock time, so emulation and sound
+//! can't drift apart. Pacing falls back to wall-clock time whenever the
+//! audio clock stops moving, e.g. when audio is disabled or not started.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::emulator::TimeSource;
+
+/// Default time left before a deadline that is spun instead of slept.
+pub const DEFAULT_SPIN_THRESHOLD: Duration = Duration::from_millis(1);
+
+/// Default backlog after which owed cycles are dropped.
+pub const DEFAULT_MAX_LAG: Duration = Duration::from_millis(100);
+
+/// How often measured rates are updated.
+const MEASURE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Time without audio progress after which pacing falls back to wall-clock.
+pub const AUDIO_STALL_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// Monotonic pacing timeline that follows an audio clock when it runs.
+///
+/// Audio positions advance in whole callback buffers, so between callbacks
+/// the wall-clock time since the position last moved is added on top.
+/// Switching between audio and wall-clock time keeps the timeline
+/// continuous, and it never moves backwards.
+#[derive(Clone)]
+struct PaceClock {
+    /// Audio clock to follow, if any.
+    audio: Option<Arc<dyn TimeSource + Send + Sync>>,
+
+    /// Whether the timeline currently follows the audio clock.
+    following_audio: bool,
+
+    /// Wall-clock time of the last reading.
+    last_wall: Instant,
+
+    /// Paced time of the last reading.
+    last_paced: Instant,
+
+    /// Last audio position seen.
+    audio_position: Duration,
+
+    /// Wall-clock time the audio position last moved.
+    audio_moved: Instant,
+
+    /// Audio time that `paced_anchor` corresponds to.
+    audio_anchor: Duration,
+
+    /// Paced time at `audio_anchor`.
+    paced_anchor: Instant,
+}
+
+impl PaceClock {
+    /// Creates a timeline that is plain wall-clock time.
+    fn wall(now: Instant) -> Self {
+        Self {
+            audio: None,
+            following_audio: false,
+            last_wall: now,
+            last_paced: now,
+            audio_position: Duration::ZERO,
+            audio_moved: now,
+            audio_anchor: Duration::ZERO,
+            paced_anchor: now,
+        }
+    }
+
+    /// Creates a timeline following an audio clock from `now`.
+    fn audio(audio: Arc<dyn TimeSource + Send + Sync>, now: Instant) -> Self {
+        let position = audio.now();
+
+        Self {
+            audio: Some(audio),
+            following_audio: true,
+            audio_position: position,
+            audio_anchor: position,
+            ..Self::wall(now)
+        }
+    }
+
+    /// Maps a wall-clock time onto the paced timeline.
+    fn now(&mut self, wall: Instant) -> Instant {
+        let Some(audio) = &self.audio else {
+            return wall;
+        };
+
+        let wall = wall.max(self.last_wall);
+        let position = audio.now();
+        if position != self.audio_position {
+            // A position that went backwards (counters reset) re-anchors
+            if position < self.audio_position {
+                self.following_audio = false;
+            }
+            self.audio_position = position;
+            self.audio_moved = wall;
+        }
+
+        let idle = wall - self.audio_moved;
+        let paced = if idle <= AUDIO_STALL_TIMEOUT {
+            let audio_time = position + idle;
+            if !self.following_audio {
+                self.following_audio = true;
+                self.audio_anchor = audio_time;
+                self.paced_anchor = self.last_paced + (wall - self.last_wall);
+            }
+            self.paced_anchor + audio_time.saturating_sub(self.audio_anchor)
+        } else {
+            // Audio stalled: carry on at wall-clock speed
+            self.following_audio = false;
+            self.last_paced + (wall - self.last_wall)
+        };
+
+        self.last_wall = wall;
+        self.last_paced = paced.max(self.last_paced);
+        self.last_paced
+    }
+
+    /// Maps a time on the paced timeline back to wall-clock time.
+    fn to_wall(&self, paced: Instant) -> Instant {
+        if self.audio.is_none() {
+            return paced;
+        }
+        self.last_wall + paced.saturating_duration_since(self.last_paced)
+    }
+}
+
+impl fmt::Debug for PaceClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PaceClock")
+            .field("has_audio", &self.audio.is_some())
+            .field("following_audio", &self.following_audio)
+            .field("last_paced", &self.last_paced)
+            .finish()
+    }
+}
+
+/// Measured execution rates.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ThrottleStats {
+    /// Instructions executed per second.
+    pub ips: f64,
+
+    /// Frames presented per second.
+    pub fps: f64,
+
+    /// Cycles dropped because the host couldn't keep up.
+    pub dropped_cycles: u64,
+}
+
+/// Paces CPU cycles to a target speed.
+#[derive(Debug, Clone)]
+pub struct Throttle {
+    /// Target cycles per second.
+    target_cps: u32,
+
+    /// Point on the paced timeline that `cycles_run` is counted from.
+    anchor: Instant,
+
+    /// Timeline the pace is measured on.
+    clock: PaceClock,
+
+    /// Cycles run since `anchor`.
+    cycles_run: u64,
+
+    /// Time before a deadline that is spun instead of slept.
+    spin_threshold: Duration,
+
+    /// Largest backlog that is caught up on instead of dropped.
+    max_lag: Duration,
+
+    /// Start of the current measurement window.
+    window_start: Instant,
+
+    /// Cycles run in the current window.
+    window_cycles: u64,
+
+    /// Frames presented in the current window.
+    window_frames: u32,
+
+    /// Cycles dropped in the current window.
+    window_dropped: u64,
+
+    /// Rates from the last complete window.
+    stats: ThrottleStats,
+}
+
+impl Throttle {
+    /// Creates a throttle for a target speed in cycles per second.
+    pub fn new(target_cps: u32) -> Self {
+        let now = Instant::now();
+
+        Self {
+            target_cps: target_cps.max(1),
+            anchor: now,
+            clock: PaceClock::wall(now),
+            cycles_run: 0,
+            spin_threshold: DEFAULT_SPIN_THRESHOLD,
+            max_lag: DEFAULT_MAX_LAG,
+            window_start: now,
+            window_cycles: 0,
+            window_frames: 0,
+            window_dropped: 0,
+            stats: ThrottleStats::default(),
+        }
+    }
+
+    /// Sets how close to a deadline waiting switches from sleeping to spinning.
+    pub fn with_spin_threshold(mut self, threshold: Duration) -> Self {
+        self.spin_threshold = threshold;
+        self
+    }
+
+    /// Sets the largest backlog that is caught up on.
+    ///
+    /// When the host falls further behind than this, the extra cycles are
+    /// dropped so emulation slows down instead of stalling to catch up.
+    pub fn with_max_lag(mut self, max_lag: Duration) -> Self {
+        self.max_lag = max_lag;
+        self
+    }
+
+    /// Paces emulation from an audio clock instead of wall-clock time.
+    pub fn with_audio_clock(mut self, clock: impl TimeSource + Send + Sync + 'static) -> Self {
+        self.set_audio_clock(Some(clock));
+        self
+    }
+
+    /// Sets or removes the audio clock the pace follows.
+    ///
+    /// While the audio clock stands still for longer than
+    /// [`AUDIO_STALL_TIMEOUT`], wall-clock time is used instead.
+    pub fn set_audio_clock(&mut self, clock: Option<impl TimeSource + Send + Sync + 'static>) {
+        self.clock = match clock {
+            Some(clock) => PaceClock::audio(Arc::new(clock), self.anchor),
+            None => PaceClock::wall(self.anchor),
+        };
+        self.cycles_run = 0;
+    }
+
+    /// Checks if the pace currently follows an audio clock.
+    pub fn is_audio_synced(&self) -> bool {
+        self.clock.following_audio
+    }
+
+    /// Gets the target cycles per second.
+    pub fn target_cps(&self) -> u32 {
+        self.target_cps
+    }
+
+    /// Changes the target speed, starting the new pace from `now`.
+    pub fn set_target_cps(&mut self, target_cps: u32, now: Instant) {
+        self.target_cps = target_cps.max(1);
+        self.reset(now);
+    }
+
+    /// Forgets any backlog, e.g. after a pause.
+    pub fn reset(&mut self, now: Instant) {
+        self.anchor = self.clock.now(now);
+        self.cycles_run = 0;
+    }
+
+    /// Gets the number of cycles that should run now to stay on pace.
+    ///
+    /// A backlog larger than the maximum lag is dropped.
+    pub fn cycles_due(&mut self, now: Instant) -> u32 {
+        let now = self.clock.now(now);
+        let elapsed = now.saturating_duration_since(self.anchor);
+        let mut due = self.cycles_for(elapsed).saturating_sub(self.cycles_run);
+
+        let max_due = self.cycles_for(self.max_lag).max(1);
+        if due > max_due {
+            self.window_dropped += due - max_due;
+            // Re-anchor so the dropped cycles are not owed later
+            self.anchor = now - self.duration_for(max_due);
+            self.cycles_run = 0;
+            due = max_due;
+        }
+
+        due.min(u32::MAX as u64) as u32
+    }
+
+    /// Records cycles that were run.
+    pub fn record_cycles(&mut self, cycles: u32) {
+        self.cycles_run += cycles as u64;
+        self.window_cycles += cycles as u64;
+    }
+
+    /// Records a presented frame and updates the measured rates.
+    pub fn record_frame(&mut self, now: Instant) {
+        self.window_frames += 1;
+
+        let elapsed = now.saturating_duration_since(self.window_start);
+        if elapsed >= MEASURE_WINDOW {
+            let seconds = elapsed.as_secs_f64();
+            self.stats = ThrottleStats {
+                ips: self.window_cycles as f64 / seconds,
+                fps: self.window_frames as f64 / seconds,
+                dropped_cycles: self.window_dropped,
+            };
+            self.window_start = now;
+            self.window_cycles = 0;
+            self.window_frames = 0;
+            self.window_dropped = 0;
+        }
+    }
+
+    /// Gets the rates measured over the last complete window.
+    pub fn stats(&self) -> ThrottleStats {
+        self.stats
+    }
+
+    /// Checks if cycles were dropped in the last window.
+    pub fn is_lagging(&self) -> bool {
+        self.stats.dropped_cycles > 0
+    }
+
+    /// Gets when the next cycle is due.
+    pub fn next_deadline(&self) -> Instant {
+        self.clock
+            .to_wall(self.anchor + self.duration_for(self.cycles_run + 1))
+    }
+
+    /// Waits until a deadline, sleeping first and spinning for the last stretch.
+    pub fn wait_until(&self, deadline: Instant) {
+        sleep_until(deadline, self.spin_threshold);
+    }
+
+    /// Converts a duration to a whole number of cycles.
+    fn cycles_for(&self, duration: Duration) -> u64 {
+        (duration.as_nanos() * self.target_cps as u128 / 1_000_000_000) as u64
+    }
+
+    /// Converts a number of cycles to the time they take.
+    fn duration_for(&self, cycles: u64) -> Duration {
+        Duration::from_nanos((cycles as u128 * 1_000_000_000 / self.target_cps as u128) as u64)
+    }
+}
+
+/// Blocks until `deadline` using a sleep plus a spin of at most `spin_threshold`.
+pub fn sleep_until(deadline: Instant, spin_threshold: Duration) {
+    let now = Instant::now();
+    if deadline <= now {
+        return;
+    }
+
+    let remaining = deadline - now;
+    if remaining > spin_threshold {
+        std::thread::sleep(remaining - spin_threshold);
+    }
+    while Instant::now() < deadline {
+        std::hint::spin_loop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::MockClock;
+
+    #[test]
+    fn test_cycles_due_follows_elapsed_time() {
+        let mut throttle = Throttle::new(1000);
+        let start = throttle.anchor;
+
+        assert_eq!(throttle.cycles_due(start), 0);
+        assert_eq!(throttle.cycles_due(start + Duration::from_millis(10)), 10);
+
+        throttle.record_cycles(10);
+        assert_eq!(throttle.cycles_due(start + Duration::from_millis(10)), 0);
+        assert_eq!(throttle.cycles_due(start + Duration::from_millis(25)), 15);
+        assert_eq!(throttle.next_deadline(), start + Duration::from_millis(11));
+    }
+
+    #[test]
+    fn test_backlog_is_dropped() {
+        let mut throttle = Throttle::new(1000).with_max_lag(Duration::from_millis(50));
+        let start = throttle.anchor;
+
+        // Two seconds behind: only the allowed lag is caught up on
+        let now = start + Duration::from_secs(2);
+        assert_eq!(throttle.cycles_due(now), 50);
+        throttle.record_cycles(50);
+        assert_eq!(throttle.cycles_due(now), 0);
+        assert_eq!(throttle.cycles_due(now + Duration::from_millis(5)), 5);
+
+        throttle.record_frame(start + Duration::from_secs(2));
+        assert!(throttle.is_lagging());
+        assert_eq!(throttle.stats().dropped_cycles, 1950);
+    }
+
+    #[test]
+    fn test_measured_rates() {
+        let mut throttle = Throttle::new(600);
+        let start = throttle.window_start;
+
+        for frame in 1..=60u64 {
+            throttle.record_cycles(10);
+            throttle.record_frame(start + Duration::from_micros(frame * 1_000_000 / 60));
+        }
+
+        let stats = throttle.stats();
+        assert!((stats.ips - 600.0).abs() < 1.0);
+        assert!((stats.fps - 60.0).abs() < 0.1);
+        assert!(!throttle.is_lagging());
+    }
+
+    #[test]
+    fn test_set_target_cps() {
+        let mut throttle = Throttle::new(1000);
+        let start = throttle.anchor;
+
+        throttle.set_target_cps(2000, start + Duration::from_millis(100));
+        assert_eq!(throttle.target_cps(), 2000);
+        assert_eq!(throttle.cycles_due(start + Duration::from_millis(110)), 20);
+    }
+
+    #[test]
+    fn test_pace_follows_audio_clock() {
+        let audio = MockClock::new();
+        let mut throttle = Throttle::new(1000).with_audio_clock(audio.clone());
+        let start = throttle.anchor;
+
+        // Audio plays slower than wall-clock time, and the pace follows it
+        audio.advance(Duration::from_millis(8));
+        assert_eq!(throttle.cycles_due(start + Duration::from_millis(10)), 8);
+        assert!(throttle.is_audio_synced());
+        throttle.record_cycles(8);
+
+        // Between callbacks, wall-clock time is interpolated on top
+        assert_eq!(throttle.cycles_due(start + Duration::from_millis(12)), 2);
+        throttle.record_cycles(2);
+        assert_eq!(throttle.next_deadline(), start + Duration::from_millis(13));
+
+        // Audio stalls, so wall-clock time takes over without a jump
+        assert_eq!(throttle.cycles_due(start + Duration::from_millis(100)), 88);
+        assert!(!throttle.is_audio_synced());
+        throttle.record_cycles(88);
+
+        // Audio resumes and pacing follows it from where it was
+        audio.advance(Duration::from_millis(5));
+        assert_eq!(throttle.cycles_due(start + Duration::from_millis(110)), 10);
+        assert!(throttle.is_audio_synced());
+        assert_eq!(throttle.cycles_due(start + Duration::from_millis(115)), 15);
+    }
+
+    #[test]
+    fn test_sleep_until() {
+        let deadline = Instant::now() + Duration::from_millis(3);
+        sleep_until(deadline, DEFAULT_SPIN_THRESHOLD);
+        assert!(Instant::now() >= deadline);
+
+        // Past deadlines return immediately
+        sleep_until(
+            Instant::now() - Duration::from_millis(1),
+            DEFAULT_SPIN_THRESHOLD,
+        );
+    }
+}
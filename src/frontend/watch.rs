@@ -0,0 +1,174 @@
+//! ROM file watching for `run --watch`.
+//!
+//! When developing a ROM with an external assembler, the emulator can
+//! follow the output file and restart the program whenever it is rebuilt.
+//! Octo source is watched directly and recompiled on every save.
+//! Like the config watcher, this polls the file's modification time, so
+//! `poll` is cheap enough to call every frame.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use super::{read_rom, FrontendResult};
+use crate::emulator::Memory;
+
+/// Watches a ROM file and reports new contents.
+#[derive(Debug, Clone)]
+pub struct RomWatcher {
+    /// Watched file.
+    path: PathBuf,
+
+    /// Contents of the last successful load.
+    current: Vec<u8>,
+
+    /// Modification time of the last load attempt.
+    last_modified: Option<SystemTime>,
+
+    /// Minimum time between modification checks.
+    interval: Duration,
+
+    /// When the file was last checked.
+    last_check: Option<Instant>,
+}
+
+impl RomWatcher {
+    /// Creates a watcher for a ROM whose contents are already loaded as `current`.
+    pub fn new<P: AsRef<Path>>(path: P, current: Vec<u8>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let last_modified = Self::modified(&path);
+
+        Self {
+            path,
+            current,
+            last_modified,
+            interval: Duration::from_millis(250),
+            last_check: None,
+        }
+    }
+
+    /// Sets how often the file is checked.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Gets the watched path.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Gets the contents of the last successful load.
+    pub fn current(&self) -> &[u8] {
+        &self.current
+    }
+
+    /// Checks for changes if the polling interval has elapsed.
+    ///
+    /// # Returns
+    /// The new ROM if the file was modified, or an error if it can't be
+    /// read or isn't a valid ROM (the previous ROM stays current).
+    pub fn poll(&mut self) -> FrontendResult<Option<Vec<u8>>> {
+        let now = Instant::now();
+        if self
+            .last_check
+            .is_some_and(|last| now.duration_since(last) < self.interval)
+        {
+            return Ok(None);
+        }
+        self.last_check = Some(now);
+        self.check_now()
+    }
+
+    /// Checks the file for changes immediately.
+    pub fn check_now(&mut self) -> FrontendResult<Option<Vec<u8>>> {
+        let modified = Self::modified(&self.path);
+        if modified == self.last_modified {
+            return Ok(None);
+        }
+        // Remember the attempt so a broken build is only reported once
+        self.last_modified = modified;
+
+        let rom = read_rom(&self.path)?;
+        Memory::new().load_rom(&rom)?;
+        if rom == self.current {
+            return Ok(None);
+        }
+        self.current = rom.clone();
+
+        Ok(Some(rom))
+    }
+
+    /// Gets a file's modification time, if it exists.
+    fn modified(path: &Path) -> Option<SystemTime> {
+        std::fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes a file and bumps its timestamp clearly past the last one.
+    fn rewrite(path: &Path, contents: &[u8], seconds: u64) {
+        std::fs::write(path, contents).unwrap();
+        let file = std::fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(seconds))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_rom_watcher() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("game.ch8");
+        std::fs::write(&path, [0x00, 0xE0]).unwrap();
+
+        let mut watcher = RomWatcher::new(&path, vec![0x00, 0xE0]);
+        assert!(watcher.check_now().unwrap().is_none());
+
+        rewrite(&path, &[0x60, 0x01], 5);
+        assert_eq!(watcher.check_now().unwrap(), Some(vec![0x60, 0x01]));
+        assert_eq!(watcher.current(), &[0x60, 0x01]);
+
+        // Touching the file without changing it doesn't restart the program
+        rewrite(&path, &[0x60, 0x01], 10);
+        assert!(watcher.check_now().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_rom_watcher_keeps_last_good_rom() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("game.ch8");
+        std::fs::write(&path, [0x00, 0xE0]).unwrap();
+        let mut watcher = RomWatcher::new(&path, vec![0x00, 0xE0]);
+
+        // An assembler that truncates the file before writing it
+        rewrite(&path, &[], 5);
+        assert!(watcher.check_now().is_err());
+        assert_eq!(watcher.current(), &[0x00, 0xE0]);
+        assert!(watcher.check_now().unwrap().is_none());
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(watcher.check_now().is_err());
+    }
+
+    #[cfg(feature = "octo")]
+    #[test]
+    fn test_rom_watcher_recompiles_octo() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("game.8o");
+        std::fs::write(&path, ": main clear").unwrap();
+        let mut watcher = RomWatcher::new(&path, read_rom(&path).unwrap());
+        assert_eq!(watcher.current(), &[0x12, 0x02, 0x00, 0xE0]);
+
+        rewrite(&path, b": main v0 := 1", 5);
+        assert_eq!(
+            watcher.check_now().unwrap(),
+            Some(vec![0x12, 0x02, 0x60, 0x01])
+        );
+
+        // A compile error keeps the last good build running
+        rewrite(&path, b": main v0 := 999", 10);
+        assert!(watcher.check_now().is_err());
+        assert_eq!(watcher.current(), &[0x12, 0x02, 0x60, 0x01]);
+    }
+}
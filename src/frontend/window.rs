@@ -0,0 +1,292 @@
+//! Window geometry and scaling for the GUI frontend.
+//!
+//! The GUI composes each frame into a fixed-size canvas and then scales it
+//! into a buffer the size of the window. `Viewport` works out where the
+//! canvas lands: stretched to fill the window, fitted with its aspect ratio
+//! kept, or scaled by a whole number with letterbox bars around it. Canvas
+//! pixels can be shown wider or taller than they are high, the way the
+//! TVs original hardware drew on did.
+
+use serde::{Deserialize, Serialize};
+
+use crate::graphics::Color;
+
+/// Saved window geometry, in logical pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowConfig {
+    /// Inner width of the window, or `None` to size it from the scale factor.
+    pub width: Option<u32>,
+
+    /// Inner height of the window, or `None` to size it from the scale factor.
+    pub height: Option<u32>,
+
+    /// Left edge of the window on the desktop, or `None` to let the system place it.
+    pub x: Option<i32>,
+
+    /// Top edge of the window on the desktop, or `None` to let the system place it.
+    pub y: Option<i32>,
+
+    /// Whether the window starts fullscreen.
+    pub fullscreen: bool,
+}
+
+impl WindowConfig {
+    /// Gets the saved inner size, if both dimensions are known.
+    pub fn size(&self) -> Option<(u32, u32)> {
+        self.width.zip(self.height)
+    }
+
+    /// Gets the saved position, if both coordinates are known.
+    pub fn position(&self) -> Option<(i32, i32)> {
+        self.x.zip(self.y)
+    }
+}
+
+/// Where a scaled canvas sits inside a window buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Viewport {
+    /// Left edge of the scaled canvas.
+    pub x: usize,
+
+    /// Top edge of the scaled canvas.
+    pub y: usize,
+
+    /// Width of the scaled canvas.
+    pub width: usize,
+
+    /// Height of the scaled canvas.
+    pub height: usize,
+}
+
+impl Viewport {
+    /// Fits a canvas into a window buffer, centered.
+    ///
+    /// # Arguments
+    /// * `window` - Window buffer size as (width, height)
+    /// * `canvas` - Canvas size as (width, height)
+    /// * `integer_scaling` - Only scale by whole numbers, letterboxing the rest
+    /// * `maintain_aspect_ratio` - Keep the canvas's shape, pixel aspect ratio included
+    /// * `pixel_aspect_ratio` - Width of a canvas pixel on screen relative to its height
+    pub fn fit(
+        window: (usize, usize),
+        canvas: (usize, usize),
+        integer_scaling: bool,
+        maintain_aspect_ratio: bool,
+        pixel_aspect_ratio: f32,
+    ) -> Self {
+        let (window_width, window_height) = window;
+        let (canvas_width, canvas_height) = (canvas.0.max(1), canvas.1.max(1));
+        // Width of the canvas at one screen pixel per row, stretched by the pixel shape
+        let shown_width = ((canvas_width as f32 * pixel_aspect_ratio).round() as usize).max(1);
+
+        let (width, height) = if integer_scaling {
+            // Windows smaller than the canvas fall back to shrinking it
+            let scale_y = (window_height / canvas_height).max(1);
+            let (width, height) = if maintain_aspect_ratio {
+                let scale = (window_width / shown_width).max(1).min(scale_y);
+                (shown_width * scale, canvas_height * scale)
+            } else {
+                let scale_x = (window_width / canvas_width).max(1);
+                (canvas_width * scale_x, canvas_height * scale_y)
+            };
+            if width > window_width || height > window_height {
+                return Self::fit(
+                    window,
+                    canvas,
+                    false,
+                    maintain_aspect_ratio,
+                    pixel_aspect_ratio,
+                );
+            }
+            (width, height)
+        } else if maintain_aspect_ratio {
+            // Compare window_width / shown_width with window_height / canvas_height
+            if window_width * canvas_height <= window_height * shown_width {
+                (window_width, window_width * canvas_height / shown_width)
+            } else {
+                (window_height * shown_width / canvas_height, window_height)
+            }
+        } else {
+            (window_width, window_height)
+        };
+
+        Self {
+            x: (window_width - width) / 2,
+            y: (window_height - height) / 2,
+            width,
+            height,
+        }
+    }
+
+    /// Converts a window buffer position to a canvas position.
+    ///
+    /// # Returns
+    /// The canvas position, or `None` if the position is in the letterbox bars.
+    pub fn to_canvas(
+        &self,
+        canvas: (usize, usize),
+        position: (usize, usize),
+    ) -> Option<(usize, usize)> {
+        let x = position.0.checked_sub(self.x)?;
+        let y = position.1.checked_sub(self.y)?;
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        Some((x * canvas.0 / self.width, y * canvas.1 / self.height))
+    }
+
+    /// Scales an RGBA canvas into an RGBA window buffer.
+    ///
+    /// Pixels outside the viewport are filled with `background`. Scaling
+    /// uses nearest-neighbor sampling so Chip-8 pixels stay sharp.
+    ///
+    /// # Arguments
+    /// * `canvas` - RGBA canvas pixels
+    /// * `canvas_size` - Canvas size as (width, height)
+    /// * `buffer` - RGBA window buffer to draw into
+    /// * `buffer_width` - Window buffer width in pixels
+    /// * `background` - Color of the letterbox bars
+    pub fn blit(
+        &self,
+        canvas: &[u8],
+        canvas_size: (usize, usize),
+        buffer: &mut [u8],
+        buffer_width: usize,
+        background: Color,
+    ) {
+        let (canvas_width, canvas_height) = canvas_size;
+        let background = background.to_rgba();
+        let columns: Vec<usize> = (0..self.width)
+            .map(|x| x * canvas_width / self.width.max(1))
+            .collect();
+
+        for (y, row) in buffer.chunks_exact_mut(buffer_width * 4).enumerate() {
+            let Some(canvas_y) = y
+                .checked_sub(self.y)
+                .filter(|&y| y < self.height)
+                .map(|y| y * canvas_height / self.height)
+            else {
+                for pixel in row.chunks_exact_mut(4) {
+                    pixel.copy_from_slice(&background);
+                }
+                continue;
+            };
+
+            let source = &canvas[canvas_y * canvas_width * 4..(canvas_y + 1) * canvas_width * 4];
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                match x.checked_sub(self.x).and_then(|x| columns.get(x)) {
+                    Some(&canvas_x) => {
+                        pixel.copy_from_slice(&source[canvas_x * 4..canvas_x * 4 + 4])
+                    }
+                    None => pixel.copy_from_slice(&background),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CANVAS: (usize, usize) = (64, 32);
+
+    #[test]
+    fn test_fit_keeps_aspect_ratio() {
+        // Wider than 2:1, so bars go on the sides
+        let viewport = Viewport::fit((300, 100), CANVAS, false, true, 1.0);
+        assert_eq!(
+            viewport,
+            Viewport {
+                x: 50,
+                y: 0,
+                width: 200,
+                height: 100
+            }
+        );
+
+        // Taller than 2:1, so bars go above and below
+        let viewport = Viewport::fit((100, 100), CANVAS, false, true, 1.0);
+        assert_eq!((viewport.y, viewport.width, viewport.height), (25, 100, 50));
+    }
+
+    #[test]
+    fn test_fit_stretches() {
+        let viewport = Viewport::fit((300, 100), CANVAS, false, false, 1.0);
+        assert_eq!((viewport.x, viewport.width, viewport.height), (0, 300, 100));
+    }
+
+    #[test]
+    fn test_fit_integer_scaling() {
+        // 3x fits horizontally but only 2x vertically
+        let viewport = Viewport::fit((200, 70), CANVAS, true, true, 1.0);
+        assert_eq!(
+            viewport,
+            Viewport {
+                x: 36,
+                y: 3,
+                width: 128,
+                height: 64
+            }
+        );
+
+        // Without a fixed aspect ratio each axis gets its own whole scale
+        let viewport = Viewport::fit((200, 70), CANVAS, true, false, 1.0);
+        assert_eq!((viewport.width, viewport.height), (192, 64));
+
+        // Too small for 1x: shrink instead of cropping
+        let viewport = Viewport::fit((32, 32), CANVAS, true, true, 1.0);
+        assert_eq!((viewport.width, viewport.height), (32, 16));
+    }
+
+    #[test]
+    fn test_fit_pixel_aspect_ratio() {
+        // Pixels half again as wide as they are tall make the canvas 3:1
+        let viewport = Viewport::fit((300, 300), CANVAS, false, true, 1.5);
+        assert_eq!(
+            (viewport.y, viewport.width, viewport.height),
+            (100, 300, 100)
+        );
+
+        // Whole-number scaling scales the stretched canvas
+        let viewport = Viewport::fit((300, 100), CANVAS, true, true, 1.5);
+        assert_eq!((viewport.x, viewport.width, viewport.height), (6, 288, 96));
+
+        // Stretching to fill ignores the pixel shape
+        let viewport = Viewport::fit((300, 300), CANVAS, false, false, 1.5);
+        assert_eq!((viewport.width, viewport.height), (300, 300));
+        assert_eq!(viewport.to_canvas(CANVAS, (299, 299)), Some((63, 31)));
+    }
+
+    #[test]
+    fn test_to_canvas() {
+        let viewport = Viewport::fit((300, 100), CANVAS, false, true, 1.0);
+        assert_eq!(viewport.to_canvas(CANVAS, (50, 0)), Some((0, 0)));
+        assert_eq!(viewport.to_canvas(CANVAS, (249, 99)), Some((63, 31)));
+        assert_eq!(viewport.to_canvas(CANVAS, (49, 50)), None);
+        assert_eq!(viewport.to_canvas(CANVAS, (250, 50)), None);
+    }
+
+    #[test]
+    fn test_blit_letterboxes() {
+        // A 2x1 canvas with one lit pixel, scaled 2x into a 6x2 buffer
+        let canvas = [255, 255, 255, 255, 0, 0, 0, 255];
+        let viewport = Viewport::fit((6, 2), (2, 1), true, true, 1.0);
+        let mut buffer = vec![7; 6 * 2 * 4];
+        viewport.blit(&canvas, (2, 1), &mut buffer, 6, Color::BLACK);
+
+        let lit: Vec<bool> = buffer.chunks_exact(4).map(|pixel| pixel[0] > 0).collect();
+        let row = [false, true, true, false, false, false];
+        assert_eq!(&lit[..6], &row);
+        assert_eq!(&lit[6..], &row);
+    }
+
+    #[test]
+    fn test_window_config_from_toml() {
+        let config: WindowConfig = toml::from_str("width = 640\nheight = 320").unwrap();
+        assert_eq!(config.size(), Some((640, 320)));
+        assert_eq!(config.position(), None);
+        assert!(!config.fullscreen);
+    }
+}
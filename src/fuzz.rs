@@ -0,0 +1,141 @@
+//! Fuzzing support for the CPU core.
+//!
+//! The cargo-fuzz target in `fuzz/` and the property tests below share
+//! these helpers: a CPU on null hardware with a fixed seed, and a driver
+//! that feeds it an arbitrary opcode stream through
+//! `Cpu::execute_arbitrary`. Errors are expected and ignored; the point is
+//! that no input makes the core panic.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::emulator::{Cpu, CpuEvent};
+use crate::hardware::{ChipKey, NullHardware};
+
+/// Creates a CPU for fuzzing: null hardware, seeded RNG and frame-driven timers.
+pub fn fuzz_cpu(seed: u64) -> Cpu<NullHardware> {
+    let mut cpu = Cpu::with_hardware(NullHardware::new());
+    cpu.seed_rng(seed);
+    cpu.set_realtime_timers(false);
+    cpu
+}
+
+/// Reads big-endian opcodes from raw fuzzer input, dropping a trailing odd byte.
+pub fn opcodes_from_bytes(data: &[u8]) -> Vec<u16> {
+    data.chunks_exact(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+        .collect()
+}
+
+/// Generates a random opcode stream.
+pub fn random_opcodes(seed: u64, count: usize) -> Vec<u16> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..count).map(|_| rng.gen()).collect()
+}
+
+/// Executes an opcode stream, one instruction at a time.
+///
+/// Key waits are answered immediately so the stream keeps going, and the
+/// timers tick every 16 instructions.
+///
+/// # Returns
+/// The number of instructions that returned an error.
+pub fn run_opcodes(cpu: &mut Cpu<NullHardware>, opcodes: &[u16]) -> usize {
+    let mut errors = 0;
+    for (count, &opcode) in opcodes.iter().enumerate() {
+        match cpu.execute_arbitrary(opcode) {
+            Ok(CpuEvent::WaitingForKey) => {
+                let key = ChipKey::from_u8(opcode as u8 & 0xF).unwrap_or(ChipKey::Key0);
+                let _ = cpu.resume_with_key(key);
+            }
+            Ok(_) => {}
+            Err(_) => errors += 1,
+        }
+        if count % 16 == 15 {
+            cpu.timers_mut().update_by_ticks(1);
+        }
+    }
+    errors
+}
+
+/// Loads raw fuzzer input as a ROM and runs it with `cycle` for up to `cycles` cycles.
+///
+/// # Returns
+/// The number of cycles run before an error, a key wait or the budget ran out.
+pub fn run_rom(cpu: &mut Cpu<NullHardware>, rom: &[u8], cycles: usize) -> usize {
+    if cpu.load_rom(rom).is_err() {
+        return 0;
+    }
+    for ran in 0..cycles {
+        match cpu.cycle() {
+            Ok(CpuEvent::WaitingForKey) | Err(_) => return ran,
+            Ok(_) => {}
+        }
+    }
+    cycles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_opcodes_from_bytes() {
+        assert_eq!(
+            opcodes_from_bytes(&[0x12, 0x34, 0xAB, 0xCD, 0xFF]),
+            [0x1234, 0xABCD]
+        );
+    }
+
+    #[test]
+    fn test_edge_opcodes_do_not_panic() {
+        // I at the top of the address space, then every I-relative and drawing opcode
+        let mut cpu = fuzz_cpu(0);
+        cpu.memory_mut().set_wraparound(true);
+        let edges = [
+            0x6FFF, 0x60FF, 0xAFFF, 0xF01E, 0xF01E, 0xFF33, 0xFF55, 0xFF65, 0xD00F, 0xB0FF, 0x6AFF,
+            0xEA9E, 0xEAA1, 0xF029, 0x00EE,
+        ];
+        run_opcodes(&mut cpu, &edges);
+
+        let mut cpu = fuzz_cpu(0);
+        assert!(run_opcodes(&mut cpu, &edges) > 0);
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn prop_opcode_streams_never_panic(
+            seed in any::<u64>(),
+            opcodes in prop::collection::vec(any::<u16>(), 0..512),
+            wraparound in any::<bool>(),
+        ) {
+            let mut cpu = fuzz_cpu(seed);
+            cpu.memory_mut().set_wraparound(wraparound);
+            let errors = run_opcodes(&mut cpu, &opcodes);
+
+            prop_assert!(errors <= opcodes.len());
+            prop_assert!(cpu.get_state().stack_contents.len() <= 16);
+            prop_assert_eq!(cpu.get_display_buffer().len(), 64 * 32);
+        }
+
+        #[test]
+        fn prop_random_roms_never_panic(
+            seed in any::<u64>(),
+            rom in prop::collection::vec(any::<u8>(), 0..256),
+        ) {
+            let mut cpu = fuzz_cpu(seed);
+            prop_assert!(run_rom(&mut cpu, &rom, 1000) <= 1000);
+        }
+    }
+
+    #[test]
+    fn test_random_opcode_streams() {
+        for seed in 0..32 {
+            let mut cpu = fuzz_cpu(seed);
+            run_opcodes(&mut cpu, &random_opcodes(seed, 4096));
+        }
+    }
+}
@@ -0,0 +1,202 @@
+//! Bitmap font for drawing text into RGBA frames.
+//!
+//! Glyphs are 3x5 pixels, drawn on a 4x6 grid so characters and lines are
+//! separated by one pixel. Letters are upper case only; lower case text is
+//! drawn in upper case and unsupported characters are drawn as `?`.
+
+use super::Color;
+
+/// Glyph width in pixels.
+pub const GLYPH_WIDTH: usize = 3;
+
+/// Glyph height in pixels.
+pub const GLYPH_HEIGHT: usize = 5;
+
+/// Horizontal distance between the starts of consecutive characters.
+pub const CHAR_ADVANCE: usize = GLYPH_WIDTH + 1;
+
+/// Vertical distance between the tops of consecutive lines.
+pub const LINE_HEIGHT: usize = GLYPH_HEIGHT + 1;
+
+/// Gets the rows of a character's glyph, top first.
+///
+/// Each row holds three pixels in its low bits, leftmost pixel in bit 2.
+pub fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    match c.to_ascii_uppercase() {
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        '*' => [0b000, 0b101, 0b010, 0b101, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '(' => [0b001, 0b010, 0b010, 0b010, 0b001],
+        ')' => [0b100, 0b010, 0b010, 0b010, 0b100],
+        '[' => [0b011, 0b010, 0b010, 0b010, 0b011],
+        ']' => [0b110, 0b010, 0b010, 0b010, 0b110],
+        '<' => [0b001, 0b010, 0b100, 0b010, 0b001],
+        '>' => [0b100, 0b010, 0b001, 0b010, 0b100],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        '\'' => [0b010, 0b010, 0b000, 0b000, 0b000],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        '#' => [0b101, 0b111, 0b101, 0b111, 0b101],
+        _ => [0b111, 0b001, 0b010, 0b000, 0b010],
+    }
+}
+
+/// Gets the width in pixels of a line of text, without trailing spacing.
+pub fn text_width(text: &str) -> usize {
+    (text.chars().count() * CHAR_ADVANCE).saturating_sub(1)
+}
+
+/// Draws a line of text into an RGBA frame.
+///
+/// Pixels outside the frame are clipped.
+///
+/// # Arguments
+/// * `frame` - RGBA pixel data, four bytes per pixel
+/// * `width` - Frame width in pixels
+/// * `x` - Left edge of the first character
+/// * `y` - Top edge of the line
+/// * `text` - Text to draw
+/// * `color` - Color of the lit glyph pixels
+pub fn draw_text(frame: &mut [u8], width: usize, x: usize, y: usize, text: &str, color: Color) {
+    for (i, c) in text.chars().enumerate() {
+        let left = x + i * CHAR_ADVANCE;
+        if left >= width {
+            break;
+        }
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for column in 0..GLYPH_WIDTH {
+                if bits & (0b100 >> column) != 0 {
+                    set_pixel(frame, width, left + column, y + row, color);
+                }
+            }
+        }
+    }
+}
+
+/// Fills a rectangle of an RGBA frame with a color.
+///
+/// Pixels outside the frame are clipped.
+pub fn fill_rect(
+    frame: &mut [u8],
+    width: usize,
+    x: usize,
+    y: usize,
+    w: usize,
+    h: usize,
+    color: Color,
+) {
+    for py in y..y + h {
+        for px in x..(x + w).min(width) {
+            set_pixel(frame, width, px, py, color);
+        }
+    }
+}
+
+/// Sets one pixel of an RGBA frame, ignoring coordinates outside it.
+fn set_pixel(frame: &mut [u8], width: usize, x: usize, y: usize, color: Color) {
+    if x >= width {
+        return;
+    }
+    let offset = (y * width + x) * 4;
+    if let Some(pixel) = frame.get_mut(offset..offset + 4) {
+        pixel.copy_from_slice(&color.to_rgba());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Renders a frame as rows of '#' and '.' for comparison.
+    fn lit_rows(frame: &[u8], width: usize) -> Vec<String> {
+        frame
+            .chunks_exact(width * 4)
+            .map(|row| {
+                row.chunks_exact(4)
+                    .map(|pixel| if pixel[0] > 0 { '#' } else { '.' })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_draw_text() {
+        let width = 8;
+        let mut frame = vec![0; width * 5 * 4];
+        draw_text(&mut frame, width, 0, 0, "hi", Color::WHITE);
+
+        assert_eq!(
+            lit_rows(&frame, width),
+            vec!["#.#.###.", "#.#..#..", "###..#..", "#.#..#..", "#.#.###."]
+        );
+    }
+
+    #[test]
+    fn test_draw_text_clips() {
+        let width = 5;
+        let mut frame = vec![0; width * 3 * 4];
+        // Only the top three rows of the first character fit
+        draw_text(&mut frame, width, 1, 0, "00", Color::WHITE);
+
+        assert_eq!(lit_rows(&frame, width), vec![".###.", ".#.#.", ".#.#."]);
+    }
+
+    #[test]
+    fn test_unknown_characters() {
+        assert_eq!(glyph('~'), glyph('?'));
+        assert_eq!(glyph('a'), glyph('A'));
+        assert_eq!(text_width("ABC"), 11);
+        assert_eq!(text_width(""), 0);
+    }
+
+    #[test]
+    fn test_fill_rect() {
+        let width = 4;
+        let mut frame = vec![0; width * 3 * 4];
+        fill_rect(&mut frame, width, 2, 1, 5, 5, Color::WHITE);
+
+        assert_eq!(lit_rows(&frame, width), vec!["....", "..##", "..##"]);
+    }
+}
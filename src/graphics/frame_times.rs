@@ -0,0 +1,174 @@
+//! Frame time history for diagnosing stutter.
+//!
+//! The GUI times three phases of every frame: running the emulator,
+//! rendering the picture and presenting it to the window. [`FrameTimes`]
+//! keeps the last [`FRAME_HISTORY`] frames so the on-screen graph and
+//! anything else in the GUI layer can show which phase is slow. With vsync
+//! on, presenting waits for the display, so a long present phase on its
+//! own is normal.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Number of frames kept.
+pub const FRAME_HISTORY: usize = 240;
+
+/// A part of the frame loop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FramePhase {
+    /// Running instructions and timers.
+    Emulation,
+    /// Drawing the picture and overlays and scaling it to the window.
+    Render,
+    /// Handing the picture to the display.
+    Present,
+}
+
+impl FramePhase {
+    /// Every phase, in frame order.
+    pub const ALL: [FramePhase; 3] = [
+        FramePhase::Emulation,
+        FramePhase::Render,
+        FramePhase::Present,
+    ];
+
+    /// Gets the short label shown on screen.
+    pub fn label(self) -> &'static str {
+        match self {
+            FramePhase::Emulation => "EMU",
+            FramePhase::Render => "REN",
+            FramePhase::Present => "PRE",
+        }
+    }
+}
+
+/// How long each phase of one frame took.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FrameTiming {
+    /// Time running the emulator.
+    pub emulation: Duration,
+
+    /// Time rendering.
+    pub render: Duration,
+
+    /// Time presenting.
+    pub present: Duration,
+}
+
+impl FrameTiming {
+    /// Gets the time spent in one phase.
+    pub fn phase(&self, phase: FramePhase) -> Duration {
+        match phase {
+            FramePhase::Emulation => self.emulation,
+            FramePhase::Render => self.render,
+            FramePhase::Present => self.present,
+        }
+    }
+
+    /// Gets the time spent in all phases.
+    pub fn total(&self) -> Duration {
+        self.emulation + self.render + self.present
+    }
+}
+
+/// The last [`FRAME_HISTORY`] frame timings.
+#[derive(Debug, Clone, Default)]
+pub struct FrameTimes {
+    /// Timings, oldest first.
+    frames: VecDeque<FrameTiming>,
+}
+
+impl FrameTimes {
+    /// Creates an empty history.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a frame, dropping the oldest once the history is full.
+    pub fn record(&mut self, timing: FrameTiming) {
+        if self.frames.len() == FRAME_HISTORY {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(timing);
+    }
+
+    /// Gets the recorded frames, oldest first.
+    pub fn frames(&self) -> impl DoubleEndedIterator<Item = &FrameTiming> + ExactSizeIterator {
+        self.frames.iter()
+    }
+
+    /// Gets the number of recorded frames.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Checks if no frames have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Forgets every recorded frame.
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    /// Gets the mean time of each phase.
+    pub fn average(&self) -> FrameTiming {
+        let count = self.frames.len().max(1) as u32;
+        let sum = |phase| self.frames.iter().map(|f| f.phase(phase)).sum::<Duration>();
+        FrameTiming {
+            emulation: sum(FramePhase::Emulation) / count,
+            render: sum(FramePhase::Render) / count,
+            present: sum(FramePhase::Present) / count,
+        }
+    }
+
+    /// Gets the slowest frame.
+    pub fn worst(&self) -> Option<FrameTiming> {
+        self.frames.iter().copied().max_by_key(FrameTiming::total)
+    }
+
+    /// Gets the phase that takes longest on average.
+    pub fn bottleneck(&self) -> Option<FramePhase> {
+        if self.frames.is_empty() {
+            return None;
+        }
+        let average = self.average();
+        FramePhase::ALL
+            .into_iter()
+            .max_by_key(|&phase| average.phase(phase))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timing(emulation: u64, render: u64, present: u64) -> FrameTiming {
+        FrameTiming {
+            emulation: Duration::from_millis(emulation),
+            render: Duration::from_millis(render),
+            present: Duration::from_millis(present),
+        }
+    }
+
+    #[test]
+    fn test_frame_times() {
+        let mut times = FrameTimes::new();
+        assert_eq!(times.bottleneck(), None);
+
+        times.record(timing(2, 1, 12));
+        times.record(timing(20, 1, 4));
+        assert_eq!(times.average(), timing(11, 1, 8));
+        assert_eq!(times.worst(), Some(timing(20, 1, 4)));
+        assert_eq!(times.bottleneck(), Some(FramePhase::Emulation));
+
+        // Only the last FRAME_HISTORY frames are kept
+        for _ in 0..FRAME_HISTORY {
+            times.record(timing(1, 2, 3));
+        }
+        assert_eq!(times.len(), FRAME_HISTORY);
+        assert_eq!(times.worst(), Some(timing(1, 2, 3)));
+        assert_eq!(times.bottleneck(), Some(FramePhase::Present));
+    }
+}
@@ -3,25 +3,79 @@
 //! This module provides the graphics implementation for rendering
 //! the Chip-8 display.
 
+pub mod font;
+pub mod frame_times;
+pub mod osd;
 pub mod renderer;
+pub mod screenshot;
+pub mod theme;
+pub mod y4m;
 
 // Re-export commonly used types
-pub use renderer::{Color, GraphicsConfig, PixelRenderer};
+pub use frame_times::{FramePhase, FrameTimes, FrameTiming};
+pub use osd::Osd;
+pub use renderer::{
+    Color, DisplayBackend, GraphicsConfig, Orientation, PixelRenderer, PresentMode, Rotation,
+};
+pub use screenshot::ScreenshotFormat;
+pub use theme::{Phosphor, Theme, ThemeManager};
 
 use crate::error::{EmulatorError, GraphicsError};
-use crate::hardware::{Display, DisplayResult, DISPLAY_HEIGHT, DISPLAY_PIXELS, DISPLAY_WIDTH};
+use crate::hardware::display::DisplayError;
+use crate::hardware::{
+    check_resolution, xor_sprite, ClipMode, Display, DisplayResult, DISPLAY_HEIGHT, DISPLAY_PIXELS,
+    DISPLAY_WIDTH,
+};
 
 /// Result type for graphics operations.
 pub type GraphicsResult<T> = Result<T, GraphicsError>;
 
-/// Hardware display implementation for testing without actual graphics.
+/// A finished frame handed to a frontend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame<'a> {
+    /// Sequence number, incremented each time a changed frame is published.
+    pub sequence: u64,
+
+    /// Width of the frame in pixels.
+    pub width: usize,
+
+    /// Height of the frame in pixels.
+    pub height: usize,
+
+    /// Pixels in row-major order.
+    pub pixels: &'a [bool],
+}
+
+/// Double-buffered display.
+///
+/// The CPU draws into a back buffer while frontends read a stable front
+/// buffer. `swap` publishes the back buffer; CHIP-8 draws incrementally, so
+/// the back buffer is copied rather than exchanged.
 pub struct GraphicsDisplay {
-    /// Internal display buffer.
-    buffer: [bool; DISPLAY_PIXELS],
+    /// Back buffer the CPU draws into.
+    buffer: Vec<bool>,
+
+    /// Front buffer frontends read.
+    front: Vec<bool>,
+
+    /// Width of both buffers in pixels.
+    width: usize,
+
+    /// Height of both buffers in pixels.
+    height: usize,
 
-    /// Dirty flag for tracking changes.
+    /// Sequence number of the front buffer.
+    sequence: u64,
+
+    /// Sequence number last returned by `take_frame`.
+    taken: u64,
+
+    /// Whether the back buffer changed since the last swap.
     dirty: bool,
 
+    /// What happens to sprite pixels past the edge.
+    clip_mode: ClipMode,
+
     /// Software renderer for converting to pixel buffer.
     renderer: PixelRenderer,
 }
@@ -29,25 +83,69 @@ pub struct GraphicsDisplay {
 impl GraphicsDisplay {
     /// Creates a new graphics display.
     pub fn new() -> GraphicsResult<Self> {
-        Ok(Self {
-            buffer: [false; DISPLAY_PIXELS],
-            dirty: false,
-            renderer: PixelRenderer::with_defaults()?,
-        })
+        Self::with_config(GraphicsConfig::default())
     }
 
     /// Creates a graphics display with custom configuration.
     pub fn with_config(graphics_config: GraphicsConfig) -> GraphicsResult<Self> {
         Ok(Self {
-            buffer: [false; DISPLAY_PIXELS],
+            buffer: vec![false; DISPLAY_PIXELS],
+            front: vec![false; DISPLAY_PIXELS],
+            width: DISPLAY_WIDTH,
+            height: DISPLAY_HEIGHT,
+            sequence: 0,
+            taken: 0,
             dirty: false,
+            clip_mode: ClipMode::default(),
             renderer: PixelRenderer::new(graphics_config)?,
         })
     }
 
-    /// Renders the display buffer and returns the pixel data.
+    /// Publishes the back buffer to the front buffer if it changed.
+    ///
+    /// # Returns
+    /// The sequence number of the front buffer.
+    pub fn swap(&mut self) -> u64 {
+        if self.dirty {
+            self.front.clone_from(&self.buffer);
+            self.sequence += 1;
+            self.dirty = false;
+        }
+        self.sequence
+    }
+
+    /// Publishes the back buffer and returns the frame if it is new.
+    ///
+    /// # Returns
+    /// The front buffer, or `None` if it hasn't changed since the last call.
+    pub fn take_frame(&mut self) -> Option<Frame<'_>> {
+        let sequence = self.swap();
+        if sequence == self.taken {
+            return None;
+        }
+        self.taken = sequence;
+        Some(self.frame())
+    }
+
+    /// Gets the front buffer without publishing pending changes.
+    pub fn frame(&self) -> Frame<'_> {
+        Frame {
+            sequence: self.sequence,
+            width: self.width,
+            height: self.height,
+            pixels: &self.front,
+        }
+    }
+
+    /// Gets the sequence number of the front buffer.
+    pub fn frame_sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Publishes the back buffer, renders it and returns the pixel data.
     pub fn get_pixel_data(&mut self) -> GraphicsResult<&[u8]> {
-        self.renderer.render(&self.buffer)?;
+        self.swap();
+        self.renderer.render(&self.front)?;
         Ok(self.renderer.frame_buffer())
     }
 
@@ -65,6 +163,21 @@ impl GraphicsDisplay {
     pub fn graphics_config(&self) -> &GraphicsConfig {
         self.renderer.config()
     }
+
+    /// Renders the current buffer and saves it as an image.
+    ///
+    /// # Arguments
+    /// * `path` - Output file
+    /// * `format` - Image format to write
+    pub fn save_screenshot<P: AsRef<std::path::Path>>(
+        &mut self,
+        path: P,
+        format: ScreenshotFormat,
+    ) -> GraphicsResult<()> {
+        self.swap();
+        self.renderer.render(&self.front)?;
+        self.renderer.save_frame(path.as_ref(), format)
+    }
 }
 
 impl Default for GraphicsDisplay {
@@ -80,27 +193,7 @@ impl Display for GraphicsDisplay {
     }
 
     fn draw_sprite(&mut self, x: u8, y: u8, sprite: &[u8]) -> DisplayResult<bool> {
-        let mut collision = false;
-
-        for (row, &sprite_byte) in sprite.iter().enumerate() {
-            let pixel_y = (y + row as u8) % DISPLAY_HEIGHT as u8;
-
-            for col in 0..8 {
-                let pixel_x = (x + col) % DISPLAY_WIDTH as u8;
-                let sprite_pixel = (sprite_byte >> (7 - col)) & 1;
-
-                if sprite_pixel == 1 {
-                    let index = pixel_y as usize * DISPLAY_WIDTH + pixel_x as usize;
-                    let old_pixel = self.buffer[index];
-                    self.buffer[index] ^= true;
-
-                    // Collision if pixel was turned off
-                    if old_pixel && !self.buffer[index] {
-                        collision = true;
-                    }
-                }
-            }
-        }
+        let collision = xor_sprite(&mut self.buffer, self.width, x, y, sprite, self.clip_mode);
 
         if !sprite.is_empty() {
             self.dirty = true;
@@ -109,34 +202,37 @@ impl Display for GraphicsDisplay {
         Ok(collision)
     }
 
-    fn get_pixel(&self, x: u8, y: u8) -> DisplayResult<bool> {
-        use crate::hardware::display::DisplayError;
+    fn set_clip_mode(&mut self, mode: ClipMode) {
+        self.clip_mode = mode;
+    }
 
-        if x as usize >= DISPLAY_WIDTH || y as usize >= DISPLAY_HEIGHT {
+    fn clip_mode(&self) -> ClipMode {
+        self.clip_mode
+    }
+
+    fn get_pixel(&self, x: u8, y: u8) -> DisplayResult<bool> {
+        if x as usize >= self.width || y as usize >= self.height {
             return Err(DisplayError::InvalidCoordinates { x, y });
         }
 
-        let index = y as usize * DISPLAY_WIDTH + x as usize;
+        let index = y as usize * self.width + x as usize;
         Ok(self.buffer[index])
     }
 
     fn set_pixel(&mut self, x: u8, y: u8, on: bool) -> DisplayResult<()> {
-        use crate::hardware::display::DisplayError;
-
-        if x as usize >= DISPLAY_WIDTH || y as usize >= DISPLAY_HEIGHT {
+        if x as usize >= self.width || y as usize >= self.height {
             return Err(DisplayError::InvalidCoordinates { x, y });
         }
 
-        let index = y as usize * DISPLAY_WIDTH + x as usize;
+        let index = y as usize * self.width + x as usize;
         self.buffer[index] = on;
         self.dirty = true;
         Ok(())
     }
 
     fn render(&mut self) -> Result<(), EmulatorError> {
-        // For this implementation, rendering just marks as clean
-        // Real rendering would be handled by external graphics systems
-        self.mark_clean();
+        // Real rendering is handled by the frontend, which reads the front buffer
+        self.swap();
         Ok(())
     }
 
@@ -145,7 +241,7 @@ impl Display for GraphicsDisplay {
     }
 
     fn mark_clean(&mut self) {
-        self.dirty = false;
+        self.swap();
     }
 
     fn get_buffer(&self) -> &[bool] {
@@ -156,6 +252,25 @@ impl Display for GraphicsDisplay {
         self.dirty = true;
         &mut self.buffer
     }
+
+    fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Reallocates both buffers and the renderer's frame, publishing a
+    /// blank frame of the new size on the next swap.
+    fn resize(&mut self, width: usize, height: usize) -> DisplayResult<()> {
+        check_resolution(width, height)?;
+        self.renderer
+            .resize(width, height)
+            .map_err(|_| DisplayError::UnsupportedResolution { width, height })?;
+        self.buffer = vec![false; width * height];
+        self.front = vec![false; width * height];
+        self.width = width;
+        self.height = height;
+        self.dirty = true;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -172,6 +287,31 @@ mod tests {
         assert!(display.get_buffer().iter().all(|&pixel| !pixel));
     }
 
+    #[test]
+    fn test_save_screenshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("screen.ppm");
+        let config = GraphicsConfig::new()
+            .with_foreground_color(Color::WHITE)
+            .with_background_color(Color::BLACK)
+            .with_scale_factor(1);
+        let mut display = GraphicsDisplay::with_config(config).unwrap();
+        display.set_pixel(1, 0, true).unwrap();
+
+        display
+            .save_screenshot(&path, ScreenshotFormat::Ppm)
+            .unwrap();
+
+        let ppm = std::fs::read(&path).unwrap();
+        let header = b"P6\n64 32\n255\n";
+        assert!(ppm.starts_with(header));
+        assert_eq!(ppm.len(), header.len() + DISPLAY_PIXELS * 3);
+        assert_eq!(
+            &ppm[header.len()..header.len() + 6],
+            &[0, 0, 0, 255, 255, 255]
+        );
+    }
+
     #[test]
     fn test_graphics_display_pixel_operations() {
         let mut display = GraphicsDisplay::new().unwrap();
@@ -248,6 +388,53 @@ mod tests {
         assert!(width > 0 && height > 0);
     }
 
+    #[test]
+    fn test_double_buffering() {
+        let mut display = GraphicsDisplay::new().unwrap();
+        assert_eq!(display.frame_sequence(), 0);
+        assert!(display.take_frame().is_none());
+
+        // Drawing only reaches the front buffer on swap
+        display.draw_sprite(0, 0, &[0b10000000]).unwrap();
+        assert!(display.get_pixel(0, 0).unwrap());
+        assert!(!display.frame().pixels[0]);
+
+        let frame = display.take_frame().unwrap();
+        assert_eq!(frame.sequence, 1);
+        assert!(frame.pixels[0]);
+        assert!(!display.is_dirty());
+
+        // Unchanged frames are skipped
+        assert!(display.take_frame().is_none());
+        assert_eq!(display.swap(), 1);
+
+        display.clear();
+        assert_eq!(display.swap(), 2);
+        let frame = display.take_frame().unwrap();
+        assert_eq!(frame.sequence, 2);
+        assert!(!frame.pixels[0]);
+    }
+
+    #[test]
+    fn test_graphics_display_resize() {
+        let mut display =
+            GraphicsDisplay::with_config(GraphicsConfig::new().with_scale_factor(1)).unwrap();
+        display.resize(128, 64).unwrap();
+        assert_eq!(display.size(), (128, 64));
+        assert_eq!(display.frame_size(), (128, 64));
+
+        // Sprites reach the whole of the larger screen
+        display.draw_sprite(127, 63, &[0b10000000]).unwrap();
+        assert!(display.get_pixel(127, 63).unwrap());
+        let frame = display.take_frame().unwrap();
+        assert_eq!((frame.width, frame.height), (128, 64));
+        assert!(frame.pixels[128 * 64 - 1]);
+        assert_eq!(display.get_pixel_data().unwrap().len(), 128 * 64 * 4);
+
+        assert!(display.resize(64, 0).is_err());
+        assert_eq!(display.size(), (128, 64));
+    }
+
     #[test]
     fn test_graphics_display_config() {
         let mut display = GraphicsDisplay::new().unwrap();
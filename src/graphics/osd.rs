@@ -0,0 +1,597 @@
+//! On-screen display overlay.
+//!
+//! The OSD draws text over a rendered frame: short-lived messages pushed
+//! by the frontend (ROM loaded, paused, screenshot saved), speed counters,
+//! a graph of recent frame times, and the state of the virtual keypad. Text
+//! is drawn with the bitmap font on a box in the background color so it
+//! stays readable over the game.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use super::font::{draw_text, fill_rect, text_width, CHAR_ADVANCE, GLYPH_HEIGHT, LINE_HEIGHT};
+use super::frame_times::{FramePhase, FrameTimes};
+use super::Color;
+use crate::emulator::{MetricCounter, Metrics};
+use crate::hardware::ChipKey;
+
+/// How long messages stay on screen by default.
+pub const DEFAULT_MESSAGE_DURATION: Duration = Duration::from_secs(2);
+
+/// Most messages shown at once; older ones are dropped first.
+const MAX_MESSAGES: usize = 4;
+
+/// Gap between overlay text and the edge of the frame.
+const MARGIN: usize = 2;
+
+/// Height of the frame time graph in pixels.
+const GRAPH_HEIGHT: usize = 20;
+
+/// Frame time at the top of the graph: two frames at 60 Hz.
+const GRAPH_RANGE: Duration = Duration::from_micros(33_333);
+
+/// Keypad keys in their physical layout, top row first.
+const KEYPAD_LAYOUT: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+/// A message waiting to expire.
+#[derive(Debug, Clone)]
+struct OsdMessage {
+    /// Text to show.
+    text: String,
+
+    /// When the message stops being shown.
+    expires: Instant,
+}
+
+/// Overlay state for a frontend window.
+#[derive(Debug, Clone)]
+pub struct Osd {
+    /// Messages on screen, oldest first.
+    messages: VecDeque<OsdMessage>,
+
+    /// How long new messages stay on screen.
+    message_duration: Duration,
+
+    /// Whether speed counters are drawn.
+    show_stats: bool,
+
+    /// Last measured (instructions per second, frames per second).
+    stats: (f64, f64),
+
+    /// Performance counters drawn below the speed counters.
+    counters: Vec<MetricCounter>,
+
+    /// Latest snapshot of the performance counters.
+    metrics: Metrics,
+
+    /// Whether the keypad is drawn.
+    show_keypad: bool,
+
+    /// Whether the latency of the last key press is drawn.
+    show_latency: bool,
+
+    /// Whether the frame time graph is drawn.
+    show_frame_times: bool,
+
+    /// Latest frame times, kept while the graph is shown.
+    frame_times: FrameTimes,
+
+    /// Which keys are held, indexed by key value.
+    keys: [bool; 16],
+}
+
+impl Osd {
+    /// Creates an overlay with nothing shown.
+    pub fn new() -> Self {
+        Self {
+            messages: VecDeque::new(),
+            message_duration: DEFAULT_MESSAGE_DURATION,
+            show_stats: false,
+            stats: (0.0, 0.0),
+            counters: Vec::new(),
+            metrics: Metrics::default(),
+            show_keypad: false,
+            show_latency: false,
+            show_frame_times: false,
+            frame_times: FrameTimes::new(),
+            keys: [false; 16],
+        }
+    }
+
+    /// Sets how long new messages stay on screen.
+    pub fn with_message_duration(mut self, duration: Duration) -> Self {
+        self.message_duration = duration;
+        self
+    }
+
+    /// Sets whether speed counters are drawn.
+    pub fn with_stats(mut self, show: bool) -> Self {
+        self.show_stats = show;
+        self
+    }
+
+    /// Sets which performance counters are drawn.
+    pub fn with_counters(mut self, counters: Vec<MetricCounter>) -> Self {
+        self.counters = counters;
+        self
+    }
+
+    /// Sets whether the keypad is drawn.
+    pub fn with_keypad(mut self, show: bool) -> Self {
+        self.show_keypad = show;
+        self
+    }
+
+    /// Sets whether the latency of the last key press is drawn.
+    pub fn with_latency(mut self, show: bool) -> Self {
+        self.show_latency = show;
+        self
+    }
+
+    /// Sets whether the frame time graph is drawn.
+    pub fn with_frame_times(mut self, show: bool) -> Self {
+        self.show_frame_times = show;
+        self
+    }
+
+    /// Shows a message until the message duration has passed.
+    ///
+    /// # Arguments
+    /// * `text` - Message to show
+    /// * `now` - Current time
+    pub fn push_message(&mut self, text: impl Into<String>, now: Instant) {
+        if self.messages.len() == MAX_MESSAGES {
+            self.messages.pop_front();
+        }
+        self.messages.push_back(OsdMessage {
+            text: text.into(),
+            expires: now + self.message_duration,
+        });
+    }
+
+    /// Drops messages that have expired.
+    ///
+    /// # Returns
+    /// True if any message was dropped, so the frame needs redrawing.
+    pub fn expire(&mut self, now: Instant) -> bool {
+        let before = self.messages.len();
+        self.messages.retain(|message| message.expires > now);
+        self.messages.len() != before
+    }
+
+    /// Gets the messages on screen, oldest first.
+    pub fn messages(&self) -> impl Iterator<Item = &str> {
+        self.messages.iter().map(|message| message.text.as_str())
+    }
+
+    /// Checks if any messages are on screen.
+    pub fn has_messages(&self) -> bool {
+        !self.messages.is_empty()
+    }
+
+    /// Records the latest speed measurement.
+    pub fn set_stats(&mut self, ips: f64, fps: f64) {
+        self.stats = (ips, fps);
+    }
+
+    /// Records the latest performance counters.
+    pub fn set_metrics(&mut self, metrics: Metrics) {
+        self.metrics = metrics;
+    }
+
+    /// Toggles the speed counters.
+    ///
+    /// # Returns
+    /// Whether they are now shown.
+    pub fn toggle_stats(&mut self) -> bool {
+        self.show_stats = !self.show_stats;
+        self.show_stats
+    }
+
+    /// Records the latest frame times, if the graph is shown.
+    pub fn set_frame_times(&mut self, frame_times: &FrameTimes) {
+        if self.show_frame_times {
+            self.frame_times.clone_from(frame_times);
+        }
+    }
+
+    /// Toggles the frame time graph.
+    ///
+    /// # Returns
+    /// Whether it is now shown.
+    pub fn toggle_frame_times(&mut self) -> bool {
+        self.show_frame_times = !self.show_frame_times;
+        self.frame_times.clear();
+        self.show_frame_times
+    }
+
+    /// Records which keypad keys are held.
+    pub fn set_keys(&mut self, pressed: &[ChipKey]) {
+        self.keys = [false; 16];
+        for key in pressed {
+            self.keys[key.to_u8() as usize] = true;
+        }
+    }
+
+    /// Toggles the keypad display.
+    ///
+    /// # Returns
+    /// Whether it is now shown.
+    pub fn toggle_keypad(&mut self) -> bool {
+        self.show_keypad = !self.show_keypad;
+        self.show_keypad
+    }
+
+    /// Checks if there is anything to draw.
+    pub fn is_visible(&self) -> bool {
+        self.has_messages()
+            || self.show_stats
+            || !self.counters.is_empty()
+            || self.show_keypad
+            || self.show_latency
+            || self.show_frame_times
+    }
+
+    /// Draws the overlay on top of an RGBA frame.
+    ///
+    /// Messages stack up from the bottom left, the counters sit at the top
+    /// right with any performance counters and the input latency below
+    /// them, the frame time graph at the top left, and the keypad at the
+    /// bottom right.
+    ///
+    /// # Arguments
+    /// * `frame` - RGBA pixel data, four bytes per pixel
+    /// * `width` - Frame width in pixels
+    /// * `height` - Frame height in pixels
+    /// * `foreground` - Text color
+    /// * `background` - Color of the boxes behind the text
+    pub fn render(
+        &self,
+        frame: &mut [u8],
+        width: usize,
+        height: usize,
+        foreground: Color,
+        background: Color,
+    ) {
+        let newest_first = self.messages.iter().rev();
+        for (line, message) in newest_first.enumerate() {
+            let Some(y) = height.checked_sub(MARGIN + GLYPH_HEIGHT + line * LINE_HEIGHT) else {
+                break;
+            };
+            label(
+                frame,
+                width,
+                MARGIN,
+                y,
+                &message.text,
+                foreground,
+                background,
+            );
+        }
+
+        let mut lines = Vec::new();
+        if self.show_stats {
+            let (ips, fps) = self.stats;
+            lines.push(format!("{:.0} IPS {:.0} FPS", ips, fps));
+        }
+        for &counter in &self.counters {
+            lines.push(format!(
+                "{} {}",
+                counter.label(),
+                self.metrics.value(counter)
+            ));
+        }
+        if self.show_latency {
+            lines.extend(latency_lines(&self.metrics));
+        }
+        for (line, text) in lines.iter().enumerate() {
+            let x = width.saturating_sub(MARGIN + text_width(text));
+            let y = MARGIN + line * LINE_HEIGHT;
+            label(frame, width, x, y, text, foreground, background);
+        }
+
+        if self.show_frame_times {
+            self.render_frame_times(frame, width, foreground, background);
+        }
+
+        if self.show_keypad {
+            let grid_width = 4 * CHAR_ADVANCE - 1;
+            let left = width.saturating_sub(MARGIN + grid_width);
+            let top = height.saturating_sub(MARGIN + 4 * LINE_HEIGHT - 1);
+            fill_rect(
+                frame,
+                width,
+                left.saturating_sub(1),
+                top.saturating_sub(1),
+                grid_width + 2,
+                4 * LINE_HEIGHT + 1,
+                background,
+            );
+            for (row, keys) in KEYPAD_LAYOUT.iter().enumerate() {
+                for (column, &key) in keys.iter().enumerate() {
+                    let x = left + column * CHAR_ADVANCE;
+                    let y = top + row * LINE_HEIGHT;
+                    let text = format!("{:X}", key);
+                    if self.keys[key as usize] {
+                        label(frame, width, x, y, &text, background, foreground);
+                    } else {
+                        draw_text(frame, width, x, y, &text, foreground);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Osd {
+    /// Draws the frame time graph and the average of each phase.
+    ///
+    /// Each column is one frame, newest on the right, as tall as the frame
+    /// took; the dotted line marks a 60 Hz frame.
+    fn render_frame_times(
+        &self,
+        frame: &mut [u8],
+        width: usize,
+        foreground: Color,
+        background: Color,
+    ) {
+        let columns = self
+            .frame_times
+            .len()
+            .min((width / 2).saturating_sub(MARGIN));
+        let graph_width = columns.max(1);
+        fill_rect(
+            frame,
+            width,
+            MARGIN - 1,
+            MARGIN - 1,
+            graph_width + 2,
+            GRAPH_HEIGHT + 2,
+            background,
+        );
+
+        let newest = self.frame_times.frames().rev().take(columns);
+        for (column, timing) in newest.enumerate() {
+            let fraction = timing.total().as_secs_f64() / GRAPH_RANGE.as_secs_f64();
+            let bar = ((fraction * GRAPH_HEIGHT as f64).round() as usize).clamp(1, GRAPH_HEIGHT);
+            let x = MARGIN + columns - 1 - column;
+            fill_rect(
+                frame,
+                width,
+                x,
+                MARGIN + GRAPH_HEIGHT - bar,
+                1,
+                bar,
+                foreground,
+            );
+        }
+        for x in (MARGIN..MARGIN + graph_width).step_by(2) {
+            fill_rect(frame, width, x, MARGIN + GRAPH_HEIGHT / 2, 1, 1, foreground);
+        }
+
+        for (line, text) in frame_time_lines(&self.frame_times).iter().enumerate() {
+            let y = MARGIN + GRAPH_HEIGHT + 2 + line * LINE_HEIGHT;
+            label(frame, width, MARGIN, y, text, foreground, background);
+        }
+    }
+}
+
+impl Default for Osd {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Formats the latency of the last key press, end to end and by stage.
+fn latency_lines(metrics: &Metrics) -> [String; 2] {
+    let latency = &metrics.input_latency;
+    let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    let Some(key) = latency.last_key else {
+        return ["KEY - -MS".to_string(), "MAP - IN - CPU -".to_string()];
+    };
+    [
+        format!("KEY {} {:.1}MS", key, ms(latency.key(key).last)),
+        format!(
+            "MAP {:.1} IN {:.1} CPU {:.1}",
+            ms(latency.mapping.last),
+            ms(latency.delivery.last),
+            ms(latency.polling.last)
+        ),
+    ]
+}
+
+/// Formats the average time of each phase and the slowest one.
+fn frame_time_lines(frame_times: &FrameTimes) -> [String; 2] {
+    let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    let average = frame_times.average();
+    let phases: Vec<String> = FramePhase::ALL
+        .iter()
+        .map(|&phase| format!("{} {:.1}", phase.label(), ms(average.phase(phase))))
+        .collect();
+    let slowest = match frame_times.bottleneck() {
+        Some(phase) => phase.label(),
+        None => "-",
+    };
+    let worst = frame_times.worst().unwrap_or_default();
+    [
+        format!("{}MS", phases.join(" ")),
+        format!("SLOW {} MAX {:.1}MS", slowest, ms(worst.total())),
+    ]
+}
+
+/// Draws text on a box one pixel larger than the text on each side.
+fn label(
+    frame: &mut [u8],
+    width: usize,
+    x: usize,
+    y: usize,
+    text: &str,
+    foreground: Color,
+    background: Color,
+) {
+    fill_rect(
+        frame,
+        width,
+        x.saturating_sub(1),
+        y.saturating_sub(1),
+        text_width(text) + 2,
+        GLYPH_HEIGHT + 2,
+        background,
+    );
+    draw_text(frame, width, x, y, text, foreground);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: usize = 64;
+    const HEIGHT: usize = 32;
+
+    /// Checks if a pixel of an RGBA frame is lit.
+    fn is_lit(frame: &[u8], x: usize, y: usize) -> bool {
+        frame[(y * WIDTH + x) * 4] > 0
+    }
+
+    #[test]
+    fn test_messages_expire() {
+        let start = Instant::now();
+        let mut osd = Osd::new().with_message_duration(Duration::from_secs(1));
+        assert!(!osd.is_visible());
+
+        osd.push_message("LOADED", start);
+        osd.push_message("PAUSED", start + Duration::from_millis(500));
+        assert!(osd.is_visible());
+
+        assert!(!osd.expire(start + Duration::from_millis(900)));
+        assert!(osd.expire(start + Duration::from_millis(1000)));
+        assert_eq!(osd.messages().collect::<Vec<_>>(), vec!["PAUSED"]);
+        assert!(osd.expire(start + Duration::from_secs(2)));
+        assert!(!osd.is_visible());
+    }
+
+    #[test]
+    fn test_old_messages_dropped() {
+        let now = Instant::now();
+        let mut osd = Osd::new();
+        for i in 0..MAX_MESSAGES + 2 {
+            osd.push_message(i.to_string(), now);
+        }
+        assert_eq!(osd.messages().collect::<Vec<_>>(), vec!["2", "3", "4", "5"]);
+    }
+
+    #[test]
+    fn test_render_message_and_stats() {
+        let mut osd = Osd::new().with_stats(true);
+        osd.push_message("1", Instant::now());
+        osd.set_stats(700.0, 60.0);
+
+        let mut frame = vec![0; WIDTH * HEIGHT * 4];
+        osd.render(&mut frame, WIDTH, HEIGHT, Color::WHITE, Color::BLACK);
+
+        // The "1" glyph's bottom row is 111, drawn just above the bottom margin
+        let bottom = HEIGHT - MARGIN - 1;
+        assert!(is_lit(&frame, MARGIN, bottom) && is_lit(&frame, MARGIN + 2, bottom));
+        // "700 IPS 60 FPS" ends in an S, whose top row is 011
+        let right = WIDTH - MARGIN - 1;
+        assert!(is_lit(&frame, right, MARGIN) && !is_lit(&frame, right - 2, MARGIN));
+    }
+
+    #[test]
+    fn test_render_counters() {
+        let mut osd = Osd::new().with_counters(vec![MetricCounter::Collisions]);
+        assert!(osd.is_visible());
+        osd.set_metrics(Metrics {
+            collisions: 1,
+            ..Default::default()
+        });
+
+        let mut frame = vec![0; WIDTH * HEIGHT * 4];
+        osd.render(&mut frame, WIDTH, HEIGHT, Color::WHITE, Color::BLACK);
+
+        // Without the speed counters, "COL 1" takes the top line; 1's top row is 010
+        let right = WIDTH - MARGIN - 1;
+        assert!(is_lit(&frame, right - 1, MARGIN) && !is_lit(&frame, right, MARGIN));
+    }
+
+    #[test]
+    fn test_latency_lines() {
+        let mut metrics = Metrics::default();
+        assert_eq!(latency_lines(&metrics)[0], "KEY - -MS");
+
+        let latency = &mut metrics.input_latency;
+        latency.keys[0x5].record(Duration::from_micros(4200));
+        latency.mapping.record(Duration::ZERO);
+        latency.delivery.record(Duration::from_micros(100));
+        latency.polling.record(Duration::from_micros(4100));
+        latency.last_key = Some(ChipKey::Key5);
+        assert_eq!(
+            latency_lines(&metrics),
+            [
+                "KEY 5 4.2MS".to_string(),
+                "MAP 0.0 IN 0.1 CPU 4.1".to_string()
+            ]
+        );
+
+        let osd = Osd::new().with_latency(true);
+        assert!(osd.is_visible());
+    }
+
+    #[test]
+    fn test_render_frame_times() {
+        use crate::graphics::frame_times::FrameTiming;
+
+        let mut frame_times = FrameTimes::new();
+        frame_times.record(FrameTiming {
+            emulation: Duration::from_micros(1500),
+            render: Duration::from_micros(500),
+            present: Duration::from_micros(14_667),
+        });
+        assert_eq!(
+            frame_time_lines(&frame_times),
+            [
+                "EMU 1.5 REN 0.5 PRE 14.7MS".to_string(),
+                "SLOW PRE MAX 16.7MS".to_string()
+            ]
+        );
+
+        // Frame times are only kept while the graph is shown
+        let mut osd = Osd::new();
+        osd.set_frame_times(&frame_times);
+        assert!(osd.toggle_frame_times());
+        assert!(osd.is_visible());
+        let mut frame = vec![0; WIDTH * HEIGHT * 4];
+        osd.render(&mut frame, WIDTH, HEIGHT, Color::WHITE, Color::BLACK);
+        assert!(!is_lit(&frame, MARGIN, MARGIN + GRAPH_HEIGHT - 1));
+        osd.set_frame_times(&frame_times);
+        osd.render(&mut frame, WIDTH, HEIGHT, Color::WHITE, Color::BLACK);
+
+        // A 60 Hz frame fills half the graph
+        assert!(is_lit(&frame, MARGIN, MARGIN + GRAPH_HEIGHT - 1));
+        assert!(is_lit(&frame, MARGIN, MARGIN + GRAPH_HEIGHT / 2));
+        assert!(!is_lit(&frame, MARGIN, MARGIN + GRAPH_HEIGHT / 2 - 1));
+    }
+
+    #[test]
+    fn test_render_keypad() {
+        let mut osd = Osd::new();
+        assert!(osd.toggle_keypad());
+        osd.set_keys(&[ChipKey::KeyF]);
+
+        let mut frame = vec![0; WIDTH * HEIGHT * 4];
+        osd.render(&mut frame, WIDTH, HEIGHT, Color::WHITE, Color::BLACK);
+
+        // Held keys are drawn inverted: F's top row is 111, so its box is dark there
+        let f_x = WIDTH - MARGIN - 3;
+        let f_y = HEIGHT - MARGIN - GLYPH_HEIGHT;
+        assert!(!is_lit(&frame, f_x, f_y));
+        assert!(is_lit(&frame, f_x - 1, f_y));
+        // B is released, so it is drawn normally: its top row is 110
+        assert!(is_lit(&frame, f_x - CHAR_ADVANCE, f_y));
+    }
+}
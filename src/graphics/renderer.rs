@@ -3,9 +3,14 @@
 //! This module handles the rendering of the Chip-8 display buffer
 //! to a pixel buffer that can be displayed on screen.
 
+use std::path::Path;
+
+use super::screenshot::{save_rgba, ScreenshotFormat};
+use super::theme::Theme;
 use super::GraphicsResult;
+use crate::emulator::MetricCounter;
 use crate::error::GraphicsError;
-use crate::hardware::{DISPLAY_HEIGHT, DISPLAY_WIDTH};
+use crate::hardware::{check_resolution, ChipKey, DISPLAY_HEIGHT, DISPLAY_WIDTH};
 
 /// Color representation for pixels.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -50,6 +55,256 @@ impl Color {
     }
 }
 
+/// Clockwise rotation of the picture.
+///
+/// Written as degrees in config files: 0, 90, 180 or 270.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(try_from = "u16", into = "u16")]
+pub enum Rotation {
+    /// Upright.
+    #[default]
+    None,
+
+    /// A quarter turn clockwise, for displays mounted on their left side.
+    Clockwise90,
+
+    /// Upside down.
+    Rotate180,
+
+    /// Three quarter turns clockwise, for displays mounted on their right side.
+    Clockwise270,
+}
+
+impl Rotation {
+    /// Gets the rotation in degrees.
+    pub fn degrees(self) -> u16 {
+        match self {
+            Rotation::None => 0,
+            Rotation::Clockwise90 => 90,
+            Rotation::Rotate180 => 180,
+            Rotation::Clockwise270 => 270,
+        }
+    }
+
+    /// Checks if the rotation swaps width and height.
+    pub fn is_sideways(self) -> bool {
+        matches!(self, Rotation::Clockwise90 | Rotation::Clockwise270)
+    }
+}
+
+impl TryFrom<u16> for Rotation {
+    type Error = String;
+
+    fn try_from(degrees: u16) -> Result<Self, Self::Error> {
+        match degrees {
+            0 => Ok(Rotation::None),
+            90 => Ok(Rotation::Clockwise90),
+            180 => Ok(Rotation::Rotate180),
+            270 => Ok(Rotation::Clockwise270),
+            _ => Err(format!(
+                "unsupported rotation {}, expected 0, 90, 180 or 270",
+                degrees
+            )),
+        }
+    }
+}
+
+impl From<Rotation> for u16 {
+    fn from(rotation: Rotation) -> Self {
+        rotation.degrees()
+    }
+}
+
+/// How finished frames are handed to the display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PresentMode {
+    /// Wait for the display's refresh, picking the best mode available.
+    #[default]
+    Vsync,
+
+    /// Don't wait for the refresh, picking the best mode available.
+    NoVsync,
+
+    /// Queue frames and show one per refresh; always supported.
+    Fifo,
+
+    /// Show the newest frame at each refresh, dropping the rest.
+    Mailbox,
+
+    /// Show frames at once, which can tear.
+    Immediate,
+}
+
+impl PresentMode {
+    /// Gets the mode's name in config files.
+    pub fn name(self) -> &'static str {
+        match self {
+            PresentMode::Vsync => "vsync",
+            PresentMode::NoVsync => "no-vsync",
+            PresentMode::Fifo => "fifo",
+            PresentMode::Mailbox => "mailbox",
+            PresentMode::Immediate => "immediate",
+        }
+    }
+
+    /// Checks if frames are only shown at a refresh, so they never tear.
+    pub fn is_synced(self) -> bool {
+        matches!(
+            self,
+            PresentMode::Vsync | PresentMode::Fifo | PresentMode::Mailbox
+        )
+    }
+
+    /// Gets the mode that turns vsync off if it is on, and on if it is off.
+    pub fn toggled(self) -> Self {
+        if self.is_synced() {
+            PresentMode::NoVsync
+        } else {
+            PresentMode::Vsync
+        }
+    }
+}
+
+/// What the GUI draws finished frames to the window with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DisplayBackend {
+    /// GPU surface through `pixels` and wgpu.
+    #[default]
+    Pixels,
+
+    /// CPU-only window surface through `softbuffer` (requires the
+    /// `softbuffer` feature), for machines with broken GPU drivers.
+    Softbuffer,
+}
+
+impl DisplayBackend {
+    /// Gets the backend's name in config files.
+    pub fn name(self) -> &'static str {
+        match self {
+            DisplayBackend::Pixels => "pixels",
+            DisplayBackend::Softbuffer => "softbuffer",
+        }
+    }
+
+    /// Checks if the backend was compiled into this build.
+    pub fn is_available(self) -> bool {
+        match self {
+            DisplayBackend::Pixels => true,
+            DisplayBackend::Softbuffer => cfg!(feature = "softbuffer"),
+        }
+    }
+}
+
+/// Rotation and mirroring applied to a rendered picture.
+///
+/// The picture is rotated first, then mirrored as it appears on screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Orientation {
+    /// Clockwise rotation.
+    pub rotation: Rotation,
+
+    /// Whether left and right are swapped.
+    pub mirror_horizontal: bool,
+
+    /// Whether top and bottom are swapped.
+    pub mirror_vertical: bool,
+}
+
+impl Orientation {
+    /// Checks if the picture is shown as drawn.
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Gets the size of a picture of `size` once oriented.
+    pub fn output_size(&self, size: (usize, usize)) -> (usize, usize) {
+        if self.rotation.is_sideways() {
+            (size.1, size.0)
+        } else {
+            size
+        }
+    }
+
+    /// Maps a position in the oriented picture back to the picture drawn.
+    ///
+    /// # Arguments
+    /// * `position` - Position in the oriented picture
+    /// * `size` - Size of the picture drawn, before orientation
+    pub fn source_position(
+        &self,
+        position: (usize, usize),
+        size: (usize, usize),
+    ) -> (usize, usize) {
+        let (width, height) = size;
+        let (output_width, output_height) = self.output_size(size);
+        let (mut x, mut y) = position;
+        if self.mirror_horizontal {
+            x = output_width.saturating_sub(x + 1);
+        }
+        if self.mirror_vertical {
+            y = output_height.saturating_sub(y + 1);
+        }
+        match self.rotation {
+            Rotation::None => (x, y),
+            Rotation::Clockwise90 => (y, height.saturating_sub(x + 1)),
+            Rotation::Rotate180 => (width.saturating_sub(x + 1), height.saturating_sub(y + 1)),
+            Rotation::Clockwise270 => (width.saturating_sub(y + 1), x),
+        }
+    }
+
+    /// Orients an RGBA picture.
+    ///
+    /// # Arguments
+    /// * `source` - RGBA pixels as drawn
+    /// * `size` - Size of the picture drawn
+    /// * `dest` - RGBA buffer of `output_size(size)` to write into
+    pub fn apply(&self, source: &[u8], size: (usize, usize), dest: &mut [u8]) {
+        let (output_width, _) = self.output_size(size);
+        for (i, pixel) in dest.chunks_exact_mut(4).enumerate() {
+            let (x, y) = self.source_position((i % output_width, i / output_width), size);
+            let index = (y * size.0 + x) * 4;
+            if let Some(color) = source.get(index..index + 4) {
+                pixel.copy_from_slice(color);
+            }
+        }
+    }
+
+    /// Maps a keypad direction as seen on screen to the direction in the
+    /// picture drawn.
+    ///
+    /// Keys 2, 4, 6 and 8 are taken as up, left, right and down; other
+    /// keys are returned unchanged.
+    pub fn source_key(&self, key: ChipKey) -> ChipKey {
+        let (mut dx, mut dy): (i8, i8) = match key {
+            ChipKey::Key2 => (0, -1),
+            ChipKey::Key4 => (-1, 0),
+            ChipKey::Key6 => (1, 0),
+            ChipKey::Key8 => (0, 1),
+            _ => return key,
+        };
+        if self.mirror_horizontal {
+            dx = -dx;
+        }
+        if self.mirror_vertical {
+            dy = -dy;
+        }
+        let (dx, dy) = match self.rotation {
+            Rotation::None => (dx, dy),
+            Rotation::Clockwise90 => (dy, -dx),
+            Rotation::Rotate180 => (-dx, -dy),
+            Rotation::Clockwise270 => (-dy, dx),
+        };
+        match (dx, dy) {
+            (0, -1) => ChipKey::Key2,
+            (-1, 0) => ChipKey::Key4,
+            (1, 0) => ChipKey::Key6,
+            _ => ChipKey::Key8,
+        }
+    }
+}
+
 /// Graphics configuration for rendering.
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct GraphicsConfig {
@@ -67,6 +322,72 @@ pub struct GraphicsConfig {
 
     /// Whether to maintain aspect ratio.
     pub maintain_aspect_ratio: bool,
+
+    /// Whether the window only scales the display by whole numbers.
+    #[serde(default)]
+    pub integer_scaling: bool,
+
+    /// Whether the on-screen display shows speed counters.
+    #[serde(default)]
+    pub show_fps: bool,
+
+    /// Whether the on-screen display shows the keypad.
+    #[serde(default)]
+    pub show_keypad: bool,
+
+    /// Whether the on-screen display shows the latency of the last key press.
+    #[serde(default)]
+    pub show_latency: bool,
+
+    /// Whether the on-screen display graphs recent frame times.
+    #[serde(default)]
+    pub show_frame_times: bool,
+
+    /// How the GUI hands frames to the display.
+    #[serde(default)]
+    pub present_mode: PresentMode,
+
+    /// What draws frames to the window.
+    #[serde(default)]
+    pub backend: DisplayBackend,
+
+    /// Performance counters the on-screen display shows.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub osd_counters: Vec<MetricCounter>,
+
+    /// Clockwise rotation of the picture, for displays mounted sideways or upside down.
+    #[serde(default)]
+    pub rotation: Rotation,
+
+    /// Whether the picture is mirrored left to right.
+    #[serde(default)]
+    pub mirror_horizontal: bool,
+
+    /// Whether the picture is mirrored top to bottom.
+    #[serde(default)]
+    pub mirror_vertical: bool,
+
+    /// Width of a pixel relative to its height, before rotation.
+    ///
+    /// Original hardware drew on TVs with non-square pixels; 1.2 gives a
+    /// picture a fifth wider than the square-pixel one.
+    #[serde(default = "default_pixel_aspect_ratio")]
+    pub pixel_aspect_ratio: f32,
+
+    /// Whether keypad directions 2, 4, 6 and 8 turn with the picture.
+    ///
+    /// Off suits a display mounted in a cabinet, where the player sees the
+    /// picture upright; on suits a turned picture on an upright display.
+    #[serde(default)]
+    pub rotate_input: bool,
+
+    /// Name of the GUI theme, or `None` for the colors above.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<String>,
+
+    /// Themes defined in addition to the presets, replacing any of the same name.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub themes: Vec<Theme>,
 }
 
 impl Default for GraphicsConfig {
@@ -77,10 +398,30 @@ impl Default for GraphicsConfig {
             scale_factor: 10,
             smooth_scaling: false,
             maintain_aspect_ratio: true,
+            integer_scaling: false,
+            show_fps: false,
+            show_keypad: false,
+            show_latency: false,
+            show_frame_times: false,
+            present_mode: PresentMode::Vsync,
+            backend: DisplayBackend::Pixels,
+            osd_counters: Vec::new(),
+            rotation: Rotation::None,
+            mirror_horizontal: false,
+            mirror_vertical: false,
+            pixel_aspect_ratio: default_pixel_aspect_ratio(),
+            rotate_input: false,
+            theme: None,
+            themes: Vec::new(),
         }
     }
 }
 
+/// Square pixels, for configs written before pixel aspect ratios existed.
+fn default_pixel_aspect_ratio() -> f32 {
+    1.0
+}
+
 impl GraphicsConfig {
     /// Creates a new graphics configuration.
     pub fn new() -> Self {
@@ -117,6 +458,74 @@ impl GraphicsConfig {
         self
     }
 
+    /// Sets whether the window only scales the display by whole numbers.
+    pub fn with_integer_scaling(mut self, integer: bool) -> Self {
+        self.integer_scaling = integer;
+        self
+    }
+
+    /// Sets which on-screen display elements are shown.
+    pub fn with_osd(mut self, show_fps: bool, show_keypad: bool) -> Self {
+        self.show_fps = show_fps;
+        self.show_keypad = show_keypad;
+        self
+    }
+
+    /// Sets the rotation.
+    pub fn with_rotation(mut self, rotation: Rotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Sets mirroring left to right and top to bottom.
+    pub fn with_mirroring(mut self, horizontal: bool, vertical: bool) -> Self {
+        self.mirror_horizontal = horizontal;
+        self.mirror_vertical = vertical;
+        self
+    }
+
+    /// Sets the width of a pixel relative to its height.
+    pub fn with_pixel_aspect_ratio(mut self, ratio: f32) -> Self {
+        self.pixel_aspect_ratio = ratio;
+        self
+    }
+
+    /// Gets the width of a pixel relative to its height as shown, after rotation.
+    pub fn shown_pixel_aspect_ratio(&self) -> f32 {
+        if self.rotation.is_sideways() {
+            1.0 / self.pixel_aspect_ratio
+        } else {
+            self.pixel_aspect_ratio
+        }
+    }
+
+    /// Sets whether keypad directions turn with the picture.
+    pub fn with_rotate_input(mut self, rotate: bool) -> Self {
+        self.rotate_input = rotate;
+        self
+    }
+
+    /// Sets the GUI theme by name.
+    pub fn with_theme(mut self, name: impl Into<String>) -> Self {
+        self.theme = Some(name.into());
+        self
+    }
+
+    /// Gets the rotation and mirroring applied to the picture.
+    pub fn orientation(&self) -> Orientation {
+        Orientation {
+            rotation: self.rotation,
+            mirror_horizontal: self.mirror_horizontal,
+            mirror_vertical: self.mirror_vertical,
+        }
+    }
+
+    /// Sets how the GUI hands frames to the display.
+    pub fn with_present_mode(mut self, mode: PresentMode) -> Self {
+        self.present_mode = mode;
+        self
+    }
+
     /// Creates a classic green monochrome configuration.
     pub fn classic_green() -> Self {
         Self::new()
@@ -152,21 +561,27 @@ pub struct PixelRenderer {
 
     /// Height of the frame buffer.
     frame_height: u32,
+
+    /// Width of the display buffers rendered, in Chip-8 pixels.
+    display_width: usize,
+
+    /// Height of the display buffers rendered, in Chip-8 pixels.
+    display_height: usize,
 }
 
 impl PixelRenderer {
-    /// Creates a new pixel renderer.
+    /// Creates a new pixel renderer for the standard 64x32 display.
     pub fn new(config: GraphicsConfig) -> GraphicsResult<Self> {
-        let frame_width = DISPLAY_WIDTH as u32 * config.scale_factor;
-        let frame_height = DISPLAY_HEIGHT as u32 * config.scale_factor;
-        let frame_buffer = vec![0u8; (frame_width * frame_height * 4) as usize];
-
-        Ok(Self {
+        let mut renderer = Self {
             config,
-            frame_buffer,
-            frame_width,
-            frame_height,
-        })
+            frame_buffer: Vec::new(),
+            frame_width: 0,
+            frame_height: 0,
+            display_width: DISPLAY_WIDTH,
+            display_height: DISPLAY_HEIGHT,
+        };
+        renderer.allocate();
+        Ok(renderer)
     }
 
     /// Creates a renderer with default configuration.
@@ -174,43 +589,77 @@ impl PixelRenderer {
         Self::new(GraphicsConfig::default())
     }
 
-    /// Renders a display buffer to the frame buffer.
+    /// Changes the display resolution rendered, reallocating the frame buffer.
+    pub fn resize(&mut self, width: usize, height: usize) -> GraphicsResult<()> {
+        check_resolution(width, height)
+            .map_err(|_| GraphicsError::UnsupportedResolution { width, height })?;
+        self.display_width = width;
+        self.display_height = height;
+        self.allocate();
+        Ok(())
+    }
+
+    /// Gets the display resolution rendered, in Chip-8 pixels.
+    pub fn display_size(&self) -> (usize, usize) {
+        (self.display_width, self.display_height)
+    }
+
+    /// Gets the size of the frame as drawn, before rotation.
+    fn unrotated_frame_size(&self) -> (usize, usize) {
+        let scale = self.config.scale_factor as usize;
+        let width = self.display_width * scale;
+        let stretched = (width as f32 * self.config.pixel_aspect_ratio).round() as usize;
+        let width = if stretched > 0 { stretched } else { width };
+        (width, self.display_height * scale)
+    }
+
+    /// Sizes the frame buffer for the display resolution, scale factor,
+    /// pixel aspect ratio and rotation.
+    fn allocate(&mut self) {
+        let (width, height) = self
+            .config
+            .orientation()
+            .output_size(self.unrotated_frame_size());
+        let (new_width, new_height) = (width as u32, height as u32);
+
+        if new_width != self.frame_width || new_height != self.frame_height {
+            self.frame_width = new_width;
+            self.frame_height = new_height;
+            self.frame_buffer = vec![0u8; (new_width * new_height * 4) as usize];
+        }
+    }
+
+    /// Renders a display buffer of the current resolution to the frame buffer.
     pub fn render(&mut self, display_buffer: &[bool]) -> GraphicsResult<()> {
-        if display_buffer.len() != DISPLAY_WIDTH * DISPLAY_HEIGHT {
+        let expected = self.display_width * self.display_height;
+        if display_buffer.len() != expected {
             return Err(GraphicsError::InvalidBufferSize {
-                expected: DISPLAY_WIDTH * DISPLAY_HEIGHT,
+                expected,
                 actual: display_buffer.len(),
             });
         }
 
         let fg_color = self.config.foreground_color.to_rgba();
         let bg_color = self.config.background_color.to_rgba();
-        let scale = self.config.scale_factor as usize;
-
-        // Clear the frame buffer
-        self.frame_buffer.fill(0);
-
-        for (i, &pixel_on) in display_buffer.iter().enumerate() {
-            let src_x = i % DISPLAY_WIDTH;
-            let src_y = i / DISPLAY_WIDTH;
-
-            let color = if pixel_on { fg_color } else { bg_color };
-
-            // Scale the pixel
-            for dy in 0..scale {
-                for dx in 0..scale {
-                    let dst_x = src_x * scale + dx;
-                    let dst_y = src_y * scale + dy;
-
-                    if dst_x < self.frame_width as usize && dst_y < self.frame_height as usize {
-                        let dst_index = (dst_y * self.frame_width as usize + dst_x) * 4;
-
-                        if dst_index + 3 < self.frame_buffer.len() {
-                            self.frame_buffer[dst_index..dst_index + 4].copy_from_slice(&color);
-                        }
-                    }
-                }
-            }
+        let orientation = self.config.orientation();
+        let (source_width, source_height) = self.unrotated_frame_size();
+        let frame_width = self.frame_width as usize;
+
+        // Each frame pixel looks up the display pixel it shows, which
+        // scales, stretches, rotates and mirrors in one pass
+        for (i, pixel) in self.frame_buffer.chunks_exact_mut(4).enumerate() {
+            let (x, y) = orientation.source_position(
+                (i % frame_width, i / frame_width),
+                (source_width, source_height),
+            );
+            let src_x = x * self.display_width / source_width;
+            let src_y = y * self.display_height / source_height;
+            let pixel_on = display_buffer
+                .get(src_y * self.display_width + src_x)
+                .copied()
+                .unwrap_or(false);
+
+            pixel.copy_from_slice(if pixel_on { &fg_color } else { &bg_color });
         }
 
         Ok(())
@@ -239,19 +688,21 @@ impl PixelRenderer {
         (self.frame_width, self.frame_height)
     }
 
+    /// Saves the last rendered frame as an image.
+    pub fn save_frame(&self, path: &Path, format: ScreenshotFormat) -> GraphicsResult<()> {
+        save_rgba(
+            path,
+            self.frame_width,
+            self.frame_height,
+            &self.frame_buffer,
+            format,
+        )
+    }
+
     /// Updates the graphics configuration.
     pub fn set_config(&mut self, config: GraphicsConfig) -> GraphicsResult<()> {
-        // Check if we need to resize the frame buffer
-        let new_width = DISPLAY_WIDTH as u32 * config.scale_factor;
-        let new_height = DISPLAY_HEIGHT as u32 * config.scale_factor;
-
-        if new_width != self.frame_width || new_height != self.frame_height {
-            self.frame_width = new_width;
-            self.frame_height = new_height;
-            self.frame_buffer = vec![0u8; (new_width * new_height * 4) as usize];
-        }
-
         self.config = config;
+        self.allocate();
         Ok(())
     }
 
@@ -290,6 +741,11 @@ impl SoftwareRenderer {
     pub fn set_config(&mut self, config: GraphicsConfig) -> GraphicsResult<()> {
         self.renderer.set_config(config)
     }
+
+    /// Changes the display resolution rendered.
+    pub fn resize(&mut self, width: usize, height: usize) -> GraphicsResult<()> {
+        self.renderer.resize(width, height)
+    }
 }
 
 #[cfg(test)]
@@ -415,4 +871,128 @@ mod tests {
         assert!(result.is_ok());
         assert_eq!(renderer.frame_size(), (256, 128)); // 64*4, 32*4
     }
+
+    #[test]
+    fn test_present_mode() {
+        assert_eq!(PresentMode::default().toggled(), PresentMode::NoVsync);
+        assert_eq!(PresentMode::Mailbox.toggled(), PresentMode::NoVsync);
+        assert_eq!(PresentMode::Immediate.toggled(), PresentMode::Vsync);
+
+        let config: GraphicsConfig =
+            toml::from_str(&toml::to_string(&GraphicsConfig::new()).unwrap()).unwrap();
+        assert_eq!(config.present_mode, PresentMode::Vsync);
+        let config = GraphicsConfig::new().with_present_mode(PresentMode::NoVsync);
+        assert!(toml::to_string(&config)
+            .unwrap()
+            .contains("present_mode = \"no-vsync\""));
+    }
+
+    #[test]
+    fn test_orientation() {
+        let size = (4, 2);
+        let sideways = Orientation {
+            rotation: Rotation::Clockwise90,
+            ..Default::default()
+        };
+        assert_eq!(sideways.output_size(size), (2, 4));
+        // The bottom-left corner turns to the top-left
+        assert_eq!(sideways.source_position((0, 0), size), (0, 1));
+        assert_eq!(sideways.source_position((1, 3), size), (3, 0));
+
+        let mirrored = Orientation {
+            rotation: Rotation::Rotate180,
+            mirror_horizontal: true,
+            ..Default::default()
+        };
+        // Upside down and mirrored left to right is a vertical flip
+        assert_eq!(mirrored.source_position((0, 0), size), (0, 1));
+
+        // Pressing up on a clockwise picture moves left in the game
+        assert_eq!(sideways.source_key(ChipKey::Key2), ChipKey::Key4);
+        assert_eq!(sideways.source_key(ChipKey::Key6), ChipKey::Key2);
+        assert_eq!(mirrored.source_key(ChipKey::Key2), ChipKey::Key8);
+        assert_eq!(mirrored.source_key(ChipKey::Key6), ChipKey::Key6);
+        assert_eq!(sideways.source_key(ChipKey::Key5), ChipKey::Key5);
+
+        let config: GraphicsConfig = toml::from_str(
+            "foreground_color = { r = 255, g = 255, b = 255, a = 255 }\n\
+             background_color = { r = 0, g = 0, b = 0, a = 255 }\n\
+             scale_factor = 1\nsmooth_scaling = false\nmaintain_aspect_ratio = true\n\
+             rotation = 270\nmirror_vertical = true",
+        )
+        .unwrap();
+        assert_eq!(config.rotation, Rotation::Clockwise270);
+        assert!(config.mirror_vertical && !config.rotate_input);
+        assert!(toml::from_str::<GraphicsConfig>("rotation = 45").is_err());
+    }
+
+    #[test]
+    fn test_pixel_renderer_rotation() {
+        let config = GraphicsConfig::new()
+            .with_scale_factor(2)
+            .with_rotation(Rotation::Clockwise90);
+        let mut renderer = PixelRenderer::new(config).unwrap();
+        assert_eq!(renderer.frame_size(), (64, 128));
+
+        // The top-left pixel turns to the top-right corner
+        let mut display_buffer = vec![false; DISPLAY_WIDTH * DISPLAY_HEIGHT];
+        display_buffer[0] = true;
+        renderer.render(&display_buffer).unwrap();
+        let frame = renderer.frame_buffer();
+        let top_right = (64 - 1) * 4;
+        assert_eq!(&frame[top_right..top_right + 4], &Color::WHITE.to_rgba());
+        assert_eq!(&frame[..4], &Color::BLACK.to_rgba());
+    }
+
+    #[test]
+    fn test_pixel_aspect_ratio() {
+        let config = GraphicsConfig::new()
+            .with_scale_factor(5)
+            .with_pixel_aspect_ratio(1.2);
+        let mut renderer = PixelRenderer::new(config.clone()).unwrap();
+        assert_eq!(renderer.frame_size(), (384, 160));
+
+        // The last column is stretched to six frame pixels
+        let mut display_buffer = vec![false; DISPLAY_WIDTH * DISPLAY_HEIGHT];
+        display_buffer[DISPLAY_WIDTH - 1] = true;
+        renderer.render(&display_buffer).unwrap();
+        let lit: Vec<bool> = renderer.frame_buffer()[..384 * 4]
+            .chunks_exact(4)
+            .map(|pixel| pixel[0] > 0)
+            .collect();
+        assert_eq!(lit.iter().filter(|&&on| on).count(), 6);
+        assert!(lit[378..].iter().all(|&on| on));
+
+        // Turned sideways, the stretch runs down the frame
+        let sideways = config.with_rotation(Rotation::Clockwise90);
+        assert!((sideways.shown_pixel_aspect_ratio() - 1.0 / 1.2).abs() < 1e-6);
+        renderer.set_config(sideways).unwrap();
+        assert_eq!(renderer.frame_size(), (160, 384));
+    }
+
+    #[test]
+    fn test_pixel_renderer_resize() {
+        let mut renderer = PixelRenderer::new(GraphicsConfig::new().with_scale_factor(2)).unwrap();
+        renderer.resize(128, 64).unwrap();
+        assert_eq!(renderer.display_size(), (128, 64));
+        assert_eq!(renderer.frame_size(), (256, 128));
+
+        // The bottom-right pixel lands in the bottom-right corner
+        let mut display_buffer = vec![false; 128 * 64];
+        display_buffer[128 * 64 - 1] = true;
+        renderer.render(&display_buffer).unwrap();
+        let frame = renderer.frame_buffer();
+        assert_eq!(&frame[frame.len() - 4..], &Color::WHITE.to_rgba());
+
+        // Buffers of the old resolution are rejected
+        assert!(renderer
+            .render(&[false; DISPLAY_WIDTH * DISPLAY_HEIGHT])
+            .is_err());
+        assert!(renderer.resize(0, 64).is_err());
+
+        renderer
+            .set_config(GraphicsConfig::new().with_scale_factor(3))
+            .unwrap();
+        assert_eq!(renderer.frame_size(), (384, 192));
+    }
 }
@@ -0,0 +1,161 @@
+//! Screenshot encoding.
+//!
+//! Rendered RGBA frames can be written as PNG (with the `png` feature) or
+//! as binary PPM, which needs no encoder and is always available.
+
+use std::path::Path;
+use std::str::FromStr;
+
+use super::GraphicsResult;
+use crate::error::GraphicsError;
+
+/// Image file format for screenshots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScreenshotFormat {
+    /// Portable Network Graphics.
+    Png,
+
+    /// Binary portable pixmap (P6).
+    Ppm,
+}
+
+impl Default for ScreenshotFormat {
+    /// PNG when it is compiled in, otherwise PPM.
+    fn default() -> Self {
+        if cfg!(feature = "png") {
+            ScreenshotFormat::Png
+        } else {
+            ScreenshotFormat::Ppm
+        }
+    }
+}
+
+impl ScreenshotFormat {
+    /// Picks a format from a file extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?;
+        extension.parse().ok()
+    }
+
+    /// Gets the usual file extension.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ScreenshotFormat::Png => "png",
+            ScreenshotFormat::Ppm => "ppm",
+        }
+    }
+}
+
+impl FromStr for ScreenshotFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "png" => Ok(ScreenshotFormat::Png),
+            "ppm" => Ok(ScreenshotFormat::Ppm),
+            other => Err(format!("unknown screenshot format '{}'", other)),
+        }
+    }
+}
+
+/// Encodes an RGBA frame as binary PPM, dropping the alpha channel.
+pub fn encode_ppm(width: u32, height: u32, rgba: &[u8]) -> Vec<u8> {
+    let mut out = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+    out.reserve(rgba.len() / 4 * 3);
+    for pixel in rgba.chunks_exact(4) {
+        out.extend_from_slice(&pixel[..3]);
+    }
+    out
+}
+
+/// Writes an RGBA frame to a file.
+///
+/// # Arguments
+/// * `path` - Output file
+/// * `width` - Frame width in pixels
+/// * `height` - Frame height in pixels
+/// * `rgba` - Pixel data, four bytes per pixel
+/// * `format` - File format to write
+pub fn save_rgba(
+    path: &Path,
+    width: u32,
+    height: u32,
+    rgba: &[u8],
+    format: ScreenshotFormat,
+) -> GraphicsResult<()> {
+    let expected = (width * height * 4) as usize;
+    if rgba.len() != expected {
+        return Err(GraphicsError::InvalidBufferSize {
+            expected,
+            actual: rgba.len(),
+        });
+    }
+
+    match format {
+        ScreenshotFormat::Ppm => std::fs::write(path, encode_ppm(width, height, rgba))
+            .map_err(|e| GraphicsError::ScreenshotFailed(e.to_string())),
+        #[cfg(feature = "png")]
+        ScreenshotFormat::Png => image::save_buffer_with_format(
+            path,
+            rgba,
+            width,
+            height,
+            image::ColorType::Rgba8,
+            image::ImageFormat::Png,
+        )
+        .map_err(|e| GraphicsError::ScreenshotFailed(e.to_string())),
+        #[cfg(not(feature = "png"))]
+        ScreenshotFormat::Png => Err(GraphicsError::ScreenshotFailed(
+            "PNG support is not compiled in (enable the `png` feature)".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(
+            ScreenshotFormat::from_path(Path::new("shot.PNG")),
+            Some(ScreenshotFormat::Png)
+        );
+        assert_eq!(
+            ScreenshotFormat::from_path(Path::new("out/shot.ppm")),
+            Some(ScreenshotFormat::Ppm)
+        );
+        assert_eq!(ScreenshotFormat::from_path(Path::new("shot.bmp")), None);
+        assert_eq!(ScreenshotFormat::from_path(Path::new("shot")), None);
+    }
+
+    #[test]
+    fn test_encode_ppm() {
+        let rgba = [255, 0, 0, 255, 0, 0, 255, 255];
+        let ppm = encode_ppm(2, 1, &rgba);
+        assert_eq!(ppm, b"P6\n2 1\n255\n\xFF\x00\x00\x00\x00\xFF".to_vec());
+    }
+
+    #[test]
+    fn test_save_rgba() {
+        let dir = tempfile::tempdir().unwrap();
+        let rgba = vec![0x80; 4 * 4 * 4];
+
+        let ppm = dir.path().join("frame.ppm");
+        save_rgba(&ppm, 4, 4, &rgba, ScreenshotFormat::Ppm).unwrap();
+        assert_eq!(std::fs::read(&ppm).unwrap().len(), 11 + 4 * 4 * 3);
+
+        let result = save_rgba(&ppm, 5, 4, &rgba, ScreenshotFormat::Ppm);
+        assert!(matches!(
+            result,
+            Err(GraphicsError::InvalidBufferSize { .. })
+        ));
+
+        #[cfg(feature = "png")]
+        {
+            let png = dir.path().join("frame.png");
+            save_rgba(&png, 4, 4, &rgba, ScreenshotFormat::Png).unwrap();
+            assert!(std::fs::read(&png).unwrap().starts_with(b"\x89PNG"));
+        }
+    }
+}
@@ -0,0 +1,318 @@
+//! Visual themes for the GUI.
+//!
+//! A [`Theme`] bundles the pixel colors with the color of the border around
+//! the picture and two CRT effects: scanlines darken the bottom of each row
+//! of pixels, and ghosting lets pixels that turn off fade out over a few
+//! frames instead of vanishing, which also hides the flicker of games that
+//! redraw sprites every frame.
+//!
+//! [`ThemeManager`] holds the built-in presets, the themes defined in the
+//! config and a theme made from the configured colors, and steps through
+//! them for the GUI's theme hotkeys. [`Phosphor`] tracks how brightly each
+//! pixel still glows.
+
+use serde::{Deserialize, Serialize};
+
+use super::renderer::{Color, GraphicsConfig};
+use crate::logging::GFX;
+
+/// Name of the theme made from `foreground_color` and `background_color`.
+pub const CONFIG_THEME: &str = "config";
+
+/// Colors and effects of a visual theme.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Theme {
+    /// Name used in config files and shown when switching.
+    pub name: String,
+
+    /// Color for "on" pixels.
+    pub foreground: Color,
+
+    /// Color for "off" pixels.
+    pub background: Color,
+
+    /// Color around the picture.
+    pub border: Color,
+
+    /// How much scanlines darken, from 0.0 (none) to 1.0 (black).
+    #[serde(default)]
+    pub scanlines: f32,
+
+    /// Brightness a pixel keeps each frame after turning off, from 0.0
+    /// (none) to below 1.0 (a long trail).
+    #[serde(default)]
+    pub ghosting: f32,
+}
+
+impl Theme {
+    /// Creates a theme with a border of the background color and no effects.
+    pub fn new(name: impl Into<String>, foreground: Color, background: Color) -> Self {
+        Self {
+            name: name.into(),
+            foreground,
+            background,
+            border: background,
+            scanlines: 0.0,
+            ghosting: 0.0,
+        }
+    }
+
+    /// Sets the border color.
+    pub fn with_border(mut self, border: Color) -> Self {
+        self.border = border;
+        self
+    }
+
+    /// Sets how much scanlines darken.
+    pub fn with_scanlines(mut self, scanlines: f32) -> Self {
+        self.scanlines = scanlines;
+        self
+    }
+
+    /// Sets the brightness pixels keep each frame after turning off.
+    pub fn with_ghosting(mut self, ghosting: f32) -> Self {
+        self.ghosting = ghosting;
+        self
+    }
+
+    /// The built-in themes.
+    pub fn presets() -> Vec<Theme> {
+        vec![
+            Theme::new("classic", Color::WHITE, Color::BLACK),
+            Theme::new(
+                "green-phosphor",
+                Color::rgb(51, 255, 102),
+                Color::rgb(0, 20, 0),
+            )
+            .with_border(Color::rgb(10, 10, 10))
+            .with_scanlines(0.35)
+            .with_ghosting(0.6),
+            Theme::new("amber", Color::AMBER, Color::rgb(24, 12, 0))
+                .with_border(Color::rgb(10, 10, 10))
+                .with_scanlines(0.35)
+                .with_ghosting(0.5),
+            Theme::new("lcd", Color::rgb(15, 56, 15), Color::rgb(155, 188, 15))
+                .with_border(Color::rgb(139, 172, 15))
+                .with_ghosting(0.4),
+            Theme::new("paper", Color::rgb(40, 40, 40), Color::rgb(240, 234, 214))
+                .with_border(Color::rgb(200, 194, 176)),
+            Theme::new("cosmac", Color::WHITE, Color::rgb(16, 16, 24))
+                .with_border(Color::rgb(60, 60, 60))
+                .with_scanlines(0.5)
+                .with_ghosting(0.3),
+        ]
+    }
+
+    /// Gets the pixel colors as (foreground, background).
+    pub fn colors(&self) -> (Color, Color) {
+        (self.foreground, self.background)
+    }
+
+    /// Checks the effect settings are in range.
+    pub fn is_valid(&self) -> bool {
+        !self.name.is_empty()
+            && (0.0..=1.0).contains(&self.scanlines)
+            && (0.0..1.0).contains(&self.ghosting)
+    }
+
+    /// Gets the color of a pixel glowing at `level`, from off (0) to on (255).
+    pub fn shade(&self, level: u8) -> [u8; 4] {
+        let mix = |off: u8, on: u8| {
+            ((off as u32 * (255 - level as u32) + on as u32 * level as u32 + 127) / 255) as u8
+        };
+        let (off, on) = (self.background, self.foreground);
+        [
+            mix(off.r, on.r),
+            mix(off.g, on.g),
+            mix(off.b, on.b),
+            mix(off.a, on.a),
+        ]
+    }
+
+    /// Darkens a color for a scanline.
+    pub fn scanline(&self, color: [u8; 4]) -> [u8; 4] {
+        let keep = 1.0 - self.scanlines.clamp(0.0, 1.0);
+        let dim = |channel: u8| (channel as f32 * keep).round() as u8;
+        [dim(color[0]), dim(color[1]), dim(color[2]), color[3]]
+    }
+}
+
+/// The themes available and the one in use.
+#[derive(Debug, Clone)]
+pub struct ThemeManager {
+    /// The config theme, then presets, then config-defined themes.
+    themes: Vec<Theme>,
+
+    /// Index of the theme in use.
+    current: usize,
+}
+
+impl ThemeManager {
+    /// Collects the themes a graphics config offers and selects its `theme`.
+    ///
+    /// Themes defined in the config replace presets of the same name. An
+    /// unknown `theme` falls back to the configured colors.
+    pub fn new(config: &GraphicsConfig) -> Self {
+        let mut themes = vec![Theme::new(
+            CONFIG_THEME,
+            config.foreground_color,
+            config.background_color,
+        )];
+        for theme in Theme::presets().into_iter().chain(config.themes.clone()) {
+            match themes.iter_mut().find(|known| known.name == theme.name) {
+                Some(known) => *known = theme,
+                None => themes.push(theme),
+            }
+        }
+
+        let mut manager = Self { themes, current: 0 };
+        if let Some(name) = &config.theme {
+            if !manager.select(name) {
+                tracing::warn!(
+                    target: GFX,
+                    "Unknown theme {:?}, using the configured colors",
+                    name
+                );
+            }
+        }
+        manager
+    }
+
+    /// Gets every theme, in carousel order.
+    pub fn themes(&self) -> &[Theme] {
+        &self.themes
+    }
+
+    /// Gets the theme in use.
+    pub fn current(&self) -> &Theme {
+        &self.themes[self.current]
+    }
+
+    /// Gets the name to save as the config's `theme`, or `None` for the
+    /// configured colors.
+    pub fn selected(&self) -> Option<&str> {
+        let name = self.current().name.as_str();
+        (name != CONFIG_THEME).then_some(name)
+    }
+
+    /// Switches to the theme called `name`.
+    ///
+    /// # Returns
+    /// False, leaving the theme unchanged, if there is no such theme.
+    pub fn select(&mut self, name: &str) -> bool {
+        match self.themes.iter().position(|theme| theme.name == name) {
+            Some(index) => {
+                self.current = index;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Switches to the next theme, wrapping around.
+    pub fn select_next(&mut self) -> &Theme {
+        self.current = (self.current + 1) % self.themes.len();
+        self.current()
+    }
+
+    /// Switches to the previous theme, wrapping around.
+    pub fn select_previous(&mut self) -> &Theme {
+        self.current = (self.current + self.themes.len() - 1) % self.themes.len();
+        self.current()
+    }
+
+    /// Updates the config theme's colors, after a config reload.
+    pub fn set_palette(&mut self, (foreground, background): (Color, Color)) {
+        let theme = &mut self.themes[0];
+        theme.foreground = foreground;
+        theme.background = background;
+        theme.border = background;
+    }
+}
+
+/// How brightly each pixel glows, for ghosting.
+#[derive(Debug, Clone, Default)]
+pub struct Phosphor {
+    /// Glow of each pixel, from off (0) to on (255).
+    levels: Vec<u8>,
+}
+
+impl Phosphor {
+    /// Creates a phosphor with every pixel dark.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lights the pixels that are on and fades the rest.
+    ///
+    /// # Arguments
+    /// * `frame` - Pixels on this frame; a new size starts from dark
+    /// * `ghosting` - Brightness kept by pixels that are off
+    ///
+    /// # Returns
+    /// The glow of each pixel.
+    pub fn update(&mut self, frame: &[bool], ghosting: f32) -> &[u8] {
+        if self.levels.len() != frame.len() {
+            self.levels = vec![0; frame.len()];
+        }
+        let keep = ghosting.clamp(0.0, 1.0);
+        for (level, &on) in self.levels.iter_mut().zip(frame) {
+            *level = if on {
+                u8::MAX
+            } else {
+                (*level as f32 * keep) as u8
+            };
+        }
+        &self.levels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_manager() {
+        let config = GraphicsConfig {
+            themes: vec![Theme::new("amber", Color::GREEN, Color::BLACK)],
+            theme: Some("amber".to_string()),
+            ..GraphicsConfig::classic_green()
+        };
+        let mut themes = ThemeManager::new(&config);
+
+        // A config theme replaces the preset of the same name
+        assert_eq!(themes.selected(), Some("amber"));
+        assert_eq!(themes.current().foreground, Color::GREEN);
+        assert_eq!(themes.themes().len(), Theme::presets().len() + 1);
+
+        // The carousel wraps around to the configured colors
+        themes.select("cosmac");
+        assert_eq!(themes.select_next().name, CONFIG_THEME);
+        assert_eq!(themes.selected(), None);
+        assert_eq!(themes.current().foreground, Color::GREEN);
+        assert_eq!(themes.select_previous().name, "cosmac");
+        assert!(!themes.select("missing"));
+
+        let unknown = GraphicsConfig {
+            theme: Some("missing".to_string()),
+            ..GraphicsConfig::default()
+        };
+        assert_eq!(ThemeManager::new(&unknown).selected(), None);
+        assert!(Theme::presets().iter().all(Theme::is_valid));
+    }
+
+    #[test]
+    fn test_ghosting_and_shading() {
+        let mut phosphor = Phosphor::new();
+        assert_eq!(phosphor.update(&[true, false], 0.5), &[255, 0]);
+        assert_eq!(phosphor.update(&[false, false], 0.5), &[127, 0]);
+        assert_eq!(phosphor.update(&[false, true], 0.5), &[63, 255]);
+        assert_eq!(phosphor.update(&[false, false], 0.0), &[0, 0]);
+
+        let theme = Theme::new("test", Color::WHITE, Color::BLACK).with_scanlines(0.5);
+        assert_eq!(theme.shade(0), [0, 0, 0, 255]);
+        assert_eq!(theme.shade(255), [255, 255, 255, 255]);
+        assert_eq!(theme.shade(51), [51, 51, 51, 255]);
+        assert_eq!(theme.scanline([200, 100, 0, 255]), [100, 50, 0, 255]);
+    }
+}
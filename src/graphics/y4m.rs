@@ -0,0 +1,142 @@
+//! YUV4MPEG2 (Y4M) video encoding.
+//!
+//! Y4M is uncompressed video with a one-line text header, which encoders
+//! such as ffmpeg read from a file or a pipe. Frames are written as 4:4:4
+//! with BT.601 limited-range colors, so the screen's colors survive without
+//! chroma subsampling smearing single pixels.
+
+use std::io::{self, Write};
+
+/// Frames per second of dumped video, one per 60Hz timer tick.
+pub const Y4M_FRAME_RATE: u32 = 60;
+
+/// Writes RGBA frames as a Y4M stream.
+pub struct Y4mWriter<W: Write> {
+    writer: W,
+    width: usize,
+    height: usize,
+    frames: u64,
+    planes: Vec<u8>,
+}
+
+impl<W: Write> Y4mWriter<W> {
+    /// Starts a stream of `width` x `height` frames at [`Y4M_FRAME_RATE`].
+    pub fn new(mut writer: W, width: usize, height: usize) -> io::Result<Self> {
+        writeln!(
+            writer,
+            "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444",
+            width, height, Y4M_FRAME_RATE
+        )?;
+        Ok(Self {
+            writer,
+            width,
+            height,
+            frames: 0,
+            planes: vec![0; width * height * 3],
+        })
+    }
+
+    /// Gets the frame size as (width, height) in pixels.
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Gets the number of frames written.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Writes a frame of RGBA pixels, four bytes per pixel.
+    pub fn write_frame(&mut self, rgba: &[u8]) -> io::Result<()> {
+        let pixels = self.width * self.height;
+        if rgba.len() != pixels * 4 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "frame has {} bytes, expected {} for {}x{}",
+                    rgba.len(),
+                    pixels * 4,
+                    self.width,
+                    self.height
+                ),
+            ));
+        }
+        let (luma, chroma) = self.planes.split_at_mut(pixels);
+        let (cb, cr) = chroma.split_at_mut(pixels);
+        for (i, pixel) in rgba.chunks_exact(4).enumerate() {
+            (luma[i], cb[i], cr[i]) = rgb_to_ycbcr(pixel[0], pixel[1], pixel[2]);
+        }
+        self.writer.write_all(b"FRAME\n")?;
+        self.writer.write_all(&self.planes)?;
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Flushes the stream and gives back the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Converts a color to BT.601 limited-range Y'CbCr.
+pub fn rgb_to_ycbcr(r: u8, g: u8, b: u8) -> (u8, u8, u8) {
+    let (r, g, b) = (r as i32, g as i32, b as i32);
+    let y = ((66 * r + 129 * g + 25 * b + 128) >> 8) + 16;
+    let cb = ((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128;
+    let cr = ((112 * r - 94 * g - 18 * b + 128) >> 8) + 128;
+    (y as u8, cb as u8, cr as u8)
+}
+
+/// Scales an RGBA frame to another size by picking the nearest pixel.
+///
+/// # Arguments
+/// * `rgba` - Source pixels, four bytes per pixel
+/// * `size` - Source size as (width, height)
+/// * `target` - Size to scale to
+pub fn scale_nearest(rgba: &[u8], size: (usize, usize), target: (usize, usize)) -> Vec<u8> {
+    let (width, height) = size;
+    let mut out = Vec::with_capacity(target.0 * target.1 * 4);
+    for y in 0..target.1 {
+        let row = y * height / target.1.max(1) * width;
+        for x in 0..target.0 {
+            let i = (row + x * width / target.0.max(1)) * 4;
+            out.extend_from_slice(&rgba[i..i + 4]);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_y4m_stream() {
+        let mut writer = Y4mWriter::new(Vec::new(), 2, 1).unwrap();
+        writer
+            .write_frame(&[255, 255, 255, 255, 0, 0, 0, 255])
+            .unwrap();
+        assert!(writer.write_frame(&[0; 4]).is_err());
+        assert_eq!(writer.frames(), 1);
+
+        let stream = writer.finish().unwrap();
+        let header = b"YUV4MPEG2 W2 H1 F60:1 Ip A1:1 C444\nFRAME\n";
+        assert_eq!(&stream[..header.len()], header);
+        // Y, Cb and Cr planes: white then black
+        assert_eq!(&stream[header.len()..], [235, 16, 128, 128, 128, 128]);
+    }
+
+    #[test]
+    fn test_scale_nearest() {
+        // A 2x1 frame, red then blue
+        let frame = [255, 0, 0, 255, 0, 0, 255, 255];
+        let scaled = scale_nearest(&frame, (2, 1), (4, 2));
+        assert_eq!(scaled.len(), 4 * 2 * 4);
+        let reds = scaled.chunks(4).filter(|pixel| pixel[0] == 255).count();
+        assert_eq!(reds, 4);
+        assert_eq!(&scaled[4..8], &frame[..4]);
+        assert_eq!(&scaled[8..12], &frame[4..]);
+        assert_eq!(rgb_to_ycbcr(255, 0, 0), (82, 90, 240));
+    }
+}
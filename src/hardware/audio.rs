@@ -4,6 +4,7 @@
 //! for the Chip-8 buzzer sound system.
 
 use crate::error::AudioError;
+use alloc::vec::Vec;
 
 /// Audio-specific result type.
 pub type AudioResult<T> = Result<T, AudioError>;
@@ -33,6 +34,56 @@ pub trait Audio {
 
     /// Gets the current frequency.
     fn get_frequency(&self) -> f32;
+
+    /// Reports the current sound timer value.
+    ///
+    /// Implementations can use this to shape the tone (e.g. pitch derived
+    /// from the timer). The default implementation ignores it.
+    fn set_sound_timer(&mut self, _value: u8) -> AudioResult<()> {
+        Ok(())
+    }
+
+    /// Gets the number of output buffers that ran out of samples.
+    ///
+    /// Implementations without an output stream never underrun.
+    fn underruns(&self) -> u64 {
+        0
+    }
+
+    /// Sets the master volume applied on top of the buzzer volume.
+    ///
+    /// Implementations without a mixer ignore it.
+    fn set_master_volume(&mut self, _volume: f32) -> AudioResult<()> {
+        Ok(())
+    }
+
+    /// Gets the master volume.
+    fn master_volume(&self) -> f32 {
+        1.0
+    }
+
+    /// Mutes or unmutes the output.
+    fn set_muted(&mut self, _muted: bool) -> AudioResult<()> {
+        Ok(())
+    }
+
+    /// Checks if the output is muted.
+    fn is_muted(&self) -> bool {
+        false
+    }
+
+    /// Tells the output that emulation paused or resumed, so it can fade.
+    fn set_paused(&mut self, _paused: bool) -> AudioResult<()> {
+        Ok(())
+    }
+
+    /// Gets the most recent output samples, oldest first, for waveform
+    /// previews.
+    ///
+    /// Implementations that don't render samples have none.
+    fn recent_samples(&self) -> Vec<f32> {
+        Vec::new()
+    }
 }
 
 /// A software audio implementation that tracks state without actual audio output.
@@ -218,6 +269,9 @@ impl AudioConfig {
 }
 
 /// Tone generator for creating audio waveforms.
+///
+/// Needs `std` for `f32::sin`.
+#[cfg(feature = "std")]
 pub struct ToneGenerator {
     /// Current frequency in Hz.
     frequency: f32,
@@ -232,6 +286,7 @@ pub struct ToneGenerator {
     volume: f32,
 }
 
+#[cfg(feature = "std")]
 impl ToneGenerator {
     /// Creates a new tone generator.
     pub fn new(frequency: f32, sample_rate: f32, volume: f32) -> Self {
@@ -347,6 +402,7 @@ mod tests {
         assert_eq!(config.buffer_size, 512);
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn test_tone_generator() {
         let mut generator = ToneGenerator::new(440.0, 44100.0, 0.5);
@@ -363,6 +419,7 @@ mod tests {
         assert!(sample2.abs() <= 0.5);
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn test_tone_generator_frequency_change() {
         let mut generator = ToneGenerator::new(440.0, 44100.0, 0.5);
@@ -377,6 +434,7 @@ mod tests {
         assert!(sample_before != sample_after || generator.phase == 0.0);
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn test_tone_generator_buffer_fill() {
         let mut generator = ToneGenerator::new(440.0, 44100.0, 0.3);
@@ -1,9 +1,19 @@
 //! Display interface for the Chip-8 emulator.
 //!
 //! This module defines the display abstraction and provides implementations
-//! for the 64x32 monochrome Chip-8 display system.
+//! for the 64x32 monochrome Chip-8 display system. Software displays can be
+//! resized for the hi-res variants: 64x64 two-page and 64x128 four-page
+//! CHIP-8.
 
+use crate::emulator::snapshot::{hex_bytes, pack_pixels, unpack_pixels};
 use crate::error::EmulatorError;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "std")]
+use std::sync::{Arc, PoisonError, RwLock};
 use thiserror::Error;
 
 /// Display width in pixels.
@@ -15,6 +25,12 @@ pub const DISPLAY_HEIGHT: usize = 32;
 /// Total number of pixels.
 pub const DISPLAY_PIXELS: usize = DISPLAY_WIDTH * DISPLAY_HEIGHT;
 
+/// Largest display width a display can be resized to.
+pub const MAX_DISPLAY_WIDTH: usize = 256;
+
+/// Largest display height a display can be resized to.
+pub const MAX_DISPLAY_HEIGHT: usize = 256;
+
 /// Display-specific error types.
 #[derive(Error, Debug)]
 pub enum DisplayError {
@@ -29,11 +45,67 @@ pub enum DisplayError {
 
     #[error("Invalid sprite data")]
     InvalidSpriteData,
+
+    #[error("Unsupported display resolution: {width}x{height}")]
+    UnsupportedResolution { width: usize, height: usize },
+
+    #[error("Invalid display snapshot: {0}")]
+    InvalidSnapshot(String),
 }
 
 /// Result type for display operations.
 pub type DisplayResult<T> = Result<T, DisplayError>;
 
+/// What happens to sprite pixels that run past the edge of the screen.
+///
+/// The start position always wraps; this only decides the rest of the sprite.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClipMode {
+    /// Pixels past the edge are dropped (original COSMAC VIP behavior).
+    Clip,
+
+    /// Pixels past the edge wrap around to the other side.
+    #[default]
+    Wrap,
+}
+
+/// Screen resolution of the HP48-style hi-res CHIP-8 variants.
+///
+/// Two-page hi-res programs start with `1260` and draw on a 64x64 screen;
+/// the rarer four-page variant uses 64x128.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HiresMode {
+    /// Always the standard 64x32 screen.
+    Off,
+
+    /// 64x64 if the program starts with `1260`, else 64x32.
+    #[default]
+    Auto,
+
+    /// Always the 64x64 two-page screen.
+    #[serde(rename = "64x64")]
+    TwoPage,
+
+    /// Always the 64x128 four-page screen.
+    #[serde(rename = "64x128")]
+    FourPage,
+}
+
+impl HiresMode {
+    /// Gets the resolution for a program, given its first instruction.
+    pub fn resolution(self, first_instruction: u16) -> (usize, usize) {
+        match self {
+            HiresMode::Off => (DISPLAY_WIDTH, DISPLAY_HEIGHT),
+            HiresMode::Auto if first_instruction == 0x1260 => (64, 64),
+            HiresMode::Auto => (DISPLAY_WIDTH, DISPLAY_HEIGHT),
+            HiresMode::TwoPage => (64, 64),
+            HiresMode::FourPage => (64, 128),
+        }
+    }
+}
+
 /// Display abstraction for the Chip-8 screen.
 ///
 /// The Chip-8 has a 64x32 monochrome display where each pixel can be
@@ -57,6 +129,16 @@ pub trait Display {
     /// True if collision occurred (any pixel turned off), false otherwise.
     fn draw_sprite(&mut self, x: u8, y: u8, sprite: &[u8]) -> DisplayResult<bool>;
 
+    /// Sets what happens to sprite pixels past the edge of the screen.
+    ///
+    /// Displays that don't support clipping ignore this and wrap.
+    fn set_clip_mode(&mut self, _mode: ClipMode) {}
+
+    /// Gets what happens to sprite pixels past the edge of the screen.
+    fn clip_mode(&self) -> ClipMode {
+        ClipMode::Wrap
+    }
+
     /// Gets the state of a pixel.
     ///
     /// # Arguments
@@ -91,6 +173,239 @@ pub trait Display {
 
     /// Gets a mutable reference to the pixel buffer.
     fn get_buffer_mut(&mut self) -> &mut [bool];
+
+    /// Gets the resolution as (width, height) in pixels.
+    fn size(&self) -> (usize, usize) {
+        (DISPLAY_WIDTH, DISPLAY_HEIGHT)
+    }
+
+    /// Changes the resolution, clearing the screen.
+    ///
+    /// Displays with a fixed resolution only accept their own size.
+    fn resize(&mut self, width: usize, height: usize) -> DisplayResult<()> {
+        if (width, height) != self.size() {
+            return Err(DisplayError::UnsupportedResolution { width, height });
+        }
+        self.clear();
+        Ok(())
+    }
+
+    /// Copies the screen into a portable snapshot.
+    fn export_buffer(&self) -> DisplaySnapshot {
+        let (width, height) = self.size();
+        DisplaySnapshot::from_pixels(width, height, self.get_buffer())
+    }
+
+    /// Replaces the screen with a snapshot's, resizing the display first if
+    /// the snapshot has another resolution.
+    fn import_buffer(&mut self, snapshot: &DisplaySnapshot) -> DisplayResult<()> {
+        let pixels = snapshot.pixels()?;
+        let size = snapshot.size();
+        if size != self.size() {
+            self.resize(size.0, size.1)?;
+        }
+        self.get_buffer_mut().copy_from_slice(&pixels);
+        Ok(())
+    }
+
+    /// Formats the display as text, one line per row, with `#` for on and `.` for off.
+    fn to_ascii_art(&self) -> String {
+        ascii_art(self.get_buffer(), self.size().0)
+    }
+
+    /// Formats the display compactly as Unicode braille, 2x4 pixels per character.
+    fn to_braille_art(&self) -> String {
+        braille_art(self.get_buffer(), self.size().0)
+    }
+}
+
+/// Checks that a resolution is within what displays can be resized to.
+pub fn check_resolution(width: usize, height: usize) -> DisplayResult<()> {
+    if width == 0 || height == 0 || width > MAX_DISPLAY_WIDTH || height > MAX_DISPLAY_HEIGHT {
+        return Err(DisplayError::UnsupportedResolution { width, height });
+    }
+    Ok(())
+}
+
+/// Formats a display buffer `width` pixels wide as text, one line per row,
+/// with `#` for on and `.` for off.
+pub fn ascii_art(buffer: &[bool], width: usize) -> String {
+    let width = width.max(1);
+    let mut out = String::with_capacity(buffer.len() + buffer.len() / width);
+    for row in buffer.chunks(width) {
+        out.extend(row.iter().map(|&on| if on { '#' } else { '.' }));
+        out.push('\n');
+    }
+    out
+}
+
+/// Formats a display buffer `width` pixels wide as Unicode braille, 2x4
+/// pixels per character.
+///
+/// The 64x32 display becomes 8 lines of 32 characters.
+pub fn braille_art(buffer: &[bool], width: usize) -> String {
+    // Braille dot bits for each (column, row) within a 2x4 cell
+    const DOTS: [[u32; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+
+    let width = width.max(1);
+    let height = buffer.len() / width;
+    let pixel =
+        |x: usize, y: usize| x < width && buffer.get(y * width + x).copied().unwrap_or(false);
+    let mut out = String::new();
+    for cell_y in (0..height).step_by(4) {
+        for cell_x in (0..width).step_by(2) {
+            let mut bits = 0;
+            for (dx, column) in DOTS.iter().enumerate() {
+                for (dy, bit) in column.iter().enumerate() {
+                    if pixel(cell_x + dx, cell_y + dy) {
+                        bits |= bit;
+                    }
+                }
+            }
+            out.push(char::from_u32(0x2800 + bits).unwrap_or(' '));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// XORs a sprite into a display buffer `width` pixels wide.
+///
+/// The start position wraps around the screen; pixels that run past the
+/// edge wrap or are dropped according to `mode`.
+///
+/// # Returns
+/// True if any pixel was turned off (collision).
+pub fn xor_sprite(
+    buffer: &mut [bool],
+    width: usize,
+    x: u8,
+    y: u8,
+    sprite: &[u8],
+    mode: ClipMode,
+) -> bool {
+    let width = width.max(1);
+    let height = (buffer.len() / width).max(1);
+    let start_x = x as usize % width;
+    let start_y = y as usize % height;
+    let mut collision = false;
+
+    for (row, &sprite_byte) in sprite.iter().enumerate() {
+        let pixel_y = start_y + row;
+        if pixel_y >= height && mode == ClipMode::Clip {
+            break;
+        }
+
+        for col in 0..8 {
+            let pixel_x = start_x + col;
+            if pixel_x >= width && mode == ClipMode::Clip {
+                break;
+            }
+            if (sprite_byte >> (7 - col)) & 1 == 0 {
+                continue;
+            }
+
+            let index = (pixel_y % height) * width + pixel_x % width;
+            // Collision if pixel was turned off
+            collision |= buffer[index];
+            buffer[index] ^= true;
+        }
+    }
+
+    collision
+}
+
+/// A portable copy of the screen, for external tools, tests and save states.
+///
+/// Pixels are stored row by row, packed eight to a byte with the leftmost
+/// pixel in the most significant bit, and written as hex. A run-length
+/// encoded snapshot stores the packed bytes as `(count, byte)` pairs
+/// instead, with counts from 1 to 255, which shrinks a mostly blank screen
+/// to a few bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DisplaySnapshot {
+    /// Width in pixels.
+    pub width: u16,
+
+    /// Height in pixels.
+    pub height: u16,
+
+    /// Whether `bits` is run-length encoded.
+    #[serde(default)]
+    pub rle: bool,
+
+    /// Packed pixels, run-length encoded if `rle` is set.
+    #[serde(with = "hex_bytes")]
+    pub bits: Vec<u8>,
+}
+
+impl DisplaySnapshot {
+    /// Creates a snapshot of a screen's pixels, in row-major order.
+    pub fn from_pixels(width: usize, height: usize, pixels: &[bool]) -> Self {
+        Self {
+            width: width as u16,
+            height: height as u16,
+            rle: false,
+            bits: pack_pixels(pixels),
+        }
+    }
+
+    /// Gets the resolution as (width, height) in pixels.
+    pub fn size(&self) -> (usize, usize) {
+        (self.width as usize, self.height as usize)
+    }
+
+    /// Gets the same snapshot with its pixels run-length encoded.
+    pub fn run_length_encoded(&self) -> Self {
+        if self.rle {
+            return self.clone();
+        }
+        let mut bits = Vec::new();
+        for &byte in &self.bits {
+            match bits.len().checked_sub(2) {
+                Some(last) if bits[last + 1] == byte && bits[last] < u8::MAX => bits[last] += 1,
+                _ => bits.extend([1, byte]),
+            }
+        }
+        Self {
+            rle: true,
+            bits,
+            ..*self
+        }
+    }
+
+    /// Unpacks the pixels in row-major order.
+    ///
+    /// # Returns
+    /// The pixels, or an error if the resolution is unsupported or the bits
+    /// don't hold exactly one screen.
+    pub fn pixels(&self) -> DisplayResult<Vec<bool>> {
+        let (width, height) = self.size();
+        check_resolution(width, height)?;
+        let packed = if self.rle {
+            if !self.bits.len().is_multiple_of(2) || self.bits.chunks(2).any(|pair| pair[0] == 0) {
+                return Err(DisplayError::InvalidSnapshot(
+                    "run-length pairs need a count from 1 to 255".to_string(),
+                ));
+            }
+            self.bits
+                .chunks(2)
+                .flat_map(|pair| core::iter::repeat_n(pair[1], pair[0] as usize))
+                .collect()
+        } else {
+            self.bits.clone()
+        };
+        let mut pixels = vec![false; width * height];
+        if !unpack_pixels(&packed, &mut pixels) {
+            return Err(DisplayError::InvalidSnapshot(format!(
+                "{} bytes of pixels for a {}x{} screen",
+                packed.len(),
+                width,
+                height
+            )));
+        }
+        Ok(pixels)
+    }
 }
 
 /// A basic software display implementation.
@@ -99,28 +414,40 @@ pub trait Display {
 /// provides the core Chip-8 display functionality without any
 /// actual rendering backend.
 pub struct SoftwareDisplay {
-    /// Pixel buffer (true = on, false = off).
-    pixels: [bool; DISPLAY_PIXELS],
+    /// Pixel buffer (true = on, false = off), row by row.
+    pixels: Vec<bool>,
+
+    /// Width in pixels.
+    width: usize,
+
+    /// Height in pixels.
+    height: usize,
 
     /// Whether the display has been modified.
     dirty: bool,
+
+    /// What happens to sprite pixels past the edge.
+    clip_mode: ClipMode,
 }
 
 impl SoftwareDisplay {
     /// Creates a new software display.
     pub fn new() -> Self {
         Self {
-            pixels: [false; DISPLAY_PIXELS],
+            pixels: vec![false; DISPLAY_PIXELS],
+            width: DISPLAY_WIDTH,
+            height: DISPLAY_HEIGHT,
             dirty: false,
+            clip_mode: ClipMode::default(),
         }
     }
 
     /// Converts coordinates to buffer index.
     fn coord_to_index(&self, x: u8, y: u8) -> DisplayResult<usize> {
-        if x as usize >= DISPLAY_WIDTH || y as usize >= DISPLAY_HEIGHT {
+        if x as usize >= self.width || y as usize >= self.height {
             return Err(DisplayError::InvalidCoordinates { x, y });
         }
-        Ok(y as usize * DISPLAY_WIDTH + x as usize)
+        Ok(y as usize * self.width + x as usize)
     }
 }
 
@@ -137,27 +464,7 @@ impl Display for SoftwareDisplay {
     }
 
     fn draw_sprite(&mut self, x: u8, y: u8, sprite: &[u8]) -> DisplayResult<bool> {
-        let mut collision = false;
-
-        for (row, &sprite_byte) in sprite.iter().enumerate() {
-            let pixel_y = (y + row as u8) % DISPLAY_HEIGHT as u8;
-
-            for col in 0..8 {
-                let pixel_x = (x + col) % DISPLAY_WIDTH as u8;
-                let sprite_pixel = (sprite_byte >> (7 - col)) & 1;
-
-                if sprite_pixel == 1 {
-                    let index = self.coord_to_index(pixel_x, pixel_y)?;
-                    let old_pixel = self.pixels[index];
-                    self.pixels[index] ^= true;
-
-                    // Collision if pixel was turned off
-                    if old_pixel && !self.pixels[index] {
-                        collision = true;
-                    }
-                }
-            }
-        }
+        let collision = xor_sprite(&mut self.pixels, self.width, x, y, sprite, self.clip_mode);
 
         if !sprite.is_empty() {
             self.dirty = true;
@@ -166,6 +473,14 @@ impl Display for SoftwareDisplay {
         Ok(collision)
     }
 
+    fn set_clip_mode(&mut self, mode: ClipMode) {
+        self.clip_mode = mode;
+    }
+
+    fn clip_mode(&self) -> ClipMode {
+        self.clip_mode
+    }
+
     fn get_pixel(&self, x: u8, y: u8) -> DisplayResult<bool> {
         let index = self.coord_to_index(x, y)?;
         Ok(self.pixels[index])
@@ -200,6 +515,19 @@ impl Display for SoftwareDisplay {
         self.dirty = true;
         &mut self.pixels
     }
+
+    fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    fn resize(&mut self, width: usize, height: usize) -> DisplayResult<()> {
+        check_resolution(width, height)?;
+        self.pixels = vec![false; width * height];
+        self.width = width;
+        self.height = height;
+        self.dirty = true;
+        Ok(())
+    }
 }
 
 /// A null display implementation for testing and headless operation.
@@ -230,6 +558,14 @@ impl Display for NullDisplay {
         self.display.draw_sprite(x, y, sprite)
     }
 
+    fn set_clip_mode(&mut self, mode: ClipMode) {
+        self.display.set_clip_mode(mode);
+    }
+
+    fn clip_mode(&self) -> ClipMode {
+        self.display.clip_mode()
+    }
+
     fn get_pixel(&self, x: u8, y: u8) -> DisplayResult<bool> {
         self.display.get_pixel(x, y)
     }
@@ -259,12 +595,233 @@ impl Display for NullDisplay {
     fn get_buffer_mut(&mut self) -> &mut [bool] {
         self.display.get_buffer_mut()
     }
+
+    fn size(&self) -> (usize, usize) {
+        self.display.size()
+    }
+
+    fn resize(&mut self, width: usize, height: usize) -> DisplayResult<()> {
+        self.display.resize(width, height)
+    }
+}
+
+/// A copy of the screen published by a [`SharedDisplay`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameSnapshot {
+    /// Sequence number, incremented each time a changed frame is published.
+    pub sequence: u64,
+
+    /// Width of the frame in pixels.
+    pub width: usize,
+
+    /// Height of the frame in pixels.
+    pub height: usize,
+
+    /// Pixels in row-major order.
+    pub pixels: Vec<bool>,
+}
+
+impl Default for FrameSnapshot {
+    fn default() -> Self {
+        Self {
+            sequence: 0,
+            width: DISPLAY_WIDTH,
+            height: DISPLAY_HEIGHT,
+            pixels: vec![false; DISPLAY_PIXELS],
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+/// A display whose frames can be read from another thread.
+///
+/// The emulation thread draws into a private back buffer; `render` (or
+/// `mark_clean`) publishes it behind an `Arc<RwLock>` that any number of
+/// [`DisplayReader`]s read. The lock is only held while copying a frame, so
+/// a render thread never blocks the CPU for longer than that.
+pub struct SharedDisplay {
+    /// Back buffer the CPU draws into.
+    display: SoftwareDisplay,
+
+    /// Last published frame.
+    front: Arc<RwLock<FrameSnapshot>>,
+}
+
+#[cfg(feature = "std")]
+impl SharedDisplay {
+    /// Creates a shared display with a blank published frame.
+    pub fn new() -> Self {
+        Self {
+            display: SoftwareDisplay::new(),
+            front: Arc::new(RwLock::new(FrameSnapshot::default())),
+        }
+    }
+
+    /// Creates a handle for reading published frames.
+    pub fn reader(&self) -> DisplayReader {
+        DisplayReader {
+            front: Arc::clone(&self.front),
+        }
+    }
+
+    /// Publishes the back buffer if it changed since the last publish.
+    ///
+    /// # Returns
+    /// The sequence number of the published frame.
+    pub fn publish(&mut self) -> u64 {
+        let mut front = self.front.write().unwrap_or_else(PoisonError::into_inner);
+        if self.display.is_dirty() {
+            front.pixels.clone_from(&self.display.pixels);
+            (front.width, front.height) = self.display.size();
+            front.sequence += 1;
+            self.display.mark_clean();
+        }
+        front.sequence
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for SharedDisplay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Display for SharedDisplay {
+    fn clear(&mut self) {
+        self.display.clear();
+    }
+
+    fn draw_sprite(&mut self, x: u8, y: u8, sprite: &[u8]) -> DisplayResult<bool> {
+        self.display.draw_sprite(x, y, sprite)
+    }
+
+    fn set_clip_mode(&mut self, mode: ClipMode) {
+        self.display.set_clip_mode(mode);
+    }
+
+    fn clip_mode(&self) -> ClipMode {
+        self.display.clip_mode()
+    }
+
+    fn get_pixel(&self, x: u8, y: u8) -> DisplayResult<bool> {
+        self.display.get_pixel(x, y)
+    }
+
+    fn set_pixel(&mut self, x: u8, y: u8, on: bool) -> DisplayResult<()> {
+        self.display.set_pixel(x, y, on)
+    }
+
+    fn render(&mut self) -> Result<(), EmulatorError> {
+        self.publish();
+        Ok(())
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.display.is_dirty()
+    }
+
+    fn mark_clean(&mut self) {
+        self.publish();
+    }
+
+    fn get_buffer(&self) -> &[bool] {
+        self.display.get_buffer()
+    }
+
+    fn get_buffer_mut(&mut self) -> &mut [bool] {
+        self.display.get_buffer_mut()
+    }
+
+    fn size(&self) -> (usize, usize) {
+        self.display.size()
+    }
+
+    fn resize(&mut self, width: usize, height: usize) -> DisplayResult<()> {
+        self.display.resize(width, height)
+    }
+}
+
+#[cfg(feature = "std")]
+/// Read side of a [`SharedDisplay`], safe to send to a render thread.
+#[derive(Debug, Clone)]
+pub struct DisplayReader {
+    front: Arc<RwLock<FrameSnapshot>>,
+}
+
+#[cfg(feature = "std")]
+impl DisplayReader {
+    /// Gets the sequence number of the last published frame.
+    pub fn sequence(&self) -> u64 {
+        self.front
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .sequence
+    }
+
+    /// Copies the last published frame.
+    pub fn snapshot(&self) -> FrameSnapshot {
+        self.front
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Copies the last published frame if it is newer than one already seen.
+    ///
+    /// # Arguments
+    /// * `seen` - Sequence number of the frame the caller last drew
+    pub fn newer_than(&self, seen: u64) -> Option<FrameSnapshot> {
+        let front = self.front.read().unwrap_or_else(PoisonError::into_inner);
+        (front.sequence > seen).then(|| front.clone())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_display_snapshot_round_trip() {
+        let mut display = SoftwareDisplay::new();
+        display.set_pixel(0, 0, true).unwrap();
+        display.set_pixel(63, 31, true).unwrap();
+
+        let snapshot = display.export_buffer();
+        let encoded = snapshot.run_length_encoded();
+        assert!(encoded.rle);
+        assert!(encoded.bits.len() < snapshot.bits.len());
+        assert_eq!(encoded.pixels().unwrap(), display.get_buffer());
+
+        let json = serde_json::to_string(&encoded).unwrap();
+        let parsed: DisplaySnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, encoded);
+
+        let mut bad = encoded.clone();
+        bad.bits.pop();
+        assert!(matches!(
+            bad.pixels(),
+            Err(DisplayError::InvalidSnapshot(_))
+        ));
+    }
+
+    #[test]
+    fn test_import_buffer_resizes() {
+        let mut hires = SoftwareDisplay::new();
+        hires.resize(128, 64).unwrap();
+        hires.set_pixel(100, 50, true).unwrap();
+        let snapshot = hires.export_buffer().run_length_encoded();
+
+        let mut display = SoftwareDisplay::new();
+        display.mark_clean();
+        display.import_buffer(&snapshot).unwrap();
+        assert_eq!(display.size(), (128, 64));
+        assert!(display.get_pixel(100, 50).unwrap());
+        assert!(display.is_dirty());
+        assert_eq!(display.export_buffer(), hires.export_buffer());
+    }
+
     #[test]
     fn test_display_creation() {
         let display = SoftwareDisplay::new();
@@ -274,6 +831,37 @@ mod tests {
         assert!(display.get_buffer().iter().all(|&pixel| !pixel));
     }
 
+    #[test]
+    fn test_ascii_art() {
+        let mut display = SoftwareDisplay::new();
+        display.set_pixel(0, 0, true).unwrap();
+        display.set_pixel(63, 31, true).unwrap();
+
+        let art = display.to_ascii_art();
+        let lines: Vec<&str> = art.lines().collect();
+        assert_eq!(lines.len(), DISPLAY_HEIGHT);
+        assert_eq!(lines[0], format!("#{}", ".".repeat(63)));
+        assert_eq!(lines[31], format!("{}#", ".".repeat(63)));
+    }
+
+    #[test]
+    fn test_braille_art() {
+        let mut display = SoftwareDisplay::new();
+        // Fill the first 2x4 cell and set the bottom-right dot of the second
+        for y in 0..4 {
+            display.set_pixel(0, y, true).unwrap();
+            display.set_pixel(1, y, true).unwrap();
+        }
+        display.set_pixel(3, 3, true).unwrap();
+
+        let art = display.to_braille_art();
+        let lines: Vec<&str> = art.lines().collect();
+        assert_eq!(lines.len(), 8);
+        assert!(lines.iter().all(|line| line.chars().count() == 32));
+        assert!(lines[0].starts_with("⣿⢀⠀"));
+        assert!(lines[7].chars().all(|c| c == '⠀'));
+    }
+
     #[test]
     fn test_clear_display() {
         let mut display = SoftwareDisplay::new();
@@ -364,6 +952,95 @@ mod tests {
         assert!(display.get_pixel(0, 0).unwrap());
     }
 
+    #[test]
+    fn test_sprite_clipping() {
+        let mut display = SoftwareDisplay::new();
+        display.set_clip_mode(ClipMode::Clip);
+        assert_eq!(display.clip_mode(), ClipMode::Clip);
+
+        // Right and bottom edges are clipped
+        display
+            .draw_sprite(63, 31, &[0b11000000, 0b11000000])
+            .unwrap();
+        assert!(display.get_pixel(63, 31).unwrap());
+        assert_eq!(display.get_buffer().iter().filter(|&&p| p).count(), 1);
+
+        // The start position still wraps
+        display.clear();
+        display.draw_sprite(64 + 2, 32 + 3, &[0b10000000]).unwrap();
+        assert!(display.get_pixel(2, 3).unwrap());
+
+        // Clipped pixels never collide
+        display.clear();
+        display.set_pixel(0, 0, true).unwrap();
+        assert!(!display.draw_sprite(63, 0, &[0b11000000]).unwrap());
+        assert!(display.get_pixel(0, 0).unwrap());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_shared_display_publishes_on_render() {
+        let mut display = SharedDisplay::new();
+        let reader = display.reader();
+        assert_eq!(reader.newer_than(0), None);
+
+        display.draw_sprite(0, 0, &[0b10000000]).unwrap();
+        assert!(!reader.snapshot().pixels[0]);
+
+        display.render().unwrap();
+        let frame = reader.newer_than(0).unwrap();
+        assert_eq!(frame.sequence, 1);
+        assert!(frame.pixels[0]);
+        assert!(!display.is_dirty());
+
+        // Unchanged frames aren't republished
+        display.render().unwrap();
+        assert_eq!(reader.sequence(), 1);
+        assert_eq!(reader.newer_than(1), None);
+
+        let reader = std::thread::spawn(move || reader.snapshot())
+            .join()
+            .unwrap();
+        assert_eq!(reader, frame);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_resize() {
+        let mut display = SharedDisplay::new();
+        let reader = display.reader();
+        display.resize(64, 64).unwrap();
+        assert_eq!(display.size(), (64, 64));
+        assert_eq!(display.get_buffer().len(), 64 * 64);
+
+        // Sprites wrap at the new bottom edge rather than the old one
+        display
+            .draw_sprite(0, 63, &[0b10000000, 0b10000000])
+            .unwrap();
+        assert!(display.get_pixel(0, 63).unwrap() && display.get_pixel(0, 0).unwrap());
+        assert!(!display.get_pixel(0, 32).unwrap());
+        assert_eq!(display.to_ascii_art().lines().count(), 64);
+
+        display.render().unwrap();
+        let frame = reader.snapshot();
+        assert_eq!(
+            (frame.width, frame.height, frame.pixels.len()),
+            (64, 64, 4096)
+        );
+
+        assert!(display.resize(0, 32).is_err());
+        assert!(display.resize(512, 32).is_err());
+        assert!(NullDisplay::new().resize(64, 64).is_ok());
+    }
+
+    #[test]
+    fn test_hires_mode_resolution() {
+        assert_eq!(HiresMode::Auto.resolution(0x1260), (64, 64));
+        assert_eq!(HiresMode::Auto.resolution(0x00E0), (64, 32));
+        assert_eq!(HiresMode::Off.resolution(0x1260), (64, 32));
+        assert_eq!(HiresMode::FourPage.resolution(0x00E0), (64, 128));
+    }
+
     #[test]
     fn test_dirty_flag() {
         let mut display = SoftwareDisplay::new();
@@ -4,13 +4,26 @@
 //! for the 16-key hexadecimal keypad used by Chip-8 systems.
 
 use crate::error::InputError;
-use std::collections::HashSet;
+use crate::input::latency::InputLatency;
+#[cfg(feature = "std")]
+use crate::input::latency::{KeyStamps, LatencyTracker};
+use crate::input::rollover::{HeldKeys, KeyRollover};
+use alloc::collections::BTreeSet;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+#[cfg(feature = "std")]
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+#[cfg(feature = "std")]
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+#[cfg(feature = "std")]
+use std::time::Instant;
 
 /// Input-specific result type.
 pub type InputResult<T> = Result<T, InputError>;
 
 /// Chip-8 hexadecimal keys (0-F).
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum ChipKey {
     Key0 = 0x0,
     Key1 = 0x1,
@@ -82,8 +95,8 @@ impl ChipKey {
     }
 }
 
-impl std::fmt::Display for ChipKey {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for ChipKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{:X}", self.to_u8())
     }
 }
@@ -125,53 +138,105 @@ pub trait Input {
             .find(|&&key| self.is_key_pressed(key))
             .copied()
     }
+
+    /// Reports that the CPU has read a key as pressed.
+    ///
+    /// Implementations that measure input latency complete the key's
+    /// measurement here. The default implementation ignores it.
+    fn key_seen(&mut self, _key: ChipKey) {}
+
+    /// Gets the input latency statistics.
+    ///
+    /// Implementations that don't measure latency report none.
+    fn latency(&self) -> InputLatency {
+        InputLatency::default()
+    }
 }
 
 /// A software input implementation that maintains key state.
 #[derive(Clone)]
 pub struct SoftwareInput {
     /// Currently pressed keys.
-    pressed_keys: HashSet<ChipKey>,
+    pressed_keys: BTreeSet<ChipKey>,
 
     /// Keys pressed this frame.
-    keys_pressed_this_frame: HashSet<ChipKey>,
+    keys_pressed_this_frame: BTreeSet<ChipKey>,
 
     /// Keys released this frame.
-    keys_released_this_frame: HashSet<ChipKey>,
+    keys_released_this_frame: BTreeSet<ChipKey>,
+
+    /// Keys physically held, limited by the keypad rollover.
+    held: HeldKeys,
+
+    /// Latency of timestamped presses.
+    #[cfg(feature = "std")]
+    latency: LatencyTracker,
 }
 
 impl SoftwareInput {
     /// Creates a new software input system.
     pub fn new() -> Self {
         Self {
-            pressed_keys: HashSet::new(),
-            keys_pressed_this_frame: HashSet::new(),
-            keys_released_this_frame: HashSet::new(),
+            pressed_keys: BTreeSet::new(),
+            keys_pressed_this_frame: BTreeSet::new(),
+            keys_released_this_frame: BTreeSet::new(),
+            held: HeldKeys::default(),
+            #[cfg(feature = "std")]
+            latency: LatencyTracker::new(),
         }
     }
 
+    /// Sets how many held keys are registered at once.
+    pub fn set_rollover(&mut self, rollover: KeyRollover) {
+        self.held.set_rollover(rollover);
+        self.sync_held();
+    }
+
     /// Simulates pressing a key.
     pub fn press_key(&mut self, key: ChipKey) {
-        if !self.pressed_keys.contains(&key) {
-            self.keys_pressed_this_frame.insert(key);
+        self.held.press(key);
+        self.sync_held();
+    }
+
+    #[cfg(feature = "std")]
+    /// Presses a key, measuring its latency from the frontend's timestamps.
+    ///
+    /// Key repeats of a held key are not measured.
+    pub fn press_key_stamped(&mut self, key: ChipKey, stamps: KeyStamps) {
+        if !self.held.is_held(key) {
+            self.latency.press(key, stamps, Instant::now());
         }
-        self.pressed_keys.insert(key);
+        self.press_key(key);
     }
 
     /// Simulates releasing a key.
     pub fn release_key(&mut self, key: ChipKey) {
-        if self.pressed_keys.contains(&key) {
-            self.keys_released_this_frame.insert(key);
-        }
-        self.pressed_keys.remove(&key);
+        self.held.release(key);
+        self.sync_held();
+        #[cfg(feature = "std")]
+        self.latency.cancel(key);
+    }
+
+    #[cfg(feature = "std")]
+    /// Clears the input latency statistics.
+    pub fn reset_latency(&mut self) {
+        self.latency.reset();
     }
 
     /// Releases all keys.
     pub fn release_all_keys(&mut self) {
-        for &key in &self.pressed_keys {
-            self.keys_released_this_frame.insert(key);
-        }
-        self.pressed_keys.clear();
+        self.held.clear();
+        self.sync_held();
+    }
+
+    /// Registers the held keys the rollover allows, noting keys that changed.
+    fn sync_held(&mut self) {
+        let registered: BTreeSet<ChipKey> = self.held.registered().iter().copied().collect();
+        self.keys_pressed_this_frame
+            .extend(registered.difference(&self.pressed_keys));
+        self.keys_released_this_frame
+            .extend(self.pressed_keys.difference(&registered));
+        self.pressed_keys = registered;
     }
 
     /// Checks if a key was just pressed this frame.
@@ -220,6 +285,16 @@ impl Input for SoftwareInput {
         self.keys_released_this_frame.clear();
         Ok(())
     }
+
+    #[cfg(feature = "std")]
+    fn key_seen(&mut self, key: ChipKey) {
+        self.latency.seen(key, Instant::now());
+    }
+
+    #[cfg(feature = "std")]
+    fn latency(&self) -> InputLatency {
+        self.latency.stats()
+    }
 }
 
 /// A null input implementation for testing and automated operation.
@@ -267,6 +342,196 @@ impl Input for NullInput {
     fn update(&mut self) -> InputResult<()> {
         self.input.update()
     }
+
+    fn key_seen(&mut self, key: ChipKey) {
+        self.input.key_seen(key);
+    }
+
+    fn latency(&self) -> InputLatency {
+        self.input.latency()
+    }
+}
+
+/// A key press or release sent to a [`ChannelInput`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyEvent {
+    /// Key that changed.
+    pub key: ChipKey,
+
+    /// Whether the key went down (true) or up (false).
+    pub pressed: bool,
+}
+
+impl KeyEvent {
+    /// Creates a key press event.
+    pub fn pressed(key: ChipKey) -> Self {
+        Self { key, pressed: true }
+    }
+
+    /// Creates a key release event.
+    pub fn released(key: ChipKey) -> Self {
+        Self {
+            key,
+            pressed: false,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+/// Input fed by key events sent over a channel.
+///
+/// The frontend keeps the `Sender` and sends events from its own thread
+/// (e.g. winit's event loop); the emulation thread owns the input and
+/// applies queued events on each `update`. If every sender is dropped, all
+/// keys are released so none stay stuck down.
+pub struct ChannelInput {
+    input: SoftwareInput,
+    events: Receiver<KeyEvent>,
+}
+
+#[cfg(feature = "std")]
+impl ChannelInput {
+    /// Creates a channel input and the sender that feeds it.
+    pub fn new() -> (Self, Sender<KeyEvent>) {
+        let (sender, receiver) = mpsc::channel();
+        (Self::with_receiver(receiver), sender)
+    }
+
+    /// Creates a channel input reading from an existing receiver.
+    pub fn with_receiver(events: Receiver<KeyEvent>) -> Self {
+        Self {
+            input: SoftwareInput::new(),
+            events,
+        }
+    }
+
+    /// Applies all queued key events without starting a new frame.
+    ///
+    /// # Returns
+    /// The number of events applied.
+    pub fn poll(&mut self) -> usize {
+        let mut applied = 0;
+        loop {
+            match self.events.try_recv() {
+                Ok(KeyEvent { key, pressed: true }) => self.input.press_key(key),
+                Ok(KeyEvent {
+                    key,
+                    pressed: false,
+                }) => self.input.release_key(key),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.input.release_all_keys();
+                    break;
+                }
+            }
+            applied += 1;
+        }
+        applied
+    }
+
+    /// Checks if a key was pressed by the events applied this frame.
+    pub fn was_key_just_pressed(&self, key: ChipKey) -> bool {
+        self.input.was_key_just_pressed(key)
+    }
+
+    /// Checks if a key was released by the events applied this frame.
+    pub fn was_key_just_released(&self, key: ChipKey) -> bool {
+        self.input.was_key_just_released(key)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Input for ChannelInput {
+    fn is_key_pressed(&self, key: ChipKey) -> bool {
+        self.input.is_key_pressed(key)
+    }
+
+    fn wait_for_key(&self) -> Option<ChipKey> {
+        self.input.wait_for_key()
+    }
+
+    fn get_pressed_keys(&self) -> Vec<ChipKey> {
+        self.input.get_pressed_keys()
+    }
+
+    fn update(&mut self) -> InputResult<()> {
+        self.input.update()?;
+        self.poll();
+        Ok(())
+    }
+
+    fn key_seen(&mut self, key: ChipKey) {
+        self.input.key_seen(key);
+    }
+
+    fn latency(&self) -> InputLatency {
+        self.input.latency()
+    }
+}
+
+/// Shared input handles forward to the wrapped input.
+///
+/// This lets an input be owned by the hardware layer while the frontend
+/// keeps a clone of the handle to feed key events into it.
+impl<T: Input + ?Sized> Input for Rc<RefCell<T>> {
+    fn is_key_pressed(&self, key: ChipKey) -> bool {
+        self.borrow().is_key_pressed(key)
+    }
+
+    fn wait_for_key(&self) -> Option<ChipKey> {
+        self.borrow().wait_for_key()
+    }
+
+    fn get_pressed_keys(&self) -> Vec<ChipKey> {
+        self.borrow().get_pressed_keys()
+    }
+
+    fn update(&mut self) -> InputResult<()> {
+        self.borrow_mut().update()
+    }
+
+    fn key_seen(&mut self, key: ChipKey) {
+        self.borrow_mut().key_seen(key);
+    }
+
+    fn latency(&self) -> InputLatency {
+        self.borrow().latency()
+    }
+}
+
+#[cfg(feature = "std")]
+/// Thread-safe shared input handles forward to the wrapped input.
+///
+/// A poisoned lock is recovered, since key state can't be left half-updated.
+impl<T: Input + ?Sized> Input for Arc<Mutex<T>> {
+    fn is_key_pressed(&self, key: ChipKey) -> bool {
+        lock(self).is_key_pressed(key)
+    }
+
+    fn wait_for_key(&self) -> Option<ChipKey> {
+        lock(self).wait_for_key()
+    }
+
+    fn get_pressed_keys(&self) -> Vec<ChipKey> {
+        lock(self).get_pressed_keys()
+    }
+
+    fn update(&mut self) -> InputResult<()> {
+        lock(self).update()
+    }
+
+    fn key_seen(&mut self, key: ChipKey) {
+        lock(self).key_seen(key);
+    }
+
+    fn latency(&self) -> InputLatency {
+        lock(self).latency()
+    }
+}
+
+#[cfg(feature = "std")]
+fn lock<T: ?Sized>(input: &Mutex<T>) -> MutexGuard<'_, T> {
+    input.lock().unwrap_or_else(PoisonError::into_inner)
 }
 
 /// Default key mapping for QWERTY keyboards.
@@ -457,6 +722,28 @@ mod tests {
         assert_eq!(input.get_first_pressed_key(), Some(ChipKey::Key7));
     }
 
+    #[test]
+    fn test_software_input_rollover() {
+        let mut input = SoftwareInput::new();
+        input.set_rollover(KeyRollover::Single);
+
+        input.press_key(ChipKey::Key1);
+        input.press_key(ChipKey::Key2);
+        assert_eq!(input.get_pressed_keys(), vec![ChipKey::Key1]);
+        assert!(!input.was_key_just_pressed(ChipKey::Key2));
+
+        // Key 2 is still held, so it registers when key 1 goes up
+        input.update().unwrap();
+        input.release_key(ChipKey::Key1);
+        assert_eq!(input.get_pressed_keys(), vec![ChipKey::Key2]);
+        assert!(input.was_key_just_released(ChipKey::Key1));
+        assert!(input.was_key_just_pressed(ChipKey::Key2));
+
+        input.press_key(ChipKey::Key3);
+        input.set_rollover(KeyRollover::Unlimited);
+        assert_eq!(input.get_pressed_keys().len(), 2);
+    }
+
     #[test]
     fn test_null_input() {
         let mut input = NullInput::new();
@@ -472,6 +759,34 @@ mod tests {
         input.update().unwrap();
     }
 
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_channel_input() {
+        let (mut input, sender) = ChannelInput::new();
+
+        // Events can be sent from any thread and are applied on update
+        let sender = std::thread::spawn(move || {
+            sender.send(KeyEvent::pressed(ChipKey::Key4)).unwrap();
+            sender.send(KeyEvent::pressed(ChipKey::KeyC)).unwrap();
+            sender.send(KeyEvent::released(ChipKey::Key4)).unwrap();
+            sender
+        })
+        .join()
+        .unwrap();
+        assert!(!input.is_key_pressed(ChipKey::KeyC));
+
+        input.update().unwrap();
+        assert_eq!(input.get_pressed_keys(), [ChipKey::KeyC]);
+        assert!(input.was_key_just_pressed(ChipKey::KeyC));
+        input.update().unwrap();
+        assert!(!input.was_key_just_pressed(ChipKey::KeyC));
+
+        // Dropping the sender releases everything
+        drop(sender);
+        input.update().unwrap();
+        assert!(input.get_pressed_keys().is_empty());
+    }
+
     #[test]
     fn test_qwerty_key_mapping() {
         // Test char to chip key
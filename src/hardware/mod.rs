@@ -2,6 +2,9 @@
 //!
 //! This module defines the traits and interfaces that abstract away
 //! platform-specific hardware implementations for display, audio, and input.
+//!
+//! Without `std`, [`NullHardware`] or your own [`Hardware`] implementation
+//! drives the CPU; the shared, boxed and threaded hardware need `std`.
 
 pub mod audio;
 pub mod display;
@@ -9,13 +12,24 @@ pub mod input;
 
 // Re-export commonly used types
 pub use audio::{Audio, AudioResult};
-pub use display::{Display, DisplayResult, DISPLAY_HEIGHT, DISPLAY_PIXELS, DISPLAY_WIDTH};
-pub use input::{ChipKey, Input, InputResult};
+pub use display::{
+    ascii_art, braille_art, check_resolution, xor_sprite, ClipMode, Display, DisplayResult,
+    DisplaySnapshot, FrameSnapshot, HiresMode, DISPLAY_HEIGHT, DISPLAY_PIXELS, DISPLAY_WIDTH,
+};
+#[cfg(feature = "std")]
+pub use display::{DisplayReader, SharedDisplay};
+#[cfg(feature = "std")]
+pub use input::ChannelInput;
+pub use input::{ChipKey, Input, InputResult, KeyEvent};
 
 // Re-export error types from the main error module
 pub use crate::error::{AudioError, GraphicsError, InputError};
 
 use crate::error::EmulatorError;
+#[cfg(feature = "std")]
+use alloc::boxed::Box;
+#[cfg(feature = "std")]
+use std::sync::{Arc, Mutex};
 
 /// Result type for hardware operations.
 pub type HardwareResult<T> = Result<T, EmulatorError>;
@@ -25,14 +39,17 @@ pub type HardwareResult<T> = Result<T, EmulatorError>;
 /// This trait combines all hardware interfaces into a single
 /// abstraction that can be easily implemented and tested.
 pub trait Hardware {
-    type Display: Display;
-    type Audio: Audio;
-    type Input: Input;
+    type Display: Display + ?Sized;
+    type Audio: Audio + ?Sized;
+    type Input: Input + ?Sized;
 
     fn display(&mut self) -> &mut Self::Display;
     fn audio(&mut self) -> &mut Self::Audio;
     fn input(&mut self) -> &mut Self::Input;
 
+    /// Gets read-only access to the display.
+    fn display_ref(&self) -> &Self::Display;
+
     /// Updates all hardware systems.
     ///
     /// This should be called once per frame to update input state,
@@ -72,6 +89,10 @@ impl Hardware for NullHardware {
         &mut self.display
     }
 
+    fn display_ref(&self) -> &Self::Display {
+        &self.display
+    }
+
     fn audio(&mut self) -> &mut Self::Audio {
         &mut self.audio
     }
@@ -87,6 +108,241 @@ impl Hardware for NullHardware {
     }
 }
 
+#[cfg(feature = "std")]
+/// Input shared between the hardware layer and a frontend.
+///
+/// The handle is `Send`, so the frontend can feed key events from a
+/// different thread than the one running the CPU.
+pub type SharedInput = Arc<Mutex<dyn Input + Send>>;
+
+#[cfg(feature = "std")]
+/// Hardware built from trait objects, for mixing backends at runtime.
+///
+/// Any combination of display, audio and input implementations can be
+/// used (e.g. a real display with null audio). Parts that are not
+/// supplied to the builder default to the null implementations.
+pub struct DynHardware {
+    display: Box<dyn Display>,
+    audio: Box<dyn Audio>,
+    input: SharedInput,
+}
+
+#[cfg(feature = "std")]
+impl DynHardware {
+    /// Creates dynamic hardware from already boxed parts.
+    pub fn new(display: Box<dyn Display>, audio: Box<dyn Audio>, input: SharedInput) -> Self {
+        Self {
+            display,
+            audio,
+            input,
+        }
+    }
+
+    /// Creates a builder for dynamic hardware.
+    pub fn builder() -> DynHardwareBuilder {
+        DynHardwareBuilder::new()
+    }
+
+    /// Replaces the display implementation.
+    pub fn set_display(&mut self, display: Box<dyn Display>) {
+        self.display = display;
+    }
+
+    /// Replaces the audio implementation.
+    pub fn set_audio(&mut self, audio: Box<dyn Audio>) {
+        self.audio = audio;
+    }
+
+    /// Replaces the input implementation.
+    pub fn set_input(&mut self, input: SharedInput) {
+        self.input = input;
+    }
+
+    /// Gets a clone of the shared input handle.
+    pub fn input_handle(&self) -> SharedInput {
+        Arc::clone(&self.input)
+    }
+
+    /// Splits the hardware into its parts.
+    pub fn into_parts(self) -> (Box<dyn Display>, Box<dyn Audio>, SharedInput) {
+        (self.display, self.audio, self.input)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for DynHardware {
+    fn default() -> Self {
+        DynHardwareBuilder::new().build()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Hardware for DynHardware {
+    type Display = dyn Display;
+    type Audio = dyn Audio;
+    type Input = SharedInput;
+
+    fn display(&mut self) -> &mut Self::Display {
+        self.display.as_mut()
+    }
+
+    fn display_ref(&self) -> &Self::Display {
+        self.display.as_ref()
+    }
+
+    fn audio(&mut self) -> &mut Self::Audio {
+        self.audio.as_mut()
+    }
+
+    fn input(&mut self) -> &mut Self::Input {
+        &mut self.input
+    }
+
+    fn update(&mut self) -> HardwareResult<()> {
+        self.input.lock().unwrap().update()?;
+        self.display.render()?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+/// Hardware that can be moved to another thread.
+///
+/// Pairs a [`SharedDisplay`] with a thread-safe input handle, so a CPU
+/// built on it is `Send`: it can run on an emulation thread while the
+/// frontend draws from a [`DisplayReader`] and feeds keys through the input
+/// handle on its own thread.
+pub struct ThreadedHardware {
+    display: SharedDisplay,
+    audio: Box<dyn Audio + Send>,
+    input: SharedInput,
+}
+
+#[cfg(feature = "std")]
+impl ThreadedHardware {
+    /// Creates threaded hardware.
+    ///
+    /// # Arguments
+    /// * `display` - Display whose frames are read through `reader`
+    /// * `audio` - Audio implementation, which must be `Send`
+    /// * `input` - Input handle shared with the frontend
+    pub fn new(
+        display: SharedDisplay,
+        audio: impl Audio + Send + 'static,
+        input: SharedInput,
+    ) -> Self {
+        Self {
+            display,
+            audio: Box::new(audio),
+            input,
+        }
+    }
+
+    /// Creates a handle for reading frames from another thread.
+    pub fn reader(&self) -> DisplayReader {
+        self.display.reader()
+    }
+
+    /// Gets a clone of the shared input handle.
+    pub fn input_handle(&self) -> SharedInput {
+        Arc::clone(&self.input)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Hardware for ThreadedHardware {
+    type Display = SharedDisplay;
+    type Audio = dyn Audio + Send;
+    type Input = SharedInput;
+
+    fn display(&mut self) -> &mut Self::Display {
+        &mut self.display
+    }
+
+    fn display_ref(&self) -> &Self::Display {
+        &self.display
+    }
+
+    fn audio(&mut self) -> &mut Self::Audio {
+        self.audio.as_mut()
+    }
+
+    fn input(&mut self) -> &mut Self::Input {
+        &mut self.input
+    }
+
+    fn update(&mut self) -> HardwareResult<()> {
+        self.input.update()?;
+        self.display.render()?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+/// Builder for [`DynHardware`].
+#[derive(Default)]
+pub struct DynHardwareBuilder {
+    display: Option<Box<dyn Display>>,
+    audio: Option<Box<dyn Audio>>,
+    input: Option<SharedInput>,
+}
+
+#[cfg(feature = "std")]
+impl DynHardwareBuilder {
+    /// Creates a new builder with no parts set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the display implementation.
+    pub fn with_display(self, display: impl Display + 'static) -> Self {
+        self.with_boxed_display(Box::new(display))
+    }
+
+    /// Sets an already boxed display implementation.
+    pub fn with_boxed_display(mut self, display: Box<dyn Display>) -> Self {
+        self.display = Some(display);
+        self
+    }
+
+    /// Sets the audio implementation.
+    pub fn with_audio(self, audio: impl Audio + 'static) -> Self {
+        self.with_boxed_audio(Box::new(audio))
+    }
+
+    /// Sets an already boxed audio implementation.
+    pub fn with_boxed_audio(mut self, audio: Box<dyn Audio>) -> Self {
+        self.audio = Some(audio);
+        self
+    }
+
+    /// Sets the input implementation.
+    pub fn with_input(self, input: impl Input + Send + 'static) -> Self {
+        self.with_shared_input(Arc::new(Mutex::new(input)))
+    }
+
+    /// Sets a shared input handle (e.g. one also held by the frontend).
+    pub fn with_shared_input(mut self, input: SharedInput) -> Self {
+        self.input = Some(input);
+        self
+    }
+
+    /// Builds the hardware, using null implementations for missing parts.
+    pub fn build(self) -> DynHardware {
+        DynHardware {
+            display: self
+                .display
+                .unwrap_or_else(|| Box::new(display::NullDisplay::new())),
+            audio: self
+                .audio
+                .unwrap_or_else(|| Box::new(audio::NullAudio::new())),
+            input: self
+                .input
+                .unwrap_or_else(|| Arc::new(Mutex::new(input::NullInput::new()))),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -108,4 +364,85 @@ mod tests {
         // Should not fail with null implementations
         hardware.update().unwrap();
     }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_dyn_hardware_defaults() {
+        let mut hardware = DynHardware::default();
+
+        assert!(!hardware.display().is_dirty());
+        assert!(!hardware.audio().is_playing());
+        assert!(!hardware.input().is_key_pressed(ChipKey::Key0));
+        hardware.update().unwrap();
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_dyn_hardware_mixed_backends() {
+        let mut hardware = DynHardware::builder()
+            .with_display(display::SoftwareDisplay::new())
+            .with_audio(audio::SoftwareAudio::with_settings(0.8, 880.0))
+            .with_input(input::SoftwareInput::new())
+            .build();
+
+        hardware.display().set_pixel(0, 0, true).unwrap();
+        assert!(hardware.display().get_pixel(0, 0).unwrap());
+
+        hardware.audio().play_beep().unwrap();
+        assert!(hardware.audio().is_playing());
+        assert_eq!(hardware.audio().get_volume(), 0.8);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_threaded_hardware_runs_on_another_thread() {
+        use crate::emulator::Cpu;
+
+        let input = Arc::new(Mutex::new(input::SoftwareInput::new()));
+        let hardware =
+            ThreadedHardware::new(SharedDisplay::new(), audio::NullAudio::new(), input.clone());
+        let reader = hardware.reader();
+        let mut cpu = Cpu::with_hardware(hardware);
+        // LD V0, 5; SKNP V0; LD F, V0; DRW V0, V1, 5; JP 0x208
+        cpu.load_rom(&[0x60, 0x05, 0xE0, 0xA1, 0xF0, 0x29, 0xD0, 0x15, 0x12, 0x08])
+            .unwrap();
+
+        input.lock().unwrap().press_key(ChipKey::Key5);
+        let cpu = std::thread::spawn(move || {
+            for _ in 0..5 {
+                cpu.cycle().unwrap();
+            }
+            cpu.hardware_mut().update().unwrap();
+            cpu
+        })
+        .join()
+        .unwrap();
+
+        // The top row of the "5" glyph, drawn at (5, 0)
+        let frame = reader.newer_than(0).unwrap();
+        assert_eq!(frame.sequence, 1);
+        assert!(frame.pixels[5..9].iter().all(|&pixel| pixel));
+        assert_eq!(cpu.get_state().pc, 0x208);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_dyn_hardware_shared_input() {
+        let shared = Arc::new(Mutex::new(input::SoftwareInput::new()));
+        let mut hardware = DynHardware::builder()
+            .with_shared_input(shared.clone())
+            .build();
+
+        // Key events fed through the frontend's handle are visible to the hardware
+        shared.lock().unwrap().press_key(ChipKey::KeyA);
+        assert!(hardware.input().is_key_pressed(ChipKey::KeyA));
+        assert!(hardware
+            .input_handle()
+            .lock()
+            .unwrap()
+            .is_key_pressed(ChipKey::KeyA));
+
+        let (_display, _audio, input) = hardware.into_parts();
+        assert!(input.lock().unwrap().is_key_pressed(ChipKey::KeyA));
+    }
 }
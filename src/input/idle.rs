@@ -0,0 +1,72 @@
+//! Tracking how long the player has been away.
+//!
+//! An [`IdleTracker`] remembers when input was last seen, so frontends can
+//! tell when no one is at the keyboard, e.g. to start an attract mode.
+
+use std::time::{Duration, Instant};
+
+/// Time of the last input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdleTracker {
+    /// When input was last seen.
+    last_input: Instant,
+}
+
+impl IdleTracker {
+    /// Creates a tracker that counts as having seen input at `now`.
+    pub fn new(now: Instant) -> Self {
+        Self { last_input: now }
+    }
+
+    /// Notes input at `now`.
+    pub fn touch(&mut self, now: Instant) {
+        self.last_input = self.last_input.max(now);
+    }
+
+    /// Gets when input was last seen.
+    pub fn last_input(&self) -> Instant {
+        self.last_input
+    }
+
+    /// Gets how long there has been no input as of `now`.
+    pub fn idle_for(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(self.last_input)
+    }
+
+    /// Checks if there has been no input for at least `timeout`.
+    pub fn is_idle(&self, now: Instant, timeout: Duration) -> bool {
+        self.idle_for(now) >= timeout
+    }
+}
+
+impl Default for IdleTracker {
+    fn default() -> Self {
+        Self::new(Instant::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idle_tracker() {
+        let start = Instant::now();
+        let mut idle = IdleTracker::new(start);
+        let timeout = Duration::from_secs(60);
+        assert!(!idle.is_idle(start + Duration::from_secs(59), timeout));
+        assert!(idle.is_idle(start + timeout, timeout));
+
+        idle.touch(start + Duration::from_secs(50));
+        assert_eq!(
+            idle.idle_for(start + Duration::from_secs(80)),
+            Duration::from_secs(30)
+        );
+        assert!(!idle.is_idle(start + Duration::from_secs(80), timeout));
+
+        // Input stamped earlier doesn't turn back the clock
+        idle.touch(start);
+        assert_eq!(idle.last_input(), start + Duration::from_secs(50));
+        assert_eq!(idle.idle_for(start), Duration::ZERO);
+    }
+}
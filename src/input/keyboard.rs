@@ -3,6 +3,8 @@
 //! This module provides keyboard event handling and configuration
 //! for capturing real keyboard input.
 
+use super::mapper::KeyboardLayout;
+use super::rollover::KeyRollover;
 use super::InputSystemResult;
 use std::collections::HashMap;
 
@@ -23,6 +25,14 @@ pub struct KeyboardConfig {
 
     /// Whether to use raw keyboard input (if available).
     pub use_raw_input: bool,
+
+    /// How many keypad keys are registered at once.
+    #[serde(default)]
+    pub rollover: KeyRollover,
+
+    /// Layout the keypad keys are looked up in.
+    #[serde(default)]
+    pub layout: KeyboardLayout,
 }
 
 impl Default for KeyboardConfig {
@@ -33,6 +43,8 @@ impl Default for KeyboardConfig {
             handle_repeats: false, // Don't handle repeats for Chip-8
             repeat_delay: 500,     // 500ms repeat delay
             use_raw_input: false,  // Use standard input for compatibility
+            rollover: KeyRollover::Unlimited,
+            layout: KeyboardLayout::Qwerty,
         }
     }
 }
@@ -73,6 +85,18 @@ impl KeyboardConfig {
         self
     }
 
+    /// Sets how many keypad keys are registered at once.
+    pub fn with_rollover(mut self, rollover: KeyRollover) -> Self {
+        self.rollover = rollover;
+        self
+    }
+
+    /// Sets the layout the keypad keys are looked up in.
+    pub fn with_layout(mut self, layout: KeyboardLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
     /// Creates a gaming-focused configuration.
     pub fn gaming() -> Self {
         Self::new()
@@ -189,6 +213,103 @@ pub enum PhysicalKey {
     Unknown(u32),
 }
 
+/// Physical keys of the digit and letter rows, by scancode.
+///
+/// Linux (evdev, as winit reports it on X11 and Wayland) and Windows (set 1)
+/// share these codes for the main block; macOS numbers keys its own way.
+#[cfg(not(target_os = "macos"))]
+const SCANCODES: &[(u32, PhysicalKey)] = &[
+    (2, PhysicalKey::Key1),
+    (3, PhysicalKey::Key2),
+    (4, PhysicalKey::Key3),
+    (5, PhysicalKey::Key4),
+    (6, PhysicalKey::Key5),
+    (7, PhysicalKey::Key6),
+    (8, PhysicalKey::Key7),
+    (9, PhysicalKey::Key8),
+    (10, PhysicalKey::Key9),
+    (11, PhysicalKey::Key0),
+    (16, PhysicalKey::KeyQ),
+    (17, PhysicalKey::KeyW),
+    (18, PhysicalKey::KeyE),
+    (19, PhysicalKey::KeyR),
+    (20, PhysicalKey::KeyT),
+    (21, PhysicalKey::KeyY),
+    (22, PhysicalKey::KeyU),
+    (23, PhysicalKey::KeyI),
+    (24, PhysicalKey::KeyO),
+    (25, PhysicalKey::KeyP),
+    (30, PhysicalKey::KeyA),
+    (31, PhysicalKey::KeyS),
+    (32, PhysicalKey::KeyD),
+    (33, PhysicalKey::KeyF),
+    (34, PhysicalKey::KeyG),
+    (35, PhysicalKey::KeyH),
+    (36, PhysicalKey::KeyJ),
+    (37, PhysicalKey::KeyK),
+    (38, PhysicalKey::KeyL),
+    (44, PhysicalKey::KeyZ),
+    (45, PhysicalKey::KeyX),
+    (46, PhysicalKey::KeyC),
+    (47, PhysicalKey::KeyV),
+    (48, PhysicalKey::KeyB),
+    (49, PhysicalKey::KeyN),
+    (50, PhysicalKey::KeyM),
+];
+
+/// Physical keys of the digit and letter rows, by macOS virtual key code.
+#[cfg(target_os = "macos")]
+const SCANCODES: &[(u32, PhysicalKey)] = &[
+    (0x12, PhysicalKey::Key1),
+    (0x13, PhysicalKey::Key2),
+    (0x14, PhysicalKey::Key3),
+    (0x15, PhysicalKey::Key4),
+    (0x17, PhysicalKey::Key5),
+    (0x16, PhysicalKey::Key6),
+    (0x1A, PhysicalKey::Key7),
+    (0x1C, PhysicalKey::Key8),
+    (0x19, PhysicalKey::Key9),
+    (0x1D, PhysicalKey::Key0),
+    (0x0C, PhysicalKey::KeyQ),
+    (0x0D, PhysicalKey::KeyW),
+    (0x0E, PhysicalKey::KeyE),
+    (0x0F, PhysicalKey::KeyR),
+    (0x11, PhysicalKey::KeyT),
+    (0x10, PhysicalKey::KeyY),
+    (0x20, PhysicalKey::KeyU),
+    (0x22, PhysicalKey::KeyI),
+    (0x1F, PhysicalKey::KeyO),
+    (0x23, PhysicalKey::KeyP),
+    (0x00, PhysicalKey::KeyA),
+    (0x01, PhysicalKey::KeyS),
+    (0x02, PhysicalKey::KeyD),
+    (0x03, PhysicalKey::KeyF),
+    (0x05, PhysicalKey::KeyG),
+    (0x04, PhysicalKey::KeyH),
+    (0x26, PhysicalKey::KeyJ),
+    (0x28, PhysicalKey::KeyK),
+    (0x25, PhysicalKey::KeyL),
+    (0x06, PhysicalKey::KeyZ),
+    (0x07, PhysicalKey::KeyX),
+    (0x08, PhysicalKey::KeyC),
+    (0x09, PhysicalKey::KeyV),
+    (0x0B, PhysicalKey::KeyB),
+    (0x2D, PhysicalKey::KeyN),
+    (0x2E, PhysicalKey::KeyM),
+];
+
+impl PhysicalKey {
+    /// Converts a window system scancode to the key at that position.
+    ///
+    /// Only the digit and letter rows are known; anything else is `Unknown`.
+    pub fn from_scancode(scancode: u32) -> PhysicalKey {
+        SCANCODES
+            .iter()
+            .find(|&&(code, _)| code == scancode)
+            .map_or(PhysicalKey::Unknown(scancode), |&(_, key)| key)
+    }
+}
+
 /// Logical key representations (after keyboard layout processing).
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum LogicalKey {
@@ -526,6 +647,15 @@ mod tests {
         );
     }
 
+    #[test]
+    #[cfg(not(target_os = "macos"))]
+    fn test_physical_key_from_scancode() {
+        assert_eq!(PhysicalKey::from_scancode(2), PhysicalKey::Key1);
+        assert_eq!(PhysicalKey::from_scancode(16), PhysicalKey::KeyQ);
+        assert_eq!(PhysicalKey::from_scancode(47), PhysicalKey::KeyV);
+        assert_eq!(PhysicalKey::from_scancode(1), PhysicalKey::Unknown(1));
+    }
+
     #[test]
     fn test_pressed_keys_tracking() {
         let mut keyboard = KeyboardInput::null();
@@ -0,0 +1,256 @@
+//! Input latency measurement.
+//!
+//! A key press is timestamped as it moves through the input path: when the
+//! window event arrives, when the key mapper has resolved it, when it lands
+//! in the input state, and when the CPU first reads the key as pressed. The
+//! tracker turns those timestamps into per-key and per-stage statistics,
+//! which the CPU copies into its [`Metrics`](crate::emulator::Metrics).
+//!
+//! Taking timestamps needs `std`; without it only the statistics types are
+//! available.
+
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::time::Instant;
+
+use crate::hardware::ChipKey;
+
+/// Running statistics for one kind of latency.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LatencyStats {
+    /// Number of measurements.
+    pub samples: u64,
+
+    /// Sum of all measurements.
+    pub total: Duration,
+
+    /// Longest measurement.
+    pub max: Duration,
+
+    /// Most recent measurement.
+    pub last: Duration,
+}
+
+impl LatencyStats {
+    /// Adds a measurement.
+    pub fn record(&mut self, latency: Duration) {
+        self.samples += 1;
+        self.total += latency;
+        self.max = self.max.max(latency);
+        self.last = latency;
+    }
+
+    /// Gets the average measurement.
+    pub fn average(&self) -> Duration {
+        if self.samples == 0 {
+            return Duration::ZERO;
+        }
+        let nanos = self.total.as_nanos() / self.samples as u128;
+        Duration::from_nanos(nanos as u64)
+    }
+
+    /// Combines two sets of statistics.
+    ///
+    /// The most recent measurement is taken from `other` if it has any.
+    pub fn merge(&self, other: &LatencyStats) -> LatencyStats {
+        LatencyStats {
+            samples: self.samples + other.samples,
+            total: self.total + other.total,
+            max: self.max.max(other.max),
+            last: if other.samples > 0 {
+                other.last
+            } else {
+                self.last
+            },
+        }
+    }
+}
+
+/// Input latency statistics, per key and per stage of the input path.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InputLatency {
+    /// Time from the window event to the CPU reading the key, by key value.
+    pub keys: [LatencyStats; 16],
+
+    /// Time from the window event to the key mapper's result.
+    pub mapping: LatencyStats,
+
+    /// Time from the key mapper to the input state.
+    pub delivery: LatencyStats,
+
+    /// Time from the input state to the CPU reading the key.
+    pub polling: LatencyStats,
+
+    /// Most recently measured key.
+    pub last_key: Option<ChipKey>,
+}
+
+impl InputLatency {
+    /// Gets the end-to-end statistics for a key.
+    pub fn key(&self, key: ChipKey) -> &LatencyStats {
+        &self.keys[key.to_u8() as usize]
+    }
+
+    /// Gets the end-to-end statistics over all keys.
+    pub fn overall(&self) -> LatencyStats {
+        let merged = self
+            .keys
+            .iter()
+            .fold(LatencyStats::default(), |all, key| all.merge(key));
+
+        // Merging in key order loses which press was the latest
+        LatencyStats {
+            last: self
+                .last_key
+                .map_or(Duration::ZERO, |key| self.key(key).last),
+            ..merged
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+/// Timestamps taken by the frontend before a key reaches the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyStamps {
+    /// When the window event arrived.
+    pub event: Instant,
+
+    /// When the key mapper resolved the event to a key.
+    pub mapped: Instant,
+}
+
+#[cfg(feature = "std")]
+impl KeyStamps {
+    /// Creates timestamps for an event mapped just now.
+    pub fn mapped_now(event: Instant) -> Self {
+        Self {
+            event,
+            mapped: Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+/// A press waiting for the CPU to read it.
+#[derive(Debug, Clone, Copy)]
+struct PendingPress {
+    /// Frontend timestamps.
+    stamps: KeyStamps,
+
+    /// When the press reached the input state.
+    applied: Instant,
+}
+
+#[cfg(feature = "std")]
+/// Follows key presses from the window event until the CPU reads them.
+#[derive(Debug, Clone, Default)]
+pub struct LatencyTracker {
+    /// Presses not yet seen by the CPU, by key value.
+    pending: [Option<PendingPress>; 16],
+
+    /// Statistics for completed presses.
+    stats: InputLatency,
+}
+
+#[cfg(feature = "std")]
+impl LatencyTracker {
+    /// Creates a tracker with no measurements.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a press arriving in the input state.
+    ///
+    /// # Arguments
+    /// * `key` - Key that went down
+    /// * `stamps` - Frontend timestamps for the press
+    /// * `applied` - When the input state was updated
+    pub fn press(&mut self, key: ChipKey, stamps: KeyStamps, applied: Instant) {
+        self.pending[key.to_u8() as usize] = Some(PendingPress { stamps, applied });
+    }
+
+    /// Drops a press the CPU never read (e.g. the key was released first).
+    pub fn cancel(&mut self, key: ChipKey) {
+        self.pending[key.to_u8() as usize] = None;
+    }
+
+    /// Records the CPU reading a key as pressed, completing its measurement.
+    ///
+    /// # Returns
+    /// True if a pending press was completed.
+    pub fn seen(&mut self, key: ChipKey, now: Instant) -> bool {
+        let Some(press) = self.pending[key.to_u8() as usize].take() else {
+            return false;
+        };
+
+        let stamps = press.stamps;
+        self.stats
+            .mapping
+            .record(stamps.mapped.saturating_duration_since(stamps.event));
+        self.stats
+            .delivery
+            .record(press.applied.saturating_duration_since(stamps.mapped));
+        self.stats
+            .polling
+            .record(now.saturating_duration_since(press.applied));
+        self.stats.keys[key.to_u8() as usize].record(now.saturating_duration_since(stamps.event));
+        self.stats.last_key = Some(key);
+        true
+    }
+
+    /// Gets the statistics for completed presses.
+    pub fn stats(&self) -> InputLatency {
+        self.stats
+    }
+
+    /// Clears all measurements.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_press_is_measured_when_seen() {
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+        let stamps = KeyStamps {
+            event: start,
+            mapped: start + ms(1),
+        };
+
+        let mut tracker = LatencyTracker::new();
+        assert!(!tracker.seen(ChipKey::Key5, start));
+
+        tracker.press(ChipKey::Key5, stamps, start + ms(3));
+        assert!(tracker.seen(ChipKey::Key5, start + ms(10)));
+        // Only the first read after a press counts
+        assert!(!tracker.seen(ChipKey::Key5, start + ms(20)));
+
+        let stats = tracker.stats();
+        assert_eq!(stats.mapping.last, ms(1));
+        assert_eq!(stats.delivery.last, ms(2));
+        assert_eq!(stats.polling.last, ms(7));
+        assert_eq!(stats.key(ChipKey::Key5).last, ms(10));
+        assert_eq!(stats.key(ChipKey::Key4).samples, 0);
+        assert_eq!(stats.last_key, Some(ChipKey::Key5));
+
+        tracker.press(ChipKey::KeyA, stamps, start + ms(1));
+        tracker.cancel(ChipKey::KeyA);
+        assert!(!tracker.seen(ChipKey::KeyA, start + ms(5)));
+
+        tracker.press(ChipKey::Key0, stamps, start + ms(1));
+        tracker.seen(ChipKey::Key0, start + ms(30));
+        let overall = tracker.stats().overall();
+        assert_eq!(overall.samples, 2);
+        assert_eq!(overall.average(), ms(20));
+        assert_eq!(overall.max, ms(30));
+        assert_eq!(overall.last, ms(30));
+
+        tracker.reset();
+        assert_eq!(tracker.stats(), InputLatency::default());
+    }
+}
@@ -380,18 +380,433 @@ impl KeyMapper for CustomMapper {
     }
 }
 
+/// Keypad keys by physical position, in the COSMAC VIP's 4x4 arrangement.
+const KEYPAD_POSITIONS: [(PhysicalKey, ChipKey); 16] = [
+    (PhysicalKey::Key1, ChipKey::Key1),
+    (PhysicalKey::Key2, ChipKey::Key2),
+    (PhysicalKey::Key3, ChipKey::Key3),
+    (PhysicalKey::Key4, ChipKey::KeyC),
+    (PhysicalKey::KeyQ, ChipKey::Key4),
+    (PhysicalKey::KeyW, ChipKey::Key5),
+    (PhysicalKey::KeyE, ChipKey::Key6),
+    (PhysicalKey::KeyR, ChipKey::KeyD),
+    (PhysicalKey::KeyA, ChipKey::Key7),
+    (PhysicalKey::KeyS, ChipKey::Key8),
+    (PhysicalKey::KeyD, ChipKey::Key9),
+    (PhysicalKey::KeyF, ChipKey::KeyE),
+    (PhysicalKey::KeyZ, ChipKey::KeyA),
+    (PhysicalKey::KeyX, ChipKey::Key0),
+    (PhysicalKey::KeyC, ChipKey::KeyB),
+    (PhysicalKey::KeyV, ChipKey::KeyF),
+];
+
+/// Physical keys of the letter rows, left to right, top row first.
+const LETTER_POSITIONS: [PhysicalKey; 26] = [
+    PhysicalKey::KeyQ,
+    PhysicalKey::KeyW,
+    PhysicalKey::KeyE,
+    PhysicalKey::KeyR,
+    PhysicalKey::KeyT,
+    PhysicalKey::KeyY,
+    PhysicalKey::KeyU,
+    PhysicalKey::KeyI,
+    PhysicalKey::KeyO,
+    PhysicalKey::KeyP,
+    PhysicalKey::KeyA,
+    PhysicalKey::KeyS,
+    PhysicalKey::KeyD,
+    PhysicalKey::KeyF,
+    PhysicalKey::KeyG,
+    PhysicalKey::KeyH,
+    PhysicalKey::KeyJ,
+    PhysicalKey::KeyK,
+    PhysicalKey::KeyL,
+    PhysicalKey::KeyZ,
+    PhysicalKey::KeyX,
+    PhysicalKey::KeyC,
+    PhysicalKey::KeyV,
+    PhysicalKey::KeyB,
+    PhysicalKey::KeyN,
+    PhysicalKey::KeyM,
+];
+
+/// Physical keys of the digit row, left to right.
+const DIGIT_POSITIONS: [PhysicalKey; 10] = [
+    PhysicalKey::Key1,
+    PhysicalKey::Key2,
+    PhysicalKey::Key3,
+    PhysicalKey::Key4,
+    PhysicalKey::Key5,
+    PhysicalKey::Key6,
+    PhysicalKey::Key7,
+    PhysicalKey::Key8,
+    PhysicalKey::Key9,
+    PhysicalKey::Key0,
+];
+
+/// Keyboard layout the keypad keys are looked up in.
+///
+/// The keypad always sits on the same physical keys, the left of the top
+/// four rows; the layout says which characters those keys type, so the
+/// mapper can recognize them from layout-dependent key codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyboardLayout {
+    /// US and UK QWERTY.
+    #[default]
+    Qwerty,
+
+    /// French AZERTY.
+    Azerty,
+
+    /// German and Swiss QWERTZ.
+    Qwertz,
+
+    /// US Dvorak.
+    Dvorak,
+
+    /// Colemak.
+    Colemak,
+
+    /// Guessed from the environment, then corrected from key presses.
+    Auto,
+}
+
+impl KeyboardLayout {
+    /// Every concrete layout, in detection preference order.
+    pub const PRESETS: [KeyboardLayout; 5] = [
+        KeyboardLayout::Qwerty,
+        KeyboardLayout::Qwertz,
+        KeyboardLayout::Azerty,
+        KeyboardLayout::Colemak,
+        KeyboardLayout::Dvorak,
+    ];
+
+    /// Gets the layout's display name.
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyboardLayout::Qwerty => "QWERTY",
+            KeyboardLayout::Azerty => "AZERTY",
+            KeyboardLayout::Qwertz => "QWERTZ",
+            KeyboardLayout::Dvorak => "Dvorak",
+            KeyboardLayout::Colemak => "Colemak",
+            KeyboardLayout::Auto => "Auto",
+        }
+    }
+
+    /// Gets the characters typed by the keys in `LETTER_POSITIONS`.
+    fn letters(self) -> &'static str {
+        match self {
+            KeyboardLayout::Qwerty | KeyboardLayout::Auto => "qwertyuiopasdfghjklzxcvbnm",
+            KeyboardLayout::Azerty => "azertyuiopqsdfghjklwxcvbn,",
+            KeyboardLayout::Qwertz => "qwertzuiopasdfghjklyxcvbnm",
+            KeyboardLayout::Dvorak => "',.pyfgcrlaoeuidhtn;qjkxbm",
+            KeyboardLayout::Colemak => "qwfpgjluy;arstdhneizxcvbkm",
+        }
+    }
+
+    /// Gets the characters typed by the keys in `DIGIT_POSITIONS` without Shift.
+    fn digits(self) -> &'static str {
+        match self {
+            KeyboardLayout::Azerty => "&é\"'(-è_çà",
+            _ => "1234567890",
+        }
+    }
+
+    /// Gets the character a physical key types, if it's in the digit or letter rows.
+    pub fn character(self, key: PhysicalKey) -> Option<char> {
+        if let Some(index) = LETTER_POSITIONS.iter().position(|&k| k == key) {
+            return self.letters().chars().nth(index);
+        }
+        let index = DIGIT_POSITIONS.iter().position(|&k| k == key)?;
+        self.digits().chars().nth(index)
+    }
+
+    /// Recognizes an XKB layout and variant, like `fr` or `us` + `dvorak`.
+    pub fn from_xkb(layout: &str, variant: &str) -> Option<KeyboardLayout> {
+        // Only the first of a comma-separated group is active at startup
+        let layout = layout.split(',').next().unwrap_or_default().trim();
+        let variant = variant.split(',').next().unwrap_or_default().trim();
+        match (layout, variant) {
+            (_, "dvorak") => Some(KeyboardLayout::Dvorak),
+            (_, "colemak") => Some(KeyboardLayout::Colemak),
+            ("fr" | "be", _) => Some(KeyboardLayout::Azerty),
+            ("de" | "at" | "ch" | "cz" | "hu", _) => Some(KeyboardLayout::Qwertz),
+            ("us" | "gb" | "uk", _) => Some(KeyboardLayout::Qwerty),
+            _ => None,
+        }
+    }
+
+    /// Guesses the layout from `XKB_DEFAULT_LAYOUT` and `XKB_DEFAULT_VARIANT`.
+    ///
+    /// These are only set on some Linux desktops; elsewhere the guess is
+    /// `None` and [`LayoutDetector`] has to work it out from key presses.
+    pub fn detect() -> Option<KeyboardLayout> {
+        let layout = std::env::var("XKB_DEFAULT_LAYOUT").ok()?;
+        let variant = std::env::var("XKB_DEFAULT_VARIANT").unwrap_or_default();
+        Self::from_xkb(&layout, &variant)
+    }
+
+    /// Resolves `Auto` to the detected layout, or QWERTY if there's no guess.
+    pub fn resolve(self) -> KeyboardLayout {
+        match self {
+            KeyboardLayout::Auto => Self::detect().unwrap_or(KeyboardLayout::Qwerty),
+            layout => layout,
+        }
+    }
+}
+
+/// Gets the key code winit reports for a character, if it has one.
+fn character_keycode(character: char) -> Option<VirtualKeyCode> {
+    const LETTERS: [VirtualKeyCode; 26] = [
+        VirtualKeyCode::A,
+        VirtualKeyCode::B,
+        VirtualKeyCode::C,
+        VirtualKeyCode::D,
+        VirtualKeyCode::E,
+        VirtualKeyCode::F,
+        VirtualKeyCode::G,
+        VirtualKeyCode::H,
+        VirtualKeyCode::I,
+        VirtualKeyCode::J,
+        VirtualKeyCode::K,
+        VirtualKeyCode::L,
+        VirtualKeyCode::M,
+        VirtualKeyCode::N,
+        VirtualKeyCode::O,
+        VirtualKeyCode::P,
+        VirtualKeyCode::Q,
+        VirtualKeyCode::R,
+        VirtualKeyCode::S,
+        VirtualKeyCode::T,
+        VirtualKeyCode::U,
+        VirtualKeyCode::V,
+        VirtualKeyCode::W,
+        VirtualKeyCode::X,
+        VirtualKeyCode::Y,
+        VirtualKeyCode::Z,
+    ];
+    const DIGITS: [VirtualKeyCode; 10] = [
+        VirtualKeyCode::Key0,
+        VirtualKeyCode::Key1,
+        VirtualKeyCode::Key2,
+        VirtualKeyCode::Key3,
+        VirtualKeyCode::Key4,
+        VirtualKeyCode::Key5,
+        VirtualKeyCode::Key6,
+        VirtualKeyCode::Key7,
+        VirtualKeyCode::Key8,
+        VirtualKeyCode::Key9,
+    ];
+    match character {
+        'a'..='z' => Some(LETTERS[character as usize - 'a' as usize]),
+        '0'..='9' => Some(DIGITS[character as usize - '0' as usize]),
+        '\'' => Some(VirtualKeyCode::Apostrophe),
+        ',' => Some(VirtualKeyCode::Comma),
+        '.' => Some(VirtualKeyCode::Period),
+        ';' => Some(VirtualKeyCode::Semicolon),
+        _ => None,
+    }
+}
+
+/// Key mapper that keeps the keypad on the same physical keys in any layout.
+///
+/// Physical keys map directly; logical keys and key codes are translated
+/// through the layout, so on AZERTY the keypad's `4` is the key typing `A`.
+/// Digits map from the digit row whether or not Shift is needed to type them.
+pub struct LayoutMapper {
+    layout: KeyboardLayout,
+    physical_map: HashMap<PhysicalKey, ChipKey>,
+    logical_map: HashMap<LogicalKey, ChipKey>,
+    virtual_keycode_map: HashMap<VirtualKeyCode, ChipKey>,
+    description: String,
+}
+
+impl LayoutMapper {
+    /// Creates a mapper for a layout, resolving `Auto` first.
+    pub fn new(layout: KeyboardLayout) -> Self {
+        let layout = layout.resolve();
+        let mut physical_map = HashMap::new();
+        let mut logical_map = HashMap::new();
+        let mut virtual_keycode_map = HashMap::new();
+
+        for (physical, chip) in KEYPAD_POSITIONS {
+            physical_map.insert(physical, chip);
+            let Some(character) = layout.character(physical) else {
+                continue;
+            };
+            logical_map.insert(LogicalKey::Character(character), chip);
+            logical_map.insert(LogicalKey::Character(character.to_ascii_uppercase()), chip);
+            if let Some(keycode) = character_keycode(character) {
+                virtual_keycode_map.insert(keycode, chip);
+            }
+            // The digit row types digits somewhere, shifted or not
+            if let Some(index) = DIGIT_POSITIONS.iter().position(|&k| k == physical) {
+                let digit = KeyboardLayout::Qwerty.digits().chars().nth(index);
+                if let Some(digit) = digit {
+                    logical_map.insert(LogicalKey::Character(digit), chip);
+                    if let Some(keycode) = character_keycode(digit) {
+                        virtual_keycode_map.insert(keycode, chip);
+                    }
+                }
+            }
+        }
+
+        let rows: Vec<String> = KEYPAD_POSITIONS
+            .chunks(4)
+            .map(|row| {
+                let keys: Vec<String> = row
+                    .iter()
+                    .map(|&(physical, _)| {
+                        layout
+                            .character(physical)
+                            .map_or('?', |c| c.to_ascii_uppercase())
+                            .to_string()
+                    })
+                    .collect();
+                let chips: Vec<String> = row
+                    .iter()
+                    .map(|&(_, chip)| format!("{:X}", chip.to_u8()))
+                    .collect();
+                format!("{} -> {}", keys.join(" "), chips.join(" "))
+            })
+            .collect();
+        let description = format!("{} keyboard layout:\n{}", layout.name(), rows.join("\n"));
+
+        Self {
+            layout,
+            physical_map,
+            logical_map,
+            virtual_keycode_map,
+            description,
+        }
+    }
+
+    /// Gets the layout the mapper was built for.
+    pub fn layout(&self) -> KeyboardLayout {
+        self.layout
+    }
+}
+
+impl KeyMapper for LayoutMapper {
+    fn map_key_event(&self, event: &KeyboardEvent) -> Option<ChipKey> {
+        // The physical key is layout independent, so it goes first here
+        self.map_physical_key(event.physical_key)
+            .or_else(|| self.map_logical_key(&event.logical_key))
+    }
+
+    fn map_physical_key(&self, key: PhysicalKey) -> Option<ChipKey> {
+        self.physical_map.get(&key).copied()
+    }
+
+    fn map_logical_key(&self, key: &LogicalKey) -> Option<ChipKey> {
+        self.logical_map.get(key).copied()
+    }
+
+    fn map_virtual_keycode(&self, key: VirtualKeyCode) -> Option<ChipKey> {
+        self.virtual_keycode_map.get(&key).copied()
+    }
+
+    fn get_physical_mappings(&self) -> Vec<(PhysicalKey, ChipKey)> {
+        self.physical_map.iter().map(|(&k, &v)| (k, v)).collect()
+    }
+
+    fn name(&self) -> &str {
+        self.layout.name()
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+}
+
+/// Works out the keyboard layout from the keys the user presses.
+///
+/// Each press of a letter key pairs a physical scancode with the key code
+/// the layout turned it into, which rules out layouts that would have typed
+/// something else there. When the current layout is ruled out, the detector
+/// switches to the first one still possible.
+#[derive(Debug, Clone)]
+pub struct LayoutDetector {
+    /// Layout currently assumed.
+    current: KeyboardLayout,
+
+    /// Layouts that agree with every press seen so far.
+    candidates: Vec<KeyboardLayout>,
+}
+
+impl LayoutDetector {
+    /// Creates a detector starting from an assumed layout.
+    pub fn new(initial: KeyboardLayout) -> Self {
+        Self {
+            current: initial.resolve(),
+            candidates: KeyboardLayout::PRESETS.to_vec(),
+        }
+    }
+
+    /// Gets the layout currently assumed.
+    pub fn layout(&self) -> KeyboardLayout {
+        self.current
+    }
+
+    /// Gets the layouts that agree with every press seen so far.
+    pub fn candidates(&self) -> &[KeyboardLayout] {
+        &self.candidates
+    }
+
+    /// Narrows the candidates with a key press.
+    ///
+    /// Presses outside the letter rows tell nothing and are ignored, as is a
+    /// press no known layout explains.
+    ///
+    /// # Returns
+    /// The new layout if the current one was ruled out.
+    pub fn observe(&mut self, scancode: u32, key: VirtualKeyCode) -> Option<KeyboardLayout> {
+        let physical = PhysicalKey::from_scancode(scancode);
+        if !LETTER_POSITIONS.contains(&physical) {
+            return None;
+        }
+        let remaining: Vec<KeyboardLayout> = self
+            .candidates
+            .iter()
+            .copied()
+            .filter(|layout| layout.character(physical).and_then(character_keycode) == Some(key))
+            .collect();
+        if remaining.is_empty() {
+            return None;
+        }
+        self.candidates = remaining;
+        if self.candidates.contains(&self.current) {
+            return None;
+        }
+        self.current = self.candidates[0];
+        Some(self.current)
+    }
+}
+
 /// Creates a mapper from a configuration string.
 pub fn create_mapper_from_config(config: &str) -> Result<Box<dyn KeyMapper>, String> {
     match config.to_lowercase().as_str() {
         "qwerty" | "standard" => Ok(Box::new(QwertyMapper::new())),
         "alternative" | "alt" => Ok(Box::new(AlternativeQwertyMapper::new())),
+        "azerty" => Ok(Box::new(LayoutMapper::new(KeyboardLayout::Azerty))),
+        "qwertz" => Ok(Box::new(LayoutMapper::new(KeyboardLayout::Qwertz))),
+        "dvorak" => Ok(Box::new(LayoutMapper::new(KeyboardLayout::Dvorak))),
+        "colemak" => Ok(Box::new(LayoutMapper::new(KeyboardLayout::Colemak))),
         _ => Err(format!("Unknown mapper configuration: {}", config)),
     }
 }
 
 /// Gets all available mapper names.
 pub fn get_available_mappers() -> Vec<&'static str> {
-    vec!["qwerty", "alternative"]
+    vec![
+        "qwerty",
+        "alternative",
+        "azerty",
+        "qwertz",
+        "dvorak",
+        "colemak",
+    ]
 }
 
 #[cfg(test)]
@@ -541,6 +956,99 @@ mod tests {
         assert!(invalid.is_err());
     }
 
+    #[test]
+    fn test_layout_mappers() {
+        // The keypad stays on the same physical keys in every layout
+        for layout in KeyboardLayout::PRESETS {
+            let mapper = LayoutMapper::new(layout);
+            assert_eq!(mapper.get_physical_mappings().len(), 16);
+            assert_eq!(
+                mapper.map_physical_key(PhysicalKey::KeyQ),
+                Some(ChipKey::Key4)
+            );
+        }
+
+        let azerty = LayoutMapper::new(KeyboardLayout::Azerty);
+        assert_eq!(
+            azerty.map_virtual_keycode(VirtualKeyCode::A),
+            Some(ChipKey::Key4)
+        );
+        assert_eq!(
+            azerty.map_virtual_keycode(VirtualKeyCode::Q),
+            Some(ChipKey::Key7)
+        );
+        assert_eq!(
+            azerty.map_virtual_keycode(VirtualKeyCode::W),
+            Some(ChipKey::KeyA)
+        );
+        assert_eq!(
+            azerty.map_virtual_keycode(VirtualKeyCode::Key1),
+            Some(ChipKey::Key1)
+        );
+        assert_eq!(
+            azerty.map_logical_key(&LogicalKey::Character('é')),
+            Some(ChipKey::Key2)
+        );
+
+        let dvorak = LayoutMapper::new(KeyboardLayout::Dvorak);
+        assert_eq!(
+            dvorak.map_virtual_keycode(VirtualKeyCode::Apostrophe),
+            Some(ChipKey::Key4)
+        );
+        assert_eq!(
+            dvorak.map_virtual_keycode(VirtualKeyCode::O),
+            Some(ChipKey::Key8)
+        );
+        assert_eq!(
+            dvorak.map_virtual_keycode(VirtualKeyCode::Q),
+            Some(ChipKey::Key0)
+        );
+        assert!(dvorak.description().contains("' , . P -> 4 5 6 D"));
+
+        let colemak = LayoutMapper::new(KeyboardLayout::Colemak);
+        assert_eq!(
+            colemak.map_virtual_keycode(VirtualKeyCode::T),
+            Some(ChipKey::KeyE)
+        );
+        assert_eq!(colemak.map_virtual_keycode(VirtualKeyCode::E), None);
+    }
+
+    #[test]
+    #[cfg(not(target_os = "macos"))]
+    fn test_layout_detection() {
+        assert_eq!(
+            KeyboardLayout::from_xkb("fr", ""),
+            Some(KeyboardLayout::Azerty)
+        );
+        assert_eq!(
+            KeyboardLayout::from_xkb("us,de", "dvorak,"),
+            Some(KeyboardLayout::Dvorak)
+        );
+        assert_eq!(KeyboardLayout::from_xkb("jp", ""), None);
+
+        let mut detector = LayoutDetector::new(KeyboardLayout::Qwerty);
+        // Q typing Q fits QWERTY, QWERTZ and Colemak
+        assert_eq!(detector.observe(16, VirtualKeyCode::Q), None);
+        assert_eq!(detector.candidates().len(), 3);
+        // Digits and unexplained presses are ignored
+        assert_eq!(detector.observe(2, VirtualKeyCode::Key1), None);
+        assert_eq!(detector.observe(16, VirtualKeyCode::F12), None);
+        assert_eq!(detector.candidates().len(), 3);
+        // Z typing Y rules QWERTY out
+        assert_eq!(
+            detector.observe(44, VirtualKeyCode::Y),
+            Some(KeyboardLayout::Qwertz)
+        );
+        assert_eq!(detector.candidates(), [KeyboardLayout::Qwertz]);
+
+        let mut detector = LayoutDetector::new(KeyboardLayout::Qwerty);
+        assert_eq!(
+            detector.observe(16, VirtualKeyCode::A),
+            Some(KeyboardLayout::Azerty)
+        );
+        assert_eq!(detector.layout(), KeyboardLayout::Azerty);
+    }
+
     #[test]
     fn test_available_mappers() {
         let mappers = get_available_mappers();
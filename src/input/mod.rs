@@ -2,20 +2,51 @@
 //!
 //! This module provides real keyboard input handling for the Chip-8
 //! hexadecimal keypad using various input backends.
+//!
+//! Without `std` only keypad rollover and the latency statistics types are
+//! available, for use by the core's `hardware::Input` types.
 
+#[cfg(feature = "std")]
+pub mod idle;
+#[cfg(feature = "std")]
 pub mod keyboard;
+pub mod latency;
+#[cfg(feature = "std")]
 pub mod mapper;
+pub mod rollover;
+#[cfg(feature = "std")]
+pub mod virtual_keypad;
 
 // Re-export commonly used types
+#[cfg(feature = "std")]
+pub use idle::IdleTracker;
+#[cfg(feature = "std")]
 pub use keyboard::{KeyboardConfig, KeyboardInput};
-pub use mapper::{CustomMapper, KeyMapper, QwertyMapper};
-
+pub use latency::{InputLatency, LatencyStats};
+#[cfg(feature = "std")]
+pub use latency::{KeyStamps, LatencyTracker};
+#[cfg(feature = "std")]
+pub use mapper::{
+    CustomMapper, KeyMapper, KeyboardLayout, LayoutDetector, LayoutMapper, QwertyMapper,
+};
+pub use rollover::{HeldKeys, KeyRollover};
+#[cfg(feature = "std")]
+pub use virtual_keypad::{
+    KeypadLayout, KeypadPosition, Pointer, VirtualKeypad, VirtualKeypadConfig,
+};
+
+#[cfg(feature = "std")]
 use crate::error::InputError;
+#[cfg(feature = "std")]
 use crate::hardware::{ChipKey, Input, InputResult};
+#[cfg(feature = "std")]
+use std::time::Instant;
 
+#[cfg(feature = "std")]
 /// Result type for input operations.
 pub type InputSystemResult<T> = Result<T, InputError>;
 
+#[cfg(feature = "std")]
 /// Complete input system that implements the Input trait with real keyboard handling.
 pub struct InputSystem {
     /// The keyboard input handler.
@@ -35,8 +66,18 @@ pub struct InputSystem {
 
     /// Whether to capture keyboard events.
     capture_enabled: bool,
+
+    /// Keys physically held, limited by the keypad rollover.
+    held: HeldKeys,
+
+    /// Latency of presses from keyboard events.
+    latency: LatencyTracker,
+
+    /// When input was last seen.
+    idle: IdleTracker,
 }
 
+#[cfg(feature = "std")]
 impl InputSystem {
     /// Creates a new input system.
     pub fn new() -> InputSystemResult<Self> {
@@ -50,6 +91,9 @@ impl InputSystem {
             keys_pressed_this_frame: std::collections::HashSet::new(),
             keys_released_this_frame: std::collections::HashSet::new(),
             capture_enabled: true,
+            held: HeldKeys::default(),
+            latency: LatencyTracker::new(),
+            idle: IdleTracker::default(),
         })
     }
 
@@ -58,6 +102,7 @@ impl InputSystem {
         keyboard_config: KeyboardConfig,
         mapper: Box<dyn KeyMapper>,
     ) -> InputSystemResult<Self> {
+        let held = HeldKeys::new(keyboard_config.rollover);
         let keyboard = KeyboardInput::new(keyboard_config)?;
 
         Ok(Self {
@@ -67,6 +112,9 @@ impl InputSystem {
             keys_pressed_this_frame: std::collections::HashSet::new(),
             keys_released_this_frame: std::collections::HashSet::new(),
             capture_enabled: true,
+            held,
+            latency: LatencyTracker::new(),
+            idle: IdleTracker::default(),
         })
     }
 
@@ -98,20 +146,21 @@ impl InputSystem {
         if !self.capture_enabled {
             return Ok(());
         }
+        // Any key counts as someone being there, mapped or not
+        self.idle.touch(Instant::now());
 
         if let Some(chip_key) = self.mapper.map_key_event(event) {
+            let stamps = KeyStamps::mapped_now(event.timestamp);
             match event.state {
                 keyboard::KeyState::Pressed => {
-                    if !self.pressed_keys.contains(&chip_key) {
-                        self.keys_pressed_this_frame.insert(chip_key);
+                    if !self.held.is_held(chip_key) {
+                        self.latency.press(chip_key, stamps, Instant::now());
                     }
-                    self.pressed_keys.insert(chip_key);
+                    self.simulate_key_press(chip_key);
                 }
                 keyboard::KeyState::Released => {
-                    if self.pressed_keys.contains(&chip_key) {
-                        self.keys_released_this_frame.insert(chip_key);
-                    }
-                    self.pressed_keys.remove(&chip_key);
+                    self.simulate_key_release(chip_key);
+                    self.latency.cancel(chip_key);
                 }
             }
         }
@@ -119,20 +168,39 @@ impl InputSystem {
         Ok(())
     }
 
+    /// Sets how many held keys are registered at once.
+    pub fn set_rollover(&mut self, rollover: KeyRollover) {
+        self.held.set_rollover(rollover);
+        self.sync_held();
+    }
+
+    /// Gets how many held keys are registered at once.
+    pub fn rollover(&self) -> KeyRollover {
+        self.held.rollover()
+    }
+
     /// Simulates a key press (for testing or external control).
     pub fn simulate_key_press(&mut self, key: ChipKey) {
-        if !self.pressed_keys.contains(&key) {
-            self.keys_pressed_this_frame.insert(key);
-        }
-        self.pressed_keys.insert(key);
+        self.idle.touch(Instant::now());
+        self.held.press(key);
+        self.sync_held();
     }
 
     /// Simulates a key release (for testing or external control).
     pub fn simulate_key_release(&mut self, key: ChipKey) {
-        if self.pressed_keys.contains(&key) {
-            self.keys_released_this_frame.insert(key);
-        }
-        self.pressed_keys.remove(&key);
+        self.held.release(key);
+        self.sync_held();
+    }
+
+    /// Registers the held keys the rollover allows, noting keys that changed.
+    fn sync_held(&mut self) {
+        let registered: std::collections::HashSet<ChipKey> =
+            self.held.registered().iter().copied().collect();
+        self.keys_pressed_this_frame
+            .extend(registered.difference(&self.pressed_keys));
+        self.keys_released_this_frame
+            .extend(self.pressed_keys.difference(&registered));
+        self.pressed_keys = registered;
     }
 
     /// Gets all keys pressed this frame.
@@ -155,12 +223,23 @@ impl InputSystem {
         self.keys_released_this_frame.contains(&key)
     }
 
+    /// Gets how long there has been no keyboard input as of `now`.
+    pub fn idle_for(&self, now: Instant) -> std::time::Duration {
+        self.idle.idle_for(now)
+    }
+
+    /// Gets when keyboard input was last seen.
+    pub fn last_input(&self) -> Instant {
+        self.idle.last_input()
+    }
+
     /// Gets the keyboard configuration.
     pub fn keyboard_config(&self) -> &KeyboardConfig {
         self.keyboard.config()
     }
 }
 
+#[cfg(feature = "std")]
 impl Default for InputSystem {
     fn default() -> Self {
         Self::new().unwrap_or_else(|_| {
@@ -175,11 +254,15 @@ impl Default for InputSystem {
                 keys_pressed_this_frame: std::collections::HashSet::new(),
                 keys_released_this_frame: std::collections::HashSet::new(),
                 capture_enabled: true,
+                held: HeldKeys::default(),
+                latency: LatencyTracker::new(),
+                idle: IdleTracker::default(),
             }
         })
     }
 }
 
+#[cfg(feature = "std")]
 impl Input for InputSystem {
     fn is_key_pressed(&self, key: ChipKey) -> bool {
         self.pressed_keys.contains(&key)
@@ -203,13 +286,23 @@ impl Input for InputSystem {
 
         Ok(())
     }
+
+    fn key_seen(&mut self, key: ChipKey) {
+        self.latency.seen(key, Instant::now());
+    }
+
+    fn latency(&self) -> InputLatency {
+        self.latency.stats()
+    }
 }
 
+#[cfg(feature = "std")]
 /// Simple input system for headless operation and testing.
 pub struct NullInputSystem {
     input: crate::hardware::input::NullInput,
 }
 
+#[cfg(feature = "std")]
 impl NullInputSystem {
     pub fn new() -> Self {
         Self {
@@ -228,12 +321,14 @@ impl NullInputSystem {
     }
 }
 
+#[cfg(feature = "std")]
 impl Default for NullInputSystem {
     fn default() -> Self {
         Self::new()
     }
 }
 
+#[cfg(feature = "std")]
 impl Input for NullInputSystem {
     fn is_key_pressed(&self, key: ChipKey) -> bool {
         self.input.is_key_pressed(key)
@@ -252,7 +347,7 @@ impl Input for NullInputSystem {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use super::*;
 
@@ -274,6 +369,17 @@ mod tests {
         assert!(system.is_capture_enabled());
     }
 
+    #[test]
+    fn test_input_system_idle() {
+        let mut system = InputSystem::default();
+        let later = Instant::now() + std::time::Duration::from_secs(5);
+        assert!(system.idle_for(later) >= std::time::Duration::from_secs(5));
+
+        system.simulate_key_press(ChipKey::Key1);
+        assert!(system.last_input() <= Instant::now());
+        assert!(system.idle_for(system.last_input()).is_zero());
+    }
+
     #[test]
     fn test_input_system_simulation() {
         let mut system = InputSystem::default();
@@ -317,6 +423,51 @@ mod tests {
         assert!(system.is_capture_enabled());
     }
 
+    #[test]
+    fn test_input_system_measures_latency() {
+        use keyboard::{KeyState, LogicalKey, PhysicalKey};
+
+        let mut system = InputSystem::default();
+        let event = keyboard::KeyboardEvent {
+            physical_key: PhysicalKey::KeyW,
+            logical_key: LogicalKey::Character('w'),
+            state: KeyState::Pressed,
+            is_repeat: false,
+            timestamp: Instant::now(),
+        };
+        system.process_keyboard_event(&event).unwrap();
+        assert!(system.is_key_pressed(ChipKey::Key5));
+        assert_eq!(system.latency().key(ChipKey::Key5).samples, 0);
+
+        system.key_seen(ChipKey::Key5);
+        let latency = system.latency();
+        assert_eq!(latency.key(ChipKey::Key5).samples, 1);
+        assert_eq!(latency.polling.samples, 1);
+        assert!(latency.key(ChipKey::Key5).last >= latency.polling.last);
+    }
+
+    #[test]
+    fn test_input_system_rollover() {
+        let config = KeyboardConfig::new().with_rollover(KeyRollover::Max(2));
+        let mut system = InputSystem::with_config(config, Box::new(QwertyMapper::new())).unwrap();
+        assert_eq!(system.rollover(), KeyRollover::Max(2));
+
+        system.simulate_key_press(ChipKey::Key1);
+        system.simulate_key_press(ChipKey::Key2);
+        system.simulate_key_press(ChipKey::Key3);
+        assert!(system.is_key_pressed(ChipKey::Key2));
+        assert!(!system.is_key_pressed(ChipKey::Key3));
+        assert!(!system.was_key_just_pressed(ChipKey::Key3));
+
+        // Letting go of an older key makes room for the held one
+        system.simulate_key_release(ChipKey::Key1);
+        assert!(system.is_key_pressed(ChipKey::Key3));
+        assert!(system.was_key_just_released(ChipKey::Key1));
+
+        system.set_rollover(KeyRollover::Single);
+        assert_eq!(system.get_pressed_keys(), vec![ChipKey::Key2]);
+    }
+
     #[test]
     fn test_null_input_system() {
         let mut system = NullInputSystem::new();
@@ -0,0 +1,140 @@
+//! Keypad rollover simulation.
+//!
+//! The original hex keypads were scanned one key at a time, so programs
+//! could only ever see a single key down. Modern keyboards report many
+//! keys at once, which some historical ROMs never expected. [`HeldKeys`]
+//! tracks every key physically held and registers only as many of them as
+//! the configured [`KeyRollover`] allows, oldest press first.
+
+use alloc::vec::Vec;
+
+use crate::hardware::ChipKey;
+
+/// How many keys the keypad registers at the same time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum KeyRollover {
+    /// Every held key is registered.
+    #[default]
+    Unlimited,
+
+    /// Only the first held key is registered, like the original keypad.
+    Single,
+
+    /// At most this many held keys are registered.
+    Max(u8),
+}
+
+impl KeyRollover {
+    /// Gets the most keys registered at once, or `None` if unlimited.
+    pub fn limit(self) -> Option<usize> {
+        match self {
+            KeyRollover::Unlimited => None,
+            KeyRollover::Single => Some(1),
+            KeyRollover::Max(max) => Some(max as usize),
+        }
+    }
+}
+
+/// Keys physically held, in the order they were pressed.
+#[derive(Debug, Clone, Default)]
+pub struct HeldKeys {
+    /// How many held keys are registered.
+    rollover: KeyRollover,
+
+    /// Held keys, oldest press first.
+    held: Vec<ChipKey>,
+}
+
+impl HeldKeys {
+    /// Creates an empty set of held keys.
+    pub fn new(rollover: KeyRollover) -> Self {
+        Self {
+            rollover,
+            held: Vec::new(),
+        }
+    }
+
+    /// Gets the rollover limit.
+    pub fn rollover(&self) -> KeyRollover {
+        self.rollover
+    }
+
+    /// Sets the rollover limit.
+    pub fn set_rollover(&mut self, rollover: KeyRollover) {
+        self.rollover = rollover;
+    }
+
+    /// Checks if a key is physically held, registered or not.
+    pub fn is_held(&self, key: ChipKey) -> bool {
+        self.held.contains(&key)
+    }
+
+    /// Records a key going down. Repeats of a held key are ignored.
+    pub fn press(&mut self, key: ChipKey) {
+        if !self.held.contains(&key) {
+            self.held.push(key);
+        }
+    }
+
+    /// Records a key going up.
+    pub fn release(&mut self, key: ChipKey) {
+        self.held.retain(|&held| held != key);
+    }
+
+    /// Releases every key.
+    pub fn clear(&mut self) {
+        self.held.clear();
+    }
+
+    /// Gets the held keys the keypad registers.
+    ///
+    /// When more keys are held than the limit allows, the earliest presses
+    /// win; releasing one of them lets the next held key through.
+    pub fn registered(&self) -> &[ChipKey] {
+        let count = self
+            .rollover
+            .limit()
+            .map_or(self.held.len(), |limit| limit.min(self.held.len()));
+        &self.held[..count]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_key_rollover() {
+        let mut keys = HeldKeys::new(KeyRollover::Single);
+        keys.press(ChipKey::Key1);
+        keys.press(ChipKey::Key2);
+        keys.press(ChipKey::Key1);
+        assert_eq!(keys.registered(), &[ChipKey::Key1]);
+
+        // The next held key registers once the first is let go
+        keys.release(ChipKey::Key1);
+        assert_eq!(keys.registered(), &[ChipKey::Key2]);
+
+        keys.set_rollover(KeyRollover::Unlimited);
+        keys.press(ChipKey::Key3);
+        assert_eq!(keys.registered(), &[ChipKey::Key2, ChipKey::Key3]);
+
+        keys.set_rollover(KeyRollover::Max(0));
+        assert!(keys.registered().is_empty());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_rollover_serde() {
+        #[derive(serde::Deserialize)]
+        struct Wrapper {
+            rollover: KeyRollover,
+        }
+
+        let parsed: Wrapper = toml::from_str("rollover = { Max = 2 }").unwrap();
+        assert_eq!(parsed.rollover.limit(), Some(2));
+        let parsed: Wrapper = toml::from_str(r#"rollover = "Single""#).unwrap();
+        assert_eq!(parsed.rollover, KeyRollover::Single);
+        assert_eq!(KeyRollover::default().limit(), None);
+    }
+}
@@ -0,0 +1,386 @@
+//! Clickable on-screen keypad.
+//!
+//! For laptops and touchscreens without a convenient keyboard, the GUI can
+//! draw a 4x4 hex keypad over the game. Mouse clicks and touches are
+//! hit-tested against it in frame-buffer coordinates and turned into key
+//! presses on the emulator's input.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::graphics::font::{draw_text, fill_rect, GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::graphics::Color;
+use crate::hardware::input::SoftwareInput;
+use crate::hardware::ChipKey;
+
+/// Smallest key size that fits a label and border.
+pub const MIN_KEY_SIZE: u32 = GLYPH_HEIGHT as u32 + 4;
+
+/// Largest key size.
+pub const MAX_KEY_SIZE: u32 = 30;
+
+/// Gap between the keypad and the frame edge, and between keys.
+const SPACING: usize = 2;
+
+/// How keys are arranged on the keypad.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeypadLayout {
+    /// The COSMAC VIP keypad: 1 2 3 C / 4 5 6 D / 7 8 9 E / A 0 B F.
+    #[default]
+    Cosmac,
+
+    /// Keys in numeric order: 0 1 2 3 / 4 5 6 7 / 8 9 A B / C D E F.
+    Sequential,
+}
+
+impl KeypadLayout {
+    /// Gets the key values in this layout, top row first.
+    pub fn rows(&self) -> [[u8; 4]; 4] {
+        match self {
+            KeypadLayout::Cosmac => [
+                [0x1, 0x2, 0x3, 0xC],
+                [0x4, 0x5, 0x6, 0xD],
+                [0x7, 0x8, 0x9, 0xE],
+                [0xA, 0x0, 0xB, 0xF],
+            ],
+            KeypadLayout::Sequential => [
+                [0x0, 0x1, 0x2, 0x3],
+                [0x4, 0x5, 0x6, 0x7],
+                [0x8, 0x9, 0xA, 0xB],
+                [0xC, 0xD, 0xE, 0xF],
+            ],
+        }
+    }
+}
+
+/// Corner of the window the keypad sits in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeypadPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+/// On-screen keypad configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VirtualKeypadConfig {
+    /// Whether the keypad is shown at startup.
+    pub visible: bool,
+
+    /// How keys are arranged.
+    pub layout: KeypadLayout,
+
+    /// Corner of the window the keypad sits in.
+    pub position: KeypadPosition,
+
+    /// Width and height of each key in frame-buffer pixels.
+    pub key_size: u32,
+}
+
+impl Default for VirtualKeypadConfig {
+    fn default() -> Self {
+        Self {
+            visible: false,
+            layout: KeypadLayout::default(),
+            position: KeypadPosition::default(),
+            key_size: 12,
+        }
+    }
+}
+
+/// Identifies a mouse or touch contact.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Pointer {
+    /// The mouse.
+    Mouse,
+
+    /// A touch contact, by the id the window system gave it.
+    Touch(u64),
+}
+
+/// A clickable keypad drawn into a frame buffer.
+#[derive(Debug, Clone)]
+pub struct VirtualKeypad {
+    /// Layout and visibility settings.
+    config: VirtualKeypadConfig,
+
+    /// Whether the keypad is shown and accepts input.
+    visible: bool,
+
+    /// Left edge of the keypad in the frame.
+    left: usize,
+
+    /// Top edge of the keypad in the frame.
+    top: usize,
+
+    /// Key held by each pointer that is down on the keypad.
+    held: HashMap<Pointer, ChipKey>,
+}
+
+impl VirtualKeypad {
+    /// Creates a keypad for a frame of the given size.
+    pub fn new(config: VirtualKeypadConfig, frame_width: usize, frame_height: usize) -> Self {
+        let size = Self::grid_size(&config);
+        let right = frame_width.saturating_sub(SPACING + size);
+        let bottom = frame_height.saturating_sub(SPACING + size);
+        let (left, top) = match config.position {
+            KeypadPosition::TopLeft => (SPACING, SPACING),
+            KeypadPosition::TopRight => (right, SPACING),
+            KeypadPosition::BottomLeft => (SPACING, bottom),
+            KeypadPosition::BottomRight => (right, bottom),
+        };
+
+        Self {
+            visible: config.visible,
+            config,
+            left,
+            top,
+            held: HashMap::new(),
+        }
+    }
+
+    /// Gets the width and height of the whole keypad in pixels.
+    fn grid_size(config: &VirtualKeypadConfig) -> usize {
+        4 * config.key_size as usize + 3 * SPACING
+    }
+
+    /// Checks if the keypad is shown.
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Shows or hides the keypad.
+    ///
+    /// Hiding it releases any keys it was holding.
+    pub fn set_visible(&mut self, visible: bool, input: &mut SoftwareInput) {
+        self.visible = visible;
+        if !visible {
+            for (_, key) in self.held.drain() {
+                input.release_key(key);
+            }
+        }
+    }
+
+    /// Finds the key under a frame-buffer position.
+    pub fn key_at(&self, x: usize, y: usize) -> Option<ChipKey> {
+        let stride = self.config.key_size as usize + SPACING;
+        let column = x.checked_sub(self.left)?;
+        let row = y.checked_sub(self.top)?;
+        let inside_key = |offset: usize| offset % stride < self.config.key_size as usize;
+        if column / stride >= 4 || row / stride >= 4 || !inside_key(column) || !inside_key(row) {
+            return None;
+        }
+        ChipKey::from_u8(self.config.layout.rows()[row / stride][column / stride])
+    }
+
+    /// Handles a pointer going down, pressing the key under it.
+    ///
+    /// # Returns
+    /// True if the pointer landed on the keypad.
+    pub fn pointer_down(
+        &mut self,
+        pointer: Pointer,
+        position: Option<(usize, usize)>,
+        input: &mut SoftwareInput,
+    ) -> bool {
+        if !self.visible {
+            return false;
+        }
+        let Some(key) = position.and_then(|(x, y)| self.key_at(x, y)) else {
+            return false;
+        };
+        self.hold(pointer, key, input);
+        true
+    }
+
+    /// Handles a pointer moving, following it from key to key while it is down.
+    pub fn pointer_moved(
+        &mut self,
+        pointer: Pointer,
+        position: Option<(usize, usize)>,
+        input: &mut SoftwareInput,
+    ) {
+        if !self.held.contains_key(&pointer) {
+            return;
+        }
+        match position.and_then(|(x, y)| self.key_at(x, y)) {
+            Some(key) => self.hold(pointer, key, input),
+            None => self.pointer_up(pointer, input),
+        }
+    }
+
+    /// Handles a pointer going up, releasing the key it held.
+    pub fn pointer_up(&mut self, pointer: Pointer, input: &mut SoftwareInput) {
+        if let Some(key) = self.held.remove(&pointer) {
+            if !self.held.values().any(|&other| other == key) {
+                input.release_key(key);
+            }
+        }
+    }
+
+    /// Moves a pointer's hold to a key, releasing the one it held before.
+    fn hold(&mut self, pointer: Pointer, key: ChipKey, input: &mut SoftwareInput) {
+        if self.held.get(&pointer) == Some(&key) {
+            return;
+        }
+        self.pointer_up(pointer, input);
+        self.held.insert(pointer, key);
+        input.press_key(key);
+    }
+
+    /// Draws the keypad into an RGBA frame if it is visible.
+    ///
+    /// # Arguments
+    /// * `frame` - RGBA pixel data, four bytes per pixel
+    /// * `width` - Frame width in pixels
+    /// * `pressed` - Keys to draw as held, from any input source
+    /// * `foreground` - Key border and label color
+    /// * `background` - Key face color
+    pub fn render(
+        &self,
+        frame: &mut [u8],
+        width: usize,
+        pressed: &[ChipKey],
+        foreground: Color,
+        background: Color,
+    ) {
+        if !self.visible {
+            return;
+        }
+
+        let size = self.config.key_size as usize;
+        for (row, keys) in self.config.layout.rows().iter().enumerate() {
+            for (column, &value) in keys.iter().enumerate() {
+                let x = self.left + column * (size + SPACING);
+                let y = self.top + row * (size + SPACING);
+                let down = pressed.iter().any(|key| key.to_u8() == value);
+                let (face, label) = if down {
+                    (foreground, background)
+                } else {
+                    (background, foreground)
+                };
+
+                fill_rect(frame, width, x, y, size, size, foreground);
+                fill_rect(frame, width, x + 1, y + 1, size - 2, size - 2, face);
+                let text = format!("{:X}", value);
+                let text_x = x + (size - GLYPH_WIDTH) / 2;
+                let text_y = y + (size - GLYPH_HEIGHT) / 2;
+                draw_text(frame, width, text_x, text_y, &text, label);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hardware::Input;
+
+    const WIDTH: usize = 256;
+    const HEIGHT: usize = 128;
+
+    fn test_keypad(position: KeypadPosition, layout: KeypadLayout) -> VirtualKeypad {
+        let config = VirtualKeypadConfig {
+            visible: true,
+            layout,
+            position,
+            key_size: 10,
+        };
+        VirtualKeypad::new(config, WIDTH, HEIGHT)
+    }
+
+    #[test]
+    fn test_hit_testing() {
+        // Grid is 4 * 10 + 3 * 2 = 46 pixels, 2 from the top left
+        let keypad = test_keypad(KeypadPosition::TopLeft, KeypadLayout::Cosmac);
+
+        assert_eq!(keypad.key_at(2, 2), Some(ChipKey::Key1));
+        assert_eq!(keypad.key_at(11, 11), Some(ChipKey::Key1));
+        assert_eq!(keypad.key_at(12, 2), None); // gap between keys
+        assert_eq!(keypad.key_at(14, 2), Some(ChipKey::Key2));
+        assert_eq!(keypad.key_at(47, 47), Some(ChipKey::KeyF));
+        assert_eq!(keypad.key_at(48, 2), None);
+        assert_eq!(keypad.key_at(1, 1), None);
+
+        let keypad = test_keypad(KeypadPosition::BottomRight, KeypadLayout::Sequential);
+        assert_eq!(keypad.key_at(WIDTH - 3, HEIGHT - 3), Some(ChipKey::KeyF));
+        assert_eq!(keypad.key_at(WIDTH - 48, HEIGHT - 48), Some(ChipKey::Key0));
+        assert_eq!(keypad.key_at(2, 2), None);
+    }
+
+    #[test]
+    fn test_pointer_presses_keys() {
+        let mut keypad = test_keypad(KeypadPosition::TopLeft, KeypadLayout::Cosmac);
+        let mut input = SoftwareInput::new();
+
+        assert!(!keypad.pointer_down(Pointer::Mouse, Some((100, 100)), &mut input));
+        assert!(keypad.pointer_down(Pointer::Mouse, Some((5, 5)), &mut input));
+        assert!(input.is_key_pressed(ChipKey::Key1));
+
+        // Sliding onto another key moves the press
+        keypad.pointer_moved(Pointer::Mouse, Some((15, 5)), &mut input);
+        assert!(!input.is_key_pressed(ChipKey::Key1));
+        assert!(input.is_key_pressed(ChipKey::Key2));
+
+        // A second finger on the same key keeps it held until both lift
+        assert!(keypad.pointer_down(Pointer::Touch(7), Some((15, 5)), &mut input));
+        keypad.pointer_up(Pointer::Mouse, &mut input);
+        assert!(input.is_key_pressed(ChipKey::Key2));
+        keypad.pointer_moved(Pointer::Touch(7), None, &mut input);
+        assert!(!input.is_key_pressed(ChipKey::Key2));
+
+        // Moving without a press does nothing
+        keypad.pointer_moved(Pointer::Mouse, Some((5, 5)), &mut input);
+        assert!(!input.any_key_pressed());
+    }
+
+    #[test]
+    fn test_hidden_keypad() {
+        let mut keypad = test_keypad(KeypadPosition::TopLeft, KeypadLayout::Cosmac);
+        let mut input = SoftwareInput::new();
+
+        keypad.pointer_down(Pointer::Touch(1), Some((5, 5)), &mut input);
+        keypad.set_visible(false, &mut input);
+        assert!(!input.any_key_pressed());
+        assert!(!keypad.pointer_down(Pointer::Mouse, Some((5, 5)), &mut input));
+
+        let mut frame = vec![0; WIDTH * HEIGHT * 4];
+        keypad.render(&mut frame, WIDTH, &[], Color::WHITE, Color::BLACK);
+        assert!(frame.iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn test_render_highlights_pressed_keys() {
+        let keypad = test_keypad(KeypadPosition::TopLeft, KeypadLayout::Cosmac);
+        let mut frame = vec![0; WIDTH * HEIGHT * 4];
+        keypad.render(
+            &mut frame,
+            WIDTH,
+            &[ChipKey::Key2],
+            Color::WHITE,
+            Color::BLACK,
+        );
+        let lit = |x: usize, y: usize| frame[(y * WIDTH + x) * 4] > 0;
+
+        // Borders are drawn, faces are background unless held
+        assert!(lit(2, 2));
+        assert!(!lit(3, 3));
+        assert!(lit(15, 3));
+    }
+
+    #[test]
+    fn test_config_from_toml() {
+        let config: VirtualKeypadConfig =
+            toml::from_str("visible = true\nposition = \"top-left\"").unwrap();
+        assert!(config.visible);
+        assert_eq!(config.position, KeypadPosition::TopLeft);
+        assert_eq!(config.layout, KeypadLayout::Cosmac);
+        assert_eq!(config.key_size, 12);
+    }
+}
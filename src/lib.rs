@@ -3,23 +3,73 @@
 //! A modern Rust implementation of the Chip-8 virtual machine system.
 //! This library provides a complete emulation core with clean APIs for
 //! building frontends and tools.
+//!
+//! Without the default `std` feature the crate is `no_std` and needs only
+//! `alloc`: the CPU, memory, registers, stack, timers, the hardware traits
+//! and the ROM analysis tools are available, so a firmware can drive the
+//! core with its own [`Display`], [`Audio`] and [`Input`]. Everything else
+//! (the frontend, audio output, windowing, config files and debugger tools)
+//! needs `std`.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
 
+pub mod analysis;
+#[cfg(feature = "std")]
 pub mod audio;
+#[cfg(feature = "std")]
+pub mod avsync;
+#[cfg(feature = "std")]
+pub mod bench;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "std")]
+pub mod cheats;
+#[cfg(feature = "std")]
+pub mod compat;
+#[cfg(feature = "std")]
+pub mod debugger;
+#[cfg(feature = "demo-roms")]
+pub mod demos;
 pub mod emulator;
 pub mod error;
+#[cfg(feature = "std")]
 pub mod frontend;
+#[cfg(feature = "std")]
+pub mod fuzz;
+#[cfg(feature = "std")]
 pub mod graphics;
 pub mod hardware;
 pub mod input;
+pub mod logging;
+#[cfg(feature = "std")]
+pub mod netplay;
+#[cfg(feature = "octo")]
+pub mod octo;
+#[cfg(feature = "std")]
+pub mod patch;
+#[cfg(feature = "std")]
+pub mod paths;
+#[cfg(feature = "std")]
+pub mod replay;
+#[cfg(feature = "std")]
+pub mod savestate;
 
-#[cfg(test)]
+#[cfg(any(all(test, feature = "std"), feature = "test-harness"))]
 pub mod test_utils;
 
 // Re-export commonly used types
+#[cfg(feature = "std")]
 pub use audio::{AudioBuzzer, AudioSystem, BuzzerConfig, NullAudioSystem};
-pub use emulator::{Cpu, CpuState, Memory, Registers, Stack, Timers};
+pub use emulator::{Cpu, CpuEvent, CpuState, EmulatorEvent, Memory, Registers, Stack, Timers};
 pub use error::{EmulatorError, Result};
+#[cfg(feature = "std")]
 pub use frontend::{CliApp, EmulatorConfig, SimpleEmulator};
+#[cfg(feature = "std")]
 pub use graphics::{GraphicsConfig, GraphicsDisplay};
+#[cfg(feature = "std")]
+pub use hardware::DynHardware;
 pub use hardware::{Audio, Display, Hardware, Input, NullHardware};
+#[cfg(feature = "std")]
 pub use input::{InputSystem, KeyboardInput, NullInputSystem, QwertyMapper};
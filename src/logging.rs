@@ -0,0 +1,425 @@
+//! Structured logging.
+//!
+//! Events are emitted with `tracing` and tagged with the subsystem they come
+//! from as their target: [`CPU`], [`INPUT`], [`AUDIO`], [`GFX`], or [`APP`]
+//! for everything else. [`init`] installs a subscriber that filters them by
+//! the per-subsystem levels in [`LogLevels`], keeps the most recent ones in
+//! a [`LogBuffer`] for the debug panel and fault reports, and with
+//! `--verbose` also prints them to stderr. Messages logged by dependencies
+//! through the `log` crate are passed through at [`LogLevels::other`].
+//!
+//! Without `std` only the targets are available. The emulation core logs
+//! through this module's `warn!`-style macros, which forward to `tracing`
+//! with `std` and compile to nothing without it, since `tracing` needs
+//! atomic compare-and-swap that targets like thumbv6m lack.
+
+#[cfg(feature = "std")]
+use std::collections::VecDeque;
+#[cfg(feature = "std")]
+use std::fmt;
+#[cfg(feature = "std")]
+use std::sync::{Arc, Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "std")]
+use tracing::field::{Field, Visit};
+#[cfg(feature = "std")]
+use tracing::{Event, Level, Subscriber};
+#[cfg(feature = "std")]
+use tracing_subscriber::filter::{LevelFilter, Targets};
+#[cfg(feature = "std")]
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+#[cfg(feature = "std")]
+use tracing_subscriber::util::SubscriberInitExt;
+#[cfg(feature = "std")]
+use tracing_subscriber::{reload, Registry};
+
+/// Target of events from the CPU and emulation core.
+pub const CPU: &str = "cpu";
+
+/// Target of events from the keyboard, keypad and key mapping.
+pub const INPUT: &str = "input";
+
+/// Target of events from the buzzer and audio output.
+pub const AUDIO: &str = "audio";
+
+/// Target of events from the display, renderer and window surface.
+pub const GFX: &str = "gfx";
+
+/// Target of events from the rest of the application.
+pub const APP: &str = "app";
+
+/// Logs an event from the emulation core at the given `tracing` level.
+///
+/// Without `std` the arguments are still type-checked but never evaluated.
+#[cfg(feature = "std")]
+macro_rules! core_event {
+    ($level:ident, $($arg:tt)+) => {
+        tracing::$level!($($arg)+)
+    };
+}
+
+#[cfg(not(feature = "std"))]
+macro_rules! core_event {
+    ($level:ident, target: $target:expr, $($arg:tt)+) => {
+        if false {
+            let _ = $target;
+            let _ = core::format_args!($($arg)+);
+        }
+    };
+}
+
+/// Logs a debug event from the emulation core.
+macro_rules! core_debug {
+    ($($arg:tt)+) => {
+        $crate::logging::core_event!(debug, $($arg)+)
+    };
+}
+
+/// Logs an informational event from the emulation core.
+macro_rules! core_info {
+    ($($arg:tt)+) => {
+        $crate::logging::core_event!(info, $($arg)+)
+    };
+}
+
+/// Logs a warning from the emulation core.
+macro_rules! core_warn {
+    ($($arg:tt)+) => {
+        $crate::logging::core_event!(warn, $($arg)+)
+    };
+}
+
+pub(crate) use {core_debug as debug, core_event, core_info as info, core_warn as warn};
+
+/// Records kept by the global log buffer.
+pub const DEFAULT_LOG_CAPACITY: usize = 256;
+
+#[cfg(feature = "std")]
+/// Handle for changing the installed subscriber's levels.
+static FILTER: OnceLock<reload::Handle<Targets, Registry>> = OnceLock::new();
+
+#[cfg(feature = "std")]
+/// The installed subscriber's log buffer.
+static BUFFER: OnceLock<LogBuffer> = OnceLock::new();
+
+/// How much a subsystem logs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogLevel {
+    /// Nothing.
+    Off,
+
+    /// Errors only.
+    Error,
+
+    /// Warnings and errors.
+    Warn,
+
+    /// Informational messages, warnings and errors.
+    #[default]
+    Info,
+
+    /// Debugging detail as well.
+    Debug,
+
+    /// Everything, including per-instruction tracing.
+    Trace,
+}
+
+#[cfg(feature = "std")]
+impl LogLevel {
+    /// Gets the equivalent `tracing` filter.
+    pub fn filter(self) -> LevelFilter {
+        match self {
+            LogLevel::Off => LevelFilter::OFF,
+            LogLevel::Error => LevelFilter::ERROR,
+            LogLevel::Warn => LevelFilter::WARN,
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Debug => LevelFilter::DEBUG,
+            LogLevel::Trace => LevelFilter::TRACE,
+        }
+    }
+}
+
+/// Log levels per subsystem.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogLevels {
+    /// CPU and emulation core.
+    pub cpu: LogLevel,
+
+    /// Keyboard, keypad and key mapping.
+    pub input: LogLevel,
+
+    /// Buzzer and audio output.
+    pub audio: LogLevel,
+
+    /// Display, renderer and window surface.
+    pub gfx: LogLevel,
+
+    /// The rest of the application.
+    pub app: LogLevel,
+
+    /// Dependencies, such as the windowing and GPU libraries.
+    pub other: LogLevel,
+}
+
+impl LogLevels {
+    /// Creates levels that are the same for every subsystem but dependencies,
+    /// which stay at warnings.
+    pub fn uniform(level: LogLevel) -> Self {
+        Self {
+            cpu: level,
+            input: level,
+            audio: level,
+            gfx: level,
+            app: level,
+            other: LogLevel::Warn,
+        }
+    }
+
+    /// Builds the target filter for these levels.
+    #[cfg(feature = "std")]
+    pub fn targets(&self) -> Targets {
+        Targets::new()
+            .with_target(CPU, self.cpu.filter())
+            .with_target(INPUT, self.input.filter())
+            .with_target(AUDIO, self.audio.filter())
+            .with_target(GFX, self.gfx.filter())
+            .with_target(APP, self.app.filter())
+            .with_default(self.other.filter())
+    }
+}
+
+impl Default for LogLevels {
+    fn default() -> Self {
+        Self::uniform(LogLevel::Info)
+    }
+}
+
+#[cfg(feature = "std")]
+/// A logged event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogRecord {
+    /// Severity.
+    pub level: Level,
+
+    /// Subsystem, or the module path for events from dependencies.
+    pub target: String,
+
+    /// The message followed by any other fields as `name=value`.
+    pub message: String,
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for LogRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&format!(
+            "{:<5} {}: {}",
+            self.level, self.target, self.message
+        ))
+    }
+}
+
+#[cfg(feature = "std")]
+/// Collects an event's fields into a [`LogRecord`].
+#[derive(Default)]
+struct RecordVisitor {
+    message: String,
+    fields: Vec<String>,
+    target: Option<String>,
+}
+
+#[cfg(feature = "std")]
+impl Visit for RecordVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "message" => self.message = value.to_string(),
+            "log.target" => self.target = Some(value.to_string()),
+            name if name.starts_with("log.") => {}
+            name => self.fields.push(format!("{}={}", name, value)),
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        match field.name() {
+            "message" => self.message = format!("{:?}", value),
+            name if name.starts_with("log.") => {}
+            name => self.fields.push(format!("{}={:?}", name, value)),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+/// The most recent log records, oldest first.
+///
+/// Clones share the same records, so the GUI and fault reports can read
+/// what the subscriber's layer writes.
+#[derive(Debug, Clone)]
+pub struct LogBuffer {
+    records: Arc<Mutex<VecDeque<LogRecord>>>,
+    capacity: usize,
+}
+
+#[cfg(feature = "std")]
+impl LogBuffer {
+    /// Creates an empty buffer that keeps up to `capacity` records.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            records: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+        }
+    }
+
+    /// Adds a record, dropping the oldest if the buffer is full.
+    pub fn push(&self, record: LogRecord) {
+        let mut records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        if records.len() >= self.capacity {
+            records.pop_front();
+        }
+        if self.capacity > 0 {
+            records.push_back(record);
+        }
+    }
+
+    /// Gets up to `count` of the most recent records, oldest first.
+    pub fn recent(&self, count: usize) -> Vec<LogRecord> {
+        let records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        let skip = records.len().saturating_sub(count);
+        records.iter().skip(skip).cloned().collect()
+    }
+
+    /// Gets the number of records held.
+    pub fn len(&self) -> usize {
+        self.records.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// Checks if no records are held.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes every record.
+    pub fn clear(&self) {
+        self.records
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S: Subscriber> Layer<S> for LogBuffer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = RecordVisitor::default();
+        event.record(&mut visitor);
+        let mut message = visitor.message;
+        for field in visitor.fields {
+            if !message.is_empty() {
+                message.push(' ');
+            }
+            message.push_str(&field);
+        }
+        self.push(LogRecord {
+            level: *event.metadata().level(),
+            target: visitor
+                .target
+                .unwrap_or_else(|| event.metadata().target().to_string()),
+            message,
+        });
+    }
+}
+
+#[cfg(feature = "std")]
+/// Installs the global subscriber, at the default levels until
+/// [`set_levels`] is called.
+///
+/// Does nothing if a subscriber is already installed.
+///
+/// # Arguments
+/// * `verbose` - Whether to also print events to stderr
+pub fn init(verbose: bool) {
+    let (filter, handle) = reload::Layer::new(LogLevels::default().targets());
+    let buffer = LogBuffer::new(DEFAULT_LOG_CAPACITY);
+    let stderr = verbose.then(|| tracing_subscriber::fmt::layer().with_writer(std::io::stderr));
+    let installed = tracing_subscriber::registry()
+        .with(filter)
+        .with(buffer.clone())
+        .with(stderr)
+        .try_init();
+    if installed.is_ok() {
+        let _ = FILTER.set(handle);
+        let _ = BUFFER.set(buffer);
+    }
+}
+
+#[cfg(feature = "std")]
+/// Changes the levels of the subscriber installed by [`init`].
+///
+/// Does nothing if it isn't installed.
+pub fn set_levels(levels: &LogLevels) {
+    if let Some(handle) = FILTER.get() {
+        if let Err(e) = handle.reload(levels.targets()) {
+            tracing::warn!(target: APP, "Failed to change log levels: {}", e);
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+/// Gets up to `count` of the most recently logged records, oldest first.
+///
+/// # Returns
+/// The records, or none if [`init`] hasn't installed the subscriber.
+pub fn recent(count: usize) -> Vec<LogRecord> {
+    BUFFER
+        .get()
+        .map_or_else(Vec::new, |buffer| buffer.recent(count))
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffer_keeps_recent_records() {
+        let buffer = LogBuffer::new(3);
+        let levels = LogLevels {
+            audio: LogLevel::Warn,
+            ..LogLevels::uniform(LogLevel::Debug)
+        };
+        let subscriber = tracing_subscriber::registry()
+            .with(levels.targets())
+            .with(buffer.clone());
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::debug!(target: CPU, "first");
+            tracing::info!(target: AUDIO, "filtered out");
+            tracing::warn!(target: AUDIO, "No output");
+            tracing::trace!(target: GFX, "filtered out");
+            tracing::debug!(target: INPUT, key = 5, "Pressed");
+            tracing::error!(target: APP, "Failed");
+        });
+
+        let records = buffer.recent(10);
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].to_string(), "WARN  audio: No output");
+        assert_eq!(records[1].target, INPUT);
+        assert_eq!(records[1].message, "Pressed key=5");
+        assert_eq!(records[2].level, Level::ERROR);
+        assert_eq!(buffer.recent(1), records[2..]);
+
+        buffer.clear();
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_log_levels_config() {
+        let levels: LogLevels = toml::from_str("cpu = \"trace\"\ngfx = \"off\"").unwrap();
+        assert_eq!(levels.cpu, LogLevel::Trace);
+        assert_eq!(levels.gfx, LogLevel::Off);
+        assert_eq!(levels.audio, LogLevel::Info);
+        assert_eq!(levels.other, LogLevel::Warn);
+        assert_eq!(LogLevel::Off.filter(), LevelFilter::OFF);
+    }
+}
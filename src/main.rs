@@ -3,7 +3,8 @@
 //! A modern Rust implementation of the classic Chip-8 virtual machine.
 
 use chip8::frontend::cli::{run_cli, CliApp};
-use chip8::frontend::gui::run_gui;
+use chip8::frontend::gui::{run_gui, RunOptions, RunReports};
+use chip8::frontend::ConfigPatch;
 use clap::Parser;
 use color_eyre::eyre::Result;
 
@@ -13,15 +14,29 @@ fn main() -> Result<()> {
 
     let args = CliApp::parse();
 
-    // Set up logging if verbose
-    if args.verbose {
-        env_logger::init();
-    }
+    // Set up logging, printed to stderr if verbose
+    chip8::logging::init(args.verbose);
 
     if let Some(rom_file) = args.rom_file.clone() {
         // Direct ROM execution defaults to GUI
         if args.command.is_none() {
-            run_gui(rom_file, args.config.as_ref(), args.profile.as_ref())?;
+            run_gui(
+                rom_file,
+                args.config.as_ref(),
+                args.profile.as_ref(),
+                &ConfigPatch::parse(&args.settings)?,
+                args.netplay_role(),
+                RunOptions {
+                    cheats: args.cheat.clone(),
+                    no_cheats: args.no_cheats,
+                    patch: args.patch.clone(),
+                    ..RunOptions::default()
+                },
+                RunReports {
+                    stats: args.stats,
+                    timeline: args.timeline.clone(),
+                },
+            )?;
         } else {
             // Has subcommand, use CLI
             run_cli()?;
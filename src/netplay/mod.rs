@@ -0,0 +1,156 @@
+//! Two-player netplay over TCP.
+//!
+//! Two emulator instances run the same ROM in lockstep: every frame each
+//! side sends its keys, waits for the peer's keys, and runs a frame's worth
+//! of clock cycles with the combined input. Execution is deterministic because
+//! both sides share an RNG seed and timers are driven by frames rather
+//! than wall-clock time. The handshake checks that both sides loaded the
+//! same ROM (by SHA-1) with the same emulator settings.
+
+pub mod protocol;
+pub mod session;
+
+pub use protocol::{Handshake, Message, SessionSettings, PROTOCOL_VERSION};
+pub use session::{NetplaySession, Player, DEFAULT_INPUT_DELAY};
+
+use std::net::TcpListener;
+
+use crate::emulator::{Cpu, CpuEvent};
+use crate::error::{NetplayResult, Result};
+use crate::hardware::input::{ChipKey, Input, SoftwareInput};
+use crate::hardware::Hardware;
+
+/// How this instance takes part in a netplay session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NetplayRole {
+    /// Wait for a peer on a TCP port.
+    Host { port: u16 },
+
+    /// Connect to a host at `address` (HOST:PORT).
+    Join { address: String },
+}
+
+impl NetplayRole {
+    /// Establishes the session, blocking until the peer is connected.
+    pub fn connect(&self, handshake: &Handshake) -> NetplayResult<NetplaySession> {
+        match self {
+            NetplayRole::Host { port } => {
+                let listener = TcpListener::bind(("0.0.0.0", *port))?;
+                NetplaySession::host(&listener, handshake, DEFAULT_INPUT_DELAY)
+            }
+            NetplayRole::Join { address } => NetplaySession::join(address.as_str(), handshake),
+        }
+    }
+}
+
+/// Reads the pressed keys as a mask where bit N is key N.
+pub fn keys_to_mask(input: &impl Input) -> u16 {
+    ChipKey::all_keys()
+        .iter()
+        .filter(|&&key| input.is_key_pressed(key))
+        .fold(0, |mask, key| mask | 1 << key.to_u8())
+}
+
+/// Sets the pressed keys of an input to match a mask.
+pub fn apply_mask(input: &mut SoftwareInput, mask: u16) {
+    for key in ChipKey::all_keys() {
+        if mask & (1 << key.to_u8()) != 0 {
+            input.press_key(key);
+        } else {
+            input.release_key(key);
+        }
+    }
+}
+
+/// Runs one lockstep frame: a frame's worth of clock cycles, then one timer tick.
+///
+/// The budget is the CPU's [`Cpu::cycles_per_frame`], charged by its timing
+/// model the same way `SimpleEmulator::advance_frame` charges it. A key
+/// wait ends the frame early, so both peers stay on the same instruction
+/// regardless of local timing.
+pub fn run_frame<H: Hardware>(cpu: &mut Cpu<H>) -> Result<()> {
+    let budget = cpu.cycles_per_frame();
+    run_frame_for(cpu, budget)
+}
+
+/// Runs one lockstep frame with an explicit clock-cycle budget.
+pub fn run_frame_for<H: Hardware>(cpu: &mut Cpu<H>, budget: u32) -> Result<()> {
+    let mut used = 0u32;
+    while used < budget {
+        let event = cpu.cycle()?;
+        used = used.saturating_add(cpu.last_cost());
+        if event == CpuEvent::WaitingForKey {
+            break;
+        }
+    }
+    cpu.timers_mut().update_by_ticks(1);
+    cpu.record_frame();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::{EmulatorBehaviorConfig, EmulatorConfig, SimpleEmulator};
+
+    #[test]
+    fn test_key_mask_round_trip() {
+        let mut input = SoftwareInput::new();
+        apply_mask(&mut input, 0b1000_0000_0001_0010);
+
+        assert!(input.is_key_pressed(ChipKey::Key1));
+        assert!(input.is_key_pressed(ChipKey::Key4));
+        assert!(input.is_key_pressed(ChipKey::KeyF));
+        assert_eq!(keys_to_mask(&input), 0b1000_0000_0001_0010);
+
+        apply_mask(&mut input, 0);
+        assert_eq!(keys_to_mask(&input), 0);
+    }
+
+    #[test]
+    fn test_lockstep_frames_are_deterministic() {
+        // Loop: V0 = random; DT = V0; jump back
+        let rom = [0xC0, 0xFF, 0xF0, 0x15, 0x12, 0x00];
+        let run = || {
+            let mut cpu = Cpu::new();
+            cpu.seed_rng(7);
+            cpu.set_realtime_timers(false);
+            cpu.load_rom(&rom).unwrap();
+            for _ in 0..10 {
+                run_frame_for(&mut cpu, 11).unwrap();
+            }
+            cpu.get_state()
+        };
+
+        assert_eq!(run(), run());
+    }
+
+    #[test]
+    fn test_frames_follow_timing_model() {
+        let classic = Cpu::new_with_config(&EmulatorBehaviorConfig::classic());
+        assert_eq!(classic.cycles_per_frame(), 500 / 60);
+
+        // LD V0, 5; ADD V0, 1; JP 0x202
+        let rom = [0x60, 0x05, 0x70, 0x01, 0x12, 0x02];
+        let config = EmulatorConfig {
+            behavior: EmulatorBehaviorConfig::cosmac_vip(),
+            ..Default::default()
+        };
+        let mut emulator = SimpleEmulator::new_with_config(&config);
+        emulator.cpu_mut().load_rom(&rom).unwrap();
+        let mut cpu = Cpu::new_with_config(&config.behavior);
+        cpu.set_realtime_timers(false);
+        cpu.load_rom(&rom).unwrap();
+
+        assert_eq!(cpu.cycles_per_frame(), 220_080 / 60);
+        for _ in 0..3 {
+            emulator.advance_frame().unwrap();
+            run_frame(&mut cpu).unwrap();
+        }
+
+        // Hundreds of cheap instructions a frame, not cpu_speed / 60
+        assert!(cpu.instruction_count() > 3 * 300);
+        assert_eq!(cpu.get_state(), emulator.cpu().get_state());
+        assert_eq!(cpu.elapsed_cycles(), emulator.cpu().elapsed_cycles());
+    }
+}
@@ -0,0 +1,319 @@
+//! Netplay wire protocol.
+//!
+//! Messages are sent over TCP as a one-byte tag followed by a fixed
+//! big-endian payload. Keys are sent as a 16-bit mask where bit N is
+//! Chip-8 key N. The handshake's behavior config is sent as length-prefixed
+//! JSON so every setting and quirk is compared.
+
+use std::collections::BTreeSet;
+use std::io::{ErrorKind, Read};
+
+use serde_json::{Map, Value};
+
+use crate::error::{NetplayError, NetplayResult};
+use crate::frontend::EmulatorBehaviorConfig;
+
+/// Protocol version; peers must match exactly.
+pub const PROTOCOL_VERSION: u16 = 2;
+
+/// Longest rejection reason that will be sent.
+const MAX_REASON_LENGTH: usize = 256;
+
+/// Longest encoded behavior config that will be accepted.
+const MAX_SETTINGS_LENGTH: usize = 4096;
+
+const TAG_HELLO: u8 = 1;
+const TAG_WELCOME: u8 = 2;
+const TAG_REJECT: u8 = 3;
+const TAG_INPUT: u8 = 4;
+const TAG_BYE: u8 = 5;
+
+/// Emulator settings that must match for both peers to stay in sync.
+///
+/// Every field of the behavior config changes how a program runs (speed,
+/// timing model, quirks, memory layout, font, sound timer), so all of it
+/// is compared.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionSettings {
+    /// The peer's behavior config.
+    pub behavior: EmulatorBehaviorConfig,
+}
+
+impl SessionSettings {
+    /// Takes the settings that affect execution from a behavior config.
+    pub fn from_behavior(config: &EmulatorBehaviorConfig) -> Self {
+        Self {
+            behavior: config.clone(),
+        }
+    }
+
+    /// Lists the settings that differ from another peer's.
+    pub fn differences(&self, other: &Self) -> Vec<String> {
+        let ours = settings_map(&self.behavior);
+        let theirs = settings_map(&other.behavior);
+        let names: BTreeSet<&String> = ours.keys().chain(theirs.keys()).collect();
+
+        names
+            .into_iter()
+            .filter_map(|name| {
+                let ours = ours.get(name).unwrap_or(&Value::Null);
+                let theirs = theirs.get(name).unwrap_or(&Value::Null);
+                (ours != theirs).then(|| format!("{} {} vs {}", name, ours, theirs))
+            })
+            .collect()
+    }
+
+    /// Encodes the settings as JSON.
+    fn encode(&self) -> Vec<u8> {
+        serde_json::to_vec(&self.behavior).expect("behavior config serializes")
+    }
+
+    /// Decodes settings sent by `encode`.
+    fn decode(bytes: &[u8]) -> NetplayResult<Self> {
+        let behavior = serde_json::from_slice(bytes)
+            .map_err(|e| NetplayError::Protocol(format!("bad settings: {}", e)))?;
+        Ok(Self { behavior })
+    }
+}
+
+/// Gets a behavior config's settings by name.
+fn settings_map(config: &EmulatorBehaviorConfig) -> Map<String, Value> {
+    match serde_json::to_value(config) {
+        Ok(Value::Object(map)) => map,
+        _ => Map::new(),
+    }
+}
+
+/// Session parameters sent by the joining peer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Handshake {
+    /// Protocol version.
+    pub version: u16,
+
+    /// SHA-1 of the loaded ROM.
+    pub rom_sha1: [u8; 20],
+
+    /// Execution settings.
+    pub settings: SessionSettings,
+}
+
+impl Handshake {
+    /// Builds the handshake for a ROM and behavior config.
+    pub fn new(rom: &[u8], config: &EmulatorBehaviorConfig) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            rom_sha1: crate::analysis::sha1(rom),
+            settings: SessionSettings::from_behavior(config),
+        }
+    }
+}
+
+/// A netplay message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    /// Sent by the joining peer to start a session.
+    Hello(Handshake),
+
+    /// Sent by the host to accept a session.
+    Welcome {
+        /// RNG seed both peers use.
+        seed: u64,
+
+        /// Frames between reading local input and applying it.
+        input_delay: u8,
+    },
+
+    /// Sent by the host to refuse a session.
+    Reject { reason: String },
+
+    /// A player's keys for a frame.
+    Input { frame: u32, keys: u16 },
+
+    /// The peer is leaving.
+    Bye,
+}
+
+impl Message {
+    /// Encodes the message for sending.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        match self {
+            Message::Hello(handshake) => {
+                out.push(TAG_HELLO);
+                out.extend_from_slice(&handshake.version.to_be_bytes());
+                out.extend_from_slice(&handshake.rom_sha1);
+                let settings = handshake.settings.encode();
+                out.extend_from_slice(&(settings.len() as u16).to_be_bytes());
+                out.extend_from_slice(&settings);
+            }
+            Message::Welcome { seed, input_delay } => {
+                out.push(TAG_WELCOME);
+                out.extend_from_slice(&seed.to_be_bytes());
+                out.push(*input_delay);
+            }
+            Message::Reject { reason } => {
+                let reason = &reason.as_bytes()[..reason.len().min(MAX_REASON_LENGTH)];
+                out.push(TAG_REJECT);
+                out.extend_from_slice(&(reason.len() as u16).to_be_bytes());
+                out.extend_from_slice(reason);
+            }
+            Message::Input { frame, keys } => {
+                out.push(TAG_INPUT);
+                out.extend_from_slice(&frame.to_be_bytes());
+                out.extend_from_slice(&keys.to_be_bytes());
+            }
+            Message::Bye => out.push(TAG_BYE),
+        }
+        out
+    }
+
+    /// Reads one message, blocking until it has fully arrived.
+    pub fn read_from(reader: &mut impl Read) -> NetplayResult<Self> {
+        let tag = read_array::<1>(reader)?[0];
+
+        let message = match tag {
+            TAG_HELLO => {
+                let version = u16::from_be_bytes(read_array(reader)?);
+                let rom_sha1 = read_array(reader)?;
+                let length = u16::from_be_bytes(read_array(reader)?) as usize;
+                if length > MAX_SETTINGS_LENGTH {
+                    return Err(NetplayError::Protocol("settings too long".into()));
+                }
+                let mut settings = vec![0u8; length];
+                read_exact(reader, &mut settings)?;
+                Message::Hello(Handshake {
+                    version,
+                    rom_sha1,
+                    settings: SessionSettings::decode(&settings)?,
+                })
+            }
+            TAG_WELCOME => Message::Welcome {
+                seed: u64::from_be_bytes(read_array(reader)?),
+                input_delay: read_array::<1>(reader)?[0],
+            },
+            TAG_REJECT => {
+                let length = u16::from_be_bytes(read_array(reader)?) as usize;
+                if length > MAX_REASON_LENGTH {
+                    return Err(NetplayError::Protocol("rejection reason too long".into()));
+                }
+                let mut reason = vec![0u8; length];
+                read_exact(reader, &mut reason)?;
+                Message::Reject {
+                    reason: String::from_utf8_lossy(&reason).into_owned(),
+                }
+            }
+            TAG_INPUT => Message::Input {
+                frame: u32::from_be_bytes(read_array(reader)?),
+                keys: u16::from_be_bytes(read_array(reader)?),
+            },
+            TAG_BYE => Message::Bye,
+            other => return Err(NetplayError::Protocol(format!("unknown tag {}", other))),
+        };
+
+        Ok(message)
+    }
+}
+
+/// Reads exactly `buffer.len()` bytes, mapping EOF to a disconnect.
+fn read_exact(reader: &mut impl Read, buffer: &mut [u8]) -> NetplayResult<()> {
+    reader.read_exact(buffer).map_err(|e| match e.kind() {
+        ErrorKind::UnexpectedEof | ErrorKind::ConnectionReset => NetplayError::Disconnected,
+        _ => NetplayError::Io(e),
+    })
+}
+
+/// Reads a fixed-size array.
+fn read_array<const N: usize>(reader: &mut impl Read) -> NetplayResult<[u8; N]> {
+    let mut buffer = [0u8; N];
+    read_exact(reader, &mut buffer)?;
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hardware::display::ClipMode;
+
+    fn round_trip(message: Message) -> Message {
+        let bytes = message.encode();
+        Message::read_from(&mut bytes.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn test_message_round_trip() {
+        let handshake = Handshake::new(&[0x00, 0xE0], &EmulatorBehaviorConfig::classic());
+        let messages = [
+            Message::Hello(handshake),
+            Message::Welcome {
+                seed: 0xDEAD_BEEF_1234,
+                input_delay: 2,
+            },
+            Message::Reject {
+                reason: "ROM mismatch".to_string(),
+            },
+            Message::Input {
+                frame: 1234,
+                keys: 0b1000_0000_0000_0010,
+            },
+            Message::Bye,
+        ];
+
+        for message in messages {
+            assert_eq!(round_trip(message.clone()), message);
+        }
+    }
+
+    #[test]
+    fn test_truncated_message_is_disconnect() {
+        let bytes = Message::Input { frame: 1, keys: 2 }.encode();
+        let result = Message::read_from(&mut &bytes[..3]);
+        assert!(matches!(result, Err(NetplayError::Disconnected)));
+
+        let result = Message::read_from(&mut &[0xFFu8][..]);
+        assert!(matches!(result, Err(NetplayError::Protocol(_))));
+    }
+
+    #[test]
+    fn test_settings_differences() {
+        let classic = SessionSettings::from_behavior(&EmulatorBehaviorConfig::classic());
+        let modern = SessionSettings::from_behavior(&EmulatorBehaviorConfig::default());
+
+        assert!(classic.differences(&classic).is_empty());
+        assert!(!classic.differences(&modern).is_empty());
+    }
+
+    #[test]
+    fn test_quirk_differences() {
+        let classic = SessionSettings::from_behavior(&EmulatorBehaviorConfig::classic());
+        let vip = SessionSettings::from_behavior(&EmulatorBehaviorConfig::cosmac_vip());
+
+        let differences = classic.differences(&vip);
+        for name in [
+            "load_store_increments_i",
+            "shift_uses_vy",
+            "sound_timer_mode",
+            "timing_model",
+        ] {
+            assert!(
+                differences.iter().any(|d| d.starts_with(name)),
+                "{} missing from {:?}",
+                name,
+                differences
+            );
+        }
+        assert_eq!(differences.len(), 4);
+
+        let clipped = SessionSettings::from_behavior(&EmulatorBehaviorConfig {
+            clip_mode: ClipMode::Clip,
+            ..EmulatorBehaviorConfig::classic()
+        });
+        assert_eq!(
+            classic.differences(&clipped),
+            vec!["clip_mode \"wrap\" vs \"clip\"".to_string()]
+        );
+
+        // Settings that differ survive the trip over the wire
+        let hello = Message::Hello(Handshake::new(&[0x00, 0xE0], &vip.behavior));
+        assert_eq!(round_trip(hello.clone()), hello);
+    }
+}
@@ -0,0 +1,303 @@
+//! Lockstep netplay sessions.
+//!
+//! Each frame both peers send their local keys and wait for the other's
+//! keys for the same frame, so both emulators see identical input. Local
+//! input is scheduled `input_delay` frames ahead to hide network latency.
+
+use std::collections::BTreeMap;
+use std::io::{ErrorKind, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+
+use super::protocol::{Handshake, Message};
+use crate::emulator::Cpu;
+use crate::error::{NetplayError, NetplayResult};
+use crate::hardware::Hardware;
+use crate::logging::APP;
+
+/// Default frames of input delay.
+pub const DEFAULT_INPUT_DELAY: u8 = 2;
+
+/// Which side of the session this instance is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Player {
+    /// The hosting peer.
+    One,
+
+    /// The joining peer.
+    Two,
+}
+
+/// A connected lockstep session.
+pub struct NetplaySession {
+    /// Connection to the peer.
+    stream: TcpStream,
+
+    /// Which player this instance is.
+    player: Player,
+
+    /// Shared RNG seed.
+    seed: u64,
+
+    /// Frames between reading and applying local input.
+    input_delay: u32,
+
+    /// Next frame to run.
+    frame: u32,
+
+    /// Scheduled local keys by frame.
+    local: BTreeMap<u32, u16>,
+
+    /// Received remote keys by frame.
+    remote: BTreeMap<u32, u16>,
+
+    /// Whether Bye was already sent.
+    closed: bool,
+}
+
+impl NetplaySession {
+    /// Waits for a peer to join and validates its handshake.
+    ///
+    /// # Arguments
+    /// * `listener` - Socket to accept the peer on
+    /// * `local` - This instance's ROM and settings
+    /// * `input_delay` - Frames of input delay for the session
+    pub fn host(listener: &TcpListener, local: &Handshake, input_delay: u8) -> NetplayResult<Self> {
+        let (mut stream, peer) = listener.accept()?;
+        stream.set_nodelay(true)?;
+        tracing::info!(target: APP, "Netplay peer connected from {}", peer);
+
+        let remote = match Message::read_from(&mut stream)? {
+            Message::Hello(handshake) => handshake,
+            other => {
+                return Err(NetplayError::Protocol(format!(
+                    "expected Hello, got {:?}",
+                    other
+                )))
+            }
+        };
+
+        let error = if remote.version != local.version {
+            Some(NetplayError::VersionMismatch {
+                local: local.version,
+                remote: remote.version,
+            })
+        } else if remote.rom_sha1 != local.rom_sha1 {
+            Some(NetplayError::RomMismatch)
+        } else {
+            let differences = local.settings.differences(&remote.settings);
+            (!differences.is_empty())
+                .then(|| NetplayError::SettingsMismatch(differences.join(", ")))
+        };
+        if let Some(error) = error {
+            let reject = Message::Reject {
+                reason: error.to_string(),
+            };
+            stream.write_all(&reject.encode())?;
+            return Err(error);
+        }
+
+        let seed = rand::random();
+        stream.write_all(&Message::Welcome { seed, input_delay }.encode())?;
+
+        Ok(Self::new(stream, Player::One, seed, input_delay))
+    }
+
+    /// Connects to a host and performs the handshake.
+    pub fn join(address: impl ToSocketAddrs, local: &Handshake) -> NetplayResult<Self> {
+        let mut stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+        stream.write_all(&Message::Hello(local.clone()).encode())?;
+
+        match Message::read_from(&mut stream)? {
+            Message::Welcome { seed, input_delay } => {
+                Ok(Self::new(stream, Player::Two, seed, input_delay))
+            }
+            Message::Reject { reason } => Err(NetplayError::Rejected(reason)),
+            other => Err(NetplayError::Protocol(format!(
+                "expected Welcome, got {:?}",
+                other
+            ))),
+        }
+    }
+
+    /// Creates a session over an established connection.
+    fn new(stream: TcpStream, player: Player, seed: u64, input_delay: u8) -> Self {
+        Self {
+            stream,
+            player,
+            seed,
+            input_delay: input_delay as u32,
+            frame: 0,
+            local: BTreeMap::new(),
+            remote: BTreeMap::new(),
+            closed: false,
+        }
+    }
+
+    /// Gets which player this instance is.
+    pub fn player(&self) -> Player {
+        self.player
+    }
+
+    /// Gets the shared RNG seed.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Gets the input delay in frames.
+    pub fn input_delay(&self) -> u32 {
+        self.input_delay
+    }
+
+    /// Gets the next frame number.
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// Makes a CPU deterministic for this session (shared seed, frame-driven timers).
+    pub fn prepare<H: Hardware>(&self, cpu: &mut Cpu<H>) {
+        cpu.seed_rng(self.seed);
+        cpu.set_realtime_timers(false);
+    }
+
+    /// Sends local keys and waits for the peer's keys for the current frame.
+    ///
+    /// # Arguments
+    /// * `local_keys` - Key mask read from this player's keyboard
+    ///
+    /// # Returns
+    /// The combined key mask of both players to apply for this frame.
+    pub fn exchange(&mut self, local_keys: u16) -> NetplayResult<u16> {
+        let target = self.frame + self.input_delay;
+        self.local.insert(target, local_keys);
+        self.send(&Message::Input {
+            frame: target,
+            keys: local_keys,
+        })?;
+
+        // Frames inside the initial delay window have no input from either side
+        while self.frame >= self.input_delay && !self.remote.contains_key(&self.frame) {
+            match Message::read_from(&mut self.stream)? {
+                Message::Input { frame, keys } => {
+                    self.remote.insert(frame, keys);
+                }
+                Message::Bye => {
+                    self.closed = true;
+                    return Err(NetplayError::Disconnected);
+                }
+                other => {
+                    return Err(NetplayError::Protocol(format!(
+                        "unexpected {:?} during play",
+                        other
+                    )))
+                }
+            }
+        }
+
+        let local = self.local.remove(&self.frame).unwrap_or(0);
+        let remote = self.remote.remove(&self.frame).unwrap_or(0);
+        self.frame += 1;
+        Ok(local | remote)
+    }
+
+    /// Sends a message, treating a closed connection as a disconnect.
+    fn send(&mut self, message: &Message) -> NetplayResult<()> {
+        self.stream
+            .write_all(&message.encode())
+            .map_err(|e| match e.kind() {
+                ErrorKind::BrokenPipe
+                | ErrorKind::ConnectionReset
+                | ErrorKind::ConnectionAborted => NetplayError::Disconnected,
+                _ => NetplayError::Io(e),
+            })
+    }
+
+    /// Tells the peer the session is over.
+    pub fn close(&mut self) {
+        if !self.closed {
+            self.closed = true;
+            let _ = self.stream.write_all(&Message::Bye.encode());
+        }
+    }
+}
+
+impl Drop for NetplaySession {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontend::EmulatorBehaviorConfig;
+
+    const ROM: [u8; 4] = [0x00, 0xE0, 0x12, 0x02];
+
+    fn connect_pair(
+        host_config: EmulatorBehaviorConfig,
+        join_rom: &'static [u8],
+    ) -> (NetplayResult<NetplaySession>, NetplayResult<NetplaySession>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let joiner = std::thread::spawn(move || {
+            let handshake = Handshake::new(join_rom, &EmulatorBehaviorConfig::default());
+            NetplaySession::join(("127.0.0.1", port), &handshake)
+        });
+
+        let host = NetplaySession::host(&listener, &Handshake::new(&ROM, &host_config), 2);
+        (host, joiner.join().unwrap())
+    }
+
+    #[test]
+    fn test_handshake_and_lockstep() {
+        let (host, join) = connect_pair(EmulatorBehaviorConfig::default(), &ROM);
+        let (mut host, mut join) = (host.unwrap(), join.unwrap());
+
+        assert_eq!(host.player(), Player::One);
+        assert_eq!(join.player(), Player::Two);
+        assert_eq!(host.seed(), join.seed());
+        assert_eq!(join.input_delay(), 2);
+
+        // The joining session is returned so it isn't closed while the host still plays
+        let remote = std::thread::spawn(move || {
+            let keys: Vec<u16> = (0..4u16)
+                .map(|i| join.exchange(0x0100 << i).unwrap())
+                .collect();
+            (keys, join)
+        });
+        let local: Vec<u16> = (0..4u16).map(|i| host.exchange(1 << i).unwrap()).collect();
+        let (remote, _join) = remote.join().unwrap();
+
+        // Both sides see the same combined keys, delayed by two frames
+        assert_eq!(local, remote);
+        assert_eq!(local, vec![0, 0, 0x0101, 0x0202]);
+    }
+
+    #[test]
+    fn test_rom_mismatch_is_rejected() {
+        let (host, join) = connect_pair(EmulatorBehaviorConfig::default(), &[0x12, 0x00]);
+
+        assert!(matches!(host, Err(NetplayError::RomMismatch)));
+        assert!(matches!(join, Err(NetplayError::Rejected(_))));
+    }
+
+    #[test]
+    fn test_settings_mismatch_is_rejected() {
+        let (host, join) = connect_pair(EmulatorBehaviorConfig::classic(), &ROM);
+
+        assert!(matches!(host, Err(NetplayError::SettingsMismatch(_))));
+        assert!(matches!(join, Err(NetplayError::Rejected(_))));
+    }
+
+    #[test]
+    fn test_peer_disconnect() {
+        let (host, join) = connect_pair(EmulatorBehaviorConfig::default(), &ROM);
+        let (mut host, join) = (host.unwrap(), join.unwrap());
+        drop(join);
+
+        let result = (0..3).try_for_each(|_| host.exchange(0).map(|_| ()));
+        assert!(matches!(result, Err(NetplayError::Disconnected)));
+    }
+}
@@ -0,0 +1,932 @@
+//! Octo assembly compiler.
+//!
+//! Enabled with the `octo` feature. Compiles `.8o` source into a ROM so
+//! programs written for [Octo](https://github.com/JohnEarnest/Octo) can be
+//! run and watched directly. The core language is supported: labels,
+//! `:const`, `:alias`, `:org`, `:byte`, `:call`, every instruction form,
+//! `if ... then`, `if ... begin ... else ... end`, `loop ... while ...
+//! again` and raw data bytes. Macros, `:calc`, `:next`, `:unpack` and the
+//! `<`/`>` comparison pseudo-ops are not.
+//!
+//! Like Octo, execution starts at the `main` label: the first instruction
+//! of every compiled ROM is a jump to it.
+
+use std::collections::HashMap;
+
+use crate::analysis::ROM_BASE;
+use crate::debugger::SymbolTable;
+use crate::error::{OctoError, OctoResult};
+
+/// Compiles Octo source into a ROM loaded at 0x200.
+///
+/// # Returns
+/// The ROM data, or the first error with its line number.
+pub fn compile(source: &str) -> OctoResult<Vec<u8>> {
+    compile_with_symbols(source).map(|(rom, _)| rom)
+}
+
+/// Compiles Octo source, also returning its labels as a symbol map.
+///
+/// # Returns
+/// The ROM data and a symbol for every label.
+pub fn compile_with_symbols(source: &str) -> OctoResult<(Vec<u8>, SymbolTable)> {
+    Compiler::new(source).compile()
+}
+
+/// A source token and the line it came from.
+#[derive(Debug, Clone, Copy)]
+struct Token<'a> {
+    /// Token text.
+    text: &'a str,
+
+    /// Line number, starting at 1.
+    line: usize,
+}
+
+/// How a forward reference is patched once its label is known.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FixupKind {
+    /// Low 12 bits of the instruction at the address.
+    Short,
+
+    /// The whole 16-bit word at the address (`i := long`).
+    Long,
+}
+
+/// A reference to a label that is resolved after the whole program is read.
+#[derive(Debug, Clone)]
+struct Fixup<'a> {
+    /// Address of the instruction to patch.
+    address: u16,
+
+    /// Label name.
+    name: &'a str,
+
+    /// Line of the reference.
+    line: usize,
+
+    /// How to patch.
+    kind: FixupKind,
+}
+
+/// An open control-flow construct.
+#[derive(Debug, Clone)]
+enum Block {
+    /// `if ... begin`; the jump skips to `else` or `end`.
+    If { jump: u16, line: usize },
+
+    /// `else`; the jump skips to `end`.
+    Else { jump: u16, line: usize },
+
+    /// `loop`; each `while` adds a jump out of the loop.
+    Loop {
+        start: u16,
+        breaks: Vec<u16>,
+        line: usize,
+    },
+}
+
+/// Skip instructions for a condition.
+#[derive(Debug, Clone, Copy)]
+struct Condition {
+    /// Skips the next instruction if the condition holds.
+    skip_if_true: u16,
+
+    /// Skips the next instruction if the condition does not hold.
+    skip_if_false: u16,
+}
+
+/// Single-pass compiler state.
+struct Compiler<'a> {
+    /// Source tokens.
+    tokens: Vec<Token<'a>>,
+
+    /// Index of the next token.
+    position: usize,
+
+    /// Compiled bytes, starting at 0x200.
+    rom: Vec<u8>,
+
+    /// Address the next byte is written to.
+    here: u16,
+
+    /// Label addresses.
+    labels: HashMap<&'a str, u16>,
+
+    /// `:const` values.
+    constants: HashMap<&'a str, u16>,
+
+    /// `:alias` register names.
+    aliases: HashMap<&'a str, u8>,
+
+    /// Label references to patch at the end.
+    fixups: Vec<Fixup<'a>>,
+
+    /// Open control-flow constructs, innermost last.
+    blocks: Vec<Block>,
+}
+
+impl<'a> Compiler<'a> {
+    /// Splits the source into tokens, dropping `#` comments.
+    fn new(source: &'a str) -> Self {
+        let tokens = source
+            .lines()
+            .enumerate()
+            .flat_map(|(index, line)| {
+                let code = line.split('#').next().unwrap_or_default();
+                code.split_whitespace().map(move |text| Token {
+                    text,
+                    line: index + 1,
+                })
+            })
+            .collect();
+
+        Self {
+            tokens,
+            position: 0,
+            rom: Vec::new(),
+            here: ROM_BASE,
+            labels: HashMap::new(),
+            constants: HashMap::new(),
+            aliases: HashMap::new(),
+            fixups: Vec::new(),
+            blocks: Vec::new(),
+        }
+    }
+
+    /// Compiles the whole program.
+    fn compile(mut self) -> OctoResult<(Vec<u8>, SymbolTable)> {
+        // Jump to main, patched like any other forward reference
+        self.emit_reference(0x1000, "main", 0);
+
+        while self.position < self.tokens.len() {
+            self.statement()?;
+        }
+
+        if let Some(block) = self.blocks.last() {
+            let (construct, line) = match block {
+                Block::If { line, .. } => ("begin", *line),
+                Block::Else { line, .. } => ("else", *line),
+                Block::Loop { line, .. } => ("loop", *line),
+            };
+            return Err(OctoError::Unterminated {
+                line,
+                construct: construct.to_string(),
+            });
+        }
+
+        if !self.labels.contains_key("main") {
+            return Err(OctoError::MissingMain);
+        }
+        for fixup in std::mem::take(&mut self.fixups) {
+            let Some(&target) = self.labels.get(fixup.name) else {
+                return Err(OctoError::UndefinedName {
+                    line: fixup.line,
+                    name: fixup.name.to_string(),
+                });
+            };
+            match fixup.kind {
+                FixupKind::Short => self.patch(fixup.address, target, fixup.line)?,
+                FixupKind::Long => self.write_word(fixup.address, target),
+            }
+        }
+
+        let mut labels: Vec<_> = self.labels.into_iter().collect();
+        labels.sort_unstable();
+        let symbols = labels
+            .into_iter()
+            .map(|(name, address)| (name.to_string(), address))
+            .collect();
+        Ok((self.rom, symbols))
+    }
+
+    /// Compiles one statement.
+    fn statement(&mut self) -> OctoResult<()> {
+        let token = self.next()?;
+
+        if let Some(x) = self.register(token.text) {
+            return self.assignment(x, token.line);
+        }
+        if let Some(value) = parse_number(token.text) {
+            let byte = self.check_byte(value, token.line)?;
+            self.emit_byte(byte);
+            return Ok(());
+        }
+
+        match token.text {
+            ":" => {
+                let name = self.name()?;
+                if self.labels.insert(name.text, self.here).is_some() {
+                    return Err(OctoError::Redefined {
+                        line: name.line,
+                        name: name.text.to_string(),
+                    });
+                }
+            }
+            ":const" => {
+                let name = self.name()?;
+                let value = self.value()?;
+                self.constants.insert(name.text, value);
+            }
+            ":alias" => {
+                let name = self.name()?;
+                let register = self.expect_register()?;
+                self.aliases.insert(name.text, register);
+            }
+            ":org" => {
+                let address = self.value()?;
+                if address < ROM_BASE {
+                    return Err(OctoError::OutOfRange {
+                        line: token.line,
+                        value: address as i64,
+                    });
+                }
+                self.here = address;
+            }
+            ":byte" => {
+                let token = self.next()?;
+                let value = self.resolve(token)?;
+                let byte = self.check_byte(value, token.line)?;
+                self.emit_byte(byte);
+            }
+            ":call" => self.address_operand(0x2000)?,
+            ":breakpoint" => {
+                // Debugger hint; the name is not needed here
+                self.next()?;
+            }
+            ":monitor" => {
+                self.next()?;
+                self.next()?;
+            }
+            "clear" => self.emit(0x00E0),
+            "return" | ";" => self.emit(0x00EE),
+            "hires" => self.emit(0x00FF),
+            "lores" => self.emit(0x00FE),
+            "exit" => self.emit(0x00FD),
+            "scroll-left" => self.emit(0x00FC),
+            "scroll-right" => self.emit(0x00FB),
+            "scroll-down" => {
+                let n = self.nibble()?;
+                self.emit(0x00C0 | n);
+            }
+            "scroll-up" => {
+                let n = self.nibble()?;
+                self.emit(0x00D0 | n);
+            }
+            "audio" => self.emit(0xF002),
+            "plane" => {
+                let n = self.nibble()?;
+                self.emit(0xF001 | n << 8);
+            }
+            "jump" => self.address_operand(0x1000)?,
+            "jump0" => self.address_operand(0xB000)?,
+            "native" => self.address_operand(0x0000)?,
+            "bcd" => self.register_op(0xF033)?,
+            "saveflags" => self.register_op(0xF075)?,
+            "loadflags" => self.register_op(0xF085)?,
+            "save" => self.save_or_load(0xF055, 0x5002)?,
+            "load" => self.save_or_load(0xF065, 0x5003)?,
+            "sprite" => {
+                let x = self.expect_register()? as u16;
+                let y = self.expect_register()? as u16;
+                let n = self.nibble()?;
+                self.emit(0xD000 | x << 8 | y << 4 | n);
+            }
+            "i" => self.index_assignment()?,
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let x = self.expect_register()? as u16;
+                let base = match token.text {
+                    "delay" => 0xF015,
+                    "buzzer" => 0xF018,
+                    _ => 0xF03A,
+                };
+                self.emit(base | x << 8);
+            }
+            "if" => self.if_statement(token.line)?,
+            "else" => match self.blocks.pop() {
+                Some(Block::If { jump, .. }) => {
+                    let skip = self.here;
+                    self.emit(0x1000);
+                    self.patch(jump, self.here, token.line)?;
+                    self.blocks.push(Block::Else {
+                        jump: skip,
+                        line: token.line,
+                    });
+                }
+                _ => return Err(syntax(token.line, "'else' without 'if ... begin'")),
+            },
+            "end" => match self.blocks.pop() {
+                Some(Block::If { jump, .. } | Block::Else { jump, .. }) => {
+                    self.patch(jump, self.here, token.line)?;
+                }
+                _ => return Err(syntax(token.line, "'end' without 'begin'")),
+            },
+            "loop" => self.blocks.push(Block::Loop {
+                start: self.here,
+                breaks: Vec::new(),
+                line: token.line,
+            }),
+            "while" => {
+                let condition = self.condition()?;
+                let exit = self.here + 2;
+                let Some(Block::Loop { breaks, .. }) = self
+                    .blocks
+                    .iter_mut()
+                    .rev()
+                    .find(|block| matches!(block, Block::Loop { .. }))
+                else {
+                    return Err(syntax(token.line, "'while' outside 'loop'"));
+                };
+                breaks.push(exit);
+                self.emit(condition.skip_if_true);
+                self.emit(0x1000);
+            }
+            "again" => match self.blocks.pop() {
+                Some(Block::Loop { start, breaks, .. }) => {
+                    self.emit(0x1000 | start);
+                    for jump in breaks {
+                        self.patch(jump, self.here, token.line)?;
+                    }
+                }
+                _ => return Err(syntax(token.line, "'again' without 'loop'")),
+            },
+            text if text.starts_with(':') => {
+                return Err(syntax(token.line, &format!("'{}' is not supported", text)));
+            }
+            _ => {
+                // A bare name calls a subroutine
+                let name = self.check_name(token)?;
+                self.emit_reference(0x2000, name.text, name.line);
+            }
+        }
+        Ok(())
+    }
+
+    /// Compiles `vx op ...`.
+    fn assignment(&mut self, x: u8, line: usize) -> OctoResult<()> {
+        let x = x as u16;
+        let operator = self.next()?;
+
+        if let Some(base) = match operator.text {
+            "|=" => Some(0x8001),
+            "&=" => Some(0x8002),
+            "^=" => Some(0x8003),
+            ">>=" => Some(0x8006),
+            "=-" => Some(0x8007),
+            "<<=" => Some(0x800E),
+            _ => None,
+        } {
+            let y = self.expect_register()? as u16;
+            self.emit(base | x << 8 | y << 4);
+            return Ok(());
+        }
+
+        let operand = self.next()?;
+        let register = self.register(operand.text).map(u16::from);
+        match (operator.text, operand.text, register) {
+            (":=", _, Some(y)) => self.emit(0x8000 | x << 8 | y << 4),
+            (":=", "random", None) => {
+                let mask = self.byte()?;
+                self.emit(0xC000 | x << 8 | mask as u16);
+            }
+            (":=", "delay", None) => self.emit(0xF007 | x << 8),
+            (":=", "key", None) => self.emit(0xF00A | x << 8),
+            (":=", _, None) => {
+                let value = self.resolve(operand)?;
+                let byte = self.check_byte(value, operand.line)?;
+                self.emit(0x6000 | x << 8 | byte as u16);
+            }
+            ("+=", _, Some(y)) => self.emit(0x8004 | x << 8 | y << 4),
+            ("+=", _, None) => {
+                let value = self.resolve(operand)?;
+                let byte = self.check_byte(value, operand.line)?;
+                self.emit(0x7000 | x << 8 | byte as u16);
+            }
+            ("-=", _, Some(y)) => self.emit(0x8005 | x << 8 | y << 4),
+            ("-=", _, None) => {
+                let value = self.resolve(operand)?;
+                let byte = self.check_byte(value, operand.line)?;
+                self.emit(0x7000 | x << 8 | byte.wrapping_neg() as u16);
+            }
+            _ => {
+                return Err(syntax(
+                    line,
+                    &format!("unknown operator '{}'", operator.text),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    /// Compiles `i := ...` and `i += vx`.
+    fn index_assignment(&mut self) -> OctoResult<()> {
+        let operator = self.next()?;
+        match operator.text {
+            ":=" => {}
+            "+=" => {
+                let x = self.expect_register()? as u16;
+                self.emit(0xF01E | x << 8);
+                return Ok(());
+            }
+            _ => {
+                return Err(syntax(
+                    operator.line,
+                    &format!("unknown operator '{}' for i", operator.text),
+                ))
+            }
+        }
+
+        let operand = self.peek()?;
+        match operand.text {
+            "hex" | "bighex" => {
+                self.next()?;
+                let x = self.expect_register()? as u16;
+                let base = if operand.text == "hex" {
+                    0xF029
+                } else {
+                    0xF030
+                };
+                self.emit(base | x << 8);
+            }
+            "long" => {
+                self.next()?;
+                self.emit(0xF000);
+                let target = self.next()?;
+                match self.known_value(target)? {
+                    Some(value) => self.emit(value),
+                    None => {
+                        let name = self.check_name(target)?;
+                        self.fixups.push(Fixup {
+                            address: self.here,
+                            name: name.text,
+                            line: name.line,
+                            kind: FixupKind::Long,
+                        });
+                        self.emit(0x0000);
+                    }
+                }
+            }
+            _ => self.address_operand(0xA000)?,
+        }
+        Ok(())
+    }
+
+    /// Compiles `if ... then` and `if ... begin`.
+    fn if_statement(&mut self, line: usize) -> OctoResult<()> {
+        let condition = self.condition()?;
+        let keyword = self.next()?;
+        match keyword.text {
+            "then" => {
+                self.emit(condition.skip_if_false);
+                Ok(())
+            }
+            "begin" => {
+                self.emit(condition.skip_if_true);
+                self.blocks.push(Block::If {
+                    jump: self.here,
+                    line,
+                });
+                self.emit(0x1000);
+                Ok(())
+            }
+            _ => Err(syntax(keyword.line, "expected 'then' or 'begin'")),
+        }
+    }
+
+    /// Parses `vx == n`, `vx != vy`, `vx key` and the like.
+    fn condition(&mut self) -> OctoResult<Condition> {
+        let x = self.expect_register()? as u16;
+        let operator = self.next()?;
+
+        let (skip_if_true, skip_if_false) = match operator.text {
+            "key" => (0xE09E, 0xE0A1),
+            "-key" => (0xE0A1, 0xE09E),
+            "==" | "!=" => {
+                let operand = self.next()?;
+                let (equal, not_equal) = match self.register(operand.text) {
+                    Some(y) => (0x5000 | (y as u16) << 4, 0x9000 | (y as u16) << 4),
+                    None => {
+                        let value = self.resolve(operand)?;
+                        let byte = self.check_byte(value, operand.line)? as u16;
+                        (0x3000 | byte, 0x4000 | byte)
+                    }
+                };
+                if operator.text == "==" {
+                    (equal, not_equal)
+                } else {
+                    (not_equal, equal)
+                }
+            }
+            "<" | ">" | "<=" | ">=" => {
+                return Err(syntax(
+                    operator.line,
+                    &format!("'{}' comparisons are not supported", operator.text),
+                ))
+            }
+            _ => {
+                return Err(syntax(
+                    operator.line,
+                    &format!("unknown condition '{}'", operator.text),
+                ))
+            }
+        };
+
+        Ok(Condition {
+            skip_if_true: skip_if_true | x << 8,
+            skip_if_false: skip_if_false | x << 8,
+        })
+    }
+
+    /// Compiles `save vx`/`load vx`, or the XO-CHIP `vx - vy` range forms.
+    fn save_or_load(&mut self, single: u16, range: u16) -> OctoResult<()> {
+        let x = self.expect_register()? as u16;
+        if self.peek().is_ok_and(|token| token.text == "-") {
+            self.next()?;
+            let y = self.expect_register()? as u16;
+            self.emit(range | x << 8 | y << 4);
+        } else {
+            self.emit(single | x << 8);
+        }
+        Ok(())
+    }
+
+    /// Compiles an instruction that takes one register as X.
+    fn register_op(&mut self, base: u16) -> OctoResult<()> {
+        let x = self.expect_register()? as u16;
+        self.emit(base | x << 8);
+        Ok(())
+    }
+
+    /// Compiles an instruction with a 12-bit address operand.
+    fn address_operand(&mut self, base: u16) -> OctoResult<()> {
+        let token = self.next()?;
+        match self.known_value(token)? {
+            Some(value) if value <= 0x0FFF => self.emit(base | value),
+            Some(value) => {
+                return Err(OctoError::OutOfRange {
+                    line: token.line,
+                    value: value as i64,
+                })
+            }
+            None => {
+                let name = self.check_name(token)?;
+                self.emit_reference(base, name.text, name.line);
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets the next token.
+    fn next(&mut self) -> OctoResult<Token<'a>> {
+        let token = self.peek()?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    /// Gets the next token without consuming it.
+    fn peek(&self) -> OctoResult<Token<'a>> {
+        self.tokens
+            .get(self.position)
+            .copied()
+            .ok_or(OctoError::UnexpectedEnd)
+    }
+
+    /// Consumes a specific token.
+    fn expect(&mut self, text: &str) -> OctoResult<()> {
+        let token = self.next()?;
+        if token.text != text {
+            return Err(syntax(
+                token.line,
+                &format!("expected '{}', found '{}'", text, token.text),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Consumes a name for a label, constant or alias.
+    fn name(&mut self) -> OctoResult<Token<'a>> {
+        let token = self.next()?;
+        self.check_name(token)
+    }
+
+    /// Checks that a token can be used as a name.
+    fn check_name(&self, token: Token<'a>) -> OctoResult<Token<'a>> {
+        let valid = token
+            .text
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_alphabetic() || c == '_')
+            && token
+                .text
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+        if !valid || self.register(token.text).is_some() {
+            return Err(syntax(
+                token.line,
+                &format!("'{}' is not a valid name", token.text),
+            ));
+        }
+        Ok(token)
+    }
+
+    /// Parses a register name (`v0`-`vF`) or alias.
+    fn register(&self, text: &str) -> Option<u8> {
+        if let Some(&register) = self.aliases.get(text) {
+            return Some(register);
+        }
+        let digit = text.strip_prefix(['v', 'V'])?;
+        if digit.len() != 1 {
+            return None;
+        }
+        u8::from_str_radix(digit, 16).ok()
+    }
+
+    /// Consumes a register operand.
+    fn expect_register(&mut self) -> OctoResult<u8> {
+        let token = self.next()?;
+        self.register(token.text).ok_or_else(|| {
+            syntax(
+                token.line,
+                &format!("expected a register, found '{}'", token.text),
+            )
+        })
+    }
+
+    /// Consumes a number or constant.
+    fn value(&mut self) -> OctoResult<u16> {
+        let token = self.next()?;
+        let value = self.resolve(token)?;
+        u16::try_from(value).map_err(|_| OctoError::OutOfRange {
+            line: token.line,
+            value,
+        })
+    }
+
+    /// Consumes a value that fits in a byte.
+    fn byte(&mut self) -> OctoResult<u8> {
+        let token = self.next()?;
+        let value = self.resolve(token)?;
+        self.check_byte(value, token.line)
+    }
+
+    /// Consumes a value that fits in a nibble.
+    fn nibble(&mut self) -> OctoResult<u16> {
+        let token = self.next()?;
+        let value = self.resolve(token)?;
+        if !(0..=0xF).contains(&value) {
+            return Err(OctoError::OutOfRange {
+                line: token.line,
+                value,
+            });
+        }
+        Ok(value as u16)
+    }
+
+    /// Resolves a number, constant or already-defined label.
+    fn resolve(&self, token: Token<'a>) -> OctoResult<i64> {
+        if let Some(value) = parse_number(token.text) {
+            return Ok(value);
+        }
+        self.known_value(token)?
+            .map(i64::from)
+            .ok_or_else(|| OctoError::UndefinedName {
+                line: token.line,
+                name: token.text.to_string(),
+            })
+    }
+
+    /// Resolves a token if its value is known yet.
+    ///
+    /// # Returns
+    /// `None` for names that may be labels defined later.
+    fn known_value(&self, token: Token<'a>) -> OctoResult<Option<u16>> {
+        if let Some(value) = parse_number(token.text) {
+            return u16::try_from(value)
+                .map(Some)
+                .map_err(|_| OctoError::OutOfRange {
+                    line: token.line,
+                    value,
+                });
+        }
+        Ok(self
+            .constants
+            .get(token.text)
+            .or_else(|| self.labels.get(token.text))
+            .copied())
+    }
+
+    /// Checks that a value fits in a byte; negative values wrap.
+    fn check_byte(&self, value: i64, line: usize) -> OctoResult<u8> {
+        if !(-128..=255).contains(&value) {
+            return Err(OctoError::OutOfRange { line, value });
+        }
+        Ok(value as u8)
+    }
+
+    /// Writes a byte at `here` and advances.
+    fn emit_byte(&mut self, byte: u8) {
+        let offset = (self.here - ROM_BASE) as usize;
+        if self.rom.len() <= offset {
+            self.rom.resize(offset + 1, 0);
+        }
+        self.rom[offset] = byte;
+        self.here = self.here.wrapping_add(1);
+    }
+
+    /// Writes an instruction at `here` and advances.
+    fn emit(&mut self, opcode: u16) {
+        let [high, low] = opcode.to_be_bytes();
+        self.emit_byte(high);
+        self.emit_byte(low);
+    }
+
+    /// Writes an instruction whose address is patched in later.
+    fn emit_reference(&mut self, base: u16, name: &'a str, line: usize) {
+        self.fixups.push(Fixup {
+            address: self.here,
+            name,
+            line,
+            kind: FixupKind::Short,
+        });
+        self.emit(base);
+    }
+
+    /// Overwrites the 16-bit word at an address.
+    fn write_word(&mut self, address: u16, word: u16) {
+        let offset = (address - ROM_BASE) as usize;
+        self.rom[offset..offset + 2].copy_from_slice(&word.to_be_bytes());
+    }
+
+    /// Fills in the address of the instruction at `address`.
+    fn patch(&mut self, address: u16, target: u16, line: usize) -> OctoResult<()> {
+        if target > 0x0FFF {
+            return Err(OctoError::OutOfRange {
+                line,
+                value: target as i64,
+            });
+        }
+        let offset = (address - ROM_BASE) as usize;
+        let opcode = u16::from_be_bytes([self.rom[offset], self.rom[offset + 1]]);
+        self.write_word(address, (opcode & 0xF000) | target);
+        Ok(())
+    }
+}
+
+/// Parses a decimal, `0x` hex or `0b` binary number, optionally negative.
+fn parse_number(text: &str) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = digits.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2).ok()?
+    } else if digits.starts_with(|c: char| c.is_ascii_digit()) {
+        digits.parse().ok()?
+    } else {
+        return None;
+    };
+    Some(if negative { -value } else { value })
+}
+
+/// Builds a syntax error.
+fn syntax(line: usize, message: &str) -> OctoError {
+    OctoError::Syntax {
+        line,
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::Cpu;
+    use crate::hardware::NullHardware;
+
+    #[test]
+    fn test_compile_statements() {
+        let source = "
+            # Draw a glyph and count
+            :const SPEED 3
+            :alias counter v4
+            : main
+                clear
+                v0 := 10  v1 := v0  counter += SPEED  v2 -= 1
+                i := glyph
+                sprite v0 v1 5
+                i := hex v2
+                bcd v3  save v3  load v3
+                delay := v0  v5 := random 0xFF  v6 := key
+                draw
+            : halt
+                jump halt
+            : draw  ;
+            : glyph  0xF0 0x90 0b11110000 -1
+        ";
+        let rom = compile(source).unwrap();
+
+        let words: Vec<u16> = rom
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect();
+        assert_eq!(
+            words,
+            vec![
+                0x1202, // jump main
+                0x00E0, 0x600A, 0x8100, 0x7403, 0x72FF, 0xA224, 0xD015, 0xF229, 0xF333, 0xF355,
+                0xF365, 0xF015, 0xC5FF, 0xF60A, 0x2222, // draw
+                0x1220, // halt
+                0x00EE, // draw
+                0xF090, 0xF0FF, // glyph
+            ]
+        );
+    }
+
+    #[test]
+    fn test_control_flow() {
+        let source = "
+            : main
+                loop
+                    v0 += 1
+                    if v0 == 5 then v1 := 1
+                    if v0 key begin
+                        v2 := 1
+                    else
+                        v2 := 2
+                    end
+                    while v0 != 10
+                again
+                jump main
+        ";
+        let rom = compile(source).unwrap();
+        let mut cpu = Cpu::with_hardware(NullHardware::new());
+        cpu.load_rom(&rom).unwrap();
+        while cpu.get_state().pc != 0x218 {
+            cpu.cycle().unwrap();
+        }
+
+        let state = cpu.get_state();
+        assert_eq!(state.v[0], 10);
+        assert_eq!(state.v[1], 1);
+        // No key is held, so the else branch ran
+        assert_eq!(state.v[2], 2);
+    }
+
+    #[test]
+    fn test_extended_instructions() {
+        let source = ": main hires scroll-down 4 plane 2 save v1 - v3 i := long data : data";
+        let rom = compile(source).unwrap();
+        assert_eq!(
+            rom,
+            [0x12, 0x02, 0x00, 0xFF, 0x00, 0xC4, 0xF2, 0x01, 0x51, 0x32, 0xF0, 0x00, 0x02, 0x0E]
+        );
+    }
+
+    #[test]
+    fn test_compile_with_symbols() {
+        let (rom, symbols) = compile_with_symbols(": main draw : draw ;").unwrap();
+
+        assert_eq!(rom, [0x12, 0x02, 0x22, 0x04, 0x00, 0xEE]);
+        assert_eq!(symbols.address("main"), Some(0x202));
+        assert_eq!(symbols.disassemble(0x2204), "CALL draw");
+    }
+
+    #[test]
+    fn test_compile_errors() {
+        let error = |source| compile(source).unwrap_err();
+
+        assert_eq!(error(": start clear"), OctoError::MissingMain);
+        assert_eq!(
+            error(": main jump nowhere"),
+            OctoError::UndefinedName {
+                line: 1,
+                name: "nowhere".to_string()
+            }
+        );
+        assert_eq!(
+            error(": main\n  v0 := 300"),
+            OctoError::OutOfRange {
+                line: 2,
+                value: 300
+            }
+        );
+        assert_eq!(
+            error(": main : main"),
+            OctoError::Redefined {
+                line: 1,
+                name: "main".to_string()
+            }
+        );
+        assert!(matches!(
+            error(": main loop v0 += 1"),
+            OctoError::Unterminated { line: 1, .. }
+        ));
+        assert!(matches!(
+            error(": main\nif v0 < v1 then clear"),
+            OctoError::Syntax { line: 2, .. }
+        ));
+        assert_eq!(error(": main v0 :="), OctoError::UnexpectedEnd);
+    }
+}
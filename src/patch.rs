@@ -0,0 +1,411 @@
+//! IPS and BPS ROM patches.
+//!
+//! Community bug fixes and translations are shared as patches rather than
+//! modified ROMs. [`RomPatch`] reads either format, telling them apart by
+//! their header, and applies it to a ROM as it loads:
+//!
+//! - IPS is a list of records that overwrite bytes at an offset, with a
+//!   run-length form for fills and an optional size to truncate to. It has
+//!   no checksums, so a patch for another ROM applies without complaint.
+//! - BPS describes the new ROM as copies from the original, from itself
+//!   and from the patch, and records the size and CRC-32 of the ROM it
+//!   expects and the ROM it makes, so a mismatch is caught before running.
+
+use std::path::Path;
+
+use crate::analysis::crc32;
+use crate::error::{PatchError, PatchResult, Result};
+
+/// Header of IPS patches.
+const IPS_MAGIC: &[u8] = b"PATCH";
+
+/// Offset that marks the end of IPS records.
+const IPS_EOF: usize = 0x454F46;
+
+/// Header of BPS patches.
+const BPS_MAGIC: &[u8] = b"BPS1";
+
+/// Size of the three CRC-32s at the end of a BPS patch.
+const BPS_FOOTER: usize = 12;
+
+/// Largest ROM a patch may produce.
+const MAX_TARGET_SIZE: usize = 16 * 1024 * 1024;
+
+/// Format of a patch file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchFormat {
+    /// International Patching System.
+    Ips,
+
+    /// Beat patching system.
+    Bps,
+}
+
+impl PatchFormat {
+    /// Gets the format's name.
+    pub fn name(self) -> &'static str {
+        match self {
+            PatchFormat::Ips => "IPS",
+            PatchFormat::Bps => "BPS",
+        }
+    }
+}
+
+/// A patch file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomPatch {
+    /// Format, from the header.
+    format: PatchFormat,
+
+    /// The whole patch file.
+    data: Vec<u8>,
+}
+
+impl RomPatch {
+    /// Reads a patch file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::from_bytes(std::fs::read(path)?)?)
+    }
+
+    /// Takes a patch from memory, checking its header.
+    pub fn from_bytes(data: Vec<u8>) -> PatchResult<Self> {
+        let format = if data.starts_with(IPS_MAGIC) {
+            PatchFormat::Ips
+        } else if data.starts_with(BPS_MAGIC) {
+            PatchFormat::Bps
+        } else {
+            return Err(PatchError::UnknownFormat);
+        };
+        Ok(Self { format, data })
+    }
+
+    /// Gets the patch's format.
+    pub fn format(&self) -> PatchFormat {
+        self.format
+    }
+
+    /// Applies the patch to a ROM.
+    ///
+    /// # Returns
+    /// The patched ROM, or an error if the patch is malformed or, for BPS,
+    /// made for a different ROM.
+    pub fn apply(&self, rom: &[u8]) -> PatchResult<Vec<u8>> {
+        match self.format {
+            PatchFormat::Ips => apply_ips(&self.data, rom),
+            PatchFormat::Bps => apply_bps(&self.data, rom),
+        }
+    }
+}
+
+/// Reads bytes from a patch in order.
+struct Reader<'a> {
+    /// Bytes not yet read.
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    /// Takes the next `count` bytes.
+    fn take(&mut self, count: usize) -> PatchResult<&'a [u8]> {
+        if self.data.len() < count {
+            return Err(PatchError::Truncated);
+        }
+        let (taken, rest) = self.data.split_at(count);
+        self.data = rest;
+        Ok(taken)
+    }
+
+    /// Reads a big-endian number of `count` bytes.
+    fn be(&mut self, count: usize) -> PatchResult<usize> {
+        Ok(self
+            .take(count)?
+            .iter()
+            .fold(0, |value, &byte| value << 8 | byte as usize))
+    }
+
+    /// Reads a BPS variable-length number.
+    fn varint(&mut self) -> PatchResult<usize> {
+        let mut value = 0usize;
+        let mut shift = 1usize;
+        loop {
+            let byte = self.take(1)?[0];
+            let overflow = || PatchError::Malformed("number too large".to_string());
+            value = ((byte & 0x7F) as usize)
+                .checked_mul(shift)
+                .and_then(|part| value.checked_add(part))
+                .ok_or_else(overflow)?;
+            if byte & 0x80 != 0 {
+                return Ok(value);
+            }
+            shift = shift.checked_mul(128).ok_or_else(overflow)?;
+            value = value.checked_add(shift).ok_or_else(overflow)?;
+        }
+    }
+}
+
+/// Applies an IPS patch.
+fn apply_ips(patch: &[u8], rom: &[u8]) -> PatchResult<Vec<u8>> {
+    let mut reader = Reader {
+        data: &patch[IPS_MAGIC.len()..],
+    };
+    let mut output = rom.to_vec();
+    loop {
+        let offset = reader.be(3)?;
+        if offset == IPS_EOF {
+            break;
+        }
+        let size = reader.be(2)?;
+        let (length, fill) = if size == 0 {
+            // Run-length record: a count and the byte to repeat
+            (reader.be(2)?, Some(reader.take(1)?[0]))
+        } else {
+            (size, None)
+        };
+        let end = offset + length;
+        if end > MAX_TARGET_SIZE {
+            return Err(PatchError::Malformed(format!(
+                "record at {:#x} is past the largest ROM",
+                offset
+            )));
+        }
+        if output.len() < end {
+            output.resize(end, 0);
+        }
+        match fill {
+            Some(byte) => output[offset..end].fill(byte),
+            None => output[offset..end].copy_from_slice(reader.take(length)?),
+        }
+    }
+
+    // Optional extension: the size to truncate the ROM to
+    if reader.data.len() >= 3 {
+        let size = reader.be(3)?;
+        output.truncate(size);
+    }
+    Ok(output)
+}
+
+/// Applies a BPS patch.
+fn apply_bps(patch: &[u8], rom: &[u8]) -> PatchResult<Vec<u8>> {
+    if patch.len() < BPS_MAGIC.len() + BPS_FOOTER {
+        return Err(PatchError::Truncated);
+    }
+    let (body, footer) = patch.split_at(patch.len() - BPS_FOOTER);
+    let checksum = |index: usize| {
+        let bytes = &footer[index * 4..index * 4 + 4];
+        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    };
+    let (source_crc, target_crc, patch_crc) = (checksum(0), checksum(1), checksum(2));
+    verify("Patch", patch_crc, crc32(&patch[..patch.len() - 4]))?;
+
+    let mut reader = Reader {
+        data: &body[BPS_MAGIC.len()..],
+    };
+    let source_size = reader.varint()?;
+    let target_size = reader.varint()?;
+    let metadata_size = reader.varint()?;
+    reader.take(metadata_size)?;
+
+    if source_size != rom.len() {
+        return Err(PatchError::SourceSize {
+            expected: source_size,
+            found: rom.len(),
+        });
+    }
+    verify("Source ROM", source_crc, crc32(rom))?;
+    if target_size > MAX_TARGET_SIZE {
+        return Err(PatchError::Malformed(format!(
+            "target of {} bytes is too large",
+            target_size
+        )));
+    }
+
+    let malformed = |what: &str| PatchError::Malformed(format!("{} out of range", what));
+    let mut output = Vec::with_capacity(target_size);
+    let mut source_offset = 0usize;
+    let mut target_offset = 0usize;
+    while !reader.data.is_empty() {
+        let action = reader.varint()?;
+        let length = (action >> 2) + 1;
+        if output.len() + length > target_size {
+            return Err(malformed("write"));
+        }
+        match action & 3 {
+            // SourceRead: the original's bytes at the same position
+            0 => {
+                let start = output.len();
+                let bytes = rom
+                    .get(start..start + length)
+                    .ok_or_else(|| malformed("source read"))?;
+                output.extend_from_slice(bytes);
+            }
+            // TargetRead: bytes stored in the patch
+            1 => output.extend_from_slice(reader.take(length)?),
+            // SourceCopy: the original's bytes from anywhere
+            2 => {
+                source_offset = relative(source_offset, reader.varint()?)
+                    .ok_or_else(|| malformed("source copy"))?;
+                let bytes = rom
+                    .get(source_offset..source_offset + length)
+                    .ok_or_else(|| malformed("source copy"))?;
+                output.extend_from_slice(bytes);
+                source_offset += length;
+            }
+            // TargetCopy: bytes already written, which may overlap the copy
+            _ => {
+                target_offset = relative(target_offset, reader.varint()?)
+                    .filter(|&offset| offset < output.len())
+                    .ok_or_else(|| malformed("target copy"))?;
+                for _ in 0..length {
+                    output.push(output[target_offset]);
+                    target_offset += 1;
+                }
+            }
+        }
+    }
+
+    if output.len() != target_size {
+        return Err(PatchError::Malformed(format!(
+            "made {} bytes, expected {}",
+            output.len(),
+            target_size
+        )));
+    }
+    verify("Patched ROM", target_crc, crc32(&output))?;
+    Ok(output)
+}
+
+/// Moves an offset by a BPS signed delta: the low bit is the sign.
+fn relative(offset: usize, delta: usize) -> Option<usize> {
+    if delta & 1 != 0 {
+        offset.checked_sub(delta >> 1)
+    } else {
+        offset.checked_add(delta >> 1)
+    }
+}
+
+/// Checks a CRC-32 against the expected one.
+fn verify(what: &'static str, expected: u32, found: u32) -> PatchResult<()> {
+    if expected != found {
+        return Err(PatchError::ChecksumMismatch {
+            what,
+            expected,
+            found,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ips() {
+        let mut patch = IPS_MAGIC.to_vec();
+        // Overwrite two bytes at 1
+        patch.extend_from_slice(&[0x00, 0x00, 0x01, 0x00, 0x02, 0xAA, 0xBB]);
+        // Fill three bytes at 5 with 0x11, growing the ROM
+        patch.extend_from_slice(&[0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x03, 0x11]);
+        patch.extend_from_slice(b"EOF");
+        let patch = RomPatch::from_bytes(patch).unwrap();
+        assert_eq!(patch.format(), PatchFormat::Ips);
+        assert_eq!(
+            patch.apply(&[0, 1, 2, 3]).unwrap(),
+            vec![0, 0xAA, 0xBB, 3, 0, 0x11, 0x11, 0x11]
+        );
+
+        // The truncation extension cuts the ROM short
+        let mut truncating = IPS_MAGIC.to_vec();
+        truncating.extend_from_slice(b"EOF");
+        truncating.extend_from_slice(&[0x00, 0x00, 0x02]);
+        let truncating = RomPatch::from_bytes(truncating).unwrap();
+        assert_eq!(truncating.apply(&[1, 2, 3, 4]).unwrap(), vec![1, 2]);
+
+        let unterminated = RomPatch::from_bytes(b"PATCH\x00\x00\x01\x00\x05\xAA".to_vec());
+        assert_eq!(
+            unterminated.unwrap().apply(&[0; 4]),
+            Err(PatchError::Truncated)
+        );
+        assert_eq!(
+            RomPatch::from_bytes(b"not a patch".to_vec()),
+            Err(PatchError::UnknownFormat)
+        );
+    }
+
+    /// Encodes a BPS variable-length number.
+    fn varint(mut value: usize, out: &mut Vec<u8>) {
+        loop {
+            let low = (value & 0x7F) as u8;
+            value >>= 7;
+            if value == 0 {
+                out.push(low | 0x80);
+                return;
+            }
+            out.push(low);
+            value -= 1;
+        }
+    }
+
+    /// Builds a BPS patch from its actions.
+    fn bps(source: &[u8], target: &[u8], actions: &[u8]) -> Vec<u8> {
+        let mut patch = BPS_MAGIC.to_vec();
+        varint(source.len(), &mut patch);
+        varint(target.len(), &mut patch);
+        varint(0, &mut patch);
+        patch.extend_from_slice(actions);
+        patch.extend_from_slice(&crc32(source).to_le_bytes());
+        patch.extend_from_slice(&crc32(target).to_le_bytes());
+        let patch_crc = crc32(&patch);
+        patch.extend_from_slice(&patch_crc.to_le_bytes());
+        patch
+    }
+
+    #[test]
+    fn test_bps() {
+        let source = b"ABCDEFGH";
+        let target = b"ABCDxyGHGHGH";
+        let mut actions = Vec::new();
+        // SourceRead 4: ABCD
+        varint((4 - 1) << 2, &mut actions);
+        // TargetRead 2: xy
+        varint(((2 - 1) << 2) | 1, &mut actions);
+        actions.extend_from_slice(b"xy");
+        // SourceCopy 2 from offset 6: GH
+        varint(((2 - 1) << 2) | 2, &mut actions);
+        varint(6 << 1, &mut actions);
+        // TargetCopy 4 from offset 6, overlapping itself: GHGH
+        varint(((4 - 1) << 2) | 3, &mut actions);
+        varint(6 << 1, &mut actions);
+
+        let data = bps(source, target, &actions);
+        let patch = RomPatch::from_bytes(data.clone()).unwrap();
+        assert_eq!(patch.format(), PatchFormat::Bps);
+        assert_eq!(patch.apply(source).unwrap(), target.to_vec());
+
+        // A different ROM of the same size is caught by its checksum
+        assert!(matches!(
+            patch.apply(b"abcdefgh"),
+            Err(PatchError::ChecksumMismatch {
+                what: "Source ROM",
+                ..
+            })
+        ));
+        assert_eq!(
+            patch.apply(b"ABC"),
+            Err(PatchError::SourceSize {
+                expected: 8,
+                found: 3
+            })
+        );
+
+        // So is a damaged patch
+        let mut damaged = data;
+        let last_action = damaged.len() - BPS_FOOTER - 1;
+        damaged[last_action] ^= 0x02;
+        let damaged = RomPatch::from_bytes(damaged).unwrap();
+        assert!(matches!(
+            damaged.apply(source),
+            Err(PatchError::ChecksumMismatch { what: "Patch", .. })
+        ));
+    }
+}
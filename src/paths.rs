@@ -0,0 +1,320 @@
+//! Where the emulator keeps its files.
+//!
+//! [`Paths`] resolves three per-user directories the way each platform
+//! expects:
+//!
+//! | | Linux and other Unixes | Windows | macOS |
+//! |---|---|---|---|
+//! | Config | `$XDG_CONFIG_HOME/chip8` or `~/.config/chip8` | `%APPDATA%\chip8` | `~/Library/Application Support/chip8` |
+//! | Data | `$XDG_DATA_HOME/chip8` or `~/.local/share/chip8` | `%APPDATA%\chip8` | `~/Library/Application Support/chip8` |
+//! | Cache | `$XDG_CACHE_HOME/chip8` or `~/.cache/chip8` | `%LOCALAPPDATA%\chip8\cache` | `~/Library/Caches/chip8` |
+//!
+//! The config directory holds `chip8.toml`, loaded when no `--config` is
+//! given. Save states, the session manifest, extracted demos and
+//! screenshots go in the data directory, and the ROM analysis cache in the
+//! cache directory. `CHIP8_CONFIG_DIR`, `CHIP8_DATA_DIR` and
+//! `CHIP8_CACHE_DIR` replace a directory, and so do `data_dir` and
+//! `cache_dir` in the `[paths]` config section, with the environment
+//! taking precedence.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use crate::logging::APP;
+use serde::{Deserialize, Serialize};
+
+/// Name of the emulator's directory in each location.
+pub const APP_DIR: &str = "chip8";
+
+/// Name of the config file in the config directory.
+pub const CONFIG_FILE: &str = "chip8.toml";
+
+/// Variable that replaces the config directory.
+pub const CONFIG_DIR_ENV: &str = "CHIP8_CONFIG_DIR";
+
+/// Variable that replaces the data directory.
+pub const DATA_DIR_ENV: &str = "CHIP8_DATA_DIR";
+
+/// Variable that replaces the cache directory.
+pub const CACHE_DIR_ENV: &str = "CHIP8_CACHE_DIR";
+
+/// Config files in the working directory that older versions loaded.
+pub const LEGACY_CONFIG_FILES: [&str; 3] =
+    ["chip8.toml", "config/chip8.toml", ".config/chip8.toml"];
+
+/// Directory used when the platform's locations can't be found.
+const FALLBACK_DIR: &str = ".chip8";
+
+/// Platform conventions for per-user directories.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    /// XDG base directories, used on Linux and other Unixes.
+    Xdg,
+
+    /// Roaming and local AppData.
+    Windows,
+
+    /// Application Support and Caches in the home Library.
+    MacOs,
+}
+
+impl Platform {
+    /// Gets the conventions of the platform being run on.
+    pub fn current() -> Self {
+        if cfg!(target_os = "windows") {
+            Platform::Windows
+        } else if cfg!(target_os = "macos") {
+            Platform::MacOs
+        } else {
+            Platform::Xdg
+        }
+    }
+}
+
+/// Directories set in the config file, replacing the platform's.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PathsConfig {
+    /// Directory for save states, screenshots and other data.
+    pub data_dir: Option<PathBuf>,
+
+    /// Directory for the ROM analysis cache.
+    pub cache_dir: Option<PathBuf>,
+}
+
+/// Per-user config, data and cache directories.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Paths {
+    /// Directory holding the config file.
+    config_dir: PathBuf,
+
+    /// Directory for save states, screenshots and other data.
+    data_dir: PathBuf,
+
+    /// Directory for files that can be rebuilt.
+    cache_dir: PathBuf,
+}
+
+impl Paths {
+    /// Resolves the directories from the environment and the platform.
+    pub fn new() -> Self {
+        Self::for_config(&PathsConfig::default())
+    }
+
+    /// Resolves the directories, with those set in the config replacing
+    /// the platform's.
+    pub fn for_config(config: &PathsConfig) -> Self {
+        Self::resolve(Platform::current(), config, |name| std::env::var_os(name))
+    }
+
+    /// Resolves the directories for a platform, reading variables through
+    /// `lookup` instead of the process environment.
+    pub fn resolve(
+        platform: Platform,
+        config: &PathsConfig,
+        lookup: impl Fn(&str) -> Option<OsString>,
+    ) -> Self {
+        let set = |name: &str| {
+            lookup(name)
+                .filter(|value| !value.is_empty())
+                .map(PathBuf::from)
+        };
+        // The XDG spec says to ignore relative paths
+        let xdg = |name: &str| set(name).filter(|path| path.is_absolute());
+        let home = set("HOME");
+
+        let (config_dir, data_dir, cache_dir) = match platform {
+            Platform::Xdg => {
+                let base = |variable: &str, default: &str| {
+                    xdg(variable)
+                        .or_else(|| home.as_ref().map(|home| home.join(default)))
+                        .map(|base| base.join(APP_DIR))
+                };
+                (
+                    base("XDG_CONFIG_HOME", ".config"),
+                    base("XDG_DATA_HOME", ".local/share"),
+                    base("XDG_CACHE_HOME", ".cache"),
+                )
+            }
+            Platform::Windows => {
+                let roaming = set("APPDATA").map(|dir| dir.join(APP_DIR));
+                let local = set("LOCALAPPDATA")
+                    .map(|dir| dir.join(APP_DIR))
+                    .or_else(|| roaming.clone());
+                (roaming.clone(), roaming, local.map(|dir| dir.join("cache")))
+            }
+            Platform::MacOs => {
+                let library = home.as_ref().map(|home| home.join("Library"));
+                let support = library
+                    .as_ref()
+                    .map(|library| library.join("Application Support").join(APP_DIR));
+                let caches = library.map(|library| library.join("Caches").join(APP_DIR));
+                (support.clone(), support, caches)
+            }
+        };
+
+        let fallback = PathBuf::from(FALLBACK_DIR);
+        Self {
+            config_dir: set(CONFIG_DIR_ENV)
+                .or(config_dir)
+                .unwrap_or_else(|| fallback.clone()),
+            data_dir: set(DATA_DIR_ENV)
+                .or_else(|| config.data_dir.clone())
+                .or(data_dir)
+                .unwrap_or_else(|| fallback.clone()),
+            cache_dir: set(CACHE_DIR_ENV)
+                .or_else(|| config.cache_dir.clone())
+                .or(cache_dir)
+                .unwrap_or_else(|| fallback.join("cache")),
+        }
+    }
+
+    /// Gets the directory holding the config file.
+    pub fn config_dir(&self) -> &Path {
+        &self.config_dir
+    }
+
+    /// Gets the directory for save states, screenshots and other data.
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    /// Gets the directory for files that can be rebuilt.
+    pub fn cache_dir(&self) -> &Path {
+        &self.cache_dir
+    }
+
+    /// Gets the path of the config file.
+    pub fn config_file(&self) -> PathBuf {
+        self.config_dir.join(CONFIG_FILE)
+    }
+
+    /// Gets the directory save states are kept in.
+    pub fn states_dir(&self) -> PathBuf {
+        self.data_dir.join("states")
+    }
+
+    /// Gets the directory screenshots are saved in.
+    pub fn screenshots_dir(&self) -> PathBuf {
+        self.data_dir.join("screenshots")
+    }
+
+    /// Gets the directory built-in demos are extracted to.
+    pub fn demos_dir(&self) -> PathBuf {
+        self.data_dir.join("demos")
+    }
+
+    /// Finds the config file to load when no `--config` is given.
+    ///
+    /// A config file in the working directory, where older versions looked,
+    /// is still used if there is none in the config directory, with a
+    /// warning to move it.
+    ///
+    /// # Returns
+    /// The config file, or `None` if there isn't one.
+    pub fn find_config_file(&self) -> Option<PathBuf> {
+        let file = self.config_file();
+        if file.is_file() {
+            return Some(file);
+        }
+        let legacy = LEGACY_CONFIG_FILES
+            .iter()
+            .map(PathBuf::from)
+            .find(|path| path.is_file())?;
+        tracing::warn!(
+            target: APP,
+            "Loading {} from the working directory; move it to {}",
+            legacy.display(),
+            file.display()
+        );
+        Some(legacy)
+    }
+}
+
+impl Default for Paths {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn resolve(platform: Platform, config: &PathsConfig, vars: &[(&str, &str)]) -> Paths {
+        let vars: HashMap<&str, &str> = vars.iter().copied().collect();
+        Paths::resolve(platform, config, |name| vars.get(name).map(OsString::from))
+    }
+
+    #[test]
+    fn test_platform_dirs() {
+        let none = PathsConfig::default();
+        let xdg = resolve(Platform::Xdg, &none, &[("HOME", "/home/ada")]);
+        assert_eq!(
+            xdg.config_file(),
+            Path::new("/home/ada/.config/chip8/chip8.toml")
+        );
+        assert_eq!(xdg.data_dir(), Path::new("/home/ada/.local/share/chip8"));
+        assert_eq!(xdg.cache_dir(), Path::new("/home/ada/.cache/chip8"));
+        let xdg = resolve(
+            Platform::Xdg,
+            &none,
+            &[
+                ("HOME", "/home/ada"),
+                ("XDG_DATA_HOME", "/data"),
+                ("XDG_CACHE_HOME", "relative"),
+            ],
+        );
+        assert_eq!(xdg.states_dir(), Path::new("/data/chip8/states"));
+        assert_eq!(xdg.cache_dir(), Path::new("/home/ada/.cache/chip8"));
+
+        let windows = resolve(
+            Platform::Windows,
+            &none,
+            &[("APPDATA", "C:/Roaming"), ("LOCALAPPDATA", "C:/Local")],
+        );
+        assert_eq!(windows.config_dir(), Path::new("C:/Roaming/chip8"));
+        assert_eq!(windows.data_dir(), Path::new("C:/Roaming/chip8"));
+        assert_eq!(windows.cache_dir(), Path::new("C:/Local/chip8/cache"));
+
+        let macos = resolve(Platform::MacOs, &none, &[("HOME", "/Users/ada")]);
+        assert_eq!(
+            macos.screenshots_dir(),
+            Path::new("/Users/ada/Library/Application Support/chip8/screenshots")
+        );
+        assert_eq!(
+            macos.cache_dir(),
+            Path::new("/Users/ada/Library/Caches/chip8")
+        );
+
+        let nowhere = resolve(Platform::Xdg, &none, &[]);
+        assert_eq!(nowhere.data_dir(), Path::new(".chip8"));
+        assert_eq!(nowhere.cache_dir(), Path::new(".chip8/cache"));
+    }
+
+    #[test]
+    fn test_overrides() {
+        let config = PathsConfig {
+            data_dir: Some(PathBuf::from("/srv/chip8")),
+            cache_dir: Some(PathBuf::from("/tmp/chip8")),
+        };
+        let paths = resolve(
+            Platform::Xdg,
+            &config,
+            &[
+                ("HOME", "/home/ada"),
+                (CACHE_DIR_ENV, "/var/cache/chip8"),
+                (CONFIG_DIR_ENV, "/etc/chip8"),
+                (DATA_DIR_ENV, ""),
+            ],
+        );
+        assert_eq!(paths.config_file(), Path::new("/etc/chip8/chip8.toml"));
+        assert_eq!(paths.data_dir(), Path::new("/srv/chip8"));
+        assert_eq!(paths.cache_dir(), Path::new("/var/cache/chip8"));
+
+        let config: PathsConfig = toml::from_str("data_dir = \"saves\"").unwrap();
+        assert_eq!(config.data_dir, Some(PathBuf::from("saves")));
+        assert_eq!(config.cache_dir, None);
+    }
+}
@@ -0,0 +1,258 @@
+//! Deterministic replay files.
+//!
+//! A `.c8replay` file holds everything needed to reproduce a run exactly:
+//! the ROM's SHA-1, the behavior settings and quirks, the RNG seed, the
+//! keys held on each frame and the bytes RND produced. Playing the bytes
+//! back rather than reseeding keeps a replay in sync even if the seeded
+//! generator changes; files without them fall back to the seed. Like netplay, replays run in lockstep
+//! frames (a frame's worth of clock cycles, then one timer tick), so
+//! playback doesn't depend on the speed of the host.
+//!
+//! Files are TOML. The `format` tag and `version` are checked before the
+//! rest of the file is read, so newer files fail with a clear message.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::analyze_rom;
+use crate::emulator::snapshot::hex_bytes;
+use crate::emulator::RecordedRandom;
+use crate::error::{ReplayError, ReplayResult, Result};
+use crate::frontend::EmulatorBehaviorConfig;
+
+/// Format tag written at the top of every replay file.
+pub const REPLAY_FORMAT: &str = "c8replay";
+
+/// Current replay format version.
+pub const REPLAY_VERSION: u32 = 1;
+
+/// File extension for replay files.
+pub const REPLAY_EXTENSION: &str = "c8replay";
+
+/// Whether a run records a new replay or plays one back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayMode {
+    /// Record the run and save it to a file on exit.
+    Record(PathBuf),
+
+    /// Play back a recorded run.
+    Play(PathBuf),
+}
+
+/// A change in the held keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputEvent {
+    /// Frame from which the keys are held.
+    pub frame: u32,
+
+    /// Key mask where bit N is key N.
+    pub keys: u16,
+}
+
+/// A recorded run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    /// Format tag, always `REPLAY_FORMAT`.
+    pub format: String,
+
+    /// Format version the file was written with.
+    pub version: u32,
+
+    /// SHA-1 of the ROM, as lowercase hex.
+    pub rom_sha1: String,
+
+    /// RNG seed, stored as hex since TOML integers are signed.
+    #[serde(with = "hex_seed")]
+    pub seed: u64,
+
+    /// Number of frames recorded.
+    pub frames: u32,
+
+    /// Emulator behavior settings, including quirks.
+    pub behavior: EmulatorBehaviorConfig,
+
+    /// Key changes in frame order.
+    #[serde(default)]
+    pub inputs: Vec<InputEvent>,
+
+    /// Bytes RND produced during the run, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty", with = "hex_bytes")]
+    pub random: Vec<u8>,
+}
+
+/// Header read before the rest of a replay file.
+#[derive(Deserialize)]
+struct ReplayHeader {
+    #[serde(default)]
+    format: String,
+
+    #[serde(default)]
+    version: u32,
+}
+
+impl Replay {
+    /// Creates an empty replay for a ROM.
+    ///
+    /// # Arguments
+    /// * `rom` - ROM data
+    /// * `behavior` - Emulator settings the run uses
+    /// * `seed` - RNG seed the run starts with
+    pub fn new(rom: &[u8], behavior: &EmulatorBehaviorConfig, seed: u64) -> Self {
+        Self {
+            format: REPLAY_FORMAT.to_string(),
+            version: REPLAY_VERSION,
+            rom_sha1: analyze_rom(rom).sha1,
+            seed,
+            frames: 0,
+            behavior: behavior.clone(),
+            inputs: Vec::new(),
+            random: Vec::new(),
+        }
+    }
+
+    /// Appends a frame with the keys held during it.
+    pub fn record(&mut self, keys: u16) {
+        if self.inputs.last().map_or(0, |event| event.keys) != keys {
+            self.inputs.push(InputEvent {
+                frame: self.frames,
+                keys,
+            });
+        }
+        self.frames += 1;
+    }
+
+    /// Gets the keys held during a frame.
+    pub fn keys_at(&self, frame: u32) -> u16 {
+        let next = self.inputs.partition_point(|event| event.frame <= frame);
+        next.checked_sub(1).map_or(0, |i| self.inputs[i].keys)
+    }
+
+    /// Creates the source RND reads from during playback: the recorded
+    /// bytes, then the seed once they run out.
+    pub fn random_source(&self) -> RecordedRandom {
+        RecordedRandom::new(self.random.clone(), self.seed)
+    }
+
+    /// Checks that a ROM is the one the replay was recorded with.
+    pub fn check_rom(&self, rom: &[u8]) -> ReplayResult<()> {
+        let found = analyze_rom(rom).sha1;
+        if found != self.rom_sha1 {
+            return Err(ReplayError::RomMismatch {
+                expected: self.rom_sha1.clone(),
+                found,
+            });
+        }
+        Ok(())
+    }
+
+    /// Parses a replay from TOML, checking the format tag and version first.
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let header: ReplayHeader = toml::from_str(text)?;
+        if header.format != REPLAY_FORMAT {
+            return Err(ReplayError::NotAReplay.into());
+        }
+        if header.version == 0 || header.version > REPLAY_VERSION {
+            return Err(ReplayError::UnsupportedVersion {
+                found: header.version,
+                supported: REPLAY_VERSION,
+            }
+            .into());
+        }
+        Ok(toml::from_str(text)?)
+    }
+
+    /// Writes the replay as TOML.
+    pub fn to_toml(&self) -> Result<String> {
+        Ok(toml::to_string(self)?)
+    }
+
+    /// Loads a replay file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_toml(&std::fs::read_to_string(path)?)
+    }
+
+    /// Saves the replay to a file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        std::fs::write(path, self.to_toml()?)?;
+        Ok(())
+    }
+}
+
+/// Serializes a seed as a hex string.
+mod hex_seed {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(seed: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{:016x}", seed))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        u64::from_str_radix(text.trim_start_matches("0x"), 16).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::EmulatorError;
+
+    const ROM: [u8; 2] = [0x12, 0x00];
+
+    #[test]
+    fn test_record_and_keys_at() {
+        let mut replay = Replay::new(&ROM, &EmulatorBehaviorConfig::default(), 7);
+        for keys in [0, 0, 0b10, 0b10, 0b11, 0] {
+            replay.record(keys);
+        }
+
+        assert_eq!(replay.frames, 6);
+        // Only changes are stored
+        assert_eq!(replay.inputs.len(), 3);
+        let keys: Vec<u16> = (0..7).map(|frame| replay.keys_at(frame)).collect();
+        assert_eq!(keys, [0, 0, 0b10, 0b10, 0b11, 0, 0]);
+    }
+
+    #[test]
+    fn test_toml_round_trip() {
+        let mut replay = Replay::new(&ROM, &EmulatorBehaviorConfig::classic(), u64::MAX);
+        replay.record(0x8001);
+        replay.random = vec![0x00, 0x7F, 0xFF];
+
+        let text = replay.to_toml().unwrap();
+        assert!(text.starts_with("format = \"c8replay\"\nversion = 1\n"));
+        let loaded = Replay::from_toml(&text).unwrap();
+        assert_eq!(loaded.seed, u64::MAX);
+        assert_eq!(loaded.rom_sha1, replay.rom_sha1);
+        assert_eq!(loaded.inputs, replay.inputs);
+        assert!(text.contains("random = \"007fff\""));
+        assert_eq!(loaded.random, replay.random);
+        assert_eq!(loaded.behavior.cpu_speed, 500);
+        assert!(loaded.check_rom(&ROM).is_ok());
+        assert!(matches!(
+            loaded.check_rom(&[0x00, 0xE0]),
+            Err(ReplayError::RomMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_rejects_other_formats_and_versions() {
+        let text = Replay::new(&ROM, &EmulatorBehaviorConfig::default(), 1)
+            .to_toml()
+            .unwrap();
+
+        let newer = text.replace("version = 1", "version = 2");
+        assert!(matches!(
+            Replay::from_toml(&newer),
+            Err(EmulatorError::Replay(ReplayError::UnsupportedVersion {
+                found: 2,
+                supported: REPLAY_VERSION
+            }))
+        ));
+        assert!(matches!(
+            Replay::from_toml("cpu_speed = 700"),
+            Err(EmulatorError::Replay(ReplayError::NotAReplay))
+        ));
+    }
+}
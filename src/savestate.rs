@@ -0,0 +1,394 @@
+//! Save state files and numbered save slots.
+//!
+//! A `.c8state` file holds a machine [`Snapshot`] tagged with the SHA-1 of
+//! the ROM it was taken from and the version of the core that wrote it.
+//! States only load into the same ROM on a compatible core, since the
+//! machine state means nothing to another program and may not match what
+//! another core expects.
+//!
+//! Files are TOML, like replays. The `format` tag, `version` and
+//! `core_version` are checked before the rest of the file is read.
+
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::sha1_hex;
+use crate::emulator::Snapshot;
+use crate::error::{Result, SaveStateError, SaveStateResult};
+
+/// Format tag written at the top of every save state file.
+pub const STATE_FORMAT: &str = "c8state";
+
+/// Current save state format version.
+pub const STATE_VERSION: u32 = 1;
+
+/// File extension for save state files.
+pub const STATE_EXTENSION: &str = "c8state";
+
+/// Version of the core writing save states.
+pub const CORE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Number of save slots per ROM.
+pub const SLOT_COUNT: u8 = 10;
+
+/// A saved machine state.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveState {
+    /// Format tag, always `STATE_FORMAT`.
+    pub format: String,
+
+    /// Format version the file was written with.
+    pub version: u32,
+
+    /// Version of the core that wrote the file.
+    pub core_version: String,
+
+    /// SHA-1 of the ROM, as lowercase hex.
+    pub rom_sha1: String,
+
+    /// When the state was saved, in seconds since the Unix epoch.
+    #[serde(default)]
+    pub saved_at: u64,
+
+    /// The machine state.
+    pub snapshot: Snapshot,
+}
+
+/// Header read before the rest of a save state file.
+#[derive(Deserialize)]
+struct StateHeader {
+    #[serde(default)]
+    format: String,
+
+    #[serde(default)]
+    version: u32,
+
+    #[serde(default)]
+    core_version: String,
+}
+
+impl SaveState {
+    /// Creates a save state for a ROM, stamped with the current time.
+    ///
+    /// # Arguments
+    /// * `rom_sha1` - SHA-1 of the running ROM, as lowercase hex
+    /// * `snapshot` - Machine state to save
+    pub fn new(rom_sha1: &str, snapshot: Snapshot) -> Self {
+        let saved_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+
+        Self {
+            format: STATE_FORMAT.to_string(),
+            version: STATE_VERSION,
+            core_version: CORE_VERSION.to_string(),
+            rom_sha1: rom_sha1.to_string(),
+            saved_at,
+            snapshot,
+        }
+    }
+
+    /// Checks that the state was saved with the given ROM.
+    pub fn check_rom(&self, rom_sha1: &str) -> SaveStateResult<()> {
+        if self.rom_sha1 != rom_sha1 {
+            return Err(SaveStateError::RomMismatch {
+                expected: self.rom_sha1.clone(),
+                found: rom_sha1.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Parses a save state from TOML, checking the format tag and versions first.
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let header: StateHeader = toml::from_str(text)?;
+        if header.format != STATE_FORMAT {
+            return Err(SaveStateError::NotASaveState.into());
+        }
+        if header.version == 0 || header.version > STATE_VERSION {
+            return Err(SaveStateError::UnsupportedVersion {
+                found: header.version,
+                supported: STATE_VERSION,
+            }
+            .into());
+        }
+        if !core_compatible(&header.core_version, CORE_VERSION) {
+            return Err(SaveStateError::IncompatibleCore {
+                found: header.core_version,
+                current: CORE_VERSION.to_string(),
+            }
+            .into());
+        }
+        Ok(toml::from_str(text)?)
+    }
+
+    /// Writes the save state as TOML.
+    pub fn to_toml(&self) -> Result<String> {
+        Ok(toml::to_string(self)?)
+    }
+
+    /// Loads a save state file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_toml(&std::fs::read_to_string(path)?)
+    }
+
+    /// Saves the state to a file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        std::fs::write(path, self.to_toml()?)?;
+        Ok(())
+    }
+}
+
+/// Checks if states written by one core version can be loaded by another.
+///
+/// Versions are compatible when their major versions match, or, before
+/// 1.0, when their minor versions match as well.
+pub fn core_compatible(found: &str, current: &str) -> bool {
+    let parse = |version: &str| -> Option<(u64, u64)> {
+        let mut parts = version.split('.');
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next()?.parse().ok()?;
+        Some((major, minor))
+    };
+
+    match (parse(found), parse(current)) {
+        (Some((0, found_minor)), Some((0, current_minor))) => found_minor == current_minor,
+        (Some((found_major, _)), Some((current_major, _))) => found_major == current_major,
+        _ => false,
+    }
+}
+
+/// Numbered save slots for the running ROM.
+///
+/// Each ROM gets its own set of slots in the states directory, named by
+/// the ROM's SHA-1, so states from different ROMs never overwrite each
+/// other.
+#[derive(Debug, Clone)]
+pub struct SaveStateManager {
+    /// Directory the state files live in.
+    dir: PathBuf,
+
+    /// SHA-1 of the running ROM, as lowercase hex.
+    rom_sha1: String,
+
+    /// Slot saves and loads use.
+    slot: u8,
+}
+
+impl SaveStateManager {
+    /// Creates a manager for a ROM, starting on slot 0.
+    ///
+    /// # Arguments
+    /// * `dir` - Directory to keep state files in
+    /// * `rom` - ROM data
+    pub fn new(dir: impl Into<PathBuf>, rom: &[u8]) -> Self {
+        Self {
+            dir: dir.into(),
+            rom_sha1: sha1_hex(rom),
+            slot: 0,
+        }
+    }
+
+    /// Gets the default states directory inside the data directory.
+    pub fn default_dir() -> PathBuf {
+        crate::paths::Paths::new().states_dir()
+    }
+
+    /// Gets the directory state files live in.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Switches to another ROM, keeping the selected slot.
+    pub fn set_rom(&mut self, rom: &[u8]) {
+        self.rom_sha1 = sha1_hex(rom);
+    }
+
+    /// Gets the SHA-1 of the ROM, as lowercase hex.
+    pub fn rom_sha1(&self) -> &str {
+        &self.rom_sha1
+    }
+
+    /// Gets the selected slot.
+    pub fn slot(&self) -> u8 {
+        self.slot
+    }
+
+    /// Selects the slot saves and loads use.
+    pub fn select_slot(&mut self, slot: u8) -> SaveStateResult<()> {
+        if slot >= SLOT_COUNT {
+            return Err(SaveStateError::InvalidSlot {
+                slot,
+                max: SLOT_COUNT - 1,
+            });
+        }
+        self.slot = slot;
+        Ok(())
+    }
+
+    /// Gets the file a slot is saved to.
+    pub fn slot_path(&self, slot: u8) -> PathBuf {
+        self.dir
+            .join(format!("{}.{}.{}", self.rom_sha1, slot, STATE_EXTENSION))
+    }
+
+    /// Checks if a slot holds a saved state.
+    pub fn has_state(&self, slot: u8) -> bool {
+        self.slot_path(slot).is_file()
+    }
+
+    /// Saves a snapshot to the selected slot, replacing what was there.
+    ///
+    /// # Returns
+    /// The path of the written file.
+    pub fn save(&self, snapshot: Snapshot) -> Result<PathBuf> {
+        self.write(self.slot_path(self.slot), snapshot)
+    }
+
+    /// Loads the snapshot in the selected slot.
+    ///
+    /// Fails if the slot is empty, or if the state was saved with another
+    /// ROM or an incompatible core.
+    pub fn load(&self) -> Result<Snapshot> {
+        let path = self.slot_path(self.slot);
+        if !path.is_file() {
+            return Err(SaveStateError::EmptySlot { slot: self.slot }.into());
+        }
+        self.read(&path)
+    }
+
+    /// Gets the file auto-saves are written to, apart from the numbered slots.
+    pub fn autosave_path(&self) -> PathBuf {
+        self.dir
+            .join(format!("{}.auto.{}", self.rom_sha1, STATE_EXTENSION))
+    }
+
+    /// Saves a snapshot as the ROM's auto-save.
+    ///
+    /// # Returns
+    /// The path of the written file.
+    pub fn save_autosave(&self, snapshot: Snapshot) -> Result<PathBuf> {
+        self.write(self.autosave_path(), snapshot)
+    }
+
+    /// Loads the ROM's auto-save, checked like a slot.
+    pub fn load_autosave(&self) -> Result<Snapshot> {
+        let path = self.autosave_path();
+        if !path.is_file() {
+            return Err(SaveStateError::NoAutosave.into());
+        }
+        self.read(&path)
+    }
+
+    /// Writes a snapshot of the running ROM to a file.
+    fn write(&self, path: PathBuf, snapshot: Snapshot) -> Result<PathBuf> {
+        std::fs::create_dir_all(&self.dir)?;
+        SaveState::new(&self.rom_sha1, snapshot).save(&path)?;
+        Ok(path)
+    }
+
+    /// Reads a snapshot from a file, checking it belongs to the running ROM.
+    fn read(&self, path: &Path) -> Result<Snapshot> {
+        let state = SaveState::load(path)?;
+        state.check_rom(&self.rom_sha1)?;
+        Ok(state.snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::EmulatorError;
+    use crate::Cpu;
+
+    const ROM: [u8; 4] = [0x60, 0x2A, 0x12, 0x02];
+
+    fn snapshot() -> Snapshot {
+        let mut cpu = Cpu::new();
+        cpu.load_rom(&ROM).unwrap();
+        cpu.cycle().unwrap();
+        cpu.snapshot()
+    }
+
+    #[test]
+    fn test_core_compatible() {
+        assert!(core_compatible("0.1.0", "0.1.7"));
+        assert!(!core_compatible("0.1.0", "0.2.0"));
+        assert!(core_compatible("1.2.0", "1.9.3"));
+        assert!(!core_compatible("1.0.0", "2.0.0"));
+        assert!(!core_compatible("", CORE_VERSION));
+        assert!(core_compatible(CORE_VERSION, CORE_VERSION));
+    }
+
+    #[test]
+    fn test_slots_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = SaveStateManager::new(dir.path().join("states"), &ROM);
+        assert!(matches!(
+            manager.load(),
+            Err(EmulatorError::SaveState(SaveStateError::EmptySlot {
+                slot: 0
+            }))
+        ));
+
+        manager.select_slot(3).unwrap();
+        let snapshot = snapshot();
+        let path = manager.save(snapshot.clone()).unwrap();
+        assert!(path.ends_with(format!("{}.3.c8state", sha1_hex(&ROM))));
+        assert!(manager.has_state(3));
+        assert!(!manager.has_state(0));
+        assert_eq!(manager.load().unwrap(), snapshot);
+
+        assert!(manager.select_slot(SLOT_COUNT).is_err());
+        assert_eq!(manager.slot(), 3);
+    }
+
+    #[test]
+    fn test_state_from_another_rom_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = SaveStateManager::new(dir.path(), &ROM);
+        let path = manager.save(snapshot()).unwrap();
+
+        // A state copied over another ROM's slot still names its own ROM
+        let mut other = SaveStateManager::new(dir.path(), &[0x00, 0xE0]);
+        other.select_slot(0).unwrap();
+        std::fs::copy(&path, other.slot_path(0)).unwrap();
+        assert!(matches!(
+            other.load(),
+            Err(EmulatorError::SaveState(SaveStateError::RomMismatch { .. }))
+        ));
+    }
+
+    #[test]
+    fn test_header_is_checked_first() {
+        let state = SaveState::new(&sha1_hex(&ROM), snapshot());
+        let text = state.to_toml().unwrap();
+        assert_eq!(
+            SaveState::from_toml(&text).unwrap().snapshot,
+            state.snapshot
+        );
+
+        let future = text.replace("version = 1\n", "version = 99\n");
+        assert!(matches!(
+            SaveState::from_toml(&future),
+            Err(EmulatorError::SaveState(
+                SaveStateError::UnsupportedVersion { found: 99, .. }
+            ))
+        ));
+
+        let old_core = text.replace(CORE_VERSION, "0.0.1");
+        assert!(matches!(
+            SaveState::from_toml(&old_core),
+            Err(EmulatorError::SaveState(
+                SaveStateError::IncompatibleCore { .. }
+            ))
+        ));
+
+        assert!(matches!(
+            SaveState::from_toml("format = \"c8replay\"\nversion = 1\n"),
+            Err(EmulatorError::SaveState(SaveStateError::NotASaveState))
+        ));
+    }
+}
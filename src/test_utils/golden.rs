@@ -0,0 +1,408 @@
+//! Golden screenshot tests.
+//!
+//! A [`ScriptedRun`] plays a ROM for a fixed number of lockstep frames with
+//! a fixed RNG seed and scripted keys, the way replays do, so the final
+//! screen is the same on every machine. [`assert_golden`] compares it with
+//! a golden file: text with one line per row, `#` for on and `.` for off,
+//! so a changed golden reads well in code review. Running the tests with
+//! `CHIP8_BLESS=1` writes the current screens as the new goldens instead.
+//!
+//! ```no_run
+//! use chip8::hardware::input::ChipKey;
+//! use chip8::test_utils::golden::{assert_golden, ScriptedRun};
+//!
+//! let rom = std::fs::read("game.ch8").unwrap();
+//! let screen = ScriptedRun::new(120)
+//!     .seed(42)
+//!     .hold(30, &[ChipKey::Key5])
+//!     .release(35)
+//!     .run(&rom)
+//!     .unwrap();
+//! assert_golden("tests/golden/title.txt", &screen);
+//! ```
+
+use std::fmt::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::emulator::{Cpu, RecordedRandom};
+use crate::error::Result;
+use crate::frontend::EmulatorBehaviorConfig;
+use crate::hardware::input::{ChipKey, Input, SoftwareInput};
+use crate::hardware::{ascii_art, Hardware};
+use crate::netplay;
+use crate::replay::{InputEvent, Replay};
+
+/// Environment variable that makes [`assert_golden`] write goldens instead
+/// of comparing against them.
+pub const BLESS_ENV: &str = "CHIP8_BLESS";
+
+/// Most differing rows listed in a mismatch report.
+const MAX_REPORTED_ROWS: usize = 8;
+
+/// A deterministic run of a ROM: frames, seed, settings and held keys.
+#[derive(Debug, Clone)]
+pub struct ScriptedRun {
+    /// Number of frames to run.
+    frames: u32,
+
+    /// RNG seed.
+    seed: u64,
+
+    /// Emulator settings, including quirks.
+    behavior: EmulatorBehaviorConfig,
+
+    /// Key changes in frame order.
+    inputs: Vec<InputEvent>,
+
+    /// RND bytes to play back before falling back to the seed.
+    random: Vec<u8>,
+}
+
+impl ScriptedRun {
+    /// Creates a run of `frames` frames with seed 0, the default settings
+    /// and no keys held.
+    pub fn new(frames: u32) -> Self {
+        Self {
+            frames,
+            seed: 0,
+            behavior: EmulatorBehaviorConfig::default(),
+            inputs: Vec::new(),
+            random: Vec::new(),
+        }
+    }
+
+    /// Sets the RNG seed.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Sets the emulator settings; the speed and timing model set each frame's cycles.
+    pub fn behavior(mut self, behavior: EmulatorBehaviorConfig) -> Self {
+        self.behavior = behavior;
+        self
+    }
+
+    /// Holds exactly `keys` from `frame` until the next change.
+    pub fn hold(mut self, frame: u32, keys: &[ChipKey]) -> Self {
+        let mask = keys.iter().fold(0, |mask, key| mask | 1 << key.to_u8());
+        self.inputs.retain(|event| event.frame != frame);
+        let index = self.inputs.partition_point(|event| event.frame < frame);
+        self.inputs.insert(index, InputEvent { frame, keys: mask });
+        self
+    }
+
+    /// Releases every key from `frame` on.
+    pub fn release(self, frame: u32) -> Self {
+        self.hold(frame, &[])
+    }
+
+    /// Gets the keys held on a frame as a mask where bit N is key N.
+    fn keys_at(&self, frame: u32) -> u16 {
+        let next = self.inputs.partition_point(|event| event.frame <= frame);
+        next.checked_sub(1).map_or(0, |i| self.inputs[i].keys)
+    }
+
+    /// Runs the ROM from power-on and captures the screen after the last frame.
+    pub fn run(&self, rom: &[u8]) -> Result<Screen> {
+        let input = Arc::new(Mutex::new(SoftwareInput::new()));
+        let mut cpu = Cpu::new_with_config(&self.behavior);
+        cpu.set_random_source(Box::new(RecordedRandom::new(
+            self.random.clone(),
+            self.seed,
+        )));
+        cpu.set_realtime_timers(false);
+        cpu.set_input(input.clone());
+        cpu.load_rom(rom)?;
+
+        for frame in 0..self.frames {
+            netplay::apply_mask(&mut input.lock().unwrap(), self.keys_at(frame));
+            netplay::run_frame(&mut cpu)?;
+            input.lock().unwrap().update()?;
+        }
+        Ok(Screen::capture(&cpu))
+    }
+}
+
+impl From<&Replay> for ScriptedRun {
+    /// Runs a recorded replay's frames, seed, settings, keys and RND bytes.
+    fn from(replay: &Replay) -> Self {
+        Self {
+            frames: replay.frames,
+            seed: replay.seed,
+            behavior: replay.behavior.clone(),
+            inputs: replay.inputs.clone(),
+            random: replay.random.clone(),
+        }
+    }
+}
+
+/// A captured display.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Screen {
+    /// Width in pixels.
+    pub width: usize,
+
+    /// Height in pixels.
+    pub height: usize,
+
+    /// Pixels in row-major order.
+    pub pixels: Vec<bool>,
+}
+
+impl Screen {
+    /// Copies the display of a CPU.
+    pub fn capture<H: Hardware>(cpu: &Cpu<H>) -> Self {
+        let (width, height) = cpu.display_size();
+        Self {
+            width,
+            height,
+            pixels: cpu.get_display_buffer().to_vec(),
+        }
+    }
+
+    /// Formats the screen as golden file text.
+    pub fn to_text(&self) -> String {
+        ascii_art(&self.pixels, self.width)
+    }
+
+    /// Parses golden file text.
+    ///
+    /// # Returns
+    /// The screen, or `None` if the rows are ragged or hold anything other
+    /// than `#` and `.`.
+    pub fn from_text(text: &str) -> Option<Self> {
+        let rows: Vec<&str> = text.lines().collect();
+        let width = rows.first()?.len();
+        let mut pixels = Vec::with_capacity(width * rows.len());
+        for row in &rows {
+            if row.len() != width {
+                return None;
+            }
+            for pixel in row.chars() {
+                match pixel {
+                    '#' => pixels.push(true),
+                    '.' => pixels.push(false),
+                    _ => return None,
+                }
+            }
+        }
+        Some(Self {
+            width,
+            height: rows.len(),
+            pixels,
+        })
+    }
+
+    /// Describes how this screen differs from an expected one.
+    ///
+    /// Differing rows are shown with `+` for pixels that are on but
+    /// shouldn't be and `-` for pixels that are off but should be on.
+    ///
+    /// # Returns
+    /// The report, or `None` if the screens are identical.
+    pub fn diff(&self, expected: &Screen) -> Option<String> {
+        if (self.width, self.height) != (expected.width, expected.height) {
+            return Some(format!(
+                "Screen is {}x{}, expected {}x{}\n",
+                self.width, self.height, expected.width, expected.height
+            ));
+        }
+
+        let rows: Vec<(usize, String)> = self
+            .pixels
+            .chunks(self.width)
+            .zip(expected.pixels.chunks(self.width))
+            .enumerate()
+            .filter(|(_, (actual, expected))| actual != expected)
+            .map(|(y, (actual, expected))| {
+                let line = actual
+                    .iter()
+                    .zip(expected)
+                    .map(|pair| match pair {
+                        (true, true) => '#',
+                        (false, false) => '.',
+                        (true, false) => '+',
+                        (false, true) => '-',
+                    })
+                    .collect();
+                (y, line)
+            })
+            .collect();
+        if rows.is_empty() {
+            return None;
+        }
+
+        let differing = self
+            .pixels
+            .iter()
+            .zip(&expected.pixels)
+            .filter(|(a, b)| a != b)
+            .count();
+        let mut report = format!(
+            "{} pixels differ in {} rows (+ on but should be off, - off but should be on):\n",
+            differing,
+            rows.len()
+        );
+        for (y, line) in rows.iter().take(MAX_REPORTED_ROWS) {
+            let _ = writeln!(report, "{:3} {}", y, line);
+        }
+        if rows.len() > MAX_REPORTED_ROWS {
+            let _ = writeln!(
+                report,
+                "... and {} more rows",
+                rows.len() - MAX_REPORTED_ROWS
+            );
+        }
+        Some(report)
+    }
+}
+
+/// Result of checking a screen against its golden file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GoldenOutcome {
+    /// The screen matches the golden.
+    Matched,
+
+    /// The golden was written from the screen.
+    Blessed,
+
+    /// The screen differs from the golden, or there is no golden yet.
+    Mismatch(String),
+}
+
+/// Checks a screen against a golden file.
+///
+/// # Arguments
+/// * `path` - Golden file
+/// * `screen` - Captured screen
+/// * `bless` - Write the screen as the golden instead of comparing
+pub fn check_golden(path: &Path, screen: &Screen, bless: bool) -> Result<GoldenOutcome> {
+    if bless {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, screen.to_text())?;
+        return Ok(GoldenOutcome::Blessed);
+    }
+
+    if !path.is_file() {
+        return Ok(GoldenOutcome::Mismatch(format!(
+            "No golden at {}; run with {}=1 to create it",
+            path.display(),
+            BLESS_ENV
+        )));
+    }
+    let text = std::fs::read_to_string(path)?;
+    let Some(expected) = Screen::from_text(&text) else {
+        return Ok(GoldenOutcome::Mismatch(format!(
+            "{} is not a golden screen (rows of # and .)",
+            path.display()
+        )));
+    };
+    Ok(match screen.diff(&expected) {
+        None => GoldenOutcome::Matched,
+        Some(report) => GoldenOutcome::Mismatch(report),
+    })
+}
+
+/// Asserts that a screen matches its golden file, or writes the golden
+/// when `CHIP8_BLESS` is set.
+///
+/// # Panics
+/// If the screen differs from the golden, with a report of the differing
+/// rows, or if the golden can't be read or written.
+#[track_caller]
+pub fn assert_golden(path: impl AsRef<Path>, screen: &Screen) {
+    let path = path.as_ref();
+    let bless = std::env::var_os(BLESS_ENV).is_some_and(|value| value != "0");
+    match check_golden(path, screen, bless) {
+        Ok(GoldenOutcome::Matched | GoldenOutcome::Blessed) => {}
+        Ok(GoldenOutcome::Mismatch(report)) => panic!(
+            "Screen doesn't match {}\n{}\nRun with {}=1 to accept the new screen",
+            path.display(),
+            report,
+            BLESS_ENV
+        ),
+        Err(e) => panic!("Failed to check golden {}: {}", path.display(), e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Draws the digit in V0 at (V1, V1), waiting for a key first if V2 is 0
+    const ROM: [u8; 12] = [
+        0x60, 0x05, // LD V0, 5
+        0xF2, 0x0A, // LD V2, K
+        0xF2, 0x29, // LD F, V2
+        0xD1, 0x15, // DRW V1, V1, 5
+        0xC3, 0xFF, // RND V3, 0xFF
+        0x12, 0x0A, // JP 0x20A
+    ];
+
+    #[test]
+    fn test_scripted_run_is_deterministic() {
+        let run = ScriptedRun::new(10).hold(2, &[ChipKey::Key7]).release(4);
+        let screen = run.run(&ROM).unwrap();
+        assert_eq!(screen, run.run(&ROM).unwrap());
+        assert_eq!((screen.width, screen.height), (64, 32));
+
+        // The "7" glyph's top row is 0xF0
+        assert!(screen.to_text().starts_with("####...."));
+        assert_eq!(Screen::from_text(&screen.to_text()), Some(screen.clone()));
+
+        // Without the key press nothing is drawn
+        let blank = ScriptedRun::new(10).run(&ROM).unwrap();
+        assert!(blank.pixels.iter().all(|&pixel| !pixel));
+        assert!(blank.diff(&blank).is_none());
+    }
+
+    #[test]
+    fn test_golden_bless_and_diff() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("golden").join("seven.txt");
+        let seven = ScriptedRun::new(10)
+            .hold(2, &[ChipKey::Key7])
+            .release(4)
+            .run(&ROM)
+            .unwrap();
+
+        assert!(matches!(
+            check_golden(&path, &seven, false).unwrap(),
+            GoldenOutcome::Mismatch(report) if report.contains("No golden")
+        ));
+        assert_eq!(
+            check_golden(&path, &seven, true).unwrap(),
+            GoldenOutcome::Blessed
+        );
+        assert_eq!(
+            check_golden(&path, &seven, false).unwrap(),
+            GoldenOutcome::Matched
+        );
+        assert_golden(&path, &seven);
+
+        let one = ScriptedRun::new(10)
+            .hold(2, &[ChipKey::Key1])
+            .release(4)
+            .run(&ROM)
+            .unwrap();
+        let GoldenOutcome::Mismatch(report) = check_golden(&path, &one, false).unwrap() else {
+            panic!("different digits should not match");
+        };
+        assert!(report.starts_with("10 pixels differ in 4 rows"));
+        assert!(report.contains("\n  0 --#-....."));
+    }
+
+    #[test]
+    fn test_replay_runs_as_scripted() {
+        let mut replay = Replay::new(&ROM, &EmulatorBehaviorConfig::default(), 3);
+        replay.record(0);
+        replay.record(1 << 7);
+        replay.record(0);
+        let screen = ScriptedRun::from(&replay).run(&ROM).unwrap();
+        assert!(screen.to_text().starts_with("####...."));
+    }
+}
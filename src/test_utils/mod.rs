@@ -1,7 +1,11 @@
 //! Test utilities for the Chip-8 emulator.
 //!
 //! This module provides common test setup and utility functions to reduce
-//! code duplication across test modules.
+//! code duplication across test modules. The [`golden`] screenshot harness
+//! is also exported with the `test-harness` feature, for testing programs
+//! in their own CI.
+
+pub mod golden;
 
 #[cfg(test)]
 pub mod helpers {
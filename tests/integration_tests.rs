@@ -24,7 +24,7 @@ fn test_cpu_with_hardware_integration() {
     // Set up hardware systems
     let display = Box::new(GraphicsDisplay::new().unwrap());
     let audio = Box::new(AudioSystem::new().unwrap());
-    let input = std::rc::Rc::new(std::cell::RefCell::new(InputSystem::new().unwrap()));
+    let input = std::sync::Arc::new(std::sync::Mutex::new(InputSystem::new().unwrap()));
 
     cpu.set_display(display);
     cpu.set_audio(audio);